@@ -0,0 +1,158 @@
+use crate::parsing::{to_char, FenError};
+use crate::{Board, Piece, BISHOP, KNIGHT, QUEEN, ROOK};
+
+/// A builder for boards with custom rules, as an alternative to writing an L-FEN by hand
+///
+/// ```
+/// use liberty_chess::builder::BoardBuilder;
+/// use liberty_chess::{QUEEN, ROOK};
+///
+/// let board = BoardBuilder::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR")
+///   .castling("KQkq")
+///   .promotions(&[QUEEN, ROOK])
+///   .friendly_fire(true)
+///   .build()
+///   .unwrap();
+/// assert!(board.friendly_fire);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BoardBuilder {
+  layout: String,
+  to_move: bool,
+  castling: String,
+  pawn_moves: usize,
+  pawn_row: usize,
+  castle_row: usize,
+  castle_columns: Option<(usize, usize)>,
+  chess960: bool,
+  promotions: Vec<Piece>,
+  friendly_fire: bool,
+}
+
+impl BoardBuilder {
+  /// Start building a board from the piece layout section of an L-FEN
+  ///
+  /// By default white is to move, castling is not allowed and the rules are the same as normal chess
+  #[must_use]
+  pub fn new(layout: &str) -> Self {
+    Self {
+      layout: layout.to_owned(),
+      to_move: true,
+      castling: String::new(),
+      pawn_moves: 2,
+      pawn_row: 2,
+      castle_row: 0,
+      castle_columns: None,
+      chess960: false,
+      promotions: vec![QUEEN, ROOK, BISHOP, KNIGHT],
+      friendly_fire: false,
+    }
+  }
+
+  /// Set the side to move, `true` for white
+  #[must_use]
+  pub const fn to_move(mut self, to_move: bool) -> Self {
+    self.to_move = to_move;
+    self
+  }
+
+  /// Set the castling rights, using the letters `KQkq` or the files of the castling pieces
+  ///
+  /// Files (Shredder-FEN style, such as `HAha`) also enable Chess960 castling
+  #[must_use]
+  pub fn castling(mut self, castling: &str) -> Self {
+    self.castling = castling.to_owned();
+    self
+  }
+
+  /// Set how many squares a pawn can move on its first move
+  #[must_use]
+  pub const fn pawn_moves(mut self, pawn_moves: usize) -> Self {
+    self.pawn_moves = pawn_moves;
+    self
+  }
+
+  /// Set the row pawns need to be on or behind to make their initial move
+  #[must_use]
+  pub const fn pawn_row(mut self, pawn_row: usize) -> Self {
+    self.pawn_row = pawn_row;
+    self
+  }
+
+  /// Set the row white castles on, counting from 0
+  #[must_use]
+  pub const fn castle_row(mut self, castle_row: usize) -> Self {
+    self.castle_row = castle_row;
+    self
+  }
+
+  /// Set the columns of the queenside and kingside castling pieces, counting from 0
+  ///
+  /// Defaults to the edges of the board
+  #[must_use]
+  pub const fn castle_columns(mut self, queen_column: usize, king_column: usize) -> Self {
+    self.castle_columns = Some((queen_column, king_column));
+    self
+  }
+
+  /// Set whether castling follows Chess960 rules, with the king and castling piece
+  /// ending up on the same columns as in normal chess
+  #[must_use]
+  pub const fn chess960(mut self, chess960: bool) -> Self {
+    self.chess960 = chess960;
+    self
+  }
+
+  /// Set the pieces pawns can promote to
+  #[must_use]
+  pub fn promotions(mut self, promotions: &[Piece]) -> Self {
+    self.promotions = promotions.iter().map(|piece| piece.abs()).collect();
+    self
+  }
+
+  /// Set whether pieces can capture pieces of their own colour
+  #[must_use]
+  pub const fn friendly_fire(mut self, friendly_fire: bool) -> Self {
+    self.friendly_fire = friendly_fire;
+    self
+  }
+
+  /// Get the L-FEN for the board being built
+  #[must_use]
+  pub fn to_fen(&self) -> String {
+    let castling = if self.castling.is_empty() {
+      "-"
+    } else {
+      &self.castling
+    };
+    let mut fen = format!(
+      "{} {} {castling} - 0 1 {},{},{}",
+      self.layout,
+      if self.to_move { 'w' } else { 'b' },
+      self.pawn_moves,
+      self.pawn_row,
+      self.castle_row + 1,
+    );
+    if let Some((queen_column, king_column)) = self.castle_columns {
+      fen += &format!(",{},{}", queen_column + 1, king_column + 1);
+    }
+    fen.push(' ');
+    fen.extend(self.promotions.iter().map(|piece| to_char(-piece)));
+    if self.friendly_fire {
+      fen += " ff";
+    }
+    if self.chess960 {
+      fen += " 960";
+    }
+    fen
+  }
+
+  /// Build the board
+  ///
+  /// # Errors
+  ///
+  /// Will return `Err` if the layout is invalid
+  pub fn build(&self) -> Result<Board, FenError> {
+    Board::new(&self.to_fen())
+  }
+}
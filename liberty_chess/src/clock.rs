@@ -1,7 +1,159 @@
 use crate::{Board, Gamestate};
 use enum_iterator::Sequence;
+use std::fmt::{Display, Formatter};
+use std::num::ParseIntError;
+use std::ops::{Add, AddAssign, Div, Mul, Sub, SubAssign};
+use std::str::FromStr;
 use std::time::{Duration, Instant};
 
+/// A length of time in milliseconds, used for time controls.
+///
+/// Arithmetic saturates instead of overflowing and conversions to `Duration` are clamped,
+/// so extreme time controls can't wrap around or panic.
+///
+/// [`Clock`] keeps `Duration` so time measured between updates isn't rounded to milliseconds,
+/// but its arithmetic saturates in the same way.
+///
+/// # Examples
+/// ```
+/// use liberty_chess::clock::Millis;
+///
+/// assert_eq!(Millis::from_mins(2) + Millis::from_secs(3), Millis::new(123_000));
+/// assert_eq!(Millis::new(5) - Millis::new(10), Millis::ZERO);
+/// assert_eq!(Millis::MAX + Millis::new(1), Millis::MAX);
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Millis(u128);
+
+impl Millis {
+  /// No time
+  pub const ZERO: Self = Self(0);
+  /// The longest representable time, used for unlimited time
+  pub const MAX: Self = Self(u128::MAX);
+
+  /// Create a time from a number of milliseconds
+  #[must_use]
+  pub const fn new(millis: u128) -> Self {
+    Self(millis)
+  }
+
+  /// Create a time from a number of seconds
+  #[must_use]
+  pub const fn from_secs(secs: u64) -> Self {
+    // can't overflow since u64::MAX * 1000 fits in a u128
+    Self(secs as u128 * 1000)
+  }
+
+  /// Create a time from a number of minutes
+  #[must_use]
+  pub const fn from_mins(mins: u64) -> Self {
+    Self(mins as u128 * 60_000)
+  }
+
+  /// The number of milliseconds
+  #[must_use]
+  pub const fn get(self) -> u128 {
+    self.0
+  }
+
+  /// Whether there is no time
+  #[must_use]
+  pub const fn is_zero(self) -> bool {
+    self.0 == 0
+  }
+
+  /// Add two times, saturating at `Millis::MAX`
+  #[must_use]
+  pub const fn saturating_add(self, rhs: Self) -> Self {
+    Self(self.0.saturating_add(rhs.0))
+  }
+
+  /// Subtract a time, saturating at zero
+  #[must_use]
+  pub const fn saturating_sub(self, rhs: Self) -> Self {
+    Self(self.0.saturating_sub(rhs.0))
+  }
+
+  /// Convert to a `Duration`, clamping times too long to represent
+  #[must_use]
+  pub fn to_duration(self) -> Duration {
+    Duration::from_millis(u64::try_from(self.0).unwrap_or(u64::MAX))
+  }
+}
+
+impl From<Duration> for Millis {
+  fn from(duration: Duration) -> Self {
+    Self(duration.as_millis())
+  }
+}
+
+impl Add for Millis {
+  type Output = Self;
+
+  fn add(self, rhs: Self) -> Self {
+    self.saturating_add(rhs)
+  }
+}
+
+impl AddAssign for Millis {
+  fn add_assign(&mut self, rhs: Self) {
+    *self = *self + rhs;
+  }
+}
+
+impl Sub for Millis {
+  type Output = Self;
+
+  fn sub(self, rhs: Self) -> Self {
+    self.saturating_sub(rhs)
+  }
+}
+
+impl SubAssign for Millis {
+  fn sub_assign(&mut self, rhs: Self) {
+    *self = *self - rhs;
+  }
+}
+
+impl Mul<u128> for Millis {
+  type Output = Self;
+
+  fn mul(self, rhs: u128) -> Self {
+    Self(self.0.saturating_mul(rhs))
+  }
+}
+
+impl Div<u128> for Millis {
+  type Output = Self;
+
+  fn div(self, rhs: u128) -> Self {
+    Self(self.0 / rhs)
+  }
+}
+
+impl Display for Millis {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    self.0.fmt(f)
+  }
+}
+
+impl FromStr for Millis {
+  type Err = ParseIntError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    s.parse().map(Self)
+  }
+}
+
+/// A notable change in the state of a clock.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClockEvent {
+  /// A player's time dropped to a low time threshold, `true` for white.
+  LowTime(bool, Duration),
+  /// A player ran out of time, `true` for white.
+  Flagged(bool),
+}
+
 /// Implements a chess clock.
 pub struct Clock {
   white_clock: Duration,
@@ -15,6 +167,20 @@ pub struct Clock {
   paused: bool,
 
   last_update: Instant,
+  move_start: Duration,
+
+  // time before the clock starts running each move
+  delay: Duration,
+  // time spent by one player is added to the other player's clock
+  hourglass: bool,
+  // time spent on the current move, for the delay
+  move_elapsed: Duration,
+
+  // low time thresholds, longest first
+  thresholds: Vec<Duration>,
+  // number of thresholds each player has reached, white first
+  reached: [usize; 2],
+  events: Vec<ClockEvent>,
 }
 
 impl Clock {
@@ -33,6 +199,13 @@ impl Clock {
       flagged: false,
       paused: true,
       last_update: Instant::now(),
+      move_start: if to_move { white_clock } else { black_clock },
+      delay: Duration::ZERO,
+      hourglass: false,
+      move_elapsed: Duration::ZERO,
+      thresholds: Vec::new(),
+      reached: [0; 2],
+      events: Vec::new(),
     }
   }
 
@@ -42,27 +215,97 @@ impl Clock {
     Self::new([clock, clock, increment, increment], to_move)
   }
 
+  /// Initialise a `Clock` where each move has a delay before the clock starts running.
+  ///
+  /// With no starting time, each move must be made within the delay.
+  #[must_use]
+  pub fn new_delay(
+    [white_clock, black_clock]: [Duration; 2],
+    delay: Duration,
+    to_move: bool,
+  ) -> Self {
+    Self {
+      delay,
+      ..Self::new(
+        [white_clock, black_clock, Duration::ZERO, Duration::ZERO],
+        to_move,
+      )
+    }
+  }
+
+  /// Initialise an hourglass `Clock`, where time spent by one player is added to the other.
+  #[must_use]
+  pub fn new_hourglass([white_clock, black_clock]: [Duration; 2], to_move: bool) -> Self {
+    Self {
+      hourglass: true,
+      ..Self::new(
+        [white_clock, black_clock, Duration::ZERO, Duration::ZERO],
+        to_move,
+      )
+    }
+  }
+
   /// Updates the internal state of the clock.
   pub fn update(&mut self) {
     let elapsed = self.last_update.elapsed();
     self.last_update = Instant::now();
     if !self.paused {
-      if self.to_move {
-        if elapsed > self.white_clock {
-          self.white_clock = Duration::ZERO;
-          self.flagged = true;
-        } else {
-          self.white_clock -= elapsed;
-        }
-      } else if elapsed > self.black_clock {
-        self.black_clock = Duration::ZERO;
-        self.flagged = true;
+      let flagged = self.flagged;
+      // time within the delay isn't taken off the clock
+      let delay_left = self.delay.saturating_sub(self.move_elapsed);
+      self.move_elapsed = self.move_elapsed.saturating_add(elapsed);
+      let elapsed = elapsed.saturating_sub(delay_left);
+      let (clock, other_clock) = if self.to_move {
+        (&mut self.white_clock, &mut self.black_clock)
       } else {
-        self.black_clock -= elapsed;
+        (&mut self.black_clock, &mut self.white_clock)
+      };
+      let spent = elapsed.min(*clock);
+      if elapsed > *clock {
+        self.flagged = true;
+      }
+      *clock -= spent;
+      if self.hourglass {
+        *other_clock = other_clock.saturating_add(spent);
+      }
+      self.check_thresholds(self.to_move);
+      if self.flagged && !flagged {
+        self.events.push(ClockEvent::Flagged(self.to_move));
       }
     }
   }
 
+  /// Set the low time thresholds that trigger `ClockEvent::LowTime`.
+  ///
+  /// Thresholds a player has already reached don't trigger events.
+  pub fn set_thresholds(&mut self, thresholds: &[Duration]) {
+    self.update();
+    self.thresholds = thresholds.to_vec();
+    self.thresholds.sort_unstable_by(|a, b| b.cmp(a));
+    self.reached = [
+      self.thresholds_reached(self.white_clock),
+      self.thresholds_reached(self.black_clock),
+    ];
+  }
+
+  /// Returns the low time thresholds, longest first.
+  #[must_use]
+  pub fn thresholds(&self) -> &[Duration] {
+    &self.thresholds
+  }
+
+  /// Updates the clock and returns the events that occurred since the last call.
+  pub fn take_events(&mut self) -> Vec<ClockEvent> {
+    self.update();
+    std::mem::take(&mut self.events)
+  }
+
+  /// Returns whether a player has reached a low time threshold, `true` for white.
+  #[must_use]
+  pub fn is_low_time(&self, side: bool) -> bool {
+    self.reached[usize::from(!side)] > 0
+  }
+
   /// Updates the clock and toggles whether it is paused.
   pub fn toggle_pause(&mut self) {
     self.update();
@@ -101,27 +344,126 @@ impl Clock {
     (self.white_inc, self.black_inc)
   }
 
-  /// Update the clock and switch the clock that is running.
-  pub fn switch_clocks(&mut self) {
+  /// Updates the clock and adds time to a player's clock, `true` for white.
+  ///
+  /// Adding time to a player who has flagged lets them continue.
+  pub fn add_time(&mut self, side: bool, time: Duration) {
+    self.update();
+    if side {
+      self.white_clock = self.white_clock.saturating_add(time);
+    } else {
+      self.black_clock = self.black_clock.saturating_add(time);
+    }
+    let remaining = if side {
+      self.white_clock
+    } else {
+      self.black_clock
+    };
+    // thresholds can be reached again after time is added
+    self.reached[usize::from(!side)] = self.thresholds_reached(remaining);
+    if side == self.to_move {
+      // don't count the extra time as time spent on the move
+      self.move_start = self.move_start.saturating_add(time);
+      if self.flagged && !time.is_zero() {
+        self.flagged = false;
+      }
+    }
+  }
+
+  /// Sets a player's increment, `true` for white.
+  ///
+  /// The new increment applies from the next move.
+  pub fn set_increment(&mut self, side: bool, increment: Duration) {
+    if side {
+      self.white_inc = increment;
+    } else {
+      self.black_inc = increment;
+    }
+  }
+
+  /// Returns the delay before the clock starts running each move
+  #[must_use]
+  pub const fn get_delay(&self) -> Duration {
+    self.delay
+  }
+
+  /// Returns whether time spent by one player is added to the other
+  #[must_use]
+  pub const fn is_hourglass(&self) -> bool {
+    self.hourglass
+  }
+
+  /// Updates the clock and returns the time the side to move has spent on the current move.
+  pub fn move_time(&mut self) -> Duration {
     self.update();
+    // time used from the delay doesn't come off the clock
+    self
+      .move_start
+      .saturating_sub(self.current_clock())
+      .saturating_add(self.move_elapsed.min(self.delay))
+  }
+
+  /// Update the clock and switch the clock that is running.
+  ///
+  /// Returns the time spent on the move by the side that just moved.
+  pub fn switch_clocks(&mut self) -> Duration {
+    let spent = self.move_time();
     self.paused = false;
     if !self.flagged {
       if self.to_move {
-        self.white_clock += self.white_inc;
+        self.white_clock = self.white_clock.saturating_add(self.white_inc);
         self.to_move = false;
       } else {
-        self.black_clock += self.black_inc;
+        self.black_clock = self.black_clock.saturating_add(self.black_inc);
         self.to_move = true;
       }
     }
+    self.move_start = self.current_clock();
+    self.move_elapsed = Duration::ZERO;
+    spent
   }
 
   /// Update the clock status when a move occurs
-  pub fn update_status(&mut self, board: &Board) {
-    self.switch_clocks();
+  ///
+  /// Returns the time spent on the move by the side that just moved.
+  pub fn update_status(&mut self, board: &Board) -> Duration {
+    let spent = self.switch_clocks();
     if board.state() != Gamestate::InProgress && !self.is_paused() {
       self.toggle_pause();
     }
+    spent
+  }
+
+  fn thresholds_reached(&self, time: Duration) -> usize {
+    self
+      .thresholds
+      .iter()
+      .take_while(|&&threshold| time <= threshold)
+      .count()
+  }
+
+  fn check_thresholds(&mut self, side: bool) {
+    let time = if side {
+      self.white_clock
+    } else {
+      self.black_clock
+    };
+    let index = usize::from(!side);
+    while let Some(&threshold) = self.thresholds.get(self.reached[index]) {
+      if time > threshold {
+        break;
+      }
+      self.reached[index] += 1;
+      self.events.push(ClockEvent::LowTime(side, threshold));
+    }
+  }
+
+  const fn current_clock(&self) -> Duration {
+    if self.to_move {
+      self.white_clock
+    } else {
+      self.black_clock
+    }
   }
 }
 
@@ -134,6 +476,10 @@ pub enum Type {
   Increment,
   /// Fischer increment where both sides have differing amounts of time and increment.
   Handicap,
+  /// A delay each move before the clock starts running.
+  Delay,
+  /// Time spent by one player is added to the other player's clock.
+  Hourglass,
 }
 
 impl ToString for Type {
@@ -142,13 +488,16 @@ impl ToString for Type {
       Self::None => "None".to_owned(),
       Self::Increment => "Increment".to_owned(),
       Self::Handicap => "Handicap".to_owned(),
+      Self::Delay => "Delay".to_owned(),
+      Self::Hourglass => "Hourglass".to_owned(),
     }
   }
 }
 
 /// Convert time in milliseconds to a user-friendly output
 #[must_use]
-pub fn format_time(millis: u128) -> String {
+pub fn format_time(time: Millis) -> String {
+  let millis = time.get();
   let secs = millis / 1000;
   if secs >= 180 {
     format!("{secs} s")
@@ -71,6 +71,7 @@ pub struct ExtraFlags {
   castle_row: usize,
   queen_column: usize,
   king_column: usize,
+  chess960: bool,
   friendly_fire: bool,
 }
 
@@ -86,6 +87,7 @@ impl ExtraFlags {
       castle_row: shared_data.castle_row,
       queen_column: shared_data.queen_column,
       king_column: shared_data.king_column,
+      chess960: shared_data.chess960,
       friendly_fire: board.friendly_fire,
     }
   }
@@ -2,6 +2,17 @@
 #![warn(missing_docs, unused)]
 #![allow(clippy::inline_always)]
 //! The backend for Liberty Chess
+//!
+//! The rules engine can be embedded in other projects, the [`prelude`] module has the commonly used types.
+//!
+//! ```
+//! use liberty_chess::prelude::*;
+//!
+//! let board = Board::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")?;
+//! let board = board.move_if_legal("e2e4".parse()?).expect("e2e4 is legal");
+//! assert_eq!(board.state(), Gamestate::InProgress);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
 
 pub use crate::keys::ExtraFlags;
 pub use crate::keys::Hash;
@@ -11,8 +22,11 @@ use crate::keys::Zobrist;
 use crate::parsing::{from_chars, get_indices, process_board, FenError};
 use array2d::Array2D;
 use moves::Move;
+use std::fmt::{Display, Formatter};
 use std::rc::Rc;
 
+/// A builder for boards with custom rules
+pub mod builder;
 /// A struct to represent a clock
 pub mod clock;
 /// Move representation
@@ -30,6 +44,18 @@ mod keys;
 mod movegen;
 mod perft;
 
+#[cfg(test)]
+mod tests;
+
+/// Re-exports of the commonly used types for embedding the rules engine
+pub mod prelude {
+  pub use crate::builder::BoardBuilder;
+  pub use crate::clock::Clock;
+  pub use crate::moves::{Move, MoveParseError};
+  pub use crate::parsing::FenError;
+  pub use crate::{Board, Gamestate, Piece, SanityIssue};
+}
+
 /// A type used for pieces.
 /// Positive values indicate a white piece, negative values indicate a black piece and 0 indicates an empty square.
 pub type Piece = i8;
@@ -73,6 +99,10 @@ pub const OBSTACLE: Piece = 17;
 /// Like an obstacle, but immune to attack from most pieces
 pub const WALL: Piece = 18;
 
+/// The number of pieces of each type a side has, indexed by the piece type minus one.
+/// Kings are included.
+pub type MaterialSignature = [u32; 18];
+
 /// All the pieces available
 pub const ALL_PIECES: &str = "kmqcaehuriwbznxlop";
 
@@ -84,6 +114,14 @@ pub const ALL_PIECES: &str = "kmqcaehuriwbznxlop";
 const ATTACK: [Piece; 19] = [0, 3, 2, 2, 2, 2, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 1, 1];
 const DEFENCE: [Piece; 19] = [0, 1, 1, 1, 1, 1, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 1, 2];
 
+/// Returns whether a piece is strong enough to capture the target, ignoring colour
+///
+/// Elephants and walls are immune to most pieces
+#[must_use]
+pub const fn can_capture(attacker: Piece, target: Piece) -> bool {
+  DEFENCE[target.unsigned_abs() as usize] < ATTACK[attacker.unsigned_abs() as usize]
+}
+
 /// represents the status of the game
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Gamestate {
@@ -103,6 +141,54 @@ pub enum Gamestate {
   Material,
 }
 
+/// A problem with a position that makes it unsuitable for starting a game
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SanityIssue {
+  /// The side not to move has a king under attack. True = White, False = Black
+  OpponentInCheck(bool),
+  /// A side has pawns stuck on its promotion row. True = White, False = Black
+  PawnOnPromotionRow(bool),
+  /// A side has more promoted pieces than its missing pawns could account for
+  ExcessPromotedPieces {
+    /// True = White, False = Black
+    side: bool,
+    /// How many pieces are unaccounted for
+    excess: u32,
+  },
+}
+
+impl SanityIssue {
+  /// Whether the issue makes the game unplayable, rather than just unusual
+  #[must_use]
+  pub const fn blocks_play(self) -> bool {
+    matches!(self, Self::OpponentInCheck(_) | Self::PawnOnPromotionRow(_))
+  }
+}
+
+impl Display for SanityIssue {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    let name = |side: bool| if side { "White" } else { "Black" };
+    match self {
+      Self::OpponentInCheck(side) => write!(
+        f,
+        "{} is in check but it is {} to move",
+        name(*side),
+        name(!side).to_lowercase()
+      ),
+      Self::PawnOnPromotionRow(side) => write!(
+        f,
+        "{} has pawns on its promotion row with nothing to promote to",
+        name(*side)
+      ),
+      Self::ExcessPromotedPieces { side, excess } => write!(
+        f,
+        "{} has {excess} more promoted pieces than its missing pawns allow",
+        name(*side)
+      ),
+    }
+  }
+}
+
 struct SharedData {
   keys: Zobrist,
   castling_masks: Array2D<u8>,
@@ -111,6 +197,8 @@ struct SharedData {
   castle_row: usize,
   queen_column: usize,
   king_column: usize,
+  // Chess960 castling moves the king and castling piece to fixed columns
+  chess960: bool,
   promotion_options: Vec<Piece>,
   // Whether pawns promote to a piece that can checkmate
   pawn_checkmates: bool,
@@ -137,6 +225,7 @@ impl SharedData {
     castle_row: usize,
     queen_column: usize,
     king_column: usize,
+    chess960: bool,
     promotion_options: Vec<Piece>,
     piece_types: Vec<Piece>,
   ) -> Self {
@@ -165,6 +254,7 @@ impl SharedData {
       castle_row,
       queen_column,
       king_column,
+      chess960,
       promotion_options,
       pawn_checkmates,
       horizontal: piece_types.iter().any(|p| {
@@ -212,6 +302,8 @@ pub struct Board {
   duplicates: Vec<Hash>,
   previous: Vec<Hash>,
   hash: Hash,
+  // the hash of only the pawns, kept up to date for pawn structure tables
+  pawn_hash: Hash,
   shared_data: Rc<SharedData>,
   /// Whether friendly fire mode is enabled.
   /// Changing this value is only supported before moves are made.
@@ -221,6 +313,8 @@ pub struct Board {
   // Piece counts ignore kings
   white_pieces: u32,
   black_pieces: u32,
+  // Counts of each piece type, indexed by side
+  material: [MaterialSignature; 2],
 
   /// Skip testing for checkmate/stalemate except for 50-move rule precedence
   pub skip_checkmate: bool,
@@ -229,6 +323,18 @@ pub struct Board {
   pub last_move: Option<Move>,
 }
 
+// Update the pawn hash when a piece moves, appears or is removed, only pawns are included
+fn update_pawn_hash(keys: &Zobrist, pawn_hash: &mut Hash, piece: Piece, square: (usize, usize)) {
+  if piece.abs() == PAWN {
+    keys.update_hash(pawn_hash, piece, square);
+  }
+}
+
+// Update the material signature when a piece is captured
+fn remove_material(material: &mut [MaterialSignature; 2], piece: Piece) {
+  material[usize::from(piece > 0)][usize::from(piece.unsigned_abs()) - 1] -= 1;
+}
+
 impl PartialEq for Board {
   fn eq(&self, other: &Self) -> bool {
     self.hash == other.hash
@@ -243,6 +349,9 @@ impl Board {
   /// # Errors
   /// Return an `FenError` if one of the invalid input types mentioned applies.
   ///
+  /// Castling rights are dropped for sides without a king on the castling row
+  /// or a friendly piece to castle with.
+  ///
   /// # Examples
   /// Getting the start position for standard chess:
   /// ```
@@ -259,6 +368,8 @@ impl Board {
     let to_move = fields.len() == 1 || fields[1] == "w";
 
     let mut castling = 0;
+    // Shredder-FEN files of castling pieces, resolved once the castling row is known
+    let mut castle_files = Vec::new();
     if fields.len() > 2 {
       for c in fields[2].chars() {
         castling |= match c {
@@ -266,6 +377,10 @@ impl Board {
           'Q' => 2,
           'k' => 4,
           'q' => 8,
+          _ if c.is_ascii_alphabetic() => {
+            castle_files.push(c);
+            0
+          }
           _ => 0,
         };
       }
@@ -301,6 +416,9 @@ impl Board {
         pawn_row = pawn_rows;
       }
       if let Some(raw_castle_row) = data.get(2).and_then(|x| x.parse::<usize>().ok()) {
+        if raw_castle_row > height {
+          Err(FenError::CastleRow)?;
+        }
         if raw_castle_row > 0 {
           castle_row = raw_castle_row - 1;
         }
@@ -325,7 +443,63 @@ impl Board {
       }
     }
 
-    let friendly_fire = fields.len() > 8 && fields[8] == "ff";
+    let flags = fields.get(8..).unwrap_or_default();
+    let friendly_fire = flags.contains(&"ff");
+    let chess960 = flags.contains(&"960") || !castle_files.is_empty();
+
+    // castling pieces given by file are on the kingside or queenside depending on the king
+    let black_castle_row = height - 1 - castle_row;
+    for c in castle_files {
+      let column = c.to_ascii_lowercase() as usize - 'a' as usize;
+      let (row, kings, offset) = if c.is_ascii_uppercase() {
+        (castle_row, &white_kings, 0)
+      } else {
+        (black_castle_row, &black_kings, 2)
+      };
+      if let Some(king) = kings.iter().find(|king| king.0 == row) {
+        if king.1 < column && column < width {
+          king_column = column;
+          castling |= 1 << offset;
+        } else if column < king.1 {
+          queen_column = column;
+          castling |= 2 << offset;
+        }
+      }
+    }
+
+    // pawns on their promotion row would have no moves
+    for (row, squares) in pieces.rows_iter().enumerate() {
+      for piece in squares {
+        if (*piece == PAWN && row == height - 1) || (*piece == -PAWN && row == 0) {
+          Err(FenError::PawnPromotionRow)?;
+        }
+      }
+    }
+
+    // castling needs a king on the castling row and a friendly piece to castle with
+    // in Chess960 the king must be between the castling pieces, with room for their destinations
+    for (flag, square, kings, side) in [
+      (1, (castle_row, king_column), &white_kings, 1),
+      (2, (castle_row, queen_column), &white_kings, 1),
+      (4, (black_castle_row, king_column), &black_kings, -1),
+      (8, (black_castle_row, queen_column), &black_kings, -1),
+    ] {
+      let kingside = flag & 5 != 0;
+      let between = |king: &(usize, usize)| {
+        if kingside {
+          king.1 < square.1
+        } else {
+          king.1 > square.1
+        }
+      };
+      if !kings
+        .iter()
+        .any(|king| king.0 == square.0 && (!chess960 || (width >= 4 && between(king))))
+        || pieces[square] * side <= 0
+      {
+        castling &= !flag;
+      }
+    }
 
     let mut piece_types = Vec::new();
     for piece in pieces.elements_row_major_iter() {
@@ -352,6 +526,7 @@ impl Board {
       castle_row,
       queen_column,
       king_column,
+      chess960,
       promotion_options,
       piece_types,
     );
@@ -370,10 +545,12 @@ impl Board {
       duplicates: Vec::new(),
       previous: Vec::new(),
       hash: 0,
+      pawn_hash: 0,
       shared_data: Rc::new(shared_data),
       friendly_fire,
       white_pieces,
       black_pieces,
+      material: [MaterialSignature::default(); 2],
 
       skip_checkmate: false,
 
@@ -381,6 +558,8 @@ impl Board {
     };
 
     board.hash = board.get_hash();
+    board.pawn_hash = board.get_pawn_hash();
+    board.material = board.count_material();
     board.update();
 
     Ok(board)
@@ -401,10 +580,12 @@ impl Board {
     self.duplicates.clone_from(&other.duplicates);
     self.previous.clone_from(&other.previous);
     self.hash = other.hash;
+    self.pawn_hash = other.pawn_hash;
     self.shared_data = other.shared_data.clone();
     self.friendly_fire = other.friendly_fire;
     self.white_pieces = other.white_pieces;
     self.black_pieces = other.black_pieces;
+    self.material = other.material;
     self.skip_checkmate = other.skip_checkmate;
     self.last_move = other.last_move;
   }
@@ -465,6 +646,14 @@ impl Board {
     self.moves
   }
 
+  /// Get the hash of the pawns in the current position
+  ///
+  /// Pawns are hashed with the same keys as in the full hash.
+  #[must_use]
+  pub const fn pawn_hash(&self) -> Hash {
+    self.pawn_hash
+  }
+
   /// Get the hash of the current position
   #[must_use]
   pub const fn hash(&self) -> Hash {
@@ -483,6 +672,43 @@ impl Board {
     &self.pieces
   }
 
+  /// The material signature of a side, the number of pieces of each type it has
+  #[must_use]
+  pub const fn material(&self, side: bool) -> &MaterialSignature {
+    &self.material[side as usize]
+  }
+
+  /// The number of a piece on the board, including kings.
+  /// Positive values count white pieces and negative values count black pieces.
+  ///
+  /// # Panics
+  /// Panics if `piece` is `SQUARE`
+  ///
+  /// # Examples
+  /// ```
+  /// use liberty_chess::{Board, PAWN, QUEEN};
+  /// use liberty_chess::positions::STARTPOS;
+  ///
+  /// let board = Board::new(STARTPOS).unwrap();
+  /// assert_eq!(board.piece_count(PAWN), 8);
+  /// assert_eq!(board.piece_count(-QUEEN), 1);
+  /// ```
+  #[must_use]
+  pub fn piece_count(&self, piece: Piece) -> u32 {
+    self.material(piece > 0)[usize::from(piece.unsigned_abs()) - 1]
+  }
+
+  // Count the material from scratch
+  fn count_material(&self) -> [MaterialSignature; 2] {
+    let mut material = [MaterialSignature::default(); 2];
+    for piece in self.pieces.elements_row_major_iter() {
+      if *piece != SQUARE {
+        material[usize::from(*piece > 0)][usize::from(piece.unsigned_abs()) - 1] += 1;
+      }
+    }
+    material
+  }
+
   /// The coordinates of the kings under attack.
   /// Only considers the side to move.
   #[must_use]
@@ -507,6 +733,79 @@ impl Board {
     false
   }
 
+  /// Whether a side has the material to win on its own, used to decide games one player leaves.
+  ///
+  /// Uses the same rules as the draw by insufficient material, so pawns count if they can promote
+  /// to a piece that can mate, and positions with more than one enemy king always count.
+  ///
+  /// # Examples
+  /// ```
+  /// use liberty_chess::Board;
+  ///
+  /// let board = Board::new("4k3/8/8/8/8/8/8/2B1KN2 w - - 0 1").unwrap();
+  /// assert!(board.has_mating_material(true));
+  /// assert!(!board.has_mating_material(false));
+  ///
+  /// let board = Board::new("4k3/8/8/8/8/8/8/4KN2 w - - 0 1").unwrap();
+  /// assert!(!board.has_mating_material(true));
+  /// ```
+  #[must_use]
+  pub fn has_mating_material(&self, side: bool) -> bool {
+    self.can_force_mate(Some(side))
+  }
+
+  /// Check the position for setups that can't come from a normal game.
+  ///
+  /// Promoted pieces are only counted for boards using the normal chess size and promotions.
+  ///
+  /// # Examples
+  /// ```
+  /// use liberty_chess::{Board, SanityIssue};
+  /// use liberty_chess::positions::STARTPOS;
+  ///
+  /// let board = Board::new(STARTPOS).unwrap();
+  /// assert!(board.sanity_report().is_empty());
+  ///
+  /// let board = Board::new("4k3/4Q3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+  /// assert_eq!(board.sanity_report(), vec![SanityIssue::OpponentInCheck(false)]);
+  /// ```
+  #[must_use]
+  pub fn sanity_report(&self) -> Vec<SanityIssue> {
+    let mut issues = Vec::new();
+    if self
+      .kings(!self.to_move)
+      .iter()
+      .any(|king| self.is_attacked(*king, self.to_move))
+    {
+      issues.push(SanityIssue::OpponentInCheck(!self.to_move));
+    }
+    // only possible when pawns promote to pawns, as they have no moves left
+    for (side, row) in [(true, self.height() - 1), (false, 0)] {
+      let pawn = if side { PAWN } else { -PAWN };
+      if (0..self.width()).any(|column| self.pieces[(row, column)] == pawn) {
+        issues.push(SanityIssue::PawnOnPromotionRow(side));
+      }
+    }
+    if !self.non_default_size() && !self.non_default_promotions() {
+      for side in [true, false] {
+        let multiplier = if side { 1 } else { -1 };
+        let pawns = self.piece_count(PAWN * multiplier);
+        let promoted: u32 = [(QUEEN, 1), (ROOK, 2), (BISHOP, 2), (KNIGHT, 2)]
+          .iter()
+          .map(|(piece, start)| self.piece_count(*piece * multiplier).saturating_sub(*start))
+          .sum();
+        let missing_pawns = 8u32.saturating_sub(pawns);
+        if promoted > missing_pawns {
+          issues.push(SanityIssue::ExcessPromotedPieces {
+            side,
+            excess: promoted - missing_pawns,
+          });
+        }
+      }
+    }
+    issues
+  }
+
   /// Get the current state of the game
   #[must_use]
   pub const fn state(&self) -> Gamestate {
@@ -531,6 +830,16 @@ impl Board {
     self.shared_data.castle_row != 0
       || self.shared_data.king_column != 7
       || self.shared_data.queen_column != 0
+      || self.shared_data.chess960
+  }
+
+  /// Whether castling follows Chess960 rules
+  ///
+  /// The king and castling piece end up on the same columns as in normal chess,
+  /// and castling moves are written as the king moving onto the castling piece.
+  #[must_use]
+  pub fn chess960(&self) -> bool {
+    self.shared_data.chess960
   }
 
   /// Whether there are multiple or 0 kings
@@ -559,6 +868,9 @@ impl Board {
     {
       return false;
     }
+    if let Some(kingside) = self.chess960_castling(start, end) {
+      return self.chess960_castling_valid(start, end, kingside);
+    }
     let destination = self.pieces[end];
     // El Vaticano
     if piece.abs() == BISHOP && piece == destination {
@@ -567,12 +879,12 @@ impl Board {
       if (rows == 2 && cols == 0) || (rows == 0 && cols == 2) {
         let target = self.pieces[((start.0 + end.0) / 2, (start.1 + end.1) / 2)];
         return target != 0
-          && DEFENCE[target.unsigned_abs() as usize] < ATTACK[BISHOP as usize]
+          && can_capture(BISHOP, target)
           && ((target > 0) != (piece > 0) || self.friendly_fire);
       }
     }
     if ((piece > 0) == (destination > 0) && destination != 0 && !self.friendly_fire)
-      || DEFENCE[destination.unsigned_abs() as usize] >= ATTACK[piece.unsigned_abs() as usize]
+      || !can_capture(piece, destination)
     {
       return false;
     }
@@ -675,7 +987,8 @@ impl Board {
       }
       KING => {
         (rows <= 1 && cols <= 1)
-          || (start.0 == self.castle_row(self.to_move)
+          || (!self.shared_data.chess960
+            && start.0 == self.castle_row(self.to_move)
             && rows == 0
             && cols == 2
             && !self.in_check()
@@ -708,8 +1021,21 @@ impl Board {
 
   /// Moves a piece from one square to another.
   /// This function assumes the move is legal.
-  fn make_move(&mut self, start: (usize, usize), end: (usize, usize)) {
+  fn make_move(&mut self, start: (usize, usize), mut end: (usize, usize)) {
     self.last_move = Some(Move::new(start, end));
+    // lift the castling piece so the king can move to its destination
+    let mut castled_piece = None;
+    if let Some(kingside) = self.chess960_castling(start, end) {
+      let (king_column, rook_column) = self.chess960_columns(kingside);
+      let rook_type = self.pieces[end];
+      self
+        .shared_data
+        .keys
+        .update_hash(&mut self.hash, rook_type, end);
+      self.pieces[end] = SQUARE;
+      castled_piece = Some((rook_type, (start.0, rook_column)));
+      end = (start.0, king_column);
+    }
     let keys = &self.shared_data.keys;
     self.halfmoves += 1;
     self.to_move = !self.to_move;
@@ -733,11 +1059,13 @@ impl Board {
         for i in lowest + 1..highest {
           let position = (start.0, i);
           keys.update_hash(&mut self.hash, self.pieces[position], position);
+          update_pawn_hash(keys, &mut self.pawn_hash, self.pieces[position], position);
           if self.pieces[position] > 0 {
             self.white_pieces -= 1;
           } else {
             self.black_pieces -= 1;
           }
+          remove_material(&mut self.material, self.pieces[position]);
           self.pieces[position] = SQUARE;
         }
         return;
@@ -750,11 +1078,13 @@ impl Board {
         for i in lowest + 1..highest {
           let position = (i, start.1);
           keys.update_hash(&mut self.hash, self.pieces[position], position);
+          update_pawn_hash(keys, &mut self.pawn_hash, self.pieces[position], position);
           if self.pieces[position] > 0 {
             self.white_pieces -= 1;
           } else {
             self.black_pieces -= 1;
           }
+          remove_material(&mut self.material, self.pieces[position]);
           self.pieces[position] = SQUARE;
         }
         return;
@@ -762,6 +1092,8 @@ impl Board {
     }
     keys.update_hash(&mut self.hash, piece, start);
     keys.update_hash(&mut self.hash, piece, end);
+    update_pawn_hash(keys, &mut self.pawn_hash, piece, start);
+    update_pawn_hash(keys, &mut self.pawn_hash, piece, end);
     match piece.abs() {
       PAWN => {
         self.halfmoves = 0;
@@ -790,11 +1122,13 @@ impl Board {
               (coords, self.pieces[coords])
             };
             self.hash ^= keys.pieces[coords][(piece - 1) as usize];
+            update_pawn_hash(keys, &mut self.pawn_hash, self.pieces[coords], coords);
             if self.pieces[coords] > 0 {
               self.white_pieces -= 1;
             } else {
               self.black_pieces -= 1;
             }
+            remove_material(&mut self.material, self.pieces[coords]);
             self.pieces[coords] = SQUARE;
           }
           keys.update_en_passant(&mut self.hash, [column, row_min, row_max]);
@@ -809,7 +1143,7 @@ impl Board {
           keys.update_en_passant(&mut self.hash, en_passant);
           self.en_passant = None;
         }
-        if start.0 == self.castle_row(!self.to_move) {
+        if !self.shared_data.chess960 && start.0 == self.castle_row(!self.to_move) {
           match start.1 {
             _ if start.1 == end.1 + 2 => {
               // queenside castling
@@ -860,19 +1194,26 @@ impl Board {
       }
     }
     let capture = self.pieces[end];
-    if capture != SQUARE {
+    // a castling king can stay on its square
+    if capture != SQUARE && start != end {
       keys.update_hash(&mut self.hash, capture, end);
+      update_pawn_hash(keys, &mut self.pawn_hash, capture, end);
       if capture > 0 {
         self.white_pieces -= 1;
       } else {
         self.black_pieces -= 1;
       }
+      remove_material(&mut self.material, capture);
       self.halfmoves = 0;
       self.previous.clear();
       self.duplicates.clear();
     }
-    self.pieces[end] = piece;
     self.pieces[start] = SQUARE;
+    self.pieces[end] = piece;
+    if let Some((rook_type, rook)) = castled_piece {
+      keys.update_hash(&mut self.hash, rook_type, rook);
+      self.pieces[rook] = rook_type;
+    }
     self.hash ^= keys.castling[usize::from(self.castling)];
     self.castling &= self.shared_data.castling_masks[start];
     self.castling &= self.shared_data.castling_masks[end];
@@ -881,6 +1222,7 @@ impl Board {
     #[cfg(feature = "validate")]
     {
       assert_eq!(self.hash, self.get_hash());
+      assert_eq!(self.pawn_hash, self.get_pawn_hash());
       let mut white_pieces = 0;
       let mut black_pieces = 0;
       for piece in self.pieces.elements_row_major_iter() {
@@ -894,6 +1236,7 @@ impl Board {
       }
       assert_eq!(self.white_pieces, white_pieces);
       assert_eq!(self.black_pieces, black_pieces);
+      assert_eq!(self.material, self.count_material());
     }
   }
 
@@ -934,8 +1277,12 @@ impl Board {
       let keys = &self.shared_data.keys;
       self.hash ^= keys.pieces[target][(PAWN - 1) as usize];
       self.hash ^= keys.pieces[target][(piece - 1) as usize];
+      update_pawn_hash(keys, &mut self.pawn_hash, self.pieces[target], target);
       self.pieces[target] *= piece;
       self.promotion_target = None;
+      let side = usize::from(self.pieces[target] > 0);
+      self.material[side][usize::from(PAWN.unsigned_abs()) - 1] -= 1;
+      self.material[side][usize::from(piece.unsigned_abs()) - 1] += 1;
       if piece == KING {
         if self.to_move {
           self.black_pieces -= 1;
@@ -959,7 +1306,7 @@ impl Board {
   #[allow(clippy::manual_flatten)]
   // inlining gives approx 2% speed improvement
   #[inline(always)]
-  fn is_attacked(&self, (row, column): (usize, usize), side: bool) -> bool {
+  pub fn is_attacked(&self, (row, column): (usize, usize), side: bool) -> bool {
     let multiplier = if side { 1 } else { -1 };
     if self.shared_data.horizontal {
       for piece in self.straight((row, column), 1) {
@@ -1210,6 +1557,65 @@ impl Board {
     }
   }
 
+  // Whether a move is Chess960 castling, and if so whether it is kingside
+  fn chess960_castling(&self, start: (usize, usize), end: (usize, usize)) -> Option<bool> {
+    let piece = self.pieces[start];
+    if !self.shared_data.chess960
+      || piece.abs() != KING
+      || piece * self.pieces[end] <= 0
+      || start.0 != end.0
+      || start.0 != self.castle_row(piece > 0)
+    {
+      return None;
+    }
+    let offset = Self::castle_offset(piece > 0);
+    if end.1 == self.shared_data.king_column
+      && start.1 < end.1
+      && self.castling & (1 << offset) != 0
+    {
+      Some(true)
+    } else if end.1 == self.shared_data.queen_column
+      && end.1 < start.1
+      && self.castling & (2 << offset) != 0
+    {
+      Some(false)
+    } else {
+      None
+    }
+  }
+
+  // The destination columns of the king and castling piece in Chess960
+  fn chess960_columns(&self, kingside: bool) -> (usize, usize) {
+    if kingside {
+      (self.width() - 2, self.width() - 3)
+    } else {
+      (2, 3)
+    }
+  }
+
+  // Chess960 castling needs the squares both pieces travel over to be empty
+  // and the squares the king travels through to be safe
+  fn chess960_castling_valid(
+    &self,
+    start: (usize, usize),
+    end: (usize, usize),
+    kingside: bool,
+  ) -> bool {
+    let (king_column, rook_column) = self.chess960_columns(kingside);
+    let span = |a: usize, b: usize| usize::min(a, b)..=usize::max(a, b);
+    !self.in_check()
+      && span(start.1, king_column)
+        .chain(span(end.1, rook_column))
+        .all(|column| {
+          column == start.1 || column == end.1 || self.pieces[(start.0, column)] == SQUARE
+        })
+      && span(start.1, king_column).all(|column| {
+        column == start.1
+          || column == king_column
+          || !self.is_attacked((start.0, column), !self.to_move)
+      })
+  }
+
   const fn kings(&self, side: bool) -> &Vec<(usize, usize)> {
     if side {
       &self.white_kings
@@ -1221,7 +1627,15 @@ impl Board {
   /// Update kings in check and game state.
   pub fn update(&mut self) {
     match (self.white_pieces == 0, self.black_pieces == 0) {
-      (true, true) => return self.state = Gamestate::Material,
+      // a side with nothing left has been eliminated
+      (true, true) => {
+        self.state = match (self.white_kings.is_empty(), self.black_kings.is_empty()) {
+          (true, false) => Gamestate::Elimination(false),
+          (false, true) => Gamestate::Elimination(true),
+          (true, true) | (false, false) => Gamestate::Material,
+        };
+        return;
+      }
       (true, false) => {
         if self.white_kings.is_empty() {
           self.state = Gamestate::Elimination(false);
@@ -1288,6 +1702,18 @@ impl Board {
     result
   }
 
+  #[must_use]
+  fn get_pawn_hash(&self) -> Hash {
+    let mut result = 0;
+    let keys = &self.shared_data.keys;
+    for i in 0..self.height() {
+      for j in 0..self.width() {
+        update_pawn_hash(keys, &mut result, self.pieces[(i, j)], (i, j));
+      }
+    }
+    result
+  }
+
   #[must_use]
   fn any_moves(&self) -> bool {
     for i in 0..self.height() {
@@ -1342,49 +1768,52 @@ impl Board {
   }
 
   fn sufficient_material(&self) -> bool {
-    if self.white_kings.len() != 1 || self.black_kings.len() != 1 {
+    self.can_force_mate(None)
+  }
+
+  // Whether the pieces of one side, or both sides together for `None`, could force checkmate
+  fn can_force_mate(&self, side: Option<bool>) -> bool {
+    let multiple_kings = match side {
+      Some(side) => self.kings(!side).len() != 1,
+      None => self.white_kings.len() != 1 || self.black_kings.len() != 1,
+    };
+    if multiple_kings {
       return true;
     }
-    let mut flexible_piece = false;
-    let mut even_piece = false;
-    let mut odd_piece = false;
-    for i in 0..self.height() {
-      for j in 0..self.width() {
-        let piece = self.pieces[(i, j)];
-        match piece.abs() {
-          ROOK | QUEEN | ARCHBISHOP | CHANCELLOR | MANN | CHAMPION | CENTAUR | AMAZON
-          | ELEPHANT => return true,
-          PAWN => {
-            if self.shared_data.pawn_checkmates || flexible_piece || even_piece || odd_piece {
-              return true;
-            }
-            flexible_piece = true;
-          }
-          KNIGHT | ZEBRA | NIGHTRIDER => {
-            if flexible_piece || even_piece || odd_piece {
-              return true;
-            }
-            flexible_piece = true;
-          }
-          BISHOP | CAMEL => {
-            let even = (i + j) % 2 == 0;
-            if even {
-              if flexible_piece || odd_piece {
-                return true;
-              }
-              even_piece = true;
-            } else if flexible_piece || even_piece {
-              return true;
-            } else {
-              odd_piece = true;
+    let count = |piece: Piece| match side {
+      Some(true) => self.piece_count(piece),
+      Some(false) => self.piece_count(-piece),
+      None => self.piece_count(piece) + self.piece_count(-piece),
+    };
+    if [
+      ROOK, QUEEN, ARCHBISHOP, CHANCELLOR, MANN, CHAMPION, CENTAUR, AMAZON, ELEPHANT,
+    ]
+    .into_iter()
+    .any(|piece| count(piece) > 0)
+      || (self.shared_data.pawn_checkmates && count(PAWN) > 0)
+    {
+      return true;
+    }
+    let flexible_pieces = count(PAWN) + count(KNIGHT) + count(ZEBRA) + count(NIGHTRIDER);
+    let bound_pieces = count(BISHOP) + count(CAMEL);
+    match (flexible_pieces, bound_pieces) {
+      (0, 0 | 1) | (1, 0) => false,
+      (0, _) => {
+        // only pieces on opposite colours can mate together
+        let mut colours = [false; 2];
+        for i in 0..self.height() {
+          for j in 0..self.width() {
+            let piece = self.pieces[(i, j)];
+            if side.is_none_or(|side| (piece > 0) == side) && matches!(piece.abs(), BISHOP | CAMEL)
+            {
+              colours[(i + j) % 2] = true;
             }
           }
-          // King, obstacle and wall
-          _ => (),
         }
+        colours[0] && colours[1]
       }
+      _ => true,
     }
-    false
   }
 
   fn test_legal(&self, start: (usize, usize), end: (usize, usize)) -> bool {
@@ -1444,12 +1873,13 @@ impl Board {
   #[must_use]
   fn ray_length(
     pieces: &Array2D<Piece>,
-    colour: bool,
+    attacker: Piece,
     (mut row, mut column): (isize, isize),
     dx: isize,
     dy: isize,
   ) -> i64 {
     let mut length = 0;
+    let colour = attacker > 0;
     let (offset, enemy_pawn) = if colour {
       (1, Some(&-PAWN))
     } else {
@@ -1469,7 +1899,8 @@ impl Board {
           }
         }
         Some(piece) => {
-          if colour != (*piece > 0) {
+          // pieces immune to the attacker don't count, but checks still do
+          if colour != (*piece > 0) && (piece.abs() == KING || can_capture(attacker, *piece)) {
             let row = (row + offset) as usize;
             if pieces.get(row, column.wrapping_sub(1)) != enemy_pawn
               && pieces.get(row, column + 1) != enemy_pawn
@@ -1486,49 +1917,82 @@ impl Board {
   }
 
   #[must_use]
-  fn bishop_mobility(pieces: &Array2D<Piece>, piece: (usize, usize), colour: bool) -> i64 {
+  fn bishop_mobility(pieces: &Array2D<Piece>, piece: (usize, usize), attacker: Piece) -> i64 {
     let piece = (piece.0 as isize, piece.1 as isize);
-    Self::ray_length(pieces, colour, piece, 1, 1)
-      + Self::ray_length(pieces, colour, piece, 1, -1)
-      + Self::ray_length(pieces, colour, piece, -1, 1)
-      + Self::ray_length(pieces, colour, piece, -1, -1)
+    Self::ray_length(pieces, attacker, piece, 1, 1)
+      + Self::ray_length(pieces, attacker, piece, 1, -1)
+      + Self::ray_length(pieces, attacker, piece, -1, 1)
+      + Self::ray_length(pieces, attacker, piece, -1, -1)
   }
 
   #[must_use]
-  fn rook_mobility(pieces: &Array2D<Piece>, piece: (usize, usize), colour: bool) -> i64 {
+  fn rook_mobility(pieces: &Array2D<Piece>, piece: (usize, usize), attacker: Piece) -> i64 {
     let piece = (piece.0 as isize, piece.1 as isize);
-    Self::ray_length(pieces, colour, piece, 1, 0)
-      + Self::ray_length(pieces, colour, piece, 0, 1)
-      + Self::ray_length(pieces, colour, piece, -1, 0)
-      + Self::ray_length(pieces, colour, piece, 0, -1)
+    Self::ray_length(pieces, attacker, piece, 1, 0)
+      + Self::ray_length(pieces, attacker, piece, 0, 1)
+      + Self::ray_length(pieces, attacker, piece, -1, 0)
+      + Self::ray_length(pieces, attacker, piece, 0, -1)
   }
 
   #[must_use]
-  fn nightrider_mobility(pieces: &Array2D<Piece>, piece: (usize, usize), colour: bool) -> i64 {
+  fn nightrider_mobility(pieces: &Array2D<Piece>, piece: (usize, usize), attacker: Piece) -> i64 {
     let piece = (piece.0 as isize, piece.1 as isize);
-    Self::ray_length(pieces, colour, piece, 2, 1)
-      + Self::ray_length(pieces, colour, piece, 1, 2)
-      + Self::ray_length(pieces, colour, piece, -2, 1)
-      + Self::ray_length(pieces, colour, piece, -1, 2)
-      + Self::ray_length(pieces, colour, piece, 2, -1)
-      + Self::ray_length(pieces, colour, piece, 1, -2)
-      + Self::ray_length(pieces, colour, piece, -2, -1)
-      + Self::ray_length(pieces, colour, piece, -1, -2)
+    Self::ray_length(pieces, attacker, piece, 2, 1)
+      + Self::ray_length(pieces, attacker, piece, 1, 2)
+      + Self::ray_length(pieces, attacker, piece, -2, 1)
+      + Self::ray_length(pieces, attacker, piece, -1, 2)
+      + Self::ray_length(pieces, attacker, piece, 2, -1)
+      + Self::ray_length(pieces, attacker, piece, 1, -2)
+      + Self::ray_length(pieces, attacker, piece, -2, -1)
+      + Self::ray_length(pieces, attacker, piece, -1, -2)
+  }
+
+  /// Count the El Vaticano captures available to a bishop
+  #[must_use]
+  fn vaticano_threats(
+    pieces: &Array2D<Piece>,
+    (row, column): (usize, usize),
+    bishop: Piece,
+  ) -> i64 {
+    let mut threats = 0;
+    for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+      let target = pieces.get(row.wrapping_add_signed(dx), column.wrapping_add_signed(dy));
+      let partner = pieces.get(
+        row.wrapping_add_signed(2 * dx),
+        column.wrapping_add_signed(2 * dy),
+      );
+      if let (Some(&target), Some(&partner)) = (target, partner) {
+        if partner == bishop
+          && target != SQUARE
+          && (target > 0) != (bishop > 0)
+          && can_capture(BISHOP, target)
+        {
+          threats += 1;
+        }
+      }
+    }
+    threats
   }
 
   /// Get the mobility of a ray attacked piece on a square
+  ///
+  /// Enemy pieces immune to the piece are not counted, and bishops count El Vaticano captures
   #[must_use]
   pub fn mobility(pieces: &Array2D<Piece>, piece: (usize, usize), piece_type: Piece) -> i64 {
     // not correct for empty square but irrelevant in that case
-    let colour = piece_type > 0;
     match piece_type.abs() {
       // factor in ray attacks only
-      BISHOP | ARCHBISHOP => Self::bishop_mobility(pieces, piece, colour),
-      ROOK | CHANCELLOR => Self::rook_mobility(pieces, piece, colour),
+      BISHOP => {
+        Self::bishop_mobility(pieces, piece, piece_type)
+          + Self::vaticano_threats(pieces, piece, piece_type)
+      }
+      ARCHBISHOP => Self::bishop_mobility(pieces, piece, piece_type),
+      ROOK | CHANCELLOR => Self::rook_mobility(pieces, piece, piece_type),
       QUEEN | AMAZON => {
-        Self::bishop_mobility(pieces, piece, colour) + Self::rook_mobility(pieces, piece, colour)
+        Self::bishop_mobility(pieces, piece, piece_type)
+          + Self::rook_mobility(pieces, piece, piece_type)
       }
-      NIGHTRIDER => Self::nightrider_mobility(pieces, piece, colour),
+      NIGHTRIDER => Self::nightrider_mobility(pieces, piece, piece_type),
       // non ray attack pieces are 0 for now
       _ => 0,
     }
@@ -1,7 +1,7 @@
 use crate::moves::Move;
 use crate::{
-  Board, BISHOP, CAMEL, CENTAUR, CHAMPION, CHANCELLOR, ELEPHANT, KING, KNIGHT, MANN, OBSTACLE,
-  PAWN, ROOK, WALL, ZEBRA,
+  Board, Piece, BISHOP, CAMEL, CENTAUR, CHAMPION, CHANCELLOR, ELEPHANT, KING, KNIGHT, MANN,
+  OBSTACLE, PAWN, ROOK, WALL, ZEBRA,
 };
 
 impl Board {
@@ -9,6 +9,12 @@ impl Board {
   #[must_use]
   pub fn generate_legal(&self) -> Vec<Self> {
     let mut boards = Vec::new();
+    self.generate_legal_into(&mut boards);
+    boards
+  }
+
+  /// Generates all legal moves from a position, appending the resulting positions to `boards`.
+  pub fn generate_legal_into(&self, boards: &mut Vec<Self>) {
     let king_safe = !self.in_check();
     for i in 0..self.height() {
       for j in 0..self.width() {
@@ -57,43 +63,43 @@ impl Board {
             }
             ROOK => {
               for k in 0..self.height() {
-                self.add_if_legal(&mut boards, (i, j), (k, j), &mut skip_legality);
+                self.add_if_legal(boards, (i, j), (k, j), &mut skip_legality);
               }
               for l in 0..self.width() {
-                self.add_if_legal(&mut boards, (i, j), (i, l), &mut skip_legality);
+                self.add_if_legal(boards, (i, j), (i, l), &mut skip_legality);
               }
             }
             KNIGHT => {
               for (k, l) in Self::jump_coords((i, j), 2, 1) {
                 if k < self.height() && l < self.width() {
-                  self.add_if_legal(&mut boards, (i, j), (k, l), &mut skip_legality);
+                  self.add_if_legal(boards, (i, j), (k, l), &mut skip_legality);
                 }
               }
             }
             CHANCELLOR => {
               for k in 0..self.height() {
-                self.add_if_legal(&mut boards, (i, j), (k, j), &mut skip_legality);
+                self.add_if_legal(boards, (i, j), (k, j), &mut skip_legality);
               }
               for l in 0..self.width() {
-                self.add_if_legal(&mut boards, (i, j), (i, l), &mut skip_legality);
+                self.add_if_legal(boards, (i, j), (i, l), &mut skip_legality);
               }
               for (k, l) in Self::jump_coords((i, j), 2, 1) {
                 if k < self.height() && l < self.width() {
-                  self.add_if_legal(&mut boards, (i, j), (k, l), &mut skip_legality);
+                  self.add_if_legal(boards, (i, j), (k, l), &mut skip_legality);
                 }
               }
             }
             CAMEL => {
               for (k, l) in Self::jump_coords((i, j), 3, 1) {
                 if k < self.height() && l < self.width() {
-                  self.add_if_legal(&mut boards, (i, j), (k, l), &mut skip_legality);
+                  self.add_if_legal(boards, (i, j), (k, l), &mut skip_legality);
                 }
               }
             }
             ZEBRA => {
               for (k, l) in Self::jump_coords((i, j), 3, 2) {
                 if k < self.height() && l < self.width() {
-                  self.add_if_legal(&mut boards, (i, j), (k, l), &mut skip_legality);
+                  self.add_if_legal(boards, (i, j), (k, l), &mut skip_legality);
                 }
               }
             }
@@ -104,7 +110,7 @@ impl Board {
               let right_row = usize::min(i + 1, self.height() - 1);
               for k in left_row..=right_row {
                 for l in left_column..=right_column {
-                  self.add_if_legal(&mut boards, (i, j), (k, l), &mut skip_legality);
+                  self.add_if_legal(boards, (i, j), (k, l), &mut skip_legality);
                 }
               }
             }
@@ -115,7 +121,7 @@ impl Board {
               let right_row = usize::min(i + 2, self.height() - 1);
               for k in left_row..=right_row {
                 for l in left_column..=right_column {
-                  self.add_if_legal(&mut boards, (i, j), (k, l), &mut skip_legality);
+                  self.add_if_legal(boards, (i, j), (k, l), &mut skip_legality);
                 }
               }
             }
@@ -126,12 +132,12 @@ impl Board {
               let right_row = usize::min(i + 1, self.height() - 1);
               for k in left_row..=right_row {
                 for l in left_column..=right_column {
-                  self.add_if_legal(&mut boards, (i, j), (k, l), &mut skip_legality);
+                  self.add_if_legal(boards, (i, j), (k, l), &mut skip_legality);
                 }
               }
               for (k, l) in Self::jump_coords((i, j), 2, 1) {
                 if k < self.height() && l < self.width() {
-                  self.add_if_legal(&mut boards, (i, j), (k, l), &mut skip_legality);
+                  self.add_if_legal(boards, (i, j), (k, l), &mut skip_legality);
                 }
               }
             }
@@ -142,21 +148,18 @@ impl Board {
               let right_row = usize::min(i + 1, self.height() - 1);
               for k in left_row..=right_row {
                 for l in left_column..=right_column {
-                  self.add_if_legal(&mut boards, (i, j), (k, l), &mut skip_legality);
+                  self.add_if_legal(boards, (i, j), (k, l), &mut skip_legality);
                 }
               }
               // Castling
-              if j >= 2 {
-                self.add_if_legal(&mut boards, (i, j), (i, j - 2), &mut skip_legality);
-              }
-              if j + 2 < self.width() {
-                self.add_if_legal(&mut boards, (i, j), (i, j + 2), &mut skip_legality);
+              for l in self.castling_columns(j) {
+                self.add_if_legal(boards, (i, j), (i, l), &mut skip_legality);
               }
             }
             _ => {
               for k in 0..self.height() {
                 for l in 0..self.width() {
-                  self.add_if_legal(&mut boards, (i, j), (k, l), &mut skip_legality);
+                  self.add_if_legal(boards, (i, j), (k, l), &mut skip_legality);
                 }
               }
             }
@@ -164,7 +167,39 @@ impl Board {
         }
       }
     }
-    boards
+  }
+
+  /// Generates all legal moves from a position, without creating the resulting positions.
+  #[must_use]
+  pub fn generate_legal_moves(&self) -> Vec<Move> {
+    let mut moves = Vec::new();
+    self.generate_legal_moves_into(&mut moves, &mut Vec::new(), &mut Vec::new());
+    moves
+  }
+
+  /// Generates all legal moves from a position, appending them to `moves`.
+  ///
+  /// `captures` and `quiets` are cleared and used as scratch space for move generation.
+  pub fn generate_legal_moves_into(
+    &self,
+    moves: &mut Vec<Move>,
+    captures: &mut Vec<(Move, u8, u8)>,
+    quiets: &mut Vec<Move>,
+  ) {
+    captures.clear();
+    quiets.clear();
+    self.generate_pseudolegal(captures, quiets);
+    let mut board = self.clone();
+    for mv in captures
+      .iter()
+      .map(|(mv, _, _)| *mv)
+      .chain(quiets.iter().copied())
+    {
+      board.clone_from(self);
+      if board.play_pseudolegal(mv.start(), mv.end()) {
+        moves.push(mv);
+      }
+    }
   }
 
   // inlining gives approx 3-4% speed improvement
@@ -194,6 +229,20 @@ impl Board {
     }
   }
 
+  // The columns a king could castle to, excluding ones covered by its normal moves
+  // Chess960 castling is written as the king moving onto the castling piece
+  fn castling_columns(&self, column: usize) -> impl Iterator<Item = usize> {
+    let columns = if self.shared_data.chess960 {
+      [self.shared_data.queen_column, self.shared_data.king_column]
+    } else {
+      [column.wrapping_sub(2), column + 2]
+    };
+    let width = self.width();
+    columns
+      .into_iter()
+      .filter(move |l| *l < width && l.abs_diff(column) > 1)
+  }
+
   /// Generates all pseudolegal moves from a position.
   ///
   /// Buckets the moves into enemy captures/promotions and other moves.
@@ -327,11 +376,8 @@ impl Board {
                 }
               }
               // Castling
-              if j >= 2 {
-                self.add_if_pseudolegal(captures, quiets, (i, j), (i, j - 2));
-              }
-              if j + 2 < self.width() {
-                self.add_if_pseudolegal(captures, quiets, (i, j), (i, j + 2));
+              for l in self.castling_columns(j) {
+                self.add_if_pseudolegal(captures, quiets, (i, j), (i, l));
               }
             }
             OBSTACLE | WALL => {
@@ -382,6 +428,12 @@ impl Board {
   #[must_use]
   pub fn generate_qsearch(&self) -> Vec<(Move, u8, u8)> {
     let mut moves = Vec::new();
+    self.generate_qsearch_into(&mut moves);
+    moves
+  }
+
+  /// Generates all captures of enemy pieces and promotions from a position, appending them to `moves`.
+  pub fn generate_qsearch_into(&self, moves: &mut Vec<(Move, u8, u8)>) {
     for i in 0..self.height() {
       for j in 0..self.width() {
         let piece = self.pieces[(i, j)];
@@ -415,43 +467,43 @@ impl Board {
             }
             ROOK => {
               for k in 0..self.height() {
-                self.add_if_pseudolegal_qsearch(&mut moves, (i, j), (k, j));
+                self.add_if_pseudolegal_qsearch(moves, (i, j), (k, j));
               }
               for l in 0..self.width() {
-                self.add_if_pseudolegal_qsearch(&mut moves, (i, j), (i, l));
+                self.add_if_pseudolegal_qsearch(moves, (i, j), (i, l));
               }
             }
             KNIGHT => {
               for (k, l) in Self::jump_coords((i, j), 2, 1) {
                 if k < self.height() && l < self.width() {
-                  self.add_if_pseudolegal_qsearch(&mut moves, (i, j), (k, l));
+                  self.add_if_pseudolegal_qsearch(moves, (i, j), (k, l));
                 }
               }
             }
             CHANCELLOR => {
               for k in 0..self.height() {
-                self.add_if_pseudolegal_qsearch(&mut moves, (i, j), (k, j));
+                self.add_if_pseudolegal_qsearch(moves, (i, j), (k, j));
               }
               for l in 0..self.width() {
-                self.add_if_pseudolegal_qsearch(&mut moves, (i, j), (i, l));
+                self.add_if_pseudolegal_qsearch(moves, (i, j), (i, l));
               }
               for (k, l) in Self::jump_coords((i, j), 2, 1) {
                 if k < self.height() && l < self.width() {
-                  self.add_if_pseudolegal_qsearch(&mut moves, (i, j), (k, l));
+                  self.add_if_pseudolegal_qsearch(moves, (i, j), (k, l));
                 }
               }
             }
             CAMEL => {
               for (k, l) in Self::jump_coords((i, j), 3, 1) {
                 if k < self.height() && l < self.width() {
-                  self.add_if_pseudolegal_qsearch(&mut moves, (i, j), (k, l));
+                  self.add_if_pseudolegal_qsearch(moves, (i, j), (k, l));
                 }
               }
             }
             ZEBRA => {
               for (k, l) in Self::jump_coords((i, j), 3, 2) {
                 if k < self.height() && l < self.width() {
-                  self.add_if_pseudolegal_qsearch(&mut moves, (i, j), (k, l));
+                  self.add_if_pseudolegal_qsearch(moves, (i, j), (k, l));
                 }
               }
             }
@@ -462,7 +514,7 @@ impl Board {
               let right_row = usize::min(i + 1, self.height() - 1);
               for k in left_row..=right_row {
                 for l in left_column..=right_column {
-                  self.add_if_pseudolegal_qsearch(&mut moves, (i, j), (k, l));
+                  self.add_if_pseudolegal_qsearch(moves, (i, j), (k, l));
                 }
               }
             }
@@ -473,7 +525,7 @@ impl Board {
               let right_row = usize::min(i + 2, self.height() - 1);
               for k in left_row..=right_row {
                 for l in left_column..=right_column {
-                  self.add_if_pseudolegal_qsearch(&mut moves, (i, j), (k, l));
+                  self.add_if_pseudolegal_qsearch(moves, (i, j), (k, l));
                 }
               }
             }
@@ -484,12 +536,12 @@ impl Board {
               let right_row = usize::min(i + 1, self.height() - 1);
               for k in left_row..=right_row {
                 for l in left_column..=right_column {
-                  self.add_if_pseudolegal_qsearch(&mut moves, (i, j), (k, l));
+                  self.add_if_pseudolegal_qsearch(moves, (i, j), (k, l));
                 }
               }
               for (k, l) in Self::jump_coords((i, j), 2, 1) {
                 if k < self.height() && l < self.width() {
-                  self.add_if_pseudolegal_qsearch(&mut moves, (i, j), (k, l));
+                  self.add_if_pseudolegal_qsearch(moves, (i, j), (k, l));
                 }
               }
             }
@@ -497,7 +549,7 @@ impl Board {
             _ => {
               for k in 0..self.height() {
                 for l in 0..self.width() {
-                  self.add_if_pseudolegal_qsearch(&mut moves, (i, j), (k, l));
+                  self.add_if_pseudolegal_qsearch(moves, (i, j), (k, l));
                 }
               }
             }
@@ -505,7 +557,6 @@ impl Board {
         }
       }
     }
-    moves
   }
 
   // inlining gives approx 2% speed improvement
@@ -528,6 +579,12 @@ impl Board {
   #[must_use]
   pub fn generate_recaptures(&self, target: (usize, usize)) -> Vec<(Move, u8)> {
     let mut moves = Vec::new();
+    self.generate_recaptures_into(&mut moves, target);
+    moves
+  }
+
+  /// Generates all recaptures of enemy pieces from a position, appending them to `moves`.
+  pub fn generate_recaptures_into(&self, moves: &mut Vec<(Move, u8)>, target: (usize, usize)) {
     for i in 0..self.height() {
       for j in 0..self.width() {
         let piece = self.pieces[(i, j)];
@@ -537,6 +594,85 @@ impl Board {
         }
       }
     }
-    moves
+  }
+
+  /// Static exchange evaluation of a move, the material won once the captures on its square end.
+  ///
+  /// Each side recaptures with its least valuable piece, with the values of piece types given by
+  /// `value`. Pins are ignored, but kings don't recapture onto defended squares.
+  /// Quiet moves give the material lost if the moved piece is captured.
+  #[must_use]
+  pub fn see(&self, mv: Move, value: impl Fn(Piece) -> i32) -> i32 {
+    let (start, target) = (mv.start(), mv.end());
+    let piece = self.pieces[start];
+    let captured = self.pieces[target];
+    let mut gain = if captured == 0 && piece.abs() == PAWN && start.1 != target.1 {
+      // en passant
+      value(PAWN)
+    } else if captured == 0 {
+      0
+    } else {
+      value(captured.abs())
+    };
+    // with friendly fire the first capture can take one of the side's own pieces
+    if captured != 0 && (captured > 0) == (piece > 0) {
+      gain = -gain;
+    }
+    let moved = match mv.promotion() {
+      Some(promotion) => {
+        gain += value(promotion) - value(PAWN);
+        if self.to_move {
+          promotion
+        } else {
+          -promotion
+        }
+      }
+      None => piece,
+    };
+    // most quiet moves go to squares the opponent doesn't attack, which can be checked without
+    // playing the move, at the cost of missing attacks from behind the moved piece
+    if captured == 0 && gain == 0 && !self.is_attacked(target, !self.to_move) {
+      return 0;
+    }
+    // the exchange is played out on a copy of the board, only moving pieces
+    let mut board = self.clone();
+    board.pieces[start] = 0;
+    board.pieces[target] = moved;
+    board.to_move = !board.to_move;
+    // the piece the next capture would win
+    let mut on_square = value(moved.abs());
+    let mut gains = vec![gain];
+    loop {
+      let mut attacker: Option<((usize, usize), Piece, i32)> = None;
+      for i in 0..board.height() {
+        for j in 0..board.width() {
+          let piece = board.pieces[(i, j)];
+          if piece != 0
+            && board.to_move == (piece > 0)
+            && attacker.is_none_or(|(_, _, cheapest)| value(piece.abs()) < cheapest)
+            && board.check_pseudolegal((i, j), target)
+          {
+            attacker = Some(((i, j), piece, value(piece.abs())));
+          }
+        }
+      }
+      let Some((square, piece, piece_value)) = attacker else {
+        break;
+      };
+      if piece.abs() == KING && board.is_attacked(target, !board.to_move) {
+        break;
+      }
+      gains.push(on_square - gains[gains.len() - 1]);
+      board.pieces[square] = 0;
+      board.pieces[target] = piece;
+      board.to_move = !board.to_move;
+      on_square = piece_value;
+    }
+    // either side can stop capturing when carrying on would lose material
+    gains
+      .into_iter()
+      .rev()
+      .reduce(|gain, previous| -(-previous).max(gain))
+      .unwrap_or_default()
   }
 }
@@ -1,5 +1,7 @@
 use crate::parsing::{to_char, to_indices, to_piece, update_column, update_row};
 use crate::{Board, Piece};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 enum Stage {
@@ -9,8 +11,20 @@ enum Stage {
   EndRow,
 }
 
+/// The error returned when a move is not valid long algebraic notation
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MoveParseError;
+
+impl Display for MoveParseError {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    write!(f, "Invalid move notation")
+  }
+}
+
+impl Error for MoveParseError {}
+
 /// A struct to represent a move
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Move {
   start: (u8, u8),
   end: (u8, u8),
@@ -41,7 +55,7 @@ impl ToString for Move {
 }
 
 impl FromStr for Move {
-  type Err = ();
+  type Err = MoveParseError;
 
   fn from_str(string: &str) -> Result<Self, Self::Err> {
     if !string.is_empty() && string.parse::<u32>() != Ok(0) {
@@ -62,7 +76,7 @@ impl FromStr for Move {
             Stage::EndRow => {
               let promotion = to_piece(c).ok().map(i8::abs);
               return if start_row == 0 || start_col == 0 || end_row == 0 || end_col == 0 {
-                Err(())
+                Err(MoveParseError)
               } else {
                 Ok(Self {
                   start: (start_row as u8 - 1, start_col as u8 - 1),
@@ -88,10 +102,10 @@ impl FromStr for Move {
         }
       }
       match stage {
-        Stage::StartCol | Stage::StartRow | Stage::EndCol => Err(()),
+        Stage::StartCol | Stage::StartRow | Stage::EndCol => Err(MoveParseError),
         Stage::EndRow => {
           if start_row == 0 || start_col == 0 || end_row == 0 || end_col == 0 {
-            Err(())
+            Err(MoveParseError)
           } else {
             Ok(Self {
               start: (start_row as u8 - 1, start_col as u8 - 1),
@@ -102,7 +116,7 @@ impl FromStr for Move {
         }
       }
     } else {
-      Err(())
+      Err(MoveParseError)
     }
   }
 }
@@ -1,12 +1,17 @@
+use crate::moves::Move;
+use crate::positions::STARTPOS;
 use crate::{
-  Board, Piece, AMAZON, ARCHBISHOP, BISHOP, CAMEL, CENTAUR, CHAMPION, CHANCELLOR, ELEPHANT, KING,
-  KNIGHT, MANN, NIGHTRIDER, OBSTACLE, PAWN, QUEEN, ROOK, SQUARE, WALL, ZEBRA,
+  Board, Gamestate, Piece, AMAZON, ARCHBISHOP, BISHOP, CAMEL, CENTAUR, CHAMPION, CHANCELLOR,
+  ELEPHANT, KING, KNIGHT, MANN, NIGHTRIDER, OBSTACLE, PAWN, QUEEN, ROOK, SQUARE, WALL, ZEBRA,
 };
 use array2d::Array2D;
-use std::str::FromStr;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
 
 /// An enum to represent the reasons for an L-FEN to be invalid.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum FenError {
   /// An unrecognised piece was encountered
   InvalidPiece(char),
@@ -14,18 +19,26 @@ pub enum FenError {
   NonRectangular,
   /// The board has a width or height less than 2
   Size,
+  /// The castling row is not on the board
+  CastleRow,
+  /// A pawn is on the row it would promote on
+  PawnPromotionRow,
 }
 
-impl ToString for FenError {
-  fn to_string(&self) -> String {
+impl Display for FenError {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
     match self {
-      Self::InvalidPiece(c) => format!("Invalid piece found: {c}"),
-      Self::NonRectangular => "Non-rectangular board found".to_owned(),
-      Self::Size => "Board must be between 2x2 and 256x256".to_owned(),
+      Self::InvalidPiece(c) => write!(f, "Invalid piece found: {c}"),
+      Self::NonRectangular => write!(f, "Non-rectangular board found"),
+      Self::Size => write!(f, "Board must be between 2x2 and 256x256"),
+      Self::CastleRow => write!(f, "Castling row must be on the board"),
+      Self::PawnPromotionRow => write!(f, "Pawns can't start on their promotion row"),
     }
   }
 }
 
+impl Error for FenError {}
+
 impl FromStr for Board {
   type Err = FenError;
 
@@ -66,15 +79,32 @@ impl ToString for Board {
     result += if self.to_move { " w " } else { " b " };
 
     // save castling rights
+    // Chess960 uses the files of the castling pieces where the letter isn't ambiguous
     let mut needs_castling = true;
+    // whether the kingside and queenside columns are given by files
+    let mut castle_files = [false; 2];
     for i in 0..4 {
       if self.castling & (1 << i) != 0 {
         needs_castling = false;
-        result.push(match i {
-          0 => 'K',
-          1 => 'Q',
-          2 => 'k',
-          3 => 'q',
+        let column = if i % 2 == 0 {
+          self.shared_data.king_column
+        } else {
+          self.shared_data.queen_column
+        };
+        let file = Some(column)
+          .filter(|column| self.shared_data.chess960 && *column < 26)
+          .map(get_letter)
+          .filter(|c| *c != 'k' && *c != 'q');
+        if file.is_some() {
+          castle_files[i % 2] = true;
+        }
+        result.push(match (i, file) {
+          (0 | 1, Some(file)) => file.to_ascii_uppercase(),
+          (_, Some(file)) => file,
+          (0, None) => 'K',
+          (1, None) => 'Q',
+          (2, None) => 'k',
+          (3, None) => 'q',
           _ => unreachable!(),
         });
       }
@@ -102,12 +132,19 @@ impl ToString for Board {
     // because previous ones are required
     let mut optional = Vec::new();
 
+    // Chess960 needs a flag if the castling rights don't imply it
+    let chess960_flag = self.shared_data.chess960 && !needs_castling && castle_files == [false; 2];
+    if chess960_flag {
+      optional.push("960".to_owned());
+    }
+
     if self.friendly_fire {
       optional.push("ff".to_owned());
     }
 
-    let custom_promotion =
-      self.friendly_fire || self.shared_data.promotion_options != [QUEEN, ROOK, BISHOP, KNIGHT];
+    let custom_promotion = self.friendly_fire
+      || chess960_flag
+      || self.shared_data.promotion_options != [QUEEN, ROOK, BISHOP, KNIGHT];
 
     // save promotion options
     if custom_promotion {
@@ -120,15 +157,26 @@ impl ToString for Board {
 
     // assemble misc options (also reversed)
     let mut misc = Vec::new();
-    let mut misc_fields = if self.shared_data.king_column == self.width() - 1 {
+    // columns given by files use the defaults
+    let king_column = if castle_files[0] {
+      self.width() - 1
+    } else {
+      self.shared_data.king_column
+    };
+    let queen_column = if castle_files[1] {
+      0
+    } else {
+      self.shared_data.queen_column
+    };
+    let mut misc_fields = if king_column == self.width() - 1 {
       false
     } else {
-      misc.push(self.shared_data.king_column.to_string());
+      misc.push((king_column + 1).to_string());
       true
     };
 
-    if misc_fields || self.shared_data.queen_column != 0 {
-      misc.push(self.shared_data.queen_column.to_string());
+    if misc_fields || queen_column != 0 {
+      misc.push((queen_column + 1).to_string());
       misc_fields = true;
     }
 
@@ -165,6 +213,79 @@ impl ToString for Board {
   }
 }
 
+impl Board {
+  /// Draw the board with ASCII characters, using `.` for empty squares.
+  ///
+  /// Rows and columns are labelled, and kings in check are shown in brackets.
+  ///
+  /// ```
+  /// use liberty_chess::Board;
+  ///
+  /// let board = Board::new("3k/4/4/K2R b - - 0 1").unwrap();
+  /// let expected = "\
+  /// 4  .  .  . [k]
+  /// 3  .  .  .  .
+  /// 2  .  .  .  .
+  /// 1  K  .  .  R
+  ///    a  b  c  d
+  /// Black to move, in check";
+  /// assert_eq!(board.to_ascii(), expected);
+  /// ```
+  #[must_use]
+  pub fn to_ascii(&self) -> String {
+    self.pretty_print(to_char, '.')
+  }
+
+  /// Draw the board with Unicode chess symbols, using `·` for empty squares.
+  ///
+  /// Pieces without a Unicode symbol use their L-FEN letter.
+  /// Rows and columns are labelled, and kings in check are shown in brackets.
+  #[must_use]
+  pub fn to_unicode(&self) -> String {
+    self.pretty_print(to_symbol, '·')
+  }
+
+  fn pretty_print(&self, symbol: fn(Piece) -> char, empty: char) -> String {
+    let checked: Vec<(usize, usize)> = self
+      .kings(self.to_move)
+      .iter()
+      .copied()
+      .filter(|king| self.is_attacked(*king, !self.to_move))
+      .collect();
+    let label_width = self.height().to_string().len();
+    let mut lines = Vec::with_capacity(self.height() + 2);
+    for row in (0..self.height()).rev() {
+      let mut line = format!("{:>label_width$} ", row + 1);
+      for column in 0..self.width() {
+        let piece = self.pieces[(row, column)];
+        let c = if piece == SQUARE {
+          empty
+        } else {
+          symbol(piece)
+        };
+        if checked.contains(&(row, column)) {
+          line += &format!("[{c}]");
+        } else {
+          line += &format!(" {c} ");
+        }
+      }
+      lines.push(line.trim_end().to_owned());
+    }
+    let mut files = " ".repeat(label_width + 1);
+    for column in 0..self.width() {
+      let letters: String = to_letters(column).into_iter().collect();
+      files += &format!("{letters:^3}");
+    }
+    lines.push(files.trim_end().to_owned());
+    let mut status = if self.to_move { "White" } else { "Black" }.to_owned() + " to move";
+    if !checked.is_empty() {
+      status += ", in check";
+    }
+    lines.push(status);
+    lines.join("\n")
+  }
+}
+
 /// Converts a group of characters into pieces
 ///
 /// Ignores invalid pieces
@@ -242,6 +363,24 @@ pub const fn to_char(piece: Piece) -> char {
   }
 }
 
+// Unicode chess symbols for the standard pieces, other pieces use their letter
+const fn to_symbol(piece: Piece) -> char {
+  let (white, black) = match piece.abs() {
+    PAWN => ('♙', '♟'),
+    KNIGHT => ('♘', '♞'),
+    BISHOP => ('♗', '♝'),
+    ROOK => ('♖', '♜'),
+    QUEEN => ('♕', '♛'),
+    KING => ('♔', '♚'),
+    _ => return to_char(piece),
+  };
+  if piece > 0 {
+    white
+  } else {
+    black
+  }
+}
+
 /// Convert a `Piece` into a string representing the type of piece it is.
 #[must_use]
 pub fn to_name(piece: Piece) -> &'static str {
@@ -428,3 +567,470 @@ pub(crate) fn process_board(
     black_pieces,
   ))
 }
+
+/// The error returned when a PGN is not valid
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PgnError {
+  /// The starting position is not a valid L-FEN
+  Fen(FenError),
+  /// A move could not be parsed or is not legal in the position
+  InvalidMove(String),
+  /// A tag, comment or variation is not closed
+  Unterminated,
+  /// A variation is closed without being opened
+  Unmatched,
+  /// A numeric annotation glyph is not a number from 0 to 255
+  InvalidNag(String),
+}
+
+impl Display for PgnError {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    match self {
+      Self::Fen(error) => write!(f, "Invalid starting position: {error}"),
+      Self::InvalidMove(mv) => write!(f, "Invalid or illegal move {mv}"),
+      Self::Unterminated => write!(f, "Unclosed tag, comment or variation"),
+      Self::Unmatched => write!(f, "Unmatched bracket"),
+      Self::InvalidNag(nag) => write!(f, "Invalid NAG {nag}"),
+    }
+  }
+}
+
+impl Error for PgnError {}
+
+/// The flavour of PGN to write
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PgnDialect {
+  /// Standard algebraic notation, which other chess software can read for normal chess
+  Standard,
+  /// Long algebraic notation with a `Variant` tag, which works for any L-FEN
+  Extended,
+}
+
+// Tokens that end a game in PGN movetext
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+// Longest line of movetext before wrapping, as recommended by the PGN standard
+const LINE_LENGTH: usize = 79;
+
+/// Move quality annotations written after moves, with the NAGs they stand for
+pub const MOVE_GLYPHS: [(u8, &str); 6] = [
+  (1, "!"),
+  (2, "?"),
+  (3, "!!"),
+  (4, "??"),
+  (5, "!?"),
+  (6, "?!"),
+];
+
+/// A game that can be saved and loaded as PGN
+///
+/// Both dialects can be read, and moves can be in standard or long algebraic notation.
+/// Comments, NAGs and variations are skipped when reading.
+///
+/// ```
+/// use liberty_chess::parsing::{Pgn, PgnDialect};
+/// use liberty_chess::positions::STARTPOS;
+/// use liberty_chess::Board;
+///
+/// let mut game = Pgn::new(Board::new(STARTPOS).unwrap());
+/// assert!(game.push("e2e4".parse().unwrap()));
+/// assert!(game.push("e7e5".parse().unwrap()));
+/// let pgn = game.write(PgnDialect::Standard);
+/// assert!(pgn.contains("1. e4 e5"));
+/// let loaded: Pgn = pgn.parse().unwrap();
+/// assert_eq!(loaded.moves(), game.moves());
+/// ```
+#[derive(Clone)]
+pub struct Pgn {
+  /// Tags other than the ones describing the starting position
+  pub tags: Vec<(String, String)>,
+  /// The result of the game, `*` if it is unfinished
+  pub result: String,
+  start: Board,
+  position: Board,
+  moves: Vec<Move>,
+}
+
+impl Pgn {
+  /// Start a game from a position
+  #[must_use]
+  pub fn new(start: Board) -> Self {
+    Self {
+      tags: Vec::new(),
+      result: "*".to_owned(),
+      position: start.clone(),
+      start,
+      moves: Vec::new(),
+    }
+  }
+
+  /// The starting position
+  #[must_use]
+  pub const fn start(&self) -> &Board {
+    &self.start
+  }
+
+  /// The position after the last move
+  #[must_use]
+  pub const fn position(&self) -> &Board {
+    &self.position
+  }
+
+  /// The moves played
+  #[must_use]
+  pub fn moves(&self) -> &[Move] {
+    &self.moves
+  }
+
+  /// Play a move, returns whether it was legal
+  ///
+  /// The result is set when the move ends the game
+  pub fn push(&mut self, mv: Move) -> bool {
+    let Some(position) = self.position.move_if_legal(mv) else {
+      return false;
+    };
+    self.result = match position.state() {
+      Gamestate::InProgress => "*",
+      Gamestate::Checkmate(true) | Gamestate::Elimination(true) => "1-0",
+      Gamestate::Checkmate(false) | Gamestate::Elimination(false) => "0-1",
+      Gamestate::Stalemate | Gamestate::Repetition | Gamestate::FiftyMove | Gamestate::Material => {
+        "1/2-1/2"
+      }
+    }
+    .to_owned();
+    self.position = position;
+    self.moves.push(mv);
+    true
+  }
+
+  /// Write the game as PGN
+  #[must_use]
+  pub fn write(&self, dialect: PgnDialect) -> String {
+    let mut movetext = Movetext::default();
+    let mut board = self.start.clone();
+    for (index, mv) in self.moves.iter().enumerate() {
+      let mut token = if board.to_move() {
+        format!("{}. ", board.moves())
+      } else if index == 0 {
+        format!("{}... ", board.moves())
+      } else {
+        String::new()
+      };
+      token += &match dialect {
+        PgnDialect::Standard => board.san(*mv).unwrap_or_else(|| mv.to_string()),
+        PgnDialect::Extended => mv.to_string(),
+      };
+      movetext.push(&token);
+      match board.move_if_legal(*mv) {
+        Some(position) => board = position,
+        None => break,
+      }
+    }
+    self.write_with(dialect, movetext)
+  }
+
+  /// Write the tags and starting position of the game, followed by movetext and the result
+  ///
+  /// Used for games with annotations or variations, the moves of the game aren't written.
+  #[must_use]
+  pub fn write_with(&self, dialect: PgnDialect, mut movetext: Movetext) -> String {
+    let mut pgn = String::new();
+    for (name, value) in &self.tags {
+      pgn += &format!("[{name} \"{}\"]\n", value.replace('"', "'"));
+    }
+    let fen = self.start.to_string();
+    if dialect == PgnDialect::Extended {
+      pgn += "[Variant \"Liberty Chess\"]\n";
+    }
+    if dialect == PgnDialect::Extended || fen != STARTPOS {
+      pgn += &format!("[SetUp \"1\"]\n[FEN \"{fen}\"]\n");
+    }
+    pgn.push('\n');
+    movetext.push(&self.result);
+    pgn += &movetext.text;
+    pgn += &movetext.line;
+    pgn.push('\n');
+    pgn
+  }
+}
+
+/// PGN movetext being written, wrapped to the line length recommended by the PGN standard
+#[derive(Default)]
+pub struct Movetext {
+  text: String,
+  line: String,
+}
+
+impl Movetext {
+  /// Add a move, move number, NAG, or `(` and `)` around a variation
+  pub fn push(&mut self, token: &str) {
+    if token == ")" {
+      self.line.push(')');
+      return;
+    }
+    if !self.line.is_empty() && self.line.len() + token.len() >= LINE_LENGTH {
+      self.text += &self.line;
+      self.text.push('\n');
+      self.line.clear();
+    }
+    if !self.line.is_empty() && !self.line.ends_with('(') {
+      self.line.push(' ');
+    }
+    self.line += token;
+  }
+
+  /// Add a comment
+  pub fn push_comment(&mut self, comment: &str) {
+    self.push(&format!("{{{}}}", comment.replace('}', ")")));
+  }
+}
+
+// read up to a closing character, returning what was inside
+fn read_until(chars: &mut Peekable<Chars>, end: char) -> Result<String, PgnError> {
+  let mut result = String::new();
+  for c in chars.by_ref() {
+    if c == end {
+      return Ok(result);
+    }
+    result.push(c);
+  }
+  Err(PgnError::Unterminated)
+}
+
+/// A part of a PGN, from [`pgn_tokens`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PgnToken {
+  /// A tag with its name and value
+  Tag(String, String),
+  /// A comment in braces or after a semicolon
+  Comment(String),
+  /// A numeric annotation glyph
+  Nag(u8),
+  /// A move in standard or long algebraic notation
+  Move(String),
+  /// The start of a variation
+  VariationStart,
+  /// The end of a variation
+  VariationEnd,
+  /// The result of the game
+  Result(String),
+}
+
+/// Split a PGN into tags, comments, annotations, moves and variations
+///
+/// Move numbers are skipped, and move quality annotations like `!?` become NAGs.
+///
+/// # Errors
+///
+/// Fails if a tag or comment isn't closed or a NAG is invalid.
+pub fn pgn_tokens(pgn: &str) -> Result<Vec<PgnToken>, PgnError> {
+  let mut tokens = Vec::new();
+  let mut chars = pgn.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '[' => {
+        let tag = read_until(&mut chars, ']')?;
+        if let Some((name, value)) = tag.trim().split_once(char::is_whitespace) {
+          let value = value.trim().trim_matches('"').to_owned();
+          tokens.push(PgnToken::Tag(name.to_owned(), value));
+        }
+      }
+      '{' => {
+        let comment = read_until(&mut chars, '}')?;
+        tokens.push(PgnToken::Comment(comment.trim().to_owned()));
+      }
+      ';' => {
+        let comment: String = chars.by_ref().take_while(|c| *c != '\n').collect();
+        tokens.push(PgnToken::Comment(comment.trim().to_owned()));
+      }
+      '(' => tokens.push(PgnToken::VariationStart),
+      ')' => tokens.push(PgnToken::VariationEnd),
+      _ if c.is_whitespace() => (),
+      _ => {
+        let mut word = c.to_string();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !"[]{}();".contains(*c)) {
+          word.push(c);
+        }
+        if RESULTS.contains(&word.as_str()) {
+          tokens.push(PgnToken::Result(word));
+          continue;
+        }
+        if let Some(nag) = word.strip_prefix('$') {
+          let nag = nag
+            .parse()
+            .map_err(|_| PgnError::InvalidNag(word.clone()))?;
+          tokens.push(PgnToken::Nag(nag));
+          continue;
+        }
+        // move numbers can be attached to the move
+        let word = word.rsplit('.').next().unwrap_or_default();
+        let notation = word.trim_end_matches(['!', '?']);
+        if notation.is_empty() {
+          continue;
+        }
+        tokens.push(PgnToken::Move(notation.to_owned()));
+        let glyph = &word[notation.len()..];
+        if let Some((nag, _)) = MOVE_GLYPHS.iter().find(|(_, symbol)| *symbol == glyph) {
+          tokens.push(PgnToken::Nag(*nag));
+        }
+      }
+    }
+  }
+  Ok(tokens)
+}
+
+impl FromStr for Pgn {
+  type Err = PgnError;
+
+  fn from_str(pgn: &str) -> Result<Self, Self::Err> {
+    let mut tags = Vec::new();
+    let mut fen = STARTPOS.to_owned();
+    let mut result = "*".to_owned();
+    let mut game: Option<Self> = None;
+    // how many variations deep the moves are, only the main line is kept
+    let mut depth = 0_usize;
+    for token in pgn_tokens(pgn)? {
+      match token {
+        PgnToken::Tag(name, value) => match name.as_str() {
+          "FEN" => fen = value,
+          "SetUp" | "Variant" => (),
+          _ => tags.push((name, value)),
+        },
+        PgnToken::VariationStart => depth += 1,
+        PgnToken::VariationEnd => depth = depth.checked_sub(1).ok_or(PgnError::Unmatched)?,
+        PgnToken::Move(notation) if depth == 0 => {
+          if game.is_none() {
+            game = Some(Self::new(Board::new(&fen).map_err(PgnError::Fen)?));
+          }
+          let game = game.as_mut().expect("Game was just created");
+          let mv = game
+            .position
+            .parse_move(&notation)
+            .ok_or(PgnError::InvalidMove(notation))?;
+          game.push(mv);
+        }
+        PgnToken::Result(word) if depth == 0 => result = word,
+        _ => (),
+      }
+    }
+    if depth > 0 {
+      return Err(PgnError::Unterminated);
+    }
+    let mut game = match game {
+      Some(game) => game,
+      None => Self::new(Board::new(&fen).map_err(PgnError::Fen)?),
+    };
+    game.tags = tags;
+    game.result = result;
+    Ok(game)
+  }
+}
+
+impl Board {
+  // Legal moves including each possible promotion
+  fn legal_moves_with_promotions(&self) -> Vec<Move> {
+    let mut moves = Vec::new();
+    for mv in self.generate_legal_moves() {
+      if self.move_if_legal(mv).is_some() {
+        moves.push(mv);
+      } else {
+        for piece in self.promotion_options() {
+          let mut promotion = mv;
+          promotion.add_promotion(*piece);
+          moves.push(promotion);
+        }
+      }
+    }
+    moves
+  }
+
+  /// Write a legal move in standard algebraic notation, or `None` if it isn't legal
+  ///
+  /// Pieces use the letters from L-FENs, and castling is written as `O-O` or `O-O-O`
+  #[must_use]
+  pub fn san(&self, mv: Move) -> Option<String> {
+    let position = self.move_if_legal(mv)?;
+    let (start, end) = (mv.start(), mv.end());
+    let piece = self.get_piece(start).abs();
+    let castling = if self.chess960() {
+      self.chess960_castling(start, end)
+    } else {
+      (piece == KING && start.0 == end.0 && start.1.abs_diff(end.1) == 2).then_some(end.1 > start.1)
+    };
+    let mut san = if let Some(kingside) = castling {
+      if kingside { "O-O" } else { "O-O-O" }.to_owned()
+    } else {
+      let capture = self.get_piece(end) != 0 || (piece == PAWN && start.1 != end.1);
+      let mut san = String::new();
+      if piece == PAWN {
+        if capture {
+          san.extend(to_letters(start.1));
+        }
+      } else {
+        san.push(to_char(piece));
+        // other pieces of the same type that can reach the square
+        let others: Vec<(usize, usize)> = self
+          .generate_legal_moves()
+          .iter()
+          .filter(|other| {
+            other.end() == end
+              && other.start() != start
+              && self.get_piece(other.start()).abs() == piece
+          })
+          .map(Move::start)
+          .collect();
+        if !others.is_empty() {
+          if others.iter().all(|other| other.1 != start.1) {
+            san.extend(to_letters(start.1));
+          } else if others.iter().all(|other| other.0 != start.0) {
+            san += &(start.0 + 1).to_string();
+          } else {
+            san += &to_indices(start.1, start.0, start.0);
+          }
+        }
+      }
+      if capture {
+        san.push('x');
+      }
+      san += &to_indices(end.1, end.0, end.0);
+      if let Some(promotion) = mv.promotion() {
+        san.push('=');
+        san.push(to_char(promotion.abs()));
+      }
+      san
+    };
+    if matches!(position.state(), Gamestate::Checkmate(_)) {
+      san.push('#');
+    } else if position.in_check() {
+      san.push('+');
+    }
+    Some(san)
+  }
+
+  /// Find the legal move described by long or standard algebraic notation
+  ///
+  /// Annotations like `+` and `!?` are ignored, as is the `=` before promotions
+  #[must_use]
+  pub fn parse_move(&self, notation: &str) -> Option<Move> {
+    let notation = notation.trim_end_matches(['+', '#', '!', '?']);
+    if let Ok(mv) = notation.parse::<Move>() {
+      if self.move_if_legal(mv).is_some() {
+        return Some(mv);
+      }
+    }
+    let normalise = |san: &str| {
+      let san = san.replace(['+', '#', '='], "");
+      // castling is sometimes written with zeros
+      if san.starts_with("0-0") {
+        san.replace('0', "O")
+      } else {
+        san
+      }
+    };
+    let notation = normalise(notation);
+    self
+      .legal_moves_with_promotions()
+      .into_iter()
+      .find(|mv| self.san(*mv).is_some_and(|san| normalise(&san) == notation))
+  }
+}
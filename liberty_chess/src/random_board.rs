@@ -1,9 +1,67 @@
+use crate::Board;
 use rand::seq::SliceRandom;
 use rand::{thread_rng, Rng};
 
+// How many layouts to try before giving up on finding a sane one
+const MAX_ATTEMPTS: usize = 100;
+
 /// Randomly generates a board given the specified parameters
+///
+/// Boards are at least 2 wide and 4 high to fit each side's pieces and pawns.
+/// Layouts failing [`Board::sanity_report`] are rerolled and pawns can't promote to pawns.
+///
+/// With `chess960`, the king is kept off the edges so it can castle Chess960 style
+/// with the pieces in the corners, and the castling rights are written as files.
 #[must_use]
-pub fn generate(width: usize, height: usize, piece_options: &str, spawn_king: bool) -> String {
+pub fn generate(
+  width: usize,
+  height: usize,
+  piece_options: &str,
+  spawn_king: bool,
+  chess960: bool,
+) -> String {
+  let promotions: String = piece_options
+    .chars()
+    .filter(|c| !c.eq_ignore_ascii_case(&'p'))
+    .collect();
+  let mut fen = String::new();
+  for _ in 0..MAX_ATTEMPTS {
+    fen = generate_layout(
+      width,
+      height,
+      piece_options,
+      &promotions,
+      spawn_king,
+      chess960,
+    );
+    if let Ok(board) = Board::new(&fen) {
+      if board
+        .sanity_report()
+        .iter()
+        .all(|issue| !issue.blocks_play())
+      {
+        // Shredder-FEN castling rights for compatibility with other programs
+        if chess960 {
+          fen = board.to_string();
+        }
+        break;
+      }
+    }
+  }
+  fen
+}
+
+fn generate_layout(
+  width: usize,
+  height: usize,
+  piece_options: &str,
+  promotions: &str,
+  spawn_king: bool,
+  chess960: bool,
+) -> String {
+  let width = width.clamp(2, 256);
+  let height = height.clamp(4, 256);
+
   // The gap between the white and black pieces
   let gap = height - 4;
 
@@ -19,7 +77,12 @@ pub fn generate(width: usize, height: usize, piece_options: &str, spawn_king: bo
 
   // Add a king to the board
   if spawn_king {
-    pieces[rng.gen_range(0..width)] = 'k';
+    let columns = if chess960 && width > 2 {
+      1..width - 1
+    } else {
+      0..width
+    };
+    pieces[rng.gen_range(columns)] = 'k';
   }
 
   let pieces = pieces.iter().collect::<String>();
@@ -36,7 +99,11 @@ pub fn generate(width: usize, height: usize, piece_options: &str, spawn_king: bo
   result += " w KQkq - 0 1 - ";
 
   // make piece options promotion options
-  result += piece_options;
+  result += promotions;
+
+  if chess960 {
+    result += " 960";
+  }
 
   result
 }
@@ -0,0 +1,245 @@
+use crate::clock::Clock;
+use std::thread::sleep;
+use std::time::Duration;
+
+#[test]
+fn add_time_mid_move() {
+  let mut clock = Clock::new_symmetric(Duration::from_secs(10), Duration::ZERO, true);
+  clock.toggle_pause();
+  sleep(Duration::from_millis(20));
+  clock.add_time(true, Duration::from_secs(5));
+  let (white, black) = clock.get_clocks();
+  assert!(white > Duration::from_secs(14) && white < Duration::from_secs(15));
+  assert_eq!(black, Duration::from_secs(10));
+  // the added time isn't counted as time spent on the move
+  assert!(clock.move_time() < Duration::from_secs(1));
+}
+
+#[test]
+fn add_time_after_flag() {
+  let mut clock = Clock::new_symmetric(Duration::ZERO, Duration::ZERO, false);
+  clock.toggle_pause();
+  sleep(Duration::from_millis(5));
+  clock.update();
+  assert!(clock.is_flagged());
+  clock.add_time(false, Duration::from_secs(1));
+  assert!(!clock.is_flagged());
+  let (_, black) = clock.get_clocks();
+  assert!(black > Duration::from_millis(900) && black <= Duration::from_secs(1));
+}
+
+#[test]
+fn mating_material_matches_draw_rule() {
+  use crate::{Board, Gamestate};
+  for (fen, mating) in [
+    ("4k3/8/8/8/8/8/8/4KN2 w - - 0 1", false),
+    ("4k3/8/8/8/8/8/8/3BKB2 w - - 0 1", false),
+    ("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1", true),
+    ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", true),
+    ("4k3/8/8/8/8/8/8/4KR2 w - - 0 1", true),
+  ] {
+    let board = Board::new(fen).unwrap();
+    assert_eq!(board.has_mating_material(true), mating, "{fen}");
+    assert_eq!(board.state() != Gamestate::Material, mating, "{fen}");
+    assert!(!board.has_mating_material(false), "{fen}");
+  }
+}
+
+#[test]
+fn pgn_round_trip() {
+  use crate::parsing::{Pgn, PgnDialect};
+  use crate::positions::{LIBERTY_CHESS, STARTPOS};
+  use crate::Board;
+  for fen in [STARTPOS, LIBERTY_CHESS] {
+    let mut game = Pgn::new(Board::new(fen).unwrap());
+    game
+      .tags
+      .push(("Event".to_owned(), "Test \"match\"".to_owned()));
+    // play enough moves to wrap the movetext
+    for _ in 0..40 {
+      let Some(mv) = game
+        .position()
+        .generate_legal()
+        .first()
+        .and_then(|b| b.last_move)
+      else {
+        break;
+      };
+      assert!(game.push(mv));
+    }
+    for dialect in [PgnDialect::Standard, PgnDialect::Extended] {
+      let pgn = game.write(dialect);
+      let mut movetext = pgn.lines().filter(|line| !line.starts_with('['));
+      assert!(movetext.all(|line| line.len() < 80), "{pgn}");
+      let loaded: Pgn = pgn.parse().unwrap();
+      assert_eq!(loaded.start().to_string(), game.start().to_string());
+      assert_eq!(loaded.moves(), game.moves());
+      assert_eq!(
+        loaded.tags,
+        [("Event".to_owned(), "Test 'match'".to_owned())]
+      );
+      assert_eq!(loaded.result, game.result);
+    }
+  }
+}
+
+#[test]
+fn pgn_annotations_round_trip() {
+  use crate::parsing::{pgn_tokens, Movetext, Pgn, PgnDialect, PgnToken};
+  use crate::positions::STARTPOS;
+  use crate::Board;
+  let pgn =
+    "[Event \"Test\"]\n\n{Start} 1. e4 $1 {Best by test} 1... e5 (1... c5 $2 2. Nf3 (2. c3) \
+             d6) 2. Nf3! *";
+  let tokens = pgn_tokens(pgn).unwrap();
+  assert_eq!(
+    tokens[0],
+    PgnToken::Tag("Event".to_owned(), "Test".to_owned())
+  );
+  assert_eq!(tokens[tokens.len() - 2], PgnToken::Nag(1));
+  let mut movetext = Movetext::default();
+  for token in &tokens[1..tokens.len() - 1] {
+    match token {
+      PgnToken::Comment(comment) => movetext.push_comment(comment),
+      PgnToken::Nag(nag) => movetext.push(&format!("${nag}")),
+      PgnToken::Move(mv) => movetext.push(mv),
+      PgnToken::VariationStart => movetext.push("("),
+      PgnToken::VariationEnd => movetext.push(")"),
+      PgnToken::Tag(..) | PgnToken::Result(_) => unreachable!(),
+    }
+  }
+  let mut game = Pgn::new(Board::new(STARTPOS).unwrap());
+  game.tags.push(("Event".to_owned(), "Test".to_owned()));
+  let written = game.write_with(PgnDialect::Standard, movetext);
+  assert_eq!(pgn_tokens(&written).unwrap(), tokens);
+  // only the main line is kept as moves
+  let loaded: Pgn = written.parse().unwrap();
+  assert_eq!(loaded.moves().len(), 3);
+}
+
+#[test]
+fn pgn_errors() {
+  use crate::parsing::{Pgn, PgnError};
+  for (pgn, error) in [
+    ("1. e4 (1. d4", PgnError::Unterminated),
+    ("1. e4 {comment", PgnError::Unterminated),
+    ("1. e4 )", PgnError::Unmatched),
+    ("1. e4 $x", PgnError::InvalidNag("$x".to_owned())),
+    ("1. e5", PgnError::InvalidMove("e5".to_owned())),
+  ] {
+    assert_eq!(pgn.parse::<Pgn>().err(), Some(error), "{pgn}");
+  }
+}
+
+#[test]
+fn static_exchange() {
+  use crate::moves::Move;
+  use crate::{Board, Piece, BISHOP, KNIGHT, PAWN, QUEEN, ROOK};
+  let value = |piece: Piece| match piece {
+    PAWN => 100,
+    KNIGHT | BISHOP => 300,
+    ROOK => 500,
+    QUEEN => 900,
+    _ => 10000,
+  };
+  for (fen, notation, expected) in [
+    // undefended and defended pawns
+    ("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5", 100),
+    ("4k3/8/2p5/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5", 0),
+    ("4k3/8/2p5/3p4/8/8/3R4/4K3 w - - 0 1", "d2d5", -400),
+    // the rook behind joins in once the first one has captured
+    ("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5", 100),
+    // quiet moves onto attacked squares
+    ("4k3/8/8/3p4/8/8/8/4KB2 w - - 0 1", "f1c4", -300),
+    ("4k3/8/8/3p4/8/8/8/4KB2 w - - 0 1", "f1e2", 0),
+    // kings don't recapture onto defended squares
+    ("4k3/8/8/8/8/3r4/3P4/4K3 b - - 0 1", "d3d2", -400),
+    ("3rk3/8/8/8/8/3r4/3P4/4K3 b - - 0 1", "d3d2", 100),
+  ] {
+    let board = Board::new(fen).unwrap();
+    let mv: Move = notation.parse().unwrap();
+    assert_eq!(board.see(mv, value), expected, "{fen} {notation}");
+  }
+}
+
+#[test]
+fn pawn_hash_incremental() {
+  use crate::positions::{HORDE, LIBERTY_CHESS, MINI, STARTPOS};
+  use crate::Board;
+  let promotion = "4k3/1P6/8/8/8/8/6p1/4K3 w - - 0 1";
+  for fen in [STARTPOS, LIBERTY_CHESS, MINI, HORDE, promotion] {
+    let mut board = Board::new(fen).unwrap();
+    for ply in 0..80 {
+      let moves = board.generate_legal();
+      if moves.is_empty() {
+        break;
+      }
+      // a fixed spread of moves keeps the test repeatable
+      board = moves[(ply * 7) % moves.len()].clone();
+      let fen = board.to_string();
+      let loaded = Board::new(&fen).unwrap();
+      assert_eq!(board.pawn_hash(), loaded.pawn_hash(), "{fen}");
+    }
+  }
+}
+
+#[test]
+fn sanity_pawns_on_promotion_row() {
+  use crate::moves::Move;
+  use crate::{Board, SanityIssue};
+  use std::str::FromStr;
+  // pawns that can still move are fine, even if they can only promote to pawns
+  let mut board = Board::new("k7/4P3/8/8/8/8/3p4/K7 w - - 0 1 - p").unwrap();
+  assert!(board.sanity_report().is_empty());
+  board.play_move(Move::from_str("e7e8p").unwrap());
+  assert_eq!(
+    board.sanity_report(),
+    vec![SanityIssue::PawnOnPromotionRow(true)]
+  );
+  board.play_move(Move::from_str("d2d1p").unwrap());
+  assert_eq!(
+    board.sanity_report(),
+    vec![
+      SanityIssue::PawnOnPromotionRow(true),
+      SanityIssue::PawnOnPromotionRow(false)
+    ]
+  );
+}
+
+#[test]
+fn clock_saturates() {
+  let mut clock = Clock::new_symmetric(Duration::MAX, Duration::MAX, true);
+  clock.add_time(true, Duration::MAX);
+  clock.switch_clocks();
+  clock.switch_clocks();
+  let (white, black) = clock.get_clocks();
+  assert!(white > Duration::from_secs(u64::MAX / 2));
+  assert!(black > Duration::from_secs(u64::MAX / 2));
+  assert!(!clock.is_flagged());
+}
+
+#[test]
+fn castling_columns_round_trip() {
+  use crate::Board;
+  // columns in the misc field are 1-based, like the other fields
+  for fen in [
+    "1r2k3r1/10/10/10/10/10/10/1R2K3R1 w KQkq - 0 1 2,2,1,2,9",
+    "r3k2r1/9/9/9/9/9/9/R3K2R1 w KQkq - 0 1 2,2,1,1,8",
+  ] {
+    let board = Board::new(fen).unwrap();
+    assert_eq!(board.to_string(), fen);
+    let loaded = Board::new(&board.to_string()).unwrap();
+    let castling = |board: &Board| {
+      let mut moves: Vec<String> = board
+        .generate_legal()
+        .iter()
+        .filter_map(|position| position.last_move)
+        .map(|mv| mv.to_string())
+        .filter(|mv| mv.starts_with('e'))
+        .collect();
+      moves.sort();
+      moves
+    };
+    assert_eq!(castling(&loaded), castling(&board), "{fen}");
+  }
+}
@@ -1,6 +1,6 @@
 use crate::keys::Hash;
 use crate::moves::Move;
-use crate::{Board, Gamestate, Piece, SharedData, PAWN};
+use crate::{Board, Gamestate, MaterialSignature, Piece, SharedData, PAWN};
 use array2d::Array2D;
 use std::rc::Rc;
 
@@ -18,6 +18,7 @@ pub struct CompressedBoard {
   castle_row: usize,
   queen_column: usize,
   king_column: usize,
+  chess960: bool,
   promotion_target: Option<(usize, usize)>,
   promotion_options: Vec<Piece>,
   white_kings: Vec<(usize, usize)>,
@@ -26,6 +27,7 @@ pub struct CompressedBoard {
   duplicates: Vec<Hash>,
   previous: Vec<Hash>,
   hash: Hash,
+  pawn_hash: Hash,
   /// Whether friendly fire mode is enabled.
   /// Changing this value is only supported before moves are made.
   pub friendly_fire: bool,
@@ -34,6 +36,7 @@ pub struct CompressedBoard {
   // Piece counts ignore kings
   white_pieces: u32,
   black_pieces: u32,
+  material: [MaterialSignature; 2],
 
   last_move: Option<Move>,
 }
@@ -70,6 +73,7 @@ impl CompressedBoard {
       self.castle_row,
       self.queen_column,
       self.king_column,
+      self.chess960,
       self.promotion_options,
       piece_types,
     );
@@ -88,10 +92,12 @@ impl CompressedBoard {
       duplicates: self.duplicates,
       previous: self.previous,
       hash: self.hash,
+      pawn_hash: self.pawn_hash,
       shared_data: Rc::new(shared_data),
       friendly_fire: self.friendly_fire,
       white_pieces: self.white_pieces,
       black_pieces: self.black_pieces,
+      material: self.material,
       skip_checkmate: false,
       last_move: self.last_move,
     }
@@ -115,6 +121,7 @@ impl Board {
       castle_row: self.shared_data.castle_row,
       queen_column: self.shared_data.queen_column,
       king_column: self.shared_data.king_column,
+      chess960: self.shared_data.chess960,
       promotion_target: self.promotion_target,
       promotion_options: self.shared_data.promotion_options.clone(),
       white_kings: self.white_kings.clone(),
@@ -123,9 +130,11 @@ impl Board {
       duplicates: self.duplicates.clone(),
       previous: self.previous.clone(),
       hash: self.hash,
+      pawn_hash: self.pawn_hash,
       friendly_fire: self.friendly_fire,
       white_pieces: self.white_pieces,
       black_pieces: self.black_pieces,
+      material: self.material,
       last_move: self.last_move,
     }
   }
@@ -0,0 +1,382 @@
+use crate::helpers::{checkbox, menu_button, raw_text_edit};
+use crate::players::{EngineInterface, Limits as GuiLimits, SearchType};
+use crate::LibertyChessGUI;
+use core::cmp::Ordering;
+use eframe::egui;
+use egui::{Context, Grid, ScrollArea, TextEdit, Ui};
+use enum_iterator::{all, Sequence};
+use liberty_chess::moves::Move;
+use liberty_chess::{Board, Gamestate};
+use oxidation::HASH_SIZE;
+use std::time::Duration;
+use ulci::{Limits, Score, SearchTime};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::helpers::label_text_edit;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fmt::Write as _;
+
+// Columns of the results table
+#[derive(Clone, Copy, Eq, PartialEq, Sequence)]
+enum Column {
+  Position,
+  Score,
+  BestMove,
+  Depth,
+}
+
+impl Column {
+  const fn title(self) -> &'static str {
+    match self {
+      Self::Position => "Position",
+      Self::Score => "Score",
+      Self::BestMove => "Best move",
+      Self::Depth => "Depth",
+    }
+  }
+}
+
+// The engine's verdict on one position
+struct AnalysisRow {
+  // position in the input, starting from 1
+  index: usize,
+  fen: String,
+  // relative to white, None if the position is already over
+  score: Option<Score>,
+  bestmove: Option<Move>,
+  depth: u16,
+}
+
+impl AnalysisRow {
+  fn new(index: usize, board: &Board) -> Self {
+    Self {
+      index,
+      fen: board.to_string(),
+      score: None,
+      bestmove: None,
+      depth: 0,
+    }
+  }
+
+  fn compare(&self, other: &Self, column: Column) -> Ordering {
+    match column {
+      Column::Position => self.index.cmp(&other.index),
+      // scores from the engine are better for white when they are lower
+      Column::Score => self.score.map(|s| -s).cmp(&other.score.map(|s| -s)),
+      Column::BestMove => self
+        .bestmove
+        .map(|mv| mv.to_string())
+        .cmp(&other.bestmove.map(|mv| mv.to_string())),
+      Column::Depth => self.depth.cmp(&other.depth),
+    }
+  }
+}
+
+// show a score from white's perspective in pawns
+fn show_score(score: Option<Score>) -> String {
+  match score {
+    Some(Score::Win(moves)) => format!("#-{moves}"),
+    Some(Score::Loss(moves)) => format!("#{moves}"),
+    Some(Score::Centipawn(score)) => format!("{:+.2}", -score as f32 / 100.0),
+    None => "-".to_owned(),
+  }
+}
+
+// Runs the built-in engine over a list of positions
+pub struct BatchAnalysis {
+  // file to load positions from and export results to
+  #[cfg(not(target_arch = "wasm32"))]
+  path: String,
+  // one L-FEN per line, anything after a semicolon is ignored so EPD operations can be kept
+  positions: String,
+  limits: SearchType,
+  engine: Option<EngineInterface>,
+  // positions waiting to be analysed, in reverse order
+  queue: Vec<(usize, Board)>,
+  current: Option<(AnalysisRow, Board)>,
+  results: Vec<AnalysisRow>,
+  sort: Column,
+  descending: bool,
+  message: Option<String>,
+}
+
+impl Default for BatchAnalysis {
+  fn default() -> Self {
+    Self {
+      #[cfg(not(target_arch = "wasm32"))]
+      path: String::new(),
+      positions: String::new(),
+      limits: SearchType::Other(GuiLimits {
+        depth: Some(SearchType::depth()),
+        nodes: Some(SearchType::nodes()),
+        time: None,
+      }),
+      engine: None,
+      queue: Vec::new(),
+      current: None,
+      results: Vec::new(),
+      sort: Column::Position,
+      descending: false,
+      message: None,
+    }
+  }
+}
+
+impl BatchAnalysis {
+  // stop any analysis in progress
+  pub fn stop(&mut self) {
+    if let Some(engine) = &mut self.engine {
+      engine.cancel_move();
+    }
+    self.engine = None;
+    self.queue.clear();
+    self.current = None;
+  }
+
+  fn start(&mut self, ctx: &Context) {
+    self.stop();
+    self.results.clear();
+    self.message = None;
+    let mut invalid = 0;
+    for (index, line) in self.positions.lines().enumerate() {
+      let fen = line.split(';').next().unwrap_or_default().trim();
+      if fen.is_empty() {
+        continue;
+      }
+      match Board::new(fen) {
+        Ok(board) => self.queue.push((index + 1, board)),
+        Err(_) => invalid += 1,
+      }
+    }
+    if invalid > 0 {
+      self.message = Some(format!("Skipped {invalid} invalid positions"));
+    }
+    self.queue.reverse();
+    if !self.queue.is_empty() {
+      self.engine = Some(EngineInterface::new(HASH_SIZE, None, ctx));
+    }
+  }
+
+  fn searchtime(&self) -> SearchTime {
+    #[cfg(feature = "clock")]
+    let searchtime = self.limits.get_value(false).0;
+    #[cfg(not(feature = "clock"))]
+    let searchtime = self.limits.get_value();
+    // analysis needs to finish on its own
+    if searchtime == SearchTime::Other(Limits::default()) {
+      SearchTime::Other(Limits {
+        depth: SearchType::depth().get_value() as u8,
+        ..Limits::default()
+      })
+    } else {
+      searchtime
+    }
+  }
+
+  // poll the engine, moving on to the next position when it finishes
+  fn poll(&mut self, ctx: &Context) {
+    let searchtime = self.searchtime();
+    let Some(engine) = &mut self.engine else {
+      return;
+    };
+    let finished = match &mut self.current {
+      Some((row, board)) => {
+        let (bestmove, analysis, _) = engine.get_move(board, searchtime);
+        if let Some((score, depth)) = analysis {
+          row.score = Some(score);
+          row.depth = depth;
+        }
+        row.bestmove = bestmove;
+        bestmove.is_some()
+      }
+      None => match self.queue.pop() {
+        Some((index, board)) => {
+          // positions that are over have nothing to analyse
+          if board.state() == Gamestate::InProgress && !board.promotion_available() {
+            self.current = Some((AnalysisRow::new(index, &board), board));
+          } else {
+            self.results.push(AnalysisRow::new(index, &board));
+            self.sort_results();
+          }
+          false
+        }
+        None => {
+          self.engine = None;
+          return;
+        }
+      },
+    };
+    if finished {
+      if let Some((row, _)) = self.current.take() {
+        self.results.push(row);
+        self.sort_results();
+      }
+    }
+    ctx.request_repaint_after(Duration::from_millis(100));
+  }
+
+  fn sort_results(&mut self) {
+    let (column, descending) = (self.sort, self.descending);
+    self.results.sort_by(|a, b| {
+      let ordering = a.compare(b, column);
+      if descending {
+        ordering.reverse()
+      } else {
+        ordering
+      }
+    });
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  fn load(&mut self) {
+    match std::fs::read_to_string(&self.path) {
+      Ok(positions) => {
+        self.positions = positions;
+        self.message = None;
+      }
+      Err(error) => self.message = Some(format!("Could not load {}: {error}", self.path)),
+    }
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  fn export(&mut self) {
+    let mut csv = "position,fen,score,bestmove,depth\n".to_owned();
+    for row in &self.results {
+      writeln!(
+        csv,
+        "{},\"{}\",{},{},{}",
+        row.index,
+        row.fen,
+        show_score(row.score),
+        row.bestmove.map_or(String::new(), |mv| mv.to_string()),
+        row.depth
+      )
+      .ok();
+    }
+    let path = format!("{}.csv", self.path);
+    self.message = Some(match std::fs::write(&path, csv) {
+      Ok(()) => format!("Exported results to {path}"),
+      Err(error) => format!("Could not write {path}: {error}"),
+    });
+  }
+}
+
+pub(crate) fn draw(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
+  let size = f32::from(gui.config.get_text_size());
+  menu_button(gui, ui);
+  let analysis = &mut gui.analysis;
+  analysis.poll(ctx);
+  #[cfg(not(target_arch = "wasm32"))]
+  ui.horizontal_top(|ui| {
+    label_text_edit(ui, size * 12.0, &mut analysis.path, "File");
+    if ui.button("Load").clicked() {
+      analysis.load();
+    }
+    if !analysis.results.is_empty() && ui.button("Export CSV").clicked() {
+      analysis.export();
+    }
+  });
+  ui.label("Positions (one L-FEN per line)");
+  ScrollArea::vertical()
+    .id_source("Positions")
+    .max_height(size * 8.0)
+    .show(ui, |ui| {
+      ui.add(TextEdit::multiline(&mut analysis.positions).desired_width(f32::INFINITY));
+    });
+  if let SearchType::Other(ref mut limits) = analysis.limits {
+    ui.horizontal_top(|ui| {
+      if checkbox(
+        ui,
+        &mut limits.depth.is_some(),
+        "Depth",
+        #[cfg(feature = "sound")]
+        gui.audio_engine.as_mut(),
+      ) {
+        limits.depth = match limits.depth {
+          Some(_) => None,
+          None => Some(SearchType::depth()),
+        };
+      }
+      if let Some(ref mut depth) = limits.depth {
+        raw_text_edit(ui, size * 2.0, depth);
+      }
+      if checkbox(
+        ui,
+        &mut limits.nodes.is_some(),
+        "Nodes",
+        #[cfg(feature = "sound")]
+        gui.audio_engine.as_mut(),
+      ) {
+        limits.nodes = match limits.nodes {
+          Some(_) => None,
+          None => Some(SearchType::nodes()),
+        };
+      }
+      if let Some(ref mut nodes) = limits.nodes {
+        raw_text_edit(ui, size * 5.0, nodes);
+      }
+      if checkbox(
+        ui,
+        &mut limits.time.is_some(),
+        "Time (ms)",
+        #[cfg(feature = "sound")]
+        gui.audio_engine.as_mut(),
+      ) {
+        limits.time = match limits.time {
+          Some(_) => None,
+          None => Some(SearchType::time()),
+        };
+      }
+      if let Some(ref mut time) = limits.time {
+        raw_text_edit(ui, size * 4.0, time);
+      }
+    });
+  }
+  ui.horizontal_top(|ui| {
+    if analysis.engine.is_some() {
+      if ui.button("Stop").clicked() {
+        analysis.stop();
+      }
+      let done = analysis.results.len();
+      let total = done + analysis.queue.len() + usize::from(analysis.current.is_some());
+      ui.label(format!("Analysing {done}/{total}"));
+    } else if ui.button("Analyse").clicked() {
+      analysis.start(ctx);
+    }
+  });
+  if let Some(message) = &analysis.message {
+    ui.label(message);
+  }
+  ScrollArea::vertical().id_source("Results").show(ui, |ui| {
+    Grid::new("Results").striped(true).show(ui, |ui| {
+      let mut resort = false;
+      for column in all::<Column>() {
+        let mut title = column.title().to_owned();
+        if column == analysis.sort {
+          title += if analysis.descending { " v" } else { " ^" };
+        }
+        if ui.button(title).clicked() {
+          if column == analysis.sort {
+            analysis.descending = !analysis.descending;
+          } else {
+            analysis.sort = column;
+            analysis.descending = false;
+          }
+          resort = true;
+        }
+      }
+      ui.end_row();
+      if resort {
+        analysis.sort_results();
+      }
+      for row in &analysis.results {
+        ui.label(row.index.to_string())
+          .on_hover_text(row.fen.as_str());
+        ui.label(show_score(row.score));
+        ui.label(row.bestmove.map_or("-".to_owned(), |mv| mv.to_string()));
+        ui.label(row.depth.to_string());
+        ui.end_row();
+      }
+    });
+  });
+}
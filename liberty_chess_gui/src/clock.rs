@@ -2,8 +2,12 @@ use crate::helpers::{populate_dropdown, raw_text_edit, NumericalInput};
 use crate::themes::Colours;
 use crate::{LibertyChessGUI, MAX_TIME};
 use core::time::Duration;
-use eframe::egui::{ComboBox, Context, RichText, TopBottomPanel, Ui};
-use liberty_chess::clock::{Clock, Type};
+use eframe::egui::{
+  pos2, ComboBox, Context, DragValue, Label, Rect, RichText, Rounding, Sense, Stroke,
+  TopBottomPanel, Ui, Vec2,
+};
+use liberty_chess::clock::{format_time, Clock, Millis, Type};
+use liberty_chess::Board;
 
 const DEFAULT_TIME: u64 = 10;
 
@@ -11,30 +15,157 @@ pub fn init_input() -> NumericalInput<u64> {
   NumericalInput::new(DEFAULT_TIME, 0, MAX_TIME)
 }
 
-pub fn draw(ctx: &Context, clock: &mut Clock, flipped: bool) {
-  clock.update();
-  let (mut white, mut black) = clock.get_clocks();
+// Time that can be added to a clock from the clock menu, in seconds
+const EXTRA_TIME: [u64; 3] = [15, 60, 300];
+
+// Moves assumed when comparing time budgets with different increments
+const BUDGET_MOVES: u32 = 40;
+
+// The time each side started with in a game with time odds
+#[derive(Clone, Copy)]
+pub struct TimeOdds([Duration; 4]);
+
+impl TimeOdds {
+  // Only games where the sides have different time budgets have time odds
+  pub fn new(times: [Duration; 4]) -> Option<Self> {
+    let [white_clock, black_clock, white_inc, black_inc] = times;
+    if white_clock == black_clock && white_inc == black_inc {
+      None
+    } else {
+      Some(Self(times))
+    }
+  }
+
+  fn budget(&self, side: bool) -> Duration {
+    let [white_clock, black_clock, white_inc, black_inc] = self.0;
+    if side {
+      white_clock + white_inc * BUDGET_MOVES
+    } else {
+      black_clock + black_inc * BUDGET_MOVES
+    }
+  }
+
+  // The starting time and increment for one side
+  pub fn budget_text(&self, side: bool) -> String {
+    let [white_clock, black_clock, white_inc, black_inc] = self.0;
+    let (clock, inc) = if side {
+      (white_clock, white_inc)
+    } else {
+      (black_clock, black_inc)
+    };
+    format!("{}+{}", print_clock(clock), inc.as_secs())
+  }
+
+  // How much more time white has than black, such as 3:1 or 1:2
+  pub fn ratio_text(&self) -> String {
+    let (white, black) = (self.budget(true), self.budget(false));
+    let format_ratio = |ratio: f32| {
+      if ratio >= 10.0 {
+        format!("{ratio:.0}")
+      } else {
+        format!("{ratio:.1}")
+      }
+    };
+    if white >= black {
+      let ratio = white.as_secs_f32() / black.as_secs_f32().max(1.0);
+      format!("{}:1", format_ratio(ratio))
+    } else {
+      let ratio = black.as_secs_f32() / white.as_secs_f32().max(1.0);
+      format!("1:{}", format_ratio(ratio))
+    }
+  }
+
+  pub fn description(&self) -> String {
+    format!(
+      "Time odds: white has {}, black has {}",
+      self.budget_text(true),
+      self.budget_text(false)
+    )
+  }
+}
+
+pub fn draw(
+  ctx: &Context,
+  clock: &mut Clock,
+  flipped: bool,
+  editable: bool,
+  time_odds: Option<TimeOdds>,
+) {
+  let (white, black) = clock.get_clocks();
+  let mut white_text = clock_text(clock, true, white, time_odds);
+  let mut black_text = clock_text(clock, false, black, time_odds);
   if flipped {
-    (black, white) = (white, black);
+    (black_text, white_text) = (white_text, black_text);
   }
-  let mut white_text = RichText::new(print_clock(white));
-  let mut black_text = RichText::new(print_clock(black));
-  let color = if clock.is_flagged() {
-    Colours::Check
-  } else {
-    Colours::Selected
+  let bottom = TopBottomPanel::bottom("White Clock")
+    .resizable(false)
+    .show(ctx, |ui| {
+      ui.add(Label::new(white_text).sense(Sense::click()))
+    })
+    .inner;
+  let top = TopBottomPanel::top("Black Clock")
+    .resizable(false)
+    .show(ctx, |ui| {
+      ui.add(Label::new(black_text).sense(Sense::click()))
+    })
+    .inner;
+  // right click or long press on a clock to adjust it
+  if editable {
+    bottom.context_menu(|ui| adjust_menu(ui, clock, !flipped));
+    top.context_menu(|ui| adjust_menu(ui, clock, flipped));
+  }
+}
+
+fn clock_text(clock: &Clock, side: bool, time: Duration, time_odds: Option<TimeOdds>) -> RichText {
+  // show each side's budget so the difference is clear
+  let text = match time_odds {
+    Some(time_odds) => RichText::new(format!(
+      "{} (started with {})",
+      print_clock(time),
+      time_odds.budget_text(side)
+    )),
+    None => RichText::new(print_clock(time)),
   };
-  if clock.to_move() ^ flipped {
-    white_text = white_text.color(color.value());
+  let colour = if side == clock.to_move() {
+    if clock.is_flagged() {
+      Colours::Check
+    } else if clock.is_low_time(side) {
+      Colours::LowTime
+    } else {
+      Colours::Selected
+    }
+  } else if clock.is_low_time(side) {
+    Colours::LowTime
   } else {
-    black_text = black_text.color(color.value());
+    return text;
+  };
+  text.color(colour.value())
+}
+
+fn adjust_menu(ui: &mut Ui, clock: &mut Clock, side: bool) {
+  ui.label(if side { "White clock" } else { "Black clock" });
+  for seconds in EXTRA_TIME {
+    let text = if seconds >= 60 {
+      format!("Add {} min", seconds / 60)
+    } else {
+      format!("Add {seconds} s")
+    };
+    if ui.button(text).clicked() {
+      clock.add_time(side, Duration::from_secs(seconds));
+      ui.close_menu();
+    }
   }
-  TopBottomPanel::bottom("White Clock")
-    .resizable(false)
-    .show(ctx, |ui| ui.label(white_text));
-  TopBottomPanel::top("Black Clock")
-    .resizable(false)
-    .show(ctx, |ui| ui.label(black_text));
+  let (white_inc, black_inc) = clock.get_increment();
+  let mut increment = if side { white_inc } else { black_inc }.as_secs();
+  ui.horizontal(|ui| {
+    ui.label("Increment (s):");
+    if ui
+      .add(DragValue::new(&mut increment).clamp_range(0..=MAX_TIME))
+      .changed()
+    {
+      clock.set_increment(side, Duration::from_secs(increment));
+    }
+  });
 }
 
 pub(crate) fn draw_edit(gui: &mut LibertyChessGUI, ui: &mut Ui, size: f32) {
@@ -69,6 +200,20 @@ pub(crate) fn draw_edit(gui: &mut LibertyChessGUI, ui: &mut Ui, size: f32) {
         raw_text_edit(ui, size, &mut gui.clock_data[3]);
       });
     }
+    Type::Delay => {
+      ui.horizontal_top(|ui| {
+        ui.label("Time (min):");
+        raw_text_edit(ui, size, &mut gui.clock_data[0]);
+        ui.label("Delay (s):");
+        raw_text_edit(ui, size, &mut gui.clock_data[2]);
+      });
+    }
+    Type::Hourglass => {
+      ui.horizontal_top(|ui| {
+        ui.label("Time (min):");
+        raw_text_edit(ui, size, &mut gui.clock_data[0]);
+      });
+    }
   }
 }
 
@@ -83,20 +228,94 @@ fn print_clock(time: Duration) -> String {
   }
 }
 
+// Time spent on each move, indexed by the ply the move was played on
+pub type MoveTimes = Vec<(u32, Duration)>;
+
+// The ply of the move that will be played from this position, even for white and odd for black
+fn ply(board: &Board) -> u32 {
+  board.moves() * 2 + u32::from(!board.to_move())
+}
+
+// Record the time spent on the move that led to the position
+pub fn record_move(move_times: &mut MoveTimes, board: &Board, time: Duration) {
+  move_times.push((ply(board) - 1, time));
+}
+
+// Add the time spent so far on the move being played from the position
+pub fn record_current_move(move_times: &mut MoveTimes, board: &Board, time: Duration) {
+  move_times.push((ply(board), time));
+}
+
+// Discard the times for moves that were undone
+pub fn undo_moves(move_times: &mut MoveTimes, board: &Board) {
+  let ply = ply(board);
+  move_times.retain(|(move_ply, _)| *move_ply < ply);
+}
+
+// Bar chart of the time used per move, white above the line and black below
+pub fn draw_move_times(ui: &mut Ui, move_times: &MoveTimes, size: f32) {
+  let longest = move_times
+    .iter()
+    .map(|(_, time)| time.as_secs_f32())
+    .fold(0.0, f32::max);
+  if longest <= 0.0 {
+    return;
+  }
+  ui.label("Time per move:");
+  let width = ui.available_width();
+  let height = size * 4.0;
+  let (response, painter) = ui.allocate_painter(Vec2::new(width, height), Sense::hover());
+  let rect = response.rect;
+  let middle = rect.center().y;
+  let bar_width = width / move_times.len() as f32;
+  for (i, (ply, time)) in move_times.iter().enumerate() {
+    let bar_height = time.as_secs_f32() / longest * height / 2.0;
+    let left = (i as f32).mul_add(bar_width, rect.min.x);
+    let (top, bottom, colour) = if ply % 2 == 0 {
+      (middle - bar_height, middle, Colours::WhiteSquare)
+    } else {
+      (middle, middle + bar_height, Colours::BlackSquare)
+    };
+    painter.rect_filled(
+      Rect {
+        min: pos2(left, top),
+        max: pos2(left + bar_width, bottom),
+      },
+      Rounding::ZERO,
+      colour.value(),
+    );
+  }
+  painter.hline(
+    rect.x_range(),
+    middle,
+    Stroke::new(1.0, Colours::Moved.value()),
+  );
+  if let Some(position) = response.hover_pos() {
+    let index = ((position.x - rect.min.x) / bar_width) as usize;
+    if let Some((ply, time)) = move_times.get(index) {
+      let side = if ply % 2 == 0 { "White" } else { "Black" };
+      let text = format!("Move {}, {side}: {}", ply / 2, format_time((*time).into()));
+      response.on_hover_text_at_pointer(text);
+    }
+  }
+}
+
 pub fn convert(clock_data: &[NumericalInput<u64>; 4]) -> [Duration; 4] {
   let [white_clock, black_clock, white_increment, black_increment] =
     clock_data.clone().map(|data| data.get_value());
-  let white_clock = Duration::from_secs(if white_clock == 0 {
-    10
-  } else {
-    white_clock * 60
-  });
-  let black_clock = Duration::from_secs(if black_clock == 0 {
-    10
-  } else {
-    black_clock * 60
-  });
-  let white_increment = Duration::from_secs(white_increment);
-  let black_increment = Duration::from_secs(black_increment);
-  [white_clock, black_clock, white_increment, black_increment]
+  // no time is treated as 10 seconds
+  let minutes = |time: u64| {
+    if time == 0 {
+      Millis::from_secs(10)
+    } else {
+      Millis::from_mins(time)
+    }
+  };
+  [
+    minutes(white_clock),
+    minutes(black_clock),
+    Millis::from_secs(white_increment),
+    Millis::from_secs(black_increment),
+  ]
+  .map(Millis::to_duration)
 }
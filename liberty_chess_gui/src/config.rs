@@ -1,7 +1,11 @@
-use crate::themes::{GetVisuals, PresetTheme, Theme};
+use crate::engine_options::EngineOptions;
+use crate::keybindings::{Action, Keybindings};
+use crate::themes::{Coordinates, GetVisuals, PresetTheme, Theme};
 use core::str::FromStr;
 use eframe::{egui, Storage};
-use egui::{Context, FontId, TextStyle};
+use egui::style::Spacing;
+use egui::{Context, FontId, Key, TextStyle, Vec2};
+use enum_iterator::{all, first, Sequence};
 
 pub const BOARD_KEY: &str = "Board";
 #[cfg(feature = "sound")]
@@ -45,6 +49,7 @@ fn get_value<T: Parameter<T> + Clone>(raw: &Value<T>) -> T {
   }
 }
 
+// kept from when coordinates could only be turned on or off
 const NUMBER_KEY: &str = "Numbers";
 const TEXT_SIZE_KEY: &str = "Text_Size";
 const THEME_KEY: &str = "Theme";
@@ -52,41 +57,123 @@ const AUTOFLIP_KEY: &str = "Autoflip";
 const OPPONENTFLIP_KEY: &str = "Opponentflip";
 const ADVANCED_KEY: &str = "Advanced_Settings";
 const EVAL_BAR_KEY: &str = "Eval_Bar";
+const NORMALIZE_EVAL_KEY: &str = "Normalize_Eval";
+const PV_PREVIEW_KEY: &str = "PV_Preview";
+const INSTANT_MOVE_KEY: &str = "Instant_Move";
+const PONDER_KEY: &str = "Ponder";
+const PREMOVE_KEY: &str = "Premove";
+const LAYOUT_KEY: &str = "Layout";
+const KEYBINDINGS_KEY: &str = "Keybindings";
+const ENGINE_OPTIONS_KEY: &str = "Engine_Options";
+#[cfg(feature = "clock")]
+const LOW_TIME_KEY: &str = "Low_Time";
+
+// How the interface is laid out
+#[derive(Clone, Copy, Eq, PartialEq, Sequence)]
+pub enum Layout {
+  // Touch layout in portrait orientation, desktop layout otherwise
+  Automatic,
+  Desktop,
+  // Larger controls, tap-tap move entry and a collapsible sidebar
+  Touch,
+}
+
+impl ToString for Layout {
+  fn to_string(&self) -> String {
+    match self {
+      Self::Automatic => "Automatic",
+      Self::Desktop => "Desktop",
+      Self::Touch => "Touch",
+    }
+    .to_owned()
+  }
+}
+
+impl FromStr for Layout {
+  type Err = ();
+
+  fn from_str(layout: &str) -> Result<Self, Self::Err> {
+    all::<Self>()
+      .find(|&possible_layout| possible_layout.to_string() == layout)
+      .ok_or(())
+  }
+}
+
+// Whether the window is taller than it is wide
+pub fn is_portrait(ctx: &Context) -> bool {
+  let size = ctx.screen_rect().size();
+  size.y > size.x
+}
 
 pub struct Configuration {
   theme: Value<Theme>,
   text_size: Value<TextSize>,
-  numbers: Value<bool>,
+  coordinates: Value<Coordinates>,
   auto_flip: Value<bool>,
   opponent_flip: Value<bool>,
   advanced_settings: Value<bool>,
   eval_bar: Value<bool>,
+  normalize_eval: Value<bool>,
+  pv_preview: Value<bool>,
+  instant_move: Value<bool>,
+  ponder: Value<bool>,
+  premove: Value<bool>,
+  layout: Value<Layout>,
+  keybindings: Value<Keybindings>,
+  engine_options: Value<EngineOptions>,
+  #[cfg(feature = "clock")]
+  low_time: Value<LowTime>,
+  // whether the touch layout is currently in use
+  touch: bool,
 }
 
 impl Configuration {
-  pub fn new(ctx: &eframe::CreationContext) -> Self {
-    let config = ctx.storage.as_ref().map_or(
+  pub fn new(ctx: &Context, storage: Option<&dyn Storage>) -> Self {
+    let mut config = storage.map_or(
       Self {
         theme: Value::Default,
         text_size: Value::Default,
-        numbers: Value::Default,
+        coordinates: Value::Default,
         auto_flip: Value::Default,
         opponent_flip: Value::Default,
         advanced_settings: Value::Default,
         eval_bar: Value::Default,
+        normalize_eval: Value::Default,
+        pv_preview: Value::Default,
+        instant_move: Value::Default,
+        ponder: Value::Default,
+        premove: Value::Default,
+        layout: Value::Default,
+        keybindings: Value::Default,
+        engine_options: Value::Default,
+        #[cfg(feature = "clock")]
+        low_time: Value::Default,
+        touch: false,
       },
       |storage| Self {
         theme: load(storage.get_string(THEME_KEY)),
         text_size: load(storage.get_string(TEXT_SIZE_KEY)),
-        numbers: load(storage.get_string(NUMBER_KEY)),
+        coordinates: load(storage.get_string(NUMBER_KEY)),
         auto_flip: load(storage.get_string(AUTOFLIP_KEY)),
         opponent_flip: load(storage.get_string(OPPONENTFLIP_KEY)),
         advanced_settings: load(storage.get_string(ADVANCED_KEY)),
         eval_bar: load(storage.get_string(EVAL_BAR_KEY)),
+        normalize_eval: load(storage.get_string(NORMALIZE_EVAL_KEY)),
+        pv_preview: load(storage.get_string(PV_PREVIEW_KEY)),
+        instant_move: load(storage.get_string(INSTANT_MOVE_KEY)),
+        ponder: load(storage.get_string(PONDER_KEY)),
+        premove: load(storage.get_string(PREMOVE_KEY)),
+        layout: load(storage.get_string(LAYOUT_KEY)),
+        keybindings: load(storage.get_string(KEYBINDINGS_KEY)),
+        engine_options: load(storage.get_string(ENGINE_OPTIONS_KEY)),
+        #[cfg(feature = "clock")]
+        low_time: load(storage.get_string(LOW_TIME_KEY)),
+        touch: false,
       },
     );
-    config.set_style(&ctx.egui_ctx);
-    config.apply_theme(&ctx.egui_ctx);
+    config.touch = config.resolve_touch(ctx);
+    config.set_style(ctx);
+    config.apply_theme(ctx);
 
     config
   }
@@ -94,11 +181,21 @@ impl Configuration {
   pub fn save(&self, storage: &mut dyn Storage) {
     save(storage, THEME_KEY, &self.theme);
     save(storage, TEXT_SIZE_KEY, &self.text_size);
-    save(storage, NUMBER_KEY, &self.numbers);
+    save(storage, NUMBER_KEY, &self.coordinates);
     save(storage, AUTOFLIP_KEY, &self.auto_flip);
     save(storage, OPPONENTFLIP_KEY, &self.opponent_flip);
     save(storage, ADVANCED_KEY, &self.advanced_settings);
     save(storage, EVAL_BAR_KEY, &self.eval_bar);
+    save(storage, NORMALIZE_EVAL_KEY, &self.normalize_eval);
+    save(storage, PV_PREVIEW_KEY, &self.pv_preview);
+    save(storage, INSTANT_MOVE_KEY, &self.instant_move);
+    save(storage, PONDER_KEY, &self.ponder);
+    save(storage, PREMOVE_KEY, &self.premove);
+    save(storage, LAYOUT_KEY, &self.layout);
+    save(storage, KEYBINDINGS_KEY, &self.keybindings);
+    save(storage, ENGINE_OPTIONS_KEY, &self.engine_options);
+    #[cfg(feature = "clock")]
+    save(storage, LOW_TIME_KEY, &self.low_time);
   }
 
   // Reset every parameter to their default value
@@ -133,12 +230,20 @@ impl Configuration {
     self.set_style(ctx);
   }
 
-  pub fn get_numbers(&self) -> bool {
-    get_value(&self.numbers)
+  pub fn get_coordinates(&self) -> Coordinates {
+    get_value(&self.coordinates)
   }
 
-  pub fn toggle_numbers(&mut self) {
-    self.numbers = Value::Modified(!self.get_numbers());
+  pub fn set_coordinates(&mut self, coordinates: Coordinates) {
+    self.coordinates = Value::Modified(coordinates);
+  }
+
+  // Switch to the next style of coordinates, going back to the first after the last
+  pub fn cycle_coordinates(&mut self) {
+    let next = self.get_coordinates().next().or_else(first::<Coordinates>);
+    if let Some(coordinates) = next {
+      self.set_coordinates(coordinates);
+    }
   }
 
   pub fn get_autoflip(&self) -> bool {
@@ -173,12 +278,130 @@ impl Configuration {
     self.eval_bar = Value::Modified(self.get_evalbar());
   }
 
+  // Show the evaluation in pawns of the variant rather than centipawns
+  pub fn get_normalize_eval(&self) -> bool {
+    get_value(&self.normalize_eval)
+  }
+
+  pub fn toggle_normalize_eval(&mut self) {
+    self.normalize_eval = Value::Modified(!self.get_normalize_eval());
+  }
+
+  // Off by default since seeing the engine's plans can be considered cheating
+  pub fn get_pv_preview(&self) -> bool {
+    !get_value(&self.pv_preview)
+  }
+
+  pub fn toggle_pv_preview(&mut self) {
+    self.pv_preview = Value::Modified(self.get_pv_preview());
+  }
+
+  // Whether the built-in engine skips searching forced moves and known mates
+  pub fn get_instant_move(&self) -> bool {
+    !get_value(&self.instant_move)
+  }
+
+  pub fn toggle_instant_move(&mut self) {
+    self.instant_move = Value::Modified(self.get_instant_move());
+  }
+
+  // Whether the built-in engine keeps thinking on the opponent's time
+  pub fn get_ponder(&self) -> bool {
+    !get_value(&self.ponder)
+  }
+
+  pub fn toggle_ponder(&mut self) {
+    self.ponder = Value::Modified(self.get_ponder());
+  }
+
+  // Whether a move can be entered on the opponent's turn, to be played as soon as possible
+  pub fn get_premove(&self) -> bool {
+    !get_value(&self.premove)
+  }
+
+  pub fn toggle_premove(&mut self) {
+    self.premove = Value::Modified(self.get_premove());
+  }
+
+  // Low time warning threshold in seconds, 0 is disabled
+  #[cfg(feature = "clock")]
+  pub fn get_low_time(&self) -> LowTime {
+    get_value(&self.low_time)
+  }
+
+  #[cfg(feature = "clock")]
+  pub fn set_low_time(&mut self, low_time: LowTime) {
+    self.low_time = Value::Modified(low_time);
+  }
+
+  pub fn get_layout(&self) -> Layout {
+    get_value(&self.layout)
+  }
+
+  pub fn set_layout(&mut self, ctx: &Context, layout: Layout) {
+    self.layout = Value::Modified(layout);
+    self.update_layout(ctx);
+  }
+
+  pub fn get_keybindings(&self) -> Keybindings {
+    get_value(&self.keybindings)
+  }
+
+  pub fn set_keybinding(&mut self, action: Action, key: Option<Key>) {
+    let mut keybindings = self.get_keybindings();
+    keybindings.set(action, key);
+    self.keybindings = Value::Modified(keybindings);
+  }
+
+  pub fn get_engine_options(&self) -> EngineOptions {
+    get_value(&self.engine_options)
+  }
+
+  pub fn set_engine_options(&mut self, engine_options: EngineOptions) {
+    self.engine_options = Value::Modified(engine_options);
+  }
+
+  // Whether the touch layout is in use
+  pub const fn touch(&self) -> bool {
+    self.touch
+  }
+
+  // Switch between the touch and desktop layouts if needed, call every frame for automatic layout
+  pub fn update_layout(&mut self, ctx: &Context) {
+    let touch = self.resolve_touch(ctx);
+    if touch != self.touch {
+      self.touch = touch;
+      self.set_style(ctx);
+    }
+  }
+
+  fn resolve_touch(&self, ctx: &Context) -> bool {
+    match self.get_layout() {
+      Layout::Automatic => is_portrait(ctx),
+      Layout::Desktop => false,
+      Layout::Touch => true,
+    }
+  }
+
   fn set_style(&self, ctx: &Context) {
     let mut style = (*ctx.style()).clone();
     let text_size = f32::from(get_value(&self.text_size));
     let font = FontId::proportional(text_size);
-    style.spacing.icon_width = text_size * 0.7;
-    style.spacing.icon_width_inner = text_size * 0.5;
+    let default_spacing = Spacing::default();
+    if self.touch {
+      // larger hit targets for fingers
+      style.spacing.icon_width = text_size;
+      style.spacing.icon_width_inner = text_size * 0.7;
+      style.spacing.interact_size = Vec2::new(text_size * 2.0, text_size * 1.8);
+      style.spacing.button_padding = Vec2::new(text_size * 0.5, text_size * 0.3);
+      style.spacing.item_spacing = Vec2::splat(text_size * 0.4);
+    } else {
+      style.spacing.icon_width = text_size * 0.7;
+      style.spacing.icon_width_inner = text_size * 0.5;
+      style.spacing.interact_size = default_spacing.interact_size;
+      style.spacing.button_padding = default_spacing.button_padding;
+      style.spacing.item_spacing = default_spacing.item_spacing;
+    }
     style.spacing.combo_height = 460.0;
     style.text_styles = [
       (TextStyle::Body, font.clone()),
@@ -194,6 +417,30 @@ impl Configuration {
   }
 }
 
+impl Parameter<Self> for Coordinates {
+  fn default_value() -> Self {
+    Self::Edges
+  }
+}
+
+impl Parameter<Self> for Layout {
+  fn default_value() -> Self {
+    Self::Automatic
+  }
+}
+
+impl Parameter<Self> for Keybindings {
+  fn default_value() -> Self {
+    Self::default()
+  }
+}
+
+impl Parameter<Self> for EngineOptions {
+  fn default_value() -> Self {
+    Self::default()
+  }
+}
+
 impl Parameter<Self> for Theme {
   fn default_value() -> Self {
     Self::Preset(PresetTheme::Dark)
@@ -208,6 +455,16 @@ impl Parameter<Self> for TextSize {
   }
 }
 
+#[cfg(feature = "clock")]
+type LowTime = u16;
+
+#[cfg(feature = "clock")]
+impl Parameter<Self> for LowTime {
+  fn default_value() -> Self {
+    30
+  }
+}
+
 impl Parameter<Self> for bool {
   fn default_value() -> Self {
     true
@@ -0,0 +1,210 @@
+use crate::helpers::menu_button;
+use crate::players::PlayerType;
+use crate::LibertyChessGUI;
+use core::str::FromStr;
+use eframe::egui::{ComboBox, Grid, ScrollArea, TextEdit, Ui};
+use std::collections::BTreeMap;
+use ulci::{OptionValue, UlciOption};
+
+// Option values chosen for each external engine, saved between sessions
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct EngineOptions {
+  // engine path to option names and values, ordered so they are saved the same way each time
+  engines: BTreeMap<String, Vec<(String, String)>>,
+}
+
+impl EngineOptions {
+  // The saved options for an engine
+  pub fn get(&self, path: &str) -> &[(String, String)] {
+    self.engines.get(path).map_or(&[], Vec::as_slice)
+  }
+
+  fn value(&self, path: &str, name: &str) -> Option<&str> {
+    self
+      .get(path)
+      .iter()
+      .find(|(option, _)| option == name)
+      .map(|(_, value)| value.as_str())
+  }
+
+  // Save the value of an option, None goes back to the engine's default
+  pub fn set(&mut self, path: &str, name: &str, value: Option<String>) {
+    let options = self.engines.entry(path.to_owned()).or_default();
+    options.retain(|(option, _)| option != name);
+    if let Some(value) = value {
+      options.push((name.to_owned(), value));
+    }
+    if options.is_empty() {
+      self.engines.remove(path);
+    }
+  }
+}
+
+// Stored as one tab separated path, name and value per line, which UCI values can't contain
+impl ToString for EngineOptions {
+  fn to_string(&self) -> String {
+    self
+      .engines
+      .iter()
+      .flat_map(|(path, options)| {
+        options
+          .iter()
+          .map(move |(name, value)| format!("{path}\t{name}\t{value}"))
+      })
+      .collect::<Vec<String>>()
+      .join("\n")
+  }
+}
+
+impl FromStr for EngineOptions {
+  type Err = ();
+
+  fn from_str(options: &str) -> Result<Self, Self::Err> {
+    let mut result = Self::default();
+    for line in options.lines().filter(|line| !line.is_empty()) {
+      let mut fields = line.splitn(3, '\t');
+      let (Some(path), Some(name), Some(value)) = (fields.next(), fields.next(), fields.next())
+      else {
+        return Err(());
+      };
+      result.set(path, name, Some(value.to_owned()));
+    }
+    Ok(result)
+  }
+}
+
+// The value to send for a saved option, None if it doesn't suit the option any more
+pub fn parse_value(option: &UlciOption, value: &str) -> Option<OptionValue> {
+  match option {
+    UlciOption::String(_) => Some(OptionValue::UpdateString(value.to_owned())),
+    UlciOption::Int(int) => value
+      .parse()
+      .ok()
+      .filter(|value| (int.min..=int.max).contains(value))
+      .map(OptionValue::UpdateInt),
+    UlciOption::Bool(_) => value.parse().ok().map(OptionValue::UpdateBool),
+    UlciOption::Range(range) => range
+      .options
+      .contains(value)
+      .then(|| OptionValue::UpdateRange(value.to_owned())),
+    UlciOption::Trigger => None,
+  }
+}
+
+fn default_value(option: &UlciOption) -> String {
+  match option {
+    UlciOption::String(default) => default.clone(),
+    UlciOption::Int(int) => int.default.to_string(),
+    UlciOption::Bool(default) => default.to_string(),
+    UlciOption::Range(range) => range.default.clone(),
+    UlciOption::Trigger => String::new(),
+  }
+}
+
+// Edit the options of the engine on standby
+pub(crate) fn draw(gui: &mut LibertyChessGUI, ui: &mut Ui) {
+  menu_button(gui, ui);
+  let Some(PlayerType::External(path)) = gui.alternate_player.clone() else {
+    ui.label("Select an external engine to change its options");
+    return;
+  };
+  let Some(interface) = gui.engine_pool.standby_uci() else {
+    ui.label("The engine is not loaded");
+    return;
+  };
+  interface.poll();
+  let Some(options) = interface.options() else {
+    ui.label("Loading engine");
+    return;
+  };
+  let mut options: Vec<(String, UlciOption)> = options
+    .iter()
+    .map(|(name, option)| (name.clone(), option.clone()))
+    .collect();
+  options.sort_by(|(a, _), (b, _)| a.cmp(b));
+  let mut saved = gui.config.get_engine_options();
+  // options to send to the engine, and whether to save them
+  let mut changes = Vec::new();
+  let size = f32::from(gui.config.get_text_size());
+  ScrollArea::vertical().show(ui, |ui| {
+    Grid::new("Engine options").show(ui, |ui| {
+      for (name, option) in &options {
+        ui.label(name);
+        let default = default_value(option);
+        let mut value = saved.value(&path, name).unwrap_or(&default).to_owned();
+        match option {
+          UlciOption::String(_) | UlciOption::Int(_) => {
+            // the text is only checked and saved once editing finishes
+            let mut text = match &gui.option_edit {
+              Some((edited, text)) if edited == name => text.clone(),
+              _ => value.clone(),
+            };
+            let response = ui.add(TextEdit::singleline(&mut text).desired_width(size * 8.0));
+            if response.changed() {
+              gui.option_edit = Some((name.clone(), text.clone()));
+            }
+            if response.lost_focus() {
+              gui.option_edit = None;
+              if text != value {
+                changes.push((name.clone(), text));
+              }
+            }
+          }
+          UlciOption::Bool(_) => {
+            let mut checked = value == "true";
+            if ui.checkbox(&mut checked, "").changed() {
+              changes.push((name.clone(), checked.to_string()));
+            }
+          }
+          UlciOption::Range(range) => {
+            let mut choices: Vec<&String> = range.options.iter().collect();
+            choices.sort();
+            let previous = value.clone();
+            ComboBox::from_id_source(name)
+              .selected_text(value.as_str())
+              .show_ui(ui, |ui| {
+                for choice in choices {
+                  ui.selectable_value(&mut value, choice.clone(), choice.as_str());
+                }
+              });
+            if value != previous {
+              changes.push((name.clone(), value));
+            }
+          }
+          UlciOption::Trigger => {
+            if ui.button("Run").clicked() {
+              interface.set_option(name, OptionValue::SendTrigger);
+            }
+          }
+        }
+        if saved.value(&path, name).is_some() && ui.button("Reset").clicked() {
+          saved.set(&path, name, None);
+          changes.push((name.clone(), default));
+        }
+        ui.end_row();
+      }
+    });
+  });
+  for (name, value) in changes {
+    let Some((_, option)) = options.iter().find(|(option, _)| *option == name) else {
+      continue;
+    };
+    match parse_value(option, &value) {
+      Some(update) => {
+        interface.set_option(&name, update);
+        if value == default_value(option) {
+          saved.set(&path, &name, None);
+        } else {
+          saved.set(&path, &name, Some(value));
+        }
+      }
+      None => gui.message = Some(format!("Invalid value {value} for {name}")),
+    }
+  }
+  if saved != gui.config.get_engine_options() {
+    gui.config.set_engine_options(saved);
+  }
+  if let Some(message) = &gui.message {
+    ui.label(message);
+  }
+}
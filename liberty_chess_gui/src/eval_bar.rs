@@ -0,0 +1,82 @@
+use eframe::egui::Context;
+use std::collections::VecDeque;
+
+// how quickly the bar closes the gap to the latest evaluation, per second
+const SMOOTHING_RATE: f64 = 6.0;
+// number of recent evaluations kept to spot big swings
+const HISTORY_LENGTH: usize = 8;
+// a change in win chance from the recent average bigger than this is a swing
+const LARGE_SWING: f32 = 0.2;
+// seconds the bar is highlighted for after a swing
+const SWING_TIME: f64 = 0.8;
+// the bar is close enough to stop animating
+const SETTLED: f32 = 0.001;
+
+// Recent evaluations and the position of the bar as it moves between them
+#[derive(Default)]
+pub struct EvalBar {
+  // recent chances for black to win, newest last
+  history: VecDeque<f32>,
+  // the chance for black to win currently shown
+  shown: Option<f32>,
+  // the time of the last frame drawn
+  last_frame: Option<f64>,
+  // when the latest swing happened
+  swing: Option<f64>,
+}
+
+impl EvalBar {
+  // Record the latest evaluation as the chance for black to win, mates move the bar straight away
+  pub fn update(&mut self, ctx: &Context, black_win_chance: f32, mate: bool) {
+    if self.history.back() == Some(&black_win_chance) {
+      return;
+    }
+    if !self.history.is_empty() {
+      let average = self.history.iter().sum::<f32>() / self.history.len() as f32;
+      if (black_win_chance - average).abs() > LARGE_SWING {
+        self.swing = Some(ctx.input(|input| input.time));
+      }
+    }
+    if self.history.len() == HISTORY_LENGTH {
+      self.history.pop_front();
+    }
+    self.history.push_back(black_win_chance);
+    if mate || self.shown.is_none() {
+      self.shown = Some(black_win_chance);
+    }
+  }
+
+  // Move the bar towards the latest evaluation
+  //
+  // Returns the chance for black to win to show and how strongly to highlight a recent swing
+  pub fn animate(&mut self, ctx: &Context) -> (f32, f32) {
+    let time = ctx.input(|input| input.time);
+    let elapsed = self.last_frame.map_or(0.0, |last_frame| time - last_frame);
+    self.last_frame = Some(time);
+    let target = self.history.back().copied().unwrap_or(0.5);
+    let shown = self.shown.get_or_insert(target);
+    *shown += (target - *shown) * (1.0 - (-SMOOTHING_RATE * elapsed).exp()) as f32;
+    let mut animating = (target - *shown).abs() > SETTLED;
+    if !animating {
+      *shown = target;
+    }
+    let highlight = match self.swing {
+      Some(swing) if time - swing < SWING_TIME => {
+        animating = true;
+        (1.0 - (time - swing) / SWING_TIME) as f32
+      }
+      _ => {
+        self.swing = None;
+        0.0
+      }
+    };
+    if animating {
+      ctx.request_repaint();
+    }
+    (*shown, highlight)
+  }
+
+  pub fn clear(&mut self) {
+    *self = Self::default();
+  }
+}
@@ -0,0 +1,212 @@
+use crate::images::{self, image_index};
+use crate::themes::{BoardColours, Colours, Theme};
+use eframe::egui::Context;
+use gif::{Encoder, Frame, Repeat};
+use liberty_chess::Board;
+use resvg::render;
+use resvg::tiny_skia::{Color, Paint, Pixmap, Rect, Transform};
+use resvg::usvg::{FitTo, Tree};
+use std::fs::File;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread::spawn;
+
+// largest side of the exported image in pixels
+const IMAGE_SIZE: usize = 480;
+// smallest square size, very large boards go over the image size instead
+const MIN_SQUARE_SIZE: usize = 8;
+// colour quantisation speed from 1 to 30, higher is faster but uglier
+const QUANTISATION_SPEED: i32 = 10;
+
+// Settings for exporting a game as an animated GIF
+#[derive(Clone)]
+pub struct GifExport {
+  pub path: String,
+  // seconds each position is shown for
+  pub delay: f32,
+  // theme for the board colours, or None for the current theme
+  pub theme: Option<Theme>,
+}
+
+impl Default for GifExport {
+  fn default() -> Self {
+    Self {
+      path: String::new(),
+      delay: 1.0,
+      theme: None,
+    }
+  }
+}
+
+// Render a position to a pixmap without going through egui
+fn render_board(
+  images: &[Tree; 36],
+  board: &Board,
+  colours: BoardColours,
+  flipped: bool,
+  size: usize,
+) -> Option<Pixmap> {
+  let (rows, cols) = (board.height(), board.width());
+  let mut pixmap = Pixmap::new((cols * size) as u32, (rows * size) as u32)?;
+  let attacked_kings = board.attacked_kings();
+  for i in 0..rows {
+    for j in 0..cols {
+      let (x, y) = if flipped {
+        (cols - 1 - j, i)
+      } else {
+        (j, rows - 1 - i)
+      };
+      let mut colour = if (i + j) % 2 == 0 {
+        Colours::BlackSquare
+      } else {
+        Colours::WhiteSquare
+      };
+      if attacked_kings.contains(&&(i, j)) {
+        colour = Colours::Check;
+      } else if let Some(last_move) = board.last_move {
+        if (i, j) == last_move.start() || (i, j) == last_move.end() {
+          colour = Colours::Moved;
+        }
+      }
+      let colour = colours.get(&colour);
+      let mut paint = Paint::default();
+      paint.set_color(Color::from_rgba8(
+        colour.r(),
+        colour.g(),
+        colour.b(),
+        u8::MAX,
+      ));
+      let rect = Rect::from_xywh(
+        (x * size) as f32,
+        (y * size) as f32,
+        size as f32,
+        size as f32,
+      )?;
+      pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+      let piece = board.get_piece((i, j));
+      if piece != 0 {
+        render(
+          &images[image_index(piece)],
+          FitTo::Size(size as u32, size as u32),
+          Transform::from_translate((x * size) as f32, (y * size) as f32),
+          pixmap.as_mut(),
+        )?;
+      }
+    }
+  }
+  Some(pixmap)
+}
+
+// Save the positions of a game as an animated GIF, reporting the number of frames written
+fn export_gif(
+  settings: &GifExport,
+  images: &[Tree; 36],
+  positions: &[Board],
+  colours: BoardColours,
+  flipped: bool,
+  mut progress: impl FnMut(usize),
+) -> Result<(), String> {
+  let Some(first) = positions.first() else {
+    return Err("No positions to export".to_owned());
+  };
+  let size = (IMAGE_SIZE / first.height().max(first.width())).max(MIN_SQUARE_SIZE);
+  let (width, height) = (first.width() * size, first.height() * size);
+  let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+    return Err("Board is too large to export".to_owned());
+  };
+  // GIF delays are in hundredths of a second
+  let delay = (settings.delay * 100.0).round() as u16;
+  let file = File::create(&settings.path)
+    .map_err(|error| format!("Could not create {}: {error}", settings.path))?;
+  let mut encoder = Encoder::new(file, width, height, &[])
+    .map_err(|error| format!("Could not write GIF: {error}"))?;
+  encoder
+    .set_repeat(Repeat::Infinite)
+    .map_err(|error| format!("Could not write GIF: {error}"))?;
+  for (i, position) in positions.iter().enumerate() {
+    let pixmap = render_board(images, position, colours, flipped, size)
+      .ok_or_else(|| "Could not render position".to_owned())?;
+    // every pixel is opaque, so premultiplied alpha doesn't matter
+    let mut pixels = pixmap.take();
+    let mut frame = Frame::from_rgba_speed(width, height, &mut pixels, QUANTISATION_SPEED);
+    frame.delay = delay;
+    encoder
+      .write_frame(&frame)
+      .map_err(|error| format!("Could not write GIF: {error}"))?;
+    progress(i + 1);
+  }
+  Ok(())
+}
+
+// A GIF being written on another thread to keep the GUI responsive
+pub struct GifJob {
+  path: String,
+  frames: usize,
+  written: usize,
+  // the number of frames written so far, or the error that stopped the export
+  progress: Receiver<Result<usize, String>>,
+}
+
+impl GifJob {
+  pub fn new(
+    settings: &GifExport,
+    positions: &[Board],
+    colours: BoardColours,
+    flipped: bool,
+    ctx: &Context,
+  ) -> Self {
+    let (tx, progress) = channel();
+    let settings_copy = settings.clone();
+    let positions_copy: Vec<_> = positions.iter().map(Board::send_to_thread).collect();
+    let ctx = ctx.clone();
+    spawn(move || {
+      let positions: Vec<Board> = positions_copy
+        .into_iter()
+        .map(|position| position.load_from_thread())
+        .collect();
+      let result = export_gif(
+        &settings_copy,
+        &images::get(),
+        &positions,
+        colours,
+        flipped,
+        |frames| {
+          tx.send(Ok(frames)).ok();
+          ctx.request_repaint();
+        },
+      );
+      if let Err(error) = result {
+        tx.send(Err(error)).ok();
+      }
+      ctx.request_repaint();
+    });
+    Self {
+      path: settings.path.clone(),
+      frames: positions.len(),
+      written: 0,
+      progress,
+    }
+  }
+
+  // The fraction of frames written so far
+  pub fn progress(&self) -> f32 {
+    self.written as f32 / self.frames.max(1) as f32
+  }
+
+  // Returns a message once the export has finished
+  pub fn poll(&mut self) -> Option<String> {
+    loop {
+      match self.progress.try_recv() {
+        Ok(Ok(written)) => self.written = written,
+        Ok(Err(error)) => return Some(error),
+        Err(TryRecvError::Empty) => return None,
+        Err(TryRecvError::Disconnected) => {
+          return Some(if self.written == self.frames {
+            format!("Saved animation to {}", self.path)
+          } else {
+            "GIF export has crashed".to_owned()
+          });
+        }
+      }
+    }
+  }
+}
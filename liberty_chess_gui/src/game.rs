@@ -0,0 +1,547 @@
+use crate::helpers::{InlineIcons, MoveGlyph};
+use eframe::egui::{ComboBox, Context, TextEdit, Ui};
+use liberty_chess::moves::Move;
+use liberty_chess::parsing::{
+  pgn_tokens, Movetext, Pgn, PgnDialect, PgnError, PgnToken, MOVE_GLYPHS,
+};
+use liberty_chess::positions::STARTPOS;
+use liberty_chess::Board;
+use std::time::Duration;
+use ulci::report;
+
+fn nag_glyph(nag: u8) -> Option<&'static str> {
+  MOVE_GLYPHS
+    .iter()
+    .find(|(value, _)| *value == nag)
+    .map(|(_, glyph)| *glyph)
+}
+
+// the number of pieces on the board, including kings
+fn total_material(board: &Board) -> u32 {
+  [true, false]
+    .iter()
+    .map(|side| board.material(*side).iter().sum::<u32>())
+    .sum()
+}
+
+// A position in the game and the move that reached it
+struct Node {
+  board: Board,
+  // None for the starting position
+  mv: Option<Move>,
+  // the move in algebraic notation, empty for the starting position
+  san: String,
+  comment: String,
+  nags: Vec<u8>,
+  parent: Option<usize>,
+  // the first child continues the line, the rest are variations
+  children: Vec<usize>,
+}
+
+impl Node {
+  fn new(board: Board, mv: Option<Move>, san: String, parent: Option<usize>) -> Self {
+    Self {
+      board,
+      mv,
+      san,
+      comment: String::new(),
+      nags: Vec::new(),
+      parent,
+      children: Vec::new(),
+    }
+  }
+}
+
+// The moves of a game with annotations and variations, kept separate from the board
+pub struct GameTree {
+  nodes: Vec<Node>,
+  current: usize,
+  // extra PGN tags describing the game
+  tags: Vec<(String, String)>,
+}
+
+impl GameTree {
+  pub fn new(board: &Board) -> Self {
+    Self {
+      nodes: vec![Node::new(board.clone(), None, String::new(), None)],
+      current: 0,
+      tags: Vec::new(),
+    }
+  }
+
+  // Add a PGN tag to the game, replacing any with the same name
+  pub fn set_tag(&mut self, name: &str, value: String) {
+    self.tags.retain(|(tag, _)| tag != name);
+    self.tags.push((name.to_owned(), value));
+  }
+
+  // The position the game is currently at
+  pub fn board(&self) -> &Board {
+    &self.nodes[self.current].board
+  }
+
+  // The positions before the current one, oldest first
+  pub fn history(&self) -> Vec<Board> {
+    let mut history = Vec::new();
+    let mut node = self.nodes[self.current].parent;
+    while let Some(index) = node {
+      history.push(self.nodes[index].board.clone());
+      node = self.nodes[index].parent;
+    }
+    history.reverse();
+    history
+  }
+
+  // The moves played to reach the current position
+  #[cfg(feature = "overlay")]
+  pub fn moves(&self) -> Vec<Move> {
+    let mut moves = Vec::new();
+    let mut node = self.current;
+    while let (Some(mv), Some(parent)) = (self.nodes[node].mv, self.nodes[node].parent) {
+      moves.push(mv);
+      node = parent;
+    }
+    moves.reverse();
+    moves
+  }
+
+  fn child(&self, parent: usize, mv: Move) -> Option<usize> {
+    self.nodes[parent]
+      .children
+      .iter()
+      .copied()
+      .find(|child| self.nodes[*child].mv == Some(mv))
+  }
+
+  fn add_move(&mut self, parent: usize, mv: Move, board: Board) -> usize {
+    if let Some(child) = self.child(parent, mv) {
+      return child;
+    }
+    let index = self.nodes.len();
+    let san = self.nodes[parent]
+      .board
+      .san(mv)
+      .unwrap_or_else(|| mv.to_string());
+    self
+      .nodes
+      .push(Node::new(board, Some(mv), san, Some(parent)));
+    self.nodes[parent].children.push(index);
+    index
+  }
+
+  // Follow the position shown on the board, adding moves as they are played
+  // Starts a new game if the position isn't connected to the current one
+  pub fn sync(&mut self, board: &Board) {
+    if board.promotion_available() || self.nodes[self.current].board == *board {
+      return;
+    }
+    // kept up to date in case the GUI crashes
+    report::set_position(board);
+    if let Some(mv) = board.last_move {
+      let current = &self.nodes[self.current].board;
+      if current.move_if_legal(mv).is_some_and(|next| next == *board) {
+        self.current = self.add_move(self.current, mv, board.clone());
+        return;
+      }
+    }
+    // moves that were taken back stay in the record
+    let mut node = self.nodes[self.current].parent;
+    while let Some(index) = node {
+      if self.nodes[index].board == *board {
+        self.current = index;
+        return;
+      }
+      node = self.nodes[index].parent;
+    }
+    *self = Self::new(board);
+  }
+
+  // Edit the annotations of the last move played
+  pub fn draw_annotations(&mut self, ui: &mut Ui, size: f32) {
+    let node = &mut self.nodes[self.current];
+    if node.mv.is_none() {
+      return;
+    }
+    ui.horizontal_top(|ui| {
+      ui.label(&node.san);
+      let mut quality = node
+        .nags
+        .iter()
+        .copied()
+        .find(|nag| nag_glyph(*nag).is_some());
+      ComboBox::from_id_source("Annotation")
+        .selected_text(quality.and_then(nag_glyph).unwrap_or("-"))
+        .show_ui(ui, |ui| {
+          ui.selectable_value(&mut quality, None, "-");
+          for (nag, glyph) in MOVE_GLYPHS {
+            ui.selectable_value(&mut quality, Some(nag), glyph);
+          }
+        });
+      // only one move quality annotation makes sense
+      node.nags.retain(|nag| nag_glyph(*nag).is_none());
+      if let Some(nag) = quality {
+        node.nags.insert(0, nag);
+      }
+    });
+    ui.add(
+      TextEdit::multiline(&mut node.comment)
+        .hint_text("Comment")
+        .desired_width(size * 8.0)
+        .desired_rows(2),
+    );
+  }
+
+  // Go to a position in the game, keeping the moves after it
+  pub fn go_to(&mut self, index: usize) {
+    if index < self.nodes.len() {
+      self.current = index;
+    }
+  }
+
+  // Whether any moves have been played
+  pub fn has_moves(&self) -> bool {
+    !self.nodes[0].children.is_empty()
+  }
+
+  // The position before the current one
+  pub fn previous(&self) -> Option<usize> {
+    self.nodes[self.current].parent
+  }
+
+  // The position after the current one in the line being followed
+  pub fn next(&self) -> Option<usize> {
+    self.nodes[self.current].children.first().copied()
+  }
+
+  // The line through the current position followed to its end, and where the current position is
+  pub fn line(&self) -> (Vec<usize>, usize) {
+    let mut line = vec![self.current];
+    let mut node = self.nodes[self.current].parent;
+    while let Some(index) = node {
+      line.push(index);
+      node = self.nodes[index].parent;
+    }
+    line.reverse();
+    let position = line.len() - 1;
+    let mut index = self.current;
+    while let Some(&next) = self.nodes[index].children.first() {
+      line.push(next);
+      index = next;
+    }
+    (line, position)
+  }
+
+  // Whether the move reaching a position captured something or gave check
+  fn is_event(&self, index: usize) -> bool {
+    let node = &self.nodes[index];
+    node.parent.is_some_and(|parent| {
+      node.board.in_check()
+        || total_material(&node.board) < total_material(&self.nodes[parent].board)
+    })
+  }
+
+  // the first move of the variation containing the current position, None on the main line
+  fn variation_start(&self) -> Option<usize> {
+    let mut index = self.current;
+    while let Some(parent) = self.nodes[index].parent {
+      if self.nodes[parent].children.first() != Some(&index) {
+        return Some(index);
+      }
+      index = parent;
+    }
+    None
+  }
+
+  // Whether the current position is off the main line
+  pub fn in_variation(&self) -> bool {
+    self.variation_start().is_some()
+  }
+
+  // Move the variation containing the current position up one place, making it the main line if
+  // it was the first alternative
+  pub fn promote_variation(&mut self) {
+    if let Some(start) = self.variation_start() {
+      if let Some(parent) = self.nodes[start].parent {
+        let children = &mut self.nodes[parent].children;
+        if let Some(position) = children.iter().position(|child| *child == start) {
+          children.swap(position - 1, position);
+        }
+      }
+    }
+  }
+
+  // Remove the variation containing the current position, returning where it branched off
+  // The removed positions are left unreachable rather than renumbering the rest of the tree
+  pub fn delete_variation(&mut self) -> Option<usize> {
+    let start = self.variation_start()?;
+    let parent = self.nodes[start].parent?;
+    self.nodes[parent].children.retain(|child| *child != start);
+    Some(parent)
+  }
+
+  // the text shown for a move in the move tree
+  // the move number if it is shown, and the move with its annotations
+  fn move_glyph(&self, index: usize, show_number: bool) -> (Option<String>, MoveGlyph) {
+    let node = &self.nodes[index];
+    let parent = &self.nodes[node.parent.unwrap_or_default()].board;
+    let number = if parent.to_move() {
+      Some(format!("{}.", parent.moves()))
+    } else if show_number {
+      Some(format!("{}...", parent.moves()))
+    } else {
+      None
+    };
+    let piece = node.mv.map_or(0, |mv| parent.get_piece(mv.start()));
+    let mut glyph = MoveGlyph::new(piece, &node.san);
+    for nag in &node.nags {
+      match nag_glyph(*nag) {
+        Some(text) => glyph.push_str(text),
+        None => glyph.push_str(&format!(" ${nag}")),
+      }
+    }
+    (number, glyph)
+  }
+
+  fn draw_move(
+    &self,
+    ui: &mut Ui,
+    icons: &mut InlineIcons,
+    index: usize,
+    show_number: bool,
+  ) -> bool {
+    let (number, glyph) = self.move_glyph(index, show_number);
+    if let Some(number) = number {
+      ui.label(number);
+    }
+    let response = icons.move_button(ui, &glyph, index == self.current);
+    let clicked = response.clicked();
+    if !self.nodes[index].comment.is_empty() {
+      response.on_hover_text(&self.nodes[index].comment);
+    }
+    clicked
+  }
+
+  fn draw_line(
+    &self,
+    ui: &mut Ui,
+    icons: &mut InlineIcons,
+    mut index: usize,
+    mut show_number: bool,
+  ) -> Option<usize> {
+    let mut clicked = None;
+    while let Some((&main, variations)) = self.nodes[index].children.split_first() {
+      if self.draw_move(ui, icons, main, show_number) {
+        clicked = Some(main);
+      }
+      show_number = false;
+      for &variation in variations {
+        ui.label("(");
+        if self.draw_move(ui, icons, variation, true) {
+          clicked = Some(variation);
+        }
+        clicked = self.draw_line(ui, icons, variation, false).or(clicked);
+        ui.label(")");
+        show_number = true;
+      }
+      index = main;
+    }
+    clicked
+  }
+
+  // Show the moves with their variations, returning the position that was clicked on
+  pub fn draw_tree(&self, ui: &mut Ui, icons: &mut InlineIcons) -> Option<usize> {
+    ui.horizontal_wrapped(|ui| {
+      let clicked = ui.selectable_label(self.current == 0, "Start").clicked();
+      self
+        .draw_line(ui, icons, 0, true)
+        .or_else(|| clicked.then_some(0))
+    })
+    .inner
+  }
+
+  fn write_move(&self, index: usize, show_number: bool, movetext: &mut Movetext) {
+    let node = &self.nodes[index];
+    let Some(mv) = node.mv else {
+      return;
+    };
+    let parent = &self.nodes[node.parent.unwrap_or_default()].board;
+    let number = if parent.to_move() {
+      format!("{}. ", parent.moves())
+    } else if show_number {
+      format!("{}... ", parent.moves())
+    } else {
+      String::new()
+    };
+    movetext.push(&format!("{number}{mv}"));
+    for nag in &node.nags {
+      movetext.push(&format!("${nag}"));
+    }
+    if !node.comment.is_empty() {
+      movetext.push_comment(&node.comment);
+    }
+  }
+
+  // write the line continuing from a node along with its variations
+  fn write_line(&self, mut index: usize, mut show_number: bool, movetext: &mut Movetext) {
+    while let Some((&main, variations)) = self.nodes[index].children.split_first() {
+      self.write_move(main, show_number, movetext);
+      show_number = !self.nodes[main].comment.is_empty();
+      for &variation in variations {
+        movetext.push("(");
+        self.write_move(variation, true, movetext);
+        self.write_line(
+          variation,
+          !self.nodes[variation].comment.is_empty(),
+          movetext,
+        );
+        movetext.push(")");
+        show_number = true;
+      }
+      index = main;
+    }
+  }
+
+  // The game as PGN with the starting position stored as an L-FEN
+  pub fn to_pgn(&self) -> String {
+    let root = &self.nodes[0];
+    let mut game = Pgn::new(root.board.clone());
+    game.tags.clone_from(&self.tags);
+    let mut movetext = Movetext::default();
+    if !root.comment.is_empty() {
+      movetext.push_comment(&root.comment);
+    }
+    self.write_line(0, true, &mut movetext);
+    game.write_with(PgnDialect::Extended, movetext)
+  }
+
+  // Load a game from PGN, ending at the end of the main line
+  pub fn from_pgn(pgn: &str) -> Result<Self, String> {
+    let mut fen = STARTPOS.to_owned();
+    let mut tree: Option<Self> = None;
+    let mut tags = Vec::new();
+    // where to go back to when each variation ends
+    let mut variations = Vec::new();
+    for token in pgn_tokens(pgn).map_err(|error| error.to_string())? {
+      match token {
+        PgnToken::Tag(name, value) => match name.as_str() {
+          "FEN" => fen = value,
+          "Variant" | "SetUp" => (),
+          _ => tags.push((name, value)),
+        },
+        PgnToken::Comment(comment) => {
+          let tree = Self::start(&mut tree, &fen)?;
+          let node = &mut tree.nodes[tree.current];
+          if !node.comment.is_empty() {
+            node.comment.push(' ');
+          }
+          node.comment += &comment;
+        }
+        PgnToken::Nag(nag) => {
+          let tree = Self::start(&mut tree, &fen)?;
+          tree.nodes[tree.current].nags.push(nag);
+        }
+        PgnToken::VariationStart => {
+          let tree = Self::start(&mut tree, &fen)?;
+          variations.push(tree.current);
+          tree.current = tree.nodes[tree.current]
+            .parent
+            .ok_or_else(|| "Variation without a move".to_owned())?;
+        }
+        PgnToken::VariationEnd => {
+          let tree = Self::start(&mut tree, &fen)?;
+          tree.current = variations
+            .pop()
+            .ok_or_else(|| PgnError::Unmatched.to_string())?;
+        }
+        PgnToken::Move(notation) => {
+          // games from other software use standard algebraic notation
+          let tree = Self::start(&mut tree, &fen)?;
+          let position = &tree.nodes[tree.current].board;
+          let (mv, board) = position
+            .parse_move(&notation)
+            .and_then(|mv| Some((mv, position.move_if_legal(mv)?)))
+            .ok_or_else(|| PgnError::InvalidMove(notation).to_string())?;
+          tree.current = tree.add_move(tree.current, mv, board);
+        }
+        PgnToken::Result(_) => (),
+      }
+    }
+    if !variations.is_empty() {
+      return Err(PgnError::Unterminated.to_string());
+    }
+    let mut tree = match tree {
+      Some(tree) => tree,
+      None => Self::new(&Board::new(&fen).map_err(|error| error.to_string())?),
+    };
+    tree.tags = tags;
+    tree.current = 0;
+    while let Some(&main) = tree.nodes[tree.current].children.first() {
+      tree.current = main;
+    }
+    Ok(tree)
+  }
+
+  // the tree is created once the tags have been read
+  fn start<'a>(tree: &'a mut Option<Self>, fen: &str) -> Result<&'a mut Self, String> {
+    if tree.is_none() {
+      let board = Board::new(fen).map_err(|error| error.to_string())?;
+      *tree = Some(Self::new(&board));
+    }
+    Ok(tree.as_mut().expect("Tree was just created"))
+  }
+}
+
+// Automatic playback of a game for watching recorded matches
+pub struct Playback {
+  pub playing: bool,
+  // seconds each position is shown for
+  pub delay: f32,
+  // show the board from the side to move
+  pub flip: bool,
+  // stop playing after a capture or check
+  pub pause_on_events: bool,
+  // when the current position was reached
+  start: Option<f64>,
+}
+
+impl Default for Playback {
+  fn default() -> Self {
+    Self {
+      playing: false,
+      delay: 1.0,
+      flip: false,
+      pause_on_events: false,
+      start: None,
+    }
+  }
+}
+
+impl Playback {
+  pub fn toggle(&mut self) {
+    self.playing = !self.playing;
+    self.start = None;
+  }
+
+  // The position to show next if it is time to advance, stopping at the end of the line
+  pub fn advance(&mut self, game: &GameTree, ctx: &Context) -> Option<usize> {
+    if !self.playing {
+      return None;
+    }
+    let Some(next) = game.next() else {
+      self.toggle();
+      return None;
+    };
+    let time = ctx.input(|input| input.time);
+    let start = *self.start.get_or_insert(time);
+    let remaining = start + f64::from(self.delay) - time;
+    if remaining > 0.0 {
+      ctx.request_repaint_after(Duration::from_secs_f64(remaining));
+      return None;
+    }
+    self.start = None;
+    if self.pause_on_events && game.is_event(next) {
+      self.playing = false;
+    }
+    Some(next)
+  }
+}
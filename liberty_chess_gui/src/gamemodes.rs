@@ -1,10 +1,12 @@
 use crate::helpers::NumericalInput;
 use enum_iterator::Sequence;
+use liberty_chess::parsing::{to_char, to_name, to_piece};
 use liberty_chess::positions::{
   AFRICAN, CAPABLANCA, CAPABLANCA_RECTANGLE, DOUBLE_CHESS, ELIMINATION, HORDE, LIBERTY_CHESS,
   LOADED_BOARD, MINI, MONGOL, NARNIA, STARTPOS, TRUMP,
 };
 use liberty_chess::random_board::generate;
+use liberty_chess::{Piece, BISHOP, KING, KNIGHT, PAWN, QUEEN, ROOK, SQUARE};
 
 #[derive(Eq, PartialEq)]
 pub enum GameMode {
@@ -86,6 +88,7 @@ impl ToString for Presets {
 pub struct RandomConfig {
   pub pieces: String,
   pub spawn_king: bool,
+  pub chess960: bool,
   pub width: NumericalInput<usize>,
   pub height: NumericalInput<usize>,
 }
@@ -94,7 +97,7 @@ impl ToString for RandomConfig {
   fn to_string(&self) -> String {
     let width = self.width.get_value();
     let height = self.height.get_value();
-    generate(width, height, &self.pieces, self.spawn_king)
+    generate(width, height, &self.pieces, self.spawn_king, self.chess960)
   }
 }
 
@@ -103,8 +106,133 @@ impl Default for RandomConfig {
     Self {
       pieces: "qrbn".to_owned(),
       spawn_king: true,
+      chess960: false,
       width: NumericalInput::<usize>::new(8, 2, 256),
       height: NumericalInput::<usize>::new(8, 4, 256),
     }
   }
 }
+
+// A piece removed from the opponent's side as a handicap
+#[derive(Clone, Copy, Eq, PartialEq, Sequence)]
+pub enum MaterialOdds {
+  None,
+  Pawn,
+  Knight,
+  Bishop,
+  Rook,
+  Queen,
+}
+
+impl ToString for MaterialOdds {
+  fn to_string(&self) -> String {
+    self.piece().map_or("None".to_owned(), |piece| {
+      format!("{} odds", to_name(piece))
+    })
+  }
+}
+
+impl MaterialOdds {
+  pub const fn piece(self) -> Option<Piece> {
+    match self {
+      Self::None => None,
+      Self::Pawn => Some(PAWN),
+      Self::Knight => Some(KNIGHT),
+      Self::Bishop => Some(BISHOP),
+      Self::Rook => Some(ROOK),
+      Self::Queen => Some(QUEEN),
+    }
+  }
+}
+
+fn expand_rank(rank: &str) -> Option<Vec<Piece>> {
+  let mut pieces = Vec::new();
+  let mut squares = 0;
+  for c in rank.chars() {
+    if let Some(digit) = c.to_digit(10) {
+      squares = squares * 10 + digit as usize;
+    } else {
+      pieces.append(&mut vec![SQUARE; squares]);
+      squares = 0;
+      pieces.push(to_piece(c).ok()?);
+    }
+  }
+  pieces.append(&mut vec![SQUARE; squares]);
+  Some(pieces)
+}
+
+fn compress_rank(rank: &[Piece]) -> String {
+  let mut result = String::new();
+  let mut squares = 0;
+  for piece in rank {
+    if *piece == SQUARE {
+      squares += 1;
+    } else {
+      if squares > 0 {
+        result += &squares.to_string();
+        squares = 0;
+      }
+      result.push(to_char(*piece));
+    }
+  }
+  if squares > 0 {
+    result += &squares.to_string();
+  }
+  result
+}
+
+// Remove a piece from one side of a FEN, closest to their back rank and then the queenside
+pub fn remove_piece(fen: &str, piece: Piece, colour: bool) -> Option<String> {
+  let mut fields: Vec<String> = fen.split(' ').map(ToOwned::to_owned).collect();
+  let mut ranks = fields
+    .first()?
+    .split('/')
+    .map(expand_rank)
+    .collect::<Option<Vec<Vec<Piece>>>>()?;
+  let (target, king) = if colour {
+    (piece, KING)
+  } else {
+    (-piece, -KING)
+  };
+  // white's back rank is the last one in the FEN
+  let order: Vec<usize> = if colour {
+    (0..ranks.len()).rev().collect()
+  } else {
+    (0..ranks.len()).collect()
+  };
+  let (rank, column) = order.into_iter().find_map(|rank| {
+    ranks[rank]
+      .iter()
+      .position(|p| *p == target)
+      .map(|column| (rank, column))
+  })?;
+  ranks[rank][column] = SQUARE;
+  // can't castle with a piece that is no longer there
+  if let (Some(king_column), Some(castling)) = (
+    ranks[rank].iter().position(|p| *p == king),
+    fields.get_mut(2),
+  ) {
+    let (outermost, lost) = if column < king_column {
+      (ranks[rank][..column].iter().all(|p| *p == SQUARE), 'Q')
+    } else {
+      (ranks[rank][column..].iter().all(|p| *p == SQUARE), 'K')
+    };
+    if outermost {
+      let lost = if colour {
+        lost
+      } else {
+        lost.to_ascii_lowercase()
+      };
+      castling.retain(|c| c != lost);
+      if castling.is_empty() {
+        castling.push('-');
+      }
+    }
+  }
+  fields[0] = ranks
+    .iter()
+    .map(|rank| compress_rank(rank))
+    .collect::<Vec<String>>()
+    .join("/");
+  Some(fields.join(" "))
+}
@@ -1,16 +1,21 @@
 use crate::players::PlayerData;
-use crate::{switch_screen, LibertyChessGUI, Screen};
+use crate::{render_piece, switch_screen, LibertyChessGUI, Screen};
 use core::str::FromStr;
 use eframe::egui;
 use eframe::epaint::Vec2;
 use egui::color_picker::{color_edit_button_srgba, Alpha};
 use egui::load::SizedTexture;
-use egui::{Color32, Context, Image, TextBuffer, TextEdit, Ui};
+use egui::{
+  Button, Color32, Context, Image, Response, TextBuffer, TextEdit, TextStyle, TextureHandle, Ui,
+};
 use enum_iterator::{all, Sequence};
+use liberty_chess::moves::Move;
 use liberty_chess::parsing::to_piece;
+use liberty_chess::{Board, Hash, Piece, PAWN};
+use resvg::usvg::Tree;
 
 #[cfg(feature = "sound")]
-use liberty_chess::{Board, Gamestate};
+use liberty_chess::Gamestate;
 #[cfg(feature = "sound")]
 use sound::{Effect, Engine};
 
@@ -101,6 +106,115 @@ pub fn checkbox(
   clicked
 }
 
+// A move with the piece that moved shown as an icon instead of its letter
+pub struct MoveGlyph {
+  piece: Piece,
+  text: String,
+}
+
+impl MoveGlyph {
+  // Split a move in algebraic notation played by a piece, castling keeps its notation
+  pub fn new(piece: Piece, san: &str) -> Self {
+    let mut text = san.to_owned();
+    if piece.abs() != PAWN && !san.starts_with('O') && !text.is_empty() {
+      text.remove(0);
+    }
+    Self { piece, text }
+  }
+
+  // The glyphs for a line of moves from a position, stopping at the first illegal move
+  // White moves and the first move are numbered
+  pub fn line(board: &Board, moves: &[Move]) -> Vec<(Option<String>, Self)> {
+    let mut position = board.clone();
+    let mut glyphs = Vec::new();
+    for mv in moves {
+      let (Some(san), Some(next)) = (position.san(*mv), position.move_if_legal(*mv)) else {
+        break;
+      };
+      let number = if position.to_move() {
+        Some(format!("{}.", position.moves()))
+      } else if glyphs.is_empty() {
+        Some(format!("{}...", position.moves()))
+      } else {
+        None
+      };
+      glyphs.push((number, Self::new(position.get_piece(mv.start()), &san)));
+      position = next;
+    }
+    glyphs
+  }
+
+  pub fn push_str(&mut self, text: &str) {
+    self.text.push_str(text);
+  }
+}
+
+// The glyphs for an engine line, only worked out again when the line changes
+#[derive(Default)]
+pub struct LineCache {
+  key: Option<(Hash, Vec<Move>)>,
+  glyphs: Vec<(Option<String>, MoveGlyph)>,
+}
+
+impl LineCache {
+  pub fn get(&mut self, board: &Board, moves: &[Move]) -> &[(Option<String>, MoveGlyph)] {
+    let key = (board.hash(), moves.to_vec());
+    if self.key.as_ref() != Some(&key) {
+      self.glyphs = MoveGlyph::line(board, moves);
+      self.key = Some(key);
+    }
+    &self.glyphs
+  }
+}
+
+// Draws piece icons inline with text, with their own cache so they don't evict the board renders
+pub struct InlineIcons<'a> {
+  images: &'a [Tree; 36],
+  renders: &'a mut [Option<TextureHandle>; 36],
+}
+
+impl<'a> InlineIcons<'a> {
+  pub fn new(images: &'a [Tree; 36], renders: &'a mut [Option<TextureHandle>; 36]) -> Self {
+    Self { images, renders }
+  }
+
+  // an icon the height of a line of text
+  fn icon(&mut self, ui: &Ui, piece: Piece) -> Image<'static> {
+    let size = ui.text_style_height(&TextStyle::Body);
+    let pixels = (size * ui.ctx().pixels_per_point()).ceil() as u32;
+    let id = render_piece(self.images, self.renders, ui.ctx(), piece, pixels);
+    Image::new(SizedTexture {
+      id,
+      size: Vec2::splat(size),
+    })
+  }
+
+  // A move that can be clicked on, highlighted if it is selected
+  pub fn move_button(&mut self, ui: &mut Ui, glyph: &MoveGlyph, selected: bool) -> Response {
+    let icon = self.icon(ui, glyph.piece);
+    ui.add(
+      Button::image_and_text(icon, &glyph.text)
+        .selected(selected)
+        .frame(selected),
+    )
+  }
+
+  // A line of moves with their numbers, wrapping onto new rows as needed
+  pub fn line(&mut self, ui: &mut Ui, glyphs: &[(Option<String>, MoveGlyph)]) {
+    ui.horizontal_wrapped(|ui| {
+      ui.spacing_mut().item_spacing.x /= 2.0;
+      for (number, glyph) in glyphs {
+        if let Some(number) = number {
+          ui.label(number);
+        }
+        let icon = self.icon(ui, glyph.piece);
+        ui.add(icon);
+        ui.label(&glyph.text);
+      }
+    });
+  }
+}
+
 // Wrappers for text editing
 
 pub fn raw_text_edit(ui: &mut Ui, size: f32, input: &mut impl TextBuffer) {
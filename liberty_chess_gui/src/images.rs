@@ -1,5 +1,15 @@
+use liberty_chess::Piece;
 use resvg::usvg::{Options, Tree};
 
+// Index of a piece's image in the array
+pub const fn image_index(piece: Piece) -> usize {
+  if piece > 0 {
+    (piece - 1) as usize
+  } else {
+    (17 - piece) as usize
+  }
+}
+
 fn load_image(data: &[u8]) -> Tree {
   Tree::from_data(data, &Options::default()).unwrap()
 }
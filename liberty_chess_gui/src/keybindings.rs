@@ -0,0 +1,226 @@
+use crate::helpers::get_fen;
+use crate::{
+  can_end_game, can_review, go_to_move, offer_draw, resign, toggle_kibbutz, undo, LibertyChessGUI,
+};
+use core::str::FromStr;
+use eframe::egui;
+use egui::{Context, Event, Grid, Key, Ui};
+use enum_iterator::{all, Sequence};
+
+// Actions on the game screen that can be bound to a key
+#[derive(Clone, Copy, Eq, PartialEq, Sequence)]
+pub enum Action {
+  FlipBoard,
+  Undo,
+  CopyFen,
+  ToggleAnalysis,
+  PreviousMove,
+  NextMove,
+  OfferDraw,
+  Resign,
+  CycleCoordinates,
+}
+
+impl ToString for Action {
+  fn to_string(&self) -> String {
+    match self {
+      Self::FlipBoard => "Flip board",
+      Self::Undo => "Undo",
+      Self::CopyFen => "Copy FEN",
+      Self::ToggleAnalysis => "Toggle kibbutz",
+      Self::PreviousMove => "Previous move",
+      Self::NextMove => "Next move",
+      Self::OfferDraw => "Offer draw",
+      Self::Resign => "Resign",
+      Self::CycleCoordinates => "Cycle coordinates",
+    }
+    .to_owned()
+  }
+}
+
+impl FromStr for Action {
+  type Err = ();
+
+  fn from_str(action: &str) -> Result<Self, Self::Err> {
+    all::<Self>()
+      .find(|&possible_action| possible_action.to_string() == action)
+      .ok_or(())
+  }
+}
+
+// The key bound to each action, actions without a key are unbound
+#[derive(Clone, Eq, PartialEq)]
+pub struct Keybindings {
+  bindings: Vec<(Action, Key)>,
+}
+
+// ending the game is left unbound, so a stray key press can't lose it
+impl Default for Keybindings {
+  fn default() -> Self {
+    Self {
+      bindings: vec![
+        (Action::FlipBoard, Key::F),
+        (Action::Undo, Key::Z),
+        (Action::CopyFen, Key::C),
+        (Action::ToggleAnalysis, Key::A),
+        (Action::PreviousMove, Key::ArrowLeft),
+        (Action::NextMove, Key::ArrowRight),
+        (Action::CycleCoordinates, Key::N),
+      ],
+    }
+  }
+}
+
+impl Keybindings {
+  pub fn get(&self, action: Action) -> Option<Key> {
+    self
+      .bindings
+      .iter()
+      .find(|(bound_action, _)| *bound_action == action)
+      .map(|(_, key)| *key)
+  }
+
+  // Bind a key to an action, removing it from any other action
+  pub fn set(&mut self, action: Action, key: Option<Key>) {
+    self
+      .bindings
+      .retain(|(bound_action, bound_key)| *bound_action != action && Some(*bound_key) != key);
+    if let Some(key) = key {
+      self.bindings.push((action, key));
+    }
+  }
+
+  fn action(&self, key: Key) -> Option<Action> {
+    self
+      .bindings
+      .iter()
+      .find(|(_, bound_key)| *bound_key == key)
+      .map(|(action, _)| *action)
+  }
+}
+
+// Stored as a comma separated list of action:key pairs
+impl ToString for Keybindings {
+  fn to_string(&self) -> String {
+    self
+      .bindings
+      .iter()
+      .map(|(action, key)| format!("{}:{}", action.to_string(), key.name()))
+      .collect::<Vec<String>>()
+      .join(",")
+  }
+}
+
+impl FromStr for Keybindings {
+  type Err = ();
+
+  fn from_str(bindings: &str) -> Result<Self, Self::Err> {
+    let bindings = bindings
+      .split(',')
+      .filter(|binding| !binding.is_empty())
+      .map(|binding| -> Result<(Action, Key), ()> {
+        let (action, key) = binding.split_once(':').ok_or(())?;
+        Ok((action.parse()?, Key::from_name(key).ok_or(())?))
+      })
+      .collect::<Result<_, _>>()?;
+    Ok(Self { bindings })
+  }
+}
+
+// A key pressed this frame without modifiers
+fn pressed_key(ctx: &Context) -> Option<Key> {
+  ctx.input(|input| {
+    input.events.iter().find_map(|event| match event {
+      Event::Key {
+        key,
+        pressed: true,
+        repeat: false,
+        modifiers,
+        ..
+      } if modifiers.is_none() => Some(*key),
+      _ => None,
+    })
+  })
+}
+
+// Run the actions for any keys pressed on the game screen
+pub(crate) fn handle_shortcuts(gui: &mut LibertyChessGUI, ctx: &Context) {
+  // don't take keys meant for a text field
+  if ctx.wants_keyboard_input() {
+    return;
+  }
+  let Some(action) = pressed_key(ctx).and_then(|key| gui.config.get_keybindings().action(key))
+  else {
+    return;
+  };
+  match action {
+    Action::FlipBoard => gui.flipped = !gui.flipped,
+    Action::Undo => {
+      if !gui.undo.is_empty() {
+        undo(gui);
+      }
+    }
+    Action::CopyFen => ctx.output_mut(|o| o.copied_text = get_fen(gui)),
+    Action::ToggleAnalysis => {
+      if gui.config.get_evalbar() && gui.player.is_none() {
+        toggle_kibbutz(gui, ctx);
+      }
+    }
+    Action::PreviousMove | Action::NextMove => {
+      let target = gui.game.as_ref().and_then(|game| {
+        if action == Action::PreviousMove {
+          game.previous()
+        } else {
+          game.next()
+        }
+      });
+      if let (true, Some(index)) = (can_review(gui), target) {
+        go_to_move(gui, index);
+      }
+    }
+    Action::CycleCoordinates => gui.config.cycle_coordinates(),
+    Action::OfferDraw => {
+      if can_end_game(gui) {
+        offer_draw(gui);
+      }
+    }
+    Action::Resign => {
+      if can_end_game(gui) {
+        resign(gui);
+      }
+    }
+  }
+}
+
+// Settings for rebinding keys
+pub(crate) fn draw_edit(gui: &mut LibertyChessGUI, ui: &mut Ui) {
+  if let Some(action) = gui.rebinding {
+    if let Some(key) = pressed_key(ui.ctx()) {
+      // escape cancels rebinding
+      if key != Key::Escape {
+        gui.config.set_keybinding(action, Some(key));
+      }
+      gui.rebinding = None;
+    }
+  }
+  let keybindings = gui.config.get_keybindings();
+  ui.collapsing("Keyboard shortcuts", |ui| {
+    Grid::new("Keybindings").show(ui, |ui| {
+      for action in all::<Action>() {
+        ui.label(action.to_string());
+        let text = if gui.rebinding == Some(action) {
+          "Press a key"
+        } else {
+          keybindings.get(action).map_or("None", Key::name)
+        };
+        if ui.button(text).clicked() {
+          gui.rebinding = Some(action);
+        }
+        if keybindings.get(action).is_some() && ui.button("Clear").clicked() {
+          gui.config.set_keybinding(action, None);
+        }
+        ui.end_row();
+      }
+    });
+  });
+}
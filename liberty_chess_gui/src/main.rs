@@ -2,47 +2,62 @@
 #![warn(missing_docs, unused)]
 //! The GUI for Liberty Chess
 
-use crate::config::{Configuration, BOARD_KEY};
+use crate::analysis::BatchAnalysis;
+use crate::config::{is_portrait, Configuration, Layout, BOARD_KEY};
 use crate::credits::Credits;
-use crate::gamemodes::{GameMode, Presets, RandomConfig};
+use crate::eval_bar::EvalBar;
+use crate::game::{GameTree, Playback};
+use crate::gamemodes::{remove_piece, GameMode, MaterialOdds, Presets, RandomConfig};
 use crate::help_page::{draw_help, HelpPage};
 use crate::helpers::{
-  char_text_edit, checkbox, colour_edit, get_fen, label_text_edit, menu_button, NumericalInput,
+  char_text_edit, checkbox, colour_edit, get_fen, label_text_edit, menu_button, InlineIcons,
+  LineCache, NumericalInput,
 };
-use crate::players::{handle_loading_engine, PlayerColour, PlayerData, PlayerType, SearchType};
-use crate::render::draw_game;
+use crate::keybindings::{handle_shortcuts, Action};
+use crate::players::{
+  handle_loading_engine, white_score, EnginePool, PlayerColour, PlayerData, PlayerType, SearchType,
+  Session, UciState,
+};
+use crate::render::{candidate_score, draw_game, draw_preview, BoardBackground};
 use crate::themes::{Colours, Theme};
+use crate::thumbnails::Thumbnails;
+use crate::welcome::Step;
 use eframe::emath::Align2;
-use eframe::epaint::{pos2, Color32, FontId, Pos2, Rect, Rounding, TextureId};
+use eframe::epaint::{pos2, Color32, FontId, Pos2, Rect, Rounding, Stroke, TextureId};
 use eframe::{egui, App, CreationContext, Frame, Storage};
 use egui::{
-  Area, Button, CentralPanel, ColorImage, ComboBox, Context, IconData, Key, Label, RichText,
-  ScrollArea, SidePanel, Slider, TextureHandle, TextureOptions, TopBottomPanel, Ui, Vec2,
+  Area, Button, CentralPanel, ColorImage, ComboBox, Context, IconData, Label, RichText, ScrollArea,
+  SidePanel, Slider, TextureHandle, TextureOptions, TopBottomPanel, Ui, Vec2,
 };
 use enum_iterator::all;
 use helpers::{populate_dropdown, populate_dropdown_transform, raw_text_edit};
 use liberty_chess::moves::Move;
 use liberty_chess::parsing::to_name;
-use liberty_chess::{Board, Gamestate, Piece};
-use oxidation::HASH_SIZE;
+use liberty_chess::{Board, Gamestate, Hash, Piece};
+use oxidation::{pawn_value, win_probability_scale, HASH_SIZE};
 use players::EngineInterface;
 use resvg::render;
 use resvg::tiny_skia::{Pixmap, Transform};
 use resvg::usvg::{FitTo, Tree};
 use std::cmp::Ordering;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
 use themes::CustomTheme;
 use ulci::{Limits, Score, SearchTime};
 
-#[cfg(not(feature = "benchmarking"))]
+#[cfg(any(feature = "clock", not(feature = "benchmarking")))]
 use std::time::Duration;
 
 #[cfg(feature = "benchmarking")]
 use std::time::Instant;
 
 #[cfg(feature = "clock")]
-use crate::clock::{convert, draw, draw_edit, init_input};
+use crate::clock::{
+  convert, draw, draw_edit, draw_move_times, init_input, record_current_move, record_move,
+  undo_moves, MoveTimes, TimeOdds,
+};
 #[cfg(feature = "clock")]
-use liberty_chess::clock::{Clock, Type};
+use liberty_chess::clock::{Clock, ClockEvent, Type};
 
 #[cfg(feature = "music")]
 use crate::config::{DRAMATIC_ENABLED_KEY, MUSIC_VOLUME_KEY};
@@ -54,22 +69,52 @@ use helpers::update_sound;
 #[cfg(feature = "sound")]
 use sound::{Effect, Engine, DEFAULT_VOLUME};
 
+#[cfg(feature = "overlay")]
+use overlay::Overlay;
+
+#[cfg(feature = "recording")]
+use egui::RawInput;
+#[cfg(feature = "recording")]
+use recording::Recording;
+
 #[cfg(target_arch = "wasm32")]
 use eframe::{WebOptions, WebRunner};
 
+#[cfg(not(target_arch = "wasm32"))]
+use egui::ProgressBar;
+#[cfg(not(target_arch = "wasm32"))]
+use export::{GifExport, GifJob};
+#[cfg(not(target_arch = "wasm32"))]
+use verify::PositionCheck;
+
 // submodules
+mod analysis;
 mod config;
 mod credits;
+mod engine_options;
+mod eval_bar;
+mod game;
 mod gamemodes;
 mod help_page;
 mod helpers;
 mod images;
+mod keybindings;
 mod players;
 mod render;
 mod themes;
+mod thumbnails;
+mod welcome;
 
 #[cfg(feature = "clock")]
 mod clock;
+#[cfg(not(target_arch = "wasm32"))]
+mod export;
+#[cfg(feature = "overlay")]
+mod overlay;
+#[cfg(feature = "recording")]
+mod recording;
+#[cfg(not(target_arch = "wasm32"))]
+mod verify;
 
 const MAX_TIME: u64 = 360;
 
@@ -80,6 +125,28 @@ enum Screen {
   Help,
   Credits,
   Settings,
+  Analysis,
+  // options for the external engine on standby
+  EngineOptions,
+  // guided setup on first launch
+  Welcome(Step),
+}
+
+// A game ended by the players rather than on the board
+#[derive(Clone, Copy)]
+enum Outcome {
+  // the side that resigned, true = White
+  Resignation(bool),
+  DrawAgreed,
+}
+
+impl Outcome {
+  const fn winner(self) -> Option<bool> {
+    match self {
+      Self::Resignation(side) => Some(!side),
+      Self::DrawAgreed => None,
+    }
+  }
 }
 
 pub(crate) struct LibertyChessGUI {
@@ -94,6 +161,8 @@ pub(crate) struct LibertyChessGUI {
   gamemode: GameMode,
   friendly: bool,
   message: Option<String>,
+  // paths of bug reports written when another thread panics
+  reports: Option<Receiver<PathBuf>>,
   #[cfg(feature = "clock")]
   clock_type: Type,
   #[cfg(feature = "clock")]
@@ -101,33 +170,96 @@ pub(crate) struct LibertyChessGUI {
   alternate_player: Option<PlayerType>,
   searchsettings: SearchType,
   alternate_player_colour: PlayerColour,
+  alternate_colours: bool,
+  material_odds: MaterialOdds,
+  engine_pool: EnginePool,
 
   // fields for game screen
   selected: Option<(usize, usize)>,
   drag: Option<((usize, usize), Pos2)>,
+  // a move entered on the opponent's turn, played once it is legal
+  premove: Option<Move>,
   undo: Vec<Board>,
   #[cfg(feature = "clock")]
   clock: Option<Clock>,
+  // the starting times when they differ between the sides
+  #[cfg(feature = "clock")]
+  time_odds: Option<TimeOdds>,
+  #[cfg(feature = "clock")]
+  move_times: MoveTimes,
   promotion: Piece,
   player: Option<(PlayerData, bool)>,
+  // set once a side resigns or a draw is agreed
+  outcome: Option<Outcome>,
   searchtime: SearchTime,
   flipped: bool,
   eval: Option<(Score, u16)>,
+  // the engine line behind the eval, with the hash of the position it was searched from
+  eval_pv: Option<(Hash, Vec<Move>)>,
+  // smooths the movement of the eval bar between updates
+  eval_bar: EvalBar,
   safety_mode: bool,
+  playback: Playback,
   kibbutz: Option<(EngineInterface, Option<Move>)>,
+  // hash size and lines to search for the kibbutz, applied without restarting the analysis
+  kibbutz_hash: NumericalInput<usize>,
+  kibbutz_lines: NumericalInput<u16>,
+  // the kibbutz lines written with piece icons, one for each line searched
+  kibbutz_line: Vec<LineCache>,
+  session: Option<Session>,
+  // file to save kibbutz analysis to and resume it from
+  analysis_path: String,
+  // moves of the game with annotations
+  game: Option<GameTree>,
+  // file to save the game to and load it from
+  #[cfg(not(target_arch = "wasm32"))]
+  pgn_path: String,
+  // settings for sharing the game as an animation
+  #[cfg(not(target_arch = "wasm32"))]
+  gif: GifExport,
+  #[cfg(not(target_arch = "wasm32"))]
+  gif_job: Option<GifJob>,
+  // engine to compare move generation with
+  #[cfg(not(target_arch = "wasm32"))]
+  verify_path: String,
+  #[cfg(not(target_arch = "wasm32"))]
+  verify: Option<PositionCheck>,
+  sidebar_open: bool,
+
+  // fields for settings screen
+  rebinding: Option<Action>,
+  // the engine option being typed in and its text so far
+  option_edit: Option<(String, String)>,
 
   // fields for other screens
   help_page: HelpPage,
   credits: Credits,
+  analysis: BatchAnalysis,
 
   // images and a render cache - used on game screen
   images: [Tree; 36],
   renders: [Option<TextureHandle>; 36],
+  // separate cache for the engine line preview so it doesn't evict the main board renders
+  preview_renders: [Option<TextureHandle>; 36],
+  // icons drawn inline with moves in the move list and engine lines
+  icon_renders: [Option<TextureHandle>; 36],
+  // previews of the gamemodes, used on the menu
+  thumbnails: Thumbnails,
+  // the squares of the board, drawn as one texture
+  background: BoardBackground,
 
   // audio engine
   #[cfg(feature = "sound")]
   audio_engine: Option<Engine>,
 
+  // input recording or replay
+  #[cfg(feature = "recording")]
+  recording: Option<Recording>,
+
+  // game state served to stream overlays
+  #[cfg(feature = "overlay")]
+  overlay: Option<Overlay>,
+
   // for measuring FPS
   #[cfg(feature = "benchmarking")]
   instant: Instant,
@@ -138,16 +270,32 @@ pub(crate) struct LibertyChessGUI {
 }
 
 impl LibertyChessGUI {
-  fn new(ctx: &CreationContext) -> Self {
-    let config = Configuration::new(ctx);
-    let screen = ctx
-      .storage
-      .and_then(|data| data.get_string(BOARD_KEY))
-      .as_ref()
-      .and_then(|fen| Board::new(fen).ok())
-      .map_or(Screen::Menu, |board| Screen::Game(Box::new(board)));
+  fn new(
+    ctx: &CreationContext,
+    reports: Option<Receiver<PathBuf>>,
+    #[cfg(feature = "recording")] recording: Option<Recording>,
+  ) -> Self {
+    // saved data is ignored when recording so the replay starts from the same state
+    #[cfg(feature = "recording")]
+    let storage = if recording.is_some() {
+      None
+    } else {
+      ctx.storage
+    };
+    #[cfg(not(feature = "recording"))]
+    let storage = ctx.storage;
+    let config = Configuration::new(&ctx.egui_ctx, storage);
+    let saved_board = storage.and_then(|data| data.get_string(BOARD_KEY));
+    let screen = match (storage, saved_board) {
+      // nothing has been saved yet, so this is the first launch
+      (Some(_), None) => Screen::Welcome(Step::Appearance),
+      (_, saved_board) => saved_board
+        .as_ref()
+        .and_then(|fen| Board::new(fen).ok())
+        .map_or(Screen::Menu, |board| Screen::Game(Box::new(board))),
+    };
     #[cfg(feature = "sound")]
-    let audio_engine = match ctx.storage {
+    let audio_engine = match storage {
       Some(data) => Engine::load(
         &data.get_string(SOUND_KEY),
         &data.get_string(EFFECT_VOLUME_KEY),
@@ -167,6 +315,7 @@ impl LibertyChessGUI {
       fen: Presets::Standard.value(),
       friendly: false,
       message: None,
+      reports,
       #[cfg(feature = "clock")]
       clock_type: Type::None,
       #[cfg(feature = "clock")]
@@ -174,29 +323,74 @@ impl LibertyChessGUI {
       alternate_player: None,
       searchsettings: SearchType::default(),
       alternate_player_colour: PlayerColour::Random,
+      alternate_colours: false,
+      material_odds: MaterialOdds::None,
+      engine_pool: EnginePool::default(),
 
       selected: None,
       drag: None,
+      premove: None,
       undo: Vec::new(),
       #[cfg(feature = "clock")]
       clock: None,
+      #[cfg(feature = "clock")]
+      time_odds: None,
+      #[cfg(feature = "clock")]
+      move_times: Vec::new(),
       promotion: liberty_chess::QUEEN,
       player: None,
+      outcome: None,
       searchtime: SearchTime::Infinite,
       flipped: false,
       eval: None,
+      eval_pv: None,
+      eval_bar: EvalBar::default(),
       safety_mode: false,
+      playback: Playback::default(),
       kibbutz: None,
+      kibbutz_hash: NumericalInput::new(HASH_SIZE, 0, 1 << 16),
+      kibbutz_lines: NumericalInput::new(1, 1, 1 << 10),
+      kibbutz_line: Vec::new(),
+      session: None,
+      analysis_path: String::new(),
+      game: None,
+      #[cfg(not(target_arch = "wasm32"))]
+      pgn_path: String::new(),
+      #[cfg(not(target_arch = "wasm32"))]
+      gif: GifExport::default(),
+      #[cfg(not(target_arch = "wasm32"))]
+      gif_job: None,
+      #[cfg(not(target_arch = "wasm32"))]
+      verify_path: String::new(),
+      #[cfg(not(target_arch = "wasm32"))]
+      verify: None,
+      sidebar_open: true,
+
+      rebinding: None,
+      option_edit: None,
 
       help_page: HelpPage::PawnForward,
       credits: Credits::Coding,
+      analysis: BatchAnalysis::default(),
 
       images: images::get(),
       renders: [(); 36].map(|()| None),
+      preview_renders: [(); 36].map(|()| None),
+      icon_renders: [(); 36].map(|()| None),
+      thumbnails: Thumbnails::default(),
+      background: BoardBackground::default(),
 
       #[cfg(feature = "sound")]
       audio_engine,
 
+      #[cfg(feature = "recording")]
+      recording,
+
+      #[cfg(feature = "overlay")]
+      overlay: Overlay::start()
+        .map_err(|error| eprintln!("Could not start the overlay server: {error}"))
+        .ok(),
+
       #[cfg(feature = "benchmarking")]
       instant: Instant::now(),
       #[cfg(feature = "benchmarking")]
@@ -207,40 +401,125 @@ impl LibertyChessGUI {
   }
 
   fn get_image(&mut self, ctx: &Context, piece: Piece, size: u32) -> TextureId {
-    let index = match piece {
-      _ if piece > 0 => (piece - 1) as usize,
-      _ => (17 - piece) as usize,
-    };
-    if let Some(map) = &self.renders[index] {
-      if map.size() == [size as usize; 2] {
-        return map.id();
-      }
+    render_piece(&self.images, &mut self.renders, ctx, piece, size)
+  }
+
+  fn get_preview_image(&mut self, ctx: &Context, piece: Piece, size: u32) -> TextureId {
+    render_piece(&self.images, &mut self.preview_renders, ctx, piece, size)
+  }
+}
+
+// get the texture for a piece, rendering it if it isn't cached at this size
+fn render_piece(
+  images: &[Tree; 36],
+  renders: &mut [Option<TextureHandle>; 36],
+  ctx: &Context,
+  piece: Piece,
+  size: u32,
+) -> TextureId {
+  let index = images::image_index(piece);
+  if let Some(map) = &renders[index] {
+    if map.size() == [size as usize; 2] {
+      return map.id();
     }
-    let mut pixmap = Pixmap::new(size, size).expect("SVG is 0x0");
-    render(
-      &self.images[index],
-      FitTo::Size(size, size),
-      Transform::default(),
-      pixmap.as_mut(),
-    )
-    .unwrap();
-    let image = ColorImage::from_rgba_unmultiplied([size as usize; 2], pixmap.data());
-    let texture = ctx.load_texture("piece", image, TextureOptions::NEAREST);
-    let id = texture.id();
-    self.renders[index] = Some(texture);
-    id
   }
+  let mut pixmap = Pixmap::new(size, size).expect("SVG is 0x0");
+  render(
+    &images[index],
+    FitTo::Size(size, size),
+    Transform::default(),
+    pixmap.as_mut(),
+  )
+  .unwrap();
+  let image = ColorImage::from_rgba_unmultiplied([size as usize; 2], pixmap.data());
+  let texture = ctx.load_texture("piece", image, TextureOptions::NEAREST);
+  let id = texture.id();
+  renders[index] = Some(texture);
+  id
 }
 
 impl App for LibertyChessGUI {
+  #[cfg(feature = "recording")]
+  fn raw_input_hook(&mut self, ctx: &Context, raw_input: &mut RawInput) {
+    if let Some(recording) = &mut self.recording {
+      recording.new_frame(ctx, raw_input);
+    }
+  }
+
   fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
+    self.config.update_layout(ctx);
+    // an engine thread panicked, the GUI can carry on
+    if let Some(path) = self
+      .reports
+      .as_ref()
+      .and_then(|reports| reports.try_recv().ok())
+    {
+      self.message = Some(format!("A bug report has been saved to {}", path.display()));
+    }
     match &self.screen {
       Screen::Game(board) => {
         let board = board.clone();
-        SidePanel::right("Sidebar")
-          .min_width((f32::from(self.config.get_text_size())).mul_add(5.1, 6.5))
-          .resizable(false)
-          .show(ctx, |ui| draw_game_sidebar(self, ui, board));
+        // the eval bar is scaled to the material on the board
+        let scale = win_probability_scale(&board);
+        self
+          .game
+          .get_or_insert_with(|| {
+            let mut game = GameTree::new(&board);
+            // so games against the random mover can be replayed
+            if let Some(seed) = self.player.as_ref().and_then(|(player, _)| player.seed()) {
+              game.set_tag("RandomSeed", seed.to_string());
+            }
+            game
+          })
+          .sync(&board);
+        #[cfg(feature = "overlay")]
+        if let (Some(overlay), Some(game)) = (&mut self.overlay, &self.game) {
+          overlay.update(&board, self.eval, || game.moves());
+        }
+        handle_shortcuts(self, ctx);
+        if self.config.touch() {
+          TopBottomPanel::top("Sidebar toggle")
+            .resizable(false)
+            .show(ctx, |ui| {
+              let text = if self.sidebar_open {
+                "Hide menu"
+              } else {
+                "Show menu"
+              };
+              if ui.button(text).clicked() {
+                self.sidebar_open = !self.sidebar_open;
+              }
+            });
+          if self.sidebar_open {
+            // the sidebar goes under the board in portrait so the board can use the full width
+            if is_portrait(ctx) {
+              TopBottomPanel::bottom("Sidebar")
+                .max_height(ctx.screen_rect().height() * 0.4)
+                .resizable(false)
+                .show(ctx, |ui| {
+                  ScrollArea::vertical().show(ui, |ui| draw_game_sidebar(self, ui, board.clone()));
+                });
+            } else {
+              SidePanel::right("Sidebar")
+                .min_width((f32::from(self.config.get_text_size())).mul_add(5.1, 6.5))
+                .resizable(false)
+                .show(ctx, |ui| {
+                  ScrollArea::vertical().show(ui, |ui| draw_game_sidebar(self, ui, board.clone()));
+                });
+            }
+          }
+        } else {
+          SidePanel::right("Sidebar")
+            .min_width((f32::from(self.config.get_text_size())).mul_add(5.1, 6.5))
+            .resizable(false)
+            .show(ctx, |ui| draw_game_sidebar(self, ui, board.clone()));
+          if self.game.as_ref().is_some_and(GameTree::has_moves) {
+            SidePanel::right("Moves")
+              .min_width((f32::from(self.config.get_text_size())).mul_add(5.1, 6.5))
+              .resizable(false)
+              .show(ctx, |ui| draw_move_list(self, ui));
+          }
+        }
         if self.config.get_evalbar() {
           if let Some((score, depth)) = self.eval {
             let size = f32::from(self.config.get_text_size());
@@ -254,7 +533,13 @@ impl App for LibertyChessGUI {
                   Score::Win(moves) => (0.0, format!("#-{moves}")),
                   Score::Loss(moves) => (1.0, format!("#{moves}")),
                   Score::Centipawn(score) => {
-                    let score_abs = score.abs() / 10;
+                    // the bar uses the raw score, only the number shown is normalized
+                    let shown = if self.config.get_normalize_eval() {
+                      score * 100 / pawn_value(&board).max(1)
+                    } else {
+                      score
+                    };
+                    let score_abs = shown.abs() / 10;
                     let (pawns, centipawns) = (score_abs / 10, score_abs % 10);
                     let eval = match score.cmp(&0) {
                       Ordering::Equal => format!("{pawns}.{centipawns}"),
@@ -262,10 +547,13 @@ impl App for LibertyChessGUI {
                       Ordering::Less => format!("+{pawns}.{centipawns}"),
                     };
                     // Sigmoid calculation
-                    let score = 1.0 / (1.0 + (score as f32 / 400.0).exp());
+                    let score = 1.0 / (1.0 + (score as f32 / scale).exp());
                     (score, eval)
                   }
                 };
+                let mate = matches!(score, Score::Win(_) | Score::Loss(_));
+                self.eval_bar.update(ctx, black_win_chance, mate);
+                let (black_win_chance, highlight) = self.eval_bar.animate(ctx);
                 let (win_chance, colour_1, colour_2) = if self.flipped {
                   (black_win_chance, Color32::WHITE, Color32::BLACK)
                 } else {
@@ -289,19 +577,65 @@ impl App for LibertyChessGUI {
                   Rounding::ZERO,
                   colour_2,
                 );
+                // flash the edge of the bar after a big change in the evaluation
+                if highlight > 0.0 {
+                  painter.rect_stroke(
+                    Rect {
+                      min: pos2(0.0, 0.0),
+                      max: pos2(size * 2.0, height),
+                    },
+                    Rounding::ZERO,
+                    Stroke::new(size * 0.2, Color32::GOLD.gamma_multiply(highlight)),
+                  );
+                }
                 painter.text(
                   pos2(size, height),
                   Align2::CENTER_BOTTOM,
                   format!("{eval}/{depth}"),
                   FontId::proportional(size * 0.55),
                   Color32::GRAY,
-                )
+                );
+                // the side with less time can look worse than the position is
+                #[cfg(feature = "clock")]
+                if let Some(time_odds) = self.time_odds {
+                  let rect = painter.text(
+                    pos2(size, 0.0),
+                    Align2::CENTER_TOP,
+                    time_odds.ratio_text(),
+                    FontId::proportional(size * 0.55),
+                    Color32::GRAY,
+                  );
+                  ui.interact(rect, ui.id().with("Time odds"), egui::Sense::hover())
+                    .on_hover_text(time_odds.description());
+                }
               });
           }
         }
         #[cfg(feature = "clock")]
         if let Some(clock) = &mut self.clock {
-          draw(ctx, clock, self.flipped);
+          let low_time = self.config.get_low_time();
+          let thresholds = if low_time == 0 {
+            Vec::new()
+          } else {
+            vec![Duration::from_secs(low_time.into())]
+          };
+          if clock.thresholds() != thresholds {
+            clock.set_thresholds(&thresholds);
+          }
+          // events are taken even without sound so they don't build up
+          if clock
+            .take_events()
+            .iter()
+            .any(|event| matches!(event, ClockEvent::LowTime(..)))
+          {
+            #[cfg(feature = "sound")]
+            if let Some(engine) = &mut self.audio_engine {
+              engine.play(&Effect::LowTime);
+            }
+          }
+          // multiplayer clocks are controlled by the server
+          let editable = !matches!(self.player, Some((PlayerData::Multiplayer(_), _)));
+          draw(ctx, clock, self.flipped, editable, self.time_odds);
         }
       }
       Screen::Help => {
@@ -338,7 +672,11 @@ impl App for LibertyChessGUI {
             }
           });
       }
-      Screen::Menu | Screen::Settings => (),
+      Screen::Menu
+      | Screen::Settings
+      | Screen::Analysis
+      | Screen::EngineOptions
+      | Screen::Welcome(_) => (),
     };
 
     CentralPanel::default().show(ctx, |ui| {
@@ -347,10 +685,13 @@ impl App for LibertyChessGUI {
         Screen::Game(board) => draw_game(self, ctx, *board.clone()),
         Screen::Help => draw_help(self, ctx),
         Screen::Credits => credits::draw(self, ctx, ui),
+        Screen::Analysis => analysis::draw(self, ctx, ui),
+        Screen::EngineOptions => engine_options::draw(self, ui),
+        Screen::Welcome(step) => welcome::draw(self, ctx, ui, *step),
         Screen::Settings => {
           let width = ui.available_width();
           Area::new("Settings".into())
-            .fixed_pos(((width / 2.0) - 200.0, 0.0))
+            .fixed_pos((((width / 2.0) - 200.0).max(0.0), 0.0))
             .show(ctx, |ui| draw_settings(self, ctx, ui));
         }
       };
@@ -364,6 +705,12 @@ impl App for LibertyChessGUI {
       player.poll();
     }
 
+    // Replays need every frame to be rendered
+    #[cfg(feature = "recording")]
+    if self.recording.as_ref().is_some_and(Recording::is_replay) {
+      ctx.request_repaint();
+    }
+
     // Re-render every 100 ms if clock is ticking or waiting for engine
     #[cfg(not(feature = "benchmarking"))]
     {
@@ -394,6 +741,11 @@ impl App for LibertyChessGUI {
   }
 
   fn save(&mut self, storage: &mut dyn Storage) {
+    // don't overwrite the user's saved data with a recorded session
+    #[cfg(feature = "recording")]
+    if self.recording.is_some() {
+      return;
+    }
     self.config.save(storage);
     storage.set_string(BOARD_KEY, get_fen(self));
     #[cfg(feature = "sound")]
@@ -411,28 +763,44 @@ impl App for LibertyChessGUI {
   }
 }
 
+// reset the state of the game screen
+fn clear_game(gui: &mut LibertyChessGUI) {
+  gui.message = None;
+  gui.selected = None;
+  gui.drag = None;
+  gui.premove = None;
+  gui.undo.clear();
+  if let Some((player, _)) = &mut gui.player {
+    player.stop_pondering();
+  }
+  gui.player = None;
+  gui.outcome = None;
+  gui.eval = None;
+  gui.eval_pv = None;
+  gui.eval_bar.clear();
+  gui.kibbutz = None;
+  gui.game = None;
+  gui.playback.playing = false;
+  #[cfg(feature = "clock")]
+  {
+    gui.clock = None;
+    gui.time_odds = None;
+    gui.move_times.clear();
+  }
+  #[cfg(feature = "music")]
+  if let Some(ref mut player) = gui.audio_engine {
+    player.clear_dramatic();
+  }
+}
+
 fn switch_screen(gui: &mut LibertyChessGUI, screen: Screen) {
   match &gui.screen {
-    Screen::Menu => gui.message = None,
-    Screen::Game(_) => {
-      gui.message = None;
-      gui.selected = None;
-      gui.drag = None;
-      gui.undo.clear();
-      gui.player = None;
-      gui.eval = None;
-      gui.kibbutz = None;
-      #[cfg(feature = "clock")]
-      {
-        gui.clock = None;
-      }
-      #[cfg(feature = "music")]
-      if let Some(ref mut player) = gui.audio_engine {
-        player.clear_dramatic();
-      }
-    }
+    Screen::Menu | Screen::EngineOptions => gui.message = None,
+    Screen::Game(_) => clear_game(gui),
     Screen::Help => gui.selected = None,
-    Screen::Credits | Screen::Settings => (),
+    Screen::Settings => gui.rebinding = None,
+    Screen::Credits | Screen::Welcome(_) => (),
+    Screen::Analysis => gui.analysis.stop(),
   }
   #[cfg(feature = "sound")]
   if let Some(player) = &mut gui.audio_engine {
@@ -456,6 +824,9 @@ fn draw_nav_buttons(gui: &mut LibertyChessGUI, ui: &mut Ui) {
     if ui.button("Settings").clicked() {
       switch_screen(gui, Screen::Settings);
     }
+    if ui.button("Batch analysis").clicked() {
+      switch_screen(gui, Screen::Analysis);
+    }
   });
 }
 
@@ -466,8 +837,29 @@ fn draw_menu(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
   ComboBox::from_id_source("Gamemode")
     .selected_text("Gamemode: ".to_owned() + &gui.gamemode.to_string())
     .show_ui(ui, |ui| {
-      populate_dropdown_transform(ui, &mut gui.gamemode, GameMode::Preset);
-      ui.selectable_value(&mut gui.gamemode, GameMode::Custom, "Custom");
+      let colours = gui.config.get_theme().board_colours();
+      for preset in all::<Presets>() {
+        ui.horizontal(|ui| {
+          if let Some(thumbnail) =
+            gui
+              .thumbnails
+              .get(&gui.images, ui.ctx(), &preset.value(), colours)
+          {
+            ui.add(thumbnail);
+          }
+          ui.selectable_value(
+            &mut gui.gamemode,
+            GameMode::Preset(preset),
+            preset.to_string(),
+          );
+        });
+      }
+      ui.horizontal(|ui| {
+        if let Some(thumbnail) = gui.thumbnails.get(&gui.images, ui.ctx(), &gui.fen, colours) {
+          ui.add(thumbnail);
+        }
+        ui.selectable_value(&mut gui.gamemode, GameMode::Custom, "Custom");
+      });
       ui.selectable_value(
         &mut gui.gamemode,
         GameMode::Random(RandomConfig::default()),
@@ -487,6 +879,15 @@ fn draw_menu(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
         #[cfg(feature = "sound")]
         gui.audio_engine.as_mut(),
       );
+      if config.spawn_king {
+        checkbox(
+          ui,
+          &mut config.chess960,
+          "Chess960 castling",
+          #[cfg(feature = "sound")]
+          gui.audio_engine.as_mut(),
+        );
+      }
       let size = size * 1.5;
       label_text_edit(ui, size, &mut config.width, "Width");
       label_text_edit(ui, size, &mut config.height, "Height");
@@ -500,79 +901,8 @@ fn draw_menu(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
     gui.audio_engine.as_mut(),
   );
   if ui.button("Start Game").clicked() {
-    if let GameMode::Random(ref config) = gui.gamemode {
-      gui.fen = config.to_string();
-    }
-    match Board::new(&gui.fen) {
-      Ok(mut board) => {
-        #[cfg(feature = "clock")]
-        match gui.clock_type {
-          Type::None => gui.clock = None,
-          Type::Increment | Type::Handicap => {
-            gui.clock = Some(Clock::new(convert(&gui.clock_data), board.to_move()));
-          }
-        }
-        if gui.friendly {
-          board.friendly_fire = true;
-        }
-        #[cfg(feature = "music")]
-        if let Some(ref mut player) = gui.audio_engine {
-          player.set_dramatic(get_dramatic(&board));
-        }
-
-        if gui.config.get_autoflip() {
-          gui.flipped = !board.to_move();
-        }
-
-        let (player, message) = gui
-          .alternate_player
-          .as_ref()
-          .map_or((None, None), |player| {
-            let colour = gui.alternate_player_colour.get_colour();
-            if gui.config.get_opponentflip() && !matches!(player, PlayerType::Multiplayer(..)) {
-              gui.flipped = colour;
-            }
-            #[cfg(not(feature = "clock"))]
-            let searchtime = gui.searchsettings.get_value();
-            #[cfg(feature = "clock")]
-            let (searchtime, clock) = gui.searchsettings.get_value(colour);
-            #[cfg(feature = "clock")]
-            if let Some(clock) = clock {
-              let mut clock = Clock::new(clock, board.to_move());
-              if !board.to_move() ^ colour {
-                clock.toggle_pause();
-              }
-              gui.clock = Some(clock);
-            }
-            if searchtime == SearchTime::Other(Limits::default()) {
-              (None, Some("Must limit depth, nodes or time".to_owned()))
-            } else {
-              gui.searchtime = searchtime;
-              let player_data = PlayerData::new(player, &board, ctx);
-              match player_data {
-                Ok(player_data) => {
-                  let message = match player_data {
-                    PlayerData::Uci(_) => Some("Loading engine".to_owned()),
-                    PlayerData::Multiplayer(_) => Some("Connecting to server".to_owned()),
-                    _ => None,
-                  };
-                  (Some((player_data, colour)), message)
-                }
-                Err(error) => (None, Some(error)),
-              }
-            }
-          });
-
-        gui.player = player;
-        if message.is_none() {
-          switch_screen(gui, Screen::Game(Box::new(board)));
-        }
-        gui.message = message;
-      }
-      Err(error) => {
-        gui.message = Some(error.to_string());
-      }
-    }
+    gui.session = None;
+    start_game(gui, ctx);
   }
   if let Some(message) = &gui.message {
     ui.label(message);
@@ -581,6 +911,8 @@ fn draw_menu(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
   #[cfg(feature = "clock")]
   handle_thinking_engine(gui, ui, size);
 
+  // the engine on standby is only relaunched when the opponent changes
+  let previous_player = gui.alternate_player.clone();
   let player_name = gui
     .alternate_player
     .as_ref()
@@ -591,9 +923,13 @@ fn draw_menu(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
     .show_ui(ui, |ui| {
       ui.selectable_value(&mut gui.alternate_player, None, "Local Opponent");
       let values = [
-        PlayerType::RandomEngine,
-        PlayerType::MvvLva,
+        PlayerType::random_engine(),
+        PlayerType::mvvlva(),
+        // the web has no threads or processes for the full engine and external engines
+        #[cfg(not(target_arch = "wasm32"))]
         PlayerType::built_in(),
+        PlayerType::lite(),
+        #[cfg(not(target_arch = "wasm32"))]
         PlayerType::External(String::new()),
         PlayerType::Multiplayer(
           String::new(),
@@ -617,6 +953,18 @@ fn draw_menu(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
         .show_ui(ui, |ui| {
           populate_dropdown(ui, &mut gui.alternate_player_colour);
         });
+      checkbox(
+        ui,
+        &mut gui.alternate_colours,
+        "Alternate colours each game",
+        #[cfg(feature = "sound")]
+        gui.audio_engine.as_mut(),
+      );
+      ComboBox::from_id_source("Material Odds")
+        .selected_text(format!("Material odds: {}", gui.material_odds.to_string()))
+        .show_ui(ui, |ui| {
+          populate_dropdown(ui, &mut gui.material_odds);
+        });
     }
   }
   if let Some(ref mut player) = gui.alternate_player {
@@ -727,11 +1075,64 @@ fn draw_menu(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
       }
     }
     match player {
-      PlayerType::BuiltIn(ref mut hash_size) => {
+      PlayerType::BuiltIn(ref mut hash_size, ref mut auto_hash, ref mut elo) => {
+        if checkbox(
+          ui,
+          &mut elo.is_some(),
+          "Limit strength",
+          #[cfg(feature = "sound")]
+          gui.audio_engine.as_mut(),
+        ) {
+          if elo.is_some() {
+            *elo = None;
+          } else {
+            *elo = Some(PlayerType::elo());
+          }
+        }
+        if let Some(ref mut elo) = elo {
+          ui.horizontal_top(|ui| {
+            ui.label("Elo");
+            raw_text_edit(ui, size * 3.0, elo);
+          });
+        }
         if gui.config.get_advanced() {
+          checkbox(
+            ui,
+            auto_hash,
+            "Pick hash size from time control",
+            #[cfg(feature = "sound")]
+            gui.audio_engine.as_mut(),
+          );
+          if !*auto_hash {
+            ui.horizontal_top(|ui| {
+              ui.label("Hash size (MB)");
+              raw_text_edit(ui, size * 4.0, hash_size);
+            });
+          }
+        }
+      }
+      PlayerType::Lite(time, elo) => {
+        ui.horizontal_top(|ui| {
+          ui.label("Thinking time (ms)");
+          raw_text_edit(ui, size * 3.0, time);
+        });
+        if checkbox(
+          ui,
+          &mut elo.is_some(),
+          "Limit strength",
+          #[cfg(feature = "sound")]
+          gui.audio_engine.as_mut(),
+        ) {
+          if elo.is_some() {
+            *elo = None;
+          } else {
+            *elo = Some(PlayerType::elo());
+          }
+        }
+        if let Some(ref mut elo) = elo {
           ui.horizontal_top(|ui| {
-            ui.label("Hash size (MB)");
-            raw_text_edit(ui, size * 4.0, hash_size);
+            ui.label("Elo");
+            raw_text_edit(ui, size * 3.0, elo);
           });
         }
       }
@@ -753,14 +1154,222 @@ fn draw_menu(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
           raw_text_edit(ui, size * 6.0, name);
         });
       }
-      PlayerType::RandomEngine | PlayerType::MvvLva => (),
+      PlayerType::RandomEngine(delay, seed) => {
+        ui.horizontal_top(|ui| {
+          ui.label("Thinking delay (ms)");
+          raw_text_edit(ui, size * 3.0, delay);
+        });
+        if checkbox(
+          ui,
+          &mut seed.is_some(),
+          "Fixed seed",
+          #[cfg(feature = "sound")]
+          gui.audio_engine.as_mut(),
+        ) {
+          if seed.is_some() {
+            *seed = None;
+          } else {
+            *seed = Some(PlayerType::seed());
+          }
+        }
+        if let Some(ref mut seed) = seed {
+          ui.horizontal_top(|ui| {
+            ui.label("Seed");
+            raw_text_edit(ui, size * 6.0, seed);
+          });
+        }
+      }
+      PlayerType::MvvLva(delay, temperature) => {
+        ui.horizontal_top(|ui| {
+          ui.label("Thinking delay (ms)");
+          raw_text_edit(ui, size * 3.0, delay);
+        });
+        ui.horizontal_top(|ui| {
+          ui.label("Temperature (centipawns)");
+          raw_text_edit(ui, size * 3.0, temperature);
+        });
+      }
+    }
+  }
+
+  // keep the engine loaded so games start straight away
+  if gui.alternate_player != previous_player {
+    gui
+      .engine_pool
+      .changed(previous_player.as_ref(), gui.alternate_player.as_ref(), ctx);
+  }
+  if gui.player.is_none() {
+    gui.engine_pool.update(
+      gui.alternate_player.as_ref(),
+      &gui.fen,
+      &gui.config.get_engine_options(),
+      ctx,
+    );
+    if let Some(status) = gui.engine_pool.status() {
+      ui.label(status);
+    }
+    let configurable = gui
+      .engine_pool
+      .standby_uci()
+      .is_some_and(|interface| interface.options().is_some());
+    if configurable && ui.button("Engine options").clicked() {
+      switch_screen(gui, Screen::EngineOptions);
     }
   }
 }
 
-fn draw_settings(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
+// play another game against the same opponent without going back to the menu
+fn rematch(gui: &mut LibertyChessGUI, ctx: &Context) {
+  clear_game(gui);
+  gui.screen = Screen::Menu;
+  start_game(gui, ctx);
+}
+
+fn start_game(gui: &mut LibertyChessGUI, ctx: &Context) {
+  if let GameMode::Random(ref config) = gui.gamemode {
+    gui.fen = config.to_string();
+  }
+  match Board::new(&gui.fen) {
+    Ok(mut board) => {
+      // unplayable setups are refused, unusual ones are started with a warning
+      let issues = board.sanity_report();
+      let report = (!issues.is_empty()).then(|| {
+        issues
+          .iter()
+          .map(ToString::to_string)
+          .collect::<Vec<_>>()
+          .join("\n")
+      });
+      if issues.iter().any(|issue| issue.blocks_play()) {
+        gui.message = report;
+        return;
+      }
+      #[cfg(feature = "clock")]
+      {
+        gui.time_odds = None;
+      }
+      #[cfg(feature = "clock")]
+      match gui.clock_type {
+        Type::None => gui.clock = None,
+        Type::Increment | Type::Handicap => {
+          let times = convert(&gui.clock_data);
+          gui.time_odds = TimeOdds::new(times);
+          gui.clock = Some(Clock::new(times, board.to_move()));
+        }
+        Type::Delay => {
+          let [time, _, delay, _] = convert(&gui.clock_data);
+          gui.clock = Some(Clock::new_delay([time, time], delay, board.to_move()));
+        }
+        Type::Hourglass => {
+          let [time, ..] = convert(&gui.clock_data);
+          gui.clock = Some(Clock::new_hourglass([time, time], board.to_move()));
+        }
+      }
+      if gui.friendly {
+        board.friendly_fire = true;
+      }
+      #[cfg(feature = "music")]
+      if let Some(ref mut player) = gui.audio_engine {
+        player.set_dramatic(get_dramatic(&board));
+      }
+
+      if gui.config.get_autoflip() {
+        gui.flipped = !board.to_move();
+      }
+
+      let (player, message) = gui
+        .alternate_player
+        .as_ref()
+        .map_or((None, None), |player| {
+          let colour = match gui.session {
+            // the engine switches sides for the rematch
+            Some(ref session) if gui.alternate_colours => !session.engine_colour(),
+            _ => gui.alternate_player_colour.get_colour(),
+          };
+          if let Some(piece) = gui.material_odds.piece() {
+            if matches!(player, PlayerType::Multiplayer(..)) {
+              return (
+                None,
+                Some("Material odds are not available online".to_owned()),
+              );
+            }
+            // the opponent gives the odds
+            match remove_piece(&board.to_string(), piece, colour).map(|fen| Board::new(&fen)) {
+              Some(Ok(new_board)) => board = new_board,
+              Some(Err(error)) => return (None, Some(error.to_string())),
+              None => {
+                return (
+                  None,
+                  Some(format!("Opponent has no {} to remove", to_name(piece))),
+                )
+              }
+            }
+          }
+          if gui.config.get_opponentflip() && !matches!(player, PlayerType::Multiplayer(..)) {
+            gui.flipped = colour;
+          }
+          #[cfg(not(feature = "clock"))]
+          let searchtime = gui.searchsettings.get_value();
+          #[cfg(feature = "clock")]
+          let (searchtime, clock) = gui.searchsettings.get_value(colour);
+          #[cfg(feature = "clock")]
+          if let Some(clock) = clock {
+            gui.time_odds = TimeOdds::new(clock);
+            let mut clock = Clock::new(clock, board.to_move());
+            if !board.to_move() ^ colour {
+              clock.toggle_pause();
+            }
+            gui.clock = Some(clock);
+          }
+          if searchtime == SearchTime::Other(Limits::default()) {
+            (None, Some("Must limit depth, nodes or time".to_owned()))
+          } else {
+            gui.searchtime = searchtime;
+            let player_data = gui.engine_pool.take(
+              player,
+              &board,
+              &searchtime,
+              &gui.config.get_engine_options(),
+              ctx,
+            );
+            match player_data {
+              Ok(player_data) => {
+                let message = match player_data {
+                  PlayerData::Uci(ref interface) if interface.state == UciState::Pending => {
+                    Some("Loading engine".to_owned())
+                  }
+                  PlayerData::Multiplayer(_) => Some("Connecting to server".to_owned()),
+                  _ => None,
+                };
+                (Some((player_data, colour)), message)
+              }
+              Err(error) => (None, Some(error)),
+            }
+          }
+        });
+
+      match player {
+        Some((PlayerData::Multiplayer(_), _)) | None => gui.session = None,
+        Some((_, colour)) => gui
+          .session
+          .get_or_insert_with(Session::default)
+          .new_game(colour),
+      }
+      gui.player = player;
+      if message.is_none() {
+        switch_screen(gui, Screen::Game(Box::new(board)));
+      }
+      gui.message = message.or(report);
+    }
+    Err(error) => {
+      gui.message = Some(error.to_string());
+    }
+  }
+}
+
+// theme, layout and text size settings
+pub(crate) fn draw_appearance_settings(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
   let mut new_theme = gui.config.get_theme();
-  menu_button(gui, ui);
   ComboBox::from_id_source("Theme")
     .selected_text("Theme: ".to_owned() + &new_theme.show())
     .show_ui(ui, |ui| {
@@ -776,11 +1385,30 @@ fn draw_settings(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
     Theme::Custom(ref mut custom) => {
       colour_edit(ui, &mut custom.background, "Background");
       colour_edit(ui, &mut custom.text, "Text");
+      ui.collapsing("Board colours", |ui| {
+        let board = &mut custom.board;
+        colour_edit(ui, &mut board.white_square, "Light squares");
+        colour_edit(ui, &mut board.black_square, "Dark squares");
+        colour_edit(ui, &mut board.moved, "Last move");
+        colour_edit(ui, &mut board.selected, "Selected piece");
+        colour_edit(ui, &mut board.valid_white, "Valid move (light)");
+        colour_edit(ui, &mut board.valid_black, "Valid move (dark)");
+        colour_edit(ui, &mut board.threatened_white, "Capture (light)");
+        colour_edit(ui, &mut board.threatened_black, "Capture (dark)");
+        colour_edit(ui, &mut board.check, "Check");
+      });
     }
   }
   if gui.config.get_theme() != new_theme {
     gui.config.set_theme(ctx, new_theme);
   }
+  let mut layout = gui.config.get_layout();
+  ComboBox::from_id_source("Layout")
+    .selected_text("Layout: ".to_owned() + &layout.to_string())
+    .show_ui(ui, |ui| populate_dropdown(ui, &mut layout));
+  if gui.config.get_layout() != layout {
+    gui.config.set_layout(ctx, layout);
+  }
   let mut size = gui.config.get_text_size();
   if ui
     .add(Slider::new(&mut size, 16..=36).text("Font size"))
@@ -788,14 +1416,59 @@ fn draw_settings(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
   {
     gui.config.set_text_size(ctx, size);
   }
-  if checkbox(
-    ui,
-    &mut gui.config.get_numbers(),
-    "Show rank/file numbers",
-    #[cfg(feature = "sound")]
-    gui.audio_engine.as_mut(),
-  ) {
-    gui.config.toggle_numbers();
+}
+
+// sound effect and music settings
+#[cfg(feature = "sound")]
+pub(crate) fn draw_sound_settings(gui: &mut LibertyChessGUI, ui: &mut Ui) {
+  let mut sound = gui.audio_engine.is_some();
+  if checkbox(ui, &mut sound, "Sound", None) {
+    gui.audio_engine = if sound { Engine::new() } else { None }
+  };
+  if let Some(ref mut engine) = gui.audio_engine {
+    let mut volume = engine.get_sound_volume();
+    if ui
+      .add(Slider::new(&mut volume, 0..=DEFAULT_VOLUME).text("Effect Volume"))
+      .changed()
+    {
+      engine.set_sound_volume(volume);
+    }
+    #[cfg(feature = "music")]
+    {
+      let mut music = engine.music_enabled();
+      if checkbox(ui, &mut music, "Music", Some(engine)) {
+        engine.toggle_music();
+      }
+      if music {
+        if checkbox(
+          ui,
+          &mut engine.dramatic_enabled(),
+          "Dramatic Music",
+          Some(engine),
+        ) {
+          engine.toggle_dramatic();
+        }
+        let mut volume = engine.get_music_volume();
+        if ui
+          .add(Slider::new(&mut volume, 0..=DEFAULT_VOLUME).text("Music Volume"))
+          .changed()
+        {
+          engine.set_music_volume(volume);
+        }
+      }
+    }
+  }
+}
+
+fn draw_settings(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
+  menu_button(gui, ui);
+  draw_appearance_settings(gui, ctx, ui);
+  let mut coordinates = gui.config.get_coordinates();
+  ComboBox::from_id_source("Coordinates")
+    .selected_text("Coordinates: ".to_owned() + &coordinates.to_string())
+    .show_ui(ui, |ui| populate_dropdown(ui, &mut coordinates));
+  if gui.config.get_coordinates() != coordinates {
+    gui.config.set_coordinates(coordinates);
   }
   if checkbox(
     ui,
@@ -816,43 +1489,15 @@ fn draw_settings(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
     gui.config.toggle_opponentflip();
   }
   #[cfg(feature = "sound")]
+  draw_sound_settings(gui, ui);
+  #[cfg(feature = "clock")]
   {
-    let mut sound = gui.audio_engine.is_some();
-    if checkbox(ui, &mut sound, "Sound", None) {
-      gui.audio_engine = if sound { Engine::new() } else { None }
-    };
-    if let Some(ref mut engine) = gui.audio_engine {
-      let mut volume = engine.get_sound_volume();
-      if ui
-        .add(Slider::new(&mut volume, 0..=DEFAULT_VOLUME).text("Effect Volume"))
-        .changed()
-      {
-        engine.set_sound_volume(volume);
-      }
-      #[cfg(feature = "music")]
-      {
-        let mut music = engine.music_enabled();
-        if checkbox(ui, &mut music, "Music", Some(engine)) {
-          engine.toggle_music();
-        }
-        if music {
-          if checkbox(
-            ui,
-            &mut engine.dramatic_enabled(),
-            "Dramatic Music",
-            Some(engine),
-          ) {
-            engine.toggle_dramatic();
-          }
-          let mut volume = engine.get_music_volume();
-          if ui
-            .add(Slider::new(&mut volume, 0..=DEFAULT_VOLUME).text("Music Volume"))
-            .changed()
-          {
-            engine.set_music_volume(volume);
-          }
-        }
-      }
+    let mut low_time = gui.config.get_low_time();
+    if ui
+      .add(Slider::new(&mut low_time, 0..=120).text("Low time warning (s)"))
+      .changed()
+    {
+      gui.config.set_low_time(low_time);
     }
   }
   if checkbox(
@@ -873,6 +1518,54 @@ fn draw_settings(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
   ) {
     gui.config.toggle_evalbar();
   }
+  if gui.config.get_evalbar()
+    && checkbox(
+      ui,
+      &mut gui.config.get_normalize_eval(),
+      "Show evaluation in pawns of the variant",
+      #[cfg(feature = "sound")]
+      gui.audio_engine.as_mut(),
+    )
+  {
+    gui.config.toggle_normalize_eval();
+  }
+  if checkbox(
+    ui,
+    &mut gui.config.get_pv_preview(),
+    "Show engine's thoughts",
+    #[cfg(feature = "sound")]
+    gui.audio_engine.as_mut(),
+  ) {
+    gui.config.toggle_pv_preview();
+  }
+  if checkbox(
+    ui,
+    &mut gui.config.get_instant_move(),
+    "Engine moves instantly when the move is obvious",
+    #[cfg(feature = "sound")]
+    gui.audio_engine.as_mut(),
+  ) {
+    gui.config.toggle_instant_move();
+  }
+  if checkbox(
+    ui,
+    &mut gui.config.get_ponder(),
+    "Engine thinks on your time",
+    #[cfg(feature = "sound")]
+    gui.audio_engine.as_mut(),
+  ) {
+    gui.config.toggle_ponder();
+  }
+  if checkbox(
+    ui,
+    &mut gui.config.get_premove(),
+    "Enter moves on the opponent's turn",
+    #[cfg(feature = "sound")]
+    gui.audio_engine.as_mut(),
+  ) {
+    gui.config.toggle_premove();
+  }
+  keybindings::draw_edit(gui, ui);
   //Currently non-functional due to https://github.com/emilk/egui/issues/2641
   //if gui.config.settings_changed() && ui.button("Reset all").clicked() {
   //  gui.config.reset_all(ctx);
@@ -883,39 +1576,26 @@ fn draw_settings(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui) {
 
 fn draw_game_sidebar(gui: &mut LibertyChessGUI, ui: &mut Ui, mut gamestate: Box<Board>) {
   menu_button(gui, ui);
-  if ui.button("Flip board").clicked() || ui.input(|reader| reader.key_released(Key::F)) {
+  if ui.button("Flip board").clicked() {
     gui.flipped = !gui.flipped;
   }
   if !gui.undo.is_empty() && ui.button("Undo").clicked() {
-    let gamestate = gui.undo.pop().expect("Scrodinger's vector");
-    #[cfg(feature = "music")]
-    if let Some(ref mut player) = gui.audio_engine {
-      player.set_dramatic(get_dramatic(&gamestate));
-    }
-    if gui.player.is_none() && gui.config.get_autoflip() {
-      gui.flipped = !gamestate.to_move();
-    }
-    gui.screen = Screen::Game(Box::new(gamestate));
-    if let Some((player, _)) = &mut gui.player {
-      player.cancel_move();
-    }
-    if let Some((player, bestmove)) = &mut gui.kibbutz {
-      player.cancel_move();
-      *bestmove = None;
-    }
-    #[cfg(feature = "clock")]
-    if let Some(clock) = &mut gui.clock {
-      if gui.player.is_none() {
-        clock.switch_clocks();
-      } else if clock.is_paused() {
-        clock.toggle_pause();
+    undo(gui);
+  }
+  if can_end_game(gui) {
+    ui.horizontal(|ui| {
+      if ui.button("Offer draw").clicked() {
+        offer_draw(gui);
       }
-    };
+      if ui.button("Resign").clicked() {
+        resign(gui);
+      }
+    });
   }
 
   #[cfg(feature = "clock")]
   if let Some(clock) = &mut gui.clock {
-    if gamestate.state() == Gamestate::InProgress && !clock.is_flagged() {
+    if gamestate.state() == Gamestate::InProgress && !clock.is_flagged() && gui.outcome.is_none() {
       let text = if clock.is_paused() {
         "Unpause"
       } else {
@@ -942,7 +1622,7 @@ fn draw_game_sidebar(gui: &mut LibertyChessGUI, ui: &mut Ui, mut gamestate: Box<
       });
     if ui.button("Promote").clicked() {
       gamestate.promote(gui.promotion);
-      if let Some((PlayerData::Multiplayer(ref interface), _)) = gui.player {
+      if let Some((PlayerData::Multiplayer(ref mut interface), _)) = gui.player {
         interface.play_move(gamestate.last_move.expect("Missing last move"));
         gui.undo.clear();
       }
@@ -954,7 +1634,8 @@ fn draw_game_sidebar(gui: &mut LibertyChessGUI, ui: &mut Ui, mut gamestate: Box<
       }
       #[cfg(feature = "clock")]
       if let Some(clock) = &mut gui.clock {
-        clock.update_status(&gamestate);
+        let time = clock.update_status(&gamestate);
+        record_move(&mut gui.move_times, &gamestate, time);
       }
     }
   }
@@ -965,6 +1646,112 @@ fn draw_game_sidebar(gui: &mut LibertyChessGUI, ui: &mut Ui, mut gamestate: Box<
     ui.output_mut(|o| o.copied_text = get_fen(gui));
   }
 
+  // the move list gets its own panel when the sidebar is always shown
+  if gui.config.touch() {
+    draw_move_list(gui, ui);
+  }
+
+  // annotate the game and save it
+  let reviewing = can_review(gui);
+  if let Some(game) = &mut gui.game {
+    let size = f32::from(gui.config.get_text_size());
+    game.draw_annotations(ui, size);
+    let mut target = None;
+    if reviewing {
+      ui.horizontal_top(|ui| {
+        if game.in_variation() {
+          if ui.button("Promote variation").clicked() {
+            game.promote_variation();
+          }
+          if ui.button("Delete variation").clicked() {
+            target = game.delete_variation();
+          }
+        }
+      });
+      // watch the game play out
+      let (line, mut position) = game.line();
+      if line.len() > 1 {
+        let text = if gui.playback.playing {
+          "Pause"
+        } else {
+          "Play"
+        };
+        if ui.button(text).clicked() {
+          gui.playback.toggle();
+        }
+        if ui
+          .add(
+            Slider::new(&mut position, 0..=line.len() - 1)
+              .text("Move")
+              .show_value(false),
+          )
+          .changed()
+        {
+          target = Some(line[position]);
+        }
+        ui.add(Slider::new(&mut gui.playback.delay, 0.1..=5.0).text("Seconds per move"));
+        checkbox(
+          ui,
+          &mut gui.playback.flip,
+          "Flip to the side to move",
+          #[cfg(feature = "sound")]
+          gui.audio_engine.as_mut(),
+        );
+        checkbox(
+          ui,
+          &mut gui.playback.pause_on_events,
+          "Pause on captures and checks",
+          #[cfg(feature = "sound")]
+          gui.audio_engine.as_mut(),
+        );
+        target = gui.playback.advance(game, ui.ctx()).or(target);
+      }
+    }
+    if let (true, Some(index)) = (reviewing, target) {
+      go_to_move(gui, index);
+      return;
+    }
+  }
+  #[cfg(not(target_arch = "wasm32"))]
+  {
+    let size = f32::from(gui.config.get_text_size());
+    label_text_edit(ui, size * 8.0, &mut gui.pgn_path, "Game file");
+    ui.horizontal_top(|ui| {
+      if let Some(game) = &gui.game {
+        if ui.button("Save game").clicked() {
+          gui.message = Some(match std::fs::write(&gui.pgn_path, game.to_pgn()) {
+            Ok(()) => format!("Saved game to {}", gui.pgn_path),
+            Err(error) => format!("Could not write {}: {error}", gui.pgn_path),
+          });
+        }
+      }
+      if gui.player.is_none() && ui.button("Load game").clicked() {
+        load_game(gui);
+      }
+    });
+    draw_gif_export(gui, ui, &gamestate);
+  }
+
+  // check an engine generates the same moves as the GUI
+  #[cfg(not(target_arch = "wasm32"))]
+  if gui.config.get_advanced() {
+    let size = f32::from(gui.config.get_text_size());
+    label_text_edit(ui, size * 8.0, &mut gui.verify_path, "Engine to verify");
+    if let Some(check) = &mut gui.verify {
+      if let Some(report) = check.poll() {
+        gui.message = Some(report);
+        gui.verify = None;
+      } else {
+        ui.label("Verifying...");
+      }
+    } else if ui.button("Verify position").clicked() {
+      match PositionCheck::new(&gui.verify_path, &gamestate, ui.ctx()) {
+        Ok(check) => gui.verify = Some(check),
+        Err(error) => gui.message = Some(error),
+      }
+    }
+  }
+
   if matches!(gui.player, Some((PlayerData::Multiplayer(..), _))) {
     if gamestate.friendly_fire {
       checkbox(
@@ -975,6 +1762,31 @@ fn draw_game_sidebar(gui: &mut LibertyChessGUI, ui: &mut Ui, mut gamestate: Box<
         gui.audio_engine.as_mut(),
       );
     }
+    // the server can play several games at once on the same connection
+    let mut switch = None;
+    if let Some((PlayerData::Multiplayer(ref interface), _)) = gui.player {
+      if !interface.games.is_empty() {
+        ui.label(format!("Showing game {}", interface.game + 1));
+        ui.horizontal_wrapped(|ui| {
+          for (index, game) in interface.games.iter().enumerate() {
+            if game.position.is_some() {
+              let text = if game.to_play() {
+                format!("Game {} (your move)", game.id + 1)
+              } else {
+                format!("Game {}", game.id + 1)
+              };
+              if ui.button(text).clicked() {
+                switch = Some(index);
+              }
+            }
+          }
+        });
+      }
+    }
+    if let Some(index) = switch {
+      switch_online_game(gui, &gamestate, index);
+      return;
+    }
   } else {
     gui.safety_mode = false;
   }
@@ -987,15 +1799,137 @@ fn draw_game_sidebar(gui: &mut LibertyChessGUI, ui: &mut Ui, mut gamestate: Box<
       #[cfg(feature = "sound")]
       gui.audio_engine.as_mut(),
     ) {
-      gui.kibbutz = match gui.kibbutz {
-        Some(_) => None,
-        None => Some((EngineInterface::new(HASH_SIZE, ui.ctx()), None)),
+      toggle_kibbutz(gui, ui.ctx());
+    }
+    // long analyses can be saved and carried on later
+    let size = f32::from(gui.config.get_text_size());
+    label_text_edit(ui, size * 8.0, &mut gui.analysis_path, "Analysis file");
+    ui.horizontal_top(|ui| {
+      if let Some((engine, _)) = &mut gui.kibbutz {
+        if ui.button("Save analysis").clicked() {
+          gui.message = Some(match engine.save_session(&gui.analysis_path) {
+            Ok(()) => format!("Saved analysis to {}", gui.analysis_path),
+            Err(error) => error,
+          });
+        }
+      }
+      if ui.button("Resume analysis").clicked() {
+        resume_analysis(gui, ui.ctx());
+      }
+    });
+    // options can change mid-analysis, the search pauses and resumes with its progress kept
+    ui.horizontal_top(|ui| {
+      ui.label("Hash (MB)");
+      raw_text_edit(ui, size * 4.0, &mut gui.kibbutz_hash);
+      ui.label("Lines");
+      raw_text_edit(ui, size * 2.0, &mut gui.kibbutz_lines);
+      if let Some((engine, _)) = &mut gui.kibbutz {
+        if ui.button("Apply").clicked() {
+          engine.set_hash_size(gui.kibbutz_hash.get_value());
+          engine.set_multipv(gui.kibbutz_lines.get_value());
+        }
+      }
+    });
+    if let Some((engine, _)) = &gui.kibbutz {
+      let lines = engine.lines(&gamestate);
+      gui
+        .kibbutz_line
+        .resize_with(lines.len().max(1), LineCache::default);
+      if lines.len() > 1 {
+        // every line of a MultiPV search, best first
+        ui.label("Engine lines:");
+        ScrollArea::vertical()
+          .id_source("Engine lines")
+          .max_height(size * 15.0)
+          .show(ui, |ui| {
+            for (i, (line, cache)) in lines.iter().zip(&mut gui.kibbutz_line).enumerate() {
+              ui.label(format!(
+                "{}. {} (depth {})",
+                i + 1,
+                candidate_score(&gamestate, line.score),
+                line.depth
+              ));
+              let glyphs = cache.get(&gamestate, &line.pv);
+              InlineIcons::new(&gui.images, &mut gui.icon_renders).line(ui, glyphs);
+            }
+          });
+      } else if !engine.pv().is_empty() {
+        ui.label("Engine line:");
+        let glyphs = gui.kibbutz_line[0].get(&gamestate, engine.pv());
+        InlineIcons::new(&gui.images, &mut gui.icon_renders).line(ui, glyphs);
+      }
+    }
+    // guide the analysis towards moves the user cares about
+    if let Some((engine, _)) = &mut gui.kibbutz {
+      ui.label("Candidate moves:");
+      ui.horizontal_wrapped(|ui| {
+        for mv in engine.candidates().to_vec() {
+          if ui
+            .button(mv.to_string())
+            .on_hover_text("Click to remove")
+            .clicked()
+          {
+            engine.toggle_candidate(mv);
+          }
+        }
+      });
+      ComboBox::from_id_source("Candidate")
+        .selected_text("Add candidate")
+        .show_ui(ui, |ui| {
+          for mv in gamestate
+            .generate_legal()
+            .into_iter()
+            .filter_map(|board| board.last_move)
+          {
+            if !engine.candidates().contains(&mv) && ui.button(mv.to_string()).clicked() {
+              engine.toggle_candidate(mv);
+            }
+          }
+        });
+      if !engine.candidates().is_empty()
+        && checkbox(
+          ui,
+          &mut engine.only_candidates(),
+          "Only search candidates",
+          #[cfg(feature = "sound")]
+          gui.audio_engine.as_mut(),
+        )
+      {
+        engine.toggle_only_candidates();
       }
     }
+    if let Some(problem) = gui
+      .kibbutz
+      .as_ref()
+      .and_then(|(engine, _)| engine.connection_status())
+    {
+      ui.label(problem);
+    }
   } else {
     gui.kibbutz = None;
   }
 
+  // preview the line the engine is considering
+  if gui.config.get_pv_preview() {
+    if let Some((player, side)) = &gui.player {
+      if *side == gamestate.to_move() && !player.pv().is_empty() {
+        let pv = player.pv().to_vec();
+        draw_preview(gui, ui, &gamestate, &pv);
+      }
+    }
+  }
+
+  // show whether the opponent is working on a move
+  if let Some((player, side)) = &gui.player {
+    if let Some(status) = player.status(*side == gamestate.to_move()) {
+      ui.label(status);
+    }
+    // rather than waiting forever for a move that isn't coming
+    if let Some(problem) = player.connection_status() {
+      ui.label(problem);
+    }
+  }
+
   // if the game is over, report the reason
   let state = gamestate.state();
   ui.label(match state {
@@ -1017,21 +1951,376 @@ fn draw_game_sidebar(gui: &mut LibertyChessGUI, ui: &mut Ui, mut gamestate: Box<
       }
     }
     Gamestate::Material => "Draw by insufficient material",
-    Gamestate::InProgress => {
-      if gamestate.to_move() {
-        "White to move"
-      } else {
-        "Black to move"
+    Gamestate::InProgress => match gui.outcome {
+      Some(Outcome::Resignation(true)) => "White resigns",
+      Some(Outcome::Resignation(false)) => "Black resigns",
+      Some(Outcome::DrawAgreed) => "Draw agreed",
+      None => {
+        if gamestate.to_move() {
+          "White to move"
+        } else {
+          "Black to move"
+        }
       }
-    }
+    },
   });
   if let Some(message) = &gui.message {
     ui.label(message);
   }
+
+  // track the score of a practice session against an engine
+  if gui.session.is_some() {
+    let result = match state {
+      Gamestate::Checkmate(winner) | Gamestate::Elimination(winner) => Some(Some(winner)),
+      Gamestate::Stalemate | Gamestate::FiftyMove | Gamestate::Repetition | Gamestate::Material => {
+        Some(None)
+      }
+      Gamestate::InProgress => gui.outcome.map(Outcome::winner),
+    };
+    #[cfg(feature = "clock")]
+    let result = result.or_else(|| {
+      gui
+        .clock
+        .as_ref()
+        .filter(|clock| clock.is_flagged())
+        .map(|clock| Some(!clock.to_move()))
+    });
+    if let Some(session) = &mut gui.session {
+      if let Some(winner) = result {
+        session.record(winner);
+      }
+      ui.label(session.to_string());
+    }
+    if result.is_some() && ui.button("Rematch").clicked() {
+      let ctx = ui.ctx().clone();
+      rematch(gui, &ctx);
+      return;
+    }
+  }
+
+  // show time usage, including the move being thought about
+  #[cfg(feature = "clock")]
+  {
+    let mut move_times = gui.move_times.clone();
+    if let Some(clock) = &mut gui.clock {
+      if state == Gamestate::InProgress
+        && gui.outcome.is_none()
+        && !clock.is_flagged()
+        && !clock.is_paused()
+      {
+        record_current_move(&mut move_times, &gamestate, clock.move_time());
+      }
+    }
+    let size = f32::from(gui.config.get_text_size());
+    draw_move_times(ui, &move_times, size);
+  }
+}
+
+// The moves played so far, clicking on one goes back to that position
+fn draw_move_list(gui: &mut LibertyChessGUI, ui: &mut Ui) {
+  let reviewing = can_review(gui);
+  let Some(game) = &gui.game else {
+    return;
+  };
+  let size = f32::from(gui.config.get_text_size());
+  let mut icons = InlineIcons::new(&gui.images, &mut gui.icon_renders);
+  let mut target = ScrollArea::vertical()
+    .id_source("Moves")
+    .max_height(size * 12.0)
+    .stick_to_bottom(true)
+    .show(ui, |ui| game.draw_tree(ui, &mut icons))
+    .inner;
+  if reviewing {
+    ui.horizontal_top(|ui| {
+      if let Some(previous) = game.previous() {
+        if ui.button("<").clicked() {
+          target = Some(previous);
+        }
+      }
+      if let Some(next) = game.next() {
+        if ui.button(">").clicked() {
+          target = Some(next);
+        }
+      }
+    });
+    if let Some(index) = target {
+      go_to_move(gui, index);
+    }
+  }
 }
 
 // general helper functions
 
+// moving around the game is only possible when nobody else is playing moves
+fn can_review(gui: &LibertyChessGUI) -> bool {
+  let reviewing = gui.player.is_none();
+  #[cfg(feature = "clock")]
+  let reviewing = reviewing && gui.clock.is_none();
+  reviewing
+}
+
+// show a position from the game record, keeping the moves after it
+fn go_to_move(gui: &mut LibertyChessGUI, index: usize) {
+  let Some(game) = &mut gui.game else {
+    return;
+  };
+  game.go_to(index);
+  let board = game.board().clone();
+  gui.undo = game.history();
+  let to_move = board.to_move();
+  go_to_position(gui, board);
+  if gui.playback.flip {
+    gui.flipped = !to_move;
+  }
+}
+
+// go back to the previous position
+fn undo(gui: &mut LibertyChessGUI) {
+  let gamestate = gui.undo.pop().expect("Scrodinger's vector");
+  gui.premove = None;
+  // taking a move back carries on a game that was resigned or drawn
+  gui.outcome = None;
+  #[cfg(feature = "music")]
+  if let Some(ref mut player) = gui.audio_engine {
+    player.set_dramatic(get_dramatic(&gamestate));
+  }
+  if gui.player.is_none() && gui.config.get_autoflip() {
+    gui.flipped = !gamestate.to_move();
+  }
+  gui.screen = Screen::Game(Box::new(gamestate));
+  if let Some((player, _)) = &mut gui.player {
+    player.cancel_move();
+  }
+  if let Some((player, bestmove)) = &mut gui.kibbutz {
+    player.cancel_move();
+    *bestmove = None;
+  }
+  #[cfg(feature = "clock")]
+  if let Some(clock) = &mut gui.clock {
+    if gui.player.is_none() {
+      clock.switch_clocks();
+    } else if clock.is_paused() {
+      clock.toggle_pause();
+    }
+  };
+  #[cfg(feature = "clock")]
+  if let Screen::Game(ref gamestate) = gui.screen {
+    undo_moves(&mut gui.move_times, gamestate);
+  }
+}
+
+// whether the game is still going and can be resigned or drawn
+// online games are ended by the server, which has no way to offer a draw or resign yet
+fn can_end_game(gui: &LibertyChessGUI) -> bool {
+  let Screen::Game(ref board) = gui.screen else {
+    return false;
+  };
+  let in_progress = board.state() == Gamestate::InProgress && gui.outcome.is_none();
+  #[cfg(feature = "clock")]
+  let in_progress = in_progress && !gui.clock.as_ref().is_some_and(Clock::is_flagged);
+  in_progress && !matches!(gui.player, Some((PlayerData::Multiplayer(_), _)))
+}
+
+// the user resigns, or the side to move when nobody else is playing
+fn resign(gui: &mut LibertyChessGUI) {
+  let Screen::Game(ref board) = gui.screen else {
+    return;
+  };
+  let side = match gui.player {
+    Some((_, engine_side)) => !engine_side,
+    None => board.to_move(),
+  };
+  end_game(gui, Outcome::Resignation(side));
+}
+
+// engines accept a draw when their last score doesn't favour them
+fn offer_draw(gui: &mut LibertyChessGUI) {
+  let accepted = match gui.player {
+    Some((_, engine_side)) => gui.eval.is_some_and(|(score, _)| {
+      // the eval is positive when Black is better
+      let score = if engine_side { -score } else { score };
+      score <= Score::Centipawn(0)
+    }),
+    None => true,
+  };
+  if accepted {
+    end_game(gui, Outcome::DrawAgreed);
+  } else {
+    gui.message = Some("Draw offer declined".to_owned());
+  }
+}
+
+fn end_game(gui: &mut LibertyChessGUI, outcome: Outcome) {
+  gui.outcome = Some(outcome);
+  gui.selected = None;
+  gui.drag = None;
+  gui.premove = None;
+  if let Some((player, _)) = &mut gui.player {
+    player.cancel_move();
+    player.stop_pondering();
+  }
+  #[cfg(feature = "clock")]
+  if let Some(clock) = &mut gui.clock {
+    if !clock.is_paused() {
+      clock.toggle_pause();
+    }
+  }
+}
+
+// load a game with its annotations from a PGN file
+#[cfg(not(target_arch = "wasm32"))]
+fn load_game(gui: &mut LibertyChessGUI) {
+  let game = match std::fs::read_to_string(&gui.pgn_path) {
+    Ok(pgn) => GameTree::from_pgn(&pgn),
+    Err(error) => Err(format!("Could not load {}: {error}", gui.pgn_path)),
+  };
+  match game {
+    Ok(game) => {
+      let board = game.board().clone();
+      clear_game(gui);
+      gui.undo = game.history();
+      gui.game = Some(game);
+      if gui.config.get_autoflip() {
+        gui.flipped = !board.to_move();
+      }
+      gui.screen = Screen::Game(Box::new(board));
+      gui.message = Some(format!("Loaded game from {}", gui.pgn_path));
+    }
+    Err(error) => gui.message = Some(error),
+  }
+}
+
+// share the game so far as an animation
+#[cfg(not(target_arch = "wasm32"))]
+fn draw_gif_export(gui: &mut LibertyChessGUI, ui: &mut Ui, gamestate: &Board) {
+  let size = f32::from(gui.config.get_text_size());
+  label_text_edit(ui, size * 8.0, &mut gui.gif.path, "GIF file");
+  ui.add(Slider::new(&mut gui.gif.delay, 0.1..=5.0).text("Seconds per move"));
+  let theme_name = gui
+    .gif
+    .theme
+    .map_or("Current".to_owned(), |theme| theme.show());
+  ComboBox::from_id_source("GIF theme")
+    .selected_text(format!("GIF theme: {theme_name}"))
+    .show_ui(ui, |ui| {
+      ui.selectable_value(&mut gui.gif.theme, None, "Current");
+      populate_dropdown_transform(ui, &mut gui.gif.theme, |theme| Some(Theme::Preset(theme)));
+    });
+  if let Some(job) = &mut gui.gif_job {
+    if let Some(report) = job.poll() {
+      gui.message = Some(report);
+      gui.gif_job = None;
+    } else {
+      ui.add(ProgressBar::new(job.progress()).text("Exporting GIF..."));
+    }
+  } else if ui.button("Export GIF").clicked() {
+    let mut positions = gui.undo.clone();
+    positions.push(gamestate.clone());
+    let colours = gui
+      .gif
+      .theme
+      .unwrap_or_else(|| gui.config.get_theme())
+      .board_colours();
+    gui.gif_job = Some(GifJob::new(
+      &gui.gif,
+      &positions,
+      colours,
+      gui.flipped,
+      ui.ctx(),
+    ));
+  }
+}
+
+// show another position from the game being reviewed
+fn go_to_position(gui: &mut LibertyChessGUI, board: Board) {
+  #[cfg(feature = "music")]
+  if let Some(ref mut player) = gui.audio_engine {
+    player.set_dramatic(get_dramatic(&board));
+  }
+  if gui.config.get_autoflip() {
+    gui.flipped = !board.to_move();
+  }
+  if let Some((player, _)) = &mut gui.player {
+    player.stop_pondering();
+  }
+  if let Some((player, bestmove)) = &mut gui.kibbutz {
+    player.cancel_move();
+    *bestmove = None;
+  }
+  gui.selected = None;
+  gui.screen = Screen::Game(Box::new(board));
+}
+
+// Show another game from the same server, keeping the current one to come back to
+fn switch_online_game(gui: &mut LibertyChessGUI, board: &Board, index: usize) {
+  let Some((PlayerData::Multiplayer(ref mut interface), ref mut side)) = gui.player else {
+    return;
+  };
+  #[cfg(feature = "clock")]
+  std::mem::swap(&mut gui.clock, &mut interface.games[index].clock);
+  gui.message = interface.games[index].notice.take();
+  let (position, new_side) = interface.switch_game(index, Some(board.clone()), *side);
+  *side = new_side;
+  if gui.config.get_opponentflip() {
+    gui.flipped = new_side;
+  }
+  gui.selected = None;
+  gui.drag = None;
+  gui.premove = None;
+  gui.undo.clear();
+  gui.eval = None;
+  gui.eval_pv = None;
+  if let Some(position) = position {
+    gui.screen = Screen::Game(Box::new(position));
+  }
+}
+
+fn new_kibbutz(gui: &LibertyChessGUI, ctx: &Context) -> (EngineInterface, Option<Move>) {
+  let mut engine = EngineInterface::new(gui.kibbutz_hash.get_value(), None, ctx);
+  engine.set_multipv(gui.kibbutz_lines.get_value());
+  (engine, None)
+}
+
+fn toggle_kibbutz(gui: &mut LibertyChessGUI, ctx: &Context) {
+  gui.kibbutz = match gui.kibbutz {
+    Some(_) => None,
+    None => Some(new_kibbutz(gui, ctx)),
+  }
+}
+
+// load a saved analysis and carry it on with the kibbutz
+fn resume_analysis(gui: &mut LibertyChessGUI, ctx: &Context) {
+  if gui.kibbutz.is_none() {
+    gui.kibbutz = Some(new_kibbutz(gui, ctx));
+  }
+  let Some((engine, bestmove)) = &mut gui.kibbutz else {
+    return;
+  };
+  let session = match engine.load_session(&gui.analysis_path) {
+    Ok(session) => session,
+    Err(error) => {
+      gui.message = Some(error);
+      return;
+    }
+  };
+  match Board::new(&session.fen) {
+    Ok(board) => {
+      let result = session.latest();
+      *bestmove = result.and_then(|result| result.pv.first().copied());
+      gui.eval = result.map(|result| (white_score(&board, result.score), result.depth));
+      gui.eval_pv = result.map(|result| (board.hash(), result.pv.clone()));
+      gui.undo.clear();
+      gui.selected = None;
+      if gui.config.get_autoflip() {
+        gui.flipped = !board.to_move();
+      }
+      gui.screen = Screen::Game(Box::new(board));
+      gui.message = Some(format!("Resumed analysis at depth {}", session.depth()));
+    }
+    Err(error) => gui.message = Some(error.to_string()),
+  }
+}
+
 #[cfg(feature = "clock")]
 fn handle_thinking_engine(gui: &mut LibertyChessGUI, ui: &mut Ui, size: f32) {
   let thinking_engine = if let Some(player) = &gui.alternate_player {
@@ -1083,7 +2372,14 @@ fn main() {
       .start(
         "Liberty Chess", // hardcode it
         web_options,
-        Box::new(|cc| Box::new(LibertyChessGUI::new(cc))),
+        Box::new(|cc| {
+          Box::new(LibertyChessGUI::new(
+            cc,
+            None,
+            #[cfg(feature = "recording")]
+            None,
+          ))
+        }),
       )
       .await
       .expect("failed to start eframe");
@@ -1094,6 +2390,8 @@ fn main() {
 fn main() {
   use eframe::egui::ViewportBuilder;
 
+  let reports = ulci::report::install_panic_hook("liberty_chess_gui", env!("CARGO_PKG_VERSION"));
+
   let size = helpers::ICON_SIZE;
   let mut pixmap = Pixmap::new(size, size).unwrap();
   render(
@@ -1120,7 +2418,14 @@ fn main() {
   eframe::run_native(
     "Liberty Chess",
     options,
-    Box::new(|cc| Ok(Box::new(LibertyChessGUI::new(cc)))),
+    Box::new(|cc| {
+      Ok(Box::new(LibertyChessGUI::new(
+        cc,
+        Some(reports),
+        #[cfg(feature = "recording")]
+        Recording::from_args(),
+      )))
+    }),
   )
   .expect("Failed to load Liberty Chess");
 }
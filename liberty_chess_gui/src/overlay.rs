@@ -0,0 +1,111 @@
+use liberty_chess::moves::Move;
+use liberty_chess::{Board, Gamestate, Hash};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread::spawn;
+use ulci::Score;
+
+// port the game state is served on
+const PORT: u16 = 17380;
+
+fn escape(string: &str) -> String {
+  let mut result = String::with_capacity(string.len());
+  for c in string.chars() {
+    match c {
+      '"' => result += "\\\"",
+      '\\' => result += "\\\\",
+      '\n' => result += "\\n",
+      c if c.is_control() => result += &format!("\\u{:04x}", u32::from(c)),
+      c => result.push(c),
+    }
+  }
+  result
+}
+
+fn state_name(state: Gamestate) -> &'static str {
+  match state {
+    Gamestate::InProgress => "in progress",
+    Gamestate::Checkmate(true) => "white wins by checkmate",
+    Gamestate::Checkmate(false) => "black wins by checkmate",
+    Gamestate::Elimination(true) => "white wins by elimination",
+    Gamestate::Elimination(false) => "black wins by elimination",
+    Gamestate::Stalemate => "draw by stalemate",
+    Gamestate::FiftyMove => "draw by 50 move rule",
+    Gamestate::Repetition => "draw by 3-fold repetition",
+    Gamestate::Material => "draw by insufficient material",
+  }
+}
+
+// send the latest state to one client
+fn respond(mut stream: TcpStream, state: &Mutex<String>) {
+  // the request itself doesn't matter, but it needs to be read before responding
+  let mut reader = BufReader::new(&stream);
+  let mut line = String::new();
+  while reader.read_line(&mut line).is_ok_and(|read| read > 2) {
+    line.clear();
+  }
+  let body = state.lock().map(|state| state.clone()).unwrap_or_default();
+  write!(
+    stream,
+    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nAccess-Control-Allow-Origin: *\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+    body.len()
+  )
+  .ok();
+}
+
+// Serves the current game as JSON on localhost so stream overlays can show it
+pub struct Overlay {
+  state: Arc<Mutex<String>>,
+  // the position and eval last served, to avoid rebuilding the JSON every frame
+  last: Option<(Hash, Option<(Score, u16)>)>,
+}
+
+impl Overlay {
+  pub fn start() -> std::io::Result<Self> {
+    let listener = TcpListener::bind(("127.0.0.1", PORT))?;
+    let state = Arc::new(Mutex::new("{}".to_owned()));
+    let server_state = state.clone();
+    spawn(move || {
+      for stream in listener.incoming().map_while(Result::ok) {
+        respond(stream, &server_state);
+      }
+    });
+    Ok(Self { state, last: None })
+  }
+
+  // Update the state served if the position or eval has changed
+  pub fn update(
+    &mut self,
+    board: &Board,
+    eval: Option<(Score, u16)>,
+    moves: impl FnOnce() -> Vec<Move>,
+  ) {
+    let key = (board.hash(), eval);
+    if self.last == Some(key) {
+      return;
+    }
+    self.last = Some(key);
+    // positive scores from the engine are good for black, the overlay uses the usual convention
+    let eval = match eval {
+      Some((Score::Centipawn(score), depth)) => format!("{{\"cp\":{},\"depth\":{depth}}}", -score),
+      Some((Score::Win(moves), depth)) => format!("{{\"mate\":-{moves},\"depth\":{depth}}}"),
+      Some((Score::Loss(moves), depth)) => format!("{{\"mate\":{moves},\"depth\":{depth}}}"),
+      None => "null".to_owned(),
+    };
+    let moves: Vec<String> = moves()
+      .iter()
+      .map(|mv| format!("\"{}\"", mv.to_string()))
+      .collect();
+    let json = format!(
+      "{{\"fen\":\"{}\",\"to_move\":\"{}\",\"state\":\"{}\",\"eval\":{eval},\"moves\":[{}]}}",
+      escape(&board.to_string()),
+      if board.to_move() { "white" } else { "black" },
+      state_name(board.state()),
+      moves.join(",")
+    );
+    if let Ok(mut state) = self.state.lock() {
+      *state = json;
+    }
+  }
+}
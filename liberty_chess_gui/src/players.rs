@@ -1,33 +1,91 @@
+use crate::engine_options::{parse_value, EngineOptions};
 use crate::helpers::NumericalInput;
 use crate::{switch_screen, LibertyChessGUI, Screen, MAX_TIME};
 use eframe::egui::Context;
 use enum_iterator::Sequence;
+use liberty_chess::clock::Millis;
 use liberty_chess::moves::Move;
 use liberty_chess::parsing::from_chars;
 use liberty_chess::positions::get_startpos;
 use liberty_chess::threading::CompressedBoard;
 use liberty_chess::{Board, Gamestate, ALL_PIECES};
-use oxidation::glue::process_position;
+use oxidation::glue::{process_position, Session as AnalysisSession, HASH_NAME, MULTI_PV_NAME};
+use oxidation::lite::{LiteEngine, LITE_MAX_THINK_TIME, LITE_THINK_TIME};
 use oxidation::parameters::DEFAULT_PARAMETERS;
 use oxidation::search::SEARCH_PARAMETERS;
-use oxidation::{mvvlva_move, random_move, State, HASH_SIZE, VERSION_NUMBER};
+use oxidation::strength::{DEFAULT_ELO, MAX_ELO, MIN_ELO};
+use oxidation::{
+  mvvlva_move_with_temperature, random_move_with_rng, State, HASH_SIZE, VERSION_NUMBER,
+};
 use rand::{thread_rng, Rng};
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaChaRng;
+use std::cell::Cell;
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::io::{BufReader, ErrorKind, Write};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::mem::{discriminant, swap};
 use std::net::{SocketAddr, TcpStream};
 use std::process::{Command, Stdio};
 use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
 use std::thread::spawn;
 use std::time::Duration;
 use ulci::client::{startup, Message};
-use ulci::server::{startup_server, AnalysisRequest, Request, UlciResult};
-use ulci::{ClientInfo, Limits as OtherLimits, Score, SearchTime, SupportedFeatures, V1Features};
+use ulci::games::{ChannelInput, GameOutput, Games};
+use ulci::server::{startup_server, AnalysisRequest, Keepalive, Request, UlciResult};
+use ulci::{
+  AnalysisResult, ClientInfo, Limits as OtherLimits, OptionValue, Score, SearchTime,
+  SupportedFeatures, UlciOption, V1Features,
+};
 
 #[cfg(feature = "clock")]
 use crate::clock::convert;
 #[cfg(feature = "clock")]
 use liberty_chess::clock::Clock;
 
+// maximum thinking delay for simple engines in ms
+const MAX_DELAY: u64 = 10000;
+
+// limits for hash sizes picked from the time control in MB
+const AUTO_HASH_MIN: usize = 16;
+const AUTO_HASH_MAX: usize = 1024;
+// rough speed of the built-in engine, used to estimate thinking time for node limits
+const NODES_PER_MS: u128 = 1000;
+
+// how long the opponent's settings have to stay the same before a new engine is launched in s
+const EDIT_DELAY: f64 = 0.5;
+
+// Records messages that couldn't be delivered so the GUI can report them instead of hanging
+#[derive(Default)]
+pub struct Diagnostics {
+  failed_sends: Cell<usize>,
+  disconnected: Cell<bool>,
+}
+
+impl Diagnostics {
+  // Record whether a message was delivered
+  pub fn record<T, E>(&self, result: Result<T, E>) -> Option<T> {
+    if result.is_err() {
+      self.failed_sends.set(self.failed_sends.get() + 1);
+    }
+    result.ok()
+  }
+
+  // Record that the other end has hung up
+  pub fn disconnect(&self) {
+    self.disconnected.set(true);
+  }
+
+  // Describe what has gone wrong, if anything
+  pub fn status(&self, problem: &str) -> Option<String> {
+    match (self.disconnected.get(), self.failed_sends.get()) {
+      (false, 0) => None,
+      (true, 0) => Some(problem.to_owned()),
+      (_, failed) => Some(format!("{problem}, {failed} messages not delivered")),
+    }
+  }
+}
+
 #[derive(Eq, PartialEq)]
 pub enum SearchType {
   #[cfg(feature = "clock")]
@@ -71,8 +129,8 @@ impl SearchType {
     match self {
       Self::Increment(time, inc) => (
         SearchTime::Increment(
-          u128::from(time.get_value() * 60_000),
-          u128::from(inc.get_value() * 1000),
+          Millis::from_mins(time.get_value()),
+          Millis::from_secs(inc.get_value()),
         ),
         Some(convert(&[
           time.clone(),
@@ -91,8 +149,8 @@ impl SearchType {
         };
         (
           SearchTime::Increment(
-            u128::from(engine_time.get_value() * 60_000),
-            u128::from(engine_inc.get_value() * 1000),
+            Millis::from_mins(engine_time.get_value()),
+            Millis::from_secs(engine_inc.get_value()),
           ),
           Some(convert(&[
             white_time.clone(),
@@ -115,7 +173,7 @@ impl SearchType {
           time: limits
             .time
             .as_ref()
-            .map_or(u128::MAX, NumericalInput::get_value),
+            .map_or(Millis::MAX, |time| Millis::new(time.get_value())),
         }),
         None,
       ),
@@ -137,7 +195,7 @@ impl SearchType {
         time: limits
           .time
           .as_ref()
-          .map_or(u128::MAX, NumericalInput::get_value),
+          .map_or(Millis::MAX, |time| Millis::new(time.get_value())),
       }),
     }
   }
@@ -182,10 +240,15 @@ pub struct Limits {
 
 #[derive(Clone, Eq, PartialEq)]
 pub enum PlayerType {
-  RandomEngine,
-  MvvLva,
-  // parameter is hash size
-  BuiltIn(NumericalInput<usize>),
+  // parameters are thinking delay in ms and the seed to pick moves with, random if not set
+  RandomEngine(NumericalInput<u64>, Option<NumericalInput<u64>>),
+  // parameters are thinking delay in ms and temperature in centipawns
+  MvvLva(NumericalInput<u64>, NumericalInput<u32>),
+  // parameters are hash size, whether to pick it from the time control instead and the Elo to
+  // limit the strength to
+  BuiltIn(NumericalInput<usize>, bool, Option<NumericalInput<u32>>),
+  // parameters are thinking time in ms and the Elo to limit the strength to
+  Lite(NumericalInput<u128>, Option<NumericalInput<u32>>),
   External(String),
   Multiplayer(String, NumericalInput<u16>, String),
 }
@@ -193,9 +256,10 @@ pub enum PlayerType {
 impl ToString for PlayerType {
   fn to_string(&self) -> String {
     match self {
-      Self::RandomEngine => "Random Mover".to_owned(),
-      Self::MvvLva => "MVVLVA".to_owned(),
-      Self::BuiltIn(_) => format!("Oxidation v{VERSION_NUMBER}"),
+      Self::RandomEngine(..) => "Random Mover".to_owned(),
+      Self::MvvLva(..) => "MVVLVA".to_owned(),
+      Self::BuiltIn(..) => format!("Oxidation v{VERSION_NUMBER}"),
+      Self::Lite(..) => format!("Oxidation Lite v{VERSION_NUMBER}"),
       Self::External(_) => "External engine (beta)".to_owned(),
       Self::Multiplayer(..) => "Connect to server (beta)".to_owned(),
     }
@@ -203,24 +267,78 @@ impl ToString for PlayerType {
 }
 
 impl PlayerType {
+  pub fn random_engine() -> Self {
+    Self::RandomEngine(NumericalInput::new(0, 0, MAX_DELAY), None)
+  }
+
+  pub fn seed() -> NumericalInput<u64> {
+    NumericalInput::new(0, 0, u64::MAX)
+  }
+
+  pub fn mvvlva() -> Self {
+    Self::MvvLva(
+      NumericalInput::new(0, 0, MAX_DELAY),
+      NumericalInput::new(0, 0, 1000),
+    )
+  }
+
   pub fn built_in() -> Self {
-    Self::BuiltIn(NumericalInput::new(HASH_SIZE, 0, 1 << 32))
+    Self::BuiltIn(NumericalInput::new(HASH_SIZE, 0, 1 << 32), true, None)
+  }
+
+  // searches on the GUI thread, so it works on the web
+  pub fn lite() -> Self {
+    Self::Lite(
+      NumericalInput::new(LITE_THINK_TIME, 1, LITE_MAX_THINK_TIME),
+      None,
+    )
+  }
+
+  pub fn elo() -> NumericalInput<u32> {
+    NumericalInput::new(DEFAULT_ELO, MIN_ELO, MAX_ELO)
+  }
+
+  // The player with the hash size filled in if it is picked from the time control
+  fn for_search(&self, searchtime: &SearchTime) -> Self {
+    match self {
+      Self::BuiltIn(_, true, elo) => Self::BuiltIn(
+        NumericalInput::new(auto_hash_size(searchtime), 0, 1 << 32),
+        false,
+        elo.clone(),
+      ),
+      _ => self.clone(),
+    }
   }
 
   #[cfg(feature = "clock")]
   pub const fn is_thinking(&self) -> bool {
     match self {
-      Self::RandomEngine | Self::MvvLva => false,
+      Self::RandomEngine(..) | Self::MvvLva(..) | Self::Lite(..) => false,
       Self::BuiltIn(..) | Self::External(_) | Self::Multiplayer(..) => true,
     }
   }
 
   pub const fn custom_thinking_time(&self) -> bool {
     match self {
-      Self::RandomEngine | Self::MvvLva | Self::Multiplayer(..) => false,
+      Self::RandomEngine(..) | Self::MvvLva(..) | Self::Lite(..) | Self::Multiplayer(..) => false,
       Self::BuiltIn(..) | Self::External(_) => true,
     }
   }
+
+  // Whether two players would launch the same engine
+  // An automatic hash size is picked when the game starts, so it is set on the engine then instead
+  fn same_engine(&self, other: &Self) -> bool {
+    match (self, other) {
+      (Self::BuiltIn(hash_size, auto, elo), Self::BuiltIn(other, other_auto, other_elo)) => {
+        auto == other_auto
+          && (*auto || hash_size.get_value() == other.get_value())
+          && elo.as_ref().map(NumericalInput::get_value)
+            == other_elo.as_ref().map(NumericalInput::get_value)
+      }
+      (Self::External(path), Self::External(other)) => path == other,
+      _ => false,
+    }
+  }
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Sequence)]
@@ -251,23 +369,172 @@ impl PlayerColour {
   }
 }
 
+// Score of a series of games against the same opponent
+#[derive(Default)]
+pub struct Session {
+  engine_colour: bool,
+  wins: u32,
+  draws: u32,
+  losses: u32,
+  // whether the result of the current game has been counted
+  recorded: bool,
+}
+
+impl ToString for Session {
+  fn to_string(&self) -> String {
+    format!(
+      "Session score: +{} ={} -{}",
+      self.wins, self.draws, self.losses
+    )
+  }
+}
+
+impl Session {
+  pub fn new_game(&mut self, engine_colour: bool) {
+    self.engine_colour = engine_colour;
+    self.recorded = false;
+  }
+
+  pub const fn engine_colour(&self) -> bool {
+    self.engine_colour
+  }
+
+  // Count the result of the current game, None is a draw
+  pub fn record(&mut self, winner: Option<bool>) {
+    if !self.recorded {
+      self.recorded = true;
+      match winner {
+        Some(winner) if winner == self.engine_colour => self.losses += 1,
+        Some(_) => self.wins += 1,
+        None => self.draws += 1,
+      }
+    }
+  }
+}
+
+// Delay before a simple engine moves, using the egui time so it works on the web
+pub struct ThinkingDelay {
+  delay: f64,
+  start: Option<f64>,
+}
+
+impl ThinkingDelay {
+  fn new(millis: u64) -> Self {
+    Self {
+      delay: millis as f64 / 1000.0,
+      start: None,
+    }
+  }
+
+  // Whether the engine has finished thinking, starting the delay if it hasn't already
+  fn ready(&mut self, ctx: &Context) -> bool {
+    let time = ctx.input(|input| input.time);
+    let start = *self.start.get_or_insert(time);
+    let remaining = start + self.delay - time;
+    if remaining > 0.0 {
+      ctx.request_repaint_after(Duration::from_secs_f64(remaining));
+      false
+    } else {
+      self.start = None;
+      true
+    }
+  }
+}
+
+// Pick a hash size for the time control, more time per move fills a bigger table
+fn auto_hash_size(searchtime: &SearchTime) -> usize {
+  // rough thinking time per move in ms
+  let per_move = |time: Millis, inc: Millis| time.get() / 30 + inc.get();
+  let think_time = match searchtime {
+    SearchTime::Increment(time, inc) => per_move(*time, *inc),
+    SearchTime::Asymmetric(wtime, winc, btime, binc) => {
+      per_move(*wtime, *winc).max(per_move(*btime, *binc))
+    }
+    SearchTime::MovesToGo(wtime, winc, btime, binc, moves) => {
+      let moves = u128::from(*moves).max(1);
+      (wtime.get() / moves + winc.get()).max(btime.get() / moves + binc.get())
+    }
+    SearchTime::Delay(wtime, btime, delay) => per_move(*wtime.max(btime), *delay),
+    SearchTime::Hourglass(wtime, btime) => per_move(*wtime.max(btime), Millis::ZERO),
+    SearchTime::Other(limits) if limits.time < Millis::MAX => limits.time.get(),
+    SearchTime::Other(limits) if limits.nodes < usize::MAX => {
+      u128::try_from(limits.nodes).unwrap_or(u128::MAX) / NODES_PER_MS
+    }
+    // depth limited searches are usually short
+    SearchTime::Other(_) => return HASH_SIZE,
+    SearchTime::Infinite | SearchTime::Mate(_) => u128::MAX,
+  };
+  // about 1MB for every 100ms of thinking, leaving most of the memory for everything else
+  let limit = available_memory().map_or(AUTO_HASH_MAX, |memory| memory / 4);
+  usize::try_from(think_time / 100)
+    .unwrap_or(usize::MAX)
+    .clamp(AUTO_HASH_MIN, AUTO_HASH_MAX)
+    .next_power_of_two()
+    .min(limit.max(AUTO_HASH_MIN))
+}
+
+// The memory available to the system in MB
+#[cfg(target_os = "linux")]
+fn available_memory() -> Option<usize> {
+  let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+  let line = meminfo
+    .lines()
+    .find(|line| line.starts_with("MemAvailable:"))?;
+  let kilobytes: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+  Some(kilobytes / 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+const fn available_memory() -> Option<usize> {
+  None
+}
+
 pub enum PlayerData {
-  RandomEngine,
-  MvvLva,
+  // parameters are delay, the seed and the RNG seeded with it
+  RandomEngine(ThinkingDelay, u64, ChaChaRng),
+  // parameters are delay and temperature
+  MvvLva(ThinkingDelay, u32),
   BuiltIn(EngineInterface),
+  // parameters are the engine and the position it is searching
+  Lite(LiteEngine, String),
   Uci(UciInterface),
   Multiplayer(Connection),
 }
 
 impl PlayerData {
-  pub fn new(player: &PlayerType, board: &Board, ctx: &Context) -> Result<Self, String> {
+  pub fn new(
+    player: &PlayerType,
+    board: &Board,
+    options: &EngineOptions,
+    ctx: &Context,
+  ) -> Result<Self, String> {
     match player {
-      PlayerType::RandomEngine => Ok(Self::RandomEngine),
-      PlayerType::MvvLva => Ok(Self::MvvLva),
-      PlayerType::BuiltIn(hash_size) => Ok(Self::BuiltIn(EngineInterface::new(
+      PlayerType::RandomEngine(delay, seed) => {
+        let seed = seed
+          .as_ref()
+          .map_or_else(|| thread_rng().gen(), NumericalInput::get_value);
+        Ok(Self::RandomEngine(
+          ThinkingDelay::new(delay.get_value()),
+          seed,
+          ChaChaRng::seed_from_u64(seed),
+        ))
+      }
+      PlayerType::MvvLva(delay, temperature) => Ok(Self::MvvLva(
+        ThinkingDelay::new(delay.get_value()),
+        temperature.get_value(),
+      )),
+      PlayerType::BuiltIn(hash_size, _, elo) => Ok(Self::BuiltIn(EngineInterface::new(
         hash_size.get_value(),
+        elo.as_ref().map(NumericalInput::get_value),
         ctx,
       ))),
+      PlayerType::Lite(time, elo) => Ok(Self::Lite(
+        LiteEngine::new(
+          time.get_value(),
+          elo.as_ref().map(NumericalInput::get_value),
+        ),
+        String::new(),
+      )),
       PlayerType::External(path) => {
         let (send_request, recieve_request) = channel();
         let (send_result, recieve_result) = channel();
@@ -302,21 +569,17 @@ impl PlayerData {
           rx: recieve_result,
           state: UciState::Pending,
           board: Box::new(board.clone()),
+          info: None,
+          pv: Vec::new(),
+          diagnostics: Diagnostics::default(),
+          saved_options: options.get(path).to_vec(),
         }))
       }
       PlayerType::Multiplayer(ip, port, name) => {
         let address = format!("{ip}:{}", port.get_value())
           .parse()
           .map_err(|_| "Invalid IP address".to_owned())?;
-        let name = name.to_owned();
-        let (tx, rx) = channel();
-        spawn(move || {
-          process_connection(address, &tx, name);
-        });
-        Ok(Self::Multiplayer(Connection {
-          connection: rx,
-          output: None,
-        }))
+        Ok(Self::Multiplayer(Connection::new(address, name.to_owned())))
       }
     }
   }
@@ -326,37 +589,181 @@ impl PlayerData {
     &mut self,
     board: &Board,
     searchtime: SearchTime,
+    instant_move: bool,
+    ponder: bool,
+    ctx: &Context,
   ) -> (Option<Move>, Option<(Score, u16)>) {
     match self {
-      Self::RandomEngine => (random_move(board), None),
-      Self::MvvLva => (mvvlva_move(board), None),
+      Self::RandomEngine(delay, _, rng) => {
+        if delay.ready(ctx) {
+          (random_move_with_rng(board, rng), None)
+        } else {
+          (None, None)
+        }
+      }
+      Self::MvvLva(delay, temperature) => {
+        if delay.ready(ctx) {
+          (mvvlva_move_with_temperature(board, *temperature), None)
+        } else {
+          (None, None)
+        }
+      }
       Self::BuiltIn(interface) => {
+        interface.instant_move = instant_move;
+        interface.ponder = ponder;
         let (bestmove, score, _) = interface.get_move(board, searchtime);
         (bestmove, score)
       }
+      Self::Lite(engine, fen) => {
+        if board.state() != Gamestate::InProgress || board.promotion_available() {
+          return (None, None);
+        }
+        let position = board.to_string();
+        if !engine.is_searching() || *fen != position {
+          engine.start(board);
+          *fen = position;
+        }
+        // one depth is searched each frame so the GUI stays responsive
+        let bestmove = engine.step();
+        if bestmove.is_none() {
+          ctx.request_repaint();
+        }
+        let score = engine
+          .analysis()
+          .map(|result| (white_score(board, result.score), result.depth));
+        (bestmove, score)
+      }
       Self::Uci(interface) => interface.get_move(board, searchtime),
       Self::Multiplayer(_) => (None, None),
     }
   }
 
+  // The latest principal variation from an engine that is thinking
+  pub fn pv(&self) -> &[Move] {
+    match self {
+      Self::BuiltIn(interface) => &interface.pv,
+      Self::Lite(engine, _) => engine.analysis().map_or(&[], |result| result.pv.as_slice()),
+      Self::Uci(interface) => &interface.pv,
+      Self::RandomEngine(..) | Self::MvvLva(..) | Self::Multiplayer(_) => &[],
+    }
+  }
+
+  // What the player is doing, if it is working on a move
+  pub const fn status(&self, to_move: bool) -> Option<&'static str> {
+    let searching = match self {
+      Self::BuiltIn(interface) => interface.is_searching(),
+      Self::Lite(engine, _) => engine.is_searching(),
+      Self::Uci(interface) => matches!(interface.state, UciState::Analysing),
+      Self::RandomEngine(delay, ..) | Self::MvvLva(delay, _) => delay.start.is_some(),
+      Self::Multiplayer(_) => false,
+    };
+    match (searching, to_move) {
+      (false, _) => None,
+      (true, true) => Some("Thinking..."),
+      (true, false) => Some("Pondering..."),
+    }
+  }
+
+  // A problem with the connection to the player, if there is one
+  pub fn connection_status(&self) -> Option<String> {
+    match self {
+      Self::BuiltIn(interface) => interface.connection_status(),
+      Self::Uci(interface) => match interface.state {
+        UciState::Crashed => Some("Engine connection lost".to_owned()),
+        _ => interface.diagnostics.status("Engine connection lost"),
+      },
+      Self::Multiplayer(connection) => connection
+        .reconnect_status()
+        .or_else(|| connection.diagnostics.status("Server not responding")),
+      Self::RandomEngine(..) | Self::MvvLva(..) | Self::Lite(..) => None,
+    }
+  }
+
+  // The seed moves are picked with, to reproduce the game
+  pub const fn seed(&self) -> Option<u64> {
+    match self {
+      Self::RandomEngine(_, seed, _) => Some(*seed),
+      _ => None,
+    }
+  }
+
   pub fn cancel_move(&mut self) {
     match self {
       Self::BuiltIn(interface) => interface.cancel_move(),
+      Self::Lite(engine, _) => engine.stop(),
       Self::Uci(interface) => interface.cancel_move(),
-      Self::RandomEngine | Self::MvvLva | Self::Multiplayer(_) => (),
+      Self::RandomEngine(delay, ..) | Self::MvvLva(delay, _) => delay.start = None,
+      Self::Multiplayer(_) => (),
     }
   }
+
+  // Stop searching ahead on the opponent's time, any other search carries on
+  pub fn stop_pondering(&mut self) {
+    if let Self::BuiltIn(interface) = self {
+      interface.stop_pondering();
+    }
+  }
+}
+
+// Convert a score from the engine to be relative to white with mates counted from the position
+pub fn white_score(board: &Board, mut score: Score) -> Score {
+  if board.to_move() {
+    score = -score;
+  }
+  match score {
+    Score::Win(moves) => Score::Win(moves - board.moves()),
+    Score::Loss(moves) => Score::Loss(moves - board.moves()),
+    Score::Centipawn(_) => score,
+  }
+}
+
+enum EngineRequest {
+  // the board, search time, moves to search, moves to search first,
+  // whether to play obvious moves instantly, the depth to resume from and whether to ponder
+  Search(
+    CompressedBoard,
+    SearchTime,
+    Vec<Move>,
+    Vec<Move>,
+    bool,
+    u8,
+    bool,
+  ),
+  // save a session to a file
+  Save(AnalysisSession, String, Sender<io::Result<()>>),
+  // load a session from a file
+  Load(String, Sender<io::Result<AnalysisSession>>),
 }
 
 pub struct EngineInterface {
-  tx: Sender<(CompressedBoard, SearchTime)>,
+  tx: Sender<EngineRequest>,
   rx: Receiver<UlciResult>,
   send_message: Sender<Message>,
   status: bool,
+  // principal variation of the current search
+  pv: Vec<Move>,
+  // the latest result for each line of a MultiPV search
+  lines: Vec<AnalysisResult>,
+  instant_move: bool,
+  // whether to keep searching on the opponent's time, and the position expected after their move
+  ponder: bool,
+  pondering: Option<String>,
+  // the position being searched and every result so far, for saving the session
+  fen: String,
+  history: Vec<AnalysisResult>,
+  // a saved search to carry on from if the position matches
+  resume: Option<(String, u8)>,
+  // moves marked by the user to search first, and whether to only search them
+  candidates: Vec<Move>,
+  only_candidates: bool,
+  // options that can be changed during analysis
+  hash_size: usize,
+  multipv: u16,
+  diagnostics: Diagnostics,
 }
 
 impl EngineInterface {
-  pub fn new(hash_size: usize, ctx: &Context) -> Self {
+  pub fn new(hash_size: usize, elo: Option<u32>, ctx: &Context) -> Self {
     let (send_request, recieve_request) = channel();
     let (send_result, recieve_result) = channel();
     let (send_message, receive_message) = channel();
@@ -368,16 +775,45 @@ impl EngineInterface {
         SEARCH_PARAMETERS,
         DEFAULT_PARAMETERS,
       );
-      while let Ok((board, searchtime)) = recieve_request.recv() {
-        process_position(
-          &send_result,
-          &receive_message,
-          board,
-          searchtime,
-          &mut state,
-          1,
-        );
-        ctx.request_repaint();
+      state.set_strength(elo);
+      let mut multipv = 1;
+      while let Ok(request) = recieve_request.recv() {
+        match request {
+          EngineRequest::Search(
+            board,
+            searchtime,
+            searchmoves,
+            priority,
+            instant_move,
+            resume_depth,
+            ponder,
+          ) => {
+            let delivered = process_position(
+              &send_result,
+              &receive_message,
+              board,
+              searchtime,
+              &searchmoves,
+              &priority,
+              &mut state,
+              &mut multipv,
+              instant_move,
+              resume_depth,
+              ponder,
+            );
+            ctx.request_repaint();
+            // nobody is listening for results any more
+            if delivered.is_none() {
+              break;
+            }
+          }
+          EngineRequest::Save(session, path, reply) => {
+            reply.send(session.save(&path, &state)).ok();
+          }
+          EngineRequest::Load(path, reply) => {
+            reply.send(AnalysisSession::load(&path, &mut state)).ok();
+          }
+        }
       }
     });
     EngineInterface {
@@ -385,6 +821,19 @@ impl EngineInterface {
       rx: recieve_result,
       send_message,
       status: false,
+      pv: Vec::new(),
+      lines: Vec::new(),
+      instant_move: false,
+      ponder: false,
+      pondering: None,
+      fen: String::new(),
+      history: Vec::new(),
+      resume: None,
+      candidates: Vec::new(),
+      only_candidates: false,
+      hash_size,
+      multipv: 1,
+      diagnostics: Diagnostics::default(),
     }
   }
 
@@ -394,41 +843,256 @@ impl EngineInterface {
     searchtime: SearchTime,
   ) -> (Option<Move>, Option<(Score, u16)>, Vec<Move>) {
     let (mut result, mut analysis, mut pv) = (None, None, Vec::new());
+    if let Some(fen) = self.pondering.take() {
+      if self.status && fen == board.to_string() {
+        // the expected move was played, so the search carries on with the time control
+        self
+          .diagnostics
+          .record(self.send_message.send(Message::PonderHit));
+      } else {
+        self.cancel_move();
+      }
+    }
     if self.status {
       // request sent, poll for results
-      for message in self.rx.try_iter() {
-        match message {
-          UlciResult::AnalysisStopped(bestmove) => {
+      loop {
+        match self.rx.try_recv() {
+          Ok(UlciResult::AnalysisStopped(bestmove)) => {
             result = Some(bestmove);
             self.status = false;
           }
-          UlciResult::Analysis(result) => {
-            let mut score = result.score;
-            if board.to_move() {
-              score = -score;
+          Ok(UlciResult::Analysis(result)) => {
+            self.history.push(result.clone());
+            // lines arrive in order, so each one is either an update or the next line
+            let index = usize::from(result.pv_line.max(1)) - 1;
+            match index.cmp(&self.lines.len()) {
+              Ordering::Less => self.lines[index] = result.clone(),
+              Ordering::Equal => self.lines.push(result.clone()),
+              Ordering::Greater => (),
             }
-            match score {
-              Score::Win(moves) => score = Score::Win(moves - board.moves()),
-              Score::Loss(moves) => score = Score::Loss(moves - board.moves()),
-              Score::Centipawn(_) => (),
+            if result.pv_line == 1 {
+              analysis = Some((white_score(board, result.score), result.depth));
+              self.pv.clone_from(&result.pv);
+              pv.clone_from(&result.pv);
             }
-            analysis = Some((score, result.depth));
-            pv = result.pv;
           }
-          UlciResult::Startup(_) | UlciResult::Info(..) => (),
+          Ok(UlciResult::Startup(_) | UlciResult::Info(..) | UlciResult::Perft(..)) => (),
+          Err(TryRecvError::Empty) => break,
+          Err(TryRecvError::Disconnected) => {
+            // the engine thread has stopped, so no move is coming
+            self.diagnostics.disconnect();
+            self.status = false;
+            break;
+          }
         }
       }
-    } else if board.state() == Gamestate::InProgress && !board.promotion_available() {
+      if let (Some(bestmove), true) = (result, self.ponder) {
+        self.start_pondering(board, bestmove, searchtime);
+      }
+    } else if board.state() == Gamestate::InProgress
+      && !board.promotion_available()
+      && self.connection_status().is_none()
+    {
       // send request
-      self.tx.send((board.send_to_thread(), searchtime)).ok();
-      self.status = true;
+      let fen = board.to_string();
+      // candidates only apply to the position they were marked in
+      if fen != self.fen {
+        self.candidates.clear();
+      }
+      let searchmoves = if self.only_candidates {
+        self.candidates.clone()
+      } else {
+        Vec::new()
+      };
+      let resume_depth = match self.resume.take() {
+        Some((resume_fen, depth)) if resume_fen == fen => depth,
+        _ => {
+          self.history.clear();
+          0
+        }
+      };
+      let sent = self.tx.send(EngineRequest::Search(
+        board.send_to_thread(),
+        searchtime,
+        searchmoves,
+        self.candidates.clone(),
+        self.instant_move,
+        resume_depth,
+        false,
+      ));
+      self.fen = fen;
+      self.status = self.diagnostics.record(sent).is_some();
+      self.pv.clear();
+      self.lines.clear();
     }
     (result, analysis, pv)
   }
 
+  // Search the position after the expected reply while the opponent thinks
+  fn start_pondering(&mut self, board: &Board, bestmove: Move, searchtime: SearchTime) {
+    if self.pv.first() != Some(&bestmove) {
+      return;
+    }
+    let Some(expected) = self.pv.get(1).and_then(|reply| {
+      board
+        .move_if_legal(bestmove)?
+        .move_if_legal(*reply)
+        .filter(|position| position.state() == Gamestate::InProgress)
+    }) else {
+      return;
+    };
+    let sent = self.tx.send(EngineRequest::Search(
+      expected.send_to_thread(),
+      searchtime,
+      Vec::new(),
+      Vec::new(),
+      false,
+      0,
+      true,
+    ));
+    self.fen = expected.to_string();
+    self.status = self.diagnostics.record(sent).is_some();
+    if self.status {
+      self.pondering = Some(self.fen.clone());
+    }
+    self.candidates.clear();
+    self.history.clear();
+    self.pv.clear();
+    self.lines.clear();
+  }
+
+  // Save the analysis so far so it can be resumed later, the search carries on afterwards
+  pub fn save_session(&mut self, path: &str) -> Result<(), String> {
+    if self.history.is_empty() {
+      return Err("No analysis to save".to_owned());
+    }
+    self.cancel_move();
+    let session = AnalysisSession {
+      fen: self.fen.clone(),
+      results: self.history.clone(),
+    };
+    let depth = session.depth();
+    let (tx, rx) = channel();
+    self.diagnostics.record(
+      self
+        .tx
+        .send(EngineRequest::Save(session, path.to_owned(), tx)),
+    );
+    rx.recv()
+      .map_err(|_| "Engine has crashed".to_owned())?
+      .map_err(|error| format!("Could not save analysis: {error}"))?;
+    self.resume = Some((self.fen.clone(), depth as u8));
+    Ok(())
+  }
+
+  // Load a saved analysis, the next search of the saved position carries on from it
+  pub fn load_session(&mut self, path: &str) -> Result<AnalysisSession, String> {
+    self.cancel_move();
+    let (tx, rx) = channel();
+    self
+      .diagnostics
+      .record(self.tx.send(EngineRequest::Load(path.to_owned(), tx)));
+    let session = rx
+      .recv()
+      .map_err(|_| "Engine has crashed".to_owned())?
+      .map_err(|error| format!("Could not load analysis: {error}"))?;
+    self.resume = Some((session.fen.clone(), session.depth() as u8));
+    self.history.clone_from(&session.results);
+    Ok(session)
+  }
+
+  // Moves marked to be searched first in the current position
+  pub fn candidates(&self) -> &[Move] {
+    &self.candidates
+  }
+
+  // Mark or unmark a candidate move, the search restarts to use it
+  pub fn toggle_candidate(&mut self, mv: Move) {
+    if let Some(index) = self
+      .candidates
+      .iter()
+      .position(|candidate| *candidate == mv)
+    {
+      self.candidates.remove(index);
+    } else {
+      self.candidates.push(mv);
+    }
+    self.cancel_move();
+  }
+
+  // Whether only the candidate moves are searched
+  pub const fn only_candidates(&self) -> bool {
+    self.only_candidates
+  }
+
+  pub fn toggle_only_candidates(&mut self) {
+    self.only_candidates = !self.only_candidates;
+    self.cancel_move();
+  }
+
+  // The principal variation of the current search
+  pub fn pv(&self) -> &[Move] {
+    &self.pv
+  }
+
+  // The best line for each of the moves being searched, if the board is the one being searched
+  pub fn lines(&self, board: &Board) -> &[AnalysisResult] {
+    if self.fen == board.to_string() {
+      &self.lines[..self.lines.len().min(usize::from(self.multipv))]
+    } else {
+      &[]
+    }
+  }
+
+  pub const fn hash_size(&self) -> usize {
+    self.hash_size
+  }
+
+  pub const fn multipv(&self) -> u16 {
+    self.multipv
+  }
+
+  // Change the hash size, analysis in progress pauses and carries on with the table kept
+  pub fn set_hash_size(&mut self, hash_size: usize) {
+    if hash_size != self.hash_size {
+      self.hash_size = hash_size;
+      self.set_option(HASH_NAME, OptionValue::UpdateInt(hash_size));
+    }
+  }
+
+  // Change the number of lines searched, analysis in progress carries on from its last depth
+  pub fn set_multipv(&mut self, multipv: u16) {
+    if multipv != self.multipv {
+      self.multipv = multipv;
+      self.set_option(MULTI_PV_NAME, OptionValue::UpdateInt(usize::from(multipv)));
+    }
+  }
+
+  // Sent as a message so a search in progress sees it, otherwise it applies to the next search
+  fn set_option(&self, name: &str, value: OptionValue) {
+    self.diagnostics.record(
+      self
+        .send_message
+        .send(Message::UpdateOption(name.to_owned(), value)),
+    );
+  }
+
+  // Whether the engine is currently searching
+  pub const fn is_searching(&self) -> bool {
+    self.status
+  }
+
+  // A problem with the engine thread, if there is one
+  pub fn connection_status(&self) -> Option<String> {
+    self.diagnostics.status("Engine connection lost")
+  }
+
   pub fn cancel_move(&mut self) {
+    self.pondering = None;
     if self.status {
-      self.send_message.send(Message::Stop).ok();
+      self
+        .diagnostics
+        .record(self.send_message.send(Message::Stop));
       // wait for results
       while let Ok(message) = self.rx.recv() {
         if let UlciResult::AnalysisStopped(_) = message {
@@ -438,6 +1102,12 @@ impl EngineInterface {
       }
     }
   }
+
+  pub fn stop_pondering(&mut self) {
+    if self.pondering.is_some() {
+      self.cancel_move();
+    }
+  }
 }
 
 impl Drop for EngineInterface {
@@ -452,22 +1122,66 @@ pub struct UciInterface {
   pub state: UciState,
   // Hacky solution to preserve the board until the engine has loaded
   pub board: Box<Board>,
+  // what the engine reported at startup, kept to check support for a new board
+  info: Option<ClientInfo>,
+  // principal variation of the current search
+  pv: Vec<Move>,
+  diagnostics: Diagnostics,
+  // option values chosen by the user, sent once the engine has loaded
+  saved_options: Vec<(String, String)>,
 }
 
 impl UciInterface {
+  fn startup(&mut self, info: ClientInfo) {
+    self.state = if info.supports(&self.board) {
+      UciState::Waiting
+    } else {
+      UciState::Unsupported
+    };
+    for (name, value) in self.saved_options.drain(..) {
+      if let Some(update) = info
+        .options
+        .get(&name)
+        .and_then(|option| parse_value(option, &value))
+      {
+        self.tx.send(Request::SetOption(name, update)).ok();
+      }
+    }
+    self.info = Some(info);
+  }
+
+  // The options the engine supports, once it has loaded
+  pub fn options(&self) -> Option<&HashMap<String, UlciOption>> {
+    self.info.as_ref().map(|info| &info.options)
+  }
+
+  pub fn set_option(&self, name: &str, value: OptionValue) {
+    self
+      .tx
+      .send(Request::SetOption(name.to_owned(), value))
+      .ok();
+  }
+
+  // Switch to a different starting position before any searches
+  fn set_board(&mut self, board: &Board) {
+    self.board = Box::new(board.clone());
+    if self.state != UciState::Crashed {
+      if let Some(info) = self.info.take() {
+        self.startup(info);
+      }
+    }
+  }
+
   pub fn poll(&mut self) {
     match self.state {
       UciState::Pending => loop {
         match self.rx.try_recv() {
           Ok(message) => match message {
-            UlciResult::Startup(info) => {
-              self.state = if info.supports(&self.board) {
-                UciState::Waiting
-              } else {
-                UciState::Unsupported
-              };
-            }
-            UlciResult::Analysis(_) | UlciResult::AnalysisStopped(_) | UlciResult::Info(..) => (),
+            UlciResult::Startup(info) => self.startup(info),
+            UlciResult::Analysis(_)
+            | UlciResult::AnalysisStopped(_)
+            | UlciResult::Info(..)
+            | UlciResult::Perft(..) => (),
           },
           Err(TryRecvError::Disconnected) => {
             self.state = UciState::Crashed;
@@ -495,13 +1209,13 @@ impl UciInterface {
         match self.rx.try_recv() {
           Ok(message) => match message {
             UlciResult::Startup(info) => {
-              self.state = if info.supports(board) {
-                UciState::Waiting
-              } else {
-                UciState::Unsupported
-              };
+              self.board = Box::new(board.clone());
+              self.startup(info);
             }
-            UlciResult::Analysis(_) | UlciResult::AnalysisStopped(_) | UlciResult::Info(..) => (),
+            UlciResult::Analysis(_)
+            | UlciResult::AnalysisStopped(_)
+            | UlciResult::Info(..)
+            | UlciResult::Perft(..) => (),
           },
           Err(TryRecvError::Disconnected) => {
             self.state = UciState::Crashed;
@@ -514,17 +1228,21 @@ impl UciInterface {
         if board.state() == Gamestate::InProgress && !board.promotion_available() {
           // send request
           // TODO: send board history properly
-          self
-            .tx
-            .send(Request::Analysis(AnalysisRequest {
-              fen: board.to_string(),
-              moves: Vec::new(),
-              time: searchtime,
-              searchmoves: Vec::new(),
-              new_game: false,
-            }))
-            .ok();
-          self.state = UciState::Analysing;
+          let sent = self.tx.send(Request::Analysis(AnalysisRequest {
+            fen: board.to_string(),
+            moves: Vec::new(),
+            time: searchtime,
+            searchmoves: Vec::new(),
+            priority: Vec::new(),
+            new_game: false,
+          }));
+          // the server thread only stops when the engine has crashed
+          self.state = if self.diagnostics.record(sent).is_some() {
+            UciState::Analysing
+          } else {
+            UciState::Crashed
+          };
+          self.pv.clear();
         }
       }
       UciState::Analysing => {
@@ -542,8 +1260,9 @@ impl UciInterface {
                   score = -score;
                 }
                 analysis = Some((score, result.depth));
+                self.pv = result.pv;
               }
-              UlciResult::Startup(_) | UlciResult::Info(..) => (),
+              UlciResult::Startup(_) | UlciResult::Info(..) | UlciResult::Perft(..) => (),
             },
             Err(TryRecvError::Disconnected) => {
               self.state = UciState::Crashed;
@@ -557,7 +1276,10 @@ impl UciInterface {
         match self.rx.try_recv() {
           Ok(message) => match message {
             UlciResult::AnalysisStopped(_) => self.state = UciState::Waiting,
-            UlciResult::Analysis(_) | UlciResult::Startup(_) | UlciResult::Info(..) => (),
+            UlciResult::Analysis(_)
+            | UlciResult::Startup(_)
+            | UlciResult::Info(..)
+            | UlciResult::Perft(..) => (),
           },
           Err(TryRecvError::Disconnected) => {
             self.state = UciState::Crashed;
@@ -573,7 +1295,7 @@ impl UciInterface {
 
   fn cancel_move(&mut self) {
     if self.state == UciState::Analysing {
-      self.tx.send(Request::StopAnalysis).ok();
+      self.diagnostics.record(self.tx.send(Request::StopAnalysis));
       self.state = UciState::AwaitStop;
     }
   }
@@ -595,24 +1317,265 @@ pub enum UciState {
   Crashed,
 }
 
+// Attempts to reconnect after losing the connection to the server, waiting twice as long each time
+const RECONNECT_ATTEMPTS: u32 = 6;
+const RECONNECT_DELAY: f64 = 1.0;
+const MAX_RECONNECT_DELAY: f64 = 30.0;
+
+// Progress reconnecting to the server after the connection dropped
+struct Reconnect {
+  attempt: u32,
+  // the egui time of the next attempt, `None` once it has started
+  next: Option<f64>,
+}
+
+// A game on the same connection as the one being shown
+pub struct OnlineGame {
+  // 0 for the game the connection started with
+  pub id: u32,
+  connection: Receiver<ConnectionMessage>,
+  output: Option<GameOutput<TcpStream>>,
+  pub position: Option<Board>,
+  // the opponent's side
+  pub side: bool,
+  #[cfg(feature = "clock")]
+  pub clock: Option<Clock>,
+  pub notice: Option<String>,
+  closed: bool,
+}
+
+impl OnlineGame {
+  fn new((id, connection, output): NewGame) -> Self {
+    Self {
+      id,
+      connection,
+      output: Some(output),
+      position: None,
+      side: false,
+      #[cfg(feature = "clock")]
+      clock: None,
+      notice: None,
+      closed: false,
+    }
+  }
+
+  // Keep up with the game while it isn't shown, returning any games the server started
+  fn poll(&mut self) -> Vec<NewGame> {
+    let mut new_games = Vec::new();
+    loop {
+      match self.connection.try_recv() {
+        Ok(ConnectionMessage::Uci(message)) => match message {
+          Message::UpdatePosition(board) => {
+            let board = board.load_from_thread();
+            self.side = board.to_move();
+            self.position = Some(board);
+          }
+          #[cfg(feature = "clock")]
+          Message::Go(settings) => {
+            if let Some(ref board) = self.position {
+              self.side = !board.to_move();
+              self.clock = settings.time.to_clock(board.to_move()).map(|mut clock| {
+                clock.toggle_pause();
+                clock
+              });
+            }
+          }
+          #[cfg(not(feature = "clock"))]
+          Message::Go(_) => {
+            if let Some(ref board) = self.position {
+              self.side = !board.to_move();
+            }
+          }
+          #[cfg(feature = "clock")]
+          Message::Clock(time) => {
+            if let Some(ref board) = self.position {
+              self.clock = time.to_clock(board.to_move()).map(|mut clock| {
+                clock.toggle_pause();
+                clock
+              });
+            }
+          }
+          Message::Notice(notice) => self.notice = Some(notice),
+          _ => (),
+        },
+        Ok(ConnectionMessage::Connected(output)) => self.output = Some(output),
+        Ok(ConnectionMessage::Game(game)) => new_games.push(game),
+        Ok(ConnectionMessage::Timeout) => (),
+        Err(TryRecvError::Disconnected) => {
+          self.closed = true;
+          break;
+        }
+        Err(TryRecvError::Empty) => break,
+      }
+    }
+    new_games
+  }
+
+  // Whether the server is waiting for a move from this side
+  pub fn to_play(&self) -> bool {
+    self
+      .position
+      .as_ref()
+      .is_some_and(|position| position.to_move() != self.side)
+  }
+}
+
 pub struct Connection {
   pub connection: Receiver<ConnectionMessage>,
-  pub output: Option<TcpStream>,
+  pub output: Option<GameOutput<TcpStream>>,
+  // whether the server is sending analysis from its own engine
+  pub kibbutz: bool,
+  pub diagnostics: Diagnostics,
+  // the game being shown, and the other games on the connection
+  pub game: u32,
+  pub games: Vec<OnlineGame>,
+  // kept to connect again if the connection drops
+  address: SocketAddr,
+  name: String,
+  reconnect: Option<Reconnect>,
 }
 
 impl Connection {
-  pub fn play_move(&self, mv: Move) {
-    self
-      .output
+  fn new(address: SocketAddr, name: String) -> Self {
+    Self {
+      connection: connect(address, name.clone()),
+      output: None,
+      kibbutz: false,
+      diagnostics: Diagnostics::default(),
+      game: 0,
+      games: Vec::new(),
+      address,
+      name,
+      reconnect: None,
+    }
+  }
+
+  pub fn play_move(&mut self, mv: Move) {
+    // moves made while reconnecting are replaced by the position the server resumes from
+    let sent = match self.output {
+      Some(ref mut output) => output.write_all(format!("bestmove {}\n", mv.to_string()).as_bytes()),
+      None => Err(io::Error::from(ErrorKind::NotConnected)),
+    };
+    self.diagnostics.record(sent);
+  }
+
+  // Schedule another attempt to connect after the connection dropped
+  //
+  // Returns false once all the attempts have been used up
+  pub fn schedule_reconnect(&mut self, ctx: &Context) -> bool {
+    let attempt = self
+      .reconnect
       .as_ref()
-      .expect("Connection is missing a stream")
-      .write_all(format!("bestmove {}\n", mv.to_string()).as_bytes())
-      .ok();
+      .map_or(0, |reconnect| reconnect.attempt);
+    if attempt >= RECONNECT_ATTEMPTS {
+      return false;
+    }
+    let delay = (RECONNECT_DELAY * 2.0_f64.powi(attempt as i32)).min(MAX_RECONNECT_DELAY);
+    self.reconnect = Some(Reconnect {
+      attempt: attempt + 1,
+      next: Some(ctx.input(|input| input.time) + delay),
+    });
+    self.output = None;
+    // the other games went with the connection, the server starts them again if it needs to
+    self.game = 0;
+    self.games.clear();
+    true
+  }
+
+  // Whether the connection is waiting to reconnect, starting the attempt once it is due
+  pub fn awaiting_reconnect(&mut self, ctx: &Context) -> bool {
+    let Some(ref mut reconnect) = self.reconnect else {
+      return false;
+    };
+    let Some(next) = reconnect.next else {
+      return false;
+    };
+    let time = ctx.input(|input| input.time);
+    if time < next {
+      ctx.request_repaint_after(Duration::from_secs_f64(next - time));
+      return true;
+    }
+    reconnect.next = None;
+    self.connection = connect(self.address, self.name.clone());
+    false
+  }
+
+  // The connection is back, the attempts are only reset once the server resumes the game
+  pub fn reconnected(&mut self, output: GameOutput<TcpStream>) {
+    self.output = Some(output);
+    self.diagnostics = Diagnostics::default();
+  }
+
+  // The server has sent the position, so the game is back in progress
+  pub fn resumed(&mut self) {
+    self.reconnect = None;
+  }
+
+  // Add a game the server started on the connection
+  pub fn add_game(&mut self, game: NewGame) {
+    self.games.push(OnlineGame::new(game));
+  }
+
+  // Keep up with the games that aren't being shown
+  pub fn poll_games(&mut self) {
+    let mut new_games = Vec::new();
+    for game in &mut self.games {
+      new_games.append(&mut game.poll());
+    }
+    // the game being shown notices if the whole connection has dropped
+    self.games.retain(|game| !game.closed);
+    self
+      .games
+      .extend(new_games.into_iter().map(OnlineGame::new));
+  }
+
+  // Show another game on the connection, putting away the one being shown
+  //
+  // Returns the position of the game to show and its opponent's side
+  pub fn switch_game(
+    &mut self,
+    index: usize,
+    position: Option<Board>,
+    side: bool,
+  ) -> (Option<Board>, bool) {
+    let game = &mut self.games[index];
+    swap(&mut self.game, &mut game.id);
+    swap(&mut self.connection, &mut game.connection);
+    swap(&mut self.output, &mut game.output);
+    let shown = (game.position.take(), game.side);
+    game.position = position;
+    game.side = side;
+    shown
+  }
+
+  // What is happening with reconnecting, if the connection has dropped
+  fn reconnect_status(&self) -> Option<String> {
+    let reconnect = self.reconnect.as_ref()?;
+    let progress = format!("attempt {} of {RECONNECT_ATTEMPTS}", reconnect.attempt);
+    Some(match (reconnect.next, &self.output) {
+      (Some(_), _) => format!("Connection lost, waiting to reconnect ({progress})"),
+      (None, None) => format!("Reconnecting to server ({progress})"),
+      (None, Some(_)) => "Reconnected, waiting for the server to resume the game".to_owned(),
+    })
   }
 }
 
+// Connect to a server in the background
+fn connect(address: SocketAddr, name: String) -> Receiver<ConnectionMessage> {
+  let (tx, rx) = channel();
+  spawn(move || {
+    process_connection(address, &tx, name);
+  });
+  rx
+}
+
+// A game the server started on the connection, with its id
+pub type NewGame = (u32, Receiver<ConnectionMessage>, GameOutput<TcpStream>);
+
 pub enum ConnectionMessage {
-  Connected(TcpStream),
+  Connected(GameOutput<TcpStream>),
+  // another game the server started on the same connection
+  Game(NewGame),
   Timeout,
   Uci(Message),
 }
@@ -625,31 +1588,29 @@ fn process_connection(
   match TcpStream::connect_timeout(&address, Duration::from_secs(10)) {
     Ok(connection) => {
       let connection_2 = connection.try_clone().ok()?;
-      let connection_3 = connection.try_clone().ok()?;
-      tx.send(ConnectionMessage::Connected(connection_3)).ok()?;
-      let (uci_tx, rx) = channel();
+      // the server can start more games on the same connection
+      let (games_tx, games_rx) = channel();
+      let (_, input, output) = Games::split(
+        KeepaliveInput::new(connection),
+        connection_2,
+        Some(games_tx),
+      );
+      tx.send(ConnectionMessage::Connected(output.clone())).ok()?;
+      let game_tx = tx.clone();
+      let game_name = name.clone();
       spawn(move || {
-        startup(
-          &uci_tx,
-          &ClientInfo {
-            features: SupportedFeatures {
-              v1: V1Features::all(),
-            },
-            name: format!("Liberty Chess v{}", env!("CARGO_PKG_VERSION")),
-            username: Some(name),
-            author: "Mathmagician".to_owned(),
-            options: HashMap::new(),
-            pieces: from_chars(ALL_PIECES),
-            depth: 0,
-          },
-          BufReader::new(connection),
-          connection_2,
-          true,
-        )
+        for (id, input, output) in games_rx {
+          let (messages, connection) = channel();
+          let name = game_name.clone();
+          let client_output = output.clone();
+          spawn(move || run_client(&messages, input, client_output, name));
+          let game = (id, connection, output);
+          if game_tx.send(ConnectionMessage::Game(game)).is_err() {
+            break;
+          }
+        }
       });
-      while let Ok(message) = rx.recv() {
-        tx.send(ConnectionMessage::Uci(message)).ok()?;
-      }
+      run_client(tx, input, output, name);
     }
     Err(error) => {
       if error.kind() == ErrorKind::TimedOut {
@@ -660,6 +1621,233 @@ fn process_connection(
   None
 }
 
+// Only times out reads once the server has shown that it pings regularly,
+// since other servers can be silent for as long as the opponent takes to move
+struct KeepaliveInput {
+  input: BufReader<TcpStream>,
+  // pings for a new game follow `ucinewgame`, so they don't show that the server sends keepalives
+  new_game: bool,
+  keepalive: bool,
+}
+
+impl KeepaliveInput {
+  fn new(connection: TcpStream) -> Self {
+    Self {
+      input: BufReader::new(connection),
+      new_game: false,
+      keepalive: false,
+    }
+  }
+}
+
+impl Read for KeepaliveInput {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    self.input.read(buf)
+  }
+}
+
+impl BufRead for KeepaliveInput {
+  fn fill_buf(&mut self) -> io::Result<&[u8]> {
+    self.input.fill_buf()
+  }
+
+  fn consume(&mut self, amount: usize) {
+    self.input.consume(amount);
+  }
+
+  fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+    let start = buf.len();
+    let chars = self.input.read_line(buf)?;
+    let line = buf[start..].trim();
+    if !self.keepalive && line == "isready" && !self.new_game {
+      // a long silence now means the connection has dropped
+      self.keepalive = true;
+      self
+        .input
+        .get_ref()
+        .set_read_timeout(Some(Keepalive::default().timeout))?;
+    }
+    self.new_game = line == "ucinewgame";
+    Ok(chars)
+  }
+}
+
+// Play one game on a connection, passing on what the server sends
+fn run_client(
+  tx: &Sender<ConnectionMessage>,
+  input: ChannelInput,
+  output: GameOutput<TcpStream>,
+  name: String,
+) -> Option<()> {
+  let (uci_tx, rx) = channel();
+  spawn(move || {
+    startup(
+      &uci_tx,
+      &ClientInfo {
+        features: SupportedFeatures {
+          v1: V1Features::all(),
+          priority_moves: false,
+          compression: true,
+          games: true,
+        },
+        name: format!("Liberty Chess v{}", env!("CARGO_PKG_VERSION")),
+        username: Some(name),
+        author: "Mathmagician".to_owned(),
+        options: HashMap::new(),
+        pieces: from_chars(ALL_PIECES),
+        depth: 0,
+        build: None,
+      },
+      input,
+      output,
+      true,
+    )
+  });
+  while let Ok(message) = rx.recv() {
+    tx.send(ConnectionMessage::Uci(message)).ok()?;
+  }
+  Some(())
+}
+
+// Keeps an engine launched ahead of time so games start without waiting for it to load
+#[derive(Default)]
+pub struct EnginePool {
+  standby: Option<(PlayerType, Result<PlayerData, String>)>,
+  // the time to launch the selected engine at, set when it changes or the standby engine is used
+  pending: Option<f64>,
+}
+
+impl EnginePool {
+  // Schedule a launch after the opponent's settings change
+  // Edits to the same type of opponent wait for typing to pause, so partial paths aren't launched
+  pub fn changed(
+    &mut self,
+    previous: Option<&PlayerType>,
+    player: Option<&PlayerType>,
+    ctx: &Context,
+  ) {
+    let delay = match (previous, player) {
+      (Some(previous), Some(player)) if discriminant(previous) == discriminant(player) => {
+        EDIT_DELAY
+      }
+      _ => 0.0,
+    };
+    self.pending = Some(ctx.input(|input| input.time) + delay);
+  }
+
+  // Launch the selected engine if a launch is due, and keep the engine on standby updated
+  pub fn update(
+    &mut self,
+    player: Option<&PlayerType>,
+    fen: &str,
+    options: &EngineOptions,
+    ctx: &Context,
+  ) {
+    if let Some(due) = self.pending {
+      let time = ctx.input(|input| input.time);
+      if time >= due {
+        self.pending = None;
+        self.prepare(player, fen, options, ctx);
+      } else {
+        ctx.request_repaint_after(Duration::from_secs_f64(due - time));
+      }
+    }
+    if let Some((_, Ok(PlayerData::Uci(ref mut interface)))) = self.standby {
+      interface.poll();
+    }
+  }
+
+  // Launch the selected engine if it isn't already on standby
+  fn prepare(
+    &mut self,
+    player: Option<&PlayerType>,
+    fen: &str,
+    options: &EngineOptions,
+    ctx: &Context,
+  ) {
+    let player = player.filter(|player| match player {
+      PlayerType::BuiltIn(..) => true,
+      PlayerType::External(path) => !path.is_empty(),
+      PlayerType::RandomEngine(..)
+      | PlayerType::MvvLva(..)
+      | PlayerType::Lite(..)
+      | PlayerType::Multiplayer(..) => false,
+    });
+    let Some(player) = player else {
+      self.standby = None;
+      return;
+    };
+    if !matches!(self.standby, Some((ref standby, _)) if standby.same_engine(player)) {
+      // the real position is checked when the game starts
+      let board = Board::new(fen).unwrap_or_else(|_| get_startpos());
+      let player_data = PlayerData::new(player, &board, options, ctx);
+      self.standby = Some((player.clone(), player_data));
+    }
+  }
+
+  // The external engine on standby, if there is one
+  pub fn standby_uci(&mut self) -> Option<&mut UciInterface> {
+    match self.standby {
+      Some((_, Ok(PlayerData::Uci(ref mut interface)))) => Some(interface),
+      _ => None,
+    }
+  }
+
+  // The state of the engine on standby, if there is one
+  pub fn status(&self) -> Option<String> {
+    match self.standby {
+      Some((_, Ok(PlayerData::Uci(ref interface)))) => Some(
+        match interface.state {
+          UciState::Pending => "Loading engine",
+          UciState::Crashed => "Engine has crashed",
+          UciState::Waiting | UciState::Analysing | UciState::AwaitStop | UciState::Unsupported => {
+            "Engine ready"
+          }
+        }
+        .to_owned(),
+      ),
+      Some((_, Ok(_))) => Some("Engine ready".to_owned()),
+      Some((_, Err(ref error))) => Some(error.clone()),
+      None => None,
+    }
+  }
+
+  // Take the engine on standby if it matches, otherwise launch a new one
+  pub fn take(
+    &mut self,
+    player: &PlayerType,
+    board: &Board,
+    searchtime: &SearchTime,
+    options: &EngineOptions,
+    ctx: &Context,
+  ) -> Result<PlayerData, String> {
+    // the automatic hash size is only worked out once, when the game starts
+    let for_search = player.for_search(searchtime);
+    // launch another engine for the next game once back on the menu
+    self.pending = Some(0.0);
+    match self.standby.take() {
+      Some((standby, player_data)) if standby.same_engine(player) => {
+        let mut player_data = player_data?;
+        if let (PlayerData::BuiltIn(interface), PlayerType::BuiltIn(hash_size, ..)) =
+          (&mut player_data, &for_search)
+        {
+          interface.set_hash_size(hash_size.get_value());
+        }
+        if let PlayerData::Uci(ref mut interface) = player_data {
+          interface.set_board(board);
+          match interface.state {
+            UciState::Unsupported => return Err("Engine does not support position".to_owned()),
+            UciState::Crashed => return Err("Engine has crashed".to_owned()),
+            UciState::Pending | UciState::Waiting | UciState::Analysing | UciState::AwaitStop => (),
+          }
+        }
+        Ok(player_data)
+      }
+      _ => PlayerData::new(&for_search, board, options, ctx),
+    }
+  }
+}
+
 pub(crate) fn handle_loading_engine(gui: &mut LibertyChessGUI) {
   // handle loading engine
   if let Some((ref mut player, ref mut side)) = gui.player {
@@ -688,11 +1876,13 @@ pub(crate) fn handle_loading_engine(gui: &mut LibertyChessGUI) {
         loop {
           match interface.connection.try_recv() {
             Ok(message) => match message {
-              ConnectionMessage::Connected(stream) => {
-                interface.output = Some(stream);
+              ConnectionMessage::Connected(output) => {
+                interface.output = Some(output);
                 gui.message = Some("Waiting for server to send board".to_owned());
               }
+              ConnectionMessage::Game(game) => interface.add_game(game),
               ConnectionMessage::Timeout => {
+                interface.diagnostics.disconnect();
                 clear_player = true;
                 gui.message = Some("Connection timed out".to_owned());
                 break;
@@ -710,31 +1900,10 @@ pub(crate) fn handle_loading_engine(gui: &mut LibertyChessGUI) {
                     if gui.config.get_opponentflip() {
                       gui.flipped = *side;
                     }
-                    match settings.time {
-                      SearchTime::Increment(time, inc) => {
-                        let mut clock = Clock::new_symmetric(
-                          Duration::from_millis(time as u64),
-                          Duration::from_millis(inc as u64),
-                          board.to_move(),
-                        );
-                        clock.toggle_pause();
-                        gui.clock = Some(clock);
-                      }
-                      SearchTime::Asymmetric(wtime, winc, btime, binc) => {
-                        let mut clock = Clock::new(
-                          [
-                            Duration::from_millis(wtime as u64),
-                            Duration::from_millis(btime as u64),
-                            Duration::from_millis(winc as u64),
-                            Duration::from_millis(binc as u64),
-                          ],
-                          board.to_move(),
-                        );
-                        clock.toggle_pause();
-                        gui.clock = Some(clock);
-                      }
-                      _ => gui.clock = None,
-                    }
+                    gui.clock = settings.time.to_clock(board.to_move()).map(|mut clock| {
+                      clock.toggle_pause();
+                      clock
+                    });
                   }
                 }
                 #[cfg(not(feature = "clock"))]
@@ -749,16 +1918,20 @@ pub(crate) fn handle_loading_engine(gui: &mut LibertyChessGUI) {
                 Message::UpdateOption(..)
                 | Message::SetDebug(_)
                 | Message::Stop
+                | Message::PonderHit
                 | Message::Eval
-                | Message::Bench(_)
+                | Message::Bench(..)
                 | Message::NewGame
                 | Message::Perft(_)
                 | Message::Clock(_)
                 | Message::Info(_)
+                | Message::Kibbutz(_)
                 | Message::IsReady => (),
+                Message::Notice(notice) => gui.message = Some(notice),
               },
             },
             Err(TryRecvError::Disconnected) => {
+              interface.diagnostics.disconnect();
               clear_player = true;
               gui.message = Some("Disconnected".to_owned());
               break;
@@ -766,6 +1939,22 @@ pub(crate) fn handle_loading_engine(gui: &mut LibertyChessGUI) {
             Err(TryRecvError::Empty) => break,
           }
         }
+        // a server hosting several games might start one of the others first
+        interface.poll_games();
+        if position.is_none() {
+          if let Some(index) = interface
+            .games
+            .iter()
+            .position(|game| game.position.is_some())
+          {
+            #[cfg(feature = "clock")]
+            swap(&mut gui.clock, &mut interface.games[index].clock);
+            (position, *side) = interface.switch_game(index, None, *side);
+            if gui.config.get_opponentflip() {
+              gui.flipped = *side;
+            }
+          }
+        }
         if clear_player {
           gui.player = None;
         }
@@ -0,0 +1,187 @@
+use eframe::egui::{Context, RawInput, ViewportCommand};
+use liberty_chess::moves::Move;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use ulci::Score;
+
+// (bestmove, (score, depth))
+pub type EngineResult = (Option<Move>, Option<(Score, u16)>);
+
+// Where engine results come from
+pub enum Source {
+  Player,
+  Kibbutz,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+enum RecordedScore {
+  Win(u32),
+  Loss(u32),
+  Centipawn(i32),
+}
+
+impl From<Score> for RecordedScore {
+  fn from(score: Score) -> Self {
+    match score {
+      Score::Win(moves) => Self::Win(moves),
+      Score::Loss(moves) => Self::Loss(moves),
+      Score::Centipawn(score) => Self::Centipawn(score),
+    }
+  }
+}
+
+impl From<RecordedScore> for Score {
+  fn from(score: RecordedScore) -> Self {
+    match score {
+      RecordedScore::Win(moves) => Self::Win(moves),
+      RecordedScore::Loss(moves) => Self::Loss(moves),
+      RecordedScore::Centipawn(score) => Self::Centipawn(score),
+    }
+  }
+}
+
+type RecordedResult = (Option<String>, Option<(RecordedScore, u16)>);
+
+fn save_result((bestmove, score): EngineResult) -> RecordedResult {
+  (
+    bestmove.map(|bestmove| bestmove.to_string()),
+    score.map(|(score, depth)| (score.into(), depth)),
+  )
+}
+
+fn load_result((bestmove, score): RecordedResult) -> EngineResult {
+  (
+    bestmove.and_then(|bestmove| bestmove.parse().ok()),
+    score.map(|(score, depth)| (score.into(), depth)),
+  )
+}
+
+// Everything needed to reproduce a frame
+#[derive(Default, Deserialize, Serialize)]
+struct Frame {
+  input: RawInput,
+  // engines aren't deterministic, so their results are recorded rather than recalculated
+  player: Option<RecordedResult>,
+  kibbutz: Option<RecordedResult>,
+}
+
+impl Frame {
+  fn result(&mut self, source: &Source) -> &mut Option<RecordedResult> {
+    match source {
+      Source::Player => &mut self.player,
+      Source::Kibbutz => &mut self.kibbutz,
+    }
+  }
+}
+
+// Records user input and engine results to a file, or replays them from one
+// Saved settings are ignored while recording or replaying so the replay starts from the same state
+// Clocks and random choices when starting a game are not reproduced
+pub enum Recording {
+  Record {
+    output: BufWriter<File>,
+    frame: Option<Frame>,
+  },
+  Replay {
+    frames: VecDeque<Frame>,
+    frame: Frame,
+  },
+}
+
+impl Recording {
+  // Parse `--record <file>` or `--replay <file>` from the command line arguments
+  pub fn from_args() -> Option<Self> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "--record" => {
+          let path = args.next()?;
+          return match File::create(&path) {
+            Ok(file) => Some(Self::Record {
+              output: BufWriter::new(file),
+              frame: None,
+            }),
+            Err(error) => {
+              eprintln!("Could not create recording {path}: {error}");
+              None
+            }
+          };
+        }
+        "--replay" => {
+          let path = args.next()?;
+          return match File::open(&path) {
+            Ok(file) => {
+              let frames = BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .filter_map(|line| ron::from_str(&line).ok())
+                .collect();
+              Some(Self::Replay {
+                frames,
+                frame: Frame::default(),
+              })
+            }
+            Err(error) => {
+              eprintln!("Could not open recording {path}: {error}");
+              None
+            }
+          };
+        }
+        _ => (),
+      }
+    }
+    None
+  }
+
+  pub const fn is_replay(&self) -> bool {
+    matches!(self, Self::Replay { .. })
+  }
+
+  // Record the input for a new frame, or replace it with the recorded input when replaying
+  pub fn new_frame(&mut self, ctx: &Context, input: &mut RawInput) {
+    match self {
+      Self::Record { output, frame } => {
+        if let Some(frame) = frame.take() {
+          // flush every frame so the recording survives a crash
+          if let Ok(line) = ron::to_string(&frame) {
+            writeln!(output, "{line}").ok();
+            output.flush().ok();
+          }
+        }
+        *frame = Some(Frame {
+          input: input.clone(),
+          player: None,
+          kibbutz: None,
+        });
+      }
+      Self::Replay { frames, frame } => match frames.pop_front() {
+        Some(next_frame) => {
+          *frame = next_frame;
+          *input = frame.input.clone();
+        }
+        None => ctx.send_viewport_cmd(ViewportCommand::Close),
+      },
+    }
+  }
+
+  // Get results from an engine, either recording them or using the recorded results
+  pub fn engine(&mut self, source: &Source, poll: impl FnOnce() -> EngineResult) -> EngineResult {
+    match self {
+      Self::Record { frame, .. } => {
+        let result = poll();
+        if let Some(frame) = frame {
+          if result.0.is_some() || result.1.is_some() {
+            *frame.result(source) = Some(save_result(result));
+          }
+        }
+        result
+      }
+      Self::Replay { frame, .. } => frame
+        .result(source)
+        .take()
+        .map_or((None, None), load_result),
+    }
+  }
+}
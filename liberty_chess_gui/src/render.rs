@@ -1,22 +1,24 @@
 use crate::helpers::unwrap_tuple;
 use crate::players::{ConnectionMessage, PlayerData, UciState};
-use crate::themes::Colours;
+use crate::themes::{BoardColours, Colours, Coordinates};
 use crate::{LibertyChessGUI, Screen};
 use eframe::egui::{
-  pos2, Align2, Area, Color32, Context, FontId, PointerButton, Pos2, Rect, Response, Rounding,
-  Sense, Shape, Stroke, Ui, Vec2,
+  pos2, Align2, Area, Color32, ColorImage, Context, FontId, Painter, PointerButton, Pos2, Rect,
+  Response, Rounding, Sense, Shape, Stroke, TextureHandle, TextureId, TextureOptions, Ui, Vec2,
 };
 use liberty_chess::moves::Move;
 use liberty_chess::parsing::to_letters;
 use liberty_chess::{Board, Gamestate, Piece};
+use std::ops::Range;
 use std::sync::mpsc::TryRecvError;
+use std::time::Duration;
 use ulci::client::Message;
-use ulci::SearchTime;
+use ulci::{Score, SearchTime};
 
 #[cfg(feature = "clock")]
-use liberty_chess::clock::Clock;
+use crate::clock::record_move;
 #[cfg(feature = "clock")]
-use std::time::Duration;
+use liberty_chess::clock::Millis;
 
 #[cfg(feature = "sound")]
 use crate::helpers::update_sound;
@@ -26,34 +28,138 @@ use sound::Effect;
 #[cfg(feature = "music")]
 use crate::get_dramatic;
 
+#[cfg(feature = "recording")]
+use crate::recording::Source;
+
 //UV that does nothing
 const UV: Rect = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
 const NUMBER_SCALE: f32 = 5.0;
+// moves of the engine line drawn on the board
+const PV_ARROWS: usize = 3;
+// radius of the numbers on candidate arrows as a fraction of the square size
+const BADGE_SIZE: f32 = 0.2;
+// seconds each move of the engine line preview is shown for
+const PREVIEW_STEP: f64 = 0.8;
+
+// The checkerboard behind the pieces, cached as a texture with one pixel for each square
+#[derive(Default)]
+pub struct BoardBackground {
+  texture: Option<TextureHandle>,
+  // the colours, rows, columns and orientation the texture was made with
+  key: Option<(BoardColours, usize, usize, bool)>,
+}
+
+impl BoardBackground {
+  fn get(
+    &mut self,
+    ctx: &Context,
+    colours: BoardColours,
+    rows: usize,
+    cols: usize,
+    flipped: bool,
+  ) -> TextureId {
+    let key = Some((colours, rows, cols, flipped));
+    if self.key != key {
+      self.texture = None;
+      self.key = key;
+    }
+    self
+      .texture
+      .get_or_insert_with(|| {
+        let pixels = (0..rows)
+          .flat_map(|y| {
+            (0..cols).map(move |x| {
+              let (i, j) = if flipped {
+                (y, cols - 1 - x)
+              } else {
+                (rows - 1 - y, x)
+              };
+              if (i + j) % 2 == 0 {
+                colours.black_square
+              } else {
+                colours.white_square
+              }
+            })
+          })
+          .collect();
+        let image = ColorImage {
+          size: [cols, rows],
+          pixels,
+        };
+        // nearest filtering keeps the edges of the squares sharp
+        ctx.load_texture("board background", image, TextureOptions::NEAREST)
+      })
+      .id()
+  }
+}
 
 pub(crate) fn draw_game(gui: &mut LibertyChessGUI, ctx: &Context, mut board: Board) {
   let mut clickable;
-  clickable = !board.promotion_available() && board.state() == Gamestate::InProgress;
+  clickable =
+    !board.promotion_available() && board.state() == Gamestate::InProgress && gui.outcome.is_none();
   #[cfg(feature = "clock")]
   if let Some(clock) = &gui.clock {
     if clock.is_flagged() {
       gui.selected = None;
       clickable = false;
+      if let Some((player, _)) = &mut gui.player {
+        player.stop_pondering();
+      }
+    }
+  }
+  // the game is over, so there is no next move to search for
+  if board.state() != Gamestate::InProgress || gui.outcome.is_some() {
+    if let Some((player, _)) = &mut gui.player {
+      player.stop_pondering();
+    }
+  }
+  let instant_move = gui.config.get_instant_move();
+  let ponder = gui.config.get_ponder();
+  let premove_enabled = gui.config.get_premove();
+  // play the premove as soon as it is the user's turn
+  if clickable
+    && gui
+      .player
+      .as_ref()
+      .is_some_and(|(_, side)| *side != board.to_move())
+  {
+    if let Some(premove) = gui.premove.take() {
+      #[cfg(feature = "sound")]
+      let capture = board.get_piece(premove.end()) != 0;
+      attempt_move(
+        gui,
+        &mut board,
+        premove.start(),
+        premove.end(),
+        #[cfg(feature = "sound")]
+        capture,
+      );
     }
   }
   if let Some((player, side)) = &mut gui.player {
-    if *side == board.to_move() {
-      clickable = false;
+    if *side == board.to_move() && gui.outcome.is_none() {
+      // moves entered now are saved as a premove
+      clickable &= premove_enabled;
       #[cfg(feature = "clock")]
       if let Some(ref mut clock) = gui.clock {
         let (wtime, btime) = clock.get_clocks();
         let new_time = if board.to_move() { wtime } else { btime };
         if let SearchTime::Increment(ref mut time, _) = gui.searchtime {
-          *time = new_time.as_millis();
+          *time = Millis::from(new_time);
         }
       }
-      let (bestmove, score) = player.poll(&board, gui.searchtime);
+      #[cfg(feature = "recording")]
+      let (bestmove, score) = match &mut gui.recording {
+        Some(recording) => recording.engine(&Source::Player, || {
+          player.poll(&board, gui.searchtime, instant_move, ponder, ctx)
+        }),
+        None => player.poll(&board, gui.searchtime, instant_move, ponder, ctx),
+      };
+      #[cfg(not(feature = "recording"))]
+      let (bestmove, score) = player.poll(&board, gui.searchtime, instant_move, ponder, ctx);
       if let Some(score) = score {
         gui.eval = Some(score);
+        gui.eval_pv = Some((board.hash(), player.pv().to_vec()));
       }
       if let Some(bestmove) = bestmove {
         if let Some(position) = board.move_if_legal(bestmove) {
@@ -71,7 +177,8 @@ pub(crate) fn draw_game(gui: &mut LibertyChessGUI, ctx: &Context, mut board: Boa
           }
           #[cfg(feature = "clock")]
           if let Some(clock) = &mut gui.clock {
-            clock.update_status(&position);
+            let time = clock.update_status(&position);
+            record_move(&mut gui.move_times, &position, time);
           }
           gui.screen = Screen::Game(Box::new(position));
           // It needs 1 more frame to update for some reason
@@ -84,11 +191,12 @@ pub(crate) fn draw_game(gui: &mut LibertyChessGUI, ctx: &Context, mut board: Boa
     match player {
       PlayerData::Multiplayer(interface) => {
         let mut clear_player = false;
-        loop {
+        while !interface.awaiting_reconnect(ctx) {
           match interface.connection.try_recv() {
             Ok(message) => match message {
               ConnectionMessage::Uci(message) => match message {
                 Message::UpdatePosition(new_board) => {
+                  interface.resumed();
                   let new_board = new_board.load_from_thread();
                   *side = new_board.to_move();
                   #[cfg(feature = "sound")]
@@ -101,12 +209,25 @@ pub(crate) fn draw_game(gui: &mut LibertyChessGUI, ctx: &Context, mut board: Boa
                   gui.selected = None;
                   gui.drag = None;
                 }
+                // the server's engine is preferred over the players' analysis
                 Message::Info(result) => {
+                  if !interface.kibbutz {
+                    let mut score = result.score;
+                    if board.to_move() {
+                      score = -score;
+                    }
+                    gui.eval = Some((score, result.depth));
+                    gui.eval_pv = Some((board.hash(), result.pv));
+                  }
+                }
+                Message::Kibbutz(result) => {
+                  interface.kibbutz = true;
                   let mut score = result.score;
                   if board.to_move() {
                     score = -score;
                   }
                   gui.eval = Some((score, result.depth));
+                  gui.eval_pv = Some((board.hash(), result.pv));
                 }
                 #[cfg(feature = "clock")]
                 Message::Go(settings) => {
@@ -114,30 +235,9 @@ pub(crate) fn draw_game(gui: &mut LibertyChessGUI, ctx: &Context, mut board: Boa
                   if gui.config.get_opponentflip() {
                     gui.flipped = *side;
                   }
-                  match settings.time {
-                    SearchTime::Increment(time, inc) => {
-                      let mut clock = Clock::new_symmetric(
-                        Duration::from_millis(time as u64),
-                        Duration::from_millis(inc as u64),
-                        board.to_move(),
-                      );
-                      clock.toggle_pause();
-                      gui.clock = Some(clock);
-                    }
-                    SearchTime::Asymmetric(wtime, winc, btime, binc) => {
-                      let mut clock = Clock::new(
-                        [
-                          Duration::from_millis(wtime as u64),
-                          Duration::from_millis(btime as u64),
-                          Duration::from_millis(winc as u64),
-                          Duration::from_millis(binc as u64),
-                        ],
-                        board.to_move(),
-                      );
-                      clock.toggle_pause();
-                      gui.clock = Some(clock);
-                    }
-                    _ => (),
+                  if let Some(mut clock) = settings.time.to_clock(board.to_move()) {
+                    clock.toggle_pause();
+                    gui.clock = Some(clock);
                   }
                 }
                 #[cfg(not(feature = "clock"))]
@@ -148,52 +248,43 @@ pub(crate) fn draw_game(gui: &mut LibertyChessGUI, ctx: &Context, mut board: Boa
                   }
                 }
                 #[cfg(feature = "clock")]
-                Message::Clock(time) => match time {
-                  SearchTime::Increment(time, inc) => {
-                    let mut clock = Clock::new_symmetric(
-                      Duration::from_millis(time as u64),
-                      Duration::from_millis(inc as u64),
-                      board.to_move(),
-                    );
+                Message::Clock(time) => {
+                  gui.clock = time.to_clock(board.to_move()).map(|mut clock| {
                     clock.toggle_pause();
-                    gui.clock = Some(clock);
-                  }
-                  SearchTime::Asymmetric(wtime, winc, btime, binc) => {
-                    let mut clock = Clock::new(
-                      [
-                        Duration::from_millis(wtime as u64),
-                        Duration::from_millis(btime as u64),
-                        Duration::from_millis(winc as u64),
-                        Duration::from_millis(binc as u64),
-                      ],
-                      board.to_move(),
-                    );
-                    clock.toggle_pause();
-                    gui.clock = Some(clock);
-                  }
-                  _ => gui.clock = None,
-                },
+                    clock
+                  });
+                }
                 #[cfg(not(feature = "clock"))]
                 Message::Clock(_) => (),
                 Message::UpdateOption(..)
                 | Message::SetDebug(_)
                 | Message::Stop
+                | Message::PonderHit
                 | Message::Eval
-                | Message::Bench(_)
+                | Message::Bench(..)
                 | Message::NewGame
                 | Message::Perft(_)
                 | Message::IsReady => (),
+                Message::Notice(notice) => gui.message = Some(notice),
               },
-              ConnectionMessage::Connected(_) | ConnectionMessage::Timeout => (),
+              ConnectionMessage::Connected(output) => interface.reconnected(output),
+              ConnectionMessage::Game(game) => interface.add_game(game),
+              // a failed attempt also shows up as a disconnection
+              ConnectionMessage::Timeout => (),
             },
+            // the server keeps the game adjourned for a while, so try to get back to it
             Err(TryRecvError::Disconnected) => {
-              clear_player = true;
-              gui.message = Some("Disconnected".to_owned());
+              interface.diagnostics.disconnect();
+              if !interface.schedule_reconnect(ctx) {
+                clear_player = true;
+                gui.message = Some("Disconnected".to_owned());
+              }
               break;
             }
             Err(TryRecvError::Empty) => break,
           }
         }
+        interface.poll_games();
         if clear_player {
           gui.player = None;
         }
@@ -227,7 +318,11 @@ pub(crate) fn draw_board(
   let rows = gamestate.height();
   let cols = gamestate.width();
   let (size, board_size) = get_size(ctx, rows as f32, cols as f32);
-  let sense = if clickable {
+  let sense = if clickable && gui.config.touch() {
+    // tap-tap move entry, so dragging doesn't fight with scrolling
+    gui.drag = None;
+    Sense::click()
+  } else if clickable {
     Sense::click_and_drag()
   } else {
     gui.drag = None;
@@ -235,17 +330,52 @@ pub(crate) fn draw_board(
   };
   let (response, painter) = ui.allocate_painter(board_size, sense);
   let board_rect = response.rect;
-  painter.rect_filled(board_rect, Rounding::ZERO, Colours::WhiteSquare.value());
+  let colours = gui.config.get_theme().board_colours();
+  let background = gui
+    .background
+    .get(painter.ctx(), colours, rows, cols, flipped);
+  painter.image(background, board_rect, UV, Color32::WHITE);
   if let Some(location) = response.interact_pointer_pos() {
-    let hover = get_hovered(board_rect, location, size as usize, flipped, &gamestate);
-    register_response(gui, &mut gamestate, &response, hover);
+    // clicking the number on a candidate arrow plays that move
+    let clicked_candidate = candidate_moves(gui, &gamestate)
+      .into_iter()
+      .find(|(_, mv, _)| {
+        response.clicked()
+          && gui.selected.is_none()
+          && badge_centre(board_rect, size, flipped, *mv).distance(location) <= size * BADGE_SIZE
+      });
+    if let Some((_, mv, _)) = clicked_candidate {
+      #[cfg(feature = "sound")]
+      let capture = gamestate.get_piece(mv.end()) != 0;
+      attempt_move(
+        gui,
+        &mut gamestate,
+        mv.start(),
+        mv.end(),
+        #[cfg(feature = "sound")]
+        capture,
+      );
+    } else {
+      let hover = get_hovered(board_rect, location, size as usize, flipped, &gamestate);
+      register_response(gui, &mut gamestate, &response, hover);
+    }
   }
   let (dragged, offset) = unwrap_tuple(gui.drag);
-  let numbers = size >= NUMBER_SCALE && gui.config.get_numbers();
+  let coordinates = if size >= NUMBER_SCALE {
+    gui.config.get_coordinates()
+  } else {
+    Coordinates::Hidden
+  };
+  let numbers = coordinates == Coordinates::Edges;
   let mut dragged_image = None;
+  let mut squares = Vec::new();
   let mut images = Vec::new();
   let mut text = Vec::new();
-  for i in (0..rows).rev() {
+  // only the squares that can be seen are drawn, which matters on very large boards
+  let visible = painter.clip_rect().intersect(board_rect);
+  let (visible_rows, visible_cols) =
+    visible_squares(board_rect, visible, size, (rows, cols), flipped);
+  for i in visible_rows.rev() {
     let (min_y, max_y) = (i as f32, (i + 1) as f32);
     let (min_y, max_y) = if flipped {
       (
@@ -266,7 +396,7 @@ pub(crate) fn draw_board(
         Align2::RIGHT_TOP,
       ));
     }
-    for j in 0..cols {
+    for j in visible_cols.clone() {
       let coords = (i, j);
       let black_square = (i + j) % 2 == 0;
       let min_x = if flipped {
@@ -283,6 +413,10 @@ pub(crate) fn draw_board(
         min: pos2(min_x, min_y),
         max: pos2(max_x, max_y),
       };
+      if coordinates == Coordinates::Squares {
+        let name = to_letters(j).iter().collect::<String>() + &(i + 1).to_string();
+        text.push((rect.left_bottom(), name, black_square, Align2::LEFT_BOTTOM));
+      }
       let mut colour = if black_square {
         Colours::BlackSquare
       } else {
@@ -307,6 +441,11 @@ pub(crate) fn draw_board(
       } else {
         (gui.selected, rect)
       };
+      if let Some(premove) = gui.premove {
+        if coords == premove.start() || coords == premove.end() {
+          colour = Colours::Selected;
+        }
+      }
       if let Some(start) = selected {
         if start == coords {
           colour = Colours::Selected;
@@ -326,8 +465,13 @@ pub(crate) fn draw_board(
           }
         }
       }
-      if colour != Colours::WhiteSquare {
-        painter.rect_filled(rect, Rounding::ZERO, colour.value());
+      // plain squares are already part of the background
+      if !matches!(colour, Colours::WhiteSquare | Colours::BlackSquare) {
+        squares.push(Shape::rect_filled(
+          rect,
+          Rounding::ZERO,
+          colours.get(&colour),
+        ));
       }
       if piece != 0 {
         let texture = gui.get_image(painter.ctx(), piece, size as u32);
@@ -340,12 +484,13 @@ pub(crate) fn draw_board(
       };
     }
   }
+  painter.extend(squares);
   painter.extend(images);
   if let Some(image) = dragged_image {
     painter.add(image);
   }
   if numbers {
-    for i in 0..cols {
+    for i in visible_cols {
       let x = if flipped {
         ((i + 1) as f32).mul_add(-size, board_rect.max.x)
       } else {
@@ -370,39 +515,255 @@ pub(crate) fn draw_board(
       text,
       FontId::proportional(size / NUMBER_SCALE),
       if *colour {
-        Colours::WhiteSquare
+        colours.white_square
       } else {
-        Colours::BlackSquare
-      }
-      .value(),
+        colours.black_square
+      },
     );
   }
   if let Some((player, bestmove)) = &mut gui.kibbutz {
-    let (_, score, pv) = player.get_move(&gamestate, SearchTime::Infinite);
+    let mut line = Vec::new();
+    let mut get_move = || {
+      let (_, score, pv) = player.get_move(&gamestate, SearchTime::Infinite);
+      let new_move = pv.first().copied();
+      line = pv;
+      (new_move, score)
+    };
+    #[cfg(feature = "recording")]
+    let (new_move, score) = match &mut gui.recording {
+      Some(recording) => recording.engine(&Source::Kibbutz, get_move),
+      None => get_move(),
+    };
+    #[cfg(not(feature = "recording"))]
+    let (new_move, score) = get_move();
     if let Some(score) = score {
       gui.eval = Some(score);
+      if !line.is_empty() {
+        gui.eval_pv = Some((gamestate.hash(), line));
+      }
     }
-    if let Some(new_move) = pv.first() {
-      *bestmove = Some(*new_move);
+    if let Some(new_move) = new_move {
+      *bestmove = Some(new_move);
+    }
+  }
+  if gui.kibbutz.is_some() {
+    let candidates = candidate_moves(gui, &gamestate);
+    if candidates.is_empty() {
+      if let Some((_, Some(bestmove))) = &gui.kibbutz {
+        draw_arrow(
+          &painter,
+          board_rect,
+          size,
+          flipped,
+          *bestmove,
+          Color32::GRAY,
+        );
+      }
     }
-    if let Some(bestmove) = bestmove {
-      let start = bestmove.start();
-      let end = bestmove.end();
-      let start = (start.1 as f32 + 0.5, start.0 as f32 + 0.5);
-      let end = (end.1 as f32 + 0.5, end.0 as f32 + 0.5);
-      let start = (
-        start.0.mul_add(size, board_rect.min.x),
-        start.1.mul_add(-size, board_rect.max.y),
+    // the best line is drawn last so it stays on top
+    for (number, mv, score) in candidates.into_iter().rev() {
+      let colour = if number == 1 {
+        Color32::GRAY
+      } else {
+        Color32::GRAY.gamma_multiply(0.6)
+      };
+      draw_arrow(&painter, board_rect, size, flipped, mv, colour);
+      let centre = badge_centre(board_rect, size, flipped, mv);
+      let radius = size * BADGE_SIZE;
+      painter.circle(
+        centre,
+        radius,
+        Color32::from_black_alpha(200),
+        Stroke::new(size / 40.0, colour),
+      );
+      painter.text(
+        centre,
+        Align2::CENTER_CENTER,
+        number.to_string(),
+        FontId::proportional(radius * 1.2),
+        Color32::WHITE,
+      );
+      let galley = painter.layout_no_wrap(
+        candidate_score(&gamestate, score),
+        FontId::proportional(radius),
+        Color32::WHITE,
       );
-      let end = (
-        end.0.mul_add(size, board_rect.min.x),
-        end.1.mul_add(-size, board_rect.max.y),
+      let rect = Align2::CENTER_TOP
+        .anchor_size(centre + Vec2::new(0.0, radius), galley.size())
+        .expand(size / 40.0);
+      painter.rect_filled(
+        rect,
+        Rounding::same(size / 20.0),
+        Color32::from_black_alpha(200),
+      );
+      painter.galley(rect.min + Vec2::splat(size / 40.0), galley, Color32::WHITE);
+    }
+  }
+  // the start of the engine line, fading out further into the line
+  if gui.config.get_evalbar() {
+    if let Some((_, pv)) = gui
+      .eval_pv
+      .as_ref()
+      .filter(|(hash, _)| *hash == gamestate.hash())
+    {
+      let mut position = gamestate.clone();
+      for (i, mv) in pv.iter().take(PV_ARROWS).enumerate() {
+        let Some(new_position) = position.move_if_legal(*mv) else {
+          break;
+        };
+        position = new_position;
+        let colour = Color32::LIGHT_BLUE.gamma_multiply(0.2f32.mul_add(-(i as f32), 0.7));
+        draw_arrow(&painter, board_rect, size, flipped, *mv, colour);
+      }
+    }
+  }
+}
+
+// The first move of each kibbutz line when several lines are searched, numbered from the best
+fn candidate_moves(gui: &LibertyChessGUI, board: &Board) -> Vec<(usize, Move, Score)> {
+  let Some((engine, _)) = &gui.kibbutz else {
+    return Vec::new();
+  };
+  if engine.multipv() <= 1 {
+    return Vec::new();
+  }
+  engine
+    .lines(board)
+    .iter()
+    .enumerate()
+    .filter_map(|(i, line)| {
+      let mv = *line.pv.first()?;
+      board.move_if_legal(mv).map(|_| (i + 1, mv, line.score))
+    })
+    .collect()
+}
+
+// The score of a candidate move for the side to move
+pub(crate) fn candidate_score(board: &Board, score: Score) -> String {
+  match score {
+    Score::Win(moves) => format!("#{}", moves.saturating_sub(board.moves())),
+    Score::Loss(moves) => format!("#-{}", moves.saturating_sub(board.moves())),
+    Score::Centipawn(score) => format!("{:+.2}", score as f32 / 100.0),
+  }
+}
+
+// Where the number of a candidate arrow is drawn, halfway along the arrow
+fn badge_centre(board_rect: Rect, size: f32, flipped: bool, mv: Move) -> Pos2 {
+  let start = square_centre(board_rect, size, flipped, mv.start());
+  let end = square_centre(board_rect, size, flipped, mv.end());
+  start.lerp(end, 0.5)
+}
+
+// The centre of a square on the screen
+fn square_centre(
+  board_rect: Rect,
+  size: f32,
+  flipped: bool,
+  (row, column): (usize, usize),
+) -> Pos2 {
+  let (row, column) = (row as f32 + 0.5, column as f32 + 0.5);
+  if flipped {
+    pos2(
+      column.mul_add(-size, board_rect.max.x),
+      row.mul_add(size, board_rect.min.y),
+    )
+  } else {
+    pos2(
+      column.mul_add(size, board_rect.min.x),
+      row.mul_add(-size, board_rect.max.y),
+    )
+  }
+}
+
+// Draw a move as a line with a dot on the destination
+fn draw_arrow(
+  painter: &Painter,
+  board_rect: Rect,
+  size: f32,
+  flipped: bool,
+  mv: Move,
+  colour: Color32,
+) {
+  let start = square_centre(board_rect, size, flipped, mv.start());
+  let end = square_centre(board_rect, size, flipped, mv.end());
+  let stroke = Stroke::new(size / 10.0, colour);
+  painter.line_segment([start, end], stroke);
+  painter.circle(end, size / 10.0, colour, stroke);
+}
+
+// Draw a small board walking through the line the engine is considering
+pub(crate) fn draw_preview(gui: &mut LibertyChessGUI, ui: &mut Ui, board: &Board, pv: &[Move]) {
+  let step = (ui.input(|input| input.time) / PREVIEW_STEP) as usize % pv.len() + 1;
+  let mut position = board.clone();
+  for mv in &pv[..step] {
+    match position.move_if_legal(*mv) {
+      Some(new_position) => position = new_position,
+      None => break,
+    }
+  }
+  ui.label(format!("Engine line: move {step} of {}", pv.len()));
+  let flipped = gui.flipped;
+  draw_small_board(
+    gui,
+    ui,
+    &position,
+    ui.available_width(),
+    flipped,
+    Sense::hover(),
+  );
+  ui.ctx()
+    .request_repaint_after(Duration::from_secs_f64(PREVIEW_STEP));
+}
+
+// Draw a board without any interaction, fitting in the given width
+pub(crate) fn draw_small_board(
+  gui: &mut LibertyChessGUI,
+  ui: &mut Ui,
+  position: &Board,
+  width: f32,
+  flipped: bool,
+  sense: Sense,
+) -> Response {
+  let rows = position.height();
+  let cols = position.width();
+  let size = (width / cols as f32).floor().max(1.0);
+  let (response, painter) =
+    ui.allocate_painter(Vec2::new(size * cols as f32, size * rows as f32), sense);
+  let board_rect = response.rect;
+  let colours = gui.config.get_theme().board_colours();
+  for i in 0..rows {
+    for j in 0..cols {
+      let (x, y) = if flipped {
+        (cols - 1 - j, i)
+      } else {
+        (j, rows - 1 - i)
+      };
+      let rect = Rect::from_min_size(
+        pos2(
+          (x as f32).mul_add(size, board_rect.min.x),
+          (y as f32).mul_add(size, board_rect.min.y),
+        ),
+        Vec2::splat(size),
       );
-      let stroke = Stroke::new(size / 10.0, Color32::GRAY);
-      painter.line_segment([start.into(), end.into()], stroke);
-      painter.circle(end.into(), size / 10.0, Color32::GRAY, stroke);
+      let mut colour = if (i + j) % 2 == 0 {
+        Colours::BlackSquare
+      } else {
+        Colours::WhiteSquare
+      };
+      if let Some(last_move) = position.last_move {
+        if (i, j) == last_move.start() || (i, j) == last_move.end() {
+          colour = Colours::Moved;
+        }
+      }
+      painter.rect_filled(rect, Rounding::ZERO, colours.get(&colour));
+      let piece = position.get_piece((i, j));
+      if piece != 0 {
+        let texture = gui.get_preview_image(painter.ctx(), piece, size as u32);
+        painter.add(Shape::image(texture, rect, UV, Color32::WHITE));
+      }
     }
   }
+  response
 }
 
 fn get_size(ctx: &Context, rows: f32, cols: f32) -> (f32, Vec2) {
@@ -417,6 +778,33 @@ fn get_size(ctx: &Context, rows: f32, cols: f32) -> (f32, Vec2) {
   (size, board_size)
 }
 
+// The rows and columns of the squares at least partly inside the visible area
+fn visible_squares(
+  board_rect: Rect,
+  visible: Rect,
+  size: f32,
+  (rows, cols): (usize, usize),
+  flipped: bool,
+) -> (Range<usize>, Range<usize>) {
+  let (x, y) = if flipped {
+    (
+      board_rect.max.x - visible.max.x..board_rect.max.x - visible.min.x,
+      visible.min.y - board_rect.min.y..visible.max.y - board_rect.min.y,
+    )
+  } else {
+    (
+      visible.min.x - board_rect.min.x..visible.max.x - board_rect.min.x,
+      board_rect.max.y - visible.max.y..board_rect.max.y - visible.min.y,
+    )
+  };
+  let squares = |range: Range<f32>, count: usize| {
+    let start = ((range.start / size).floor().max(0.0) as usize).min(count);
+    let end = ((range.end / size).ceil().max(0.0) as usize).min(count);
+    start..end
+  };
+  (squares(y, rows), squares(x, cols))
+}
+
 fn get_hovered(
   board_rect: Rect,
   location: Pos2,
@@ -443,21 +831,41 @@ fn register_response(
   response: &Response,
   hover: Option<((usize, usize), Piece)>,
 ) {
+  // on the opponent's turn, the user moves the other side's pieces to enter a premove
+  let premove = gui
+    .player
+    .as_ref()
+    .is_some_and(|(_, side)| *side == gamestate.to_move());
+  if response.secondary_clicked() {
+    gui.premove = None;
+    gui.selected = None;
+  }
   if let Some((coords, piece)) = hover {
     let capture = piece != 0;
-    let valid_piece = capture && gamestate.to_move() == (piece > 0);
+    let valid_piece = capture && (gamestate.to_move() != premove) == (piece > 0);
     if response.clicked() {
-      if let Some(selected) = gui.selected {
-        attempt_move(
-          gui,
-          gamestate,
-          selected,
-          coords,
-          #[cfg(feature = "sound")]
-          capture,
-        );
+      if let Some(selected) = gui.selected.filter(|&selected| {
+        // with touch input, tapping another friendly piece selects it instead
+        !(gui.config.touch()
+          && valid_piece
+          && (premove || !gamestate.check_pseudolegal(selected, coords)))
+      }) {
+        if premove {
+          set_premove(gui, selected, coords);
+        } else {
+          attempt_move(
+            gui,
+            gamestate,
+            selected,
+            coords,
+            #[cfg(feature = "sound")]
+            capture,
+          );
+        }
       } else if valid_piece {
-        gui.selected = Some(coords);
+        gui.premove = None;
+        // tapping the selected piece again deselects it
+        gui.selected = (gui.selected != Some(coords)).then_some(coords);
       }
     }
     if response.drag_started() && response.dragged_by(PointerButton::Primary) && valid_piece {
@@ -469,14 +877,18 @@ fn register_response(
     if response.drag_stopped() {
       #[cfg(feature = "sound")]
       if let Some((coords, piece)) = hover {
-        if start != coords {
+        if premove {
+          set_premove(gui, start, coords);
+        } else if start != coords {
           let capture = piece != 0;
           attempt_move(gui, gamestate, start, coords, capture);
         }
       }
       #[cfg(not(feature = "sound"))]
       if let Some((coords, _)) = hover {
-        if start != coords {
+        if premove {
+          set_premove(gui, start, coords);
+        } else if start != coords {
           attempt_move(gui, gamestate, start, coords);
         }
       }
@@ -485,6 +897,12 @@ fn register_response(
   }
 }
 
+// Save a move to play once it is the user's turn, checking its legality then
+fn set_premove(gui: &mut LibertyChessGUI, start: (usize, usize), end: (usize, usize)) {
+  gui.premove = (start != end).then(|| Move::new(start, end));
+  gui.selected = None;
+}
+
 fn attempt_move(
   gui: &mut LibertyChessGUI,
   gamestate: &mut Board,
@@ -504,7 +922,8 @@ fn attempt_move(
         newstate.update();
         #[cfg(feature = "clock")]
         if let Some(clock) = &mut gui.clock {
-          clock.update_status(&newstate);
+          let time = clock.update_status(&newstate);
+          record_move(&mut gui.move_times, &newstate, time);
         }
       }
       if gui.player.is_none() && gui.config.get_autoflip() {
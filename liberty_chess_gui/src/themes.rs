@@ -10,7 +10,18 @@ fn rgba_to_string(rgba: Color32) -> String {
   for (i, value) in colour.iter().enumerate() {
     result += u32::from(*value) << (24 - 8 * i);
   }
-  format!("{result:X}")
+  format!("{result:08X}")
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn string_to_rgba(string: &str) -> Result<Color32, ()> {
+  let value = u32::from_str_radix(string, 16).map_err(|_| ())?;
+  Ok(Color32::from_rgba_unmultiplied(
+    (value >> 24) as u8,
+    (value >> 16) as u8,
+    (value >> 8) as u8,
+    value as u8,
+  ))
 }
 
 pub trait GetVisuals {
@@ -30,6 +41,13 @@ impl Theme {
       Self::Custom(_) => "Custom".to_owned(),
     }
   }
+
+  pub const fn board_colours(&self) -> BoardColours {
+    match self {
+      Self::Preset(preset) => preset.board_colours(),
+      Self::Custom(custom) => custom.board,
+    }
+  }
 }
 
 impl GetVisuals for Theme {
@@ -65,6 +83,7 @@ impl FromStr for Theme {
 pub struct CustomTheme {
   pub background: Color32,
   pub text: Color32,
+  pub board: BoardColours,
 }
 
 impl CustomTheme {
@@ -88,7 +107,7 @@ impl GetVisuals for CustomTheme {
 
 impl ToString for CustomTheme {
   fn to_string(&self) -> String {
-    rgba_to_string(self.background) + &rgba_to_string(self.text)
+    rgba_to_string(self.background) + &rgba_to_string(self.text) + &self.board.to_string()
   }
 }
 
@@ -97,6 +116,14 @@ impl FromStr for CustomTheme {
 
   #[allow(clippy::cast_possible_truncation)]
   fn from_str(theme: &str) -> Result<Self, Self::Err> {
+    // themes saved before board colours were added only have the background and text colours
+    if theme.len() > 16 {
+      let (colours, board) = theme.split_at(16);
+      return Ok(Self {
+        board: board.parse()?,
+        ..colours.parse::<Self>()?
+      });
+    }
     let value = u64::from_str_radix(theme, 16).map_err(|_| ())?;
     Ok(Self {
       background: Color32::from_rgba_unmultiplied(
@@ -111,10 +138,143 @@ impl FromStr for CustomTheme {
         (value >> 8) as u8,
         value as u8,
       ),
+      board: BoardColours::DEFAULT,
+    })
+  }
+}
+
+// The colours used to draw the board
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct BoardColours {
+  pub black_square: Color32,
+  pub white_square: Color32,
+  pub moved: Color32,
+  pub selected: Color32,
+  pub valid_black: Color32,
+  pub valid_white: Color32,
+  pub threatened_black: Color32,
+  pub threatened_white: Color32,
+  pub check: Color32,
+}
+
+impl BoardColours {
+  const DEFAULT: Self = Self {
+    black_square: Colours::BlackSquare.value(),
+    white_square: Colours::WhiteSquare.value(),
+    moved: Colours::Moved.value(),
+    selected: Colours::Selected.value(),
+    valid_black: Colours::ValidBlack.value(),
+    valid_white: Colours::ValidWhite.value(),
+    threatened_black: Colours::ThreatenedBlack.value(),
+    threatened_white: Colours::ThreatenedWhite.value(),
+    check: Colours::Check.value(),
+  };
+
+  // Strongly contrasting colours for accessibility
+  const HIGH_CONTRAST: Self = Self {
+    black_square: Color32::from_rgb(96, 96, 96),
+    white_square: Color32::from_rgb(224, 224, 224),
+    moved: Color32::from_rgb(0, 128, 255),
+    selected: Color32::from_rgb(255, 255, 0),
+    valid_black: Color32::from_rgb(0, 176, 0),
+    valid_white: Color32::from_rgb(64, 255, 64),
+    threatened_black: Color32::from_rgb(192, 0, 192),
+    threatened_white: Color32::from_rgb(255, 64, 255),
+    check: Color32::from_rgb(255, 0, 0),
+  };
+
+  pub const fn get(&self, colour: &Colours) -> Color32 {
+    match colour {
+      Colours::BlackSquare => self.black_square,
+      Colours::WhiteSquare => self.white_square,
+      Colours::Moved => self.moved,
+      Colours::Selected => self.selected,
+      Colours::ValidBlack => self.valid_black,
+      Colours::ValidWhite => self.valid_white,
+      Colours::ThreatenedBlack => self.threatened_black,
+      Colours::ThreatenedWhite => self.threatened_white,
+      Colours::Check => self.check,
+      Colours::LowTime => colour.value(),
+    }
+  }
+
+  const fn colours(&self) -> [Color32; 9] {
+    [
+      self.black_square,
+      self.white_square,
+      self.moved,
+      self.selected,
+      self.valid_black,
+      self.valid_white,
+      self.threatened_black,
+      self.threatened_white,
+      self.check,
+    ]
+  }
+}
+
+impl ToString for BoardColours {
+  fn to_string(&self) -> String {
+    self.colours().map(rgba_to_string).concat()
+  }
+}
+
+impl FromStr for BoardColours {
+  type Err = ();
+
+  fn from_str(colours: &str) -> Result<Self, Self::Err> {
+    if colours.len() != 72 || !colours.is_ascii() {
+      return Err(());
+    }
+    let colour = |i: usize| string_to_rgba(&colours[i * 8..(i + 1) * 8]);
+    Ok(Self {
+      black_square: colour(0)?,
+      white_square: colour(1)?,
+      moved: colour(2)?,
+      selected: colour(3)?,
+      valid_black: colour(4)?,
+      valid_white: colour(5)?,
+      threatened_black: colour(6)?,
+      threatened_white: colour(7)?,
+      check: colour(8)?,
     })
   }
 }
 
+// Where the names of the ranks and files are shown on the board
+#[derive(Clone, Copy, Eq, PartialEq, Sequence)]
+pub enum Coordinates {
+  Hidden,
+  Edges,
+  Squares,
+}
+
+impl ToString for Coordinates {
+  fn to_string(&self) -> String {
+    match self {
+      Self::Hidden => "Hidden",
+      Self::Edges => "Board edges",
+      Self::Squares => "Every square",
+    }
+    .to_owned()
+  }
+}
+
+impl FromStr for Coordinates {
+  type Err = ();
+
+  fn from_str(coordinates: &str) -> Result<Self, Self::Err> {
+    match coordinates {
+      // saved before there was a choice of style
+      "true" => Ok(Self::Edges),
+      "false" => Ok(Self::Hidden),
+      _ => all::<Self>()
+        .find(|&possible_style| possible_style.to_string() == coordinates)
+        .ok_or(()),
+    }
+  }
+}
+
 #[derive(Clone, Copy, Eq, PartialEq, Sequence)]
 pub enum PresetTheme {
   Dark,
@@ -124,6 +284,7 @@ pub enum PresetTheme {
   Blue,
   Purple,
   Light,
+  HighContrast,
 }
 
 impl PresetTheme {
@@ -132,6 +293,14 @@ impl PresetTheme {
     CustomTheme {
       background: visuals.panel_fill,
       text: visuals.text_color(),
+      board: self.board_colours(),
+    }
+  }
+
+  const fn board_colours(self) -> BoardColours {
+    match self {
+      Self::HighContrast => BoardColours::HIGH_CONTRAST,
+      _ => BoardColours::DEFAULT,
     }
   }
 }
@@ -146,6 +315,7 @@ impl ToString for PresetTheme {
       Self::Blue => "Blue",
       Self::Purple => "Purple",
       Self::Light => "Light",
+      Self::HighContrast => "High contrast",
     }
     .to_owned()
   }
@@ -176,6 +346,12 @@ impl GetVisuals for PresetTheme {
         ..Visuals::dark()
       },
       Self::Light => Visuals::light(),
+      Self::HighContrast => Visuals {
+        override_text_color: Some(Color32::WHITE),
+        panel_fill: Color32::BLACK,
+        window_fill: Color32::BLACK,
+        ..Visuals::dark()
+      },
     }
   }
 }
@@ -201,6 +377,7 @@ pub enum Colours {
   ThreatenedBlack,
   ThreatenedWhite,
   Check,
+  LowTime,
 }
 
 impl Colours {
@@ -215,6 +392,7 @@ impl Colours {
       Self::ThreatenedBlack => Color32::from_rgb(180, 74, 0),
       Self::ThreatenedWhite => Color32::from_rgb(200, 107, 0),
       Self::Check => Color32::from_rgb(192, 0, 0),
+      Self::LowTime => Color32::from_rgb(255, 128, 0),
     }
   }
 }
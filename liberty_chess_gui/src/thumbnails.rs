@@ -0,0 +1,106 @@
+use crate::images::image_index;
+use crate::themes::BoardColours;
+use eframe::egui::load::SizedTexture;
+use eframe::egui::{Color32, ColorImage, Context, Image, TextureHandle, TextureOptions, Vec2};
+use liberty_chess::Board;
+use resvg::render;
+use resvg::tiny_skia::{Color, Paint, Pixmap, Rect, Transform};
+use resvg::usvg::{FitTo, Tree};
+use std::collections::HashMap;
+
+// largest side of a thumbnail in points
+const THUMBNAIL_SIZE: f32 = 40.0;
+// custom positions get cached as they are typed, so the cache is cleared past this size
+const MAX_CACHED: usize = 32;
+
+// Small previews of starting positions, rendered separately from the main board
+#[derive(Default)]
+pub struct Thumbnails {
+  // None for FENs that don't load
+  cache: HashMap<String, Option<TextureHandle>>,
+  // the colours and scale the cached thumbnails were rendered with
+  colours: Option<BoardColours>,
+  pixels_per_point: f32,
+}
+
+impl Thumbnails {
+  // Get a preview of the position, or None if the FEN is invalid
+  pub fn get<'a>(
+    &mut self,
+    images: &[Tree; 36],
+    ctx: &Context,
+    fen: &str,
+    colours: BoardColours,
+  ) -> Option<Image<'a>> {
+    let pixels_per_point = ctx.pixels_per_point();
+    if self.colours != Some(colours)
+      || self.pixels_per_point != pixels_per_point
+      || (self.cache.len() >= MAX_CACHED && !self.cache.contains_key(fen))
+    {
+      self.cache.clear();
+      self.colours = Some(colours);
+      self.pixels_per_point = pixels_per_point;
+    }
+    let texture = self
+      .cache
+      .entry(fen.to_owned())
+      .or_insert_with(|| {
+        let board = Board::new(fen).ok()?;
+        let image = render_thumbnail(images, &board, colours, pixels_per_point)?;
+        Some(ctx.load_texture("thumbnail", image, TextureOptions::LINEAR))
+      })
+      .as_ref()?;
+    let [width, height] = texture.size();
+    Some(Image::new(SizedTexture {
+      id: texture.id(),
+      size: Vec2::new(width as f32, height as f32) / pixels_per_point,
+    }))
+  }
+}
+
+fn paint(colour: Color32) -> Paint<'static> {
+  let mut paint = Paint::default();
+  paint.set_color(Color::from_rgba8(
+    colour.r(),
+    colour.g(),
+    colour.b(),
+    u8::MAX,
+  ));
+  paint
+}
+
+// Draw the squares and pieces with white at the bottom
+fn render_thumbnail(
+  images: &[Tree; 36],
+  board: &Board,
+  colours: BoardColours,
+  pixels_per_point: f32,
+) -> Option<ColorImage> {
+  let (rows, cols) = (board.height(), board.width());
+  let size = ((THUMBNAIL_SIZE * pixels_per_point) as usize / rows.max(cols)).max(1);
+  let (width, height) = (cols * size, rows * size);
+  let mut pixmap = Pixmap::new(width as u32, height as u32)?;
+  let (black, white) = (paint(colours.black_square), paint(colours.white_square));
+  for i in 0..rows {
+    for j in 0..cols {
+      let (x, y) = ((j * size) as f32, ((rows - 1 - i) * size) as f32);
+      let square = if (i + j) % 2 == 0 { &black } else { &white };
+      let rect = Rect::from_xywh(x, y, size as f32, size as f32)?;
+      pixmap.fill_rect(rect, square, Transform::identity(), None);
+      let piece = board.get_piece((i, j));
+      if piece != 0 {
+        render(
+          &images[image_index(piece)],
+          FitTo::Size(size as u32, size as u32),
+          Transform::from_translate(x, y),
+          pixmap.as_mut(),
+        )?;
+      }
+    }
+  }
+  // every pixel is opaque, so premultiplied alpha doesn't matter
+  Some(ColorImage::from_rgba_unmultiplied(
+    [width, height],
+    pixmap.data(),
+  ))
+}
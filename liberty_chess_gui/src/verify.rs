@@ -0,0 +1,142 @@
+use eframe::egui::Context;
+use liberty_chess::moves::Move;
+use liberty_chess::{perft, Board};
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::thread::spawn;
+use ulci::server::{startup_server, InfoType, Request, UlciResult};
+
+// depth to compare move generation to
+const VERIFY_DEPTH: usize = 3;
+
+type PerftCounts = (Vec<(Move, usize)>, usize);
+
+// Compares perft counts from an external engine with the GUI's move generation
+pub struct PositionCheck {
+  // kept to stop the engine connection from closing
+  _tx: Sender<Request>,
+  rx: Receiver<UlciResult>,
+  board: Board,
+  expected: Receiver<PerftCounts>,
+  engine: Option<PerftCounts>,
+  gui: Option<PerftCounts>,
+}
+
+impl PositionCheck {
+  pub fn new(path: &str, board: &Board, ctx: &Context) -> Result<Self, String> {
+    let (send_request, recieve_request) = channel();
+    let (send_result, recieve_result) = channel();
+    let mut engine = Command::new(path)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .spawn()
+      .map_err(|_| "Invalid path".to_owned())?;
+    let stdin = engine
+      .stdin
+      .take()
+      .ok_or_else(|| "Could not load stdin".to_owned())?;
+    let stdout = BufReader::new(
+      engine
+        .stdout
+        .take()
+        .ok_or_else(|| "Could not load stdout".to_owned())?,
+    );
+    let engine_ctx = ctx.clone();
+    spawn(move || {
+      startup_server(
+        recieve_request,
+        &send_result,
+        stdout,
+        stdin,
+        false,
+        move || engine_ctx.request_repaint(),
+      );
+    });
+    send_request
+      .send(Request::Perft(board.to_string(), VERIFY_DEPTH))
+      .ok();
+    // count the positions on another thread to keep the GUI responsive
+    let (send_expected, expected) = channel();
+    let position = board.send_to_thread();
+    let ctx = ctx.clone();
+    spawn(move || {
+      let mut board = position.load_from_thread();
+      board.skip_checkmate = true;
+      let mut moves = Vec::new();
+      let mut total = 0;
+      for position in board.generate_legal() {
+        let count = perft(&position, VERIFY_DEPTH - 1);
+        total += count;
+        if let Some(mv) = position.last_move {
+          moves.push((mv, count));
+        }
+      }
+      send_expected.send((moves, total)).ok();
+      ctx.request_repaint();
+    });
+    Ok(Self {
+      _tx: send_request,
+      rx: recieve_result,
+      board: board.clone(),
+      expected,
+      engine: None,
+      gui: None,
+    })
+  }
+
+  // Returns a report once both sides have finished counting
+  pub fn poll(&mut self) -> Option<String> {
+    if self.gui.is_none() {
+      self.gui = self.expected.try_recv().ok();
+    }
+    loop {
+      match self.rx.try_recv() {
+        Ok(UlciResult::Perft(moves, total)) => self.engine = Some((moves, total)),
+        Ok(UlciResult::Startup(info)) => {
+          if !info.supports(&self.board) {
+            return Some("Engine does not support position".to_owned());
+          }
+        }
+        Ok(UlciResult::Info(InfoType::Error, error)) => {
+          return Some(format!("Engine error: {error}"));
+        }
+        Ok(UlciResult::Analysis(_) | UlciResult::AnalysisStopped(_) | UlciResult::Info(..)) => (),
+        Err(TryRecvError::Empty) => break,
+        Err(TryRecvError::Disconnected) => {
+          if self.engine.is_none() {
+            return Some("Engine has crashed".to_owned());
+          }
+          break;
+        }
+      }
+    }
+    let (engine_moves, engine_total) = self.engine.as_ref()?;
+    let (gui_moves, gui_total) = self.gui.as_ref()?;
+    let mut counts: HashMap<String, (Option<usize>, Option<usize>)> = HashMap::new();
+    for (mv, count) in engine_moves {
+      counts.entry(mv.to_string()).or_default().0 = Some(*count);
+    }
+    for (mv, count) in gui_moves {
+      counts.entry(mv.to_string()).or_default().1 = Some(*count);
+    }
+    let mut mismatches: Vec<String> = counts
+      .into_iter()
+      .filter(|(_, (engine, gui))| engine != gui)
+      .map(|(mv, (engine, gui))| {
+        let show = |count: Option<usize>| count.map_or("missing".to_owned(), |c| c.to_string());
+        format!("{mv}: engine {}, GUI {}", show(engine), show(gui))
+      })
+      .collect();
+    mismatches.sort();
+    Some(if mismatches.is_empty() && engine_total == gui_total {
+      format!("Perft {VERIFY_DEPTH} matches: {gui_total} positions")
+    } else {
+      format!(
+        "Perft {VERIFY_DEPTH} mismatch: engine {engine_total}, GUI {gui_total}\n{}",
+        mismatches.join("\n")
+      )
+    })
+  }
+}
@@ -0,0 +1,105 @@
+use crate::gamemodes::{GameMode, Presets};
+use crate::render::draw_small_board;
+use crate::{draw_appearance_settings, switch_screen, LibertyChessGUI, Screen};
+use eframe::egui::{Context, ScrollArea, Sense, Ui};
+use liberty_chess::Board;
+
+#[cfg(feature = "sound")]
+use crate::draw_sound_settings;
+
+// variants suggested to new players, roughly in order of how different they are from chess
+const VARIANTS: [Presets; 6] = [
+  Presets::Standard,
+  Presets::Mini,
+  Presets::CapablancaRectangle,
+  Presets::Liberty,
+  Presets::Horde,
+  Presets::Double,
+];
+
+// The pages of the guided setup shown on first launch
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Step {
+  Appearance,
+  #[cfg(feature = "sound")]
+  Sound,
+  LearnOrPlay,
+  Variants,
+}
+
+impl Step {
+  const fn title(self) -> &'static str {
+    match self {
+      Self::Appearance => "Choose how Liberty Chess looks",
+      #[cfg(feature = "sound")]
+      Self::Sound => "Set up sound",
+      Self::LearnOrPlay => "Are you new to chess variants?",
+      Self::Variants => "Pick a variant to start with",
+    }
+  }
+
+  const fn next(self) -> Option<Self> {
+    match self {
+      #[cfg(feature = "sound")]
+      Self::Appearance => Some(Self::Sound),
+      #[cfg(not(feature = "sound"))]
+      Self::Appearance => Some(Self::LearnOrPlay),
+      #[cfg(feature = "sound")]
+      Self::Sound => Some(Self::LearnOrPlay),
+      Self::LearnOrPlay => Some(Self::Variants),
+      Self::Variants => None,
+    }
+  }
+}
+
+pub(crate) fn draw(gui: &mut LibertyChessGUI, ctx: &Context, ui: &mut Ui, step: Step) {
+  let size = f32::from(gui.config.get_text_size());
+  if ui.button("Skip setup").clicked() {
+    switch_screen(gui, Screen::Menu);
+    return;
+  }
+  ui.heading(step.title());
+  match step {
+    Step::Appearance => draw_appearance_settings(gui, ctx, ui),
+    #[cfg(feature = "sound")]
+    Step::Sound => draw_sound_settings(gui, ui),
+    Step::LearnOrPlay => {
+      ui.label("The help pages explain the pieces and rules that differ from normal chess.");
+      ui.horizontal_top(|ui| {
+        if ui.button("Learn the rules").clicked() {
+          switch_screen(gui, Screen::Help);
+        }
+        if ui.button("Start playing").clicked() {
+          gui.screen = Screen::Welcome(Step::Variants);
+        }
+      });
+      return;
+    }
+    Step::Variants => {
+      ScrollArea::vertical().show(ui, |ui| {
+        ui.horizontal_wrapped(|ui| {
+          for preset in VARIANTS {
+            let Ok(board) = Board::new(&preset.value()) else {
+              continue;
+            };
+            ui.vertical(|ui| {
+              ui.label(preset.to_string());
+              let response = draw_small_board(gui, ui, &board, size * 8.0, false, Sense::click());
+              if response.on_hover_text("Select this variant").clicked() {
+                gui.gamemode = GameMode::Preset(preset);
+                gui.fen = preset.value();
+                switch_screen(gui, Screen::Menu);
+              }
+            });
+          }
+        });
+      });
+      return;
+    }
+  }
+  if let Some(next) = step.next() {
+    if ui.button("Next").clicked() {
+      gui.screen = Screen::Welcome(next);
+    }
+  }
+}
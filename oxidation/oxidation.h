@@ -0,0 +1,23 @@
+/* C API for the Oxidation engine, build with `cargo rustc --release -p oxidation --lib --features capi --crate-type cdylib` */
+#ifndef OXIDATION_H
+#define OXIDATION_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef struct OxidationEngine OxidationEngine;
+
+typedef struct OxidationScore {
+  bool mate;
+  int32_t value;
+} OxidationScore;
+
+OxidationEngine *oxidation_new(size_t hash_size);
+void oxidation_free(OxidationEngine *engine);
+int oxidation_set_position(OxidationEngine *engine, const char *fen, const char *moves);
+int oxidation_search(OxidationEngine *engine, uint8_t depth, uint64_t nodes, uint64_t time);
+int oxidation_bestmove(const OxidationEngine *engine, char *buffer, size_t length);
+OxidationScore oxidation_score(const OxidationEngine *engine);
+
+#endif
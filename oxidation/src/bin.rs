@@ -1,15 +1,16 @@
-use liberty_chess::clock::format_time;
+use liberty_chess::clock::{format_time, Millis};
 use liberty_chess::parsing::from_chars;
-use liberty_chess::positions::{
-  get_startpos, AFRICAN, CAPABLANCA, CAPABLANCA_RECTANGLE, DOUBLE_CHESS, ELIMINATION, HORDE,
-  LIBERTY_CHESS, LOADED_BOARD, MINI, MONGOL, NARNIA, STARTPOS, TRUMP,
-};
+use liberty_chess::positions::get_startpos;
 use liberty_chess::{Board, ALL_PIECES};
+use oxidation::dispatch::build_features;
 use oxidation::evaluate::evaluate;
+use oxidation::glue::{ClockState, HASH_NAME, MULTI_PV_NAME};
 use oxidation::parameters::DEFAULT_PARAMETERS;
 use oxidation::search::SEARCH_PARAMETERS;
+use oxidation::strength::{DEFAULT_ELO, MAX_ELO, MIN_ELO};
 use oxidation::{
-  bench, divide, search, Output, SearchConfig, State, HASH_SIZE, MULTI_PV_COUNT, VERSION_NUMBER,
+  bench, divide, load_bench_suite, search, Output, SearchConfig, State, BENCH_DEPTH,
+  BENCH_POSITIONS, HASH_SIZE, INFO_INTERVAL, MULTI_PV_COUNT, VERSION_NUMBER,
 };
 use std::collections::{HashMap, HashSet};
 use std::io::{stdin, stdout, BufReader};
@@ -17,34 +18,22 @@ use std::sync::mpsc::{channel, Sender};
 use std::thread::spawn;
 use std::time::Instant;
 use ulci::client::{startup, Message};
+use ulci::report;
 use ulci::{
   ClientInfo, IntOption, OptionValue, RangeOption, Score, SupportedFeatures, UlciOption, V1Features,
 };
 
-const BENCH_DEPTH: i8 = 9;
-
-const HASH_NAME: &str = "Hash";
-const MULTI_PV_NAME: &str = "MultiPV";
+const CONSERVATIVE_NAME: &str = "ConservativeUntuned";
+const ELO_NAME: &str = "UCI_Elo";
+const INFO_INTERVAL_NAME: &str = "InfoInterval";
+const INSTANT_MOVE_NAME: &str = "InstantMove";
+const LIMIT_STRENGTH_NAME: &str = "UCI_LimitStrength";
+const MOBILITY_VALUES_NAME: &str = "MobilityPieceValues";
+const NORMALIZE_NAME: &str = "NormalizeScores";
+const PONDER_NAME: &str = "Ponder";
+const SHOW_WDL_NAME: &str = "UCI_ShowWDL";
 const VARIANT_NAME: &str = "UCI_Variant";
 
-// i8 is an offset for bench depth
-const BENCH_POSITIONS: &[(&str, i8)] = &[
-  (STARTPOS, 0),
-  (CAPABLANCA_RECTANGLE, 0),
-  (CAPABLANCA, 0),
-  (LIBERTY_CHESS, -1),
-  (MINI, 2),
-  (MONGOL, 0),
-  (AFRICAN, -1),
-  (NARNIA, 1),
-  (TRUMP, -2),
-  (LOADED_BOARD, -4),
-  (DOUBLE_CHESS, -2),
-  (HORDE, -1),
-  (ELIMINATION, 0),
-  ("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1", 0),
-];
-
 fn startup_client(tx: &Sender<Message>) {
   let mut options = HashMap::new();
   options.insert(
@@ -63,6 +52,29 @@ fn startup_client(tx: &Sender<Message>) {
       max: 1 << 10,
     }),
   );
+  options.insert(
+    INFO_INTERVAL_NAME.to_owned(),
+    UlciOption::Int(IntOption {
+      default: INFO_INTERVAL as usize,
+      min: 0,
+      max: 10000,
+    }),
+  );
+  options.insert(INSTANT_MOVE_NAME.to_owned(), UlciOption::Bool(false));
+  options.insert(CONSERVATIVE_NAME.to_owned(), UlciOption::Bool(false));
+  options.insert(MOBILITY_VALUES_NAME.to_owned(), UlciOption::Bool(false));
+  options.insert(NORMALIZE_NAME.to_owned(), UlciOption::Bool(false));
+  options.insert(SHOW_WDL_NAME.to_owned(), UlciOption::Bool(false));
+  options.insert(LIMIT_STRENGTH_NAME.to_owned(), UlciOption::Bool(false));
+  options.insert(
+    ELO_NAME.to_owned(),
+    UlciOption::Int(IntOption {
+      default: DEFAULT_ELO as usize,
+      min: MIN_ELO as usize,
+      max: MAX_ELO as usize,
+    }),
+  );
+  options.insert(PONDER_NAME.to_owned(), UlciOption::Bool(false));
   let mut variants = HashSet::new();
   variants.insert("chess".to_owned());
   variants.insert("horde".to_owned());
@@ -76,6 +88,9 @@ fn startup_client(tx: &Sender<Message>) {
   let info = ClientInfo {
     features: SupportedFeatures {
       v1: V1Features::all(),
+      priority_moves: true,
+      compression: true,
+      games: false,
     },
     name: format!("Oxidation v{VERSION_NUMBER}"),
     username: None,
@@ -83,31 +98,62 @@ fn startup_client(tx: &Sender<Message>) {
     options,
     pieces: from_chars(ALL_PIECES),
     depth: BENCH_DEPTH,
+    build: Some(format!("features {}", build_features())),
   };
   let input = BufReader::new(stdin());
   startup(tx, &info, input, stdout(), false);
 }
 
 fn main() {
+  report::install_panic_hook("oxidation", VERSION_NUMBER);
   let (tx, rx) = channel();
   spawn(move || startup_client(&tx));
   let mut hash_size = HASH_SIZE;
   let mut pv_lines = MULTI_PV_COUNT;
+  let mut info_interval = INFO_INTERVAL;
+  let mut instant_move = false;
+  let mut conservative = false;
+  let mut mobility_values = false;
+  let mut normalize = false;
+  let mut show_wdl = false;
+  let mut limit_strength = false;
+  let mut elo = DEFAULT_ELO;
   let mut position = get_startpos();
   let mut state = State::new(hash_size, &position, SEARCH_PARAMETERS, DEFAULT_PARAMETERS);
   let mut debug = false;
+  let mut clock = ClockState::default();
   while let Ok(message) = rx.recv() {
     match message {
-      Message::SetDebug(new_debug) => debug = new_debug,
+      Message::SetDebug(new_debug) => {
+        debug = new_debug;
+        if debug {
+          println!("info string features {}", build_features());
+        }
+      }
       Message::UpdatePosition(board) => {
         position = board.load_from_thread();
+        report::set_position(&position);
         if state.new_position(&position) && debug {
           println!("info string Hash cleared");
         }
+        if debug {
+          for line in position.to_ascii().lines() {
+            println!("info string {line}");
+          }
+        }
       }
       Message::Go(settings) => {
+        let time = clock.search_time(&settings);
         let searchmoves = settings.moves;
-        let mut settings = SearchConfig::new_time(&position, settings.time, &rx, &mut debug);
+        let priority = settings.priority;
+        let mut settings = if settings.ponder {
+          SearchConfig::new_ponder(&position, time, &rx, &mut debug)
+        } else {
+          SearchConfig::new_time(&position, time, &rx, &mut debug)
+        };
+        settings.set_instant_move(instant_move);
+        settings.set_info_interval(info_interval);
+        settings.set_priority_moves(priority);
         let pv = search(
           &mut state,
           &mut settings,
@@ -116,20 +162,34 @@ fn main() {
           pv_lines,
           Output::String(stdout()),
         );
+        if let Some(time) = settings.clock_update() {
+          clock.update(time, position.to_move());
+        }
+        let ponder = pv
+          .get(1)
+          .map_or(String::new(), |mv| format!(" ponder {}", mv.to_string()));
         println!(
-          "bestmove {}",
+          "bestmove {}{ponder}",
           pv.first().map_or("0000".to_string(), ToString::to_string)
         );
       }
       Message::Stop => {
         println!("info error not currently searching");
       }
+      Message::PonderHit => {
+        println!("info error not currently pondering");
+      }
       Message::UpdateOption(name, value) => match &*name {
         HASH_NAME => match value {
           OptionValue::UpdateInt(value) => {
             if value != hash_size {
               hash_size = value;
               state = State::new(hash_size, &position, SEARCH_PARAMETERS, DEFAULT_PARAMETERS);
+              state.set_conservative(conservative);
+              state.set_mobility_values(mobility_values, &position);
+              state.set_normalize_scores(normalize);
+              state.set_show_wdl(show_wdl);
+              state.set_strength(limit_strength.then_some(elo));
             }
           }
           _ => println!("info error incorrect option type"),
@@ -140,57 +200,124 @@ fn main() {
           }
           _ => println!("info error incorrect option type"),
         },
+        INFO_INTERVAL_NAME => match value {
+          OptionValue::UpdateInt(value) => info_interval = value as u128,
+          _ => println!("info error incorrect option type"),
+        },
+        INSTANT_MOVE_NAME => match value {
+          OptionValue::UpdateBool(value) => instant_move = value,
+          _ => println!("info error incorrect option type"),
+        },
+        CONSERVATIVE_NAME => match value {
+          OptionValue::UpdateBool(value) => {
+            conservative = value;
+            state.set_conservative(conservative);
+          }
+          _ => println!("info error incorrect option type"),
+        },
+        MOBILITY_VALUES_NAME => match value {
+          OptionValue::UpdateBool(value) => {
+            mobility_values = value;
+            state.set_mobility_values(mobility_values, &position);
+          }
+          _ => println!("info error incorrect option type"),
+        },
+        NORMALIZE_NAME => match value {
+          OptionValue::UpdateBool(value) => {
+            normalize = value;
+            state.set_normalize_scores(normalize);
+          }
+          _ => println!("info error incorrect option type"),
+        },
+        SHOW_WDL_NAME => match value {
+          OptionValue::UpdateBool(value) => {
+            show_wdl = value;
+            state.set_show_wdl(show_wdl);
+          }
+          _ => println!("info error incorrect option type"),
+        },
+        LIMIT_STRENGTH_NAME => match value {
+          OptionValue::UpdateBool(value) => {
+            limit_strength = value;
+            state.set_strength(limit_strength.then_some(elo));
+          }
+          _ => println!("info error incorrect option type"),
+        },
+        ELO_NAME => match value {
+          OptionValue::UpdateInt(value) => {
+            elo = value as u32;
+            state.set_strength(limit_strength.then_some(elo));
+          }
+          _ => println!("info error incorrect option type"),
+        },
         // Does not do anything, just there for servers that expect it
-        VARIANT_NAME => (),
+        VARIANT_NAME | PONDER_NAME => (),
         _ => (),
       },
       Message::Eval => {
         println!(
           "info score {}",
-          Score::Centipawn(evaluate(&state, &position))
+          state
+            .reported_score(Score::Centipawn(evaluate(&state, &position)))
             .show_uci(position.moves(), position.to_move()),
         );
       }
-      Message::Bench(depth) => {
-        if depth < 5 {
-          println!("info error minimum bench depth 5");
-        } else {
-          let start = Instant::now();
-          state.new_game(&position);
-          let mut nodes = 0;
-          for (position, depth_offset) in BENCH_POSITIONS {
-            let depth = (depth + depth_offset) as u8;
-            let mut board = Board::new(position).expect("Loading bench position {position} failed");
-            nodes += bench(
-              &mut state,
-              &mut board,
-              depth,
-              &mut debug,
-              &rx,
-              Output::String(stdout()),
-            );
-            board.friendly_fire = true;
-            nodes += bench(
-              &mut state,
-              &mut board,
-              depth,
-              &mut debug,
-              &rx,
-              Output::String(stdout()),
+      Message::Bench(depth, suite) => {
+        let positions = match suite {
+          Some(path) => load_bench_suite(&path, depth),
+          None if depth < 5 => Err("minimum bench depth 5".to_owned()),
+          None => Ok(
+            BENCH_POSITIONS
+              .iter()
+              .map(|(position, depth_offset)| ((*position).to_owned(), depth + depth_offset))
+              .collect(),
+          ),
+        };
+        match positions {
+          Err(error) => println!("info error {error}"),
+          Ok(positions) => {
+            let start = Instant::now();
+            state.new_game(&position);
+            let mut nodes = 0;
+            for (position, depth) in positions {
+              let depth = depth as u8;
+              let mut board =
+                Board::new(&position).expect("Loading bench position {position} failed");
+              nodes += bench(
+                &mut state,
+                &mut board,
+                depth,
+                &mut debug,
+                &rx,
+                Output::String(stdout()),
+              );
+              board.friendly_fire = true;
+              nodes += bench(
+                &mut state,
+                &mut board,
+                depth,
+                &mut debug,
+                &rx,
+                Output::String(stdout()),
+              );
+            }
+            let millis = start.elapsed().as_millis();
+            println!(
+              "Total time: {} Nodes: {nodes} NPS: {}",
+              format_time(Millis::new(millis)),
+              nodes * 1000 / millis as usize,
             );
           }
-          let millis = start.elapsed().as_millis();
-          println!(
-            "Total time: {} Nodes: {nodes} NPS: {}",
-            format_time(millis),
-            nodes * 1000 / millis as usize,
-          );
         }
       }
-      Message::NewGame => state.new_game(&position),
+      Message::NewGame => {
+        state.new_game(&position);
+        clock.clear();
+      }
       Message::Perft(depth) => divide(&position, depth),
       Message::IsReady => println!("readyok"),
-      Message::Clock(_) | Message::Info(_) => (),
+      Message::Clock(time) => clock.update(time, position.to_move()),
+      Message::Info(_) | Message::Kibbutz(_) | Message::Notice(_) => (),
     }
   }
 }
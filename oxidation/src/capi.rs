@@ -0,0 +1,225 @@
+use crate::parameters::DEFAULT_PARAMETERS;
+use crate::search::SEARCH_PARAMETERS;
+use crate::{search, Output, SearchConfig, State, HASH_SIZE};
+use liberty_chess::clock::Millis;
+use liberty_chess::moves::Move;
+use liberty_chess::positions::get_startpos;
+use liberty_chess::Board;
+use std::ffi::{c_char, c_int, CStr};
+use std::ptr;
+use std::sync::mpsc::channel;
+use ulci::server::UlciResult;
+use ulci::{Limits, Score, SearchTime};
+
+/// An engine instance, opaque to C
+pub struct OxidationEngine {
+  state: State,
+  position: Board,
+  // principal variation and score of the last search
+  result: Option<(Vec<Move>, Score)>,
+}
+
+/// The score of a search from the perspective of the side to move
+#[repr(C)]
+pub struct OxidationScore {
+  /// Whether the score is a forced mate
+  pub mate: bool,
+  /// The score in centipawns, or the number of moves until mate
+  ///
+  /// Negative if the side to move is losing
+  pub value: i32,
+}
+
+/// Create an engine with a hash table of the provided size in megabytes, 0 for the default
+///
+/// The engine starts on the standard chess starting position and must be freed with `oxidation_free`
+#[no_mangle]
+pub extern "C" fn oxidation_new(hash_size: usize) -> *mut OxidationEngine {
+  let hash_size = if hash_size == 0 { HASH_SIZE } else { hash_size };
+  let position = get_startpos();
+  let state = State::new(hash_size, &position, SEARCH_PARAMETERS, DEFAULT_PARAMETERS);
+  Box::into_raw(Box::new(OxidationEngine {
+    state,
+    position,
+    result: None,
+  }))
+}
+
+/// Free an engine
+///
+/// # Safety
+///
+/// `engine` must be null or a pointer returned by `oxidation_new` that has not already been freed
+#[no_mangle]
+pub unsafe extern "C" fn oxidation_free(engine: *mut OxidationEngine) {
+  if !engine.is_null() {
+    drop(Box::from_raw(engine));
+  }
+}
+
+/// Set the position to an L-FEN followed by space-separated moves in long algebraic notation
+///
+/// `moves` can be null if there are no moves to play.
+/// Returns 0 on success, or -1 if the L-FEN or a move is invalid, in which case the position is unchanged.
+///
+/// # Safety
+///
+/// `engine` must be a valid engine, and `fen` and `moves` must be null or valid NUL-terminated strings
+#[no_mangle]
+pub unsafe extern "C" fn oxidation_set_position(
+  engine: *mut OxidationEngine,
+  fen: *const c_char,
+  moves: *const c_char,
+) -> c_int {
+  let Some(engine) = engine.as_mut() else {
+    return -1;
+  };
+  if fen.is_null() {
+    return -1;
+  }
+  let Ok(fen) = CStr::from_ptr(fen).to_str() else {
+    return -1;
+  };
+  let Ok(mut position) = Board::new(fen) else {
+    return -1;
+  };
+  if !moves.is_null() {
+    let Ok(moves) = CStr::from_ptr(moves).to_str() else {
+      return -1;
+    };
+    for mv in moves.split_whitespace() {
+      match mv.parse().ok().and_then(|mv| position.move_if_legal(mv)) {
+        Some(new_position) => position = new_position,
+        None => return -1,
+      }
+    }
+  }
+  engine.state.new_position(&position);
+  engine.position = position;
+  engine.result = None;
+  0
+}
+
+/// Search the current position, blocking until the search is finished
+///
+/// A limit of 0 is unlimited, but at least one limit must be set.
+/// Returns 0 on success, or -1 if no limits are set.
+///
+/// # Safety
+///
+/// `engine` must be a valid engine
+#[no_mangle]
+pub unsafe extern "C" fn oxidation_search(
+  engine: *mut OxidationEngine,
+  depth: u8,
+  nodes: u64,
+  time: u64,
+) -> c_int {
+  let Some(engine) = engine.as_mut() else {
+    return -1;
+  };
+  if depth == 0 && nodes == 0 && time == 0 {
+    return -1;
+  }
+  let limits = Limits {
+    depth: if depth == 0 { u8::MAX } else { depth },
+    nodes: if nodes == 0 {
+      usize::MAX
+    } else {
+      usize::try_from(nodes).unwrap_or(usize::MAX)
+    },
+    time: if time == 0 {
+      Millis::MAX
+    } else {
+      Millis::new(u128::from(time))
+    },
+  };
+  let mut position = engine.position.clone();
+  // nothing is sent, but the sender is kept so the search doesn't see a closed channel and stop
+  let (_tx, rx) = channel();
+  let (send_results, results) = channel();
+  let mut debug = false;
+  let mut settings = SearchConfig::new_time(&position, SearchTime::Other(limits), &rx, &mut debug);
+  let pv = search(
+    &mut engine.state,
+    &mut settings,
+    &mut position,
+    &[],
+    1,
+    Output::Channel(&send_results),
+  );
+  let score = results
+    .try_iter()
+    .filter_map(|result| match result {
+      UlciResult::Analysis(analysis) => Some(analysis.score),
+      _ => None,
+    })
+    .last()
+    .unwrap_or(Score::Centipawn(0));
+  engine.result = Some((pv, score));
+  0
+}
+
+/// Write the best move from the last search to `buffer` as a NUL-terminated string
+///
+/// Returns the length of the move, or -1 if there is no best move or it doesn't fit in the buffer
+///
+/// # Safety
+///
+/// `engine` must be a valid engine and `buffer` must be valid for writes of `length` bytes
+#[no_mangle]
+pub unsafe extern "C" fn oxidation_bestmove(
+  engine: *const OxidationEngine,
+  buffer: *mut c_char,
+  length: usize,
+) -> c_int {
+  let Some(bestmove) = engine
+    .as_ref()
+    .and_then(|engine| engine.result.as_ref())
+    .and_then(|(pv, _)| pv.first())
+  else {
+    return -1;
+  };
+  let bestmove = bestmove.to_string();
+  if buffer.is_null() || bestmove.len() >= length {
+    return -1;
+  }
+  ptr::copy_nonoverlapping(bestmove.as_ptr().cast(), buffer, bestmove.len());
+  *buffer.add(bestmove.len()) = 0;
+  c_int::try_from(bestmove.len()).unwrap_or(-1)
+}
+
+/// Get the score from the last search, or a score of 0 if there hasn't been a search
+///
+/// # Safety
+///
+/// `engine` must be a valid engine
+#[no_mangle]
+pub unsafe extern "C" fn oxidation_score(engine: *const OxidationEngine) -> OxidationScore {
+  let Some(engine) = engine.as_ref() else {
+    return OxidationScore {
+      mate: false,
+      value: 0,
+    };
+  };
+  let move_count = engine.position.moves();
+  match engine.result {
+    Some((_, Score::Win(moves))) => OxidationScore {
+      mate: true,
+      value: i32::try_from(moves + u32::from(engine.position.to_move()) - move_count)
+        .unwrap_or(i32::MAX),
+    },
+    Some((_, Score::Loss(moves))) => OxidationScore {
+      mate: true,
+      value: -i32::try_from(moves - move_count).unwrap_or(i32::MAX),
+    },
+    Some((_, Score::Centipawn(score))) => OxidationScore {
+      mate: false,
+      value: score,
+    },
+    None => OxidationScore {
+      mate: false,
+      value: 0,
+    },
+  }
+}
@@ -0,0 +1,53 @@
+/// Whether the CPU supports the instructions used by the fast packed evaluation
+#[must_use]
+pub fn fast_eval_available() -> bool {
+  #[cfg(target_arch = "x86_64")]
+  {
+    is_x86_feature_detected!("avx2")
+      && is_x86_feature_detected!("bmi1")
+      && is_x86_feature_detected!("bmi2")
+      && is_x86_feature_detected!("popcnt")
+  }
+  #[cfg(not(target_arch = "x86_64"))]
+  {
+    false
+  }
+}
+
+/// The optional features the binary was built with and the ones picked at runtime
+///
+/// # Examples
+/// ```
+/// let features = oxidation::dispatch::build_features();
+/// assert!(features.contains("eval"));
+/// ```
+#[must_use]
+pub fn build_features() -> String {
+  let mut features = Vec::new();
+  if cfg!(feature = "feature_extraction") {
+    features.push("feature extraction eval");
+  } else {
+    features.push("packed eval");
+  }
+  if cfg!(feature = "validate") {
+    features.push("validate");
+  }
+  if cfg!(feature = "capi") {
+    features.push("capi");
+  }
+  // instructions the whole binary can use
+  for (enabled, name) in [
+    (cfg!(target_feature = "popcnt"), "popcnt"),
+    (cfg!(target_feature = "bmi2"), "bmi2"),
+    (cfg!(target_feature = "avx2"), "avx2"),
+    (cfg!(target_feature = "neon"), "neon"),
+  ] {
+    if enabled {
+      features.push(name);
+    }
+  }
+  if cfg!(not(feature = "feature_extraction")) && fast_eval_available() {
+    features.push("avx2 eval dispatch");
+  }
+  features.join(", ")
+}
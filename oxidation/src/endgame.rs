@@ -0,0 +1,312 @@
+use liberty_chess::moves::Move;
+use liberty_chess::{Board, Gamestate, Piece, AMAZON, CHANCELLOR, KING, QUEEN, ROOK};
+use std::collections::VecDeque;
+use ulci::Score;
+
+// Pieces that can force mate with just the king by boxing in the enemy king
+const MATING_PIECES: [Piece; 4] = [ROOK, QUEEN, CHANCELLOR, AMAZON];
+
+// Tables for larger boards take too long to build to be worth it
+const MAX_SQUARES: usize = 100;
+
+// Marks positions that aren't won in the table
+const UNKNOWN: u16 = u16::MAX;
+
+const ORTHOGONAL: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const ALL_DIRECTIONS: [(isize, isize); 8] = [
+  (1, 0),
+  (-1, 0),
+  (0, 1),
+  (0, -1),
+  (1, 1),
+  (1, -1),
+  (-1, 1),
+  (-1, -1),
+];
+const KNIGHT_JUMPS: [(isize, isize); 8] = [
+  (1, 2),
+  (2, 1),
+  (2, -1),
+  (1, -2),
+  (-1, -2),
+  (-2, -1),
+  (-2, 1),
+  (-1, 2),
+];
+
+// The squares of the friendly king, enemy king and mating piece as indices, and the mating piece,
+// if the specified side has a king and a mating piece against a lone king
+fn trivial_ending(board: &Board, side: bool) -> Option<(usize, usize, usize, Piece)> {
+  let (white, black) = board.pieces();
+  let (friendly, enemy) = if side { (white, black) } else { (black, white) };
+  // the table assumes the standard capture and promotion rules
+  if board.friendly_fire
+    || board.non_default_promotions()
+    || friendly != 1
+    || enemy != 0
+    || board.piece_count(KING) != 1
+    || board.piece_count(-KING) != 1
+  {
+    return None;
+  }
+  let multiplier = if side { 1 } else { -1 };
+  let (mut friendly_king, mut enemy_king, mut mating_piece) = (None, None, None);
+  for row in 0..board.height() {
+    for column in 0..board.width() {
+      let index = row * board.width() + column;
+      match board.get_piece((row, column)) * multiplier {
+        0 => (),
+        KING => friendly_king = Some(index),
+        piece if piece == -KING => enemy_king = Some(index),
+        piece if MATING_PIECES.contains(&piece) => mating_piece = Some((index, piece)),
+        // anything else on the board, such as obstacles, isn't covered by the table
+        _ => return None,
+      }
+    }
+  }
+  let (mating_square, piece) = mating_piece?;
+  Some((friendly_king?, enemy_king?, mating_square, piece))
+}
+
+/// The number of halfmoves to mate for every position with a king and mating piece against a king
+pub(crate) struct Tablebase {
+  width: usize,
+  height: usize,
+  piece: Piece,
+  // indexed by the squares of the attacking king, defending king and mating piece
+  attacker_to_move: Vec<u16>,
+  defender_to_move: Vec<u16>,
+}
+
+impl Tablebase {
+  fn new(width: usize, height: usize, piece: Piece) -> Self {
+    let squares = width * height;
+    let mut table = Self {
+      width,
+      height,
+      piece,
+      attacker_to_move: vec![UNKNOWN; squares * squares * squares],
+      defender_to_move: vec![UNKNOWN; squares * squares * squares],
+    };
+    // the number of moves the defender has that don't lead to a lost position yet
+    let mut escapes = vec![0_u8; squares * squares * squares];
+    let mut queue = VecDeque::new();
+    let neighbours: Vec<Vec<usize>> = (0..squares)
+      .map(|square| table.king_moves(square).collect())
+      .collect();
+    for king in 0..squares {
+      for (enemy_king, enemy_moves) in neighbours.iter().enumerate() {
+        for piece in 0..squares {
+          if !table.valid(king, enemy_king, piece) {
+            continue;
+          }
+          let index = table.index(king, enemy_king, piece);
+          let mut moves = 0;
+          let mut capture = false;
+          for &square in enemy_moves {
+            if square == piece {
+              capture |= !table.adjacent(square, king);
+            } else if !table.attacked(king, piece, square) {
+              moves += 1;
+            }
+          }
+          if capture {
+            continue;
+          }
+          if moves == 0 && table.piece_attacks(piece, enemy_king, king) {
+            table.defender_to_move[index] = 0;
+            queue.push_back((king, enemy_king, piece));
+          }
+          escapes[index] = moves;
+        }
+      }
+    }
+    // work backwards from the mates, a position is won if any move reaches a lost position
+    // and lost if every move reaches a won position
+    let mut previous = Vec::new();
+    while let Some((king, enemy_king, piece)) = queue.pop_front() {
+      let plies = table.defender_to_move[table.index(king, enemy_king, piece)] + 1;
+      previous.clear();
+      previous.extend(
+        neighbours[king]
+          .iter()
+          .filter(|&&square| square != piece && !table.adjacent(square, enemy_king))
+          .map(|&square| (square, piece)),
+      );
+      table.piece_moves(&mut previous, piece, king, enemy_king);
+      for &(king, piece) in &previous {
+        // the defender can't be left in check
+        if table.piece_attacks(piece, enemy_king, king) {
+          continue;
+        }
+        let index = table.index(king, enemy_king, piece);
+        if table.attacker_to_move[index] != UNKNOWN {
+          continue;
+        }
+        table.attacker_to_move[index] = plies;
+        for &square in &neighbours[enemy_king] {
+          if square == piece || table.adjacent(square, king) {
+            continue;
+          }
+          let index = table.index(king, square, piece);
+          if table.defender_to_move[index] != UNKNOWN || escapes[index] == 0 {
+            continue;
+          }
+          escapes[index] -= 1;
+          if escapes[index] == 0 {
+            table.defender_to_move[index] = plies + 1;
+            queue.push_back((king, square, piece));
+          }
+        }
+      }
+    }
+    table
+  }
+
+  const fn index(&self, king: usize, enemy_king: usize, piece: usize) -> usize {
+    let squares = self.width * self.height;
+    (king * squares + enemy_king) * squares + piece
+  }
+
+  const fn adjacent(&self, a: usize, b: usize) -> bool {
+    let (a_row, a_column) = (a / self.width, a % self.width);
+    let (b_row, b_column) = (b / self.width, b % self.width);
+    a_row.abs_diff(b_row) <= 1 && a_column.abs_diff(b_column) <= 1
+  }
+
+  fn valid(&self, king: usize, enemy_king: usize, piece: usize) -> bool {
+    king != piece && enemy_king != piece && !self.adjacent(king, enemy_king)
+  }
+
+  // The square reached by moving from a square, if it is on the board
+  fn offset(&self, square: usize, (rows, columns): (isize, isize)) -> Option<usize> {
+    let row = (square / self.width).checked_add_signed(rows)?;
+    let column = (square % self.width).checked_add_signed(columns)?;
+    (row < self.height && column < self.width).then_some(row * self.width + column)
+  }
+
+  fn king_moves(&self, square: usize) -> impl Iterator<Item = usize> + '_ {
+    ALL_DIRECTIONS
+      .iter()
+      .filter_map(move |&direction| self.offset(square, direction))
+  }
+
+  fn directions(&self) -> (&'static [(isize, isize)], bool) {
+    match self.piece {
+      ROOK => (&ORTHOGONAL, false),
+      CHANCELLOR => (&ORTHOGONAL, true),
+      QUEEN => (&ALL_DIRECTIONS, false),
+      _ => (&ALL_DIRECTIONS, true),
+    }
+  }
+
+  // Add the squares of both attacking pieces after each move of the mating piece,
+  // the kings block its path
+  fn piece_moves(
+    &self,
+    moves: &mut Vec<(usize, usize)>,
+    piece: usize,
+    king: usize,
+    enemy_king: usize,
+  ) {
+    let (directions, knight) = self.directions();
+    for &direction in directions {
+      let mut square = piece;
+      while let Some(next) = self.offset(square, direction) {
+        if next == king || next == enemy_king {
+          break;
+        }
+        moves.push((king, next));
+        square = next;
+      }
+    }
+    if knight {
+      moves.extend(
+        KNIGHT_JUMPS
+          .iter()
+          .filter_map(|&direction| self.offset(piece, direction))
+          .filter(|&square| square != king && square != enemy_king)
+          .map(|square| (king, square)),
+      );
+    }
+  }
+
+  // Whether the mating piece attacks a square, the king can block its path
+  fn piece_attacks(&self, piece: usize, target: usize, king: usize) -> bool {
+    let (directions, knight) = self.directions();
+    let (row, column) = self.coordinates(piece);
+    let (target_row, target_column) = self.coordinates(target);
+    let (rows, columns) = (target_row - row, target_column - column);
+    if knight && rows.abs() * columns.abs() == 2 {
+      return true;
+    }
+    let straight = rows == 0 || columns == 0;
+    let diagonal = rows.abs() == columns.abs() && directions.len() == ALL_DIRECTIONS.len();
+    if piece == target || !(straight || diagonal) {
+      return false;
+    }
+    // the king blocks the line if it is on it between the two squares
+    let (king_row, king_column) = self.coordinates(king);
+    let (king_rows, king_columns) = (king_row - row, king_column - column);
+    !(king_rows * columns == king_columns * rows
+      && king_rows * rows >= 0
+      && king_columns * columns >= 0
+      && king_rows.abs() <= rows.abs()
+      && king_columns.abs() <= columns.abs())
+  }
+
+  const fn coordinates(&self, square: usize) -> (isize, isize) {
+    (
+      (square / self.width) as isize,
+      (square % self.width) as isize,
+    )
+  }
+
+  // Whether the defending king can't move to a square
+  fn attacked(&self, king: usize, piece: usize, square: usize) -> bool {
+    self.adjacent(king, square) || self.piece_attacks(piece, square, king)
+  }
+
+  // The number of halfmoves to mate, if the position is in the table and won
+  fn lookup(&self, board: &Board, attacker: bool) -> Option<u16> {
+    let (king, enemy_king, piece, piece_type) = trivial_ending(board, attacker)?;
+    if piece_type != self.piece {
+      return None;
+    }
+    let index = self.index(king, enemy_king, piece);
+    let plies = if board.to_move() == attacker {
+      self.attacker_to_move[index]
+    } else {
+      self.defender_to_move[index]
+    };
+    (plies != UNKNOWN).then_some(plies)
+  }
+}
+
+/// Play the fastest mate with a king and rook-like piece against a lone king
+///
+/// The table of every position on the board is built the first time the ending is reached,
+/// and kept in `table` while the board and piece stay the same.
+/// Returns `None` if the position isn't a trivial win.
+pub(crate) fn trivial_win(table: &mut Option<Tablebase>, board: &Board) -> Option<(Move, Score)> {
+  if board.state() != Gamestate::InProgress || board.width() * board.height() > MAX_SQUARES {
+    return None;
+  }
+  let side = board.to_move();
+  let (.., piece) = trivial_ending(board, side)?;
+  if !table.as_ref().is_some_and(|table| {
+    (table.width, table.height, table.piece) == (board.width(), board.height(), piece)
+  }) {
+    *table = Some(Tablebase::new(board.width(), board.height(), piece));
+  }
+  let table = table.as_ref()?;
+  let plies = table.lookup(board, side)?;
+  let mv = board
+    .generate_legal()
+    .into_iter()
+    .filter_map(|position| Some((table.lookup(&position, side)?, position.last_move?)))
+    .min_by_key(|(plies, _)| *plies)
+    .map(|(_, mv)| mv)?;
+  let moves = u32::from(plies).div_ceil(2);
+  Some((mv, Score::Win(board.moves() + moves - u32::from(side))))
+}
@@ -7,7 +7,7 @@ use array2d::Array2D;
 use liberty_chess::{Board, Gamestate, Piece, OBSTACLE, PAWN, WALL};
 use std::cmp::min;
 use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
-use ulci::Score;
+use ulci::{Score, WDL};
 
 #[cfg(not(feature = "feature_extraction"))]
 use crate::parameters::{pack, unpack_eg, unpack_mg, PackedParameters};
@@ -27,16 +27,38 @@ pub struct Features {
   pawn_list: Vec<(u8, i8)>,
 }
 
+// Use the packed evaluation compiled for the newest instructions the CPU supports
+#[cfg(not(feature = "feature_extraction"))]
+fn packed_eval(state: &State, board: &Board) -> i32 {
+  #[cfg(target_arch = "x86_64")]
+  if state.fast_eval {
+    // SAFETY: the CPU was checked for the enabled features when the state was created
+    return unsafe { raw_avx2(board, state.promotion_values, &state.packed_parameters) };
+  }
+  raw(board, state.promotion_values, &state.packed_parameters)
+}
+
+// The packed evaluation compiled for CPUs with AVX2 and BMI2
+#[cfg(all(target_arch = "x86_64", not(feature = "feature_extraction")))]
+#[target_feature(enable = "avx2,bmi1,bmi2,popcnt")]
+unsafe fn raw_avx2(
+  board: &Board,
+  promotion_values: (i32, i32),
+  parameters: &PackedParameters,
+) -> i32 {
+  raw(board, promotion_values, parameters)
+}
+
 #[must_use]
+#[inline(always)]
 #[cfg(not(feature = "feature_extraction"))]
 pub(crate) fn raw(
-  pieces: &Array2D<Piece>,
-  to_move: bool,
+  board: &Board,
   promotion_values: (i32, i32),
   parameters: &PackedParameters,
 ) -> i32 {
+  let pieces = board.board();
   let mut value = 0;
-  let mut material = 0;
   let height = pieces.num_rows();
   let width = pieces.num_columns();
   for i in 0..height {
@@ -49,7 +71,6 @@ pub(crate) fn raw(
           (-1, i.wrapping_sub(1), i + 1, Some(&PAWN), Some(&-PAWN))
         };
         let piece_type = piece.unsigned_abs() as usize - 1;
-        material += ENDGAME_FACTOR[piece_type];
         let mut piece_value = parameters.pieces[piece_type];
         let mobility = Board::mobility(pieces, (i, j), piece);
         piece_value += mobility * parameters.mobility_bonus[piece_type];
@@ -99,10 +120,10 @@ pub(crate) fn raw(
   }
   let middlegame = unpack_mg(value);
   let endgame = unpack_eg(value);
-  material = min(material, ENDGAME_THRESHOLD);
+  let material = phase(board);
   let score = material * middlegame + (ENDGAME_THRESHOLD - material) * endgame;
   let mut score = score / ENDGAME_THRESHOLD;
-  if !to_move {
+  if !board.to_move() {
     score *= -1;
   }
   score += TEMPO_BONUS;
@@ -343,12 +364,7 @@ pub fn extract_features(pieces: &Array2D<Piece>) -> Features {
 #[must_use]
 pub fn evaluate(state: &State, board: &Board) -> i32 {
   #[cfg(not(feature = "feature_extraction"))]
-  let score = raw(
-    board.board(),
-    board.to_move(),
-    state.promotion_values,
-    &state.packed_parameters,
-  );
+  let score = packed_eval(state, board);
   #[cfg(feature = "feature_extraction")]
   let features = extract_features(board.board());
   #[cfg(feature = "feature_extraction")]
@@ -358,7 +374,57 @@ pub fn evaluate(state: &State, board: &Board) -> i32 {
     state.promotion_values,
     &state.parameters,
   );
-  score
+  match state.strength {
+    Some(strength) => score + strength.noise(board.hash()),
+    None => score,
+  }
+}
+
+/// The game phase, from 0 in the endgame up to the endgame threshold in the middlegame
+#[must_use]
+pub fn phase(board: &Board) -> i32 {
+  let material: i32 = [board.material(true), board.material(false)]
+    .into_iter()
+    .flat_map(|material| material.iter().zip(ENDGAME_FACTOR))
+    .map(|(count, factor)| *count as i32 * factor)
+    .sum();
+  min(material, ENDGAME_THRESHOLD)
+}
+
+// The advantage that wins half the time, and how quickly the chances change around it
+fn wdl_curve(phase: i32) -> (f64, f64) {
+  let progress = f64::from(phase.clamp(0, ENDGAME_THRESHOLD)) / f64::from(ENDGAME_THRESHOLD);
+  (150.0 + 100.0 * progress, 60.0 + 40.0 * progress)
+}
+
+/// The centipawn scale of the logistic curve `1 / (1 + exp(-score / scale))` that matches the
+/// expected score from [`wdl_model`] around an even position
+#[must_use]
+pub fn expected_score_scale(phase: i32) -> f64 {
+  let (midpoint, spread) = wdl_curve(phase);
+  // the expected score is (1 + win - loss) / 2, so its slope at 0 is the slope of the win chance
+  let win = 1.0 / (1.0 + (midpoint / spread).exp());
+  spread / (4.0 * win * (1.0 - win))
+}
+
+/// Estimate the win, draw and loss chances of the side to move in permill
+///
+/// Centipawn scores should be relative to a pawn worth 100.
+/// The same advantage is more likely to win with less material left on the board.
+#[must_use]
+pub fn wdl_model(score: Score, phase: i32) -> WDL {
+  match score {
+    Score::Win(_) => WDL::new(1000, 0, 0),
+    Score::Loss(_) => WDL::new(0, 0, 1000),
+    Score::Centipawn(score) => {
+      let (midpoint, spread) = wdl_curve(phase);
+      let chance = |score: f64| 1000.0 / (1.0 + ((midpoint - score) / spread).exp());
+      let win = chance(f64::from(score)).round() as u16;
+      let loss = chance(-f64::from(score)).round() as u16;
+      // the midpoint is positive so the win and loss chances can't add up to more than 1000
+      WDL::new(win, 1000 - win - loss, loss)
+    }
+  }
 }
 
 pub(crate) fn evaluate_terminal(board: &Board) -> Score {
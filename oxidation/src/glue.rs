@@ -1,34 +1,314 @@
 use crate::{search, Output, SearchConfig, State};
+use liberty_chess::clock::Millis;
+use liberty_chess::moves::Move;
 use liberty_chess::threading::CompressedBoard;
+use liberty_chess::Board;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Error, ErrorKind, Result, Write};
 use std::sync::mpsc::{Receiver, Sender};
 use ulci::client::Message;
 use ulci::server::UlciResult;
-use ulci::SearchTime;
+use ulci::{AnalysisResult, OptionValue, Score, SearchSettings, SearchTime};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+/// The name of the option for the hash size in MB
+pub const HASH_NAME: &str = "Hash";
+/// The name of the option for the number of lines to search
+pub const MULTI_PV_NAME: &str = "MultiPV";
+
+/// The latest clock state sent by the server
+///
+/// Used for searches that don't specify a time, so the engine can manage its time in online play
+#[derive(Default)]
+pub struct ClockState {
+  // the clock, when it was updated and the side to move at the time
+  clock: Option<(SearchTime, Instant, bool)>,
+}
+
+impl ClockState {
+  /// Record a clock update from the server, clearing the clock if it has no time control
+  ///
+  /// `to_move` is the side to move when the update was received
+  pub fn update(&mut self, time: SearchTime, to_move: bool) {
+    self.clock = match time {
+      SearchTime::Increment(..)
+      | SearchTime::Asymmetric(..)
+      | SearchTime::MovesToGo(..)
+      | SearchTime::Delay(..)
+      | SearchTime::Hourglass(..) => Some((time, Instant::now(), to_move)),
+      SearchTime::Infinite | SearchTime::Other(_) | SearchTime::Mate(_) => None,
+    };
+  }
+
+  /// Forget the clock, for when a new game starts
+  pub fn clear(&mut self) {
+    self.clock = None;
+  }
+
+  /// The time to search with, using the latest clock if no time was specified for the search
+  ///
+  /// The time elapsed since the clock update is deducted from the side to move at the time
+  #[must_use]
+  pub fn search_time(&self, settings: &SearchSettings) -> SearchTime {
+    let (true, Some((time, updated, to_move))) = (settings.clock, self.clock) else {
+      return settings.time;
+    };
+    let elapsed = Millis::from(updated.elapsed());
+    let deduct = |wtime: Millis, btime: Millis, elapsed: Millis| {
+      if to_move {
+        (wtime - elapsed, btime)
+      } else {
+        (wtime, btime - elapsed)
+      }
+    };
+    match time {
+      SearchTime::Increment(time, inc) => {
+        let (wtime, btime) = deduct(time, time, elapsed);
+        SearchTime::Asymmetric(wtime, inc, btime, inc)
+      }
+      SearchTime::Asymmetric(wtime, winc, btime, binc) => {
+        let (wtime, btime) = deduct(wtime, btime, elapsed);
+        SearchTime::Asymmetric(wtime, winc, btime, binc)
+      }
+      SearchTime::MovesToGo(wtime, winc, btime, binc, moves) => {
+        let (wtime, btime) = deduct(wtime, btime, elapsed);
+        SearchTime::MovesToGo(wtime, winc, btime, binc, moves)
+      }
+      SearchTime::Delay(wtime, btime, delay) => {
+        let (wtime, btime) = deduct(wtime, btime, elapsed - delay);
+        SearchTime::Delay(wtime, btime, delay - elapsed)
+      }
+      SearchTime::Hourglass(wtime, btime) => {
+        let (wtime, btime) = deduct(wtime, btime, elapsed);
+        // the opponent gains the time spent
+        let (wtime, btime) = if to_move {
+          (wtime, btime + elapsed)
+        } else {
+          (wtime + elapsed, btime)
+        };
+        SearchTime::Hourglass(wtime, btime)
+      }
+      SearchTime::Infinite | SearchTime::Other(_) | SearchTime::Mate(_) => settings.time,
+    }
+  }
+}
+
+/// A saved analysis, so a long search can be resumed later
+pub struct Session {
+  /// The L-FEN of the position being analysed
+  pub fen: String,
+  /// Every result from the search in the order they were found, so each iteration is kept
+  pub results: Vec<AnalysisResult>,
+}
+
+fn invalid(message: &str) -> Error {
+  Error::new(ErrorKind::InvalidData, message)
+}
+
+fn show_score(score: Score) -> String {
+  match score {
+    Score::Win(moves) => format!("win {moves}"),
+    Score::Loss(moves) => format!("loss {moves}"),
+    Score::Centipawn(score) => format!("cp {score}"),
+  }
+}
+
+// Parse a result saved as `line <pv line> <depth> <nodes> <time> <score type> <score> pv <moves>`
+fn parse_result(line: &str) -> Option<AnalysisResult> {
+  let mut words = line.split_whitespace();
+  if words.next()? != "line" {
+    return None;
+  }
+  let pv_line = words.next()?.parse().ok()?;
+  let depth = words.next()?.parse().ok()?;
+  let nodes = words.next()?.parse().ok()?;
+  let time = words.next()?.parse().ok()?;
+  let score = match (words.next()?, words.next()?) {
+    ("win", moves) => Score::Win(moves.parse().ok()?),
+    ("loss", moves) => Score::Loss(moves.parse().ok()?),
+    ("cp", score) => Score::Centipawn(score.parse().ok()?),
+    _ => return None,
+  };
+  if words.next()? != "pv" {
+    return None;
+  }
+  let pv = words
+    .map(str::parse)
+    .collect::<std::result::Result<_, _>>()
+    .ok()?;
+  Some(AnalysisResult {
+    pv,
+    score,
+    depth,
+    nodes,
+    time,
+    wdl: None,
+    pv_line,
+  })
+}
+
+impl Session {
+  /// The latest result for the best line
+  #[must_use]
+  pub fn latest(&self) -> Option<&AnalysisResult> {
+    self.results.iter().rev().find(|result| result.pv_line <= 1)
+  }
+
+  /// The deepest depth reached by the saved search
+  #[must_use]
+  pub fn depth(&self) -> u16 {
+    self
+      .results
+      .iter()
+      .map(|result| result.depth)
+      .max()
+      .unwrap_or(0)
+  }
+
+  /// Save the session and the transposition table to a file
+  ///
+  /// # Errors
+  ///
+  /// Will return `Err` if the file could not be written
+  pub fn save(&self, path: &str, state: &State) -> Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "fen {}", self.fen)?;
+    for result in &self.results {
+      writeln!(
+        file,
+        "line {} {} {} {} {} pv {}",
+        result.pv_line,
+        result.depth,
+        result.nodes,
+        result.time,
+        show_score(result.score),
+        result
+          .pv
+          .iter()
+          .map(ToString::to_string)
+          .collect::<Vec<String>>()
+          .join(" ")
+      )?;
+    }
+    writeln!(file, "table")?;
+    state.table.save(&mut file)?;
+    file.flush()
+  }
+
+  /// Load a session from a file, putting the saved transposition table into the state
+  ///
+  /// The state must have the same hash size as when the session was saved
+  ///
+  /// # Errors
+  ///
+  /// Will return `Err` if the file could not be read or is not a valid session
+  pub fn load(path: &str, state: &mut State) -> Result<Self> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut line = String::new();
+    file.read_line(&mut line)?;
+    let fen = line
+      .trim()
+      .strip_prefix("fen ")
+      .ok_or_else(|| invalid("missing position"))?
+      .to_owned();
+    let position = Board::new(&fen).map_err(|_| invalid("invalid position"))?;
+    let mut results = Vec::new();
+    loop {
+      line.clear();
+      file.read_line(&mut line)?;
+      match line.trim() {
+        "table" => break,
+        result => results.push(parse_result(result).ok_or_else(|| invalid("invalid result"))?),
+      }
+    }
+    state.new_position(&position);
+    state.table.load(&mut file, &position)?;
+    Ok(Self { fen, results })
+  }
+}
+
+// Apply an option change between searches, the table keeps what it can when it is resized
+fn apply_option(state: &mut State, multipv: &mut u16, name: &str, value: OptionValue) {
+  match (name, value) {
+    (HASH_NAME, OptionValue::UpdateInt(megabytes)) => state.table.resize(megabytes),
+    (MULTI_PV_NAME, OptionValue::UpdateInt(lines)) => *multipv = lines.clamp(1, 1 << 10) as u16,
+    _ => (),
+  }
+}
 
 /// Analyse the given position
 ///
+/// Only `searchmoves` are searched if it isn't empty, and `priority` moves are searched first
+///
+/// If `instant_move` is set, obvious moves are played without searching
+///
+/// The search starts from `resume_depth` if it is resuming a saved session
+///
+/// If `ponder` is set, the search runs without limits until `Message::PonderHit`
+///
+/// Changes to the `Hash` and `MultiPV` options pause an analysis with an infinite search time,
+/// which then resumes from the last depth finished with the transposition table kept.
+/// `multipv` is updated with the new number of lines
+///
 /// Blocks the current thread
 pub fn process_position(
   tx: &Sender<UlciResult>,
   receive_message: &Receiver<Message>,
   board: CompressedBoard,
   searchtime: SearchTime,
+  searchmoves: &[Move],
+  priority: &[Move],
   state: &mut State,
-  multipv: u16,
+  multipv: &mut u16,
+  instant_move: bool,
+  mut resume_depth: u8,
+  mut ponder: bool,
 ) -> Option<()> {
   let mut position = board.load_from_thread();
   state.new_position(&position);
   let mut debug = false;
-  while receive_message.try_recv().is_ok() {}
-  let mut config = SearchConfig::new_time(&position, searchtime, receive_message, &mut debug);
-  let pv = search(
-    state,
-    &mut config,
-    &mut position,
-    &[],
-    multipv,
-    Output::Channel(tx),
-  );
-  tx.send(UlciResult::AnalysisStopped(pv[0])).ok()?;
-  Some(())
+  while let Ok(message) = receive_message.try_recv() {
+    match message {
+      Message::PonderHit => ponder = false,
+      Message::UpdateOption(name, value) => apply_option(state, multipv, &name, value),
+      _ => (),
+    }
+  }
+  // analysis can be paused to change options without losing progress
+  let analysing = !ponder && matches!(searchtime, SearchTime::Infinite);
+  loop {
+    let mut config = if ponder {
+      SearchConfig::new_ponder(&position, searchtime, receive_message, &mut debug)
+    } else {
+      SearchConfig::new_time(&position, searchtime, receive_message, &mut debug)
+    };
+    config.set_instant_move(instant_move);
+    config.resume_from(resume_depth);
+    config.set_priority_moves(priority.to_vec());
+    config.set_pause_for_options(analysing);
+    let pv = search(
+      state,
+      &mut config,
+      &mut position,
+      searchmoves,
+      *multipv,
+      Output::Channel(tx),
+    );
+    let options = config.take_pending_options();
+    if options.is_empty() {
+      tx.send(UlciResult::AnalysisStopped(pv[0])).ok()?;
+      return Some(());
+    }
+    for (name, value) in options {
+      apply_option(state, multipv, &name, value);
+    }
+    // the last depth finished is searched again with the new options
+    if let Some(iteration) = config.iterations().last() {
+      resume_depth = iteration.depth;
+    }
+  }
 }
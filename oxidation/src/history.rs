@@ -1,19 +1,62 @@
 use array2d::Array2D;
 use liberty_chess::moves::Move;
+use liberty_chess::{Board, Hash};
 
 const MAX_HISTORY: i32 = 1 << 14;
 
+// entries in the continuation history, which is hashed as a full table is too big for large boards
+const CONTINUATION_BITS: u32 = 20;
+// entries in the correction history for each side
+const CORRECTION_BITS: u32 = 14;
+// corrections are stored with extra precision so small adjustments add up
+const CORRECTION_GRAIN: i32 = 256;
+const MAX_CORRECTION: i32 = 64 * CORRECTION_GRAIN;
+
+// spreads keys across the hashed tables
+const MIX: u64 = 0x9E37_79B9_7F4A_7C15;
+
 type HistoryInternals = [Array2D<(i16, Option<Move>)>; 18];
 
 pub struct History {
   white_data: HistoryInternals,
   black_data: HistoryInternals,
+  // quiet move scores indexed by the previous move and the move
+  continuation: Vec<i16>,
+  // how far the static evaluation was off for each pawn structure, by side
+  correction: [Vec<i16>; 2],
 }
 
 fn get_data(width: usize, height: usize) -> Array2D<(i16, Option<Move>)> {
   Array2D::filled_with((0, None), height, width)
 }
 
+// The piece that just moved and where it went, if there was a move
+fn previous_move(board: &Board) -> Option<(u8, (usize, usize))> {
+  let last_move = board.last_move?;
+  let piece = board.get_piece(last_move.end()).unsigned_abs();
+  (piece != 0).then_some((piece, last_move.end()))
+}
+
+fn continuation_index(
+  side: bool,
+  (previous_piece, previous_square): (u8, (usize, usize)),
+  piece: u8,
+  square: (usize, usize),
+) -> usize {
+  let key = [
+    u64::from(side),
+    u64::from(previous_piece),
+    previous_square.0 as u64,
+    previous_square.1 as u64,
+    u64::from(piece),
+    square.0 as u64,
+    square.1 as u64,
+  ]
+  .into_iter()
+  .fold(0, |key, value| (key ^ value).wrapping_mul(MIX));
+  (key >> (u64::BITS - CONTINUATION_BITS)) as usize
+}
+
 impl History {
   pub fn new(width: usize, height: usize) -> Self {
     let white_data = [(); 18].map(|()| get_data(width, height));
@@ -21,6 +64,8 @@ impl History {
     Self {
       white_data,
       black_data,
+      continuation: vec![0; 1 << CONTINUATION_BITS],
+      correction: [(); 2].map(|()| vec![0; 1 << CORRECTION_BITS]),
     }
   }
 
@@ -32,6 +77,10 @@ impl History {
     for element in &mut self.black_data {
       *element = get_data(width, height);
     }
+    self.continuation.fill(0);
+    for table in &mut self.correction {
+      table.fill(0);
+    }
   }
 
   pub fn new_position(&mut self, width: usize, height: usize) {
@@ -49,6 +98,9 @@ impl History {
           *item /= 2;
         }
       }
+      for item in &mut self.continuation {
+        *item /= 2;
+      }
     }
   }
 
@@ -115,4 +167,58 @@ impl History {
       self.black_data[piece][square].1 = Some(mv);
     }
   }
+
+  // The score of a quiet move following the last move played
+  #[must_use]
+  pub fn get_continuation(&self, board: &Board, piece: u8, square: (usize, usize)) -> i16 {
+    previous_move(board).map_or(0, |previous| {
+      self.continuation[continuation_index(board.to_move(), previous, piece, square)]
+    })
+  }
+
+  fn update_continuation(&mut self, board: &Board, piece: u8, square: (usize, usize), bonus: i32) {
+    if let Some(previous) = previous_move(board) {
+      let index = continuation_index(board.to_move(), previous, piece, square);
+      Self::apply_history(&mut self.continuation[index], bonus);
+    }
+  }
+
+  pub fn continuation_bonus(
+    &mut self,
+    board: &Board,
+    piece: u8,
+    square: (usize, usize),
+    depth: u8,
+  ) {
+    self.update_continuation(board, piece, square, Self::stat_bonus(depth));
+  }
+
+  pub fn continuation_malus(
+    &mut self,
+    board: &Board,
+    piece: u8,
+    square: (usize, usize),
+    depth: u8,
+  ) {
+    self.update_continuation(board, piece, square, -Self::stat_bonus(depth));
+  }
+
+  // The adjustment to the static evaluation for a pawn structure in centipawns
+  #[must_use]
+  pub fn get_correction(&self, side: bool, key: Hash) -> i32 {
+    let index = (key >> (u64::BITS - CORRECTION_BITS)) as usize;
+    i32::from(self.correction[usize::from(side)][index]) / CORRECTION_GRAIN
+  }
+
+  // Move the correction towards the difference between the search result and static evaluation
+  pub fn update_correction(&mut self, side: bool, key: Hash, depth: u8, difference: i32) {
+    let index = (key >> (u64::BITS - CORRECTION_BITS)) as usize;
+    let entry = &mut self.correction[usize::from(side)][index];
+    let target = difference
+      .saturating_mul(CORRECTION_GRAIN)
+      .clamp(-MAX_CORRECTION, MAX_CORRECTION);
+    // deeper searches are trusted more
+    let weight = i32::from(depth.min(15)) + 1;
+    *entry = ((i32::from(*entry) * (256 - weight) + target * weight) / 256) as i16;
+  }
 }
@@ -1,44 +1,71 @@
 #![warn(missing_docs, unused)]
 //! A chess engine for Liberty Chess
 
-use crate::evaluate::evaluate;
+use crate::endgame::{trivial_win, Tablebase};
+use crate::evaluate::{evaluate, expected_score_scale, phase, wdl_model};
 use crate::history::History;
 use crate::movepicker::MovePicker;
-use crate::parameters::Parameters;
-use crate::search::alpha_beta_root;
+use crate::parameters::{blend_piece_values, Parameters};
 use crate::search::SearchParameters;
+use crate::search::{alpha_beta_root, get_root_moves, next_iteration, RootMove};
+use crate::strength::{Strength, STRENGTH_LINES};
 use crate::tt::TranspositionTable;
+use liberty_chess::clock::Millis;
 use liberty_chess::moves::Move;
-use liberty_chess::{perft, Board, ExtraFlags, Piece, PAWN};
+use liberty_chess::positions::{
+  AFRICAN, CAPABLANCA, CAPABLANCA_RECTANGLE, DOUBLE_CHESS, ELIMINATION, HORDE, LIBERTY_CHESS,
+  LOADED_BOARD, MINI, MONGOL, NARNIA, STARTPOS, TRUMP,
+};
+use liberty_chess::{
+  perft, Board, ExtraFlags, Piece, BISHOP, KING, KNIGHT, PAWN, QUEEN, ROOK, WALL,
+};
 use parameters::DEFAULT_PARAMETERS;
 use parameters::PAWN_SCALING_NUMERATOR;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{thread_rng, Rng};
 use std::cmp::{max, Ordering};
+use std::fs::read_to_string;
 use std::io::{Stdout, Write};
 use std::ops::Mul;
 use std::sync::mpsc::{Receiver, Sender, TryRecvError};
-use std::time::Instant;
 use ulci::client::Message;
-use ulci::server::UlciResult;
-use ulci::{AnalysisResult, Score, SearchTime};
+use ulci::server::{InfoType, UlciResult};
+use ulci::{AnalysisResult, OptionValue, Score, SearchTime, WDL};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
 
 #[cfg(not(feature = "feature_extraction"))]
 use crate::parameters::PackedParameters;
 
+/// A C API for linking the engine directly
+#[cfg(feature = "capi")]
+pub mod capi;
+/// Optional features the engine was built with and runtime CPU feature detection
+pub mod dispatch;
 /// Evaluation
 pub mod evaluate;
 /// Interface for efficiently integrating into another application
 pub mod glue;
+/// A lighter engine that searches in slices, for platforms without threads
+pub mod lite;
 /// Tunable parameters
 pub mod parameters;
 /// Searching through a position
 pub mod search;
+/// Weakening the engine to play against humans
+pub mod strength;
 
+mod endgame;
 mod history;
 mod movepicker;
 mod tt;
 
+#[cfg(test)]
+mod tests;
+
 /// The version number of the engine
 pub const VERSION_NUMBER: &str = env!("CARGO_PKG_VERSION");
 
@@ -46,9 +73,74 @@ pub const VERSION_NUMBER: &str = env!("CARGO_PKG_VERSION");
 pub const HASH_SIZE: usize = 64;
 /// Default Multi-PV lines
 pub const MULTI_PV_COUNT: u16 = 1;
+/// Default minimum time between info lines during an iteration in milliseconds
+pub const INFO_INTERVAL: u128 = 50;
+
+/// The default depth for bench
+pub const BENCH_DEPTH: i8 = 9;
+/// The positions used for bench, with an offset to the bench depth
+pub const BENCH_POSITIONS: &[(&str, i8)] = &[
+  (STARTPOS, 0),
+  (CAPABLANCA_RECTANGLE, 0),
+  (CAPABLANCA, 0),
+  (LIBERTY_CHESS, -1),
+  (MINI, 2),
+  (MONGOL, 0),
+  (AFRICAN, -1),
+  (NARNIA, 1),
+  (TRUMP, -2),
+  (LOADED_BOARD, -4),
+  (DOUBLE_CHESS, -2),
+  (HORDE, -1),
+  (ELIMINATION, 0),
+  ("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1", 0),
+];
+
+/// Load a bench suite from a file, with one L-FEN per line.
+///
+/// A line can override the bench depth with `;<depth>` after the L-FEN.
+/// Blank lines and lines starting with `#` are ignored.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read or it has an invalid line.
+pub fn load_bench_suite(path: &str, depth: i8) -> Result<Vec<(String, i8)>, String> {
+  let file = read_to_string(path).map_err(|error| format!("unable to read {path}: {error}"))?;
+  let mut positions = Vec::new();
+  for (line_number, line) in file.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    let line_number = line_number + 1;
+    let (fen, position_depth) = match line.split_once(';') {
+      Some((fen, position_depth)) => match position_depth.trim().parse() {
+        Ok(position_depth) => (fen.trim(), position_depth),
+        Err(_) => return Err(format!("invalid depth on line {line_number}")),
+      },
+      None => (line, depth),
+    };
+    if position_depth < 1 {
+      return Err(format!("depth must be positive on line {line_number}"));
+    }
+    if let Err(error) = Board::new(fen) {
+      return Err(format!("invalid L-FEN on line {line_number}: {error}"));
+    }
+    positions.push((fen.to_owned(), position_depth));
+  }
+  if positions.is_empty() {
+    return Err(format!("no positions found in {path}"));
+  }
+  Ok(positions)
+}
 
 const DRAW_SCORE: Score = Score::Centipawn(0);
 
+// time kept in reserve for communication delays
+const MOVE_OVERHEAD: Millis = Millis::new(100);
+// the search can extend to this fraction of the clock when the score drops sharply
+const PANIC_FRACTION: u128 = 8;
+
 /// The output type to use for analysis results
 pub enum Output<'a> {
   /// Output to the provided stdout
@@ -61,6 +153,9 @@ struct StackEntry {
   movepicker: MovePicker,
   board: Board,
   eval: Option<i32>,
+  // move buffers for quiescence search, reused to avoid allocating every node
+  captures: Vec<(Move, u8, u8)>,
+  recaptures: Vec<(Move, u8)>,
 }
 
 impl StackEntry {
@@ -69,6 +164,8 @@ impl StackEntry {
       movepicker: MovePicker::new(),
       board,
       eval: None,
+      captures: Vec::new(),
+      recaptures: Vec::new(),
     }
   }
 
@@ -85,11 +182,33 @@ pub struct State {
   history: History,
   // Thing indexed by ply, contains heap allocation caches
   stack: Vec<StackEntry>,
+  // the search parameters passed in and the ones used for the current position
+  tuned_search_parameters: SearchParameters,
   search_parameters: SearchParameters,
+  // search more carefully in positions the parameters weren't tuned for
+  conservative: bool,
+  // estimate values for poorly tuned pieces from their mobility
+  mobility_values: bool,
+  // report scores in pawns of the current variant
+  normalize_scores: bool,
+  // report win, draw and loss chances in info lines
+  show_wdl: bool,
+  // None for full strength
+  strength: Option<Strength>,
+  // what the parameters weren't tuned for in the current position, and if it has been reported
+  warnings: Vec<String>,
+  warnings_reported: bool,
+  // the parameters passed in, before adjusting for the position
+  tuned_parameters: Parameters<i32>,
   parameters: Parameters<i32>,
   #[cfg(not(feature = "feature_extraction"))]
   packed_parameters: PackedParameters,
   promotion_values: (i32, i32),
+  // the last trivial ending solved, reused while the board size and mating piece are the same
+  tablebase: Option<Tablebase>,
+  // whether the CPU supports the fast packed evaluation
+  #[cfg(not(feature = "feature_extraction"))]
+  fast_eval: bool,
 }
 
 impl State {
@@ -106,14 +225,109 @@ impl State {
       table: TranspositionTable::new(megabytes, position),
       history: History::new(position.width(), position.height()),
       stack: Vec::new(),
+      tuned_search_parameters: search_parameters,
       search_parameters,
+      conservative: false,
+      mobility_values: false,
+      normalize_scores: false,
+      show_wdl: false,
+      strength: None,
+      warnings: untuned_warnings(position, &parameters, &parameters),
+      warnings_reported: false,
+      tuned_parameters: parameters,
       parameters,
       #[cfg(not(feature = "feature_extraction"))]
       packed_parameters: parameters.into(),
       promotion_values,
+      tablebase: None,
+      #[cfg(not(feature = "feature_extraction"))]
+      fast_eval: dispatch::fast_eval_available(),
+    }
+  }
+
+  // Adjust the piece values for the pieces and board size of a position
+  fn update_parameters(&mut self, position: &Board) {
+    self.parameters = if self.mobility_values {
+      blend_piece_values(self.tuned_parameters, position)
+    } else {
+      self.tuned_parameters
+    };
+    #[cfg(not(feature = "feature_extraction"))]
+    {
+      self.packed_parameters = self.parameters.into();
+    }
+    self.promotion_values = get_promotion_values(position.promotion_options(), &self.parameters);
+    self.warnings = untuned_warnings(position, &self.tuned_parameters, &self.parameters);
+    self.warnings_reported = false;
+    self.update_search_parameters();
+  }
+
+  fn update_search_parameters(&mut self) {
+    self.search_parameters = if self.conservative && !self.warnings.is_empty() {
+      self.tuned_search_parameters.conservative()
+    } else {
+      self.tuned_search_parameters
+    };
+  }
+
+  /// Use a more conservative search in positions the parameters weren't tuned for
+  pub fn set_conservative(&mut self, conservative: bool) {
+    self.conservative = conservative;
+    self.update_search_parameters();
+  }
+
+  /// Estimate values from mobility for pieces whose tuned values look unreliable
+  ///
+  /// Experimental and off by default until it has passed an SPRT in random armies.
+  /// Clears the hash, as stored scores use the old values.
+  pub fn set_mobility_values(&mut self, mobility_values: bool, position: &Board) {
+    if mobility_values != self.mobility_values {
+      self.mobility_values = mobility_values;
+      self.new_game(position);
+    }
+  }
+
+  /// Report scores relative to the value of a pawn in the current variant
+  pub fn set_normalize_scores(&mut self, normalize_scores: bool) {
+    self.normalize_scores = normalize_scores;
+  }
+
+  /// Report the estimated win, draw and loss chances in info lines
+  pub fn set_show_wdl(&mut self, show_wdl: bool) {
+    self.show_wdl = show_wdl;
+  }
+
+  /// Play at roughly the specified Elo, or at full strength for `None`
+  ///
+  /// Limiting strength caps the nodes searched, adds noise to the evaluation and sometimes plays
+  /// worse moves from extra `MultiPV` lines
+  pub fn set_strength(&mut self, elo: Option<u32>) {
+    self.strength = elo.map(Strength::new);
+  }
+
+  /// The score to report, normalized if enabled
+  #[must_use]
+  pub fn reported_score(&self, score: Score) -> Score {
+    if self.normalize_scores {
+      score.normalize(average_value(&self.parameters, PAWN))
+    } else {
+      score
     }
   }
 
+  /// The estimated win, draw and loss chances for the side to move from a search score
+  #[must_use]
+  pub fn wdl(&self, score: Score, position: &Board) -> WDL {
+    let score = score.normalize(average_value(&self.parameters, PAWN));
+    wdl_model(score, phase(position))
+  }
+
+  /// The rules and pieces in the current position that the parameters weren't tuned for
+  #[must_use]
+  pub fn warnings(&self) -> &[String] {
+    &self.warnings
+  }
+
   /// Updates the state with the new position
   ///
   /// Returns true if the hash was cleared
@@ -122,15 +336,21 @@ impl State {
       .history
       .new_position(position.width(), position.height());
     self.stack.clear();
-    self.promotion_values = get_promotion_values(position.promotion_options(), &self.parameters);
-    self.table.new_position(position)
+    // only change the evaluation when the hash is cleared so stored scores stay consistent
+    let cleared = self.table.new_position(position);
+    if cleared {
+      self.update_parameters(position);
+    } else {
+      self.promotion_values = get_promotion_values(position.promotion_options(), &self.parameters);
+    }
+    cleared
   }
 
   /// Clears the hash
   pub fn new_game(&mut self, position: &Board) {
     self.history.clear(position.width(), position.height());
     self.stack.clear();
-    self.promotion_values = get_promotion_values(position.promotion_options(), &self.parameters);
+    self.update_parameters(position);
     self.table.clear(ExtraFlags::new(position));
   }
 
@@ -141,6 +361,55 @@ impl State {
   }
 }
 
+/// Rules in a position that the parameters weren't tuned for
+#[must_use]
+pub fn untuned_rules(position: &Board) -> Vec<&'static str> {
+  let mut rules = Vec::new();
+  if position.friendly_fire {
+    rules.push("friendly fire");
+  }
+  if position.king_count_changed() {
+    rules.push("king count");
+  }
+  if position.pawn_moves_changed() {
+    rules.push("pawn moves");
+  }
+  rules
+}
+
+// Everything in a position the evaluation is likely to be wrong about
+fn untuned_warnings(
+  position: &Board,
+  tuned_parameters: &Parameters<i32>,
+  parameters: &Parameters<i32>,
+) -> Vec<String> {
+  let mut warnings: Vec<String> = untuned_rules(position)
+    .into_iter()
+    .map(|rule| format!("parameters not tuned for {rule}"))
+    .collect();
+  if tuned_parameters.pieces != parameters.pieces {
+    warnings.push("piece values estimated from mobility".to_owned());
+  }
+  warnings
+}
+
+// Time to spend on a move with Fischer increment, spreading the time left over the moves to go
+fn increment_time(time: Millis, inc: Millis, moves_to_go: Option<u32>) -> u128 {
+  let time = if time.is_zero() {
+    // increment only, so the increment is all the time available
+    inc - MOVE_OVERHEAD
+  } else {
+    let time = time - MOVE_OVERHEAD;
+    let share = match moves_to_go {
+      // the soft limit usually stops the search well before this
+      Some(moves) => time * 8 / (u128::from(moves) * 5),
+      None => time / 15,
+    };
+    time.min(share + inc * 3 / 4)
+  };
+  1.max(time.get())
+}
+
 /// Convert promotion options to values
 ///
 /// For evaluating the advanced pawn bonus
@@ -161,6 +430,43 @@ pub fn get_promotion_values<T: Copy + PartialOrd + Mul<T, Output = T> + From<i32
   (pieces.0 * scale_factor, pieces.1 * scale_factor)
 }
 
+// Average of the middlegame and endgame value of a piece
+fn average_value(parameters: &Parameters<i32>, piece: Piece) -> i32 {
+  let (mg, eg) = parameters.pieces[usize::from(piece.unsigned_abs()) - 1];
+  (mg + eg) / 2
+}
+
+fn material_value(piece: Piece) -> i32 {
+  average_value(&DEFAULT_PARAMETERS, piece)
+}
+
+/// The value of a pawn in a position, for reporting scores in pawns of the variant being played
+#[must_use]
+pub fn pawn_value(board: &Board) -> i32 {
+  average_value(&blend_piece_values(DEFAULT_PARAMETERS, board), PAWN)
+}
+
+/// The centipawn scale for converting scores in a position to a win probability
+///
+/// The scale comes from the WDL model for the phase of the game, in the engine's units.
+/// Scores are inflated in variants with more material on the board than standard chess,
+/// so the scale grows with the material.
+#[must_use]
+pub fn win_probability_scale(board: &Board) -> f32 {
+  let standard_material = 16 * material_value(PAWN)
+    + 4 * (material_value(KNIGHT) + material_value(BISHOP) + material_value(ROOK))
+    + 2 * material_value(QUEEN);
+  let material: i32 = (1..=WALL)
+    .filter(|piece| *piece != KING)
+    .map(|piece| {
+      (board.piece_count(piece) + board.piece_count(-piece)) as i32 * material_value(piece)
+    })
+    .sum();
+  let inflation = (f64::from(material) / f64::from(standard_material)).max(1.0);
+  let scale = expected_score_scale(phase(board)) * f64::from(pawn_value(board)) / 100.0;
+  (scale * inflation) as f32
+}
+
 /// Configuration for the search
 pub struct SearchConfig<'a> {
   start: Instant,
@@ -182,6 +488,50 @@ pub struct SearchConfig<'a> {
   next_check: usize,
   // nodetm state
   best_move_nodes: usize,
+  // latest clock update received during the search
+  clock_update: Option<SearchTime>,
+  // fail highs this iteration, and how many of them were on the first move searched
+  fail_highs: usize,
+  first_move_fail_highs: usize,
+  // statistics for each completed iteration
+  iterations: Vec<IterationStats>,
+  // skip the search when the move is obvious
+  instant_move: bool,
+  // depth to start iterative deepening from, for resuming a saved search
+  resume_depth: u8,
+  // candidate moves from the user to search first
+  priority: Vec<Move>,
+  // smart time management: the best move and score from the last iteration,
+  // iterations in a row they stayed stable for and whether the best move failed low
+  last_best: Option<(Move, Score)>,
+  stable_iterations: usize,
+  fail_low: bool,
+  // whether the score dropped sharply this search, and the time limit to extend to if it did
+  panic: bool,
+  panic_limit: u128,
+  // depth, time, node and hard time management limits to apply on `ponderhit`
+  ponder: Option<(u8, u128, usize, bool)>,
+  // minimum time between info lines during an iteration and when the last one was shown
+  info_interval: u128,
+  last_info: u128,
+  // whether option changes pause the search, and the changes waiting to be applied
+  pause_for_options: bool,
+  pending_options: Vec<(String, OptionValue)>,
+}
+
+/// Statistics for a completed iteration of the search
+#[derive(Clone, Copy, Debug)]
+pub struct IterationStats {
+  /// The depth of the iteration
+  pub depth: u8,
+  /// The nodes searched in the iteration
+  pub nodes: usize,
+  /// The time from the start of the search to the end of the iteration in milliseconds
+  pub time: u128,
+  /// The ratio of nodes searched to the previous iteration
+  pub ebf: f32,
+  /// The proportion of fail highs that occurred on the first move searched
+  pub first_move_fail_high: f32,
 }
 
 impl<'a> SearchConfig<'a> {
@@ -212,6 +562,23 @@ impl<'a> SearchConfig<'a> {
       check_frequency: 1,
       next_check: 1,
       best_move_nodes: 0,
+      clock_update: None,
+      fail_highs: 0,
+      first_move_fail_highs: 0,
+      iterations: Vec::new(),
+      instant_move: false,
+      resume_depth: 0,
+      priority: Vec::new(),
+      last_best: None,
+      stable_iterations: 0,
+      fail_low: false,
+      panic: false,
+      panic_limit: max_time,
+      ponder: None,
+      info_interval: INFO_INTERVAL,
+      last_info: 0,
+      pause_for_options: false,
+      pending_options: Vec::new(),
     }
   }
 
@@ -224,10 +591,8 @@ impl<'a> SearchConfig<'a> {
   ) -> Self {
     match time {
       SearchTime::Increment(time, inc) => {
-        let time = time.saturating_sub(100);
-        let time = time.min(time / 15 + 3 * inc / 4);
-        let time = 1.max(time);
-        Self::new(u8::MAX, time, usize::MAX, Score::Loss(0), false, rx, debug)
+        let max_time = increment_time(time, inc, None);
+        Self::new_clock(time, max_time, rx, debug)
       }
       SearchTime::Asymmetric(wtime, winc, btime, binc) => {
         let (time, inc) = if board.to_move() {
@@ -235,9 +600,37 @@ impl<'a> SearchConfig<'a> {
         } else {
           (btime, binc)
         };
-        let time = time.saturating_sub(100);
-        let time = time.min(time / 15 + 3 * inc / 4);
-        let time = 1.max(time);
+        let max_time = increment_time(time, inc, None);
+        Self::new_clock(time, max_time, rx, debug)
+      }
+      SearchTime::MovesToGo(wtime, winc, btime, binc, moves) => {
+        let (time, inc) = if board.to_move() {
+          (wtime, winc)
+        } else {
+          (btime, binc)
+        };
+        // a move in reserve, so the last move before the time control isn't left short
+        let max_time = increment_time(time, inc, Some(moves.max(1).saturating_add(1)));
+        Self::new_clock(time, max_time, rx, debug)
+      }
+      SearchTime::Delay(wtime, btime, delay) => {
+        let time = if board.to_move() { wtime } else { btime };
+        // the delay is free, so use all of it
+        let time = (delay - MOVE_OVERHEAD) + (time - MOVE_OVERHEAD) / 20;
+        let time = 1.max(time.get());
+        Self::new(u8::MAX, time, usize::MAX, Score::Loss(0), false, rx, debug)
+      }
+      SearchTime::Hourglass(wtime, btime) => {
+        let (time, opponent_time) = if board.to_move() {
+          (wtime, btime)
+        } else {
+          (btime, wtime)
+        };
+        // time spent is given to the opponent, so spend more when ahead on time
+        let lead = time - opponent_time;
+        let time = time - MOVE_OVERHEAD;
+        let time = time.min(time / 20 + lead / 4);
+        let time = 1.max(time.get());
         Self::new(u8::MAX, time, usize::MAX, Score::Loss(0), false, rx, debug)
       }
       SearchTime::Infinite => Self::new(
@@ -251,7 +644,7 @@ impl<'a> SearchConfig<'a> {
       ),
       SearchTime::Other(limits) => Self::new(
         limits.depth,
-        limits.time,
+        limits.time.get(),
         limits.nodes,
         Score::Loss(0),
         true,
@@ -270,6 +663,95 @@ impl<'a> SearchConfig<'a> {
     }
   }
 
+  // Initialise the search config for a clock with the time left, which panicking can use more of
+  fn new_clock(
+    time: Millis,
+    max_time: u128,
+    rx: &'a Receiver<Message>,
+    debug: &'a mut bool,
+  ) -> Self {
+    let mut config = Self::new(
+      u8::MAX,
+      max_time,
+      usize::MAX,
+      Score::Loss(0),
+      false,
+      rx,
+      debug,
+    );
+    config.panic_limit = max_time.max((time - MOVE_OVERHEAD).get() / PANIC_FRACTION);
+    config
+  }
+
+  /// Initialise the search config to ponder on the opponent's time
+  ///
+  /// Searches without limits until `ponderhit`, then switches to the limits for the search time
+  pub fn new_ponder(
+    board: &Board,
+    time: SearchTime,
+    rx: &'a Receiver<Message>,
+    debug: &'a mut bool,
+  ) -> Self {
+    let mut config = Self::new_time(board, time, rx, debug);
+    config.ponder = Some((
+      config.max_depth,
+      config.max_time,
+      config.max_nodes,
+      config.hard_tm,
+    ));
+    config.max_depth = u8::MAX;
+    config.max_time = u128::MAX;
+    config.max_nodes = usize::MAX;
+    config.hard_tm = true;
+    config
+  }
+
+  /// Play forced moves and mates found in the transposition table without searching
+  ///
+  /// Only applies to searches with time management
+  pub fn set_instant_move(&mut self, instant_move: bool) {
+    self.instant_move = instant_move;
+  }
+
+  /// Set the minimum time between info lines in milliseconds
+  ///
+  /// The final line for each depth is always shown, 0 shows every line
+  pub fn set_info_interval(&mut self, info_interval: u128) {
+    self.info_interval = info_interval;
+  }
+
+  /// Set candidate moves to search first at the root
+  ///
+  /// Candidates are never reduced or pruned, but other moves are still searched
+  pub fn set_priority_moves(&mut self, priority: Vec<Move>) {
+    self.priority = priority;
+  }
+
+  /// Start the search from a depth reached by a previous search
+  ///
+  /// The transposition table should contain the results of the previous search
+  pub fn resume_from(&mut self, depth: u8) {
+    self.resume_depth = depth;
+  }
+
+  /// Stop the search when an option is changed instead of refusing the change
+  ///
+  /// The changes are kept for the caller to apply before resuming the search
+  pub fn set_pause_for_options(&mut self, pause_for_options: bool) {
+    self.pause_for_options = pause_for_options;
+  }
+
+  /// Take the option changes received during the search
+  pub fn take_pending_options(&mut self) -> Vec<(String, OptionValue)> {
+    std::mem::take(&mut self.pending_options)
+  }
+
+  /// The latest clock update received during the search, if any
+  #[must_use]
+  pub const fn clock_update(&self) -> Option<SearchTime> {
+    self.clock_update
+  }
+
   fn search_is_over(&mut self) -> bool {
     if self.stopped || self.nodes >= self.max_nodes {
       self.stopped = true;
@@ -279,34 +761,18 @@ impl<'a> SearchConfig<'a> {
       let millis = self.start.elapsed().as_millis();
       if millis > self.millis {
         self.millis = millis;
-        if millis >= self.max_time {
+        if millis >= self.time_limit() {
           self.stopped = true;
           return true;
         }
         loop {
           match self.rx.try_recv() {
-            Ok(message) => match message {
-              Message::SetDebug(new_debug) => *self.debug = new_debug,
-              Message::UpdatePosition(_) => {
-                println!("info error search in progress, cannot change position")
-              }
-              Message::Go(_)
-              | Message::Eval
-              | Message::Bench(_)
-              | Message::NewGame
-              | Message::Perft(_) => {
-                println!("info error already searching, cannot start new search")
-              }
-              Message::Stop => {
-                self.stopped = true;
+            Ok(message) => {
+              self.handle_message(message);
+              if self.stopped {
                 return true;
               }
-              Message::UpdateOption(..) => {
-                println!("info error cannot change options during search")
-              }
-              Message::IsReady => println!("readyok"),
-              Message::Clock(_) | Message::Info(_) => (),
-            },
+            }
             Err(TryRecvError::Disconnected) => {
               self.stopped = true;
               return true;
@@ -323,7 +789,128 @@ impl<'a> SearchConfig<'a> {
     false
   }
 
-  fn soft_limit(&self, multipv: bool) -> u128 {
+  fn handle_message(&mut self, message: Message) {
+    match message {
+      Message::SetDebug(new_debug) => *self.debug = new_debug,
+      Message::UpdatePosition(_) => {
+        println!("info error search in progress, cannot change position")
+      }
+      Message::Go(_)
+      | Message::Eval
+      | Message::Bench(..)
+      | Message::NewGame
+      | Message::Perft(_) => {
+        println!("info error already searching, cannot start new search")
+      }
+      Message::Stop => self.stopped = true,
+      Message::PonderHit => {
+        if let Some(limits) = self.ponder.take() {
+          (self.max_depth, self.max_time, self.max_nodes, self.hard_tm) = limits;
+          // the time control starts from when the opponent moved
+          self.start = Instant::now();
+          self.millis = 0;
+          self.max_nodes = self.max_nodes.saturating_add(self.nodes);
+        }
+      }
+      Message::UpdateOption(name, value) => {
+        if self.pause_for_options {
+          self.pending_options.push((name, value));
+          self.stopped = true;
+        } else {
+          println!("info error cannot change options during search")
+        }
+      }
+      Message::IsReady => println!("readyok"),
+      Message::Clock(time) => self.clock_update = Some(time),
+      Message::Info(_) | Message::Kibbutz(_) | Message::Notice(_) => (),
+    }
+  }
+
+  // A finished ponder search can't report a move until the opponent has moved
+  fn wait_for_ponderhit(&mut self) {
+    while self.ponder.is_some() && !self.stopped {
+      match self.rx.recv() {
+        Ok(message) => self.handle_message(message),
+        Err(_) => self.stopped = true,
+      }
+    }
+  }
+
+  fn add_fail_high(&mut self, first_move: bool) {
+    self.fail_highs += 1;
+    if first_move {
+      self.first_move_fail_highs += 1;
+    }
+  }
+
+  fn add_iteration(&mut self, depth: u8, nodes: usize) {
+    let ebf = self
+      .iterations
+      .last()
+      .map_or(0.0, |last| nodes as f32 / last.nodes.max(1) as f32);
+    let first_move_fail_high = if self.fail_highs > 0 {
+      self.first_move_fail_highs as f32 / self.fail_highs as f32
+    } else {
+      0.0
+    };
+    self.iterations.push(IterationStats {
+      depth,
+      nodes,
+      time: self.start.elapsed().as_millis(),
+      ebf,
+      first_move_fail_high,
+    });
+  }
+
+  /// Statistics for each iteration completed by the last search
+  #[must_use]
+  pub fn iterations(&self) -> &[IterationStats] {
+    &self.iterations
+  }
+
+  // The previous best move scoring well below its last score means the position needs more time,
+  // and a sharp drop to a score that isn't a win means the game could be about to go wrong
+  fn check_fail_low(&mut self, mv: Move, score: Score, parameters: &SearchParameters) {
+    if let Some((last_move, last_score)) = self.last_best {
+      if mv == last_move && score < last_score {
+        if !within_margin(score, last_score, parameters.tm_fail_low_margin) {
+          self.fail_low = true;
+        }
+        if !within_margin(score, last_score, parameters.tm_panic_margin)
+          && !matches!(score, Score::Win(_))
+        {
+          self.panic = true;
+        }
+      }
+    }
+  }
+
+  // The hard time limit, which is raised once the search panics
+  fn time_limit(&self) -> u128 {
+    if self.panic {
+      self.max_time.max(self.panic_limit)
+    } else {
+      self.max_time
+    }
+  }
+
+  // Track how many iterations in a row the best move and score have stayed the same
+  fn update_stability(&mut self, best_move: Option<Move>, score: Score, margin: f32) {
+    let stable = match (self.last_best, best_move) {
+      (Some((last_move, last_score)), Some(mv)) => {
+        mv == last_move && within_margin(score, last_score, margin)
+      }
+      _ => false,
+    };
+    self.stable_iterations = if stable {
+      self.stable_iterations + 1
+    } else {
+      0
+    };
+    self.last_best = best_move.map(|mv| (mv, score));
+  }
+
+  fn soft_limit(&self, multipv: bool, parameters: &SearchParameters) -> u128 {
     if multipv {
       self.max_time / 3
     } else {
@@ -332,16 +919,40 @@ impl<'a> SearchConfig<'a> {
       } else {
         (self.best_move_nodes * 1000 / self.nodes) as u128
       };
-      self.max_time * (1410 - best_move_permill) / 2282
+      let limit = self.max_time * (1410 - best_move_permill) / 2282;
+      let scale = if self.panic {
+        parameters.tm_panic_extension
+      } else if self.fail_low {
+        parameters.tm_fail_low_extension
+      } else if self.stable_iterations >= parameters.tm_stability_iterations as usize {
+        parameters.tm_stability_scale
+      } else {
+        1.0
+      };
+      ((limit as f32 * scale) as u128).min(self.time_limit())
     }
   }
 }
 
+// Whether two scores are within a margin in centipawns, mate scores have to match exactly
+fn within_margin(score: Score, other: Score, margin: f32) -> bool {
+  match (score, other) {
+    (Score::Centipawn(score), Score::Centipawn(other)) => score.abs_diff(other) as f32 <= margin,
+    _ => score == other,
+  }
+}
+
 /// Returns a random legal move from the provided position, if one exists
 #[must_use]
 pub fn random_move(board: &Board) -> Option<Move> {
-  let moves = board.generate_legal();
-  moves.choose(&mut thread_rng())?.last_move
+  random_move_with_rng(board, &mut thread_rng())
+}
+
+/// Returns a random legal move picked with the provided RNG, so games can be reproduced from a seed
+#[must_use]
+pub fn random_move_with_rng<R: Rng + ?Sized>(board: &Board, rng: &mut R) -> Option<Move> {
+  let moves = board.generate_legal_moves();
+  moves.choose(rng).copied()
 }
 
 /// Returns the top capture by MVV-LVA or a random quiet if there are no captures
@@ -355,6 +966,40 @@ pub fn mvvlva_move(board: &Board) -> Option<Move> {
   }
 }
 
+/// Picks a capture weighted by MVV-LVA or a random quiet if there are no captures
+///
+/// The temperature is in centipawns, higher temperatures make worse captures more likely
+#[must_use]
+pub fn mvvlva_move_with_temperature(board: &Board, temperature: u32) -> Option<Move> {
+  if temperature == 0 {
+    return mvvlva_move(board);
+  }
+  let mut captures = Vec::new();
+  let mut quiets = Vec::new();
+  board.generate_pseudolegal(&mut captures, &mut quiets);
+  let captures: Vec<(Move, i32)> = captures
+    .into_iter()
+    .filter(|(m, _, _)| board.move_if_legal(*m).is_some())
+    .map(|(m, piece, capture)| {
+      let attacker = DEFAULT_PARAMETERS.pieces[usize::from(piece - 1)].0;
+      let victim = DEFAULT_PARAMETERS.pieces[usize::from(capture - 1)].0;
+      (m, victim - attacker / 100)
+    })
+    .collect();
+  if let Some(best) = captures.iter().map(|(_, value)| *value).max() {
+    let temperature = f64::from(temperature);
+    captures
+      .choose_weighted(&mut thread_rng(), |(_, value)| {
+        (f64::from(value - best) / temperature).exp()
+      })
+      .ok()
+      .map(|(m, _)| *m)
+  } else {
+    quiets.retain(|m| board.move_if_legal(*m).is_some());
+    quiets.choose(&mut thread_rng()).copied()
+  }
+}
+
 /// Sort the searchmoves from a position
 #[must_use]
 pub fn get_move_order(
@@ -396,18 +1041,24 @@ pub fn get_move_order(
   (captures, quiets)
 }
 
+// Summary lines end each depth and are always shown, other lines are rate limited
 fn print_info(
   out: &mut Output,
   position: &Board,
   score: Score,
   depth: u8,
-  settings: &SearchConfig,
+  settings: &mut SearchConfig,
   pv: &[Move],
   pv_line: u16,
   show_pv_line: bool,
-  hashfull: usize,
+  state: &State,
+  summary: bool,
 ) {
   let time = settings.start.elapsed().as_millis();
+  if !summary && time < settings.last_info.saturating_add(settings.info_interval) {
+    return;
+  }
+  settings.last_info = time;
   let nps = (1000 * settings.nodes) / max(time as usize, 1);
   match out {
     Output::String(ref mut out) => {
@@ -416,15 +1067,30 @@ fn print_info(
       } else {
         String::new()
       };
+      // only worth reporting once the depth is finished
+      let details = if summary {
+        format!(
+          "seldepth {} hashfull {} ",
+          settings.seldepth,
+          state.table.capacity()
+        )
+      } else {
+        String::new()
+      };
+      let wdl = if state.show_wdl {
+        format!("{} ", state.wdl(score, position).to_string())
+      } else {
+        String::new()
+      };
       out
         .write_all(
           format!(
-            "info depth {depth} seldepth {} score {} time {time} nodes {} nps {nps} hashfull {hashfull} {multipv}pv {}\n",
-            settings.seldepth,
-            score.show_uci(position.moves(), position.to_move()),
+            "info depth {depth} {details}score {} {wdl}time {time} nodes {} nps {nps} {multipv}pv {}\n",
+            state
+              .reported_score(score)
+              .show_uci(position.moves(), position.to_move()),
             settings.nodes,
-            pv
-              .iter()
+            pv.iter()
               .map(Move::to_string)
               .collect::<Vec<String>>()
               .join(" ")
@@ -440,7 +1106,7 @@ fn print_info(
         depth: u16::from(depth),
         nodes: settings.nodes,
         time,
-        wdl: None,
+        wdl: Some(state.wdl(score, position)),
         pv_line,
       }))
       .ok();
@@ -448,6 +1114,96 @@ fn print_info(
   }
 }
 
+// Show the nodes spent on each root move, most searched first
+fn print_root_nodes(out: &mut Output, root_moves: &[RootMove]) {
+  let mut root_nodes: Vec<(Move, usize)> = root_moves
+    .iter()
+    .filter(|root_move| root_move.nodes > 0)
+    .map(|root_move| (root_move.mv, root_move.nodes))
+    .collect();
+  root_nodes.sort_by(|(_, a), (_, b)| b.cmp(a));
+  let info = format!(
+    "rootnodes {}",
+    root_nodes
+      .iter()
+      .map(|(mv, nodes)| format!("{} {nodes}", mv.to_string()))
+      .collect::<Vec<String>>()
+      .join(" ")
+  );
+  match out {
+    Output::String(ref mut out) => {
+      out
+        .write_all(format!("info string {info}\n").as_bytes())
+        .ok();
+    }
+    Output::Channel(tx) => {
+      tx.send(UlciResult::Info(InfoType::String, info)).ok();
+    }
+  }
+}
+
+// Report why the search was skipped
+fn print_instant_move(out: &mut Output, reason: &str) {
+  let info = format!("instant move {reason}");
+  match out {
+    Output::String(ref mut out) => {
+      out
+        .write_all(format!("info string {info}\n").as_bytes())
+        .ok();
+    }
+    Output::Channel(tx) => {
+      tx.send(UlciResult::Info(InfoType::String, info)).ok();
+    }
+  }
+}
+
+// Warn about the evaluation being unreliable
+fn print_warnings(out: &mut Output, warnings: &[String]) {
+  for warning in warnings {
+    let info = format!("warning {warning}");
+    match out {
+      Output::String(ref mut out) => {
+        out
+          .write_all(format!("info string {info}\n").as_bytes())
+          .ok();
+      }
+      Output::Channel(tx) => {
+        tx.send(UlciResult::Info(InfoType::String, info)).ok();
+      }
+    }
+  }
+}
+
+// Show the statistics for each completed iteration of the search
+fn print_search_stats(out: &mut Output, settings: &SearchConfig) {
+  let info = format!(
+    "searchstats {}",
+    settings
+      .iterations
+      .iter()
+      .map(|iteration| format!(
+        "{} {} {} {:.2} {:.3}",
+        iteration.depth,
+        iteration.nodes,
+        iteration.time,
+        iteration.ebf,
+        iteration.first_move_fail_high
+      ))
+      .collect::<Vec<String>>()
+      .join(" ")
+  );
+  match out {
+    Output::String(ref mut out) => {
+      out
+        .write_all(format!("info string {info}\n").as_bytes())
+        .ok();
+    }
+    Output::Channel(tx) => {
+      tx.send(UlciResult::Info(InfoType::String, info)).ok();
+    }
+  }
+}
+
 /// Search the specified position and moves to the specified depth
 pub fn search(
   state: &mut State,
@@ -458,52 +1214,99 @@ pub fn search(
   mut out: Output,
 ) -> Vec<Move> {
   position.skip_checkmate = true;
+  if !state.warnings_reported {
+    print_warnings(&mut out, &state.warnings);
+    state.warnings_reported = true;
+  }
+  // weaker moves are picked from the extra lines, which aren't reported
+  let shown_lines = multipv;
+  let multipv = match state.strength {
+    Some(strength) => {
+      settings.max_nodes = settings.max_nodes.min(strength.node_limit());
+      multipv.max(STRENGTH_LINES)
+    }
+    None => multipv,
+  };
+  // the lines found by the last completed iteration
+  let mut lines = Vec::new();
   let mut current_score = Score::Centipawn(evaluate(state, position));
   let mut depth = 0;
   let mut display_depth = 0;
-  let (captures, mut quiets) = get_move_order(&state.parameters, position, searchmoves);
-  let mut best_moves = Vec::new();
-  if let (_, Some(ttmove)) = state.table.get(
+  let (tt_score, ttmove) = state.table.get(
     position.hash(),
     position.moves(),
     settings.initial_alpha,
     Score::Win(0),
     depth,
-  ) {
-    if searchmoves.is_empty() || searchmoves.contains(&ttmove) {
-      best_moves.push(ttmove);
+  );
+  let mut root_moves = get_root_moves(state, position, searchmoves, &settings.priority, ttmove);
+  let moves = root_moves.len();
+  let mut best_pv: Vec<Move> = root_moves
+    .first()
+    .map(|root_move| root_move.mv)
+    .into_iter()
+    .collect();
+  settings.iterations.clear();
+  if settings.instant_move && !settings.hard_tm {
+    // the TT move is first if it is legal
+    let tt_mate = matches!(tt_score, Some(Score::Win(_)))
+      && root_moves
+        .first()
+        .is_some_and(|root_move| Some(root_move.mv) == ttmove);
+    if tt_mate || moves == 1 {
+      if *settings.debug {
+        let reason = if tt_mate {
+          "mate found"
+        } else {
+          "only legal move"
+        };
+        print_instant_move(&mut out, reason);
+      }
+      return best_pv;
     }
-  };
-  let moves = captures.len() + quiets.len();
-  let mut best_pv = if let Some(best_move) = best_moves.first() {
-    vec![*best_move]
-  } else if let Some(capture) = captures.first() {
-    vec![*capture]
-  } else if let Some(quiet) = quiets.first() {
-    vec![*quiet]
-  } else {
-    Vec::new()
-  };
+  }
+  // trivially won endings don't need searching
+  if settings.instant_move && !settings.hard_tm && searchmoves.is_empty() && multipv == 1 {
+    if let Some((mv, score)) = trivial_win(&mut state.tablebase, position) {
+      if *settings.debug {
+        print_instant_move(&mut out, "trivial endgame");
+      }
+      let pv = vec![mv];
+      print_info(
+        &mut out, position, score, 1, settings, &pv, 1, false, state, true,
+      );
+      return pv;
+    }
+  }
+  // shallower depths were already searched before the search was saved
+  depth = settings.resume_depth.saturating_sub(1);
   'outer: while depth < settings.max_depth
     && (settings.hard_tm
-      || settings.start.elapsed().as_millis() <= settings.soft_limit(multipv > 1))
+      || settings.start.elapsed().as_millis()
+        <= settings.soft_limit(multipv > 1, &state.search_parameters))
   {
     depth += 1;
+    settings.fail_highs = 0;
+    settings.first_move_fail_highs = 0;
+    settings.fail_low = false;
+    let iteration_nodes = settings.nodes;
     let mut excluded_moves = Vec::new();
+    let mut best_score = current_score;
+    let mut iteration_lines = Vec::new();
     for pv_line in 1..=multipv {
       settings.seldepth = 0;
+      state.table.protect_exact(pv_line > 1);
       let (pv, score) = alpha_beta_root(
         state,
         settings,
         position,
-        &captures,
-        &mut quiets,
+        &mut root_moves,
         searchmoves.is_empty() && pv_line == 1,
-        &best_moves,
         &excluded_moves,
         depth,
         pv_line,
-        multipv > 1,
+        shown_lines > 1,
+        pv_line <= shown_lines,
         &mut out,
       );
       if !pv.is_empty() {
@@ -511,8 +1314,12 @@ pub fn search(
         if let Some(best_move) = pv.first() {
           excluded_moves.push(*best_move);
         }
+        if state.strength.is_some() {
+          iteration_lines.push((pv.clone(), score));
+        }
         if pv_line == 1 {
           best_pv.clone_from(&pv);
+          best_score = score;
         }
         current_score = score;
       } else if !settings.search_is_over() {
@@ -521,27 +1328,51 @@ pub fn search(
           break;
         }
       }
-      print_info(
-        &mut out,
-        position,
-        current_score,
-        display_depth,
-        settings,
-        &pv,
-        pv_line,
-        multipv > 1,
-        state.table.capacity(),
-      );
+      if pv_line <= shown_lines {
+        print_info(
+          &mut out,
+          position,
+          current_score,
+          display_depth,
+          settings,
+          &pv,
+          pv_line,
+          shown_lines > 1,
+          state,
+          true,
+        );
+      }
       if settings.search_is_over() {
         break 'outer;
       }
     }
+    lines = iteration_lines;
+    settings.update_stability(
+      best_pv.first().copied(),
+      best_score,
+      state.search_parameters.tm_stability_margin,
+    );
+    settings.add_iteration(depth, settings.nodes - iteration_nodes);
+    if *settings.debug {
+      print_root_nodes(&mut out, &root_moves);
+    }
     if !settings.hard_tm && moves <= 1 {
       break;
     }
-    best_moves = excluded_moves;
+    next_iteration(&mut root_moves);
+  }
+  state.table.protect_exact(false);
+  settings.wait_for_ponderhit();
+  if *settings.debug {
+    print_search_stats(&mut out, settings);
+  }
+  match state
+    .strength
+    .and_then(|strength| strength.pick_line(&lines))
+  {
+    Some(index) => lines.swap_remove(index).0,
+    None => best_pv,
   }
-  best_pv
 }
 
 /// Search the specified position to a certain depth and return the node count
@@ -0,0 +1,161 @@
+use crate::parameters::DEFAULT_PARAMETERS;
+use crate::search::SEARCH_PARAMETERS;
+use crate::{search, Output, SearchConfig, State};
+use liberty_chess::clock::Millis;
+use liberty_chess::moves::Move;
+use liberty_chess::positions::get_startpos;
+use liberty_chess::Board;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use ulci::client::Message;
+use ulci::server::UlciResult;
+use ulci::{AnalysisResult, Limits, Score, SearchTime};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+/// The hash size of the lite engine in MB, small enough for a browser tab
+pub const LITE_HASH_SIZE: usize = 16;
+/// The default thinking time of the lite engine in milliseconds
+pub const LITE_THINK_TIME: u128 = 1000;
+/// The maximum thinking time of the lite engine in milliseconds
+pub const LITE_MAX_THINK_TIME: u128 = 10000;
+
+// most nodes searched in one slice, so a single depth can't freeze the caller for long
+const SLICE_NODES: usize = 50_000;
+
+struct LiteSearch {
+  board: Board,
+  depth: u8,
+  // whether the last slice finished its depth
+  completed: bool,
+  start: Instant,
+  best: Option<Move>,
+}
+
+/// A reduced version of the engine that searches in short slices on the caller's thread
+///
+/// Made for platforms without threads such as the web, where a full search would block the UI.
+/// Each call to [`LiteEngine::step`] searches a slice of the current position, continuing the
+/// depth being searched if the previous slice ran out of nodes.
+pub struct LiteEngine {
+  state: State,
+  think_time: u128,
+  current: Option<LiteSearch>,
+  analysis: Option<AnalysisResult>,
+  results: (Sender<UlciResult>, Receiver<UlciResult>),
+  // the search stops if the sender is dropped, so keep it alive
+  messages: (Sender<Message>, Receiver<Message>),
+}
+
+impl LiteEngine {
+  /// Initialise the engine with a thinking time in milliseconds and an optional Elo limit
+  #[must_use]
+  pub fn new(think_time: u128, elo: Option<u32>) -> Self {
+    let mut state = State::new(
+      LITE_HASH_SIZE,
+      &get_startpos(),
+      SEARCH_PARAMETERS,
+      DEFAULT_PARAMETERS,
+    );
+    state.set_strength(elo);
+    Self {
+      state,
+      think_time: think_time.min(LITE_MAX_THINK_TIME),
+      current: None,
+      analysis: None,
+      results: channel(),
+      messages: channel(),
+    }
+  }
+
+  /// Start searching a position, replacing any search in progress
+  pub fn start(&mut self, board: &Board) {
+    self.state.new_position(board);
+    self.analysis = None;
+    self.current = Some(LiteSearch {
+      board: board.clone(),
+      depth: 0,
+      completed: true,
+      start: Instant::now(),
+      best: None,
+    });
+  }
+
+  /// Whether a search is in progress
+  #[must_use]
+  pub const fn is_searching(&self) -> bool {
+    self.current.is_some()
+  }
+
+  /// Abandon the search in progress
+  pub fn stop(&mut self) {
+    self.current = None;
+  }
+
+  /// The latest analysis of the position being searched
+  #[must_use]
+  pub const fn analysis(&self) -> Option<&AnalysisResult> {
+    self.analysis.as_ref()
+  }
+
+  /// Search the next slice, returning the best move once the thinking time is used up
+  pub fn step(&mut self) -> Option<Move> {
+    let current = self.current.as_mut()?;
+    let elapsed = current.start.elapsed().as_millis();
+    let remaining = self.think_time.saturating_sub(elapsed);
+    // an unfinished depth is searched again, with the table keeping the work already done
+    if current.completed {
+      current.depth += 1;
+    }
+    let mut debug = false;
+    let limits = Limits {
+      depth: current.depth,
+      nodes: SLICE_NODES,
+      time: Millis::new(remaining.max(1)),
+    };
+    let mut config = SearchConfig::new_time(
+      &current.board,
+      SearchTime::Other(limits),
+      &self.messages.1,
+      &mut debug,
+    );
+    // the table holds the shallower depths, so only the current depth is searched
+    config.resume_from(current.depth);
+    let mut position = current.board.clone();
+    let pv = search(
+      &mut self.state,
+      &mut config,
+      &mut position,
+      &[],
+      1,
+      Output::Channel(&self.results.0),
+    );
+    for result in self.results.1.try_iter() {
+      if let UlciResult::Analysis(result) = result {
+        self.analysis = Some(result);
+      }
+    }
+    current.best = pv.first().copied().or(current.best);
+    current.completed = config
+      .iterations()
+      .last()
+      .is_some_and(|iteration| iteration.depth == current.depth);
+    // no legal moves or a forced mate don't need any more searching
+    let decided = current.completed
+      && (pv.is_empty()
+        || self
+          .analysis
+          .as_ref()
+          .is_some_and(|analysis| !matches!(analysis.score, Score::Centipawn(_))));
+    let finished = decided
+      || (current.completed && current.depth == u8::MAX)
+      || current.start.elapsed().as_millis() >= self.think_time;
+    if finished {
+      self.current.take()?.best
+    } else {
+      None
+    }
+  }
+}
@@ -1,7 +1,7 @@
 use crate::history::History;
 use crate::parameters::Parameters;
 use liberty_chess::moves::Move;
-use liberty_chess::Board;
+use liberty_chess::{Board, KING};
 
 enum Stage {
   TTmove,
@@ -20,6 +20,8 @@ pub struct MovePicker {
   searched_countermove: Option<Move>,
   captures: Vec<(Move, u8, u8)>,
   quiets: Vec<Move>,
+  // in check, so only evasions need to be ordered well
+  evasion: bool,
 }
 
 impl MovePicker {
@@ -31,12 +33,14 @@ impl MovePicker {
       searched_countermove: None,
       captures: Vec::new(),
       quiets: Vec::new(),
+      evasion: false,
     }
   }
 
-  pub fn init(&mut self, ttmove: Option<Move>) {
+  pub fn init(&mut self, ttmove: Option<Move>, in_check: bool) {
     self.stage = Stage::TTmove;
     self.ttmove = ttmove;
+    self.evasion = in_check;
     self.searched_countermove = None;
     self.captures.clear();
     self.quiets.clear();
@@ -46,6 +50,15 @@ impl MovePicker {
     self.killer = Some(killer);
   }
 
+  // The killer if it has been searched already
+  fn searched_killer(&self) -> Option<Move> {
+    if self.evasion {
+      None
+    } else {
+      self.killer
+    }
+  }
+
   // Returns pseudolegal move and whether the move is a capture
   pub fn pick_move(
     &mut self,
@@ -68,9 +81,19 @@ impl MovePicker {
         Stage::PendingGeneration => {
           self.stage = Stage::Captures;
           board.generate_pseudolegal(&mut self.captures, &mut self.quiets);
-          self.captures.sort_by_key(|(_, piece, capture)| {
-            100 * parameters.pieces[usize::from(*capture - 1)].0
-              - parameters.pieces[usize::from(*piece - 1)].0
+          // the piece that just moved is usually the checker, so capture it first
+          let checker = if self.evasion {
+            board.last_move.map(|mv| mv.end())
+          } else {
+            None
+          };
+          // captures that lose material in the exchange are tried after the others
+          self.captures.sort_by_cached_key(|(mv, piece, capture)| {
+            (
+              Some(mv.end()) == checker,
+              !losing_capture(parameters, board, *mv, *piece, *capture),
+              100 * piece_value(parameters, *capture) - piece_value(parameters, *piece),
+            )
           });
         }
         Stage::Captures => {
@@ -78,6 +101,9 @@ impl MovePicker {
             if Some(capture) != self.ttmove {
               return Some((capture, true));
             }
+          } else if self.evasion {
+            // killers from sibling nodes rarely get out of check
+            self.stage = Stage::CounterMove;
           } else {
             self.stage = Stage::Killer;
           }
@@ -105,7 +131,7 @@ impl MovePicker {
               let capture = board.get_piece(countermove.end());
               let is_capture = capture != 0 && ((capture > 0) != board.to_move());
               if Some(countermove) != self.ttmove
-                && Some(countermove) != self.killer
+                && Some(countermove) != self.searched_killer()
                 && !is_capture
                 && board.check_pseudolegal(countermove.start(), countermove.end())
               {
@@ -117,11 +143,13 @@ impl MovePicker {
         }
         Stage::SortQuiets => {
           self.stage = Stage::Quiets;
-          self.quiets.sort_by_key(|mv| {
-            history.get(
-              board.to_move(),
-              board.get_piece(mv.start()).unsigned_abs(),
-              mv.end(),
+          self.quiets.sort_by_cached_key(|mv| {
+            let piece = board.get_piece(mv.start()).unsigned_abs();
+            // blocks come before king moves when evading check
+            (
+              !(self.evasion && piece == KING.unsigned_abs()),
+              i32::from(history.get(board.to_move(), piece, mv.end()))
+                + i32::from(history.get_continuation(board, piece, mv.end())),
             )
           });
         }
@@ -129,7 +157,7 @@ impl MovePicker {
           if let Some(quiet) = self.quiets.pop() {
             let some_quiet = Some(quiet);
             if some_quiet != self.ttmove
-              && some_quiet != self.killer
+              && some_quiet != self.searched_killer()
               && some_quiet != self.searched_countermove
             {
               return Some((quiet, false));
@@ -142,3 +170,29 @@ impl MovePicker {
     }
   }
 }
+
+// The value of a piece in exchanges, 0 for an empty square
+fn piece_value(parameters: &Parameters<i32>, piece: u8) -> i32 {
+  if piece == 0 {
+    0
+  } else {
+    parameters.pieces[usize::from(piece - 1)].0
+  }
+}
+
+// Static exchange evaluation of a move with the search's piece values
+pub fn see(parameters: &Parameters<i32>, board: &Board, mv: Move) -> i32 {
+  board.see(mv, |piece| piece_value(parameters, piece.unsigned_abs()))
+}
+
+// Whether a capture loses material, captures with a less valuable piece never do
+pub fn losing_capture(
+  parameters: &Parameters<i32>,
+  board: &Board,
+  mv: Move,
+  piece: u8,
+  capture: u8,
+) -> bool {
+  piece_value(parameters, piece) > piece_value(parameters, capture)
+    && see(parameters, board, mv) < 0
+}
@@ -1,5 +1,8 @@
 use liberty_chess::parsing::to_name;
-use liberty_chess::{CENTAUR, CHAMPION, ELEPHANT, KING, MANN, OBSTACLE, WALL};
+use liberty_chess::{
+  Board, Piece, BISHOP, CENTAUR, CHAMPION, ELEPHANT, KING, KNIGHT, MANN, OBSTACLE, QUEEN, ROOK,
+  WALL,
+};
 use std::ops::{Add, AddAssign, Div, Mul};
 
 const PIECE_VALUES: [(i32, i32); 18] = [
@@ -595,6 +598,30 @@ impl Parameters<f64> {
     }
   }
 
+  /// Apply a function to every parameter
+  #[must_use]
+  pub fn map(&self, f: impl Fn(f64) -> f64) -> Self {
+    Self {
+      pieces: self.pieces.map(|(x, y)| (f(x), f(y))),
+      mg_edge: self.mg_edge.map(|x| x.map(&f)),
+      eg_edge: self.eg_edge.map(|x| x.map(&f)),
+      mg_friendly_pawn_penalty: self.mg_friendly_pawn_penalty.map(&f),
+      eg_friendly_pawn_penalty: self.eg_friendly_pawn_penalty.map(&f),
+      mg_enemy_pawn_penalty: self.mg_enemy_pawn_penalty.map(&f),
+      eg_enemy_pawn_penalty: self.eg_enemy_pawn_penalty.map(&f),
+      mg_mobility_bonus: self.mg_mobility_bonus.map(&f),
+      eg_mobility_bonus: self.eg_mobility_bonus.map(&f),
+      mg_pawn_attacked_penalty: self.mg_pawn_attacked_penalty.map(&f),
+      eg_pawn_attacked_penalty: self.eg_pawn_attacked_penalty.map(&f),
+      mg_pawn_defended_bonus: self.mg_pawn_defended_bonus.map(&f),
+      eg_pawn_defended_bonus: self.eg_pawn_defended_bonus.map(&f),
+      mg_pawn_scale_factor: f(self.mg_pawn_scale_factor),
+      mg_pawn_scaling_bonus: f(self.mg_pawn_scaling_bonus),
+      eg_pawn_scale_factor: f(self.eg_pawn_scale_factor),
+      eg_pawn_scaling_bonus: f(self.eg_pawn_scaling_bonus),
+    }
+  }
+
   fn remove_nan(x: f64) -> f64 {
     if x.is_finite() {
       x
@@ -796,3 +823,124 @@ impl ToString for Parameters<f64> {
     result + "\n];"
   }
 }
+
+// Leaps and rides of each piece as offsets, which are reflected and rotated
+// Pieces with no entry have abilities mobility can't capture, so keep their tuned values
+type Movement = Option<(&'static [(i32, i32)], &'static [(i32, i32)])>;
+
+const MOVEMENT: [Movement; 18] = [
+  None,                                                   // Pawn
+  Some((&[(1, 2)], &[])),                                 // Knight
+  Some((&[], &[(1, 1)])),                                 // Bishop
+  Some((&[], &[(1, 0)])),                                 // Rook
+  Some((&[], &[(1, 0), (1, 1)])),                         // Queen
+  None,                                                   // King
+  Some((&[(1, 2)], &[(1, 1)])),                           // Archbishop
+  Some((&[(1, 2)], &[(1, 0)])),                           // Chancellor
+  Some((&[(1, 3)], &[])),                                 // Camel
+  Some((&[(2, 3)], &[])),                                 // Zebra
+  Some((&[(1, 0), (1, 1)], &[])),                         // Mann
+  Some((&[], &[(1, 2)])),                                 // Nightrider
+  Some((&[(1, 0), (1, 1), (2, 0), (2, 1), (2, 2)], &[])), // Champion
+  Some((&[(1, 0), (1, 1), (1, 2)], &[])),                 // Centaur
+  Some((&[(1, 2)], &[(1, 0), (1, 1)])),                   // Amazon
+  None,                                                   // Elephant
+  None,                                                   // Obstacle
+  None,                                                   // Wall
+];
+
+// Pieces that are well represented in the tuning data, used to convert mobility to value
+const REFERENCE_PIECES: [Piece; 4] = [KNIGHT, BISHOP, ROOK, QUEEN];
+
+// Tuned values further than this factor from the estimate are treated as unreliable
+const BLEND_THRESHOLD: f64 = 2.5;
+
+fn directions(offsets: &[(i32, i32)]) -> Vec<(i32, i32)> {
+  let mut directions = Vec::new();
+  for &(a, b) in offsets {
+    for (x, y) in [(a, b), (b, a)] {
+      for direction in [(x, y), (-x, y), (x, -y), (-x, -y)] {
+        if !directions.contains(&direction) {
+          directions.push(direction);
+        }
+      }
+    }
+  }
+  directions
+}
+
+// Average number of squares a piece reaches on an empty board
+fn mobility(width: usize, height: usize, leaps: &[(i32, i32)], rides: &[(i32, i32)]) -> f64 {
+  let (width, height) = (width as i32, height as i32);
+  let on_board = |x: i32, y: i32| (0..width).contains(&x) && (0..height).contains(&y);
+  let leaps = directions(leaps);
+  let rides = directions(rides);
+  let mut total = 0;
+  for x in 0..width {
+    for y in 0..height {
+      total += leaps
+        .iter()
+        .filter(|(dx, dy)| on_board(x + dx, y + dy))
+        .count();
+      for (dx, dy) in &rides {
+        let (mut i, mut j) = (x + dx, y + dy);
+        while on_board(i, j) {
+          total += 1;
+          i += dx;
+          j += dy;
+        }
+      }
+    }
+  }
+  total as f64 / f64::from(width * height)
+}
+
+/// Blend the tuned piece values with estimates from mobility on the board
+///
+/// Only affects pieces in the position whose tuned values are far from the estimate
+#[must_use]
+pub fn blend_piece_values(mut parameters: Parameters<i32>, position: &Board) -> Parameters<i32> {
+  let (width, height) = (position.width(), position.height());
+  // value per square of mobility, calibrated on a standard board
+  let (mut mg_total, mut eg_total, mut reference_mobility) = (0.0, 0.0, 0.0);
+  for piece in REFERENCE_PIECES {
+    let index = usize::from(piece.unsigned_abs()) - 1;
+    if let Some((leaps, rides)) = MOVEMENT[index] {
+      let (mg, eg) = parameters.pieces[index];
+      mg_total += f64::from(mg);
+      eg_total += f64::from(eg);
+      reference_mobility += mobility(8, 8, leaps, rides);
+    }
+  }
+  let (mg_scale, eg_scale) = (mg_total / reference_mobility, eg_total / reference_mobility);
+  let mut present = [false; 18];
+  for (i, (white, black)) in position
+    .material(true)
+    .iter()
+    .zip(position.material(false))
+    .enumerate()
+  {
+    present[i] = white + black > 0;
+  }
+  for piece in position.promotion_options() {
+    if *piece != 0 {
+      present[usize::from(piece.unsigned_abs()) - 1] = true;
+    }
+  }
+  let blend = |tuned: i32, estimate: f64| {
+    let value = f64::from(tuned);
+    if value * BLEND_THRESHOLD < estimate || value > estimate * BLEND_THRESHOLD {
+      ((value + estimate) / 2.0) as i32
+    } else {
+      tuned
+    }
+  };
+  for (index, movement) in MOVEMENT.iter().enumerate() {
+    if let (true, Some((leaps, rides))) = (present[index], movement) {
+      let squares = mobility(width, height, leaps, rides);
+      let (mg, eg) = parameters.pieces[index];
+      parameters.pieces[index] = (blend(mg, squares * mg_scale), blend(eg, squares * eg_scale));
+    }
+  }
+  parameters
+}
@@ -1,4 +1,3 @@
-use liberty_chess::moves::Move;
 use liberty_chess::parsing::from_chars;
 use liberty_chess::positions::get_startpos;
 use liberty_chess::ALL_PIECES;
@@ -17,6 +16,9 @@ fn main() {
   let info = ClientInfo {
     features: SupportedFeatures {
       v1: V1Features::all(),
+      priority_moves: false,
+      compression: true,
+      games: false,
     },
     name: "Random mover".to_owned(),
     username: None,
@@ -24,28 +26,37 @@ fn main() {
     options: HashMap::new(),
     pieces: from_chars(ALL_PIECES),
     depth: 0,
+    build: None,
   };
   let input = BufReader::new(stdin());
   let output = stdout();
   let mut position = get_startpos();
   let mut selected_move = None;
+  // the time control to use once the ponder move is played
+  let mut ponder_time = None;
   spawn(move || startup(&tx, &info, input, output, true));
   while let Ok(message) = rx.recv() {
     match message {
       Message::UpdatePosition(board) => position = board.load_from_thread(),
       Message::Go(settings) => {
-        let moves = position.generate_legal();
-        let moves = moves.iter().filter_map(|board| board.last_move);
-        let moves: Vec<Move> = if settings.moves.is_empty() {
-          moves.collect()
-        } else {
-          moves.filter(|m| settings.moves.contains(m)).collect()
-        };
+        let mut moves = position.generate_legal_moves();
+        if !settings.moves.is_empty() {
+          moves.retain(|m| settings.moves.contains(m));
+        }
         selected_move = moves.choose(&mut thread_rng()).copied();
-        if let Some(chosen_move) = selected_move {
+        if let (Some(chosen_move), true) = (selected_move, settings.ponder) {
+          println!(
+            "info depth 1 score cp 0 time 0 nodes 1 nps 1 pv {}",
+            chosen_move.to_string()
+          );
+          ponder_time = Some(settings.time);
+        } else if let Some(chosen_move) = selected_move {
           match settings.time {
             SearchTime::Increment(..)
             | SearchTime::Asymmetric(..)
+            | SearchTime::MovesToGo(..)
+            | SearchTime::Delay(..)
+            | SearchTime::Hourglass(..)
             | SearchTime::Other(_)
             | SearchTime::Mate(_) => {
               println!(
@@ -76,6 +87,7 @@ fn main() {
         }
       }
       Message::Stop => {
+        ponder_time = None;
         if let Some(chosen_move) = selected_move {
           println!("bestmove {}", chosen_move.to_string());
           selected_move = None;
@@ -83,14 +95,24 @@ fn main() {
           println!("info error not currently searching");
         }
       }
+      Message::PonderHit => {
+        if let (Some(time), Some(chosen_move)) = (ponder_time.take(), selected_move) {
+          if time != SearchTime::Infinite {
+            println!("bestmove {}", chosen_move.to_string());
+            selected_move = None;
+          }
+        }
+      }
       Message::Perft(depth) => divide(&position, depth),
       Message::SetDebug(_)
       | Message::UpdateOption(..)
       | Message::Eval
-      | Message::Bench(_)
+      | Message::Bench(..)
       | Message::NewGame
       | Message::Clock(_)
       | Message::Info(_)
+      | Message::Kibbutz(_)
+      | Message::Notice(_)
       | Message::IsReady => (),
     }
   }
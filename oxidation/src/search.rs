@@ -1,8 +1,9 @@
 use crate::evaluate::{evaluate, evaluate_terminal};
+use crate::movepicker::{losing_capture, see};
 use crate::tt::{Entry, ScoreType};
-use crate::{print_info, Output, SearchConfig, StackEntry, State, DRAW_SCORE};
+use crate::{get_move_order, print_info, Output, SearchConfig, StackEntry, State, DRAW_SCORE};
 use liberty_chess::moves::Move;
-use liberty_chess::{Board, Gamestate};
+use liberty_chess::{Board, Gamestate, Hash};
 use std::cmp::max;
 use std::ops::{Add, Mul, Sub};
 use ulci::Score;
@@ -13,6 +14,18 @@ pub const SEARCH_PARAMETERS: SearchParameters = SearchParameters {
   lmr_factor: 0.36211678,
   lmr_pv_reduction: 0.6459082,
   lmr_improving_reduction: 0.5,
+  history_pruning_depth: 3.0,
+  history_pruning_margin: 1024.0,
+  see_pruning_depth: 4.0,
+  root_widening_base: 32.0,
+  root_widening_factor: 8.0,
+  tm_fail_low_margin: 30.0,
+  tm_fail_low_extension: 1.5,
+  tm_panic_margin: 100.0,
+  tm_panic_extension: 2.5,
+  tm_stability_margin: 10.0,
+  tm_stability_iterations: 4.0,
+  tm_stability_scale: 0.6,
 };
 
 /// Parameters affecting the behaviour of the search
@@ -28,6 +41,46 @@ pub struct SearchParameters {
   pub lmr_pv_reduction: f32,
   /// How much to increase LMR by when not improving
   pub lmr_improving_reduction: f32,
+  /// Maximum depth to prune quiets with bad history
+  pub history_pruning_depth: f32,
+  /// History threshold per depth below which quiets are pruned
+  pub history_pruning_margin: f32,
+  /// Maximum depth to prune quiets that can be captured by a cheaper piece
+  pub see_pruning_depth: f32,
+  /// Number of root quiets searched at depth 0
+  pub root_widening_base: f32,
+  /// How fast the number of root quiets searched grows with depth squared
+  pub root_widening_factor: f32,
+  /// How far in centipawns the previous best move has to drop to count as failing low
+  pub tm_fail_low_margin: f32,
+  /// How much to scale the soft time limit by when the best move fails low
+  pub tm_fail_low_extension: f32,
+  /// How far in centipawns the previous best move has to drop to panic and use more of the clock
+  pub tm_panic_margin: f32,
+  /// How much to scale the soft time limit by when panicking
+  pub tm_panic_extension: f32,
+  /// How far in centipawns the score can move while still counting as stable
+  pub tm_stability_margin: f32,
+  /// Iterations in a row with the same best move and a stable score to stop early
+  pub tm_stability_iterations: f32,
+  /// How much to scale the soft time limit by when the search is stable
+  pub tm_stability_scale: f32,
+}
+
+impl SearchParameters {
+  /// A configuration that prunes and reduces less
+  ///
+  /// For positions where the evaluation is unreliable, so pruning decisions based on it are too
+  #[must_use]
+  pub fn conservative(self) -> Self {
+    Self {
+      lmr_factor: self.lmr_factor * 0.75,
+      history_pruning_depth: 0.0,
+      see_pruning_depth: self.see_pruning_depth / 2.0,
+      root_widening_base: self.root_widening_base * 2.0,
+      ..self
+    }
+  }
 }
 
 impl Add for SearchParameters {
@@ -39,6 +92,18 @@ impl Add for SearchParameters {
       lmr_factor: self.lmr_factor + rhs.lmr_factor,
       lmr_pv_reduction: self.lmr_pv_reduction + rhs.lmr_pv_reduction,
       lmr_improving_reduction: self.lmr_improving_reduction + rhs.lmr_improving_reduction,
+      history_pruning_depth: self.history_pruning_depth + rhs.history_pruning_depth,
+      history_pruning_margin: self.history_pruning_margin + rhs.history_pruning_margin,
+      see_pruning_depth: self.see_pruning_depth + rhs.see_pruning_depth,
+      root_widening_base: self.root_widening_base + rhs.root_widening_base,
+      root_widening_factor: self.root_widening_factor + rhs.root_widening_factor,
+      tm_fail_low_margin: self.tm_fail_low_margin + rhs.tm_fail_low_margin,
+      tm_fail_low_extension: self.tm_fail_low_extension + rhs.tm_fail_low_extension,
+      tm_panic_margin: self.tm_panic_margin + rhs.tm_panic_margin,
+      tm_panic_extension: self.tm_panic_extension + rhs.tm_panic_extension,
+      tm_stability_margin: self.tm_stability_margin + rhs.tm_stability_margin,
+      tm_stability_iterations: self.tm_stability_iterations + rhs.tm_stability_iterations,
+      tm_stability_scale: self.tm_stability_scale + rhs.tm_stability_scale,
     }
   }
 }
@@ -52,6 +117,18 @@ impl Sub for SearchParameters {
       lmr_factor: self.lmr_factor - rhs.lmr_factor,
       lmr_pv_reduction: self.lmr_pv_reduction - rhs.lmr_pv_reduction,
       lmr_improving_reduction: self.lmr_improving_reduction - rhs.lmr_improving_reduction,
+      history_pruning_depth: self.history_pruning_depth - rhs.history_pruning_depth,
+      history_pruning_margin: self.history_pruning_margin - rhs.history_pruning_margin,
+      see_pruning_depth: self.see_pruning_depth - rhs.see_pruning_depth,
+      root_widening_base: self.root_widening_base - rhs.root_widening_base,
+      root_widening_factor: self.root_widening_factor - rhs.root_widening_factor,
+      tm_fail_low_margin: self.tm_fail_low_margin - rhs.tm_fail_low_margin,
+      tm_fail_low_extension: self.tm_fail_low_extension - rhs.tm_fail_low_extension,
+      tm_panic_margin: self.tm_panic_margin - rhs.tm_panic_margin,
+      tm_panic_extension: self.tm_panic_extension - rhs.tm_panic_extension,
+      tm_stability_margin: self.tm_stability_margin - rhs.tm_stability_margin,
+      tm_stability_iterations: self.tm_stability_iterations - rhs.tm_stability_iterations,
+      tm_stability_scale: self.tm_stability_scale - rhs.tm_stability_scale,
     }
   }
 }
@@ -65,6 +142,18 @@ impl Mul<f32> for SearchParameters {
       lmr_factor: self.lmr_factor * rhs,
       lmr_pv_reduction: self.lmr_pv_reduction * rhs,
       lmr_improving_reduction: self.lmr_improving_reduction * rhs,
+      history_pruning_depth: self.history_pruning_depth * rhs,
+      history_pruning_margin: self.history_pruning_margin * rhs,
+      see_pruning_depth: self.see_pruning_depth * rhs,
+      root_widening_base: self.root_widening_base * rhs,
+      root_widening_factor: self.root_widening_factor * rhs,
+      tm_fail_low_margin: self.tm_fail_low_margin * rhs,
+      tm_fail_low_extension: self.tm_fail_low_extension * rhs,
+      tm_panic_margin: self.tm_panic_margin * rhs,
+      tm_panic_extension: self.tm_panic_extension * rhs,
+      tm_stability_margin: self.tm_stability_margin * rhs,
+      tm_stability_iterations: self.tm_stability_iterations * rhs,
+      tm_stability_scale: self.tm_stability_scale * rhs,
     }
   }
 }
@@ -89,14 +178,22 @@ fn recaptures(
       alpha = best_score;
     }
     let mut best_pv = Vec::new();
-    let mut moves = board.generate_recaptures(target);
-    moves.sort_by_key(|(_, piece)| state.parameters.pieces[usize::from(*piece - 1)].0);
+    let entry = &mut state.stack[ply];
+    entry.recaptures.clear();
+    entry
+      .board
+      .generate_recaptures_into(&mut entry.recaptures, target);
+    let pieces = &state.parameters.pieces;
+    entry
+      .recaptures
+      .sort_by_key(|(_, piece)| pieces[usize::from(*piece - 1)].0);
     while state.stack.len() <= ply + 1 {
       state
         .stack
         .push(StackEntry::new(state.stack[ply].board.clone()));
     }
-    for (mv, _) in moves {
+    for i in 0..state.stack[ply].recaptures.len() {
+      let (mv, _) = state.stack[ply].recaptures[i];
       // Safety - the indices are different therefore the references don't alias
       let position = unsafe {
         let board = &*(&state.stack[ply].board as *const Board);
@@ -165,17 +262,30 @@ pub fn quiescence(
     if settings.search_is_over() {
       return None;
     }
-    let mut moves = board.generate_qsearch();
-    moves.sort_by_key(|(_, piece, capture)| {
-      state.parameters.pieces[usize::from(*piece - 1)].0
-        - 100 * state.parameters.pieces[usize::from(*capture - 1)].0
+    let entry = &mut state.stack[ply];
+    entry.captures.clear();
+    entry.board.generate_qsearch_into(&mut entry.captures);
+    let pieces = &state.parameters.pieces;
+    entry.captures.sort_by_key(|(_, piece, capture)| {
+      pieces[usize::from(*piece - 1)].0 - 100 * pieces[usize::from(*capture - 1)].0
     });
     while state.stack.len() <= ply + 1 {
       state
         .stack
         .push(StackEntry::new(state.stack[ply].board.clone()));
     }
-    for (mv, _, _) in moves {
+    for i in 0..state.stack[ply].captures.len() {
+      let (mv, piece, capture) = state.stack[ply].captures[i];
+      // captures that lose material in the exchange are unlikely to raise alpha
+      if losing_capture(
+        &state.parameters,
+        &state.stack[ply].board,
+        mv,
+        piece,
+        capture,
+      ) {
+        continue;
+      }
       // Safety - the indices are different therefore the references don't alias
       let position = unsafe {
         let board = &*(&state.stack[ply].board as *const Board);
@@ -265,7 +375,13 @@ fn alpha_beta(
     let mut futility_score = None;
     let movecount = board.moves();
 
-    let eval = evaluate(state, board);
+    // the evaluation is adjusted by how far off it has been for the pawn structure
+    let correction_key = board.pawn_hash();
+    let raw_eval = evaluate(state, board);
+    let eval = raw_eval
+      + state
+        .history
+        .get_correction(board.to_move(), correction_key);
 
     while state.stack.len() <= ply + 1 {
       state
@@ -346,11 +462,13 @@ fn alpha_beta(
     let mut best_score = Score::Loss(0);
     let mut move_count = 0;
     let mut fail_lows: Vec<Move> = Vec::new();
-    state.stack[ply].movepicker.init(ttmove);
+    state.stack[ply].movepicker.init(ttmove, in_check);
     while let Some((mv, is_capture)) = state.stack[ply].pick_move(&state.history, &state.parameters)
     {
       // Move loop pruning for quiets - we need to avoid mate first
-      if !is_capture && !matches!(best_score, Score::Loss(_)) {
+      // Evasions are never pruned, there are few of them and they are often forced
+      let prune_quiet = !is_capture && !in_check && !matches!(best_score, Score::Loss(_));
+      if prune_quiet {
         if let Some(futility_score) = futility_score {
           best_score = max(best_score, futility_score);
           break;
@@ -360,7 +478,23 @@ fn alpha_beta(
         if depth <= 2 && move_count >= (5 << depth) {
           break;
         }
+
+        // History pruning, the PV is searched fully
+        let board = &state.stack[ply].board;
+        let depth = f32::from(depth);
+        if !pv_node && depth <= state.search_parameters.history_pruning_depth {
+          let piece = board.get_piece(mv.start()).unsigned_abs();
+          let history = f32::from(state.history.get(board.to_move(), piece, mv.end()));
+          if history < -state.search_parameters.history_pruning_margin * depth {
+            continue;
+          }
+        }
       }
+      // Quiets that lose the moved piece in the exchange on its square
+      let loses_material = prune_quiet
+        && !pv_node
+        && f32::from(depth) <= state.search_parameters.see_pruning_depth
+        && see(&state.parameters, &state.stack[ply].board, mv) < 0;
       // Safety - the indices are different therefore the references don't alias
       let position = unsafe {
         let board = &*(&state.stack[ply].board as *const Board);
@@ -369,23 +503,30 @@ fn alpha_beta(
         position
       };
       if position.make_pseudolegal_move(mv) {
+        // SEE pruning, checks are kept as they can win the material back
+        if loses_material && !position.in_check() {
+          continue;
+        }
         settings.nodes += 1;
         move_count += 1;
         // Late move reductions
-        let reduction = if !is_capture && depth >= 3 && move_count > 5 && !position.in_check() {
-          let mut reduction = state.search_parameters.lmr_base
-            + f32::from(depth).ln() * (move_count as f32).ln() * state.search_parameters.lmr_factor;
-          if pv_node {
-            reduction -= state.search_parameters.lmr_pv_reduction;
-          }
-          if !improving {
-            reduction += state.search_parameters.lmr_improving_reduction;
-          }
-          // avoid dropping into qsearch
-          (reduction as i8).clamp(0, (depth / 2) as i8) as u8
-        } else {
-          0
-        };
+        let reduction =
+          if !is_capture && !in_check && depth >= 3 && move_count > 5 && !position.in_check() {
+            let mut reduction = state.search_parameters.lmr_base
+              + f32::from(depth).ln()
+                * (move_count as f32).ln()
+                * state.search_parameters.lmr_factor;
+            if pv_node {
+              reduction -= state.search_parameters.lmr_pv_reduction;
+            }
+            if !improving {
+              reduction += state.search_parameters.lmr_improving_reduction;
+            }
+            // avoid dropping into qsearch
+            (reduction as i8).clamp(0, (depth / 2) as i8) as u8
+          } else {
+            0
+          };
         let (mut pv, score) = if (pv_node && move_count > 1) || reduction > 0 {
           // Zero window search to see if raises alpha
           let score = -zero_window_search(
@@ -425,29 +566,34 @@ fn alpha_beta(
           (pv, -score)
         };
         if score >= beta {
+          settings.add_fail_high(move_count == 1);
           if !is_capture {
             state.stack[ply].movepicker.store_killer(mv);
             let board = &state.stack[ply].board;
             for fail_low in fail_lows {
-              state.history.malus(
-                board.to_move(),
-                board.get_piece(fail_low.start()).unsigned_abs(),
-                fail_low.end(),
-                depth,
-              );
+              let piece = board.get_piece(fail_low.start()).unsigned_abs();
+              state
+                .history
+                .malus(board.to_move(), piece, fail_low.end(), depth);
+              state
+                .history
+                .continuation_malus(board, piece, fail_low.end(), depth);
             }
-            state.history.bonus(
-              board.to_move(),
-              board.get_piece(mv.start()).unsigned_abs(),
-              mv.end(),
-              depth,
-            );
+            let piece = board.get_piece(mv.start()).unsigned_abs();
+            state.history.bonus(board.to_move(), piece, mv.end(), depth);
+            state
+              .history
+              .continuation_bonus(board, piece, mv.end(), depth);
             if let Some(last_move) = board.last_move {
               let piece = board.get_piece(last_move.end()).unsigned_abs();
               state
                 .history
                 .store_countermove(board.to_move(), piece, last_move.end(), mv);
             }
+            // lower bounds only show the evaluation was too low
+            if !in_check && score > Score::Centipawn(eval) {
+              update_correction(state, ply, correction_key, depth, raw_eval, score);
+            }
           }
           state.table.store(Entry {
             hash,
@@ -489,6 +635,15 @@ fn alpha_beta(
       } else {
         (ScoreType::Exact, best_pv.first().copied())
       };
+      let board = &state.stack[ply].board;
+      let quiet_best = !best_pv
+        .first()
+        .is_some_and(|mv| board.get_piece(mv.end()) != 0);
+      // upper bounds only show the evaluation was too high
+      let useful = scoretype == ScoreType::Exact || best_score < Score::Centipawn(eval);
+      if !in_check && quiet_best && useful {
+        update_correction(state, ply, correction_key, depth, raw_eval, best_score);
+      }
       state.table.store(Entry {
         hash,
         depth,
@@ -502,6 +657,23 @@ fn alpha_beta(
   }
 }
 
+// Move the evaluation correction for the pawn structure towards a search result
+fn update_correction(
+  state: &mut State,
+  ply: usize,
+  key: Hash,
+  depth: u8,
+  raw_eval: i32,
+  score: Score,
+) {
+  if let Score::Centipawn(score) = score {
+    let side = state.stack[ply].board.to_move();
+    state
+      .history
+      .update_correction(side, key, depth, score - raw_eval);
+  }
+}
+
 fn null_move_search(
   state: &mut State,
   settings: &mut SearchConfig,
@@ -535,24 +707,136 @@ fn zero_window_search(
   Some(score)
 }
 
+/// A legal move at the root, with statistics used to order the root moves between iterations
+pub(crate) struct RootMove {
+  pub(crate) mv: Move,
+  capture: bool,
+  // exact score from the latest search of the move, None if it hasn't raised alpha
+  score: Option<Score>,
+  // raised alpha in the previous iteration or is the TT move, so is never reduced or pruned
+  was_best: bool,
+  // marked by the user as a candidate, so is searched early and never reduced or pruned
+  priority: bool,
+  // nodes spent on the move this iteration
+  pub(crate) nodes: usize,
+  // principal variation from the latest exact score, starting with the move
+  pv: Vec<Move>,
+}
+
+impl RootMove {
+  fn new(mv: Move, capture: bool) -> Self {
+    Self {
+      mv,
+      capture,
+      score: None,
+      was_best: false,
+      priority: false,
+      nodes: 0,
+      pv: Vec::new(),
+    }
+  }
+
+  // quiets can be reduced or dropped by progressive widening
+  const fn is_quiet(&self) -> bool {
+    !self.capture && !self.was_best && !self.priority
+  }
+}
+
+// Swap the remaining quiet with the best history to the front
+// Picked when the search reaches it so the history from searching the earlier moves is used
+fn pick_quiet(state: &State, board: &Board, excluded_moves: &[Move], root_moves: &mut [RootMove]) {
+  if let Some((index, _)) = root_moves
+    .iter()
+    .enumerate()
+    .filter(|(_, root_move)| root_move.is_quiet() && !excluded_moves.contains(&root_move.mv))
+    .min_by_key(|(_, root_move)| {
+      -state.history.get(
+        board.to_move(),
+        board.get_piece(root_move.mv.start()).unsigned_abs(),
+        root_move.mv.end(),
+      )
+    })
+  {
+    root_moves.swap(0, index);
+  }
+}
+
+/// Get the moves to search at the root, ordered for the first iteration
+///
+/// The TT move is searched first, followed by captures by MVV-LVA and then quiets
+pub(crate) fn get_root_moves(
+  state: &State,
+  position: &Board,
+  searchmoves: &[Move],
+  priority: &[Move],
+  ttmove: Option<Move>,
+) -> Vec<RootMove> {
+  let (captures, quiets) = get_move_order(&state.parameters, position, searchmoves);
+  let mut root_moves: Vec<RootMove> = captures
+    .into_iter()
+    .map(|mv| RootMove::new(mv, true))
+    .chain(quiets.into_iter().map(|mv| RootMove::new(mv, false)))
+    .collect();
+  // the TT move may not be legal, so only use it if it is one of the root moves
+  if let Some(index) = ttmove.and_then(|ttmove| {
+    root_moves
+      .iter()
+      .position(|root_move| root_move.mv == ttmove)
+  }) {
+    let mut root_move = root_moves.remove(index);
+    root_move.was_best = true;
+    root_moves.insert(0, root_move);
+  }
+  // candidates from the user go ahead of the TT move, in the order given
+  for mv in priority.iter().rev() {
+    if let Some(index) = root_moves.iter().position(|root_move| root_move.mv == *mv) {
+      let mut root_move = root_moves.remove(index);
+      root_move.priority = true;
+      root_moves.insert(0, root_move);
+    }
+  }
+  root_moves
+}
+
+/// Sort the root moves for the next iteration and reset the statistics for this iteration
+///
+/// Moves that raised alpha come first by score, then candidates from the user, then captures by the nodes spent on them.
+/// Quiets are picked by history during the search.
+pub(crate) fn next_iteration(root_moves: &mut [RootMove]) {
+  root_moves.sort_by(|a, b| {
+    b.score
+      .cmp(&a.score)
+      .then(b.priority.cmp(&a.priority))
+      .then(b.capture.cmp(&a.capture))
+      .then(b.nodes.cmp(&a.nodes))
+  });
+  for root_move in root_moves {
+    root_move.was_best = root_move.score.take().is_some();
+    root_move.nodes = 0;
+  }
+}
+
+fn best_pv(root_moves: &[RootMove], best: Option<usize>) -> Vec<Move> {
+  best.map_or_else(Vec::new, |index| root_moves[index].pv.clone())
+}
+
 pub(crate) fn alpha_beta_root(
   state: &mut State,
   settings: &mut SearchConfig,
   board: &Board,
-  captures: &[Move],
-  quiets: &mut Vec<Move>,
+  root_moves: &mut [RootMove],
   ttstore: bool,
-  best_moves: &[Move],
   excluded_moves: &[Move],
   depth: u8,
   pv_line: u16,
   show_pv_line: bool,
+  report: bool,
   out: &mut Output,
 ) -> (Vec<Move>, Score) {
   let mut alpha = settings.initial_alpha;
   let beta = Score::Win(0);
-  let mut best_pv = Vec::new();
-  let mut backup_pv = Vec::new();
+  // index of the move that last raised alpha
+  let mut best = None;
   let mut move_count = 0;
   let mut show_output = false;
   while state.stack.len() <= 1 {
@@ -563,66 +847,57 @@ pub(crate) fn alpha_beta_root(
   } else {
     Some(evaluate(state, board))
   };
-  for best_move in best_moves {
-    if !excluded_moves.contains(best_move) {
-      if let Some(position) = board.move_if_legal(*best_move) {
-        let node_count = settings.nodes;
-        settings.nodes += 1;
-        move_count += 1;
-        state.stack[1].board = position;
-        let mut failed_high = false;
-        let (mut pv, score) = if move_count > 1 {
-          // Zero window search to see if raises alpha
-          let score = zero_window_search(state, settings, 1, depth - 1, -alpha, false);
-          if let Some(mut score) = score {
-            score = -score;
-            if score > alpha {
-              failed_high = true;
-              backup_pv = best_pv;
-              best_pv = vec![*best_move];
-              if show_output {
-                print_info(
-                  out,
-                  board,
-                  alpha,
-                  depth,
-                  settings,
-                  &best_pv,
-                  pv_line,
-                  show_pv_line,
-                  state.table.capacity(),
-                );
-              }
-              if let Some((pv, score)) =
-                alpha_beta(state, settings, 1, depth - 1, -beta, -alpha, true, false)
-              {
-                (pv, -score)
-              } else {
-                return (best_pv, alpha);
-              }
-            } else {
-              (Vec::new(), score)
-            }
-          } else {
-            return (best_pv, alpha);
-          }
-        } else if let Some((pv, score)) =
-          alpha_beta(state, settings, 1, depth - 1, -beta, -alpha, true, false)
-        {
-          if settings.millis >= 100 {
-            show_output = true;
-          }
-          (pv, -score)
-        } else {
-          return (best_pv, alpha);
-        };
+  // Progressive widening: only search the quiets with the best history at low depths
+  // The previous best moves and captures are always searched first so are never dropped
+  let quiet_limit = if board.in_check() {
+    usize::MAX
+  } else {
+    let depth = f32::from(depth);
+    (state.search_parameters.root_widening_base
+      + state.search_parameters.root_widening_factor * depth * depth) as usize
+  };
+  let mut quiets_searched = 0;
+  for index in 0..root_moves.len() {
+    let quiet = root_moves[index].is_quiet();
+    if quiet {
+      if quiets_searched >= quiet_limit && move_count > 0 {
+        continue;
+      }
+      pick_quiet(state, board, excluded_moves, &mut root_moves[index..]);
+    }
+    let mv = root_moves[index].mv;
+    if excluded_moves.contains(&mv) {
+      continue;
+    }
+    if quiet {
+      quiets_searched += 1;
+    }
+    let position = &mut state.stack[1].board;
+    position.clone_from(board);
+    if !position.make_pseudolegal_move(mv) {
+      continue;
+    }
+    let node_count = settings.nodes;
+    settings.nodes += 1;
+    move_count += 1;
+    // Late move reductions
+    let reduction = if quiet && depth >= 3 && move_count > 5 && !position.in_check() {
+      let reduction = state.search_parameters.lmr_base
+        + f32::from(depth).ln() * (move_count as f32).ln() * state.search_parameters.lmr_factor
+        - state.search_parameters.lmr_pv_reduction;
+      // avoid dropping into qsearch
+      (reduction as i8).clamp(0, (depth / 2) as i8) as u8
+    } else {
+      0
+    };
+    let mut failed_high = false;
+    let (mut pv, score) = if move_count > 1 {
+      // Zero window search to see if raises alpha
+      let score = zero_window_search(state, settings, 1, depth - 1 - reduction, -alpha, false);
+      if let Some(mut score) = score {
+        score = -score;
         if score > alpha {
-          settings.best_move_nodes += settings.nodes - node_count;
-          alpha = score;
-          let mut new_pv = vec![*best_move];
-          new_pv.append(&mut pv);
-          best_pv = new_pv;
-          backup_pv.clone_from(&best_pv);
+          failed_high = true;
           if show_output {
             print_info(
               out,
@@ -630,243 +905,81 @@ pub(crate) fn alpha_beta_root(
               alpha,
               depth,
               settings,
-              &best_pv,
+              &[mv],
               pv_line,
               show_pv_line,
-              state.table.capacity(),
-            );
-          }
-        } else if failed_high {
-          // In case of PVS research fail-low, revert best pv
-          best_pv.clone_from(&backup_pv);
-          if show_output {
-            print_info(
-              out,
-              board,
-              alpha,
-              depth,
-              settings,
-              &best_pv,
-              pv_line,
-              show_pv_line,
-              state.table.capacity(),
+              state,
+              false,
             );
           }
-        }
-      }
-    }
-  }
-  for capture in captures {
-    if !best_moves.contains(capture) && !excluded_moves.contains(capture) {
-      let mut position = board.clone();
-      position.play_move(*capture);
-      let node_count = settings.nodes;
-      settings.nodes += 1;
-      move_count += 1;
-      state.stack[1].board = position;
-      let mut failed_high = false;
-      let (mut pv, score) = if move_count > 1 {
-        // Zero window search to see if raises alpha
-        let score = zero_window_search(state, settings, 1, depth - 1, -alpha, false);
-        if let Some(mut score) = score {
-          score = -score;
-          if score > alpha {
-            failed_high = true;
-            backup_pv = best_pv;
-            best_pv = vec![*capture];
-            if show_output {
-              print_info(
-                out,
-                board,
-                alpha,
-                depth,
-                settings,
-                &best_pv,
-                pv_line,
-                show_pv_line,
-                state.table.capacity(),
-              );
-            }
-            if let Some((pv, score)) =
-              alpha_beta(state, settings, 1, depth - 1, -beta, -alpha, true, false)
-            {
-              (pv, -score)
-            } else {
-              return (best_pv, alpha);
-            }
+          if let Some((pv, score)) =
+            alpha_beta(state, settings, 1, depth - 1, -beta, -alpha, true, false)
+          {
+            (pv, -score)
           } else {
-            (Vec::new(), score)
+            return (vec![mv], alpha);
           }
         } else {
-          return (best_pv, alpha);
+          (Vec::new(), score)
         }
-      } else if let Some((pv, score)) =
-        alpha_beta(state, settings, 1, depth - 1, -beta, -alpha, true, false)
-      {
-        if settings.millis >= 100 {
-          show_output = true;
-        }
-        (pv, -score)
       } else {
-        return (best_pv, alpha);
-      };
-      if score > alpha {
-        let nodes_taken = settings.nodes - node_count;
-        if move_count == 1 {
-          settings.best_move_nodes += nodes_taken;
-        } else {
-          settings.best_move_nodes = nodes_taken;
-        }
-        alpha = score;
-        let mut new_pv = vec![*capture];
-        new_pv.append(&mut pv);
-        best_pv = new_pv;
-        backup_pv.clone_from(&best_pv);
-        if show_output {
-          print_info(
-            out,
-            board,
-            alpha,
-            depth,
-            settings,
-            &best_pv,
-            pv_line,
-            show_pv_line,
-            state.table.capacity(),
-          );
-        }
-      } else if failed_high {
-        // In case of PVS research fail-low, revert best pv
-        best_pv.clone_from(&backup_pv);
-        if show_output {
-          print_info(
-            out,
-            board,
-            alpha,
-            depth,
-            settings,
-            &best_pv,
-            pv_line,
-            show_pv_line,
-            state.table.capacity(),
-          );
-        }
+        return (best_pv(root_moves, best), alpha);
+      }
+    } else if let Some((pv, score)) =
+      alpha_beta(state, settings, 1, depth - 1, -beta, -alpha, true, false)
+    {
+      if report && settings.millis >= 100 {
+        show_output = true;
       }
+      (pv, -score)
+    } else {
+      return (best_pv(root_moves, best), alpha);
+    };
+    let nodes_taken = settings.nodes - node_count;
+    root_moves[index].nodes += nodes_taken;
+    if move_count == 1 && pv_line == 1 {
+      settings.check_fail_low(mv, score, &state.search_parameters);
     }
-  }
-  quiets.sort_by_key(|mv| {
-    -state.history.get(
-      board.to_move(),
-      board.get_piece(mv.start()).unsigned_abs(),
-      mv.end(),
-    )
-  });
-  for quiet in quiets {
-    if !best_moves.contains(quiet) && !excluded_moves.contains(quiet) {
-      let mut position = board.clone();
-      position.play_move(*quiet);
-      let node_count = settings.nodes;
-      settings.nodes += 1;
-      move_count += 1;
-      // Late move reductions
-      let reduction = if depth >= 3 && move_count > 5 && !position.in_check() {
-        let reduction = state.search_parameters.lmr_base
-          + f32::from(depth).ln() * (move_count as f32).ln() * state.search_parameters.lmr_factor
-          - state.search_parameters.lmr_pv_reduction;
-        // avoid dropping into qsearch
-        (reduction as i8).clamp(0, (depth / 2) as i8) as u8
+    if score > alpha {
+      if move_count == 1 {
+        settings.best_move_nodes += nodes_taken;
       } else {
-        0
-      };
-      state.stack[1].board = position;
-      let mut failed_high = false;
-      let (mut pv, score) = if move_count > 1 {
-        // Zero window search to see if raises alpha
-        let score = zero_window_search(state, settings, 1, depth - 1 - reduction, -alpha, false);
-        if let Some(mut score) = score {
-          score = -score;
-          if score > alpha {
-            failed_high = true;
-            backup_pv = best_pv;
-            best_pv = vec![*quiet];
-            if show_output {
-              print_info(
-                out,
-                board,
-                alpha,
-                depth,
-                settings,
-                &best_pv,
-                pv_line,
-                show_pv_line,
-                state.table.capacity(),
-              );
-            }
-            if let Some((pv, score)) =
-              alpha_beta(state, settings, 1, depth - 1, -beta, -alpha, true, false)
-            {
-              (pv, -score)
-            } else {
-              return (best_pv, alpha);
-            }
-          } else {
-            (Vec::new(), score)
-          }
-        } else {
-          return (best_pv, alpha);
-        }
-      } else if let Some((pv, score)) =
-        alpha_beta(state, settings, 1, depth - 1, -beta, -alpha, true, false)
-      {
-        if settings.millis >= 100 {
-          show_output = true;
-        }
-        (pv, -score)
-      } else {
-        return (best_pv, alpha);
-      };
-      if score > alpha {
-        let nodes_taken = settings.nodes - node_count;
-        if move_count == 1 {
-          settings.best_move_nodes += nodes_taken;
-        } else {
-          settings.best_move_nodes = nodes_taken;
-        }
-        alpha = score;
-        let mut new_pv = vec![*quiet];
-        new_pv.append(&mut pv);
-        best_pv = new_pv;
-        backup_pv.clone_from(&best_pv);
-        if show_output {
-          print_info(
-            out,
-            board,
-            alpha,
-            depth,
-            settings,
-            &best_pv,
-            pv_line,
-            show_pv_line,
-            state.table.capacity(),
-          );
-        }
-      } else if failed_high {
-        // In case of PVS research fail-low, revert best pv
-        best_pv.clone_from(&backup_pv);
-        if show_output {
-          print_info(
-            out,
-            board,
-            alpha,
-            depth,
-            settings,
-            &best_pv,
-            pv_line,
-            show_pv_line,
-            state.table.capacity(),
-          );
-        }
+        settings.best_move_nodes = nodes_taken;
+      }
+      alpha = score;
+      let root_move = &mut root_moves[index];
+      root_move.score = Some(score);
+      root_move.pv = vec![mv];
+      root_move.pv.append(&mut pv);
+      best = Some(index);
+      if show_output {
+        print_info(
+          out,
+          board,
+          alpha,
+          depth,
+          settings,
+          &root_moves[index].pv,
+          pv_line,
+          show_pv_line,
+          state,
+          false,
+        );
       }
+    } else if failed_high && show_output {
+      // In case of PVS research fail-low, show the previous best pv again
+      print_info(
+        out,
+        board,
+        alpha,
+        depth,
+        settings,
+        &best_pv(root_moves, best),
+        pv_line,
+        show_pv_line,
+        state,
+        false,
+      );
     }
   }
   if move_count == 0 {
@@ -881,10 +994,12 @@ pub(crate) fn alpha_beta_root(
       },
     )
   } else {
-    let (scoretype, bestmove) = if best_pv.is_empty() {
-      (ScoreType::UpperBound, best_moves.first().copied())
-    } else {
-      (ScoreType::Exact, best_pv.first().copied())
+    let (scoretype, bestmove) = match best {
+      Some(index) => (ScoreType::Exact, Some(root_moves[index].mv)),
+      None => (
+        ScoreType::UpperBound,
+        root_moves.first().map(|root_move| root_move.mv),
+      ),
     };
     if ttstore {
       state.table.store(Entry {
@@ -896,6 +1011,6 @@ pub(crate) fn alpha_beta_root(
         bestmove,
       });
     }
-    (best_pv, alpha)
+    (best_pv(root_moves, best), alpha)
   }
 }
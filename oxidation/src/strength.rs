@@ -0,0 +1,92 @@
+use liberty_chess::moves::Move;
+use liberty_chess::Hash;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+use ulci::Score;
+
+/// The weakest Elo the engine can be limited to
+pub const MIN_ELO: u32 = 500;
+/// The strongest Elo the engine can be limited to
+pub const MAX_ELO: u32 = 2800;
+/// The default Elo when limiting strength
+pub const DEFAULT_ELO: u32 = 1500;
+/// Lines searched with `MultiPV` when limiting strength, so weaker moves can be picked
+pub const STRENGTH_LINES: u16 = 4;
+
+// the value used for mate scores when picking a line
+const MATE_CENTIPAWNS: f64 = 10_000.0;
+
+/// Settings for playing at a limited strength
+#[derive(Clone, Copy, Debug)]
+pub struct Strength {
+  elo: u32,
+  // changes the evaluation noise between games
+  seed: Hash,
+}
+
+impl Strength {
+  /// Play at roughly the specified Elo, clamped to the supported range
+  #[must_use]
+  pub fn new(elo: u32) -> Self {
+    Self {
+      elo: elo.clamp(MIN_ELO, MAX_ELO),
+      seed: thread_rng().gen(),
+    }
+  }
+
+  /// The Elo being played at
+  #[must_use]
+  pub const fn elo(self) -> u32 {
+    self.elo
+  }
+
+  // how far below the strongest limited strength
+  const fn deficit(self) -> u32 {
+    MAX_ELO - self.elo
+  }
+
+  /// The most nodes to search for each move, doubling every 150 Elo
+  #[must_use]
+  pub const fn node_limit(self) -> usize {
+    100 << ((self.elo - MIN_ELO) / 150)
+  }
+
+  /// Noise to add to the evaluation of a position in centipawns
+  ///
+  /// The noise is the same every time a position is evaluated so the search stays consistent
+  #[must_use]
+  pub fn noise(self, hash: Hash) -> i32 {
+    let amplitude = u64::from(self.deficit() / 10);
+    if amplitude == 0 {
+      return 0;
+    }
+    // spread similar hashes apart
+    let mixed = (hash ^ self.seed).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32;
+    (mixed % (2 * amplitude + 1)) as i32 - amplitude as i32
+  }
+
+  /// Pick one of the lines found by the search, worse lines are picked more often at lower Elo
+  #[must_use]
+  pub fn pick_line(self, lines: &[(Vec<Move>, Score)]) -> Option<usize> {
+    let temperature = f64::from(self.deficit() / 20);
+    let scores: Vec<f64> = lines
+      .iter()
+      .map(|(_, score)| match score {
+        Score::Centipawn(score) => f64::from(*score),
+        Score::Win(_) => MATE_CENTIPAWNS,
+        Score::Loss(_) => -MATE_CENTIPAWNS,
+      })
+      .collect();
+    let best = scores.iter().copied().reduce(f64::max)?;
+    if temperature == 0.0 {
+      return scores.iter().position(|score| *score == best);
+    }
+    let indices: Vec<usize> = (0..lines.len()).collect();
+    indices
+      .choose_weighted(&mut thread_rng(), |index| {
+        ((scores[*index] - best) / temperature).exp()
+      })
+      .ok()
+      .copied()
+  }
+}
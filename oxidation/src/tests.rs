@@ -0,0 +1,275 @@
+use crate::endgame::trivial_win;
+use liberty_chess::{Board, Gamestate};
+use ulci::Score;
+
+// Play the table against a defender that keeps as many moves as possible
+fn mates(fen: &str) -> bool {
+  let mut table = None;
+  let mut board = Board::new(fen).expect("Invalid test position");
+  let mut expected = None;
+  loop {
+    let Some((mv, score)) = trivial_win(&mut table, &board) else {
+      return false;
+    };
+    // the mate doesn't get any further away
+    if let Score::Win(moves) = score {
+      assert!(expected.is_none_or(|expected| moves <= expected));
+      expected = Some(moves);
+    }
+    board = board.move_if_legal(mv).expect("Illegal move");
+    if board.state() != Gamestate::InProgress {
+      return matches!(board.state(), Gamestate::Checkmate(_));
+    }
+    board = board
+      .generate_legal()
+      .into_iter()
+      .max_by_key(|position| {
+        position
+          .nullmove()
+          .map_or(0, |reply| reply.generate_legal().len())
+      })
+      .expect("No legal moves");
+  }
+}
+
+#[test]
+fn trivial_win_kqk() {
+  assert!(mates("8/8/8/3k4/8/8/8/Q3K3 w - - 0 1"));
+  assert!(mates("7K/8/8/8/4Q3/8/8/k7 w - - 0 1"));
+  assert!(mates("k2K4/8/8/8/3Q4/8/8/8 w - - 0 1"));
+}
+
+#[test]
+fn trivial_win_krk() {
+  assert!(mates("8/8/8/3k4/8/8/8/R3K3 w - - 0 1"));
+  assert!(mates("r7/8/8/8/4K3/8/8/7k b - - 0 1"));
+  assert!(mates("K4k2/8/8/4R3/8/8/8/8 w - - 0 1"));
+}
+
+#[test]
+fn trivial_win_small_board() {
+  assert!(mates("k5/6/6/1R2K1 w - - 0 1"));
+  assert!(mates("5/5/2k2/5/1Q2K w - - 0 1"));
+}
+
+#[test]
+fn trivial_win_mate_distance() {
+  let mut table = None;
+  let board = Board::new("6k1/8/6K1/8/8/8/8/R7 w - - 0 1").unwrap();
+  let (mv, score) = trivial_win(&mut table, &board).unwrap();
+  assert_eq!(mv.to_string(), "a1a8");
+  assert_eq!(score.show_uci(board.moves(), board.to_move()), "mate 1");
+  // the longest mate with a rook on a standard board is 16 moves
+  let board = Board::new("8/8/8/3k4/8/8/8/R3K3 w - - 0 1").unwrap();
+  let (_, score) = trivial_win(&mut table, &board).unwrap();
+  let Score::Win(moves) = score else {
+    panic!("Expected a win");
+  };
+  assert!(moves <= 16);
+}
+
+#[test]
+fn trivial_win_other_positions() {
+  let mut table = None;
+  // the rook can be defended
+  let board = Board::new("8/8/8/3k4/8/8/8/R3K2r w - - 0 1").unwrap();
+  assert!(trivial_win(&mut table, &board).is_none());
+  // non-standard rules
+  let board = Board::new("8/8/8/3k4/8/8/8/R3K3 w - - 0 1 - qrbn ff").unwrap();
+  assert!(trivial_win(&mut table, &board).is_none());
+  let board = Board::new("8/8/8/3k4/8/8/8/R3K3 w - - 0 1 - qa").unwrap();
+  assert!(trivial_win(&mut table, &board).is_none());
+}
+
+#[test]
+fn win_probability_matches_wdl_model() {
+  use crate::evaluate::{phase, wdl_model};
+  use crate::{pawn_value, win_probability_scale};
+  use liberty_chess::positions::STARTPOS;
+  for fen in [STARTPOS, "4k3/pppp4/8/8/8/8/PPPP4/4K3 w - - 0 1"] {
+    let board = Board::new(fen).unwrap();
+    let scale = f64::from(win_probability_scale(&board));
+    let pawn = f64::from(pawn_value(&board));
+    // the curves agree near an even position, where the eval bar spends most of its time
+    for score in [10, 30, 60] {
+      let wdl = wdl_model(Score::Centipawn(score), phase(&board)).to_string();
+      let chances: Vec<f64> = wdl
+        .split_whitespace()
+        .skip(1)
+        .map(|chance| chance.parse().unwrap())
+        .collect();
+      let expected = (chances[0] + chances[1] / 2.0) / 1000.0;
+      let probability = 1.0 / (1.0 + (-f64::from(score) * pawn / 100.0 / scale).exp());
+      assert!((expected - probability).abs() < 0.01, "{fen} {score}");
+    }
+  }
+}
+
+#[test]
+fn strength_node_limit() {
+  use crate::strength::{Strength, MAX_ELO, MIN_ELO};
+  assert_eq!(Strength::new(MIN_ELO).node_limit(), 100);
+  assert_eq!(Strength::new(MIN_ELO + 150).node_limit(), 200);
+  assert!(Strength::new(1500).node_limit() < Strength::new(MAX_ELO).node_limit());
+  // out of range values are clamped
+  assert_eq!(Strength::new(0).elo(), MIN_ELO);
+  assert_eq!(Strength::new(u32::MAX).elo(), MAX_ELO);
+}
+
+#[test]
+fn strength_noise() {
+  use crate::strength::{Strength, MAX_ELO, MIN_ELO};
+  let strength = Strength::new(MIN_ELO);
+  let amplitude = ((MAX_ELO - MIN_ELO) / 10) as i32;
+  for hash in [0, 1, 12_345, u64::MAX] {
+    let noise = strength.noise(hash);
+    // the same position always gets the same noise
+    assert_eq!(noise, strength.noise(hash));
+    assert!(noise.abs() <= amplitude, "{noise}");
+  }
+  assert_eq!(Strength::new(MAX_ELO).noise(12_345), 0);
+}
+
+#[test]
+fn strength_pick_line() {
+  use crate::strength::{Strength, MAX_ELO, MIN_ELO};
+  let lines = vec![
+    (Vec::new(), Score::Centipawn(20)),
+    (Vec::new(), Score::Win(3)),
+    (Vec::new(), Score::Loss(2)),
+    (Vec::new(), Score::Centipawn(-50)),
+  ];
+  assert_eq!(Strength::new(MAX_ELO).pick_line(&lines), Some(1));
+  assert_eq!(Strength::new(MIN_ELO).pick_line(&[]), None);
+  for _ in 0..20 {
+    let index = Strength::new(MIN_ELO).pick_line(&lines).unwrap();
+    assert!(index < lines.len());
+  }
+}
+
+#[test]
+fn tt_resize_keeps_entries() {
+  use crate::tt::{Entry, ScoreType, TranspositionTable};
+  use liberty_chess::positions::STARTPOS;
+  let board = Board::new(STARTPOS).unwrap();
+  let mut table = TranspositionTable::new(1, &board);
+  let store = |table: &mut TranspositionTable, hash| {
+    table.store(Entry {
+      hash,
+      depth: 1,
+      movecount: 0,
+      scoretype: ScoreType::Exact,
+      score: Score::Centipawn(hash as i32),
+      bestmove: None,
+    });
+  };
+  // fill a quarter of the table
+  for hash in 0..16_384 {
+    store(&mut table, hash);
+  }
+  assert_eq!(table.capacity(), 250);
+  // growing copies each entry to every slot it could belong in
+  table.resize(2);
+  assert_eq!(table.capacity(), 250);
+  for hash in [0, 100, 16_383] {
+    let (score, _) = table.get(hash, 0, Score::Loss(0), Score::Win(0), 1);
+    assert!(matches!(score, Some(Score::Centipawn(score)) if score == hash as i32));
+  }
+  // shrinking back keeps one copy of each
+  table.resize(1);
+  assert_eq!(table.capacity(), 250);
+  // the new slots aren't known when neither size is a multiple of the other
+  table.resize(2);
+  table.resize(3);
+  assert_eq!(table.capacity(), 0);
+}
+
+// write a bench suite to a temporary file and load it
+fn bench_suite(name: &str, contents: &str, depth: i8) -> Result<Vec<(String, i8)>, String> {
+  let path = std::env::temp_dir().join(format!("oxidation-{name}-{}.txt", std::process::id()));
+  std::fs::write(&path, contents).unwrap();
+  let suite = crate::load_bench_suite(path.to_str().unwrap(), depth);
+  std::fs::remove_file(path).ok();
+  suite
+}
+
+#[test]
+fn bench_suite_depth_overrides() {
+  let suite = bench_suite(
+    "depths",
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\n8/8/8/3k4/8/8/8/R3K3 w - - 0 1 ; 12\n",
+    5,
+  )
+  .unwrap();
+  assert_eq!(
+    suite,
+    vec![
+      (
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_owned(),
+        5
+      ),
+      ("8/8/8/3k4/8/8/8/R3K3 w - - 0 1".to_owned(), 12),
+    ]
+  );
+}
+
+#[test]
+fn bench_suite_comments() {
+  let suite = bench_suite(
+    "comments",
+    "# horde positions\n\n  # indented comment\n8/8/8/3k4/8/8/8/R3K3 w - - 0 1\n\n",
+    4,
+  )
+  .unwrap();
+  assert_eq!(
+    suite,
+    vec![("8/8/8/3k4/8/8/8/R3K3 w - - 0 1".to_owned(), 4)]
+  );
+  assert!(bench_suite("empty", "# nothing here\n", 4)
+    .unwrap_err()
+    .starts_with("no positions found"));
+}
+
+#[test]
+fn bench_suite_errors() {
+  let valid = "8/8/8/3k4/8/8/8/R3K3 w - - 0 1";
+  assert_eq!(
+    bench_suite("depth", &format!("{valid}\n{valid};deep\n"), 4),
+    Err("invalid depth on line 2".to_owned())
+  );
+  assert_eq!(
+    bench_suite("zero", &format!("# comment\n{valid};0\n"), 4),
+    Err("depth must be positive on line 2".to_owned())
+  );
+  assert!(bench_suite("fen", &format!("{valid}\n\nnot a fen\n"), 4)
+    .unwrap_err()
+    .starts_with("invalid L-FEN on line 3: "));
+  assert!(crate::load_bench_suite("/nonexistent/suite.txt", 4)
+    .unwrap_err()
+    .starts_with("unable to read /nonexistent/suite.txt"));
+}
+
+#[test]
+fn lite_engine_uses_think_time() {
+  use crate::lite::LiteEngine;
+  use std::time::Instant;
+  let board =
+    Board::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+  let mut engine = LiteEngine::new(1000, None);
+  let start = Instant::now();
+  engine.start(&board);
+  let mut slices = 0;
+  let mv = loop {
+    if let Some(mv) = engine.step() {
+      break mv;
+    }
+    slices += 1;
+    // slices are short and the move isn't returned early
+    assert!(start.elapsed().as_millis() < 1000, "{slices}");
+  };
+  let elapsed = start.elapsed().as_millis();
+  assert!((1000..3000).contains(&elapsed), "{elapsed}");
+  assert!(slices > 1);
+  assert!(!engine.is_searching());
+  assert!(board.move_if_legal(mv).is_some());
+}
@@ -1,6 +1,7 @@
 use liberty_chess::moves::Move;
 use liberty_chess::{Board, ExtraFlags, Hash};
 use std::cmp::max;
+use std::io::{Error, ErrorKind, Read, Result, Write};
 use ulci::Score;
 
 #[derive(Clone, Copy, Eq, PartialEq)]
@@ -50,6 +51,18 @@ impl From<CompactEntry> for Entry {
   }
 }
 
+const FLAGS: [Flags; 9] = [
+  Flags::ExactCentipawn,
+  Flags::ExactWin,
+  Flags::ExactLoss,
+  Flags::LowerCentipawn,
+  Flags::LowerWin,
+  Flags::LowerLoss,
+  Flags::UpperCentipawn,
+  Flags::UpperWin,
+  Flags::UpperLoss,
+];
+
 #[derive(Clone, Copy)]
 pub enum Flags {
   ExactCentipawn,
@@ -95,11 +108,74 @@ impl From<Entry> for CompactEntry {
   }
 }
 
+impl CompactEntry {
+  // Size of an entry in a saved table, including its index
+  const SAVED_SIZE: usize = 24;
+
+  const fn is_exact(&self) -> bool {
+    matches!(
+      self.flags,
+      Flags::ExactCentipawn | Flags::ExactWin | Flags::ExactLoss
+    )
+  }
+
+  fn save(&self, index: usize) -> [u8; Self::SAVED_SIZE] {
+    let mut bytes = [0; Self::SAVED_SIZE];
+    bytes[0..8].copy_from_slice(&(index as u64).to_le_bytes());
+    bytes[8..12].copy_from_slice(&self.hash.to_le_bytes());
+    bytes[12..16].copy_from_slice(&self.raw_score.to_le_bytes());
+    bytes[16] = self.flags as u8;
+    bytes[17] = self.depth;
+    if let Some(mv) = self.bestmove {
+      let (start, end) = (mv.start(), mv.end());
+      bytes[18] = 1;
+      bytes[19] = start.0 as u8;
+      bytes[20] = start.1 as u8;
+      bytes[21] = end.0 as u8;
+      bytes[22] = end.1 as u8;
+      if let Some(piece) = mv.promotion() {
+        bytes[18] = 2;
+        bytes[23] = piece as u8;
+      }
+    }
+    bytes
+  }
+
+  fn load(bytes: [u8; Self::SAVED_SIZE]) -> Option<(usize, Self)> {
+    let index = u64::from_le_bytes(bytes[0..8].try_into().ok()?) as usize;
+    let bestmove = match bytes[18] {
+      0 => None,
+      promotion => {
+        let mut mv = Move::new(
+          (usize::from(bytes[19]), usize::from(bytes[20])),
+          (usize::from(bytes[21]), usize::from(bytes[22])),
+        );
+        if promotion > 1 {
+          mv.add_promotion(bytes[23] as i8);
+        }
+        Some(mv)
+      }
+    };
+    Some((
+      index,
+      Self {
+        hash: u32::from_le_bytes(bytes[8..12].try_into().ok()?),
+        bestmove,
+        raw_score: u32::from_le_bytes(bytes[12..16].try_into().ok()?),
+        flags: *FLAGS.get(usize::from(bytes[16]))?,
+        depth: bytes[17],
+      },
+    ))
+  }
+}
+
 pub struct TranspositionTable {
   entries: Box<[Option<CompactEntry>]>,
   flags: ExtraFlags,
   // the number of entries full
   capacity: usize,
+  // searches with excluded root moves can't replace exact entries with bounds
+  protect_exact: bool,
 }
 
 impl TranspositionTable {
@@ -111,6 +187,7 @@ impl TranspositionTable {
       entries,
       flags: ExtraFlags::new(board),
       capacity: 0,
+      protect_exact: false,
     }
   }
 
@@ -155,9 +232,16 @@ impl TranspositionTable {
     if self.entries.len() > 0 {
       let index = entry.hash as usize % self.entries.len();
       if let Some(old_entry) = self.entries[index] {
-        if old_entry.hash != (entry.hash >> 32) as u32
-          || entry.scoretype == ScoreType::Exact
-          || entry.depth.saturating_add(1) >= old_entry.depth
+        let same_position = old_entry.hash == (entry.hash >> 32) as u32;
+        // only the same position is protected, other positions in the slot replace it as usual
+        let protected = self.protect_exact
+          && same_position
+          && old_entry.is_exact()
+          && entry.scoretype != ScoreType::Exact;
+        if !protected
+          && (!same_position
+            || entry.scoretype == ScoreType::Exact
+            || entry.depth.saturating_add(1) >= old_entry.depth)
         {
           self.entries[index] = Some(CompactEntry::from(entry));
         }
@@ -168,6 +252,12 @@ impl TranspositionTable {
     }
   }
 
+  // Set while searching the later MultiPV lines, whose bounds come from searches with the best
+  // moves excluded at the root and would otherwise overwrite the main line on later iterations
+  pub fn protect_exact(&mut self, protect: bool) {
+    self.protect_exact = protect;
+  }
+
   // Clears the table if the flags change
   // Call whenever the position to search changes
   // Returns whether the table was cleared
@@ -190,6 +280,82 @@ impl TranspositionTable {
     }
   }
 
+  // Change the size of the table, keeping the entries if their new slot is known
+  // Only the slot and the upper half of the hash are stored, so entries are only kept when one size
+  // is a multiple of the other, growing copies each entry to every slot it could belong in
+  pub fn resize(&mut self, megabytes: usize) {
+    let size = megabytes * 65536;
+    let old_size = self.entries.len();
+    if size == old_size {
+      return;
+    }
+    let mut entries = vec![None; size].into_boxed_slice();
+    if size > 0 && old_size > 0 && size % old_size == 0 {
+      for (index, entry) in self.entries.iter().enumerate() {
+        if entry.is_some() {
+          for slot in (index..size).step_by(old_size) {
+            entries[slot] = *entry;
+          }
+        }
+      }
+    } else if size > 0 && old_size % size == 0 {
+      for (index, entry) in self.entries.iter().enumerate() {
+        let Some(entry) = entry else {
+          continue;
+        };
+        let slot = &mut entries[index % size];
+        match slot {
+          Some(existing) if existing.depth >= entry.depth => (),
+          _ => *slot = Some(*entry),
+        }
+      }
+    }
+    // count the slots filled by the copy
+    self.capacity = entries.iter().filter(|entry| entry.is_some()).count();
+    self.entries = entries;
+  }
+
+  // Write the entries to a file so a search can be resumed later
+  // The flags aren't saved, so the position should be saved alongside the table
+  pub fn save(&self, out: &mut impl Write) -> Result<()> {
+    out.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+    out.write_all(&(self.capacity as u64).to_le_bytes())?;
+    for (index, entry) in self.entries.iter().enumerate() {
+      if let Some(entry) = entry {
+        out.write_all(&entry.save(index))?;
+      }
+    }
+    Ok(())
+  }
+
+  // Load entries saved with `save`, replacing the current entries
+  // The table must be the same size as the saved one
+  pub fn load(&mut self, input: &mut impl Read, position: &Board) -> Result<()> {
+    let invalid = |message: &str| Error::new(ErrorKind::InvalidData, message);
+    let mut header = [0; 8];
+    input.read_exact(&mut header)?;
+    if u64::from_le_bytes(header) != self.entries.len() as u64 {
+      return Err(invalid("hash size differs from the saved table"));
+    }
+    input.read_exact(&mut header)?;
+    let count = u64::from_le_bytes(header);
+    self.clear(ExtraFlags::new(position));
+    for _ in 0..count {
+      let mut bytes = [0; CompactEntry::SAVED_SIZE];
+      input.read_exact(&mut bytes)?;
+      let (index, entry) = CompactEntry::load(bytes).ok_or_else(|| invalid("invalid entry"))?;
+      let slot = self
+        .entries
+        .get_mut(index)
+        .ok_or_else(|| invalid("invalid entry"))?;
+      if slot.is_none() {
+        self.capacity += 1;
+      }
+      *slot = Some(entry);
+    }
+    Ok(())
+  }
+
   pub fn capacity(&self) -> usize {
     self.capacity * 1000 / max(self.entries.len(), 1)
   }
@@ -2,12 +2,14 @@
 #![warn(missing_docs, unused)]
 //! A helpful program to test and benchmark the move generation
 
-use liberty_chess::clock::format_time;
+use liberty_chess::clock::{format_time, Millis};
+use liberty_chess::parsing::FenError;
 use liberty_chess::positions::{
   AFRICAN, CAPABLANCA, CAPABLANCA_RECTANGLE, DOUBLE_CHESS, HORDE, LIBERTY_CHESS, LOADED_BOARD,
   MINI, MONGOL, NARNIA, STARTPOS, TRUMP,
 };
 use liberty_chess::{perft, Board};
+use std::env::args;
 use std::sync::mpsc::channel;
 use std::time::{Duration, Instant};
 
@@ -18,12 +20,14 @@ use std::thread::available_parallelism;
 #[cfg(feature = "parallel")]
 use threadpool::ThreadPool;
 
+mod repl;
+
 const LIMIT: usize = usize::MAX;
 
 fn print_time(fen: &str, time: Duration, depth: usize, nodes: usize) {
   let millis = time.as_millis();
   let kilonodes = nodes / usize::max(millis as usize, 1);
-  let time = format_time(millis);
+  let time = format_time(Millis::from(time));
   println!("{time} for depth {depth} ({kilonodes} knodes/s) {fen}");
 }
 
@@ -44,7 +48,7 @@ fn perft_process_other(pool: &ThreadPool, board: &Board, depth: usize, result: u
   let closure = move || {
     let mut board = board.load_from_thread();
     board.skip_checkmate = true;
-    assert_eq!(perft(&board, depth), result)
+    assert_eq!(perft(&board, depth), result, "\n{}", board.to_ascii());
   };
   pool.execute(closure);
 }
@@ -74,7 +78,7 @@ fn perft_test(fen: &'static str, results: &[usize]) {
     #[cfg(feature = "parallel")]
     perft_process_other(&pool, &board, i, *result);
     #[cfg(not(feature = "parallel"))]
-    assert_eq!(perft(&board, i), *result);
+    assert_eq!(perft(&board, i), *result, "\n{}", board.to_ascii());
   }
 
   let (tx, rx) = channel();
@@ -100,13 +104,39 @@ fn perft_test(fen: &'static str, results: &[usize]) {
   }
   #[cfg(feature = "parallel")]
   pool.join();
-  assert_eq!(rx.iter().take(num_moves).sum::<usize>(), results[max]);
+  assert_eq!(
+    rx.iter().take(num_moves).sum::<usize>(),
+    results[max],
+    "\n{}",
+    board.to_ascii()
+  );
   print_time(fen, start.elapsed(), max, nodes);
 }
 
+// Check a board is rejected for the expected reason
+fn invalid_test(fen: &str, error: FenError) {
+  assert_eq!(Board::new(fen).err(), Some(error), "{fen}");
+}
+
 fn main() {
+  // explore positions by hand instead of running the test suite
+  if args().nth(1).as_deref() == Some("repl") {
+    repl::run();
+    return;
+  }
+
   let start = Instant::now();
 
+  // degenerate boards
+  // bare kings are a draw by insufficient material straight away
+  perft_test("k1/2/1K w - - 0 1", &[1, 0, 0]);
+  // no kings, the only move eliminates black
+  perft_test("n1/2/1N w - - 0 1", &[1, 1, 0]);
+  invalid_test("k/K w - - 0 1", FenError::Size);
+  invalid_test("k1/2/1K w - - 0 1 2,2,4", FenError::CastleRow);
+  invalid_test("kP/2/1K w - - 0 1", FenError::PawnPromotionRow);
+  invalid_test("k1/2/pK b - - 0 1", FenError::PawnPromotionRow);
+
   // standard chess
   perft_test(
     STARTPOS,
@@ -182,6 +212,12 @@ fn main() {
     &[1, 40, 1_592, 68_142, 2_898_457, 132_653_171],
   );
 
+  // chess960 from https://www.chessprogramming.org/Chess960_Perft_Results
+  perft_test(
+    "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+    &[1, 21, 528, 12_189, 326_672, 8_146_062],
+  );
+
   //friendly fire
   perft_test(
     "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 - qrbn ff",
@@ -212,5 +248,5 @@ fn main() {
     &[1, 40, 1_600, 71_502, 3_178_819],
   );
 
-  println!("{}", format_time(start.elapsed().as_millis()));
+  println!("{}", format_time(start.elapsed().into()));
 }
@@ -0,0 +1,154 @@
+use liberty_chess::clock::format_time;
+use liberty_chess::moves::Move;
+use liberty_chess::parsing::to_letters;
+use liberty_chess::positions::STARTPOS;
+use liberty_chess::{perft, Board};
+use std::io::{stdin, stdout, Write};
+use std::time::Instant;
+
+const HELP: &str = "\
+Commands:
+  fen <L-FEN>       load a position
+  startpos          load the starting position
+  show              draw the board
+  moves             list the legal moves
+  move <moves>...   play moves in long algebraic notation
+  undo              take back the last move
+  hash              show the Zobrist hash
+  state             show the game state and any sanity issues
+  attacks [w|b]     show the squares attacked by a side, defaults to the side to move
+  perft <depth>     count the positions after each legal move
+  help              show this message
+  quit              leave the REPL";
+
+// An interactive shell for investigating move generation by hand
+pub fn run() {
+  let mut board = Board::new(STARTPOS).expect("startpos is valid");
+  let mut history: Vec<Board> = Vec::new();
+  println!("{HELP}");
+  let mut buffer = String::new();
+  loop {
+    print!("> ");
+    stdout().flush().ok();
+    buffer.clear();
+    match stdin().read_line(&mut buffer) {
+      Ok(0) | Err(_) => break,
+      Ok(_) => (),
+    }
+    let mut words = buffer.split_whitespace();
+    match words.next() {
+      Some("fen") => {
+        let fen = words.collect::<Vec<_>>().join(" ");
+        match Board::new(&fen) {
+          Ok(new_board) => {
+            board = new_board;
+            history.clear();
+            println!("{}", board.to_unicode());
+          }
+          Err(error) => println!("Invalid L-FEN: {error}"),
+        }
+      }
+      Some("startpos") => {
+        board = Board::new(STARTPOS).expect("startpos is valid");
+        history.clear();
+        println!("{}", board.to_unicode());
+      }
+      Some("show") => println!("{}\n{}", board.to_string(), board.to_unicode()),
+      Some("moves") => {
+        let moves = board.generate_legal_moves();
+        let moves: Vec<String> = moves.iter().map(Move::to_string).collect();
+        println!("{} legal moves: {}", moves.len(), moves.join(" "));
+      }
+      Some("move") => {
+        for word in words {
+          let Some(new_board) = word
+            .parse::<Move>()
+            .ok()
+            .and_then(|mv| board.move_if_legal(mv))
+          else {
+            println!("Illegal move {word}");
+            break;
+          };
+          history.push(board);
+          board = new_board;
+        }
+        println!("{}", board.to_unicode());
+      }
+      Some("undo") => match history.pop() {
+        Some(previous) => {
+          board = previous;
+          println!("{}", board.to_unicode());
+        }
+        None => println!("No moves to undo"),
+      },
+      Some("hash") => println!("{:016x}", board.hash()),
+      Some("state") => show_state(&board),
+      Some("attacks") => {
+        let side = match words.next() {
+          Some("w") => true,
+          Some("b") => false,
+          _ => board.to_move(),
+        };
+        show_attacks(&board, side);
+      }
+      Some("perft") => match words.next().and_then(|w| w.parse::<usize>().ok()) {
+        Some(depth) if depth > 0 => divide(&board, depth),
+        _ => println!("Usage: perft <depth>"),
+      },
+      Some("help") => println!("{HELP}"),
+      Some("quit") => break,
+      Some(command) => println!("Unrecognised command {command}, try help"),
+      None => (),
+    }
+  }
+}
+
+fn show_state(board: &Board) {
+  println!("State: {:?}", board.state());
+  println!(
+    "{} to move, move {}, {} halfmoves",
+    if board.to_move() { "White" } else { "Black" },
+    board.moves(),
+    board.halfmoves()
+  );
+  println!("In check: {}", board.in_check());
+  println!("Chess960 castling: {}", board.chess960());
+  for issue in board.sanity_report() {
+    println!("Sanity issue: {issue}");
+  }
+}
+
+fn show_attacks(board: &Board, side: bool) {
+  let label_width = board.height().to_string().len();
+  for row in (0..board.height()).rev() {
+    let mut line = format!("{:>label_width$} ", row + 1);
+    for column in 0..board.width() {
+      line += if board.is_attacked((row, column), side) {
+        " x "
+      } else {
+        " . "
+      };
+    }
+    println!("{}", line.trim_end());
+  }
+  let mut files = " ".repeat(label_width + 1);
+  for column in 0..board.width() {
+    let letters: String = to_letters(column).into_iter().collect();
+    files += &format!("{letters:^3}");
+  }
+  println!("{}", files.trim_end());
+}
+
+fn divide(board: &Board, depth: usize) {
+  let start = Instant::now();
+  let mut total = 0;
+  for mv in board.generate_legal_moves() {
+    if let Some(mut position) = board.move_if_legal(mv) {
+      position.skip_checkmate = true;
+      let count = perft(&position, depth - 1);
+      total += count;
+      println!("{}: {count}", mv.to_string());
+    }
+  }
+  println!("Total: {total} in {}", format_time(start.elapsed().into()));
+}
@@ -0,0 +1,208 @@
+#![warn(missing_docs, unused)]
+//! Python bindings for Liberty Chess and Oxidation
+
+use liberty_chess::clock::Millis;
+use liberty_chess::moves::Move;
+use liberty_chess::parsing::to_char;
+use liberty_chess::{Board, Gamestate};
+use oxidation::parameters::DEFAULT_PARAMETERS;
+use oxidation::search::SEARCH_PARAMETERS;
+use oxidation::{search as search_position, Output, SearchConfig, State, HASH_SIZE};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::sync::mpsc::channel;
+use ulci::server::UlciResult;
+use ulci::{Limits, SearchTime};
+
+/// A Liberty Chess position
+#[pyclass(name = "Board", unsendable)]
+pub struct PyBoard {
+  board: Board,
+}
+
+fn parse_move(mv: &str) -> PyResult<Move> {
+  mv.parse()
+    .map_err(|_| PyValueError::new_err(format!("Invalid move {mv}")))
+}
+
+#[pymethods]
+impl PyBoard {
+  /// Load a board from an L-FEN
+  #[new]
+  fn new(fen: &str) -> PyResult<Self> {
+    Board::new(fen)
+      .map(|board| Self { board })
+      .map_err(|error| PyValueError::new_err(error.to_string()))
+  }
+
+  /// The L-FEN of the board
+  fn fen(&self) -> String {
+    self.board.to_string()
+  }
+
+  fn __str__(&self) -> String {
+    self.board.to_string()
+  }
+
+  fn __repr__(&self) -> String {
+    format!("Board(\"{}\")", self.board.to_string())
+  }
+
+  /// The width of the board
+  #[getter]
+  fn width(&self) -> usize {
+    self.board.width()
+  }
+
+  /// The height of the board
+  #[getter]
+  fn height(&self) -> usize {
+    self.board.height()
+  }
+
+  /// The piece on a square as an L-FEN character, row 0 is white's side of the board
+  fn piece(&self, row: usize, column: usize) -> Option<char> {
+    match self.board.fetch_piece((row, column)) {
+      Some(&piece) if piece != 0 => Some(to_char(piece)),
+      _ => None,
+    }
+  }
+
+  /// Whether it is white to move
+  #[getter]
+  fn white_to_move(&self) -> bool {
+    self.board.to_move()
+  }
+
+  /// Whether the side to move is in check
+  fn in_check(&self) -> bool {
+    self.board.in_check()
+  }
+
+  /// The legal moves in long algebraic notation
+  fn legal_moves(&self) -> Vec<String> {
+    self
+      .board
+      .generate_legal_moves()
+      .iter()
+      .map(Move::to_string)
+      .collect()
+  }
+
+  /// Whether a move in long algebraic notation is legal
+  fn is_legal(&self, mv: &str) -> bool {
+    parse_move(mv).is_ok_and(|mv| self.board.move_if_legal(mv).is_some())
+  }
+
+  /// Play a move in long algebraic notation, raising ValueError if it is illegal
+  fn play(&mut self, mv: &str) -> PyResult<()> {
+    self.board = self
+      .board
+      .move_if_legal(parse_move(mv)?)
+      .ok_or_else(|| PyValueError::new_err(format!("Illegal move {mv}")))?;
+    Ok(())
+  }
+
+  /// The state of the game
+  ///
+  /// One of "in progress", "checkmate", "elimination", "stalemate", "fifty move", "repetition" or "material"
+  #[getter]
+  fn state(&self) -> &'static str {
+    match self.board.state() {
+      Gamestate::InProgress => "in progress",
+      Gamestate::Checkmate(_) => "checkmate",
+      Gamestate::Elimination(_) => "elimination",
+      Gamestate::Stalemate => "stalemate",
+      Gamestate::FiftyMove => "fifty move",
+      Gamestate::Repetition => "repetition",
+      Gamestate::Material => "material",
+    }
+  }
+
+  /// The winner of the game, True for white, or None if there is no winner
+  #[getter]
+  fn winner(&self) -> Option<bool> {
+    match self.board.state() {
+      Gamestate::Checkmate(winner) | Gamestate::Elimination(winner) => Some(winner),
+      _ => None,
+    }
+  }
+}
+
+/// The result of an Oxidation search
+#[pyclass(get_all)]
+pub struct SearchResult {
+  /// The best move, or None if there are no legal moves
+  bestmove: Option<String>,
+  /// The principal variation
+  pv: Vec<String>,
+  /// The score in UCI format, e.g. "cp 25" or "mate 3"
+  score: Option<String>,
+  /// The depth searched
+  depth: u16,
+  /// The nodes searched
+  nodes: usize,
+}
+
+/// Search a position with Oxidation, at least one of nodes, time (in ms) or depth must be set
+#[pyfunction]
+#[pyo3(signature = (board, nodes=None, time=None, depth=None, hash_size=HASH_SIZE))]
+fn search(
+  board: &PyBoard,
+  nodes: Option<usize>,
+  time: Option<u64>,
+  depth: Option<u8>,
+  hash_size: usize,
+) -> PyResult<SearchResult> {
+  if nodes.is_none() && time.is_none() && depth.is_none() {
+    return Err(PyValueError::new_err("Must limit depth, nodes or time"));
+  }
+  let limits = Limits {
+    depth: depth.unwrap_or(u8::MAX),
+    nodes: nodes.unwrap_or(usize::MAX),
+    time: time.map_or(Millis::MAX, |time| Millis::new(u128::from(time))),
+  };
+  let mut position = board.board.clone();
+  let mut state = State::new(hash_size, &position, SEARCH_PARAMETERS, DEFAULT_PARAMETERS);
+  // the search stops if the message channel is closed
+  let (_tx, rx) = channel();
+  let (send_results, results) = channel();
+  let mut debug = false;
+  let mut settings = SearchConfig::new_time(&position, SearchTime::Other(limits), &rx, &mut debug);
+  let pv = search_position(
+    &mut state,
+    &mut settings,
+    &mut position,
+    &[],
+    1,
+    Output::Channel(&send_results),
+  );
+  let analysis = results
+    .try_iter()
+    .filter_map(|result| match result {
+      UlciResult::Analysis(analysis) => Some(analysis),
+      _ => None,
+    })
+    .last();
+  Ok(SearchResult {
+    bestmove: pv.first().map(Move::to_string),
+    pv: pv.iter().map(Move::to_string).collect(),
+    score: analysis.as_ref().map(|analysis| {
+      analysis
+        .score
+        .show_uci(board.board.moves(), board.board.to_move())
+    }),
+    depth: analysis.as_ref().map_or(0, |analysis| analysis.depth),
+    nodes: analysis.map_or(0, |analysis| analysis.nodes),
+  })
+}
+
+/// The Liberty Chess rules engine and the Oxidation engine
+#[pymodule]
+#[pyo3(name = "liberty_chess")]
+fn liberty_chess_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+  m.add_class::<PyBoard>()?;
+  m.add_class::<SearchResult>()?;
+  m.add_function(wrap_pyfunction!(search, m)?)?;
+  Ok(())
+}
@@ -0,0 +1,227 @@
+use liberty_chess::clock::Millis;
+use liberty_chess::moves::Move;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs::{read_to_string, write, OpenOptions};
+use std::io::Write;
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::Duration;
+use ulci::server::{AnalysisRequest, Request, UlciResult};
+use ulci::{Limits, SearchTime};
+
+/// The file reports are appended to
+const REPORT_FILE: &str = "anticheat.txt";
+/// The file each player's totals are kept in
+const TOTALS_FILE: &str = "anticheat_players.txt";
+/// The nodes the analysis engine searches for each position
+const ANALYSIS_NODES: usize = 100_000;
+/// The minimum number of moves from a player before they can be flagged
+const MIN_MOVES: usize = 20;
+/// Players agreeing with the engine more often than this are flagged
+const AGREEMENT_THRESHOLD: f64 = 0.9;
+/// Players with a move time standard deviation below this fraction of the mean are flagged
+const CONSISTENCY_THRESHOLD: f64 = 0.15;
+
+/// A move played in a game
+pub struct MoveRecord {
+  /// The position before the move
+  pub fen: String,
+  /// The move played
+  pub mv: Move,
+  /// The time taken to play the move
+  pub time: Duration,
+}
+
+/// The moves played by each player in a game
+pub struct GameRecord {
+  /// The starting position
+  pub fen: String,
+  /// The names of the players, which their totals are kept under
+  pub names: [String; 2],
+  /// The moves played by each player
+  pub moves: [Vec<MoveRecord>; 2],
+}
+
+// Move time and engine agreement statistics for a game, or totals for a player across games
+#[derive(Clone, Copy, Default)]
+struct PlayerStats {
+  moves: usize,
+  // sums of move times and their squares in ms
+  time: f64,
+  time_squared: f64,
+  // moves checked by the analysis engine, and how many matched it
+  analysed: usize,
+  agreement: usize,
+}
+
+impl PlayerStats {
+  fn new(moves: &[MoveRecord], engine: Option<&(Sender<Request>, Receiver<UlciResult>)>) -> Self {
+    let times = moves
+      .iter()
+      .map(|record| record.time.as_secs_f64() * 1000.0);
+    let (analysed, agreement) = engine.map_or((0, 0), |engine| {
+      let agreement = moves
+        .iter()
+        .filter(|record| analyse(engine, &record.fen) == Some(record.mv))
+        .count();
+      (moves.len(), agreement)
+    });
+    Self {
+      moves: moves.len(),
+      time: times.clone().sum(),
+      time_squared: times.map(|time| time * time).sum(),
+      analysed,
+      agreement,
+    }
+  }
+
+  fn add(&mut self, other: &Self) {
+    self.moves += other.moves;
+    self.time += other.time;
+    self.time_squared += other.time_squared;
+    self.analysed += other.analysed;
+    self.agreement += other.agreement;
+  }
+
+  fn mean(&self) -> f64 {
+    self.time / self.moves.max(1) as f64
+  }
+
+  fn deviation(&self) -> f64 {
+    let mean = self.mean();
+    (self.time_squared / self.moves.max(1) as f64 - mean * mean)
+      .max(0.0)
+      .sqrt()
+  }
+
+  // The fraction of analysed moves that matched the engine
+  fn agreement(&self) -> Option<f64> {
+    (self.analysed > 0).then(|| self.agreement as f64 / self.analysed as f64)
+  }
+
+  // The reasons the player's moves look suspicious
+  fn flags(&self) -> Vec<&'static str> {
+    let mut flags = Vec::new();
+    if self.moves < MIN_MOVES {
+      return flags;
+    }
+    if self
+      .agreement()
+      .is_some_and(|agreement| agreement > AGREEMENT_THRESHOLD)
+    {
+      flags.push("high engine agreement");
+    }
+    if self.deviation() < self.mean() * CONSISTENCY_THRESHOLD {
+      flags.push("consistent move times");
+    }
+    flags
+  }
+
+  fn show(&self) -> String {
+    let mut result = format!(
+      "{} moves, move time {:.0}ms mean {:.0}ms deviation",
+      self.moves,
+      self.mean(),
+      self.deviation()
+    );
+    if let Some(agreement) = self.agreement() {
+      write!(result, ", {:.1}% engine agreement", agreement * 100.0).ok();
+    }
+    result
+  }
+
+  // One line of the totals file, the name goes last as it can contain anything
+  fn save(&self, name: &str) -> String {
+    format!(
+      "{};{};{};{};{};{name}",
+      self.moves, self.time, self.time_squared, self.analysed, self.agreement
+    )
+  }
+
+  fn load(line: &str) -> Option<(String, Self)> {
+    let mut fields = line.splitn(6, ';');
+    let stats = Self {
+      moves: fields.next()?.parse().ok()?,
+      time: fields.next()?.parse().ok()?,
+      time_squared: fields.next()?.parse().ok()?,
+      analysed: fields.next()?.parse().ok()?,
+      agreement: fields.next()?.parse().ok()?,
+    };
+    Some((fields.next()?.to_owned(), stats))
+  }
+}
+
+// The move the engine would play in a position
+fn analyse(engine: &(Sender<Request>, Receiver<UlciResult>), fen: &str) -> Option<Move> {
+  let (tx, rx) = engine;
+  tx.send(Request::Analysis(AnalysisRequest {
+    fen: fen.to_owned(),
+    moves: Vec::new(),
+    time: SearchTime::Other(Limits {
+      depth: u8::MAX,
+      nodes: ANALYSIS_NODES,
+      time: Millis::MAX,
+    }),
+    searchmoves: Vec::new(),
+    priority: Vec::new(),
+    new_game: false,
+  }))
+  .ok()?;
+  loop {
+    if let UlciResult::AnalysisStopped(mv) = rx.recv().ok()? {
+      return Some(mv);
+    }
+  }
+}
+
+/// Write a report for each game, flagging suspicious players for admins
+///
+/// Each player's totals are kept by name in a file between runs, so players are judged on all
+/// their games rather than just the latest one.
+///
+/// Blocks the thread it runs on, should be spawned in a new thread
+pub fn report_games(
+  engine: Option<(Sender<Request>, Receiver<UlciResult>)>,
+  games: &Receiver<GameRecord>,
+) {
+  let mut totals: HashMap<String, PlayerStats> = read_to_string(TOTALS_FILE)
+    .map(|file| file.lines().filter_map(PlayerStats::load).collect())
+    .unwrap_or_default();
+  for game in games {
+    let mut report = format!("Game from {}\n", game.fen);
+    let mut flagged = false;
+    for (name, moves) in game.names.iter().zip(&game.moves) {
+      let stats = PlayerStats::new(moves, engine.as_ref());
+      let total = totals.entry(name.clone()).or_default();
+      total.add(&stats);
+      writeln!(report, "{name}: {}", stats.show()).ok();
+      writeln!(report, "{name} in all games: {}", total.show()).ok();
+      let flags = total.flags();
+      if !flags.is_empty() {
+        flagged = true;
+        writeln!(report, "FLAGGED {name}: {}", flags.join(", ")).ok();
+      }
+    }
+    print!("{report}");
+    if flagged {
+      println!("Suspicious game, see {REPORT_FILE}");
+    }
+    let file = OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(REPORT_FILE);
+    match file {
+      Ok(mut file) => {
+        writeln!(file, "{report}").ok();
+      }
+      Err(_) => println!("Failed to write to {REPORT_FILE}"),
+    }
+    let lines: Vec<String> = totals
+      .iter()
+      .map(|(name, stats)| stats.save(name))
+      .collect();
+    if write(TOTALS_FILE, lines.join("\n") + "\n").is_err() {
+      println!("Failed to write to {TOTALS_FILE}");
+    }
+  }
+}
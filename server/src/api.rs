@@ -0,0 +1,289 @@
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Result, Write};
+use std::iter::Peekable;
+use std::net::TcpStream;
+use std::str::Chars;
+
+/// A parsed JSON value
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+  Null,
+  Bool(bool),
+  Number(f64),
+  String(String),
+  Array(Vec<Json>),
+  Object(Vec<(String, Json)>),
+}
+
+impl Json {
+  /// Parse a complete JSON document
+  pub fn parse(input: &str) -> Option<Self> {
+    let mut chars = input.chars().peekable();
+    let value = parse_value(&mut chars)?;
+    skip_whitespace(&mut chars);
+    chars.next().is_none().then_some(value)
+  }
+
+  /// Get a field of an object
+  pub fn get(&self, key: &str) -> Option<&Self> {
+    match self {
+      Self::Object(fields) => fields
+        .iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      Self::String(string) => Some(string),
+      _ => None,
+    }
+  }
+
+  pub fn as_u128(&self) -> Option<u128> {
+    match self {
+      Self::Number(number) if *number >= 0.0 => Some(*number as u128),
+      _ => None,
+    }
+  }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+  while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+fn parse_literal(chars: &mut Peekable<Chars>, literal: &str, value: Json) -> Option<Json> {
+  for expected in literal.chars() {
+    if chars.next()? != expected {
+      return None;
+    }
+  }
+  Some(value)
+}
+
+// parse the 4 hex digits of a `\u` escape
+fn parse_hex(chars: &mut Peekable<Chars>) -> Option<u32> {
+  let code: String = (0..4).map(|_| chars.next()).collect::<Option<_>>()?;
+  u32::from_str_radix(&code, 16).ok()
+}
+
+// parse the rest of a string after the opening quote
+fn parse_string(chars: &mut Peekable<Chars>) -> Option<String> {
+  let mut result = String::new();
+  loop {
+    match chars.next()? {
+      '"' => return Some(result),
+      '\\' => match chars.next()? {
+        'b' => result.push('\u{8}'),
+        'f' => result.push('\u{c}'),
+        'n' => result.push('\n'),
+        'r' => result.push('\r'),
+        't' => result.push('\t'),
+        'u' => {
+          let mut code = parse_hex(chars)?;
+          // characters outside the basic plane are sent as a surrogate pair
+          if (0xD800..0xDC00).contains(&code) {
+            let mut low = chars.clone();
+            if low.next() == Some('\\') && low.next() == Some('u') {
+              let low_code = parse_hex(&mut low)?;
+              if (0xDC00..0xE000).contains(&low_code) {
+                code = 0x10000 + ((code - 0xD800) << 10) + (low_code - 0xDC00);
+                *chars = low;
+              }
+            }
+          }
+          result.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+        c => result.push(c),
+      },
+      c => result.push(c),
+    }
+  }
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Option<Json> {
+  skip_whitespace(chars);
+  match chars.peek()? {
+    '{' => {
+      chars.next();
+      let mut fields = Vec::new();
+      skip_whitespace(chars);
+      if chars.next_if_eq(&'}').is_some() {
+        return Some(Json::Object(fields));
+      }
+      loop {
+        skip_whitespace(chars);
+        if chars.next()? != '"' {
+          return None;
+        }
+        let key = parse_string(chars)?;
+        skip_whitespace(chars);
+        if chars.next()? != ':' {
+          return None;
+        }
+        fields.push((key, parse_value(chars)?));
+        skip_whitespace(chars);
+        match chars.next()? {
+          ',' => (),
+          '}' => return Some(Json::Object(fields)),
+          _ => return None,
+        }
+      }
+    }
+    '[' => {
+      chars.next();
+      let mut values = Vec::new();
+      skip_whitespace(chars);
+      if chars.next_if_eq(&']').is_some() {
+        return Some(Json::Array(values));
+      }
+      loop {
+        values.push(parse_value(chars)?);
+        skip_whitespace(chars);
+        match chars.next()? {
+          ',' => (),
+          ']' => return Some(Json::Array(values)),
+          _ => return None,
+        }
+      }
+    }
+    '"' => {
+      chars.next();
+      parse_string(chars).map(Json::String)
+    }
+    't' => parse_literal(chars, "true", Json::Bool(true)),
+    'f' => parse_literal(chars, "false", Json::Bool(false)),
+    'n' => parse_literal(chars, "null", Json::Null),
+    _ => {
+      let mut number = String::new();
+      while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || "+-.eE".contains(*c)) {
+        number.push(c);
+      }
+      number.parse().ok().map(Json::Number)
+    }
+  }
+}
+
+// Decodes a body sent with chunked transfer encoding
+struct Chunked<R> {
+  inner: R,
+  remaining: usize,
+  done: bool,
+}
+
+impl<R: BufRead> Read for Chunked<R> {
+  fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+    if self.done {
+      return Ok(0);
+    }
+    if self.remaining == 0 {
+      let mut line = String::new();
+      self.inner.read_line(&mut line)?;
+      let size = line.trim().split(';').next().unwrap_or_default();
+      self.remaining = usize::from_str_radix(size, 16)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid chunk size"))?;
+      if self.remaining == 0 {
+        self.done = true;
+        return Ok(0);
+      }
+    }
+    let max = buf.len().min(self.remaining);
+    let read = self.inner.read(&mut buf[..max])?;
+    self.remaining -= read;
+    if self.remaining == 0 {
+      // each chunk ends with a line break
+      self.inner.read_line(&mut String::new())?;
+    }
+    Ok(read)
+  }
+}
+
+/// A client for a bot API over plain HTTP
+///
+/// Use a local TLS proxy to connect to servers that require HTTPS
+pub struct Api {
+  host: String,
+  token: String,
+}
+
+impl Api {
+  pub fn new(host: String, token: String) -> Self {
+    Self { host, token }
+  }
+
+  pub fn host(&self) -> &str {
+    &self.host
+  }
+
+  fn send(&self, method: &str, path: &str) -> Result<(u16, Box<dyn BufRead + Send>)> {
+    let mut stream = TcpStream::connect(&self.host)?;
+    write!(
+      stream,
+      "{method} {path} HTTP/1.1\r\nHost: {}\r\nAuthorization: Bearer {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+      self.host, self.token
+    )?;
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let status = line
+      .split_whitespace()
+      .nth(1)
+      .and_then(|status| status.parse().ok())
+      .ok_or_else(|| Error::new(ErrorKind::InvalidData, "invalid status line"))?;
+    let mut chunked = false;
+    loop {
+      line.clear();
+      if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+        break;
+      }
+      if let Some((name, value)) = line.split_once(':') {
+        if name.trim().eq_ignore_ascii_case("transfer-encoding")
+          && value.trim().eq_ignore_ascii_case("chunked")
+        {
+          chunked = true;
+        }
+      }
+    }
+    let body: Box<dyn BufRead + Send> = if chunked {
+      Box::new(BufReader::new(Chunked {
+        inner: reader,
+        remaining: 0,
+        done: false,
+      }))
+    } else {
+      Box::new(reader)
+    };
+    Ok((status, body))
+  }
+
+  /// Make a POST request, returns whether it succeeded
+  pub fn post(&self, path: &str) -> bool {
+    self
+      .send("POST", path)
+      .is_ok_and(|(status, _)| (200..300).contains(&status))
+  }
+
+  /// Get a single JSON document
+  pub fn get(&self, path: &str) -> Option<Json> {
+    let (status, mut body) = self.send("GET", path).ok()?;
+    if !(200..300).contains(&status) {
+      return None;
+    }
+    let mut text = String::new();
+    body.read_to_string(&mut text).ok()?;
+    Json::parse(&text)
+  }
+
+  /// Stream newline delimited JSON until the connection closes
+  pub fn stream(&self, path: &str) -> Option<impl Iterator<Item = Json>> {
+    let (status, body) = self.send("GET", path).ok()?;
+    // blank keep-alive lines don't parse and are skipped
+    (200..300).contains(&status).then(|| {
+      body
+        .lines()
+        .map_while(|line| line.ok())
+        .filter_map(|line| Json::parse(&line))
+    })
+  }
+}
@@ -0,0 +1,238 @@
+use liberty_chess::clock::Millis;
+use liberty_chess::moves::Move;
+use liberty_chess::positions::STARTPOS;
+use liberty_chess::Board;
+use server::api::{Api, Json};
+use std::env::var;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{sleep, spawn};
+use std::time::Duration;
+use ulci::load_engine;
+use ulci::server::{AnalysisRequest, Request, UlciResult};
+use ulci::{Limits, SearchTime};
+
+// Used when the environment variables aren't set
+const DEFAULT_HOST: &str = "127.0.0.1:8080";
+const DEFAULT_ENGINE: &str = "oxidation";
+const DEFAULT_PREFIX: &str = "/api";
+
+// Used when the server doesn't send the clock
+const DEFAULT_MOVETIME: Millis = Millis::new(5000);
+
+// Games played at once, further challenges are declined
+const MAX_GAMES: usize = 1;
+
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+// Variants where the server's rules match ours
+const VARIANTS: &[&str] = &["standard", "fromPosition"];
+
+// The endpoints, laid out like the lichess bot API under a configurable prefix
+struct Endpoints {
+  prefix: String,
+}
+
+impl Endpoints {
+  fn account(&self) -> String {
+    format!("{}/account", self.prefix)
+  }
+
+  fn events(&self) -> String {
+    format!("{}/stream/event", self.prefix)
+  }
+
+  fn accept_challenge(&self, id: &str) -> String {
+    format!("{}/challenge/{id}/accept", self.prefix)
+  }
+
+  fn decline_challenge(&self, id: &str) -> String {
+    format!("{}/challenge/{id}/decline", self.prefix)
+  }
+
+  fn game_stream(&self, id: &str) -> String {
+    format!("{}/bot/game/stream/{id}", self.prefix)
+  }
+
+  fn make_move(&self, id: &str, mv: Move) -> String {
+    format!("{}/bot/game/{id}/move/{}", self.prefix, mv.to_string())
+  }
+}
+
+// Games are counted from when the challenge is accepted so a burst of challenges can't go over
+fn handle_challenge(
+  api: &Api,
+  endpoints: &Endpoints,
+  event: &Json,
+  name: &str,
+  games: &AtomicUsize,
+) -> Option<()> {
+  let challenge = event.get("challenge")?;
+  let id = challenge.get("id")?.as_str()?;
+  // challenges sent by the bot also show up
+  let challenger = challenge.get("challenger")?.get("id")?.as_str()?;
+  if challenger.eq_ignore_ascii_case(name) {
+    return Some(());
+  }
+  let variant = challenge.get("variant")?.get("key")?.as_str()?;
+  if !VARIANTS.contains(&variant) {
+    println!("Declining {id} from {challenger}: {variant} is not supported");
+    api.post(&endpoints.decline_challenge(id));
+  } else if games
+    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |games| {
+      (games < MAX_GAMES).then_some(games + 1)
+    })
+    .is_err()
+  {
+    println!("Declining {id} from {challenger}: already playing");
+    api.post(&endpoints.decline_challenge(id));
+  } else if api.post(&endpoints.accept_challenge(id)) {
+    println!("Accepted {id} from {challenger}");
+  } else {
+    games.fetch_sub(1, Ordering::SeqCst);
+  }
+  Some(())
+}
+
+// Work out the time control to search with from a game state
+fn search_time(state: &Json) -> Option<SearchTime> {
+  let millis = |key: &str| state.get(key)?.as_u128().map(Millis::new);
+  Some(SearchTime::Asymmetric(
+    millis("wtime")?,
+    millis("winc")?,
+    millis("btime")?,
+    millis("binc")?,
+  ))
+}
+
+fn play_game(
+  api: &Api,
+  endpoints: &Endpoints,
+  id: &str,
+  name: &str,
+  engine: &'static str,
+) -> Option<()> {
+  let (tx, rx) = load_engine(engine);
+  let mut fen = STARTPOS.to_owned();
+  let mut colour = None;
+  let mut new_game = true;
+  for event in api.stream(&endpoints.game_stream(id))? {
+    let state = match event.get("type").and_then(Json::as_str) {
+      Some("gameFull") => {
+        fen = match event.get("initialFen").and_then(Json::as_str) {
+          Some("startpos") | None => STARTPOS.to_owned(),
+          Some(initial) => initial.to_owned(),
+        };
+        let white = event.get("white")?.get("id")?.as_str()?;
+        colour = Some(white.eq_ignore_ascii_case(name));
+        event.get("state")?
+      }
+      Some("gameState") => &event,
+      _ => continue,
+    };
+    if state.get("status").and_then(Json::as_str) != Some("started") {
+      break;
+    }
+    let mut board = Board::new(&fen).ok()?;
+    let mut moves = Vec::new();
+    for mv in state.get("moves")?.as_str()?.split_whitespace() {
+      let mv = mv.parse().ok()?;
+      board = board.move_if_legal(mv)?;
+      moves.push(mv);
+    }
+    if Some(board.to_move()) != colour {
+      continue;
+    }
+    tx.send(Request::Analysis(AnalysisRequest {
+      fen: fen.clone(),
+      moves,
+      time: search_time(state).unwrap_or(SearchTime::Other(Limits {
+        time: DEFAULT_MOVETIME,
+        ..Limits::default()
+      })),
+      searchmoves: Vec::new(),
+      priority: Vec::new(),
+      new_game,
+    }))
+    .ok()?;
+    new_game = false;
+    let bestmove = loop {
+      if let UlciResult::AnalysisStopped(mv) = rx.recv().ok()? {
+        break mv;
+      }
+    };
+    if !api.post(&endpoints.make_move(id, bestmove)) {
+      println!("Failed to play {} in {id}", bestmove.to_string());
+    }
+  }
+  Some(())
+}
+
+fn main() {
+  let host = var("BRIDGE_HOST").unwrap_or_else(|_| DEFAULT_HOST.to_owned());
+  let Ok(token) = var("BRIDGE_TOKEN") else {
+    println!("Set BRIDGE_TOKEN to the API token of the bot account");
+    return;
+  };
+  let engine: &'static str = Box::leak(
+    var("BRIDGE_ENGINE")
+      .unwrap_or_else(|_| DEFAULT_ENGINE.to_owned())
+      .into_boxed_str(),
+  );
+  let endpoints = Arc::new(Endpoints {
+    prefix: var("BRIDGE_PREFIX").unwrap_or_else(|_| DEFAULT_PREFIX.to_owned()),
+  });
+  let api = Arc::new(Api::new(host, token));
+  let Some(name) = api
+    .get(&endpoints.account())
+    .and_then(|account| Some(account.get("id")?.as_str()?.to_owned()))
+  else {
+    println!("Could not get the account details from {}", api.host());
+    return;
+  };
+  println!("Connected to {} as {name}", api.host());
+  let games = Arc::new(AtomicUsize::new(0));
+  loop {
+    if let Some(events) = api.stream(&endpoints.events()) {
+      for event in events {
+        match event.get("type").and_then(Json::as_str) {
+          Some("challenge") => {
+            handle_challenge(&api, &endpoints, &event, &name, &games);
+          }
+          Some("gameStart") => {
+            let Some(id) = event
+              .get("game")
+              .and_then(|game| game.get("gameId").or_else(|| game.get("id")))
+              .and_then(Json::as_str)
+            else {
+              continue;
+            };
+            let (api, endpoints, id, name, games) = (
+              api.clone(),
+              endpoints.clone(),
+              id.to_owned(),
+              name.clone(),
+              games.clone(),
+            );
+            spawn(move || {
+              println!("Starting game {id}");
+              if play_game(&api, &endpoints, &id, &name, engine).is_none() {
+                println!("Lost track of game {id}");
+              }
+              // games not started from an accepted challenge were never counted
+              games
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |games| {
+                  games.checked_sub(1)
+                })
+                .ok();
+              println!("Finished game {id}");
+            });
+          }
+          _ => (),
+        }
+      }
+    }
+    println!("Lost connection to the event stream, reconnecting");
+    sleep(RETRY_DELAY);
+  }
+}
@@ -0,0 +1,302 @@
+use crate::anticheat::MoveRecord;
+use crate::ConnectionInfo;
+use liberty_chess::clock::Clock;
+use liberty_chess::moves::Move;
+use liberty_chess::{Board, Gamestate};
+use std::fmt::{Display, Formatter};
+use std::mem::take;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use ulci::server::{AnalysisRequest, Request, UlciResult};
+use ulci::SearchTime;
+
+/// How a managed game ended, `true` for white
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GameEnd {
+  /// The game ended on the board
+  Board(Gamestate),
+  /// A player ran out of time
+  Flagged(bool),
+  /// A player sent an illegal move
+  IllegalMove(bool),
+  /// A player disconnected
+  Disconnected(bool),
+}
+
+/// The result of a managed game
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct GameResult {
+  /// The winner, `true` for white, `None` for a draw
+  pub winner: Option<bool>,
+  /// How the game ended
+  pub end: GameEnd,
+}
+
+impl GameResult {
+  /// Adjudicate a game that ended in a position
+  ///
+  /// Players who flag or disconnect only lose if their opponent could still win.
+  #[must_use]
+  pub fn adjudicate(end: GameEnd, position: &Board) -> Self {
+    let winner = match end {
+      GameEnd::Board(Gamestate::Checkmate(winner) | Gamestate::Elimination(winner)) => Some(winner),
+      GameEnd::Board(_) => None,
+      GameEnd::IllegalMove(side) => Some(!side),
+      GameEnd::Flagged(side) | GameEnd::Disconnected(side) => {
+        position.has_mating_material(!side).then_some(!side)
+      }
+    };
+    Self { winner, end }
+  }
+}
+
+fn side_name(side: bool) -> &'static str {
+  if side {
+    "White"
+  } else {
+    "Black"
+  }
+}
+
+impl Display for GameResult {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    let result = match self.winner {
+      Some(winner) => format!("{} wins", side_name(winner)),
+      None => "Draw".to_owned(),
+    };
+    let reason = match self.end {
+      GameEnd::Board(Gamestate::Checkmate(_)) => "checkmate".to_owned(),
+      GameEnd::Board(Gamestate::Elimination(_)) => "elimination".to_owned(),
+      GameEnd::Board(Gamestate::Stalemate) => "stalemate".to_owned(),
+      GameEnd::Board(Gamestate::Repetition) => "repetition".to_owned(),
+      GameEnd::Board(Gamestate::FiftyMove) => "the 50 move rule".to_owned(),
+      GameEnd::Board(Gamestate::Material | Gamestate::InProgress) => {
+        "insufficient material".to_owned()
+      }
+      GameEnd::Flagged(side) => format!("{} running out of time", side_name(side)),
+      GameEnd::IllegalMove(side) => format!("{} playing an illegal move", side_name(side)),
+      GameEnd::Disconnected(side) => format!("{} disconnecting", side_name(side)),
+    };
+    write!(f, "{result} by {reason}")
+  }
+}
+
+/// Lets the host of a managed game step in while it is played
+pub trait GameHooks {
+  /// Called before each move is asked for, so the clock can be adjusted
+  fn before_move(&mut self, _clock: &mut Clock) {}
+
+  /// A player disconnected on their move, `true` for white
+  ///
+  /// Their clock is stopped while this waits. Returns a new connection to carry on the game with,
+  /// or `None` to end it.
+  fn reconnect(
+    &mut self,
+    _side: bool,
+    _opponent: &Sender<Request>,
+  ) -> Option<(Sender<Request>, Receiver<UlciResult>)> {
+    None
+  }
+}
+
+/// Games without a host to step in end when a player disconnects
+impl GameHooks for () {}
+
+// send a request to every spectator, dropping the ones that have disconnected
+fn broadcast(spectators: &mut Vec<Sender<Request>>, request: &Request) {
+  spectators.retain(|spectator| spectator.send(request.clone()).is_ok());
+}
+
+/// Plays a game between two connected clients, managing the clock and the result
+pub struct GameManager {
+  // white first
+  players: [ConnectionInfo; 2],
+  spectators: Vec<Sender<Request>>,
+  clock: Clock,
+  // the position after the last irreversible move, and the moves played since
+  base_position: Board,
+  moves: Vec<Move>,
+  position: Board,
+  // the moves played by each side, white first
+  records: [Vec<MoveRecord>; 2],
+}
+
+impl GameManager {
+  /// Pair two clients for a game from a position
+  ///
+  /// The clock should have the same side to move as the position.
+  #[must_use]
+  pub fn new(white: ConnectionInfo, black: ConnectionInfo, position: Board, clock: Clock) -> Self {
+    Self {
+      players: [white, black],
+      spectators: Vec::new(),
+      clock,
+      base_position: position.clone(),
+      moves: Vec::new(),
+      position,
+      records: [Vec::new(), Vec::new()],
+    }
+  }
+
+  /// Add a client to be shown the game as it is played
+  pub fn add_spectator(&mut self, spectator: Sender<Request>) {
+    spectator.send(self.position_request()).ok();
+    self.spectators.push(spectator);
+  }
+
+  /// The current position of the game
+  #[must_use]
+  pub const fn position(&self) -> &Board {
+    &self.position
+  }
+
+  /// The moves played since the last irreversible move
+  #[must_use]
+  pub fn moves(&self) -> &[Move] {
+    &self.moves
+  }
+
+  /// The moves played by each side with the time they took, white first
+  pub fn take_records(&mut self) -> [Vec<MoveRecord>; 2] {
+    take(&mut self.records)
+  }
+
+  /// The clients playing the game, white first
+  #[must_use]
+  pub fn into_players(self) -> [ConnectionInfo; 2] {
+    self.players
+  }
+
+  fn position_request(&self) -> Request {
+    Request::Position(self.base_position.to_string(), self.moves.clone(), false)
+  }
+
+  // send a request to both players and the spectators
+  fn announce(&mut self, request: &Request) {
+    for (tx, _, _) in &self.players {
+      tx.send(request.clone()).ok();
+    }
+    broadcast(&mut self.spectators, request);
+  }
+
+  /// Play the game until it is over
+  ///
+  /// Blocks the thread it runs on until the game ends.
+  pub fn play(&mut self) -> GameResult {
+    self.play_with(&mut ())
+  }
+
+  /// Play the game until it is over, letting the host step in
+  ///
+  /// Blocks the thread it runs on until the game ends.
+  pub fn play_with(&mut self, hooks: &mut impl GameHooks) -> GameResult {
+    let request = self.position_request();
+    self.announce(&request);
+    if self.clock.is_paused() {
+      self.clock.toggle_pause();
+    }
+    let end = loop {
+      let state = self.position.state();
+      if state != Gamestate::InProgress {
+        break GameEnd::Board(state);
+      }
+      hooks.before_move(&mut self.clock);
+      if let Some(end) = self.play_move(hooks) {
+        break end;
+      }
+    };
+    if !self.clock.is_paused() {
+      self.clock.toggle_pause();
+    }
+    let result = GameResult::adjudicate(end, &self.position);
+    let request = self.position_request();
+    self.announce(&request);
+    self.announce(&Request::Notice(result.to_string()));
+    result
+  }
+
+  // ask the side to move for a move and play it, returning how the game ended if it did
+  fn play_move(&mut self, hooks: &mut impl GameHooks) -> Option<GameEnd> {
+    let side = self.position.to_move();
+    let time = SearchTime::from_clock(&mut self.clock);
+    let request = self.position_request();
+    broadcast(&mut self.spectators, &request);
+    broadcast(&mut self.spectators, &Request::Clock(time));
+    let player = usize::from(!side);
+    let request = AnalysisRequest {
+      fen: self.base_position.to_string(),
+      moves: self.moves.clone(),
+      time,
+      searchmoves: Vec::new(),
+      priority: Vec::new(),
+      new_game: false,
+    };
+    let mut sent = self.players[player]
+      .0
+      .send(Request::Analysis(request.clone()))
+      .is_ok();
+    loop {
+      if !sent {
+        // the player can come back and carry on from the current position,
+        // with their clock stopped while they are away
+        self.clock.update();
+        if self.clock.is_flagged() {
+          return Some(GameEnd::Flagged(side));
+        }
+        self.clock.toggle_pause();
+        let connection = hooks.reconnect(side, &self.players[usize::from(side)].0);
+        self.clock.toggle_pause();
+        let Some((tx, rx)) = connection else {
+          return Some(GameEnd::Disconnected(side));
+        };
+        let request = AnalysisRequest {
+          time: SearchTime::from_clock(&mut self.clock),
+          ..request.clone()
+        };
+        sent =
+          tx.send(self.position_request()).is_ok() && tx.send(Request::Analysis(request)).is_ok();
+        (self.players[player].0, self.players[player].1) = (tx, rx);
+        continue;
+      }
+      let (white_clock, black_clock) = self.clock.get_clocks();
+      let remaining = if side { white_clock } else { black_clock };
+      // wake up when the clock would run out to check for a flag
+      match self.players[player]
+        .1
+        .recv_timeout(remaining + self.clock.get_delay())
+      {
+        Ok(UlciResult::AnalysisStopped(mv)) => {
+          self.clock.update();
+          if self.clock.is_flagged() {
+            return Some(GameEnd::Flagged(side));
+          }
+          let Some(position) = self.position.move_if_legal(mv) else {
+            return Some(GameEnd::IllegalMove(side));
+          };
+          let fen = self.position.to_string();
+          if position.halfmoves() == 0 {
+            self.base_position = position.clone();
+            self.moves.clear();
+          } else {
+            self.moves.push(mv);
+          }
+          self.position = position;
+          let time = self.clock.switch_clocks();
+          self.records[player].push(MoveRecord { fen, mv, time });
+          return None;
+        }
+        Ok(UlciResult::Analysis(result)) => {
+          broadcast(&mut self.spectators, &Request::AnalysisResult(result));
+        }
+        Ok(_) => (),
+        Err(RecvTimeoutError::Timeout) => {
+          self.clock.update();
+          if self.clock.is_flagged() {
+            self.players[player].0.send(Request::StopAnalysis).ok();
+            return Some(GameEnd::Flagged(side));
+          }
+        }
+        Err(RecvTimeoutError::Disconnected) => sent = false,
+      }
+    }
+  }
+}
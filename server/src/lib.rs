@@ -3,28 +3,74 @@ use std::net::{TcpListener, TcpStream};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
 use std::thread::spawn;
-use ulci::server::{startup_server, Request, UlciResult};
+use std::time::Duration;
+use ulci::games::Games;
+use ulci::server::{startup_server, startup_server_with_keepalive, Keepalive, Request, UlciResult};
 use ulci::ClientInfo;
 
+pub mod anticheat;
+pub mod api;
+pub mod game;
+
+#[cfg(test)]
+mod tests;
+
 const PORT: u16 = 25565;
 
+// How often clients are pinged and how long they can stay silent before being disconnected
+const KEEPALIVE: Keepalive = Keepalive {
+  interval: Duration::from_secs(5),
+  timeout: Duration::from_secs(30),
+};
+
 pub type ConnectionInfo = (Sender<Request>, Receiver<UlciResult>, ClientInfo);
+/// A client that has connected, with a way to play more games with it if it supports them
+pub type Client = (ConnectionInfo, Option<GameHost>);
+
+/// Starts more games on a connection to a client that supports the `games` feature
+pub struct GameHost {
+  games: Games<TcpStream>,
+  last_game: u32,
+}
+
+impl GameHost {
+  /// Start another game on the connection, which can be played like a separate client
+  ///
+  /// Blocks until the client has set up the game, returns `None` if the connection has closed
+  pub fn open_game(&mut self) -> Option<ConnectionInfo> {
+    self.last_game += 1;
+    let (input, output) = self.games.open(self.last_game);
+    let (tx, rx) = channel();
+    let (tx_2, rx_2) = channel();
+    spawn(move || startup_server(rx, &tx_2, input, output, false, || ()));
+    wait_for_startup(tx, rx_2)
+  }
+}
+
+/// Wait for a client to finish starting up, returns `None` if it disconnects first
+pub fn wait_for_startup(tx: Sender<Request>, rx: Receiver<UlciResult>) -> Option<ConnectionInfo> {
+  loop {
+    if let UlciResult::Startup(info) = rx.recv().ok()? {
+      return Some((tx, rx, info));
+    }
+  }
+}
 
 fn run_client(
-  connections: &Arc<Sender<ConnectionInfo>>,
+  connections: &Arc<Sender<Client>>,
   tx: Sender<Request>,
   rx: Receiver<UlciResult>,
+  games: Games<TcpStream>,
 ) -> Option<()> {
-  while let Ok(message) = rx.recv() {
-    if let UlciResult::Startup(info) = message {
-      connections.send((tx, rx, info)).ok()?;
-      break;
-    }
-  }
-  Some(())
+  let connection = wait_for_startup(tx, rx)?;
+  let host = connection.2.features.games.then_some(GameHost {
+    games,
+    last_game: 0,
+  });
+  connections.send((connection, host)).ok()
 }
 
-fn handle_connection(stream: TcpStream, connections: Arc<Sender<ConnectionInfo>>) -> Option<()> {
+fn handle_connection(stream: TcpStream, connections: Arc<Sender<Client>>) -> Option<()> {
   let name = if let Ok(ip) = stream.peer_addr() {
     println!("{ip} Connected");
     ip.to_string()
@@ -32,18 +78,22 @@ fn handle_connection(stream: TcpStream, connections: Arc<Sender<ConnectionInfo>>
     println!("Unknown Connected");
     "Unknown".to_string()
   };
+  // reads stop blocking once the client has been silent for too long
+  stream.set_read_timeout(Some(KEEPALIVE.timeout)).ok()?;
   let stream_2 = stream.try_clone().ok()?;
+  // clients that support it can have more games on the same connection
+  let (games, input, output) = Games::split(BufReader::new(stream), stream_2, None);
   let (tx, rx) = channel();
   let (tx_2, rx_2) = channel();
   spawn(move || {
-    startup_server(rx, &tx_2, BufReader::new(stream), stream_2, false, || ());
+    startup_server_with_keepalive(rx, &tx_2, input, output, false, || (), KEEPALIVE);
     println!("{name} Disconnected");
   });
-  spawn(move || run_client(&connections, tx, rx_2));
+  spawn(move || run_client(&connections, tx, rx_2, games));
   Some(())
 }
 
-pub fn handle_connections(connections: Sender<ConnectionInfo>) {
+pub fn handle_connections(connections: Sender<Client>) {
   let connections = Arc::new(connections);
   let listener = TcpListener::bind(format!("0.0.0.0:{PORT}"))
     .unwrap_or_else(|_| panic!("Failed to bind to port {PORT}"));
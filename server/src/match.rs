@@ -3,16 +3,19 @@ use liberty_chess::positions::{
   AFRICAN, CAPABLANCA, CAPABLANCA_RECTANGLE, DOUBLE_CHESS, ELIMINATION, HORDE, LIBERTY_CHESS,
   LOADED_BOARD, MINI, MONGOL, NARNIA, STARTPOS, TRUMP,
 };
-use liberty_chess::{Board, Gamestate};
+use liberty_chess::Board;
 use rand::distributions::Alphanumeric;
 use rand::seq::SliceRandom;
 use rand::{thread_rng, Rng};
-use server::handle_connections;
+use server::anticheat::{report_games, GameRecord};
+use server::game::{GameEnd, GameHooks, GameManager};
+use server::{handle_connections, wait_for_startup, ConnectionInfo};
+use std::io::stdin;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::thread::{sleep, spawn};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use ulci::server::{AnalysisRequest, Request, UlciResult};
-use ulci::{load_engine, OptionValue, SearchTime};
+use ulci::{load_engine, AnalysisResult, OptionValue, SearchTime};
 
 /// The test positions for the match
 const POSITIONS: &[&str] = &[
@@ -34,18 +37,139 @@ const POSITIONS: &[&str] = &[
 
 const FRIENDLY_FIRE_CHANCE: f64 = 0.5;
 
+/// Names for the players in the order of their passwords, which anti-cheat totals are kept under
+///
+/// Players without a name set here go by the name their client reports
+const PLAYER_NAMES: [Option<&str>; 2] = [None, None];
+/// How long a player has to reconnect before losing the game, can be changed with `grace`
+const GRACE_PERIOD: Duration = Duration::from_secs(60);
+
 const GAME_LIMIT: usize = 10;
 
 const WHITE_ENGINE: Option<&str> = None;
 const BLACK_ENGINE: Option<&str> = None;
 
 const KIBBUTZ_ENGINE: Option<&str> = None;
+/// The engine used to check agreement with the players' moves for anti-cheat reports
+const ANTICHEAT_ENGINE: Option<&str> = None;
 const HASH_SIZE: usize = 1024;
 
+/// A player connecting again, with the name of the player
+type Reconnection = (String, Sender<Request>, Receiver<UlciResult>);
+
 enum SpectatorMessage {
   Request(Request),
   Spectator(Sender<Request>),
-  Kibbutz(UlciResult),
+  // analysis from the players
+  Analysis(AnalysisResult),
+  // analysis from the server's engine
+  Kibbutz(AnalysisResult),
+}
+
+/// Analysis engines attached to games, which only report their results to spectators
+struct KibbutzPool {
+  path: Option<&'static str>,
+  idle: Vec<Sender<Request>>,
+  spectators: Sender<SpectatorMessage>,
+}
+
+impl KibbutzPool {
+  fn new(path: Option<&'static str>, spectators: Sender<SpectatorMessage>) -> Self {
+    Self {
+      path,
+      idle: Vec::new(),
+      spectators,
+    }
+  }
+
+  /// Get an idle engine, loading a new one if none are available
+  fn acquire(&mut self) -> Option<Sender<Request>> {
+    if let Some(engine) = self.idle.pop() {
+      return Some(engine);
+    }
+    let (tx, rx) = load_engine(self.path?);
+    tx.send(Request::SetOption(
+      "Hash".to_owned(),
+      OptionValue::UpdateInt(HASH_SIZE),
+    ))
+    .ok()?;
+    let spectators = self.spectators.clone();
+    spawn(move || {
+      for message in rx {
+        if let UlciResult::Analysis(result) = message {
+          if spectators.send(SpectatorMessage::Kibbutz(result)).is_err() {
+            break;
+          }
+        }
+      }
+    });
+    Some(tx)
+  }
+
+  /// Stop the engine's analysis and make it available for another game
+  fn release(&mut self, engine: Sender<Request>) {
+    // engines that have crashed are dropped
+    if engine.send(Request::StopAnalysis).is_ok() {
+      self.idle.push(engine);
+    }
+  }
+}
+
+/// An adjustment made by the server admin, `true` for white
+enum AdminCommand {
+  AddTime(bool, Duration),
+  SetIncrement(bool, Duration),
+  /// How long players have to reconnect, from the next game
+  SetGracePeriod(Duration),
+}
+
+fn parse_command(line: &str) -> Option<AdminCommand> {
+  let mut words = line.split_whitespace();
+  let command = words.next()?;
+  if command == "grace" {
+    let time = Duration::from_secs(words.next()?.parse().ok()?);
+    return Some(AdminCommand::SetGracePeriod(time));
+  }
+  let side = match words.next()? {
+    "white" | "w" => true,
+    "black" | "b" => false,
+    _ => return None,
+  };
+  let time = Duration::from_secs(words.next()?.parse().ok()?);
+  match command {
+    "addtime" => Some(AdminCommand::AddTime(side, time)),
+    "increment" => Some(AdminCommand::SetIncrement(side, time)),
+    _ => None,
+  }
+}
+
+/// Read adjustments from stdin, e.g. `addtime white 60`, `increment black 5` or `grace 120`
+fn read_commands(commands: &Sender<AdminCommand>) {
+  for line in stdin().lines().map_while(Result::ok) {
+    match parse_command(&line) {
+      Some(command) => {
+        if commands.send(command).is_err() {
+          break;
+        }
+      }
+      None => println!("Usage: addtime|increment white|black <seconds> or grace <seconds>"),
+    }
+  }
+}
+
+/// Apply any pending adjustments, clock changes take effect from the next move
+fn apply_commands(
+  clock: &mut Clock,
+  grace_period: &mut Duration,
+  commands: &Receiver<AdminCommand>,
+) {
+  for command in commands.try_iter() {
+    match command {
+      AdminCommand::AddTime(side, time) => clock.add_time(side, time),
+      AdminCommand::SetIncrement(side, increment) => clock.set_increment(side, increment),
+      AdminCommand::SetGracePeriod(time) => *grace_period = time,
+    }
+  }
 }
 
 fn process_spectators(mut spectators: Vec<Sender<Request>>, messages: &Receiver<SpectatorMessage>) {
@@ -62,25 +186,118 @@ fn process_spectators(mut spectators: Vec<Sender<Request>>, messages: &Receiver<
         }
         spectators.push(spectator);
       }
+      SpectatorMessage::Analysis(result) => {
+        spectators.retain(|spectator| {
+          spectator
+            .send(Request::AnalysisResult(result.clone()))
+            .is_ok()
+        });
+      }
       SpectatorMessage::Kibbutz(result) => {
-        if let UlciResult::Analysis(result) = result {
-          spectators.retain(|spectator| {
-            spectator
-              .send(Request::AnalysisResult(result.clone()))
-              .is_ok()
-          });
-        }
+        spectators.retain(|spectator| spectator.send(Request::Kibbutz(result.clone())).is_ok());
       }
     }
   }
 }
 
+/// Give a player who disconnected the grace period to reconnect, keeping their opponent informed
+fn await_reconnection(
+  name: &str,
+  opponent: &Sender<Request>,
+  reconnections: &Receiver<Reconnection>,
+  grace_period: Duration,
+) -> Option<(Sender<Request>, Receiver<UlciResult>)> {
+  println!("{name} disconnected");
+  opponent
+    .send(Request::Notice(format!(
+      "{name} disconnected, waiting {}s for them to reconnect",
+      grace_period.as_secs()
+    )))
+    .ok();
+  let deadline = Instant::now() + grace_period;
+  loop {
+    let (player, tx, rx) = reconnections
+      .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+      .ok()?;
+    if player == name {
+      println!("{name} reconnected");
+      opponent
+        .send(Request::Notice(format!("{name} reconnected")))
+        .ok();
+      return Some((tx, rx));
+    }
+  }
+}
+
+/// The match's part in each game, white first
+struct MatchHooks<'a> {
+  names: &'a [String; 2],
+  commands: &'a Receiver<AdminCommand>,
+  reconnections: &'a Receiver<Reconnection>,
+  grace_period: &'a mut Duration,
+  // changes to the grace period apply from the next game
+  game_grace_period: Duration,
+}
+
+impl GameHooks for MatchHooks<'_> {
+  fn before_move(&mut self, clock: &mut Clock) {
+    apply_commands(clock, self.grace_period, self.commands);
+  }
+
+  fn reconnect(
+    &mut self,
+    side: bool,
+    opponent: &Sender<Request>,
+  ) -> Option<(Sender<Request>, Receiver<UlciResult>)> {
+    await_reconnection(
+      &self.names[usize::from(!side)],
+      opponent,
+      self.reconnections,
+      self.game_grace_period,
+    )
+  }
+}
+
+/// Pass the game on to the spectators, and have the kibbutz engine analyse each position
+fn forward_game(
+  game: &Receiver<Request>,
+  spectators: &Sender<SpectatorMessage>,
+  kibbutz: Option<&Sender<Request>>,
+) {
+  for request in game {
+    if let (Request::Position(fen, moves, _), Some(kibbutz)) = (&request, kibbutz) {
+      kibbutz.send(Request::StopAnalysis).ok();
+      kibbutz
+        .send(Request::Analysis(AnalysisRequest {
+          fen: fen.clone(),
+          moves: moves.clone(),
+          searchmoves: Vec::new(),
+          priority: Vec::new(),
+          time: SearchTime::Infinite,
+          new_game: false,
+        }))
+        .ok();
+    }
+    let message = match request {
+      Request::AnalysisResult(result) => SpectatorMessage::Analysis(result),
+      request => SpectatorMessage::Request(request),
+    };
+    spectators.send(message).ok();
+  }
+}
+
 fn run_match(
-  (mut tx_1, mut rx_1): (Sender<Request>, Receiver<UlciResult>),
-  (mut tx_2, mut rx_2): (Sender<Request>, Receiver<UlciResult>),
+  player_1: ConnectionInfo,
+  player_2: ConnectionInfo,
   spectators: &Sender<SpectatorMessage>,
-  kibbutz_tx: &Option<Sender<Request>>,
-) -> Option<()> {
+  kibbutz: &mut KibbutzPool,
+  commands: &Receiver<AdminCommand>,
+  reconnections: &Receiver<Reconnection>,
+  reports: &Sender<GameRecord>,
+) {
+  let mut names = player_names(&player_1, &player_2);
+  let mut players = [player_1, player_2];
+  let mut grace_period = GRACE_PERIOD;
   for _ in 0..GAME_LIMIT {
     let fen = POSITIONS
       .choose(&mut thread_rng())
@@ -89,169 +306,71 @@ fn run_match(
     if thread_rng().gen_bool(FRIENDLY_FIRE_CHANCE) {
       position.friendly_fire = true;
     }
-    let mut base_position = position.clone();
-    let mut moves = Vec::new();
-    tx_1
-      .send(Request::Position(
-        base_position.to_string(),
-        moves.clone(),
-        false,
-      ))
-      .ok()?;
-    tx_2
-      .send(Request::Position(
-        base_position.to_string(),
-        moves.clone(),
-        false,
-      ))
-      .ok()?;
-    let mut clock = Clock::new_symmetric(
+    let clock = Clock::new_symmetric(
       Duration::from_secs(1200),
       Duration::from_secs(15),
       position.to_move(),
     );
-    clock.toggle_pause();
-    while position.state() == Gamestate::InProgress {
-      tx_1
-        .send(Request::Analysis(AnalysisRequest {
-          fen: base_position.to_string(),
-          moves: moves.clone(),
-          time: SearchTime::from_clock(&mut clock),
-          searchmoves: Vec::new(),
-          new_game: false,
-        }))
-        .ok()?;
-      spectators
-        .send(SpectatorMessage::Request(Request::Position(
-          base_position.to_string(),
-          moves.clone(),
-          false,
-        )))
-        .ok();
-      spectators
-        .send(SpectatorMessage::Request(Request::Clock(
-          SearchTime::from_clock(&mut clock),
-        )))
-        .ok();
-      if let Some(ref kibbutz) = kibbutz_tx {
-        kibbutz.send(Request::StopAnalysis).ok();
-        kibbutz
-          .send(Request::Analysis(AnalysisRequest {
-            fen: base_position.to_string(),
-            moves: moves.clone(),
-            searchmoves: Vec::new(),
-            time: SearchTime::Infinite,
-            new_game: false,
-          }))
-          .ok();
-      }
-      loop {
-        match rx_1.recv().ok()? {
-          UlciResult::AnalysisStopped(mv) => {
-            if let Some(board) = position.move_if_legal(mv) {
-              if board.halfmoves() == 0 {
-                base_position = position;
-                moves.clear();
-              }
-              position = board;
-              moves.push(mv);
-              clock.switch_clocks();
-            }
-            break;
-          }
-          UlciResult::Analysis(result) => {
-            spectators
-              .send(SpectatorMessage::Kibbutz(UlciResult::Analysis(result)))
-              .ok();
-          }
-          _ => (),
-        }
-      }
-      if position.state() != Gamestate::InProgress {
-        break;
-      }
-      tx_2
-        .send(Request::Analysis(AnalysisRequest {
-          fen: base_position.to_string(),
-          moves: moves.clone(),
-          time: SearchTime::from_clock(&mut clock),
-          searchmoves: Vec::new(),
-          new_game: false,
-        }))
-        .ok()?;
-      spectators
-        .send(SpectatorMessage::Request(Request::Position(
-          base_position.to_string(),
-          moves.clone(),
-          false,
-        )))
-        .ok();
-      spectators
-        .send(SpectatorMessage::Request(Request::Clock(
-          SearchTime::from_clock(&mut clock),
-        )))
-        .ok();
-      if let Some(ref kibbutz) = kibbutz_tx {
-        kibbutz.send(Request::StopAnalysis).ok();
-        kibbutz
-          .send(Request::Analysis(AnalysisRequest {
-            fen: base_position.to_string(),
-            moves: moves.clone(),
-            searchmoves: Vec::new(),
-            time: SearchTime::Infinite,
-            new_game: false,
-          }))
-          .ok();
-      }
-      loop {
-        match rx_2.recv().ok()? {
-          UlciResult::AnalysisStopped(mv) => {
-            if let Some(board) = position.move_if_legal(mv) {
-              if board.halfmoves() == 0 {
-                base_position = position;
-                moves.clear();
-              }
-              position = board;
-              moves.push(mv);
-              clock.switch_clocks();
-            }
-            break;
-          }
-          UlciResult::Analysis(result) => {
-            spectators
-              .send(SpectatorMessage::Kibbutz(UlciResult::Analysis(result)))
-              .ok();
-          }
-          _ => (),
-        }
-      }
-    }
-    tx_1
-      .send(Request::Position(
-        base_position.to_string(),
-        moves.clone(),
-        false,
-      ))
-      .ok()?;
-    tx_2
-      .send(Request::Position(
-        base_position.to_string(),
-        moves.clone(),
-        false,
-      ))
-      .ok()?;
-    spectators
-      .send(SpectatorMessage::Request(Request::Position(
-        base_position.to_string(),
-        moves.clone(),
-        false,
-      )))
+    let [white, black] = players;
+    let mut game = GameManager::new(white, black, position, clock);
+    let kibbutz_tx = kibbutz.acquire();
+    let (game_tx, game_rx) = channel();
+    game.add_spectator(game_tx);
+    let forwarder = {
+      let spectators = spectators.clone();
+      let kibbutz_tx = kibbutz_tx.clone();
+      spawn(move || forward_game(&game_rx, &spectators, kibbutz_tx.as_ref()))
+    };
+    let mut hooks = MatchHooks {
+      names: &names,
+      commands,
+      reconnections,
+      game_grace_period: grace_period,
+      grace_period: &mut grace_period,
+    };
+    let result = game.play_with(&mut hooks);
+    println!("{} vs {}: {result}", names[0], names[1]);
+    reports
+      .send(GameRecord {
+        fen: fen.to_string(),
+        names: names.clone(),
+        moves: game.take_records(),
+      })
       .ok();
+    players = game.into_players();
+    forwarder.join().ok();
+    if let Some(engine) = kibbutz_tx {
+      kibbutz.release(engine);
+    }
+    // a player who didn't come back loses the game, but can still reconnect for the next one
+    if let GameEnd::Disconnected(side) = result.end {
+      println!("{} abandoned the game", names[usize::from(!side)]);
+    }
     sleep(Duration::from_secs(10));
-    (tx_1, tx_2) = (tx_2, tx_1);
-    (rx_1, rx_2) = (rx_2, rx_1);
+    players.swap(0, 1);
+    names.swap(0, 1);
   }
-  None
+}
+
+// The names of the players, numbered if they are the same
+fn player_names(player_1: &ConnectionInfo, player_2: &ConnectionInfo) -> [String; 2] {
+  let name = |(_, _, client): &ConnectionInfo, name: Option<&str>| {
+    name.map_or_else(|| client.name.clone(), str::to_owned)
+  };
+  let (name_1, name_2) = (
+    name(player_1, PLAYER_NAMES[0]),
+    name(player_2, PLAYER_NAMES[1]),
+  );
+  if name_1 == name_2 {
+    [format!("{name_1} (1)"), format!("{name_2} (2)")]
+  } else {
+    [name_1, name_2]
+  }
+}
+
+fn start_engine(path: &'static str) -> Option<ConnectionInfo> {
+  let (tx, rx) = load_engine(path);
+  wait_for_startup(tx, rx)
 }
 
 fn main() {
@@ -267,22 +386,22 @@ fn main() {
     .map(char::from)
     .collect();
   println!("Password 2: {password_2}");
-  let mut player_1 = WHITE_ENGINE.map(load_engine);
-  let mut player_2 = BLACK_ENGINE.map(load_engine);
+  let mut player_1 = WHITE_ENGINE.and_then(start_engine);
+  let mut player_2 = BLACK_ENGINE.and_then(start_engine);
   let mut spectators = Vec::new();
   let (tx, rx) = channel();
   spawn(|| handle_connections(tx));
-  while let Ok((tx, rx, client)) = rx.recv() {
-    let name = client.username;
+  while let Ok(((tx, rx, client), _)) = rx.recv() {
+    let name = client.username.clone();
     if name == Some(password_1.clone()) {
       println!("Found player 1");
-      player_1 = Some((tx, rx));
+      player_1 = Some((tx, rx, client));
       if player_2.is_some() {
         break;
       }
     } else if name == Some(password_2.clone()) {
       println!("Found player 2");
-      player_2 = Some((tx, rx));
+      player_2 = Some((tx, rx, client));
       if player_1.is_some() {
         break;
       }
@@ -295,35 +414,42 @@ fn main() {
     }
   }
   if let (Some(player_1), Some(player_2)) = (player_1, player_2) {
-    println!("Starting match");
+    let names = player_names(&player_1, &player_2);
+    println!("Starting match: {} vs {}", names[0], names[1]);
     let (spectator_tx, spectator_rx) = channel();
     let spectator_tx_copy = spectator_tx.clone();
-    let mut kibbutz_tx = None;
-    if let Some((tx, rx)) = KIBBUTZ_ENGINE.map(load_engine) {
-      tx.send(Request::SetOption(
-        "Hash".to_owned(),
-        OptionValue::UpdateInt(HASH_SIZE),
-      ))
-      .ok();
-      kibbutz_tx = Some(tx);
-      let spectator_tx_copy = spectator_tx.clone();
-      spawn(move || {
-        while let Ok(message) = rx.recv() {
-          spectator_tx_copy
-            .send(SpectatorMessage::Kibbutz(message))
-            .ok();
-        }
-      });
-    }
+    let mut kibbutz = KibbutzPool::new(KIBBUTZ_ENGINE, spectator_tx.clone());
+    let (reconnection_tx, reconnection_rx) = channel();
     spawn(move || {
-      while let Ok((spectator, _, _)) = rx.recv() {
-        spectator_tx_copy
-          .send(SpectatorMessage::Spectator(spectator))
-          .ok();
+      let [name_1, name_2] = names;
+      while let Ok(((tx, rx, client), _)) = rx.recv() {
+        let name = client.username;
+        if name == Some(password_1.clone()) {
+          println!("{name_1} reconnecting");
+          reconnection_tx.send((name_1.clone(), tx, rx)).ok();
+        } else if name == Some(password_2.clone()) {
+          println!("{name_2} reconnecting");
+          reconnection_tx.send((name_2.clone(), tx, rx)).ok();
+        } else {
+          spectator_tx_copy.send(SpectatorMessage::Spectator(tx)).ok();
+        }
       }
     });
     spawn(move || process_spectators(spectators, &spectator_rx));
-    run_match(player_1, player_2, &spectator_tx, &kibbutz_tx);
+    let (command_tx, command_rx) = channel();
+    spawn(move || read_commands(&command_tx));
+    let (report_tx, report_rx) = channel();
+    let anticheat = ANTICHEAT_ENGINE.map(load_engine);
+    spawn(move || report_games(anticheat, &report_rx));
+    run_match(
+      player_1,
+      player_2,
+      &spectator_tx,
+      &mut kibbutz,
+      &command_rx,
+      &reconnection_rx,
+      &report_tx,
+    );
   } else {
     println!("Something went wrong!");
   }
@@ -0,0 +1,74 @@
+use liberty_chess::clock::Clock;
+use liberty_chess::positions::STARTPOS;
+use liberty_chess::Board;
+use server::game::GameManager;
+use server::{handle_connections, wait_for_startup, Client, ConnectionInfo};
+use std::sync::mpsc::channel;
+use std::thread::spawn;
+use std::time::Duration;
+use ulci::load_engine;
+
+/// The engine that plays every game
+const SIMUL_ENGINE: &str = "oxidation";
+
+/// Games played against clients that support several games on one connection
+const SIMUL_GAMES: usize = 4;
+
+const TIME: Duration = Duration::from_secs(600);
+const INCREMENT: Duration = Duration::from_secs(5);
+
+/// Play a game against a new copy of the engine
+fn play_game(client: ConnectionInfo, client_white: bool) -> Option<String> {
+  let (tx, rx) = load_engine(SIMUL_ENGINE);
+  let engine = wait_for_startup(tx, rx)?;
+  let position = Board::new(STARTPOS).ok()?;
+  let clock = Clock::new_symmetric(TIME, INCREMENT, position.to_move());
+  let (white, black) = if client_white {
+    (client, engine)
+  } else {
+    (engine, client)
+  };
+  let result = GameManager::new(white, black, position, clock).play();
+  Some(result.to_string())
+}
+
+/// Play every game of the simul against a client at once
+fn run_simul((connection, mut host): Client) {
+  let name = connection
+    .2
+    .username
+    .clone()
+    .unwrap_or(connection.2.name.clone());
+  let mut games = vec![connection];
+  // clients without the games feature only get one game
+  if let Some(ref mut host) = host {
+    while games.len() < SIMUL_GAMES {
+      let Some(game) = host.open_game() else {
+        break;
+      };
+      games.push(game);
+    }
+  }
+  println!("Starting {} games against {name}", games.len());
+  let games: Vec<_> = games
+    .into_iter()
+    .enumerate()
+    // the client alternates between white and black
+    .map(|(game, client)| spawn(move || play_game(client, game & 1 == 0)))
+    .collect();
+  for (game, handle) in games.into_iter().enumerate() {
+    match handle.join().ok().flatten() {
+      Some(result) => println!("{name} game {}: {result}", game + 1),
+      None => println!("{name} game {}: could not load {SIMUL_ENGINE}", game + 1),
+    }
+  }
+}
+
+fn main() {
+  let (tx, rx) = channel();
+  spawn(|| handle_connections(tx));
+  println!("Waiting for players");
+  while let Ok(client) = rx.recv() {
+    spawn(move || run_simul(client));
+  }
+}
@@ -0,0 +1,180 @@
+use crate::api::Json;
+use crate::game::{GameEnd, GameHooks, GameManager};
+use crate::{handle_connection, wait_for_startup, ConnectionInfo};
+use liberty_chess::clock::Clock;
+use liberty_chess::moves::Move;
+use liberty_chess::positions::STARTPOS;
+use liberty_chess::Board;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::spawn;
+use std::time::Duration;
+use ulci::games::Games;
+use ulci::mock::{run, spawn_mock, Action, Script};
+use ulci::server::{startup_server, AnalysisRequest, Request, UlciResult};
+use ulci::SearchTime;
+
+#[test]
+fn parse_object() {
+  let json = Json::parse(r#"{"type": "gameState", "wtime": 60000, "moves": "e2e4 e7e5"}"#).unwrap();
+  assert_eq!(json.get("type").and_then(Json::as_str), Some("gameState"));
+  assert_eq!(json.get("wtime").and_then(Json::as_u128), Some(60000));
+  assert_eq!(json.get("moves").and_then(Json::as_str), Some("e2e4 e7e5"));
+  assert_eq!(json.get("missing"), None);
+}
+
+#[test]
+fn parse_nested() {
+  let json =
+    Json::parse(r#"{"challenge": {"variant": {"key": "standard"}}, "list": [1, true, null]}"#)
+      .unwrap();
+  let variant = json.get("challenge").and_then(|c| c.get("variant"));
+  assert_eq!(
+    variant.and_then(|v| v.get("key")).and_then(Json::as_str),
+    Some("standard")
+  );
+  assert_eq!(
+    json.get("list"),
+    Some(&Json::Array(vec![
+      Json::Number(1.0),
+      Json::Bool(true),
+      Json::Null
+    ]))
+  );
+}
+
+#[test]
+fn parse_escapes() {
+  let json = Json::parse(r#""a\"b\\c\nd\u00e9""#).unwrap();
+  assert_eq!(json.as_str(), Some("a\"b\\c\nd\u{e9}"));
+}
+
+#[test]
+fn parse_surrogate_pair() {
+  let json = Json::parse(r#""\ud83d\ude00""#).unwrap();
+  assert_eq!(json.as_str(), Some("\u{1f600}"));
+  // a lone surrogate can't be decoded
+  let json = Json::parse(r#""\ud83dx""#).unwrap();
+  assert_eq!(json.as_str(), Some("\u{fffd}x"));
+}
+
+#[test]
+fn parse_invalid() {
+  assert_eq!(Json::parse(""), None);
+  assert_eq!(Json::parse("{\"a\": 1"), None);
+  assert_eq!(Json::parse("[1, 2] 3"), None);
+  assert_eq!(Json::parse("tru"), None);
+}
+
+// A mock engine that supports several games on one connection
+fn games_script(bestmove: &str) -> Script {
+  let lines = [
+    "id version 1",
+    "id feature games",
+    "id name Mock",
+    "id author Mock",
+    "uciok",
+  ];
+  Script::new(bestmove).respond(
+    "uci",
+    lines.map(|line| Action::Send(line.to_owned())).to_vec(),
+  )
+}
+
+fn search(tx: &Sender<Request>) {
+  tx.send(Request::Analysis(AnalysisRequest {
+    fen: STARTPOS.to_owned(),
+    moves: Vec::new(),
+    time: SearchTime::Infinite,
+    searchmoves: Vec::new(),
+    priority: Vec::new(),
+    new_game: true,
+  }))
+  .unwrap();
+}
+
+fn bestmove(rx: &Receiver<UlciResult>) -> Option<Move> {
+  rx.iter().find_map(|result| match result {
+    UlciResult::AnalysisStopped(bestmove) => Some(bestmove),
+    _ => None,
+  })
+}
+
+#[test]
+fn open_game_over_connection() {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let address = listener.local_addr().unwrap();
+  let (clients, connected) = channel();
+  spawn(move || {
+    let (stream, _) = listener.accept().unwrap();
+    handle_connection(stream, Arc::new(clients));
+  });
+  // the client plays a different move in each game to tell them apart
+  let stream = TcpStream::connect(address).unwrap();
+  let (discovered, games) = channel();
+  let (_games, input, output) = Games::split(
+    BufReader::new(stream.try_clone().unwrap()),
+    stream,
+    Some(discovered),
+  );
+  spawn(move || run(games_script("e2e4"), input, output));
+  spawn(move || {
+    for (_, input, output) in games {
+      spawn(move || run(games_script("d2d4"), input, output));
+    }
+  });
+  let ((tx, rx, info), host) = connected.recv().unwrap();
+  assert!(info.features.games);
+  let mut host = host.expect("the client supports games");
+  let (game_tx, game_rx, _) = host.open_game().unwrap();
+  search(&game_tx);
+  search(&tx);
+  assert!(bestmove(&game_rx) == "d2d4".parse().ok());
+  assert!(bestmove(&rx) == "e2e4".parse().ok());
+}
+
+fn mock_player(script: Script) -> ConnectionInfo {
+  let (tx, rx) = channel();
+  let (send_results, results) = channel();
+  spawn(move || {
+    let (input, out) = spawn_mock(script);
+    startup_server(rx, &send_results, input, out, false, || ());
+  });
+  wait_for_startup(tx, results).unwrap()
+}
+
+// Brings a player back once with a new engine
+struct Reconnect(Option<Script>);
+
+impl GameHooks for Reconnect {
+  fn reconnect(
+    &mut self,
+    _side: bool,
+    _opponent: &Sender<Request>,
+  ) -> Option<(Sender<Request>, Receiver<UlciResult>)> {
+    let (tx, rx, _) = mock_player(self.0.take()?);
+    Some((tx, rx))
+  }
+}
+
+#[test]
+fn game_reconnection() {
+  let play = |hooks: &mut Reconnect| {
+    let white = mock_player(Script::new("e2e4").respond_once("go", vec![Action::Crash]));
+    let black = mock_player(Script::new("e7e5"));
+    let position = Board::new(STARTPOS).unwrap();
+    let clock = Clock::new_symmetric(Duration::from_secs(60), Duration::ZERO, true);
+    let mut game = GameManager::new(white, black, position, clock);
+    (game.play_with(hooks), game.take_records())
+  };
+  // white crashes and doesn't come back
+  let (result, _) = play(&mut Reconnect(None));
+  assert_eq!(result.end, GameEnd::Disconnected(true));
+  assert_eq!(result.winner, Some(false));
+  // white comes back and the game carries on until white repeats e2e4
+  let (result, [white, black]) = play(&mut Reconnect(Some(Script::new("e2e4"))));
+  assert_eq!(result.end, GameEnd::IllegalMove(true));
+  assert_eq!((white.len(), black.len()), (1, 1));
+}
@@ -95,6 +95,8 @@ pub enum Effect {
   Enable,
   /// The sound for disabling a checkbox
   Disable,
+  /// The sound for a player running low on time, shares the check sound
+  LowTime,
 }
 
 /// The sound engine
@@ -219,7 +221,7 @@ impl Engine {
         Effect::Move => 0,
         Effect::Illegal => 1,
         Effect::Capture => 2,
-        Effect::Check => 3,
+        Effect::Check | Effect::LowTime => 3,
         Effect::Victory => 4,
         Effect::Draw => 5,
         Effect::Navigate => 6,
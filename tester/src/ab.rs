@@ -0,0 +1,115 @@
+use liberty_chess::threading::CompressedBoard;
+use oxidation::parameters::{Parameters, DEFAULT_PARAMETERS};
+use oxidation::search::{SearchParameters, SEARCH_PARAMETERS};
+use rand::{thread_rng, Rng};
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Sender};
+use std::time::Instant;
+use tester::{
+  elo_difference, get_threadpool, in_process_engine, GameResult, Pentanomial, Sprt, SprtResult,
+  POSITIONS, VSTC,
+};
+use ulci::SearchTime;
+
+// Number of game pairs to play from each position, the most played if the SPRT is undecided
+const GAME_PAIR_COUNT: usize = 20;
+
+// Stop the match early once B is shown to be no better than elo0 or better than elo1,
+// set to None to play every game
+const SPRT: Option<Sprt> = Some(Sprt {
+  elo0: 0.0,
+  elo1: 5.0,
+  alpha: 0.05,
+  beta: 0.05,
+});
+
+const TIME: SearchTime = VSTC;
+
+// The baseline parameters
+const SEARCH_A: SearchParameters = SEARCH_PARAMETERS;
+const PARAMETERS_A: Parameters<i32> = DEFAULT_PARAMETERS;
+
+// The parameters being tested, edit these to try a change
+const SEARCH_B: SearchParameters = SEARCH_PARAMETERS;
+const PARAMETERS_B: Parameters<i32> = DEFAULT_PARAMETERS;
+
+fn play_game(
+  board: CompressedBoard,
+  a_side: bool,
+  pair: usize,
+  results: &Sender<(usize, GameResult)>,
+) {
+  let board = board.load_from_thread();
+  let engine_a = in_process_engine(SEARCH_A, PARAMETERS_A);
+  let engine_b = in_process_engine(SEARCH_B, PARAMETERS_B);
+  let record = tester::play_game(&board, &engine_a, &engine_b, a_side, [TIME, TIME], &mut ());
+  results.send((pair, record.result)).ok();
+}
+
+fn main() {
+  let start = Instant::now();
+  let pool = get_threadpool();
+  let (tx, rx) = channel();
+  // each pair plays the same opening with the colours swapped
+  let mut pair = 0;
+  for (_, position, _) in POSITIONS {
+    for _ in 0..GAME_PAIR_COUNT {
+      let position = position.get_position(thread_rng().gen_bool(0.5));
+      let position_2 = position.clone();
+      let tx = tx.clone();
+      let tx_2 = tx.clone();
+      pool.execute(move || play_game(position, true, pair, &tx));
+      pool.execute(move || play_game(position_2, false, pair, &tx_2));
+      pair += 1;
+    }
+  }
+  // to make sure it actually finishes
+  drop(tx);
+  let (mut wins, mut draws, mut losses) = (0, 0, 0);
+  let mut pentanomial = Pentanomial::default();
+  // the first game to finish from each pair still being played
+  let mut unpaired = HashMap::new();
+  for (pair, result) in &rx {
+    match result {
+      GameResult::ChampWin => wins += 1,
+      GameResult::Draw => draws += 1,
+      GameResult::ChallengeWin => losses += 1,
+    }
+    if let Some(first) = unpaired.remove(&pair) {
+      pentanomial.add(&first, &result);
+    } else {
+      unpaired.insert(pair, result);
+      continue;
+    }
+    if let Some(sprt) = SPRT {
+      // the test is from the point of view of B, using the completed pairs
+      let (lower, upper) = sprt.bounds();
+      let llr = sprt.llr(&pentanomial);
+      println!("+{losses} ={draws} -{wins} LLR {llr:.2} ({lower:.2}, {upper:.2})");
+      match sprt.status(&pentanomial) {
+        SprtResult::H0 => {
+          println!("H0 accepted, B is no better than {:+} elo", sprt.elo0);
+          break;
+        }
+        SprtResult::H1 => {
+          println!("H1 accepted, B is at least {:+} elo", sprt.elo1);
+          break;
+        }
+        SprtResult::Continue => (),
+      }
+    }
+  }
+  let games = wins + draws + losses;
+  let score = (f64::from(losses) + f64::from(draws) / 2.0) / f64::from(games.max(1));
+  let elo = match elo_difference(score) {
+    Some(elo) => format!("{elo:+.0}"),
+    None if score > 0.5 => "+inf".to_owned(),
+    None => "-inf".to_owned(),
+  };
+  println!(
+    "B vs A: +{losses} ={draws} -{wins} score {:.1}% elo {elo}",
+    score * 100.0
+  );
+  println!("B vs A: {}", pentanomial.show());
+  println!("Finished {games} games in {}s", start.elapsed().as_secs());
+}
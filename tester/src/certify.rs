@@ -0,0 +1,164 @@
+use liberty_chess::clock::Millis;
+use liberty_chess::threading::CompressedBoard;
+use liberty_chess::{Board, Gamestate};
+use oxidation::parameters::DEFAULT_PARAMETERS;
+use oxidation::search::SEARCH_PARAMETERS;
+use oxidation::{mvvlva_move, random_move, search, Output, SearchConfig, State, HASH_SIZE};
+use rand::{thread_rng, Rng};
+use std::sync::mpsc::{channel, Sender};
+use std::time::Instant;
+use tester::{elo_difference, get_threadpool, GameResult, POSITIONS};
+use ulci::{Limits, SearchTime};
+
+// time per move for the engine being tested
+const MOVE_TIME: Millis = Millis::new(50);
+// depth of the fixed depth reference engine
+const REFERENCE_DEPTH: u8 = 2;
+// games longer than this are adjudicated as a draw
+const MAX_PLIES: usize = 1000;
+
+// The engine being tested against each opponent, with the minimum score expected
+const OPPONENTS: &[(Opponent, f64)] = &[
+  (Opponent::Random, 0.95),
+  (Opponent::Mvvlva, 0.9),
+  (Opponent::FixedDepth(REFERENCE_DEPTH), 0.75),
+];
+
+#[derive(Clone, Copy)]
+enum Opponent {
+  Random,
+  Mvvlva,
+  FixedDepth(u8),
+}
+
+impl ToString for Opponent {
+  fn to_string(&self) -> String {
+    match self {
+      Self::Random => "Random mover".to_owned(),
+      Self::Mvvlva => "MVV-LVA".to_owned(),
+      Self::FixedDepth(depth) => format!("Oxidation depth {depth}"),
+    }
+  }
+}
+
+fn engine_move(state: &mut State, board: &mut Board, time: SearchTime) -> Option<()> {
+  let mut debug = false;
+  let (_tx, rx) = channel();
+  // search output isn't needed
+  let (results, _) = channel();
+  let mut settings = SearchConfig::new_time(board, time, &rx, &mut debug);
+  let pv = search(
+    state,
+    &mut settings,
+    &mut board.clone(),
+    &[],
+    1,
+    Output::Channel(&results),
+  );
+  *board = board.move_if_legal(*pv.first()?)?;
+  Some(())
+}
+
+fn play_game(board: CompressedBoard, opponent: Opponent, side: bool, results: &Sender<GameResult>) {
+  let mut board = board.load_from_thread();
+  let mut state = State::new(HASH_SIZE, &board, SEARCH_PARAMETERS, DEFAULT_PARAMETERS);
+  let mut opponent_state = State::new(0, &board, SEARCH_PARAMETERS, DEFAULT_PARAMETERS);
+  let mut plies = 0;
+  while board.state() == Gamestate::InProgress && plies < MAX_PLIES {
+    let legal = if board.to_move() == side {
+      let time = SearchTime::Other(Limits {
+        time: MOVE_TIME,
+        ..Limits::default()
+      });
+      engine_move(&mut state, &mut board, time)
+    } else {
+      match opponent {
+        Opponent::Random => random_move(&board)
+          .and_then(|mv| board.move_if_legal(mv))
+          .map(|new_board| board = new_board),
+        Opponent::Mvvlva => mvvlva_move(&board)
+          .and_then(|mv| board.move_if_legal(mv))
+          .map(|new_board| board = new_board),
+        Opponent::FixedDepth(depth) => {
+          let time = SearchTime::Other(Limits {
+            depth,
+            ..Limits::default()
+          });
+          engine_move(&mut opponent_state, &mut board, time)
+        }
+      }
+    };
+    if legal.is_none() {
+      println!("no legal move returned in position {}", board.to_string());
+      break;
+    }
+    plies += 1;
+  }
+  let result = match board.state() {
+    Gamestate::Checkmate(winner) | Gamestate::Elimination(winner) => {
+      if side == winner {
+        GameResult::ChampWin
+      } else {
+        GameResult::ChallengeWin
+      }
+    }
+    Gamestate::InProgress
+    | Gamestate::Material
+    | Gamestate::FiftyMove
+    | Gamestate::Repetition
+    | Gamestate::Stalemate => GameResult::Draw,
+  };
+  results.send(result).ok();
+}
+
+fn main() {
+  let start = Instant::now();
+  let pool = get_threadpool();
+  let mut passed = true;
+  for (opponent, expected) in OPPONENTS {
+    let (tx, rx) = channel();
+    for (_, position, _) in POSITIONS {
+      let position = position.get_position(thread_rng().gen_bool(0.5));
+      let position_2 = position.clone();
+      let opponent = *opponent;
+      let tx = tx.clone();
+      let tx_2 = tx.clone();
+      pool.execute(move || play_game(position, opponent, true, &tx));
+      pool.execute(move || play_game(position_2, opponent, false, &tx_2));
+    }
+    // to make sure it actually finishes
+    drop(tx);
+    let (mut wins, mut draws, mut losses) = (0, 0, 0);
+    for result in &rx {
+      match result {
+        GameResult::ChampWin => wins += 1,
+        GameResult::Draw => draws += 1,
+        GameResult::ChallengeWin => losses += 1,
+      }
+    }
+    let games = wins + draws + losses;
+    let score = (f64::from(wins) + f64::from(draws) / 2.0) / f64::from(games.max(1));
+    let elo = match elo_difference(score) {
+      Some(elo) => format!("{elo:+.0}"),
+      None if score > 0.5 => "+inf".to_owned(),
+      None => "-inf".to_owned(),
+    };
+    let verdict = if score >= *expected {
+      "as expected"
+    } else {
+      passed = false;
+      "lower than expected"
+    };
+    println!(
+      "vs {}: +{wins} ={draws} -{losses} score {:.1}% elo {elo} ({verdict})",
+      opponent.to_string(),
+      score * 100.0,
+    );
+  }
+  println!("Finished in {}s", start.elapsed().as_secs());
+  if passed {
+    println!("Engine is performing as expected");
+  } else {
+    println!("Engine is weaker than expected, check the build is optimised");
+  }
+}
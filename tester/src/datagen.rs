@@ -0,0 +1,123 @@
+use liberty_chess::clock::Millis;
+use liberty_chess::threading::CompressedBoard;
+use liberty_chess::Board;
+use oxidation::parameters::DEFAULT_PARAMETERS;
+use oxidation::search::SEARCH_PARAMETERS;
+use rand::{thread_rng, Rng};
+use std::env::args;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::process::exit;
+use std::sync::mpsc::{channel, Sender};
+use std::time::Instant;
+use tester::{
+  get_threadpool, in_process_engine, play_game, DataRecord, GameObserver, GameResult, MoveEvent,
+  POSITIONS,
+};
+use ulci::{AnalysisResult, Limits, Score, SearchTime};
+
+// Number of games to play from each position
+const GAME_COUNT: usize = 100;
+
+// A short search for each move, the node limit keeps the data the same on any hardware
+const TIME: SearchTime = SearchTime::Other(Limits {
+  depth: u8::MAX,
+  nodes: 5000,
+  time: Millis::new(1000),
+});
+
+// Where to save the records if no path is given
+const DEFAULT_PATH: &str = "datagen.bin";
+
+// Records the score of each position searched, the result is filled in after the game
+#[derive(Default)]
+struct Recorder {
+  // the latest analysis of the position to move, from white's perspective
+  pending: Option<(String, i16)>,
+  positions: Vec<(String, i16)>,
+}
+
+impl GameObserver for Recorder {
+  fn analysis(&mut self, _first_engine: bool, board: &Board, result: &AnalysisResult) {
+    // mate scores and positions in check don't help tune the evaluation
+    self.pending = match result.score {
+      Score::Centipawn(score) if !board.in_check() => {
+        let score = score.clamp(i32::from(i16::MIN), i32::from(i16::MAX)) as i16;
+        let score = if board.to_move() { score } else { -score };
+        Some((board.to_string(), score))
+      }
+      Score::Centipawn(_) | Score::Win(_) | Score::Loss(_) => None,
+    };
+  }
+
+  fn on_move(&mut self, _event: &MoveEvent) {
+    if let Some(position) = self.pending.take() {
+      self.positions.push(position);
+    }
+  }
+}
+
+fn generate_game(board: CompressedBoard, results: &Sender<Vec<DataRecord>>) {
+  let board = board.load_from_thread();
+  // the same engine plays both sides
+  let engine = in_process_engine(SEARCH_PARAMETERS, DEFAULT_PARAMETERS);
+  let mut recorder = Recorder::default();
+  let record = play_game(&board, &engine, &engine, true, [TIME, TIME], &mut recorder);
+  let result = match record.result {
+    GameResult::ChampWin => 2,
+    GameResult::Draw => 1,
+    GameResult::ChallengeWin => 0,
+  };
+  let records = recorder
+    .positions
+    .into_iter()
+    .map(|(fen, score)| DataRecord { fen, score, result })
+    .collect();
+  results.send(records).ok();
+}
+
+fn main() {
+  let path = args().nth(1).unwrap_or_else(|| DEFAULT_PATH.to_owned());
+  let mut out = match File::create(&path) {
+    Ok(file) => BufWriter::new(file),
+    Err(error) => {
+      println!("Unable to create {path}: {error}");
+      exit(1);
+    }
+  };
+  let start = Instant::now();
+  let pool = get_threadpool();
+  let (tx, rx) = channel();
+  for (_, position, _) in POSITIONS {
+    for _ in 0..GAME_COUNT {
+      let position = position.get_position(thread_rng().gen_bool(0.5));
+      let tx = tx.clone();
+      pool.execute(move || generate_game(position, &tx));
+    }
+  }
+  // to make sure it actually finishes
+  drop(tx);
+  let total_games = POSITIONS.len() * GAME_COUNT;
+  let (mut games, mut positions) = (0, 0);
+  for records in &rx {
+    for record in &records {
+      if let Err(error) = record.write(&mut out) {
+        println!("Unable to write to {path}: {error}");
+        exit(1);
+      }
+    }
+    games += 1;
+    positions += records.len();
+    if games % 100 == 0 || games == total_games {
+      println!(
+        "{games}/{total_games} games, {positions} positions in {}s",
+        start.elapsed().as_secs()
+      );
+    }
+  }
+  if let Err(error) = out.flush() {
+    println!("Unable to write to {path}: {error}");
+    exit(1);
+  }
+  println!("Saved {positions} positions to {path}");
+}
@@ -2,33 +2,43 @@
 #![warn(missing_docs, unused)]
 //! A testing program for comparing 2 different engines against each other in a range of positions.
 
+use liberty_chess::clock::Millis;
+use liberty_chess::moves::Move;
+use liberty_chess::parsing::{to_char, Pgn, PgnDialect};
 use liberty_chess::positions::{
   AFRICAN, CAPABLANCA, CAPABLANCA_RECTANGLE, DOUBLE_CHESS, ELIMINATION, HORDE, LIBERTY_CHESS,
   LOADED_BOARD, MINI, MONGOL, NARNIA, STARTPOS, TRUMP,
 };
 use liberty_chess::random_board::generate;
 use liberty_chess::threading::CompressedBoard;
-use liberty_chess::Board;
+use liberty_chess::{Board, Gamestate, Piece};
 use oxidation::evaluate::evaluate;
-use oxidation::parameters::DEFAULT_PARAMETERS;
-use oxidation::search::{quiescence, SEARCH_PARAMETERS};
-use oxidation::{random_move, SearchConfig, State};
+use oxidation::glue::process_position;
+use oxidation::parameters::{Parameters, DEFAULT_PARAMETERS};
+use oxidation::search::{quiescence, SearchParameters, SEARCH_PARAMETERS};
+use oxidation::{random_move, SearchConfig, State, HASH_SIZE};
 use rand::{thread_rng, Rng};
+use std::io::{Error, ErrorKind, Read, Result, Write};
 use std::num::NonZeroUsize;
-use std::sync::mpsc::channel;
-use std::thread::available_parallelism;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{available_parallelism, spawn};
+use std::time::Instant;
 use threadpool::ThreadPool;
-use ulci::{Score, SearchTime};
+use ulci::server::{AnalysisRequest, InfoType, Request, UlciResult};
+use ulci::{AnalysisResult, Score, SearchTime};
+
+#[cfg(test)]
+mod tests;
 
 const RANDOM_MOVE_COUNT: usize = 6;
 const FILTER_THRESHOLD: i32 = 200;
 
 /// 1+0.01 for speedups
-pub const VSTC: SearchTime = SearchTime::Increment(1000, 10);
+pub const VSTC: SearchTime = SearchTime::Increment(Millis::new(1000), Millis::new(10));
 /// 8+0.08 for most tests
-pub const STC: SearchTime = SearchTime::Increment(8000, 80);
+pub const STC: SearchTime = SearchTime::Increment(Millis::new(8000), Millis::new(80));
 /// 40+0.4 for progression tests and checking scaling
-pub const LTC: SearchTime = SearchTime::Increment(40000, 400);
+pub const LTC: SearchTime = SearchTime::Increment(Millis::new(40000), Millis::new(400));
 
 /// The test positions for the match
 pub const POSITIONS: &[(&str, StartingPosition, u32)] = &[
@@ -75,56 +85,664 @@ impl StartingPosition {
   /// Convert a starting position to an actual board
   #[must_use]
   pub fn get_position(&self, friendly_fire: bool) -> CompressedBoard {
+    self.get_position_with_rules(&RuleFlags {
+      friendly_fire,
+      ..RuleFlags::default()
+    })
+  }
+
+  /// Convert a starting position to an actual board with altered rules
+  #[must_use]
+  pub fn get_position_with_rules(&self, rules: &RuleFlags) -> CompressedBoard {
     match self {
       Self::Fen(fen) => {
-        let mut board = Board::new(fen).expect("Loading board failed");
-        board.friendly_fire = friendly_fire;
-        let mut state = State::new(0, &board, SEARCH_PARAMETERS, DEFAULT_PARAMETERS);
-        let mut debug = false;
-        let (_tx, rx_2) = channel();
-        let mut settings = SearchConfig::new_time(&board, SearchTime::Infinite, &rx_2, &mut debug);
-        let mut eval = evaluate(&state, &board);
-        if RANDOM_MOVE_COUNT % 2 == 1 {
-          // Final board is opposite stm, invert score
-          eval = -eval;
-        }
-        let alpha = Score::Centipawn(eval - FILTER_THRESHOLD);
-        let beta = Score::Centipawn(eval + FILTER_THRESHOLD);
-        let board = loop {
-          let mut board = board.clone();
-          for _ in 0..RANDOM_MOVE_COUNT {
-            if let Some(randommove) = random_move(&board) {
-              if let Some(new_board) = board.move_if_legal(randommove) {
-                board = new_board;
-              }
-            }
-          }
-          // Filter out busted openings
-          state.set_first_stack_entry(&board);
-          let (_, score) = quiescence(&mut state, &mut settings, 0, 1, alpha, beta)
-            .unwrap_or((Vec::new(), Score::Centipawn(eval)));
-          if score > alpha && score < beta {
-            break board;
-          }
-        };
-        board.send_to_thread()
+        let board = Board::new(&rules.apply(fen)).expect("Loading board failed");
+        random_opening(&board).send_to_thread()
       }
       Self::Random => {
         let mut rng = thread_rng();
         let width = rng.gen_range(6..=12);
         let height = rng.gen_range(6..=12);
-        let fen = generate(width, height, "mqcaehuriwbznxlo", true);
-        let mut board = Board::new(&fen).expect("Loading board failed");
-        board.friendly_fire = friendly_fire;
+        let fen = generate(width, height, "mqcaehuriwbznxlo", true, false);
+        let board = Board::new(&rules.apply(&fen)).expect("Loading board failed");
         board.send_to_thread()
       }
     }
   }
 }
 
+// Play random moves from the position, skipping openings that are busted
+fn random_opening(board: &Board) -> Board {
+  let mut state = State::new(0, board, SEARCH_PARAMETERS, DEFAULT_PARAMETERS);
+  let mut debug = false;
+  let (_tx, rx_2) = channel();
+  let mut settings = SearchConfig::new_time(board, SearchTime::Infinite, &rx_2, &mut debug);
+  let mut eval = evaluate(&state, board);
+  if RANDOM_MOVE_COUNT % 2 == 1 {
+    // Final board is opposite stm, invert score
+    eval = -eval;
+  }
+  let alpha = Score::Centipawn(eval - FILTER_THRESHOLD);
+  let beta = Score::Centipawn(eval + FILTER_THRESHOLD);
+  loop {
+    let mut board = board.clone();
+    for _ in 0..RANDOM_MOVE_COUNT {
+      if let Some(randommove) = random_move(&board) {
+        if let Some(new_board) = board.move_if_legal(randommove) {
+          board = new_board;
+        }
+      }
+    }
+    // Filter out busted openings
+    state.set_first_stack_entry(&board);
+    let (_, score) = quiescence(&mut state, &mut settings, 0, 1, alpha, beta)
+      .unwrap_or((Vec::new(), Score::Centipawn(eval)));
+    if score > alpha && score < beta {
+      break board;
+    }
+  }
+}
+
+/// Changes to the rules of a starting position, for checking rule subsets separately
+#[derive(Clone, Copy, Default)]
+pub struct RuleFlags {
+  /// Whether pieces can capture pieces of their own colour
+  pub friendly_fire: bool,
+  /// How many squares pawns can move on their first move, `None` keeps the position's rules
+  pub pawn_moves: Option<usize>,
+  /// The pieces pawns can promote to, `None` keeps the position's rules
+  pub promotions: Option<&'static [Piece]>,
+}
+
+impl RuleFlags {
+  /// Apply the rule changes to an L-FEN
+  #[must_use]
+  pub fn apply(&self, fen: &str) -> String {
+    // missing fields after the board are filled in with the defaults
+    let mut fields: Vec<String> = fen.split(' ').map(str::to_owned).collect();
+    for (index, default) in ["w", "-", "-", "0", "1", "2,2,1", "qrbn"]
+      .iter()
+      .enumerate()
+    {
+      if fields.len() <= index + 1 {
+        fields.push((*default).to_owned());
+      }
+    }
+    if let Some(pawn_moves) = self.pawn_moves {
+      let mut data: Vec<String> = fields[6].split(',').map(str::to_owned).collect();
+      data[0] = pawn_moves.to_string();
+      fields[6] = data.join(",");
+    }
+    if let Some(promotions) = self.promotions {
+      fields[7] = promotions
+        .iter()
+        .map(|piece| to_char(-piece.abs()))
+        .collect();
+    }
+    // Chess960 is part of the position rather than a rule change
+    let chess960 = fields.iter().skip(8).any(|flag| flag == "960");
+    fields.truncate(8);
+    if self.friendly_fire {
+      fields.push("ff".to_owned());
+    }
+    if chess960 {
+      fields.push("960".to_owned());
+    }
+    fields.join(" ")
+  }
+
+  /// A short description of the rule changes
+  #[must_use]
+  pub fn describe(&self) -> String {
+    let mut changes = Vec::new();
+    if self.friendly_fire {
+      changes.push("friendly fire".to_owned());
+    }
+    if let Some(pawn_moves) = self.pawn_moves {
+      changes.push(format!("pawn moves {pawn_moves}"));
+    }
+    if let Some(promotions) = self.promotions {
+      let pieces: String = promotions
+        .iter()
+        .map(|piece| to_char(piece.abs()))
+        .collect();
+      changes.push(format!("promotions {pieces}"));
+    }
+    if changes.is_empty() {
+      "default rules".to_owned()
+    } else {
+      changes.join(", ")
+    }
+  }
+}
+
 /// Get a threadpool to execute tasks with
 #[must_use]
 pub fn get_threadpool() -> ThreadPool {
   let cores = available_parallelism().map_or(1, NonZeroUsize::get);
   ThreadPool::new(cores - 1)
 }
+
+/// A connection to an engine, as returned by `ulci::load_engine`
+pub type EngineConnection = (Sender<Request>, Receiver<UlciResult>);
+
+/// Information about a move played in a game
+pub struct MoveEvent<'a> {
+  /// Whether the first engine played the move
+  pub first_engine: bool,
+  /// The position after the move
+  pub board: &'a Board,
+  /// The move played
+  pub mv: Move,
+  /// The time taken in ms
+  pub time: u128,
+  /// How far the engine went over its time
+  pub overrun: Millis,
+  /// The last analysis the engine sent before moving
+  pub analysis: Option<&'a AnalysisResult>,
+}
+
+/// Callbacks for events during a game, all of which do nothing by default
+pub trait GameObserver {
+  /// Called for each analysis result with the position being analysed
+  fn analysis(&mut self, _first_engine: bool, _board: &Board, _result: &AnalysisResult) {}
+
+  /// Called for each info string from an engine
+  fn info(&mut self, _first_engine: bool, _info: &str) {}
+
+  /// Called after each move
+  fn on_move(&mut self, _event: &MoveEvent) {}
+}
+
+impl GameObserver for () {}
+
+/// How a game ended
+pub enum Termination {
+  /// The game ended by the rules
+  Rules(Gamestate),
+  /// An engine made an illegal move and lost
+  IllegalMove(Move),
+  /// An engine stopped responding and lost
+  Disconnected,
+}
+
+/// The record of a finished game
+pub struct GameRecord {
+  /// The starting position
+  pub start: String,
+  /// The moves played with the time taken for each in ms
+  pub moves: Vec<(Move, u128)>,
+  /// The result from the perspective of the first engine
+  pub result: GameResult,
+  /// How the game ended
+  pub termination: Termination,
+}
+
+impl GameRecord {
+  /// The game as PGN, with the names of the first and second engines
+  #[must_use]
+  pub fn to_pgn(&self, first_side: bool, first: &str, second: &str) -> String {
+    let Ok(start) = Board::new(&self.start) else {
+      return String::new();
+    };
+    let mut pgn = Pgn::new(start);
+    let (white, black) = if first_side {
+      (first, second)
+    } else {
+      (second, first)
+    };
+    pgn.tags = vec![
+      ("White".to_owned(), white.to_owned()),
+      ("Black".to_owned(), black.to_owned()),
+    ];
+    for (mv, _) in &self.moves {
+      pgn.push(*mv);
+    }
+    let first_won = match self.result {
+      GameResult::ChampWin => Some(true),
+      GameResult::Draw => None,
+      GameResult::ChallengeWin => Some(false),
+    };
+    pgn.result = match first_won {
+      Some(first_won) if first_won == first_side => "1-0",
+      Some(_) => "0-1",
+      None => "1/2-1/2",
+    }
+    .to_owned();
+    // the tester plays variants, so the moves need long algebraic notation
+    pgn.write(PgnDialect::Extended)
+  }
+}
+
+/// Update the time left after a move, returning how far over the time limit the move went
+pub fn update_time(search_time: &mut SearchTime, side: bool, millis: Millis) -> Millis {
+  match search_time {
+    SearchTime::Increment(time, inc) => {
+      let excess = millis - *time;
+      *time = *time - millis + *inc;
+      excess
+    }
+    SearchTime::Asymmetric(wtime, winc, btime, binc)
+    | SearchTime::MovesToGo(wtime, winc, btime, binc, _) => {
+      let (time, inc) = if side { (wtime, winc) } else { (btime, binc) };
+      let excess = millis - *time;
+      *time = *time - millis + *inc;
+      excess
+    }
+    SearchTime::Delay(wtime, btime, delay) => {
+      let time = if side { wtime } else { btime };
+      let excess = millis - (*time + *delay);
+      *time -= millis - *delay;
+      excess
+    }
+    SearchTime::Hourglass(wtime, btime) => {
+      let (time, opponent_time) = if side { (wtime, btime) } else { (btime, wtime) };
+      let excess = millis - *time;
+      *opponent_time += millis.min(*time);
+      *time -= millis;
+      excess
+    }
+    SearchTime::Other(limits) => {
+      // allow some leeway for fixed time searches
+      let excess = millis - limits.time;
+      if excess >= Millis::new(25) {
+        excess
+      } else {
+        Millis::ZERO
+      }
+    }
+    SearchTime::Infinite | SearchTime::Mate(_) => Millis::ZERO,
+  }
+}
+
+/// Play a game between 2 engines from the provided position
+///
+/// `first_side` is the side the first engine plays, and `times` are the time controls for
+/// the first and second engines
+pub fn play_game(
+  position: &Board,
+  first: &EngineConnection,
+  second: &EngineConnection,
+  first_side: bool,
+  mut times: [SearchTime; 2],
+  observer: &mut impl GameObserver,
+) -> GameRecord {
+  let start = position.to_string();
+  let mut board = position.clone();
+  // the last position after an irreversible move and the moves since
+  let mut base = position.clone();
+  let mut moves_since_base = Vec::new();
+  let mut moves = Vec::new();
+  let termination = loop {
+    if board.state() != Gamestate::InProgress {
+      break Termination::Rules(board.state());
+    }
+    let first_engine = board.to_move() == first_side;
+    let ((requests, results), index) = if first_engine {
+      (first, 0)
+    } else {
+      (second, 1)
+    };
+    let move_time = Instant::now();
+    let sent = requests.send(Request::Analysis(AnalysisRequest {
+      fen: base.to_string(),
+      moves: moves_since_base.clone(),
+      time: times[index],
+      searchmoves: Vec::new(),
+      priority: Vec::new(),
+      new_game: false,
+    }));
+    if sent.is_err() {
+      break Termination::Disconnected;
+    }
+    let mut analysis = None;
+    let bestmove = loop {
+      match results.recv() {
+        Ok(UlciResult::Analysis(result)) => {
+          observer.analysis(first_engine, &board, &result);
+          analysis = Some(result);
+        }
+        Ok(UlciResult::Info(InfoType::String, info)) => observer.info(first_engine, &info),
+        Ok(UlciResult::AnalysisStopped(bestmove)) => break Some(bestmove),
+        Ok(
+          UlciResult::Startup(_) | UlciResult::Info(InfoType::Error, _) | UlciResult::Perft(..),
+        ) => (),
+        Err(_) => break None,
+      }
+    };
+    let Some(bestmove) = bestmove else {
+      break Termination::Disconnected;
+    };
+    let Some(new_board) = board.move_if_legal(bestmove) else {
+      break Termination::IllegalMove(bestmove);
+    };
+    let time = move_time.elapsed().as_millis();
+    let overrun = update_time(&mut times[index], board.to_move(), Millis::new(time));
+    board = new_board;
+    if board.halfmoves() == 0 {
+      base = board.clone();
+      moves_since_base.clear();
+    } else {
+      moves_since_base.push(bestmove);
+    }
+    moves.push((bestmove, time));
+    observer.on_move(&MoveEvent {
+      first_engine,
+      board: &board,
+      mv: bestmove,
+      time,
+      overrun,
+      analysis: analysis.as_ref(),
+    });
+  };
+  let result = match termination {
+    Termination::Rules(Gamestate::Checkmate(winner) | Gamestate::Elimination(winner)) => {
+      if winner == first_side {
+        GameResult::ChampWin
+      } else {
+        GameResult::ChallengeWin
+      }
+    }
+    Termination::Rules(_) => GameResult::Draw,
+    // the engine to move loses
+    Termination::IllegalMove(_) | Termination::Disconnected => {
+      if board.to_move() == first_side {
+        GameResult::ChallengeWin
+      } else {
+        GameResult::ChampWin
+      }
+    }
+  };
+  GameRecord {
+    start,
+    moves,
+    result,
+    termination,
+  }
+}
+
+/// Run the built-in engine on another thread with the provided parameters
+///
+/// Accepts the same requests as an engine loaded from a path without spawning a process
+#[must_use]
+pub fn in_process_engine(
+  search_parameters: SearchParameters,
+  parameters: Parameters<i32>,
+) -> EngineConnection {
+  let (tx, requests) = channel();
+  let (results, rx) = channel();
+  spawn(move || {
+    // nothing stops the search early
+    let (_stop, messages) = channel();
+    let mut state: Option<State> = None;
+    while let Ok(request) = requests.recv() {
+      let Request::Analysis(request) = request else {
+        continue;
+      };
+      let Ok(mut board) = Board::new(&request.fen) else {
+        break;
+      };
+      for mv in request.moves {
+        if let Some(new_board) = board.move_if_legal(mv) {
+          board = new_board;
+        }
+      }
+      let state =
+        state.get_or_insert_with(|| State::new(HASH_SIZE, &board, search_parameters, parameters));
+      if request.new_game {
+        state.new_game(&board);
+      }
+      let searched = process_position(
+        &results,
+        &messages,
+        board.send_to_thread(),
+        request.time,
+        &request.searchmoves,
+        &request.priority,
+        state,
+        &mut 1,
+        false,
+        0,
+        false,
+      );
+      if searched.is_none() {
+        break;
+      }
+    }
+  });
+  (tx, rx)
+}
+
+/// Estimate the Elo difference from the fraction of points scored
+///
+/// Returns `None` for a perfect or zero score
+#[must_use]
+pub fn elo_difference(score: f64) -> Option<f64> {
+  if score <= 0.0 || score >= 1.0 {
+    None
+  } else {
+    Some(-400.0 * (1.0 / score - 1.0).log10())
+  }
+}
+
+/// The outcome of a sequential probability ratio test
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SprtResult {
+  /// The change is no better than `elo0`
+  H0,
+  /// The change is at least as good as `elo1`
+  H1,
+  /// More games are needed
+  Continue,
+}
+
+/// A sequential probability ratio test, to stop a match as soon as the result is clear
+#[derive(Clone, Copy, Debug)]
+pub struct Sprt {
+  /// The Elo difference of the null hypothesis
+  pub elo0: f64,
+  /// The Elo difference of the alternative hypothesis
+  pub elo1: f64,
+  /// The chance of accepting H1 when H0 is true
+  pub alpha: f64,
+  /// The chance of accepting H0 when H1 is true
+  pub beta: f64,
+}
+
+impl Sprt {
+  /// The log-likelihood ratio bounds for accepting H0 and H1
+  #[must_use]
+  pub fn bounds(&self) -> (f64, f64) {
+    (
+      (self.beta / (1.0 - self.alpha)).ln(),
+      ((1.0 - self.beta) / self.alpha).ln(),
+    )
+  }
+
+  /// The log-likelihood ratio of H1 over H0 from the game pairs of the engine being tested
+  ///
+  /// Uses the normal approximation of the pentanomial model, so it is 0 until the pair results
+  /// vary enough to estimate the variance from.
+  #[must_use]
+  pub fn llr(&self, results: &Pentanomial) -> f64 {
+    let Some((mean, variance)) = results.statistics() else {
+      return 0.0;
+    };
+    if variance <= 0.0 {
+      return 0.0;
+    }
+    let expected = |elo: f64| 1.0 / (1.0 + 10.0_f64.powf(-elo / 400.0));
+    let (score0, score1) = (expected(self.elo0), expected(self.elo1));
+    (score1 - score0) * (2.0 * mean - score0 - score1) * f64::from(results.count())
+      / (2.0 * variance)
+  }
+
+  /// Decide whether a hypothesis can be accepted yet
+  #[must_use]
+  pub fn status(&self, results: &Pentanomial) -> SprtResult {
+    let llr = self.llr(results);
+    let (lower, upper) = self.bounds();
+    if llr <= lower {
+      SprtResult::H0
+    } else if llr >= upper {
+      SprtResult::H1
+    } else {
+      SprtResult::Continue
+    }
+  }
+}
+
+/// Results of game pairs played from the same opening with the colours swapped
+///
+/// Counting pairs instead of games cancels out most of the bias from unbalanced openings,
+/// which gives much tighter error bars for small changes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Pentanomial {
+  /// Pairs where the engine being tested scored 0, 0.5, 1, 1.5 and 2 points
+  pub pairs: [u32; 5],
+}
+
+impl Pentanomial {
+  /// Add the results of a game pair, the second engine is the one being tested
+  pub fn add(&mut self, first: &GameResult, second: &GameResult) {
+    let half_points = |result: &GameResult| match result {
+      GameResult::ChampWin => 0,
+      GameResult::Draw => 1,
+      GameResult::ChallengeWin => 2,
+    };
+    self.pairs[half_points(first) + half_points(second)] += 1;
+  }
+
+  /// The number of pairs played
+  #[must_use]
+  pub fn count(&self) -> u32 {
+    self.pairs.iter().sum()
+  }
+
+  // the mean and variance of the score per game in each pair
+  fn statistics(&self) -> Option<(f64, f64)> {
+    let count = f64::from(self.count());
+    if count == 0.0 {
+      return None;
+    }
+    let scores = [0.0, 0.25, 0.5, 0.75, 1.0];
+    let mean = scores
+      .iter()
+      .zip(self.pairs)
+      .map(|(score, pairs)| score * f64::from(pairs))
+      .sum::<f64>()
+      / count;
+    let variance = scores
+      .iter()
+      .zip(self.pairs)
+      .map(|(score, pairs)| (score - mean).powi(2) * f64::from(pairs))
+      .sum::<f64>()
+      / count;
+    Some((mean, variance))
+  }
+
+  /// The Elo difference of the engine being tested and its 95% error margin
+  ///
+  /// Returns `None` without any pairs or with a perfect or zero score
+  #[must_use]
+  pub fn elo(&self) -> Option<(f64, f64)> {
+    let (mean, variance) = self.statistics()?;
+    let margin = 1.96 * (variance / f64::from(self.count())).sqrt();
+    let elo = elo_difference(mean)?;
+    let lower = elo_difference((mean - margin).max(f64::EPSILON))?;
+    let upper = elo_difference((mean + margin).min(1.0 - f64::EPSILON))?;
+    Some((elo, (upper - lower) / 2.0))
+  }
+
+  /// The normalized Elo difference and its 95% error margin
+  ///
+  /// Normalized Elo scales the score by its spread, so it doesn't depend on the draw rate.
+  /// Returns `None` until the results vary.
+  #[must_use]
+  pub fn normalized_elo(&self) -> Option<(f64, f64)> {
+    let (mean, variance) = self.statistics()?;
+    if variance <= 0.0 {
+      return None;
+    }
+    let scale = 800.0 / std::f64::consts::LN_10;
+    let per_game = (2.0 * variance).sqrt();
+    let margin = 1.96 * scale / (2.0 * f64::from(self.count())).sqrt();
+    Some(((mean - 0.5) / per_game * scale, margin))
+  }
+
+  /// The pair counts and Elo estimates on one line
+  #[must_use]
+  pub fn show(&self) -> String {
+    let [zero, quarter, half, three_quarters, one] = self.pairs;
+    let elo = self
+      .elo()
+      .map_or("elo unknown".to_owned(), |(elo, margin)| {
+        format!("elo {elo:+.1} ± {margin:.1}")
+      });
+    let normalized = self
+      .normalized_elo()
+      .map_or("nelo unknown".to_owned(), |(elo, margin)| {
+        format!("nelo {elo:+.1} ± {margin:.1}")
+      });
+    format!("pairs [{zero}, {quarter}, {half}, {three_quarters}, {one}] {elo} {normalized}")
+  }
+}
+
+/// A position from a generated game with the search score and the result
+///
+/// Saved as the length of the L-FEN as 2 bytes, the L-FEN, the score as 2 bytes and the result
+/// as 1 byte, with numbers in little endian.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DataRecord {
+  /// The L-FEN of the position
+  pub fen: String,
+  /// The score from the search in centipawns, from white's perspective
+  pub score: i16,
+  /// The result of the game in half points for white
+  pub result: u8,
+}
+
+impl DataRecord {
+  /// Write the record in the binary format
+  ///
+  /// # Errors
+  ///
+  /// Will return `Err` if the L-FEN is too long or the record could not be written
+  pub fn write(&self, out: &mut impl Write) -> Result<()> {
+    let length = u16::try_from(self.fen.len())
+      .map_err(|_| Error::new(ErrorKind::InvalidInput, "L-FEN is too long"))?;
+    out.write_all(&length.to_le_bytes())?;
+    out.write_all(self.fen.as_bytes())?;
+    out.write_all(&self.score.to_le_bytes())?;
+    out.write_all(&[self.result])
+  }
+
+  /// Read the next record, `None` at the end of the input
+  ///
+  /// # Errors
+  ///
+  /// Will return `Err` if the input could not be read or ends partway through a record
+  pub fn read(input: &mut impl Read) -> Result<Option<Self>> {
+    let mut length = [0; 2];
+    // only a clean end of the input before a record starts isn't an error
+    if input.read(&mut length[..1])? == 0 {
+      return Ok(None);
+    }
+    input.read_exact(&mut length[1..])?;
+    let mut fen = vec![0; usize::from(u16::from_le_bytes(length))];
+    input.read_exact(&mut fen)?;
+    let fen = String::from_utf8(fen).map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+    let mut score = [0; 2];
+    input.read_exact(&mut score)?;
+    let mut result = [0];
+    input.read_exact(&mut result)?;
+    if result[0] > 2 {
+      return Err(Error::new(ErrorKind::InvalidData, "invalid result"));
+    }
+    Ok(Some(Self {
+      fen,
+      score: i16::from_le_bytes(score),
+      result: result[0],
+    }))
+  }
+}
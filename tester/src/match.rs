@@ -1,19 +1,22 @@
-use liberty_chess::clock::format_time;
+use liberty_chess::clock::{format_time, Millis};
 use liberty_chess::moves::Move;
 use liberty_chess::threading::CompressedBoard;
-use liberty_chess::{Board, Gamestate};
+use liberty_chess::{Board, Gamestate, Hash};
 use oxidation::parameters::DEFAULT_PARAMETERS;
 use oxidation::search::{quiescence, SEARCH_PARAMETERS};
 use oxidation::{SearchConfig, State};
 use rand::{thread_rng, Rng};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
 use std::fs::write;
+use std::hash::{Hash as _, Hasher};
 use std::ops::AddAssign;
-use std::sync::mpsc::{channel, Receiver, Sender};
-use std::time::Instant;
-use tester::{get_threadpool, GameResult, StartingPosition, POSITIONS, STC};
-use ulci::server::{AnalysisRequest, Request, UlciResult};
-use ulci::{load_engine, Score, SearchTime};
+use std::sync::mpsc::{channel, Sender};
+use tester::{
+  get_threadpool, GameObserver, GameResult, MoveEvent, Pentanomial, StartingPosition, Termination,
+  POSITIONS, STC,
+};
+use ulci::{load_engine, load_engine_debug, AnalysisResult, Score, SearchTime};
 
 const CHAMPION: &str = "./target/release/oxidation";
 const CHALLENGER: &str = "./target/release/oxidation";
@@ -23,14 +26,252 @@ const GAME_PAIR_COUNT: usize = 180;
 const CHAMP_TIME: SearchTime = STC;
 const CHALLENGE_TIME: SearchTime = STC;
 
+// Run the engines in debug mode to collect root node and search statistics
+// The extra output costs time, so the statistics are off for normal matches
+const SEARCH_STATISTICS: bool = false;
+
+// Warn if more than this share of games repeat an earlier game
+const DUPLICATE_THRESHOLD: f32 = 0.05;
+
 struct GameInfo {
+  // games from the same opening with the colours swapped share a pair
+  pair: usize,
   result: GameResult,
   points: u32,
   champ_moves: (u32, u32, u32),
   challenge_moves: (u32, u32, u32),
   champ_depth: (u32, u32, u32),
   challenge_depth: (u32, u32, u32),
+  champ_root_nodes: RootNodeStats,
+  challenge_root_nodes: RootNodeStats,
+  champ_search_stats: SearchStats,
+  challenge_search_stats: SearchStats,
   positions: HashSet<String>,
+  start: String,
+  moves: Vec<Move>,
+  pgn: String,
+}
+
+// Distribution of nodes between root moves in the last iteration of each search
+#[derive(Clone, Copy, Default)]
+struct RootNodeStats {
+  searches: u32,
+  root_moves: u32,
+  // permill of nodes spent on the move played
+  best_permill: usize,
+  // permill of nodes spent on the most searched move
+  top_permill: usize,
+}
+
+impl RootNodeStats {
+  fn update(&mut self, root_nodes: &[(Move, usize)], bestmove: Move) {
+    let total: usize = root_nodes.iter().map(|(_, nodes)| nodes).sum();
+    if total > 0 {
+      let best_nodes = root_nodes
+        .iter()
+        .find(|(mv, _)| *mv == bestmove)
+        .map_or(0, |(_, nodes)| *nodes);
+      let top_nodes = root_nodes
+        .iter()
+        .map(|(_, nodes)| *nodes)
+        .max()
+        .unwrap_or(0);
+      self.searches += 1;
+      self.root_moves += root_nodes.len() as u32;
+      self.best_permill += best_nodes * 1000 / total;
+      self.top_permill += top_nodes * 1000 / total;
+    }
+  }
+
+  fn show(&self) -> String {
+    let searches = self.searches.max(1);
+    format!(
+      "{:.1}% on move played, {:.1}% on most searched, {:.1} root moves",
+      self.best_permill as f32 / 10.0 / searches as f32,
+      self.top_permill as f32 / 10.0 / searches as f32,
+      self.root_moves as f32 / searches as f32
+    )
+  }
+}
+
+impl AddAssign for RootNodeStats {
+  fn add_assign(&mut self, rhs: Self) {
+    self.searches += rhs.searches;
+    self.root_moves += rhs.root_moves;
+    self.best_permill += rhs.best_permill;
+    self.top_permill += rhs.top_permill;
+  }
+}
+
+// Parse the per root move node counts reported by oxidation in debug mode
+fn parse_root_nodes(info: &str) -> Option<Vec<(Move, usize)>> {
+  let mut words = info.strip_prefix("rootnodes")?.split_whitespace();
+  let mut root_nodes = Vec::new();
+  while let (Some(mv), Some(nodes)) = (words.next(), words.next()) {
+    root_nodes.push((mv.parse().ok()?, nodes.parse().ok()?));
+  }
+  Some(root_nodes)
+}
+
+// Per-depth iteration statistics reported by oxidation in debug mode
+#[derive(Clone, Copy, Default)]
+struct DepthStats {
+  iterations: u32,
+  // total time to reach the end of the iteration in ms
+  time: u128,
+  // number of iterations with a previous iteration to compare against
+  ebf_count: u32,
+  ebf: f32,
+  first_move_fail_high: f32,
+}
+
+#[derive(Clone, Default)]
+struct SearchStats {
+  depths: Vec<DepthStats>,
+}
+
+impl SearchStats {
+  fn update(&mut self, iterations: &[(u8, u128, f32, f32)]) {
+    for &(depth, time, ebf, first_move_fail_high) in iterations {
+      let index = usize::from(depth.max(1) - 1);
+      if self.depths.len() <= index {
+        self.depths.resize(index + 1, DepthStats::default());
+      }
+      let stats = &mut self.depths[index];
+      stats.iterations += 1;
+      stats.time += time;
+      if ebf > 0.0 {
+        stats.ebf_count += 1;
+        stats.ebf += ebf;
+      }
+      stats.first_move_fail_high += first_move_fail_high;
+    }
+  }
+
+  fn show(&self) -> String {
+    self
+      .depths
+      .iter()
+      .enumerate()
+      .filter(|(_, stats)| stats.iterations > 0)
+      .map(|(depth, stats)| {
+        format!(
+          "depth {}: {} searches, {} to depth, {:.2} EBF, {:.1}% first move fail highs",
+          depth + 1,
+          stats.iterations,
+          format_time(Millis::new(stats.time / u128::from(stats.iterations))),
+          stats.ebf / stats.ebf_count.max(1) as f32,
+          stats.first_move_fail_high * 100.0 / stats.iterations as f32
+        )
+      })
+      .collect::<Vec<String>>()
+      .join("\n")
+  }
+}
+
+impl AddAssign for SearchStats {
+  fn add_assign(&mut self, rhs: Self) {
+    if self.depths.len() < rhs.depths.len() {
+      self.depths.resize(rhs.depths.len(), DepthStats::default());
+    }
+    for (stats, rhs) in self.depths.iter_mut().zip(rhs.depths) {
+      stats.iterations += rhs.iterations;
+      stats.time += rhs.time;
+      stats.ebf_count += rhs.ebf_count;
+      stats.ebf += rhs.ebf;
+      stats.first_move_fail_high += rhs.first_move_fail_high;
+    }
+  }
+}
+
+// Parse the per iteration statistics reported by oxidation in debug mode
+// Each iteration is reported as depth, nodes, time, EBF and first move fail high rate
+fn parse_search_stats(info: &str) -> Option<Vec<(u8, u128, f32, f32)>> {
+  let words: Vec<&str> = info
+    .strip_prefix("searchstats")?
+    .split_whitespace()
+    .collect();
+  words
+    .chunks_exact(5)
+    .map(|iteration| {
+      Some((
+        iteration[0].parse().ok()?,
+        iteration[2].parse().ok()?,
+        iteration[3].parse().ok()?,
+        iteration[4].parse().ok()?,
+      ))
+    })
+    .collect()
+}
+
+// Repeated positions and games across the games from one starting position
+// Repeated games make the results look more certain than they are
+#[derive(Default)]
+struct DuplicationStats {
+  games: u32,
+  // games from the same opening as a different pair
+  duplicate_openings: u32,
+  // games with the same opening and moves as an earlier game
+  duplicate_games: u32,
+  // positions reached, counting each position once per game
+  positions: u32,
+  // positions reached with different moves to the first game reaching them
+  transpositions: u32,
+  // the pair that first played each opening
+  openings: HashMap<String, usize>,
+  // the hash of the opening and moves leading to the first time each position was reached
+  paths: HashMap<Hash, u64>,
+  // the hash of the opening and moves of each game
+  seen_games: HashSet<u64>,
+}
+
+impl DuplicationStats {
+  fn add(&mut self, pair: usize, start: &str, moves: &[Move]) {
+    self.games += 1;
+    if *self.openings.entry(start.to_owned()).or_insert(pair) != pair {
+      self.duplicate_openings += 1;
+    }
+    let Ok(mut board) = Board::new(start) else {
+      return;
+    };
+    let mut path = DefaultHasher::new();
+    start.hash(&mut path);
+    // repetitions within a game aren't transpositions
+    let mut reached = HashSet::new();
+    for mv in moves {
+      let Some(new_board) = board.move_if_legal(*mv) else {
+        break;
+      };
+      board = new_board;
+      mv.to_string().hash(&mut path);
+      if reached.insert(board.hash()) {
+        self.positions += 1;
+        let path = path.finish();
+        if *self.paths.entry(board.hash()).or_insert(path) != path {
+          self.transpositions += 1;
+        }
+      }
+    }
+    if !self.seen_games.insert(path.finish()) {
+      self.duplicate_games += 1;
+    }
+  }
+
+  fn duplicate_rate(&self) -> f32 {
+    self.duplicate_games as f32 / self.games.max(1) as f32
+  }
+
+  fn show(&self) -> String {
+    let positions = self.positions.max(1) as f32;
+    format!(
+      "{} positions, {:.1}% unique, {:.1}% transpositions, {} duplicate openings, {} duplicate games",
+      self.positions,
+      self.paths.len() as f32 * 100.0 / positions,
+      self.transpositions as f32 * 100.0 / positions,
+      self.duplicate_openings,
+      self.duplicate_games
+    )
+  }
 }
 
 fn sum_tuple<T: AddAssign>(accumulator: &mut (T, T, T), element: (T, T, T)) {
@@ -46,184 +287,106 @@ fn total_tuple<T: AddAssign>(tuple: (T, T, T)) -> T {
   result
 }
 
-fn process_move(
-  name: &'static str,
-  results: &Receiver<UlciResult>,
-  board: &mut Board,
-  moves: &mut Vec<Move>,
+// Statistics for one engine over a game
+#[derive(Default)]
+struct EngineStats {
+  moves: (u32, u32, u32),
+  depth: (u32, u32, u32),
+  // root node counts from the current search
+  root_nodes: Vec<(Move, usize)>,
+  root_node_stats: RootNodeStats,
+  search_stats: SearchStats,
+}
+
+// Collects statistics during a game with the champion as the first engine
+struct MatchObserver {
   move_threshold: u32,
-  current_board: &mut Board,
-  total_depth: &mut (u32, u32, u32),
-  move_count: &mut (u32, u32, u32),
-  search_time: &mut SearchTime,
-) {
-  let move_time = Instant::now();
-  let mut depth = 0;
-  while let Ok(result) = results.recv() {
-    match result {
-      UlciResult::Analysis(results) => {
-        let mut test_board = current_board.clone();
-        for pv_move in results.pv {
-          if let Some(new_board) = test_board.move_if_legal(pv_move) {
-            test_board = new_board;
-          } else {
-            println!(
-              "{name} made illegal pv move {} in position {}",
-              pv_move.to_string(),
-              test_board.to_string()
-            );
-            break;
-          }
-        }
-        depth = u32::from(results.depth);
-      }
-      UlciResult::AnalysisStopped(bestmove) => {
-        if let Some(new_board) = current_board.move_if_legal(bestmove) {
-          *current_board = new_board;
-          if current_board.halfmoves() == 0 {
-            *board = current_board.clone();
-            moves.clear();
-          } else {
-            moves.push(bestmove);
-          }
-        } else {
-          println!(
-            "{name} made illegal move {} in position {}",
-            bestmove.to_string(),
-            current_board.to_string()
-          );
-        }
-        let elapsed = move_time.elapsed();
-        let millis = elapsed.as_millis();
-        match search_time {
-          SearchTime::Increment(time, inc) => {
-            let excess = millis.saturating_sub(*time);
-            if excess > 0 {
-              println!(
-                "{name} took {} extra time in posiiton {}",
-                format_time(excess),
-                current_board.to_string()
-              );
-            }
-            *time = time.saturating_sub(millis) + *inc;
-          }
-          SearchTime::Asymmetric(wtime, winc, btime, binc) => {
-            let (time, inc) = if board.to_move() {
-              (wtime, winc)
-            } else {
-              (btime, binc)
-            };
-            let excess = millis.saturating_sub(*time);
-            if excess > 0 {
-              println!(
-                "{name} took {} extra time in posiiton {}",
-                format_time(excess),
-                current_board.to_string()
-              );
-            }
-            *time = time.saturating_sub(millis) + *inc;
-          }
-          SearchTime::Other(limits) => {
-            let excess = millis.saturating_sub(limits.time);
-            if excess >= 25 {
-              println!(
-                "{name} took {} extra time in posiiton {}",
-                format_time(excess),
-                current_board.to_string()
-              );
-            }
-          }
-          SearchTime::Infinite | SearchTime::Mate(_) => (),
-        }
-        let moves = board.moves();
-        if moves > 2 * move_threshold {
-          total_depth.2 += depth;
-          move_count.2 += 1;
-        } else if moves > move_threshold {
-          total_depth.1 += depth;
-          move_count.1 += 1;
-        } else {
-          total_depth.0 += depth;
-          move_count.0 += 1;
-        }
+  champion: EngineStats,
+  challenger: EngineStats,
+  state: State,
+  positions: HashSet<String>,
+}
+
+impl MatchObserver {
+  fn stats(&mut self, first_engine: bool) -> &mut EngineStats {
+    if first_engine {
+      &mut self.champion
+    } else {
+      &mut self.challenger
+    }
+  }
+}
+
+const fn engine_name(first_engine: bool) -> &'static str {
+  if first_engine {
+    "champion"
+  } else {
+    "challenger"
+  }
+}
+
+impl GameObserver for MatchObserver {
+  fn analysis(&mut self, first_engine: bool, board: &Board, result: &AnalysisResult) {
+    let mut test_board = board.clone();
+    for pv_move in &result.pv {
+      if let Some(new_board) = test_board.move_if_legal(*pv_move) {
+        test_board = new_board;
+      } else {
+        println!(
+          "{} made illegal pv move {} in position {}\n{}",
+          engine_name(first_engine),
+          pv_move.to_string(),
+          test_board.to_string(),
+          test_board.to_unicode(),
+        );
         break;
       }
-      UlciResult::Startup(_) | UlciResult::Info(..) => (),
     }
   }
-}
 
-fn play_game(
-  board: CompressedBoard,
-  move_count: u32,
-  champion_side: bool,
-  results: &Sender<GameInfo>,
-) {
-  let (champ_requests, champ_results) = load_engine(CHAMPION);
-  let (challenge_requests, challenge_results) = load_engine(CHALLENGER);
-  let (mut champ_moves, mut challenge_moves) = ((0, 0, 0), (0, 0, 0));
-  let (mut champ_depth, mut challenge_depth) = ((0, 0, 0), (0, 0, 0));
-  let mut positions = HashSet::new();
-  let mut board = board.load_from_thread();
-  let mut moves = Vec::new();
-  let mut current_board = board.clone();
-  let mut champ_tc = CHAMP_TIME;
-  let mut challenge_tc = CHALLENGE_TIME;
-  let mut state = State::new(0, &board, SEARCH_PARAMETERS, DEFAULT_PARAMETERS);
-  let mut debug = false;
-  let (_tx, rx_2) = channel();
-  let mut settings = SearchConfig::new_time(&board, SearchTime::Infinite, &rx_2, &mut debug);
-  while current_board.state() == Gamestate::InProgress {
-    if current_board.to_move() ^ champion_side {
-      challenge_requests
-        .send(Request::Analysis(AnalysisRequest {
-          fen: board.to_string(),
-          moves: moves.clone(),
-          time: challenge_tc,
-          searchmoves: Vec::new(),
-          new_game: false,
-        }))
-        .ok();
-      process_move(
-        "challenger",
-        &challenge_results,
-        &mut board,
-        &mut moves,
-        move_count,
-        &mut current_board,
-        &mut challenge_depth,
-        &mut challenge_moves,
-        &mut challenge_tc,
+  fn info(&mut self, first_engine: bool, info: &str) {
+    let stats = self.stats(first_engine);
+    if let Some(nodes) = parse_root_nodes(info) {
+      stats.root_nodes = nodes;
+    } else if let Some(iterations) = parse_search_stats(info) {
+      stats.search_stats.update(&iterations);
+    }
+  }
+
+  fn on_move(&mut self, event: &MoveEvent) {
+    if !event.overrun.is_zero() {
+      println!(
+        "{} took {} extra time in posiiton {}",
+        engine_name(event.first_engine),
+        format_time(event.overrun),
+        event.board.to_string()
       );
+    }
+    let move_threshold = self.move_threshold;
+    let stats = self.stats(event.first_engine);
+    let root_nodes = std::mem::take(&mut stats.root_nodes);
+    stats.root_node_stats.update(&root_nodes, event.mv);
+    let depth = event.analysis.map_or(0, |result| u32::from(result.depth));
+    let moves = event.board.moves();
+    if moves > 2 * move_threshold {
+      stats.depth.2 += depth;
+      stats.moves.2 += 1;
+    } else if moves > move_threshold {
+      stats.depth.1 += depth;
+      stats.moves.1 += 1;
     } else {
-      champ_requests
-        .send(Request::Analysis(AnalysisRequest {
-          fen: board.to_string(),
-          moves: moves.clone(),
-          time: champ_tc,
-          searchmoves: Vec::new(),
-          new_game: false,
-        }))
-        .ok();
-      process_move(
-        "champion",
-        &champ_results,
-        &mut board,
-        &mut moves,
-        move_count,
-        &mut current_board,
-        &mut champ_depth,
-        &mut champ_moves,
-        &mut champ_tc,
-      );
+      stats.depth.0 += depth;
+      stats.moves.0 += 1;
     }
-    if current_board.state() == Gamestate::InProgress
-      && current_board.halfmoves() < 30
-      && !current_board.in_check()
-    {
-      state.set_first_stack_entry(&current_board);
+    // record quiet positions for tuning
+    let board = event.board;
+    if board.state() == Gamestate::InProgress && board.halfmoves() < 30 && !board.in_check() {
+      let mut debug = false;
+      let (_tx, rx) = channel();
+      let mut settings = SearchConfig::new_time(board, SearchTime::Infinite, &rx, &mut debug);
+      self.state.set_first_stack_entry(board);
       let (pv, _) = quiescence(
-        &mut state,
+        &mut self.state,
         &mut settings,
         0,
         1,
@@ -232,33 +395,85 @@ fn play_game(
       )
       .unwrap_or((Vec::new(), Score::Centipawn(0)));
       if pv.is_empty() {
-        positions.insert(current_board.to_string());
+        self.positions.insert(board.to_string());
       }
     }
   }
-  let (result, points) = match current_board.state() {
-    Gamestate::InProgress => unreachable!(),
-    Gamestate::Checkmate(winner) | Gamestate::Elimination(winner) => (
-      if champion_side ^ winner {
-        GameResult::ChallengeWin
-      } else {
-        GameResult::ChampWin
-      },
-      if winner { 2 } else { 0 },
-    ),
-    Gamestate::Material | Gamestate::FiftyMove | Gamestate::Repetition | Gamestate::Stalemate => {
-      (GameResult::Draw, 1)
+}
+
+fn play_game(
+  board: CompressedBoard,
+  move_count: u32,
+  champion_side: bool,
+  pair: usize,
+  results: &Sender<GameInfo>,
+) {
+  let load = if SEARCH_STATISTICS {
+    load_engine_debug
+  } else {
+    load_engine
+  };
+  let champion = load(CHAMPION);
+  let challenger = load(CHALLENGER);
+  let board = board.load_from_thread();
+  let mut observer = MatchObserver {
+    move_threshold: move_count,
+    champion: EngineStats::default(),
+    challenger: EngineStats::default(),
+    state: State::new(0, &board, SEARCH_PARAMETERS, DEFAULT_PARAMETERS),
+    positions: HashSet::new(),
+  };
+  let record = tester::play_game(
+    &board,
+    &champion,
+    &challenger,
+    champion_side,
+    [CHAMP_TIME, CHALLENGE_TIME],
+    &mut observer,
+  );
+  match record.termination {
+    Termination::IllegalMove(mv) => {
+      let position = record
+        .moves
+        .iter()
+        .fold(board.clone(), |position, (played, _)| {
+          position.move_if_legal(*played).unwrap_or(position)
+        });
+      println!(
+        "{} made illegal move {} in game from {}\n{}",
+        engine_name(matches!(record.result, GameResult::ChallengeWin)),
+        mv.to_string(),
+        record.start,
+        position.to_unicode(),
+      );
     }
+    Termination::Disconnected => println!("Engine disconnected in game from {}", record.start),
+    Termination::Rules(_) => (),
+  }
+  let pgn = record.to_pgn(champion_side, engine_name(true), engine_name(false));
+  // points for white
+  let points = match record.result {
+    GameResult::ChampWin => 2 * u32::from(champion_side),
+    GameResult::Draw => 1,
+    GameResult::ChallengeWin => 2 * u32::from(!champion_side),
   };
   results
     .send(GameInfo {
-      result,
+      pair,
+      result: record.result,
       points,
-      champ_moves,
-      challenge_moves,
-      champ_depth,
-      challenge_depth,
-      positions,
+      champ_moves: observer.champion.moves,
+      challenge_moves: observer.challenger.moves,
+      champ_depth: observer.champion.depth,
+      challenge_depth: observer.challenger.depth,
+      champ_root_nodes: observer.champion.root_node_stats,
+      challenge_root_nodes: observer.challenger.root_node_stats,
+      champ_search_stats: observer.champion.search_stats,
+      challenge_search_stats: observer.challenger.search_stats,
+      positions: observer.positions,
+      moves: record.moves.iter().map(|(mv, _)| *mv).collect(),
+      start: record.start,
+      pgn,
     })
     .ok();
 }
@@ -268,19 +483,20 @@ fn test_position(
   position: &StartingPosition,
   moves: u32,
   positions: &mut HashMap<String, (u32, u32)>,
+  games: &mut Vec<String>,
   friendly_fire: bool,
 ) {
   println!("Testing {name}");
   let pool = get_threadpool();
   let champion_side: bool = thread_rng().gen();
   let (tx, rx) = channel();
-  for _ in 0..GAME_PAIR_COUNT {
+  for pair in 0..GAME_PAIR_COUNT {
     let position = position.get_position(friendly_fire);
     let position_2 = position.clone();
     let tx = tx.clone();
     let tx_2 = tx.clone();
-    pool.execute(move || play_game(position, moves, champion_side, &tx));
-    pool.execute(move || play_game(position_2, moves, !champion_side, &tx_2));
+    pool.execute(move || play_game(position, moves, champion_side, pair, &tx));
+    pool.execute(move || play_game(position_2, moves, !champion_side, pair, &tx_2));
   }
   // to make sure it actually finishes
   drop(tx);
@@ -288,18 +504,33 @@ fn test_position(
   let (mut white_win, mut black_win) = (0, 0);
   let (mut champ_moves, mut challenge_moves) = ((0, 0, 0), (0, 0, 0));
   let (mut champ_depth, mut challenge_depth) = ((0, 0, 0), (0, 0, 0));
+  let mut champ_root_nodes = RootNodeStats::default();
+  let mut challenge_root_nodes = RootNodeStats::default();
+  let mut champ_search_stats = SearchStats::default();
+  let mut challenge_search_stats = SearchStats::default();
+  let mut pentanomial = Pentanomial::default();
+  let mut duplication = DuplicationStats::default();
+  // the first game to finish from each pair still being played
+  let mut unpaired = HashMap::new();
   for result in &rx {
     match result.result {
       GameResult::ChampWin => win += 1,
       GameResult::Draw => draw += 1,
       GameResult::ChallengeWin => loss += 1,
     };
+    if let Some(first) = unpaired.remove(&result.pair) {
+      pentanomial.add(&first, &result.result);
+    } else {
+      unpaired.insert(result.pair, result.result);
+    }
     let game_score = result.points;
     match game_score {
       0 => black_win += 1,
       2 => white_win += 1,
       _ => (),
     }
+    duplication.add(result.pair, &result.start, &result.moves);
+    games.push(result.pgn);
     for position in result.positions {
       if let Some(result) = positions.get_mut(&position) {
         result.0 += 1;
@@ -312,11 +543,16 @@ fn test_position(
     sum_tuple(&mut challenge_moves, result.challenge_moves);
     sum_tuple(&mut champ_depth, result.champ_depth);
     sum_tuple(&mut challenge_depth, result.challenge_depth);
+    champ_root_nodes += result.champ_root_nodes;
+    challenge_root_nodes += result.challenge_root_nodes;
+    champ_search_stats += result.champ_search_stats;
+    challenge_search_stats += result.challenge_search_stats;
   }
   assert_eq!(win + draw + loss, GAME_PAIR_COUNT * 2);
   let move_count = total_tuple(champ_moves) + total_tuple(challenge_moves);
   let average_move_count = move_count as usize / GAME_PAIR_COUNT / 2;
   println!("Champion vs Challenger: +{win} ={draw} -{loss}, {average_move_count} moves per game");
+  println!("Challenger vs Champion: {}", pentanomial.show());
   println!("White vs Black: +{white_win} ={draw} -{black_win}");
   println!(
     "Average opening depth: Champion: {:.2}, Challenger: {:.2}",
@@ -333,17 +569,35 @@ fn test_position(
     champ_depth.2 as f32 / champ_moves.2 as f32,
     challenge_depth.2 as f32 / challenge_moves.2 as f32
   );
+  println!("Positions: {}", duplication.show());
+  if duplication.duplicate_rate() > DUPLICATE_THRESHOLD {
+    println!(
+      "Warning: {:.1}% of games are duplicates, the opening filter may be too strict",
+      duplication.duplicate_rate() * 100.0
+    );
+  }
+  if SEARCH_STATISTICS {
+    println!("Champion root nodes: {}", champ_root_nodes.show());
+    println!("Challenger root nodes: {}", challenge_root_nodes.show());
+    println!("Champion search stats:\n{}", champ_search_stats.show());
+    println!(
+      "Challenger search stats:\n{}",
+      challenge_search_stats.show()
+    );
+  }
 }
 
 fn main() {
   for (name, position, moves) in POSITIONS {
     let mut positions = HashMap::new();
-    test_position(name, position, *moves, &mut positions, false);
+    let mut games = Vec::new();
+    test_position(name, position, *moves, &mut positions, &mut games, false);
     test_position(
       &format!("friendly {name}"),
       position,
       *moves,
       &mut positions,
+      &mut games,
       true,
     );
     let data = positions
@@ -352,5 +606,6 @@ fn main() {
       .collect::<Vec<String>>()
       .join("\n");
     write(format!("target/release/{name}.txt"), data).expect("Writing file failed");
+    write(format!("target/release/{name}.pgn"), games.join("\n")).expect("Writing file failed");
   }
 }
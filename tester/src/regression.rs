@@ -0,0 +1,198 @@
+use liberty_chess::positions::{
+  AFRICAN, CAPABLANCA, CAPABLANCA_RECTANGLE, DOUBLE_CHESS, ELIMINATION, HORDE, LIBERTY_CHESS,
+  LOADED_BOARD, MINI, MONGOL, NARNIA, STARTPOS, TRUMP,
+};
+use liberty_chess::{perft, Board};
+use oxidation::parameters::DEFAULT_PARAMETERS;
+use oxidation::search::SEARCH_PARAMETERS;
+use oxidation::{
+  bench, search, Output, SearchConfig, State, BENCH_DEPTH, BENCH_POSITIONS, HASH_SIZE,
+};
+use std::env::args;
+use std::fs::{read_to_string, write};
+use std::process::ExitCode;
+use std::sync::mpsc::channel;
+use std::time::Instant;
+use ulci::{Limits, SearchTime};
+
+// Where the expected output is stored, regenerate it with `--update`
+const EXPECTED_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/regression.txt");
+
+// perft stops at the first depth with at least this many nodes
+const PERFT_NODES: usize = 1_000_000;
+// nodes searched to find the best move in each position
+const SEARCH_NODES: usize = 20_000;
+// plies played from each preset before checking the FEN round-trips
+const FEN_PLIES: usize = 6;
+
+const PRESETS: &[(&str, &str)] = &[
+  ("Standard", STARTPOS),
+  ("Capablanca", CAPABLANCA_RECTANGLE),
+  ("Capablanca 10x10", CAPABLANCA),
+  ("Liberty", LIBERTY_CHESS),
+  ("Mini", MINI),
+  ("Mongol", MONGOL),
+  ("African", AFRICAN),
+  ("Narnia", NARNIA),
+  ("Trump", TRUMP),
+  ("Loaded", LOADED_BOARD),
+  ("Double", DOUBLE_CHESS),
+  ("Horde", HORDE),
+  ("Elimination", ELIMINATION),
+];
+
+fn perft_suite(output: &mut Vec<String>) {
+  for (name, fen) in PRESETS {
+    let mut board = Board::new(fen).expect("Invalid preset");
+    board.skip_checkmate = true;
+    let mut counts = Vec::new();
+    let mut depth = 1;
+    loop {
+      let nodes = perft(&board, depth);
+      counts.push(nodes.to_string());
+      if nodes >= PERFT_NODES || nodes == 0 {
+        break;
+      }
+      depth += 1;
+    }
+    output.push(format!("perft {name}: {}", counts.join(" ")));
+  }
+}
+
+// Play the first legal move repeatedly and check every position survives a round-trip
+fn fen_round_trip(output: &mut Vec<String>) {
+  for (name, fen) in PRESETS {
+    let mut board = Board::new(fen).expect("Invalid preset");
+    let mut result = if board.to_string() == *fen {
+      "ok".to_owned()
+    } else {
+      format!("preset written as {}", board.to_string())
+    };
+    for _ in 0..FEN_PLIES {
+      let Some(next) = board.generate_legal().into_iter().next() else {
+        break;
+      };
+      board = next;
+      let fen = board.to_string();
+      match Board::new(&fen) {
+        Ok(loaded) if loaded.to_string() == fen => (),
+        Ok(loaded) => result = format!("{fen} reloaded as {}", loaded.to_string()),
+        Err(error) => result = format!("{fen} failed to load: {error:?}"),
+      }
+    }
+    output.push(format!("fen {name}: {result} {}", board.to_string()));
+  }
+}
+
+fn bench_signature(output: &mut Vec<String>) {
+  let (_tx, rx) = channel();
+  let (results, _) = channel();
+  let mut debug = false;
+  let mut state = State::new(
+    HASH_SIZE,
+    &Board::new(STARTPOS).unwrap(),
+    SEARCH_PARAMETERS,
+    DEFAULT_PARAMETERS,
+  );
+  let mut nodes = 0;
+  // the same positions as the engine's bench command
+  for (position, depth_offset) in BENCH_POSITIONS {
+    let depth = (BENCH_DEPTH + depth_offset) as u8;
+    let mut board = Board::new(position).expect("Invalid bench position");
+    nodes += bench(
+      &mut state,
+      &mut board,
+      depth,
+      &mut debug,
+      &rx,
+      Output::Channel(&results),
+    );
+    board.friendly_fire = true;
+    nodes += bench(
+      &mut state,
+      &mut board,
+      depth,
+      &mut debug,
+      &rx,
+      Output::Channel(&results),
+    );
+  }
+  output.push(format!("bench: {nodes}"));
+}
+
+fn best_moves(output: &mut Vec<String>) {
+  let (_tx, rx) = channel();
+  let (results, _) = channel();
+  let mut debug = false;
+  for (name, fen) in PRESETS {
+    let mut board = Board::new(fen).expect("Invalid preset");
+    let mut state = State::new(HASH_SIZE, &board, SEARCH_PARAMETERS, DEFAULT_PARAMETERS);
+    let time = SearchTime::Other(Limits {
+      nodes: SEARCH_NODES,
+      ..Limits::default()
+    });
+    let mut settings = SearchConfig::new_time(&board, time, &rx, &mut debug);
+    let pv = search(
+      &mut state,
+      &mut settings,
+      &mut board,
+      &[],
+      1,
+      Output::Channel(&results),
+    );
+    let best = pv.first().map_or("0000".to_owned(), ToString::to_string);
+    output.push(format!("bestmove {name}: {best}"));
+  }
+}
+
+fn main() -> ExitCode {
+  let update = args().any(|arg| arg == "--update");
+  let start = Instant::now();
+  let mut output = Vec::new();
+  perft_suite(&mut output);
+  fen_round_trip(&mut output);
+  bench_signature(&mut output);
+  best_moves(&mut output);
+  println!("Finished in {}s", start.elapsed().as_secs());
+
+  let output = output.join("\n") + "\n";
+  if update {
+    return match write(EXPECTED_PATH, output) {
+      Ok(()) => {
+        println!("Expected output written to {EXPECTED_PATH}");
+        ExitCode::SUCCESS
+      }
+      Err(error) => {
+        println!("Failed to write {EXPECTED_PATH}: {error}");
+        ExitCode::FAILURE
+      }
+    };
+  }
+  let Ok(expected) = read_to_string(EXPECTED_PATH) else {
+    println!("No expected output at {EXPECTED_PATH}, run with --update to create it");
+    return ExitCode::FAILURE;
+  };
+  let expected: Vec<&str> = expected.lines().collect();
+  let actual: Vec<&str> = output.lines().collect();
+  let mut differences = 0;
+  for i in 0..expected.len().max(actual.len()) {
+    let (old, new) = (expected.get(i), actual.get(i));
+    if old != new {
+      differences += 1;
+      if let Some(old) = old {
+        println!("- {old}");
+      }
+      if let Some(new) = new {
+        println!("+ {new}");
+      }
+    }
+  }
+  if differences == 0 {
+    println!("All results match");
+    ExitCode::SUCCESS
+  } else {
+    println!("{differences} results differ from {EXPECTED_PATH}");
+    println!("If the changes are intended, run with --update and commit the new results");
+    ExitCode::FAILURE
+  }
+}
@@ -1,4 +1,4 @@
-use liberty_chess::clock::format_time;
+use liberty_chess::clock::{format_time, Millis};
 use liberty_chess::threading::CompressedBoard;
 use liberty_chess::{Board, Gamestate};
 use oxidation::glue::process_position;
@@ -8,7 +8,7 @@ use oxidation::{State, HASH_SIZE};
 use rand::{thread_rng, Rng};
 use std::sync::mpsc::{channel, Sender};
 use std::time::Instant;
-use tester::{get_threadpool, GameResult, POSITIONS, STC};
+use tester::{get_threadpool, update_time, GameResult, POSITIONS, STC};
 use ulci::server::UlciResult;
 use ulci::SearchTime;
 
@@ -39,6 +39,18 @@ impl Spsa<f32> for SearchParameters {
       lmr_factor: rng.gen_range(-0.04..0.04),
       lmr_pv_reduction: rng.gen_range(-0.1..0.1),
       lmr_improving_reduction: rng.gen_range(-0.1..0.1),
+      history_pruning_depth: rng.gen_range(-0.3..0.3),
+      history_pruning_margin: rng.gen_range(-100.0..100.0),
+      see_pruning_depth: rng.gen_range(-0.3..0.3),
+      root_widening_base: rng.gen_range(-3.0..3.0),
+      root_widening_factor: rng.gen_range(-0.8..0.8),
+      tm_fail_low_margin: rng.gen_range(-5.0..5.0),
+      tm_fail_low_extension: rng.gen_range(-0.15..0.15),
+      tm_panic_margin: rng.gen_range(-15.0..15.0),
+      tm_panic_extension: rng.gen_range(-0.25..0.25),
+      tm_stability_margin: rng.gen_range(-3.0..3.0),
+      tm_stability_iterations: rng.gen_range(-0.5..0.5),
+      tm_stability_scale: rng.gen_range(-0.06..0.06),
     }
   }
 
@@ -57,7 +69,19 @@ fn process_move(state: &mut State, board: &mut Board, search_time: &mut SearchTi
   let move_time = Instant::now();
   let (tx, rx) = channel();
   let (_tx_2, rx_2) = channel();
-  process_position(&tx, &rx_2, board.send_to_thread(), *search_time, state, 1);
+  process_position(
+    &tx,
+    &rx_2,
+    board.send_to_thread(),
+    *search_time,
+    &[],
+    &[],
+    state,
+    &mut 1,
+    false,
+    0,
+    false,
+  );
   while let Ok(result) = rx.recv() {
     match result {
       UlciResult::Analysis(results) => {
@@ -67,9 +91,10 @@ fn process_move(state: &mut State, board: &mut Board, search_time: &mut SearchTi
             test_board = new_board;
           } else {
             println!(
-              "illegal pv move {} in position {}",
+              "illegal pv move {} in position {}\n{}",
               pv_move.to_string(),
-              test_board.to_string()
+              test_board.to_string(),
+              test_board.to_unicode(),
             );
             break;
           }
@@ -80,56 +105,24 @@ fn process_move(state: &mut State, board: &mut Board, search_time: &mut SearchTi
           *board = new_board;
         } else {
           println!(
-            "illegal move {} in position {}",
+            "illegal move {} in position {}\n{}",
             bestmove.to_string(),
-            board.to_string()
+            board.to_string(),
+            board.to_unicode(),
           );
         }
-        let elapsed = move_time.elapsed();
-        let millis = elapsed.as_millis();
-        match search_time {
-          SearchTime::Increment(time, inc) => {
-            let excess = millis.saturating_sub(*time);
-            if excess > 0 {
-              println!(
-                "{} extra time in posiiton {}",
-                format_time(excess),
-                board.to_string()
-              );
-            }
-            *time = time.saturating_sub(millis) + *inc;
-          }
-          SearchTime::Asymmetric(wtime, winc, btime, binc) => {
-            let (time, inc) = if board.to_move() {
-              (wtime, winc)
-            } else {
-              (btime, binc)
-            };
-            let excess = millis.saturating_sub(*time);
-            if excess > 0 {
-              println!(
-                "{} extra time in posiiton {}",
-                format_time(excess),
-                board.to_string()
-              );
-            }
-            *time = time.saturating_sub(millis) + *inc;
-          }
-          SearchTime::Other(limits) => {
-            let excess = millis.saturating_sub(limits.time);
-            if excess >= 25 {
-              println!(
-                "{} extra time in posiiton {}",
-                format_time(excess),
-                board.to_string()
-              );
-            }
-          }
-          SearchTime::Infinite | SearchTime::Mate(_) => (),
+        let millis = Millis::from(move_time.elapsed());
+        let excess = update_time(search_time, !board.to_move(), millis);
+        if !excess.is_zero() {
+          println!(
+            "{} extra time in posiiton {}",
+            format_time(excess),
+            board.to_string()
+          );
         }
         break;
       }
-      UlciResult::Startup(_) | UlciResult::Info(..) => (),
+      UlciResult::Startup(_) | UlciResult::Info(..) | UlciResult::Perft(..) => (),
     }
   }
 }
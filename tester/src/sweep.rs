@@ -0,0 +1,165 @@
+use liberty_chess::parsing::to_char;
+use liberty_chess::threading::CompressedBoard;
+use liberty_chess::{Piece, BISHOP, KNIGHT, QUEEN, ROOK};
+use oxidation::parameters::{Parameters, DEFAULT_PARAMETERS};
+use oxidation::search::{SearchParameters, SEARCH_PARAMETERS};
+use std::sync::mpsc::{channel, Sender};
+use std::time::Instant;
+use tester::{
+  elo_difference, get_threadpool, in_process_engine, GameResult, RuleFlags, POSITIONS, VSTC,
+};
+use ulci::SearchTime;
+
+// Number of game pairs to play from each position with each set of rules
+const GAME_PAIR_COUNT: usize = 2;
+
+const TIME: SearchTime = VSTC;
+
+// The baseline parameters
+const SEARCH_A: SearchParameters = SEARCH_PARAMETERS;
+const PARAMETERS_A: Parameters<i32> = DEFAULT_PARAMETERS;
+
+// The parameters being tested, edit these to try a change
+const SEARCH_B: SearchParameters = SEARCH_PARAMETERS;
+const PARAMETERS_B: Parameters<i32> = DEFAULT_PARAMETERS;
+
+// The values of each flag to sweep, every combination is played
+const FRIENDLY_FIRE: &[bool] = &[false, true];
+const PAWN_MOVES: &[Option<usize>] = &[None, Some(1), Some(3)];
+const PROMOTIONS: &[Option<&[Piece]>] = &[None, Some(&[QUEEN]), Some(&[ROOK, BISHOP, KNIGHT])];
+
+// Wins, draws and losses for engine B
+#[derive(Clone, Copy, Default)]
+struct Tally {
+  wins: u32,
+  draws: u32,
+  losses: u32,
+}
+
+impl Tally {
+  fn add(&mut self, result: &GameResult) {
+    match result {
+      GameResult::ChampWin => self.losses += 1,
+      GameResult::Draw => self.draws += 1,
+      GameResult::ChallengeWin => self.wins += 1,
+    }
+  }
+
+  fn merge(&mut self, other: &Self) {
+    self.wins += other.wins;
+    self.draws += other.draws;
+    self.losses += other.losses;
+  }
+
+  fn games(&self) -> u32 {
+    self.wins + self.draws + self.losses
+  }
+
+  fn show(&self, name: &str) -> String {
+    let score =
+      (f64::from(self.wins) + f64::from(self.draws) / 2.0) / f64::from(self.games().max(1));
+    let elo = match elo_difference(score) {
+      Some(elo) => format!("{elo:+.0}"),
+      None if score > 0.5 => "+inf".to_owned(),
+      None => "-inf".to_owned(),
+    };
+    format!(
+      "{name}: +{} ={} -{} score {:.1}% elo {elo}",
+      self.wins,
+      self.draws,
+      self.losses,
+      score * 100.0
+    )
+  }
+}
+
+fn rule_matrix() -> Vec<RuleFlags> {
+  let mut matrix = Vec::new();
+  for friendly_fire in FRIENDLY_FIRE {
+    for pawn_moves in PAWN_MOVES {
+      for promotions in PROMOTIONS {
+        matrix.push(RuleFlags {
+          friendly_fire: *friendly_fire,
+          pawn_moves: *pawn_moves,
+          promotions: *promotions,
+        });
+      }
+    }
+  }
+  matrix
+}
+
+fn play_game(
+  board: CompressedBoard,
+  a_side: bool,
+  config: usize,
+  results: &Sender<(usize, GameResult)>,
+) {
+  let board = board.load_from_thread();
+  let engine_a = in_process_engine(SEARCH_A, PARAMETERS_A);
+  let engine_b = in_process_engine(SEARCH_B, PARAMETERS_B);
+  let record = tester::play_game(&board, &engine_a, &engine_b, a_side, [TIME, TIME], &mut ());
+  results.send((config, record.result)).ok();
+}
+
+fn main() {
+  let start = Instant::now();
+  let pool = get_threadpool();
+  let (tx, rx) = channel();
+  let matrix = rule_matrix();
+  for (config, rules) in matrix.iter().enumerate() {
+    for (_, position, _) in POSITIONS {
+      for _ in 0..GAME_PAIR_COUNT {
+        let position = position.get_position_with_rules(rules);
+        let position_2 = position.clone();
+        let tx = tx.clone();
+        let tx_2 = tx.clone();
+        pool.execute(move || play_game(position, true, config, &tx));
+        pool.execute(move || play_game(position_2, false, config, &tx_2));
+      }
+    }
+  }
+  // to make sure it actually finishes
+  drop(tx);
+  let mut tallies = vec![Tally::default(); matrix.len()];
+  for (config, result) in &rx {
+    tallies[config].add(&result);
+  }
+
+  println!("B vs A by rule set:");
+  for (rules, tally) in matrix.iter().zip(&tallies) {
+    println!("  {}", tally.show(&rules.describe()));
+  }
+
+  // each flag value combines every rule set using it, so regressions in one rule stand out
+  println!("B vs A by flag:");
+  let mut flags: Vec<(String, Tally)> = Vec::new();
+  for (rules, tally) in matrix.iter().zip(&tallies) {
+    let names = [
+      format!("friendly fire {}", rules.friendly_fire),
+      rules
+        .pawn_moves
+        .map_or("default pawn moves".to_owned(), |moves| {
+          format!("pawn moves {moves}")
+        }),
+      rules
+        .promotions
+        .map_or("default promotions".to_owned(), |promotions| {
+          let pieces: String = promotions.iter().map(|piece| to_char(*piece)).collect();
+          format!("promotions {pieces}")
+        }),
+    ];
+    for name in names {
+      match flags.iter_mut().find(|(flag, _)| *flag == name) {
+        Some((_, total)) => total.merge(tally),
+        None => flags.push((name, *tally)),
+      }
+    }
+  }
+  for (name, tally) in &flags {
+    println!("  {}", tally.show(name));
+  }
+
+  let games: u32 = tallies.iter().map(Tally::games).sum();
+  println!("Finished {games} games in {}s", start.elapsed().as_secs());
+}
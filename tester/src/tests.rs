@@ -0,0 +1,205 @@
+use crate::{DataRecord, GameResult, Pentanomial, RuleFlags, Sprt, SprtResult};
+use liberty_chess::positions::STARTPOS;
+use liberty_chess::{KNIGHT, QUEEN};
+
+#[test]
+fn rule_flags_default_fields() {
+  let rules = RuleFlags {
+    friendly_fire: false,
+    pawn_moves: Some(3),
+    promotions: Some(&[QUEEN, KNIGHT]),
+  };
+  assert_eq!(
+    rules.apply(STARTPOS),
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 3,2,1 qn"
+  );
+}
+
+#[test]
+fn rule_flags_friendly_fire() {
+  let rules = RuleFlags {
+    friendly_fire: true,
+    ..RuleFlags::default()
+  };
+  assert_eq!(
+    rules.apply("8/8/8/8/8/8/8/8"),
+    "8/8/8/8/8/8/8/8 w - - 0 1 2,2,1 qrbn ff"
+  );
+}
+
+const SPRT: Sprt = Sprt {
+  elo0: 0.0,
+  elo1: 10.0,
+  alpha: 0.05,
+  beta: 0.05,
+};
+
+#[test]
+fn sprt_bounds() {
+  let (lower, upper) = SPRT.bounds();
+  assert!((lower + 2.944).abs() < 0.001, "{lower}");
+  assert!((upper - 2.944).abs() < 0.001, "{upper}");
+}
+
+#[test]
+fn sprt_llr_needs_varied_results() {
+  assert_eq!(SPRT.llr(&Pentanomial::default()), 0.0);
+  let drawn = Pentanomial {
+    pairs: [0, 0, 100, 0, 0],
+  };
+  assert_eq!(SPRT.llr(&drawn), 0.0);
+}
+
+#[test]
+fn sprt_llr_sign() {
+  // evenly matched results favour H0
+  let even = Pentanomial {
+    pairs: [10, 40, 100, 40, 10],
+  };
+  assert!(SPRT.llr(&even) < 0.0);
+  // the same results with the challenger scoring more often favour H1
+  let better = Pentanomial {
+    pairs: [10, 30, 100, 50, 10],
+  };
+  assert!(SPRT.llr(&better) > 0.0);
+  // more pairs with the same spread give stronger evidence
+  let more = Pentanomial {
+    pairs: better.pairs.map(|pairs| pairs * 4),
+  };
+  assert!((SPRT.llr(&more) - 4.0 * SPRT.llr(&better)).abs() < 1e-9);
+}
+
+#[test]
+fn sprt_status() {
+  let small = Pentanomial {
+    pairs: [1, 3, 10, 5, 1],
+  };
+  assert_eq!(SPRT.status(&small), SprtResult::Continue);
+  let better = Pentanomial {
+    pairs: [100, 300, 1000, 500, 100],
+  };
+  assert_eq!(SPRT.status(&better), SprtResult::H1);
+  let worse = Pentanomial {
+    pairs: [100, 500, 1000, 300, 100],
+  };
+  assert_eq!(SPRT.status(&worse), SprtResult::H0);
+}
+
+#[test]
+fn pentanomial_add() {
+  let mut results = Pentanomial::default();
+  results.add(&GameResult::ChampWin, &GameResult::ChampWin);
+  results.add(&GameResult::ChampWin, &GameResult::Draw);
+  results.add(&GameResult::Draw, &GameResult::Draw);
+  results.add(&GameResult::ChallengeWin, &GameResult::ChampWin);
+  results.add(&GameResult::ChallengeWin, &GameResult::Draw);
+  results.add(&GameResult::ChallengeWin, &GameResult::ChallengeWin);
+  assert_eq!(results.pairs, [1, 1, 2, 1, 1]);
+  assert_eq!(results.count(), 6);
+}
+
+#[test]
+fn pentanomial_elo() {
+  assert!(Pentanomial::default().elo().is_none());
+  assert!(Pentanomial::default().normalized_elo().is_none());
+  // a perfect score has no finite Elo difference
+  let perfect = Pentanomial {
+    pairs: [0, 0, 0, 0, 10],
+  };
+  assert!(perfect.elo().is_none());
+  let even = Pentanomial {
+    pairs: [10, 40, 100, 40, 10],
+  };
+  let (elo, margin) = even.elo().unwrap();
+  assert!(elo.abs() < 1e-9, "{elo}");
+  assert!(margin > 0.0);
+  let (normalized, _) = even.normalized_elo().unwrap();
+  assert!(normalized.abs() < 1e-9, "{normalized}");
+  let better = Pentanomial {
+    pairs: [10, 30, 100, 50, 10],
+  };
+  assert!(better.elo().unwrap().0 > 0.0);
+  assert!(better.normalized_elo().unwrap().0 > 0.0);
+  // the margin shrinks with more pairs
+  let more = Pentanomial {
+    pairs: better.pairs.map(|pairs| pairs * 4),
+  };
+  assert!(more.elo().unwrap().1 < better.elo().unwrap().1);
+}
+
+#[test]
+fn pentanomial_show() {
+  assert_eq!(
+    Pentanomial::default().show(),
+    "pairs [0, 0, 0, 0, 0] elo unknown nelo unknown"
+  );
+  let results = Pentanomial {
+    pairs: [0, 0, 3, 1, 0],
+  };
+  assert_eq!(
+    results.show(),
+    "pairs [0, 0, 3, 1, 0] elo +43.7 ± 76.1 nelo +141.8 ± 240.8"
+  );
+}
+
+#[test]
+fn data_record_round_trip() {
+  let records = [
+    DataRecord {
+      fen: STARTPOS.to_owned(),
+      score: 25,
+      result: 1,
+    },
+    DataRecord {
+      fen: "8/8/8/3k4/8/8/8/R3K3 w - - 0 1".to_owned(),
+      score: i16::MIN,
+      result: 0,
+    },
+  ];
+  let mut data = Vec::new();
+  for record in &records {
+    record.write(&mut data).unwrap();
+  }
+  let mut input = data.as_slice();
+  for record in &records {
+    assert_eq!(DataRecord::read(&mut input).unwrap().as_ref(), Some(record));
+  }
+  assert_eq!(DataRecord::read(&mut input).unwrap(), None);
+}
+
+#[test]
+fn data_record_eof() {
+  assert_eq!(DataRecord::read(&mut [].as_slice()).unwrap(), None);
+  let record = DataRecord {
+    fen: STARTPOS.to_owned(),
+    score: -300,
+    result: 2,
+  };
+  let mut data = Vec::new();
+  record.write(&mut data).unwrap();
+  // a record cut off anywhere is an error, including partway through the length
+  for end in 1..data.len() {
+    let error = DataRecord::read(&mut &data[..end]).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof, "{end}");
+  }
+}
+
+#[test]
+fn data_record_invalid() {
+  let record = DataRecord {
+    fen: STARTPOS.to_owned(),
+    score: 0,
+    result: 3,
+  };
+  let mut data = Vec::new();
+  record.write(&mut data).unwrap();
+  let error = DataRecord::read(&mut data.as_slice()).unwrap_err();
+  assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+  let record = DataRecord {
+    fen: "x".repeat(70_000),
+    score: 0,
+    result: 1,
+  };
+  let error = record.write(&mut Vec::new()).unwrap_err();
+  assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
@@ -4,17 +4,53 @@ use oxidation::get_promotion_values;
 use oxidation::parameters::{Parameters, DEFAULT_PARAMETERS};
 use rayon::prelude::*;
 use rayon::ThreadPoolBuilder;
-use std::fs::{read_dir, read_to_string};
+use std::env::args;
+use std::fs::{read_dir, read_to_string, File};
+use std::io::{BufReader, Error, ErrorKind};
+use std::process::exit;
 use std::time::Instant;
-use tester::POSITIONS;
+use tester::{DataRecord, POSITIONS};
 
 const ITERATION_COUNT: i32 = 210;
 const PRINT_FREQUENCY: i32 = 30;
+const OPTIMIZER: Optimizer = Optimizer::Adam;
+// Nesterov momentum settings
 const LR: f64 = 15000.0;
 const MOMENTUM_FACTOR: f64 = 0.85;
+// Adam settings, the learning rate is roughly the most a parameter changes per iteration
+const ADAM_LR: f64 = 1.0;
+const BETA1: f64 = 0.9;
+const BETA2: f64 = 0.999;
+const EPSILON: f64 = 1e-8;
 
 type GameData = Vec<(Features, Vec<Piece>, bool, u32, f64)>;
 
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Optimizer {
+  Momentum,
+  Adam,
+}
+
+// Running averages of the gradient and its square for each parameter
+#[derive(Default)]
+struct Adam {
+  moment: Parameters<f64>,
+  velocity: Parameters<f64>,
+  steps: i32,
+}
+
+impl Adam {
+  fn step(&mut self, gradient: Parameters<f64>) -> Parameters<f64> {
+    self.steps += 1;
+    self.moment = self.moment * BETA1 + gradient * (1.0 - BETA1);
+    self.velocity = self.velocity * BETA2 + gradient.map(|x| x * x) * (1.0 - BETA2);
+    // correct for the averages starting at 0
+    let moment = self.moment / (1.0 - BETA1.powi(self.steps));
+    let velocity = self.velocity / (1.0 - BETA2.powi(self.steps));
+    (moment / velocity.map(|x| x.sqrt() + EPSILON)) * ADAM_LR
+  }
+}
+
 fn calculate_gradients_batch(
   data: &Vec<(f64, GameData)>,
   parameters: &Parameters<f64>,
@@ -105,6 +141,68 @@ fn setup_threadpool() {
     .expect("Failed to set stack size");
 }
 
+// Extract the features for a position with the score of the game from white's perspective
+fn load_record(fen: &str, games: u32, score: f64) -> (Features, Vec<Piece>, bool, u32, f64) {
+  let board = Board::new(fen).expect("Invalid position");
+  let features = extract_features(board.board());
+  (
+    features,
+    board.promotion_options().clone(),
+    board.to_move(),
+    games,
+    score,
+  )
+}
+
+// Parse a game result as 1-0, 1/2-1/2, 0-1 or the score for white
+fn parse_result(result: &str) -> Option<f64> {
+  match result.trim() {
+    "1-0" => Some(1.0),
+    "1/2-1/2" => Some(0.5),
+    "0-1" => Some(0.0),
+    score => score
+      .parse()
+      .ok()
+      .filter(|score| (0.0..=1.0).contains(score)),
+  }
+}
+
+// Find the scaling constant that best fits the evaluations to the results
+fn calculate_k(name: &str, data: &GameData) -> f64 {
+  let mut best_k = 0.0;
+  let (loss, positions) = calculate_loss(best_k, data, &DEFAULT_PARAMETERS);
+  let mut best_loss = loss / f64::from(positions);
+  let mut delta = 0.09;
+  println!("k {best_k} loss {best_loss:.6}");
+  while delta > 0.0001 {
+    let mut changed = false;
+    let k = best_k + delta;
+    let (loss, positions) = calculate_loss(k, data, &DEFAULT_PARAMETERS);
+    let average_loss = loss / f64::from(positions);
+    if average_loss < best_loss {
+      println!("Position {name} k {k} loss {average_loss:.6}");
+      best_loss = average_loss;
+      best_k = k;
+      changed = true;
+    } else {
+      let k = best_k - delta;
+      let (loss, positions) = calculate_loss(k, data, &DEFAULT_PARAMETERS);
+      let average_loss = loss / f64::from(positions);
+      if average_loss < best_loss {
+        println!("Position {name} k {k} loss {average_loss:.6}");
+        best_loss = average_loss;
+        best_k = k;
+        changed = true;
+      }
+    }
+    if !changed {
+      delta /= 3.0;
+    }
+  }
+  println!("Final k for {name}: {best_k}");
+  best_k
+}
+
 fn process_position(position: &&str, data: &mut Vec<(f64, GameData)>, total_positions: &mut usize) {
   println!("Position {position}");
   let mut processed_data = Vec::new();
@@ -119,7 +217,7 @@ fn process_position(position: &&str, data: &mut Vec<(f64, GameData)>, total_posi
         .par_iter()
         .map(|line| {
           let mut line = line.split(';');
-          let board = Board::new(line.next().expect("missing FEN")).expect("Invalid position");
+          let fen = line.next().expect("missing FEN");
           let games: u32 = line
             .next()
             .expect("Missing games")
@@ -130,14 +228,7 @@ fn process_position(position: &&str, data: &mut Vec<(f64, GameData)>, total_posi
             .expect("Missing score")
             .parse()
             .expect("Invalid score");
-          let features = extract_features(board.board());
-          (
-            features,
-            board.promotion_options().clone(),
-            board.to_move(),
-            games,
-            f64::from(score) / f64::from(games) / 2.0,
-          )
+          load_record(fen, games, f64::from(score) / f64::from(games) / 2.0)
         })
         .collect::<GameData>(),
     );
@@ -145,39 +236,54 @@ fn process_position(position: &&str, data: &mut Vec<(f64, GameData)>, total_posi
   let position_count = processed_data.len();
   println!("Loaded {position_count} positions");
   *total_positions += position_count;
-  // Calculate K
-  let mut best_k = 0.0;
-  let (loss, positions) = calculate_loss(best_k, &processed_data, &DEFAULT_PARAMETERS);
-  let mut best_loss = loss / f64::from(positions);
-  let mut delta = 0.09;
-  println!("k {best_k} loss {best_loss:.6}");
-  while delta > 0.0001 {
-    let mut changed = false;
-    let k = best_k + delta;
-    let (loss, positions) = calculate_loss(k, &processed_data, &DEFAULT_PARAMETERS);
-    let average_loss = loss / f64::from(positions);
-    if average_loss < best_loss {
-      println!("Position {position} k {k} loss {average_loss:.6}");
-      best_loss = average_loss;
-      best_k = k;
-      changed = true;
-    } else {
-      let k = best_k - delta;
-      let (loss, positions) = calculate_loss(k, &processed_data, &DEFAULT_PARAMETERS);
-      let average_loss = loss / f64::from(positions);
-      if average_loss < best_loss {
-        println!("Position {position} k {k} loss {average_loss:.6}");
-        best_loss = average_loss;
-        best_k = k;
-        changed = true;
-      }
-    }
-    if !changed {
-      delta /= 3.0;
-    }
+  let k = calculate_k(position, &processed_data);
+  data.push((k, processed_data));
+}
+
+// Read the positions and results saved by datagen
+fn read_records(path: &str) -> std::io::Result<Vec<(String, f64)>> {
+  let mut file = BufReader::new(File::open(path)?);
+  let mut records = Vec::new();
+  while let Some(record) = DataRecord::read(&mut file)? {
+    records.push((record.fen, f64::from(record.result) / 2.0));
   }
-  println!("Final k for {position}: {best_k}");
-  data.push((best_k, processed_data));
+  Ok(records)
+}
+
+// Read a file with an L-FEN and the game result on each line, separated by a semicolon
+fn read_text(path: &str) -> std::io::Result<Vec<(String, f64)>> {
+  let mut records = Vec::new();
+  for line in read_to_string(path)?
+    .lines()
+    .filter(|line| !line.is_empty())
+  {
+    let invalid = || Error::new(ErrorKind::InvalidData, format!("invalid record {line}"));
+    let (fen, result) = line.rsplit_once(';').ok_or_else(invalid)?;
+    records.push((fen.to_owned(), parse_result(result).ok_or_else(invalid)?));
+  }
+  Ok(records)
+}
+
+// Load positions from a file generated by datagen, or a text file of results
+fn process_file(path: &str, data: &mut Vec<(f64, GameData)>, total_positions: &mut usize) {
+  let records = if path.ends_with(".bin") {
+    read_records(path)
+  } else {
+    read_text(path)
+  };
+  let records = records.unwrap_or_else(|error| {
+    println!("Unable to read {path}: {error}");
+    exit(1);
+  });
+  let processed_data = records
+    .par_iter()
+    .map(|(fen, score)| load_record(fen, 1, *score))
+    .collect::<GameData>();
+  let position_count = processed_data.len();
+  println!("Loaded {position_count} positions");
+  *total_positions += position_count;
+  let k = calculate_k(path, &processed_data);
+  data.push((k, processed_data));
 }
 
 fn main() {
@@ -195,28 +301,41 @@ fn main() {
   let mut data = Vec::new();
   let mut total_positions = 0;
   let mut start = Instant::now();
-  for (position, _, _) in POSITIONS {
-    process_position(position, &mut data, &mut total_positions);
+  // records from a file, or the data generated for each preset position
+  if let Some(path) = args().nth(1) {
+    process_file(&path, &mut data, &mut total_positions);
+  } else {
+    for (position, _, _) in POSITIONS {
+      process_position(position, &mut data, &mut total_positions);
+    }
   }
   println!("{total_positions} positions in dataset");
   let mut best_loss = f64::INFINITY;
   println!("Data loading took {}s", start.elapsed().as_secs());
   start = Instant::now();
-  // Tune parameters using Nesterov momentum
+  // Tune parameters using Nesterov momentum or Adam
   let mut momentum = Parameters::default();
+  let mut adam = Adam::default();
   for i in 0..=ITERATION_COUNT {
-    parameters += momentum;
-    parameters.enforce_invariants();
-    let (loss, mut gradient) = calculate_gradients_batch(&data, &parameters);
+    if OPTIMIZER == Optimizer::Momentum {
+      parameters += momentum;
+      parameters.enforce_invariants();
+    }
+    let (loss, gradient) = calculate_gradients_batch(&data, &parameters);
     if loss < best_loss {
       best_loss = loss;
       println!("Iteration {i}/{ITERATION_COUNT} Loss record {loss:.7}");
     } else {
       println!("Iteration {i}/{ITERATION_COUNT} Loss {loss:.7} (Best: {best_loss:.7})");
     }
-    gradient = gradient * LR;
-    momentum = (gradient + momentum) * MOMENTUM_FACTOR;
-    parameters += gradient;
+    match OPTIMIZER {
+      Optimizer::Momentum => {
+        let gradient = gradient * LR;
+        momentum = (gradient + momentum) * MOMENTUM_FACTOR;
+        parameters += gradient;
+      }
+      Optimizer::Adam => parameters += adam.step(gradient),
+    }
     parameters.enforce_invariants();
     if i % PRINT_FREQUENCY == 0 {
       println!("{parameters:?}");
@@ -0,0 +1,392 @@
+use liberty_chess::moves::Move;
+use liberty_chess::parsing::to_piece;
+use liberty_chess::positions::{get_startpos, MINI};
+use liberty_chess::Board;
+use std::env::args;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{exit, Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant};
+use ulci::SupportedFeatures;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const SEARCH_DELAY: Duration = Duration::from_millis(300);
+
+#[derive(Default)]
+struct EngineInfo {
+  version: bool,
+  features: SupportedFeatures,
+  // name, min, max
+  spin_options: Vec<(String, i64, i64)>,
+}
+
+impl EngineInfo {
+  fn supports_boardsize(&self) -> bool {
+    self.version || self.features.v1.board_sizes()
+  }
+}
+
+struct Engine {
+  child: Child,
+  stdin: ChildStdin,
+  lines: Receiver<String>,
+}
+
+impl Engine {
+  fn new(path: &str) -> Result<Self, String> {
+    let mut child = Command::new(path)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .spawn()
+      .map_err(|error| format!("could not start engine: {error}"))?;
+    let stdin = child.stdin.take().ok_or("could not load stdin")?;
+    let stdout = BufReader::new(child.stdout.take().ok_or("could not load stdout")?);
+    let (tx, rx) = channel();
+    spawn(move || {
+      for line in stdout.lines().map_while(Result::ok) {
+        if tx.send(line).is_err() {
+          break;
+        }
+      }
+    });
+    Ok(Self {
+      child,
+      stdin,
+      lines: rx,
+    })
+  }
+
+  fn send(&mut self, command: &str) -> Result<(), String> {
+    self
+      .stdin
+      .write_all(format!("{command}\n").as_bytes())
+      .and_then(|()| self.stdin.flush())
+      .map_err(|_| format!("engine closed stdin before \"{command}\""))
+  }
+
+  // Collect output until a line starting with the token, which is returned last
+  fn read_until(&self, token: &str, timeout: Duration) -> Result<Vec<String>, String> {
+    let deadline = Instant::now() + timeout;
+    let mut lines = Vec::new();
+    loop {
+      let remaining = deadline.saturating_duration_since(Instant::now());
+      match self.lines.recv_timeout(remaining) {
+        Ok(line) => {
+          let found = line.split_whitespace().next() == Some(token);
+          lines.push(line);
+          if found {
+            return Ok(lines);
+          }
+        }
+        Err(RecvTimeoutError::Timeout) => return Err(format!("timed out waiting for {token}")),
+        Err(RecvTimeoutError::Disconnected) => {
+          return Err(format!("engine exited while waiting for {token}"))
+        }
+      }
+    }
+  }
+
+  // Ensure nothing was sent other than info lines
+  fn expect_silence(&self, duration: Duration) -> Result<(), String> {
+    sleep(duration);
+    for line in self.lines.try_iter() {
+      if line.split_whitespace().next() != Some("info") {
+        return Err(format!("unexpected output \"{line}\""));
+      }
+    }
+    Ok(())
+  }
+
+  fn is_ready(&mut self) -> Result<(), String> {
+    self.send("isready")?;
+    self.read_until("readyok", TIMEOUT).map(|_| ())
+  }
+
+  fn has_exited(&mut self) -> bool {
+    !matches!(self.child.try_wait(), Ok(None))
+  }
+
+  fn handshake(&mut self) -> Result<EngineInfo, String> {
+    self.send("uci")?;
+    let lines = self.read_until("uciok", TIMEOUT)?;
+    let mut info = EngineInfo::default();
+    let (mut name, mut author) = (false, false);
+    for line in &lines {
+      let mut words = line.split_whitespace();
+      match words.next() {
+        Some("id") => match words.next() {
+          Some("name") => name = true,
+          Some("author") => author = true,
+          Some("version") => match words.next().map(str::parse::<u32>) {
+            Some(Ok(version)) => info.version = version >= 1,
+            _ => return Err(format!("invalid version in \"{line}\"")),
+          },
+          Some("feature") => match words.next() {
+            // features from later versions or other extensions are allowed
+            Some(feature) => {
+              if !info.features.declare(feature) {
+                println!("NOTE ignoring unknown feature {feature}");
+              }
+            }
+            None => return Err("empty feature declaration".to_owned()),
+          },
+          Some("pieces") => {
+            let pieces = words.next().unwrap_or_default();
+            if let Some(piece) = pieces.chars().find(|c| to_piece(*c).is_err()) {
+              return Err(format!("unknown piece {piece} in \"{line}\""));
+            }
+          }
+          Some("username") => (),
+          _ => return Err(format!("unknown id line \"{line}\"")),
+        },
+        Some("option") => {
+          if words.next() != Some("name") {
+            return Err(format!("malformed option \"{line}\""));
+          }
+          let words: Vec<&str> = words.collect();
+          if let Some(index) = words.iter().position(|word| *word == "type") {
+            if words.get(index + 1) == Some(&"spin") {
+              let find = |key| {
+                words
+                  .iter()
+                  .position(|word| *word == key)
+                  .and_then(|i| words.get(i + 1))
+                  .and_then(|value| value.parse::<i64>().ok())
+              };
+              if let (Some(min), Some(max)) = (find("min"), find("max")) {
+                info.spin_options.push((words[..index].join(" "), min, max));
+              }
+            }
+          } else {
+            return Err(format!("option without type \"{line}\""));
+          }
+        }
+        _ => (),
+      }
+    }
+    if !name {
+      return Err("missing id name".to_owned());
+    }
+    if !author {
+      return Err("missing id author".to_owned());
+    }
+    Ok(info)
+  }
+
+  // Play a search to completion and check the bestmove is legal
+  fn check_search(&mut self, board: &Board, go: &str) -> Result<(), String> {
+    self.send(go)?;
+    let lines = self.read_until("bestmove", TIMEOUT)?;
+    check_bestmove(board, lines.last().expect("Missing bestmove"))
+  }
+}
+
+impl Drop for Engine {
+  fn drop(&mut self) {
+    self.child.kill().ok();
+  }
+}
+
+fn check_bestmove(board: &Board, line: &str) -> Result<(), String> {
+  let bestmove = line
+    .split_whitespace()
+    .nth(1)
+    .ok_or_else(|| format!("no move in \"{line}\""))?;
+  let parsed = bestmove
+    .parse::<Move>()
+    .map_err(|_| format!("could not parse bestmove {bestmove}"))?;
+  board
+    .move_if_legal(parsed)
+    .map(|_| ())
+    .ok_or_else(|| format!("illegal bestmove {bestmove} in {}", board.to_string()))
+}
+
+fn test_isready(engine: &mut Engine, _: &EngineInfo) -> Result<(), String> {
+  engine.is_ready()
+}
+
+fn test_unknown_option(engine: &mut Engine, _: &EngineInfo) -> Result<(), String> {
+  engine.send("setoption name ThisOptionDoesNotExist value 1")?;
+  engine.send("setoption")?;
+  engine.send("setoption name")?;
+  engine.is_ready()
+}
+
+fn test_option_bounds(engine: &mut Engine, info: &EngineInfo) -> Result<(), String> {
+  for (name, min, max) in &info.spin_options {
+    engine.send(&format!(
+      "setoption name {name} value {}",
+      min.saturating_sub(1)
+    ))?;
+    engine.send(&format!(
+      "setoption name {name} value {}",
+      max.saturating_add(1)
+    ))?;
+    engine.send(&format!("setoption name {name} value notanumber"))?;
+    engine.send(&format!("setoption name {name}"))?;
+    engine.is_ready()?;
+  }
+  Ok(())
+}
+
+fn test_search(engine: &mut Engine, _: &EngineInfo) -> Result<(), String> {
+  engine.send("ucinewgame")?;
+  engine.is_ready()?;
+  engine.send("position startpos moves e2e4")?;
+  let board = get_startpos()
+    .move_if_legal("e2e4".parse().expect("Invalid move"))
+    .expect("Illegal move");
+  engine.check_search(&board, "go depth 1")
+}
+
+fn test_zero_time(engine: &mut Engine, _: &EngineInfo) -> Result<(), String> {
+  engine.send("position startpos")?;
+  engine.check_search(&get_startpos(), "go wtime 0 btime 0 winc 0 binc 0")
+}
+
+fn test_stop(engine: &mut Engine, _: &EngineInfo) -> Result<(), String> {
+  engine.send("position startpos")?;
+  engine.send("go infinite")?;
+  engine
+    .expect_silence(SEARCH_DELAY)
+    .map_err(|error| format!("{error} during infinite search"))?;
+  engine.send("stop")?;
+  let lines = engine.read_until("bestmove", TIMEOUT)?;
+  check_bestmove(&get_startpos(), lines.last().expect("Missing bestmove"))
+}
+
+fn test_isready_searching(engine: &mut Engine, _: &EngineInfo) -> Result<(), String> {
+  engine.send("position startpos")?;
+  engine.send("go infinite")?;
+  sleep(SEARCH_DELAY);
+  engine.send("isready")?;
+  let lines = engine.read_until("readyok", TIMEOUT)?;
+  if lines.iter().any(|line| line.starts_with("bestmove")) {
+    return Err("search ended by isready".to_owned());
+  }
+  engine.send("stop")?;
+  engine.read_until("bestmove", TIMEOUT).map(|_| ())
+}
+
+fn test_stop_idle(engine: &mut Engine, _: &EngineInfo) -> Result<(), String> {
+  engine.send("stop")?;
+  engine.expect_silence(SEARCH_DELAY)?;
+  engine.is_ready()
+}
+
+fn test_variant(engine: &mut Engine, info: &EngineInfo) -> Result<(), String> {
+  if !info.supports_boardsize() {
+    return Ok(());
+  }
+  engine.send(&format!("position fen {MINI}"))?;
+  let board = Board::new(MINI).expect("Invalid startpos");
+  engine.check_search(&board, "go depth 1")
+}
+
+// The engine may quit on invalid positions, but must not hang or keep going silently
+fn invalid_position(engine: &mut Engine, command: &str) -> Result<(), String> {
+  engine.send(command)?;
+  if engine.send("isready").is_err() {
+    return Ok(());
+  }
+  let lines = match engine.read_until("readyok", TIMEOUT) {
+    Ok(lines) => lines,
+    Err(_) if engine.has_exited() => return Ok(()),
+    Err(error) => return Err(error),
+  };
+  if lines.iter().any(|line| line.starts_with("info error")) {
+    Ok(())
+  } else {
+    Err("no error reported".to_owned())
+  }
+}
+
+fn test_illegal_fen(engine: &mut Engine, _: &EngineInfo) -> Result<(), String> {
+  invalid_position(engine, "position fen rnbqkbnr/ppppXppp/8 w KQkq - 0 1")
+}
+
+fn test_illegal_move(engine: &mut Engine, _: &EngineInfo) -> Result<(), String> {
+  invalid_position(engine, "position startpos moves e2e5")
+}
+
+fn test_quit(engine: &mut Engine, _: &EngineInfo) -> Result<(), String> {
+  engine.send("quit")?;
+  let deadline = Instant::now() + TIMEOUT;
+  while Instant::now() < deadline {
+    if engine.has_exited() {
+      return Ok(());
+    }
+    sleep(Duration::from_millis(10));
+  }
+  Err("engine did not exit".to_owned())
+}
+
+type Test = fn(&mut Engine, &EngineInfo) -> Result<(), String>;
+
+const TESTS: &[(&str, Test)] = &[
+  ("isready", test_isready),
+  ("unknown option", test_unknown_option),
+  ("option bounds", test_option_bounds),
+  ("search", test_search),
+  ("zero time", test_zero_time),
+  ("stop", test_stop),
+  ("isready while searching", test_isready_searching),
+  ("stop while idle", test_stop_idle),
+  ("variant position", test_variant),
+  ("illegal fen", test_illegal_fen),
+  ("illegal move", test_illegal_move),
+  ("quit", test_quit),
+];
+
+fn start(path: &str) -> Result<(Engine, EngineInfo), String> {
+  let mut engine = Engine::new(path)?;
+  let info = engine.handshake()?;
+  Ok((engine, info))
+}
+
+fn main() {
+  let Some(path) = args().nth(1) else {
+    println!("Usage: ulci-test <engine path>");
+    exit(2);
+  };
+  let (mut passed, mut failed) = (0, 0);
+  let (mut engine, info) = match start(&path) {
+    Ok(result) => {
+      println!("PASS handshake");
+      passed += 1;
+      result
+    }
+    Err(error) => {
+      println!("FAIL handshake: {error}");
+      exit(1);
+    }
+  };
+  for (name, test) in TESTS {
+    // restart engines that quit after a previous test
+    if engine.has_exited() {
+      match start(&path) {
+        Ok((new_engine, _)) => engine = new_engine,
+        Err(error) => {
+          println!("FAIL restart before {name}: {error}");
+          failed += 1;
+          break;
+        }
+      }
+    }
+    match test(&mut engine, &info) {
+      Ok(()) => {
+        println!("PASS {name}");
+        passed += 1;
+      }
+      Err(error) => {
+        println!("FAIL {name}: {error}");
+        failed += 1;
+      }
+    }
+  }
+  println!("{passed} passed, {failed} failed");
+  if failed > 0 {
+    exit(1);
+  }
+}
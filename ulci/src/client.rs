@@ -1,9 +1,12 @@
-use crate::server::UlciResult;
+use crate::compression::decompress;
+use crate::report::{RecordedInput, RecordedOutput, Traffic};
+use crate::server::{InfoType, UlciResult};
 use crate::{
-  process_info, write, AnalysisResult, OptionValue, SearchSettings, SearchTime, UlciOption,
-  V1Features,
+  convert_words, process_info, write, AnalysisResult, OptionValue, SearchSettings, SearchTime,
+  UlciOption, V1Features,
 };
 use crate::{ClientInfo, Limits};
+use liberty_chess::clock::Millis;
 use liberty_chess::parsing::to_char;
 use liberty_chess::positions::get_startpos;
 use liberty_chess::threading::CompressedBoard;
@@ -25,10 +28,12 @@ pub enum Message {
   Go(SearchSettings),
   /// The server wants to stop the search
   Stop,
+  /// The opponent played the expected move, so the ponder search switches to its time control
+  PonderHit,
   /// The server wants a static evaluation of the position
   Eval,
-  /// The server wants the standardised bench results
-  Bench(i8),
+  /// The server wants bench results, from a suite file if one is given
+  Bench(i8, Option<String>),
   /// Clear the TT
   NewGame,
   /// Perft
@@ -37,11 +42,15 @@ pub enum Message {
   Clock(SearchTime),
   /// The server has some info
   Info(AnalysisResult),
+  /// The server has analysis from its own engine, only sent to spectators
+  Kibbutz(AnalysisResult),
   /// Respond with ReadyOk
   IsReady,
+  /// The server has a message to show the user
+  Notice(String),
 }
 
-fn print_uci(out: &mut impl Write, info: &ClientInfo) -> Option<()> {
+pub(crate) fn print_uci(out: &mut impl Write, info: &ClientInfo) -> Option<()> {
   let v1_features = info.features.v1;
   if v1_features == V1Features::all() {
     write(out, "id version 1")?;
@@ -56,7 +65,7 @@ fn print_uci(out: &mut impl Write, info: &ClientInfo) -> Option<()> {
       write(out, "id feature castling")?;
     }
     if v1_features.multiple_kings {
-      write(out, "id feature multplekings")?;
+      write(out, "id feature multiplekings")?;
     }
     if v1_features.promotion_options {
       write(out, "id feature promotion")?;
@@ -65,6 +74,15 @@ fn print_uci(out: &mut impl Write, info: &ClientInfo) -> Option<()> {
       write(out, "id feature friendlyfire")?;
     }
   }
+  if info.features.priority_moves {
+    write(out, "id feature priority")?;
+  }
+  if info.features.compression {
+    write(out, "id feature compression")?;
+  }
+  if info.features.games {
+    write(out, "id feature games")?;
+  }
   write(
     out,
     format!(
@@ -80,6 +98,9 @@ fn print_uci(out: &mut impl Write, info: &ClientInfo) -> Option<()> {
   for (name, option) in &info.options {
     write(out, format!("option name {name} {}", option.to_string()))?;
   }
+  if let Some(ref build) = info.build {
+    write(out, format!("info string {build}"))?;
+  }
   write(out, "uciok")?;
   Some(())
 }
@@ -222,6 +243,13 @@ fn position(
         return if !debug { None } else { Some(()) };
       }
     }
+    Some("compressed") => {
+      let Some(text) = decompress(&convert_words(words)) else {
+        write(out, "info error malformed compressed position")?;
+        return Some(());
+      };
+      return position(out, client, board, text.split_whitespace(), debug);
+    }
     Some(_) | None => {
       write(out, "info error malformed position command")?;
       return Some(());
@@ -262,16 +290,38 @@ fn position(
     .ok()
 }
 
-fn go(
+pub(crate) fn go(
   out: &mut impl Write,
   client: &Sender<Message>,
   mut words: SplitWhitespace,
   debug: bool,
 ) -> Option<()> {
   let mut time = SearchTime::Infinite;
+  let mut priority = Vec::new();
+  let mut ponder = false;
+  let mut moves_to_go = None;
+  let mut clock = true;
   while let Some(word) = words.next() {
+    if matches!(
+      word,
+      "infinite"
+        | "depth"
+        | "mate"
+        | "nodes"
+        | "movetime"
+        | "wtime"
+        | "btime"
+        | "winc"
+        | "binc"
+        | "delay"
+        | "hourglass"
+        | "movestogo"
+    ) {
+      clock = false;
+    }
     match word {
       "infinite" => time = SearchTime::Infinite,
+      "ponder" => ponder = true,
       "depth" => {
         if let Some(value) = words.next().and_then(|w| w.parse().ok()) {
           let depth = usize::from(u8::MAX).min(value);
@@ -320,49 +370,35 @@ fn go(
           write(out, "info error no time specified")?;
         }
       }
-      "wtime" => {
+      "wtime" | "btime" | "winc" | "binc" | "delay" => {
         if let Some(value) = words.next().and_then(|w| w.parse().ok()) {
-          if let SearchTime::Asymmetric(ref mut wtime, _, _, _) = time {
-            *wtime = value;
-          } else {
-            time = SearchTime::Asymmetric(value, 0, 1000, 0);
-          }
+          time = set_clock(time, word, value);
         } else {
           write(out, "info error no time specified")?;
         }
       }
-      "btime" => {
+      "hourglass" => time = set_clock(time, word, Millis::ZERO),
+      "movestogo" => {
         if let Some(value) = words.next().and_then(|w| w.parse().ok()) {
-          if let SearchTime::Asymmetric(_, _, ref mut btime, _) = time {
-            *btime = value;
-          } else {
-            time = SearchTime::Asymmetric(1000, 0, value, 0);
-          }
+          moves_to_go = Some(value);
         } else {
-          write(out, "info error no time specified")?;
+          write(out, "info error no move count specified")?;
         }
       }
-      "winc" => {
-        if let Some(value) = words.next().and_then(|w| w.parse().ok()) {
-          if let SearchTime::Asymmetric(_, ref mut winc, _, _) = time {
-            *winc = value;
-          } else {
-            time = SearchTime::Asymmetric(1000, value, 1000, 0);
+      // priority moves run until searchmoves or the end
+      "priority" => {
+        for word in words.by_ref() {
+          if word == "searchmoves" {
+            break;
           }
-        } else {
-          write(out, "info error no time specified")?;
-        }
-      }
-      "binc" => {
-        if let Some(value) = words.next().and_then(|w| w.parse().ok()) {
-          if let SearchTime::Asymmetric(_, _, _, ref mut binc) = time {
-            *binc = value;
+          if let Ok(mv) = word.parse() {
+            priority.push(mv);
           } else {
-            time = SearchTime::Asymmetric(1000, 0, 1000, value);
+            write(out, "info error invalid move specified")?;
+            return if !debug { None } else { Some(()) };
           }
-        } else {
-          write(out, "info error no time specified")?;
         }
+        break;
       }
       "searchmoves" => break,
       _ => {
@@ -380,59 +416,88 @@ fn go(
       return if !debug { None } else { Some(()) };
     }
   }
+  // applied last since the clock parameters can come in any order
+  if let Some(moves) = moves_to_go {
+    time = set_moves_to_go(time, moves);
+  }
   client
-    .send(Message::Go(SearchSettings { moves, time }))
+    .send(Message::Go(SearchSettings {
+      moves,
+      priority,
+      time,
+      ponder,
+      clock,
+    }))
     .ok()
 }
 
+// Update a time control with a clock parameter from `go` or `clock`
+//
+// With only increments and no times, the increment is all the time available each move
+fn set_clock(time: SearchTime, parameter: &str, value: Millis) -> SearchTime {
+  let (wtime, winc, btime, binc) = match time {
+    SearchTime::Increment(time, inc) => (time, inc, time, inc),
+    SearchTime::Asymmetric(wtime, winc, btime, binc)
+    | SearchTime::MovesToGo(wtime, winc, btime, binc, _) => (wtime, winc, btime, binc),
+    SearchTime::Delay(wtime, btime, _) | SearchTime::Hourglass(wtime, btime) => {
+      (wtime, Millis::ZERO, btime, Millis::ZERO)
+    }
+    SearchTime::Infinite | SearchTime::Other(_) | SearchTime::Mate(_) => {
+      let default = Millis::from_secs(1);
+      match parameter {
+        "wtime" => (value, Millis::ZERO, default, Millis::ZERO),
+        "btime" => (default, Millis::ZERO, value, Millis::ZERO),
+        _ => (Millis::ZERO, Millis::ZERO, Millis::ZERO, Millis::ZERO),
+      }
+    }
+  };
+  match (parameter, time) {
+    ("wtime", SearchTime::Delay(_, btime, delay)) => SearchTime::Delay(value, btime, delay),
+    ("btime", SearchTime::Delay(wtime, _, delay)) => SearchTime::Delay(wtime, value, delay),
+    ("wtime", SearchTime::Hourglass(_, btime)) => SearchTime::Hourglass(value, btime),
+    ("btime", SearchTime::Hourglass(wtime, _)) => SearchTime::Hourglass(wtime, value),
+    // increments don't apply to delay or hourglass time controls
+    ("winc" | "binc", SearchTime::Delay(..) | SearchTime::Hourglass(..)) => time,
+    ("wtime", _) => SearchTime::Asymmetric(value, winc, btime, binc),
+    ("btime", _) => SearchTime::Asymmetric(wtime, winc, value, binc),
+    ("winc", _) => SearchTime::Asymmetric(wtime, value, btime, binc),
+    ("binc", _) => SearchTime::Asymmetric(wtime, winc, btime, value),
+    ("delay", _) => SearchTime::Delay(wtime, btime, value),
+    ("hourglass", _) => SearchTime::Hourglass(wtime, btime),
+    _ => time,
+  }
+}
+
+// Add the moves to play before the next time control to a time control with increments
+//
+// Delay and hourglass time controls, and searches without a clock, don't use moves to go
+fn set_moves_to_go(time: SearchTime, moves: u32) -> SearchTime {
+  match time {
+    SearchTime::Increment(time, inc) => SearchTime::MovesToGo(time, inc, time, inc, moves),
+    SearchTime::Asymmetric(wtime, winc, btime, binc)
+    | SearchTime::MovesToGo(wtime, winc, btime, binc, _) => {
+      SearchTime::MovesToGo(wtime, winc, btime, binc, moves)
+    }
+    SearchTime::Delay(..)
+    | SearchTime::Hourglass(..)
+    | SearchTime::Infinite
+    | SearchTime::Other(_)
+    | SearchTime::Mate(_) => time,
+  }
+}
+
 fn clock(out: &mut impl Write, client: &Sender<Message>, mut words: SplitWhitespace) -> Option<()> {
   let mut time = SearchTime::Infinite;
   while let Some(word) = words.next() {
     match word {
-      "wtime" => {
-        if let Some(value) = words.next().and_then(|w| w.parse().ok()) {
-          if let SearchTime::Asymmetric(ref mut wtime, _, _, _) = time {
-            *wtime = value;
-          } else {
-            time = SearchTime::Asymmetric(value, 0, 1000, 0);
-          }
-        } else {
-          write(out, "info error no time specified")?;
-        }
-      }
-      "btime" => {
+      "wtime" | "btime" | "winc" | "binc" | "delay" => {
         if let Some(value) = words.next().and_then(|w| w.parse().ok()) {
-          if let SearchTime::Asymmetric(_, _, ref mut btime, _) = time {
-            *btime = value;
-          } else {
-            time = SearchTime::Asymmetric(1000, 0, value, 0);
-          }
-        } else {
-          write(out, "info error no time specified")?;
-        }
-      }
-      "winc" => {
-        if let Some(value) = words.next().and_then(|w| w.parse().ok()) {
-          if let SearchTime::Asymmetric(_, ref mut winc, _, _) = time {
-            *winc = value;
-          } else {
-            time = SearchTime::Asymmetric(1000, value, 1000, 0);
-          }
-        } else {
-          write(out, "info error no time specified")?;
-        }
-      }
-      "binc" => {
-        if let Some(value) = words.next().and_then(|w| w.parse().ok()) {
-          if let SearchTime::Asymmetric(_, _, _, ref mut binc) = time {
-            *binc = value;
-          } else {
-            time = SearchTime::Asymmetric(1000, 0, 1000, value);
-          }
+          time = set_clock(time, word, value);
         } else {
           write(out, "info error no time specified")?;
         }
       }
+      "hourglass" => time = set_clock(time, word, Millis::ZERO),
       _ => {
         write(out, "info error unknown clock parameter")?;
       }
@@ -447,10 +512,13 @@ fn clock(out: &mut impl Write, client: &Sender<Message>, mut words: SplitWhitesp
 pub fn startup(
   client: &Sender<Message>,
   info: &ClientInfo,
-  mut input: impl BufRead,
-  mut out: impl Write,
+  input: impl BufRead,
+  out: impl Write,
   is_always_ready: bool,
 ) -> Option<()> {
+  let traffic = Traffic::new();
+  let mut input = RecordedInput::new(input, traffic.clone());
+  let mut out = RecordedOutput::new(out, traffic);
   let mut debug = false;
   let mut buffer = String::new();
   let mut board = get_startpos();
@@ -473,6 +541,7 @@ pub fn startup(
       Some("position") => position(&mut out, client, &mut board, words, debug)?,
       Some("go") => go(&mut out, client, words, debug)?,
       Some("stop") => client.send(Message::Stop).ok()?,
+      Some("ponderhit") => client.send(Message::PonderHit).ok()?,
       Some("eval") => client.send(Message::Eval).ok()?,
       Some("ucinewgame") => client.send(Message::NewGame).ok()?,
       Some("perft") => {
@@ -484,20 +553,39 @@ pub fn startup(
         client.send(Message::Perft(depth)).ok()?;
       }
       Some("bench") => {
+        let mut words = words.peekable();
         let depth = words
-          .next()
+          .peek()
           .and_then(|w| w.parse().ok())
-          .unwrap_or(info.depth);
-        client.send(Message::Bench(depth)).ok()?;
+          .map_or(info.depth, |depth| {
+            words.next();
+            depth
+          });
+        let suite = words.collect::<Vec<_>>().join(" ");
+        let suite = (!suite.is_empty()).then_some(suite);
+        client.send(Message::Bench(depth, suite)).ok()?;
       }
       Some("clock") => clock(&mut out, client, words)?,
+      Some("kibbutz") => {
+        for message in process_info(words) {
+          if let UlciResult::Analysis(result) = message {
+            client.send(Message::Kibbutz(result)).ok();
+          }
+        }
+      }
       // End the program, the channel being dropped will stop the other thread
       Some("quit") => break,
       // Commands that can be ignored or blank line
       Some("info") => {
         for message in process_info(words) {
-          if let UlciResult::Analysis(result) = message {
-            client.send(Message::Info(result)).ok();
+          match message {
+            UlciResult::Analysis(result) => {
+              client.send(Message::Info(result)).ok();
+            }
+            UlciResult::Info(InfoType::String, notice) => {
+              client.send(Message::Notice(notice)).ok();
+            }
+            _ => (),
           }
         }
       }
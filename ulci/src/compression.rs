@@ -0,0 +1,100 @@
+// A small LZ77 codec for long position commands, which repeat a lot on big boards
+//
+// Text is copied as is, except repeated text is replaced by a reference to an earlier copy,
+// written as `~` followed by 2 digits for the distance back and 1 digit for the length.
+// The digits are base 64 so the result stays printable and has no whitespace of its own.
+use std::collections::HashMap;
+
+const MARKER: u8 = b'~';
+const DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+// a reference takes 4 characters, so shorter repeats aren't worth replacing
+const MIN_MATCH: usize = 5;
+const MAX_MATCH: usize = MIN_MATCH + 63;
+const MAX_DISTANCE: usize = 64 * 64 - 1;
+// earlier copies checked for each repeat, more finds longer repeats but is slower
+const MAX_CANDIDATES: usize = 16;
+
+fn digit(value: usize) -> u8 {
+  DIGITS[value]
+}
+
+fn value(digit: u8) -> Option<usize> {
+  DIGITS.iter().position(|d| *d == digit)
+}
+
+// Compress text without the marker character, returns `None` if the marker is used
+pub(crate) fn compress(text: &str) -> Option<String> {
+  let bytes = text.as_bytes();
+  if bytes.contains(&MARKER) {
+    return None;
+  }
+  let mut output = Vec::with_capacity(bytes.len());
+  let mut previous: HashMap<&[u8], Vec<usize>> = HashMap::new();
+  let mut i = 0;
+  while i < bytes.len() {
+    let mut best = (0, 0);
+    if let Some(key) = bytes.get(i..i + MIN_MATCH) {
+      if let Some(candidates) = previous.get(key) {
+        for &start in candidates.iter().rev().take(MAX_CANDIDATES) {
+          let distance = i - start;
+          if distance > MAX_DISTANCE {
+            break;
+          }
+          // the copy can overlap the text being written, which handles long runs
+          let length = (0..MAX_MATCH.min(bytes.len() - i))
+            .take_while(|&offset| bytes[start + offset] == bytes[i + offset])
+            .count();
+          if length > best.1 {
+            best = (distance, length);
+          }
+        }
+      }
+    }
+    let (distance, length) = best;
+    let step = if length >= MIN_MATCH {
+      output.extend_from_slice(&[
+        MARKER,
+        digit(distance / 64),
+        digit(distance % 64),
+        digit(length - MIN_MATCH),
+      ]);
+      length
+    } else {
+      output.push(bytes[i]);
+      1
+    };
+    for position in i..i + step {
+      if let Some(key) = bytes.get(position..position + MIN_MATCH) {
+        previous.entry(key).or_default().push(position);
+      }
+    }
+    i += step;
+  }
+  String::from_utf8(output).ok()
+}
+
+// Undo `compress`, returns `None` if the text is not valid compressed text
+pub(crate) fn decompress(text: &str) -> Option<String> {
+  let bytes = text.as_bytes();
+  let mut output = Vec::with_capacity(bytes.len() * 2);
+  let mut i = 0;
+  while i < bytes.len() {
+    if bytes[i] == MARKER {
+      let reference = bytes.get(i + 1..i + 4)?;
+      let distance = value(reference[0])? * 64 + value(reference[1])?;
+      let length = value(reference[2])? + MIN_MATCH;
+      if distance == 0 {
+        return None;
+      }
+      let start = output.len().checked_sub(distance)?;
+      for offset in 0..length {
+        output.push(output[start + offset]);
+      }
+      i += 4;
+    } else {
+      output.push(bytes[i]);
+      i += 1;
+    }
+  }
+  String::from_utf8(output).ok()
+}
@@ -0,0 +1,199 @@
+use parking_lot::Mutex;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io::{BufRead, Read, Result, Write};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::spawn;
+
+type Routes = Arc<Mutex<HashMap<u32, Sender<String>>>>;
+
+/// A game started by the other end of a connection, with its id, input and output
+pub type Game<W> = (u32, ChannelInput, GameOutput<W>);
+
+/// Lines read on another thread, an empty line is the end of the input
+pub struct ChannelInput {
+  lines: Receiver<String>,
+  line: String,
+  position: usize,
+}
+
+impl ChannelInput {
+  /// Read the lines sent on a channel, the input ends when the channel closes
+  #[must_use]
+  pub fn new(lines: Receiver<String>) -> Self {
+    Self {
+      lines,
+      line: String::new(),
+      position: 0,
+    }
+  }
+}
+
+impl Read for ChannelInput {
+  fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+    let available = self.fill_buf()?;
+    let amount = available.len().min(buf.len());
+    buf[..amount].copy_from_slice(&available[..amount]);
+    self.consume(amount);
+    Ok(amount)
+  }
+}
+
+impl BufRead for ChannelInput {
+  fn fill_buf(&mut self) -> Result<&[u8]> {
+    if self.position == self.line.len() {
+      // the reading thread hanging up is also the end of the input
+      self.line = self.lines.recv().unwrap_or_default();
+      self.position = 0;
+    }
+    Ok(&self.line.as_bytes()[self.position..])
+  }
+
+  fn consume(&mut self, amount: usize) {
+    self.position += amount;
+  }
+}
+
+/// Writes the lines for one game to a connection shared with other games
+///
+/// Lines for game 0 are written as they are, other games have `game <id>` in front of each line
+pub struct GameOutput<W> {
+  out: Arc<Mutex<W>>,
+  prefix: String,
+  line: Vec<u8>,
+}
+
+impl<W> GameOutput<W> {
+  fn new(out: Arc<Mutex<W>>, game: u32) -> Self {
+    let prefix = if game == 0 {
+      String::new()
+    } else {
+      format!("game {game} ")
+    };
+    Self {
+      out,
+      prefix,
+      line: Vec::new(),
+    }
+  }
+}
+
+// Each copy buffers its own lines, so copies can be used on different threads
+impl<W> Clone for GameOutput<W> {
+  fn clone(&self) -> Self {
+    Self {
+      out: self.out.clone(),
+      prefix: self.prefix.clone(),
+      line: Vec::new(),
+    }
+  }
+}
+
+impl<W: Write> Write for GameOutput<W> {
+  fn write(&mut self, buf: &[u8]) -> Result<usize> {
+    self.line.extend_from_slice(buf);
+    // only whole lines are written, so lines from different games don't get mixed up
+    while let Some(end) = self.line.iter().position(|byte| *byte == b'\n') {
+      let line: Vec<u8> = self.line.drain(..=end).collect();
+      let mut out = self.out.lock();
+      out.write_all(self.prefix.as_bytes())?;
+      out.write_all(&line)?;
+    }
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> Result<()> {
+    self.out.lock().flush()
+  }
+}
+
+/// A connection split between the main conversation and any number of games
+///
+/// Every line of a game's conversation starts with `game <id>`, for ids from 1 up.
+pub struct Games<W> {
+  out: Arc<Mutex<W>>,
+  routes: Routes,
+}
+
+impl<W: Write + Send + 'static> Games<W> {
+  /// Split a connection, returning the input and output for the main conversation
+  ///
+  /// Games the other end starts are sent to `discovered`, otherwise their lines are ignored.
+  /// Reads the input on another thread, which ends every game when the input ends.
+  pub fn split(
+    input: impl BufRead + Send + 'static,
+    out: W,
+    discovered: Option<Sender<Game<W>>>,
+  ) -> (Self, ChannelInput, GameOutput<W>) {
+    let out = Arc::new(Mutex::new(out));
+    let routes = Arc::new(Mutex::new(HashMap::new()));
+    let (tx, rx) = channel();
+    let route_out = out.clone();
+    let route_routes = routes.clone();
+    spawn(move || route_lines(input, &tx, &route_routes, &route_out, discovered.as_ref()));
+    let main = GameOutput::new(out.clone(), 0);
+    (Self { out, routes }, ChannelInput::new(rx), main)
+  }
+
+  /// Start a game with the given id, returning its input and output
+  ///
+  /// The id should be at least 1 and not already in use
+  #[must_use]
+  pub fn open(&self, game: u32) -> (ChannelInput, GameOutput<W>) {
+    let (tx, rx) = channel();
+    self.routes.lock().insert(game, tx);
+    (
+      ChannelInput::new(rx),
+      GameOutput::new(self.out.clone(), game),
+    )
+  }
+}
+
+// Send each line to the game it is for, without the game id
+fn route_lines<W>(
+  mut input: impl BufRead,
+  main: &Sender<String>,
+  routes: &Routes,
+  out: &Arc<Mutex<W>>,
+  discovered: Option<&Sender<Game<W>>>,
+) {
+  let mut buffer = String::new();
+  while let Ok(chars) = input.read_line(&mut buffer) {
+    if chars == 0 {
+      break;
+    }
+    let game = buffer
+      .strip_prefix("game ")
+      .and_then(|line| line.split_once(' '))
+      .and_then(|(game, line)| Some((game.parse::<u32>().ok()?, line)));
+    match game {
+      Some((game, line)) if game > 0 => {
+        let mut routes = routes.lock();
+        if let (Entry::Vacant(entry), Some(discovered)) = (routes.entry(game), discovered) {
+          let (tx, rx) = channel();
+          let output = GameOutput::new(out.clone(), game);
+          if discovered
+            .send((game, ChannelInput::new(rx), output))
+            .is_ok()
+          {
+            entry.insert(tx);
+          }
+        }
+        if let Some(route) = routes.get(&game) {
+          // games that have finished are dropped
+          if route.send(line.to_owned()).is_err() {
+            routes.remove(&game);
+          }
+        }
+      }
+      // the main conversation can end before the games do
+      _ => {
+        main.send(buffer.clone()).ok();
+      }
+    }
+    buffer.clear();
+  }
+  // closing the channels ends the input for every game
+  routes.lock().clear();
+}
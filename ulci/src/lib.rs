@@ -4,7 +4,7 @@
 
 use crate::server::{startup_server, Request, UlciResult};
 use core::ops::Neg;
-use liberty_chess::clock::Clock;
+use liberty_chess::clock::{Clock, Millis};
 use liberty_chess::moves::Move;
 use liberty_chess::{Board, Piece, PAWN};
 use parking_lot::Mutex;
@@ -21,6 +21,13 @@ use std::thread::spawn;
 
 /// The functionality for a ULCI client
 pub mod client;
+mod compression;
+/// Several games sharing one connection
+pub mod games;
+/// A scripted mock engine for testing servers
+pub mod mock;
+/// Bug reports written when a program panics
+pub mod report;
 /// The functionality for a ULCI server
 pub mod server;
 
@@ -43,6 +50,8 @@ pub struct ClientInfo {
   pub pieces: Vec<Piece>,
   /// Default bench depth
   pub depth: i8,
+  /// How the client was built, sent as an `info string` in response to `uci`
+  pub build: Option<String>,
 }
 
 impl ClientInfo {
@@ -74,10 +83,35 @@ impl ClientInfo {
 }
 
 /// The features supported by the client
-#[derive(Default)]
+#[derive(Clone, Copy, Default)]
 pub struct SupportedFeatures {
   /// Features from version 1
   pub v1: V1Features,
+  /// Supports moves to search first with `go priority`, an extension to version 1
+  pub priority_moves: bool,
+  /// Supports shorter position commands with `position compressed`, an extension to version 1
+  pub compression: bool,
+  /// Supports playing several games on one connection with `game`, an extension to version 1
+  pub games: bool,
+}
+
+impl SupportedFeatures {
+  /// Record a feature from an `id feature` line, returning `false` if it is unknown
+  pub fn declare(&mut self, feature: &str) -> bool {
+    match feature {
+      "boardsize" => self.v1.board_sizes = true,
+      "pawnmoves" => self.v1.pawn_moves = true,
+      "castling" => self.v1.castling = true,
+      "multiplekings" => self.v1.multiple_kings = true,
+      "promotion" => self.v1.promotion_options = true,
+      "friendlyfire" => self.v1.friendly_fire = true,
+      "priority" => self.priority_moves = true,
+      "compression" => self.compression = true,
+      "games" => self.games = true,
+      _ => return false,
+    }
+    true
+  }
 }
 
 /// The ULCI extensions available in version 1 of the protocol
@@ -104,23 +138,41 @@ impl V1Features {
       friendly_fire: true,
     }
   }
+
+  /// Supports board sizes other than 8x8
+  #[must_use]
+  pub const fn board_sizes(&self) -> bool {
+    self.board_sizes
+  }
 }
 
 /// Settings for a search
 pub struct SearchSettings {
   /// The available moves to search
   pub moves: Vec<Move>,
+  /// Moves to search first and never prune
+  pub priority: Vec<Move>,
   /// The time control for searching
   pub time: SearchTime,
+  /// Search on the opponent's time until `ponderhit`, then switch to the time control
+  pub ponder: bool,
+  /// No time control was specified, so the latest clock update should be used
+  pub clock: bool,
 }
 
 /// The time control for searching
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub enum SearchTime {
   /// Time and increment
-  Increment(u128, u128),
+  Increment(Millis, Millis),
   /// Time and increment for both players
-  Asymmetric(u128, u128, u128, u128),
+  Asymmetric(Millis, Millis, Millis, Millis),
+  /// Time and increment for both players, and the moves to play before the clock gets more time
+  MovesToGo(Millis, Millis, Millis, Millis, u32),
+  /// Time for both players and a delay each move before the clock starts running
+  Delay(Millis, Millis, Millis),
+  /// Time for both players, where time spent by one player is added to the other
+  Hourglass(Millis, Millis),
   /// Infinite search
   Infinite,
   /// Depth/Nodes/Movetime
@@ -138,6 +190,11 @@ impl ToString for SearchTime {
       Self::Asymmetric(wtime, winc, btime, binc) => {
         format!(" wtime {wtime} winc {winc} btime {btime} binc {binc}")
       }
+      Self::MovesToGo(wtime, winc, btime, binc, moves) => {
+        format!(" wtime {wtime} winc {winc} btime {btime} binc {binc} movestogo {moves}")
+      }
+      Self::Delay(wtime, btime, delay) => format!(" wtime {wtime} btime {btime} delay {delay}"),
+      Self::Hourglass(wtime, btime) => format!(" wtime {wtime} btime {btime} hourglass"),
       Self::Infinite => " infinite".to_owned(),
       Self::Other(limits) => {
         let mut result = String::new();
@@ -150,7 +207,7 @@ impl ToString for SearchTime {
           result += &format!(" nodes {}", limits.nodes);
           limit_count += 1;
         }
-        if limits.time < u128::MAX {
+        if limits.time < Millis::MAX {
           result += &format!(" movetime {}", limits.time);
           limit_count += 1;
         }
@@ -169,12 +226,41 @@ impl SearchTime {
   pub fn from_clock(clock: &mut Clock) -> Self {
     let (wtime, btime) = clock.get_clocks();
     let (winc, binc) = clock.get_increment();
-    Self::Asymmetric(
-      wtime.as_millis(),
-      winc.as_millis(),
-      btime.as_millis(),
-      binc.as_millis(),
-    )
+    let delay = clock.get_delay();
+    let (wtime, btime) = (Millis::from(wtime), Millis::from(btime));
+    if clock.is_hourglass() {
+      Self::Hourglass(wtime, btime)
+    } else if !delay.is_zero() {
+      Self::Delay(wtime, btime, Millis::from(delay))
+    } else {
+      Self::Asymmetric(wtime, Millis::from(winc), btime, Millis::from(binc))
+    }
+  }
+
+  /// Convert a search time to a paused clock, if it is a clock-based time control
+  #[must_use]
+  pub fn to_clock(&self, to_move: bool) -> Option<Clock> {
+    let millis = Millis::to_duration;
+    match *self {
+      Self::Increment(time, inc) => Some(Clock::new_symmetric(millis(time), millis(inc), to_move)),
+      // the clock doesn't track moves to go, so it only covers the time until the next control
+      Self::Asymmetric(wtime, winc, btime, binc) | Self::MovesToGo(wtime, winc, btime, binc, _) => {
+        Some(Clock::new(
+          [millis(wtime), millis(btime), millis(winc), millis(binc)],
+          to_move,
+        ))
+      }
+      Self::Delay(wtime, btime, delay) => Some(Clock::new_delay(
+        [millis(wtime), millis(btime)],
+        millis(delay),
+        to_move,
+      )),
+      Self::Hourglass(wtime, btime) => Some(Clock::new_hourglass(
+        [millis(wtime), millis(btime)],
+        to_move,
+      )),
+      Self::Infinite | Self::Other(_) | Self::Mate(_) => None,
+    }
   }
 }
 
@@ -185,8 +271,8 @@ pub struct Limits {
   pub depth: u8,
   /// Limit search to nodes
   pub nodes: usize,
-  /// Limit search to time in ms
-  pub time: u128,
+  /// Limit search to time
+  pub time: Millis,
 }
 
 impl Default for Limits {
@@ -194,7 +280,7 @@ impl Default for Limits {
     Self {
       depth: u8::MAX,
       nodes: usize::MAX,
-      time: u128::MAX,
+      time: Millis::MAX,
     }
   }
 }
@@ -216,6 +302,7 @@ pub enum OptionValue {
 }
 
 /// An option supported by the client
+#[derive(Clone)]
 pub enum UlciOption {
   /// A string option
   String(String),
@@ -242,6 +329,7 @@ impl ToString for UlciOption {
 }
 
 /// An option with an integer value and optional min/max
+#[derive(Clone)]
 pub struct IntOption {
   /// the default value of the option
   pub default: usize,
@@ -261,6 +349,7 @@ impl ToString for IntOption {
 }
 
 /// One of a range of possibilities
+#[derive(Clone)]
 pub struct RangeOption {
   /// The default value of the range
   pub default: String,
@@ -341,6 +430,15 @@ impl Score {
       Self::Centipawn(cp) => format!("cp {cp}"),
     }
   }
+
+  /// Rescale a centipawn score so 100 is the provided pawn value
+  #[must_use]
+  pub fn normalize(self, pawn_value: i32) -> Self {
+    match self {
+      Self::Centipawn(cp) => Self::Centipawn(cp * 100 / pawn_value.max(1)),
+      score => score,
+    }
+  }
 }
 
 /// Side to move has these chances to win, draw and loss permill
@@ -351,6 +449,14 @@ pub struct WDL {
   loss: u16,
 }
 
+impl WDL {
+  /// Create a new set of chances, which should add up to 1000
+  #[must_use]
+  pub const fn new(win: u16, draw: u16, loss: u16) -> Self {
+    Self { win, draw, loss }
+  }
+}
+
 impl ToString for WDL {
   fn to_string(&self) -> String {
     format!("wdl {} {} {}", self.win, self.draw, self.loss)
@@ -482,7 +588,12 @@ fn process_info(mut words: SplitWhitespace) -> Vec<UlciResult> {
   results
 }
 
-fn spawn_engine(path: &'static str, requests: Receiver<Request>, results: &Sender<UlciResult>) {
+fn spawn_engine(
+  path: &'static str,
+  requests: Receiver<Request>,
+  results: &Sender<UlciResult>,
+  debug: bool,
+) {
   let mut engine = Command::new(path)
     .stdin(Stdio::piped())
     .stdout(Stdio::piped())
@@ -495,19 +606,17 @@ fn spawn_engine(path: &'static str, requests: Receiver<Request>, results: &Sende
     results,
     BufReader::new(stdout),
     stdin,
-    false,
+    debug,
     || (),
   );
   // To avoid your computer being infected by thousands of zombies
   engine.wait().expect("Waiting failed");
 }
 
-/// Load an engine from the provided path
-#[must_use]
-pub fn load_engine(path: &'static str) -> (Sender<Request>, Receiver<UlciResult>) {
+fn start_engine(path: &'static str, debug: bool) -> (Sender<Request>, Receiver<UlciResult>) {
   let (send_results, results) = channel();
   let (tx, rx) = channel();
-  spawn(move || spawn_engine(path, rx, &send_results));
+  spawn(move || spawn_engine(path, rx, &send_results, debug));
   while let Ok(result) = results.recv() {
     if let UlciResult::Startup(_) = result {
       break;
@@ -515,3 +624,15 @@ pub fn load_engine(path: &'static str) -> (Sender<Request>, Receiver<UlciResult>
   }
   (tx, results)
 }
+
+/// Load an engine from the provided path
+#[must_use]
+pub fn load_engine(path: &'static str) -> (Sender<Request>, Receiver<UlciResult>) {
+  start_engine(path, false)
+}
+
+/// Load an engine from the provided path with debug mode enabled
+#[must_use]
+pub fn load_engine_debug(path: &'static str) -> (Sender<Request>, Receiver<UlciResult>) {
+  start_engine(path, true)
+}
@@ -0,0 +1,196 @@
+use crate::server::{startup_server, Request, UlciResult};
+use crate::write;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{sleep, spawn};
+use std::time::Duration;
+
+/// A scripted action for the mock engine to take
+#[derive(Clone)]
+pub enum Action {
+  /// Send a line of output
+  Send(String),
+  /// Wait before continuing, to simulate a slow engine
+  Delay(Duration),
+  /// Stop responding and close the output, to simulate a crash
+  Crash,
+}
+
+#[derive(Clone, Default)]
+struct Response {
+  once: VecDeque<Vec<Action>>,
+  always: Vec<Action>,
+}
+
+/// A script for a mock engine, mapping the first word of each command to canned responses
+#[derive(Clone, Default)]
+pub struct Script {
+  responses: HashMap<String, Response>,
+}
+
+impl Script {
+  /// A script for a minimal well-behaved engine that always plays `bestmove`
+  #[must_use]
+  pub fn new(bestmove: &str) -> Self {
+    Self::default()
+      .respond(
+        "uci",
+        vec![
+          Action::Send("id version 1".to_owned()),
+          Action::Send("id name Mock".to_owned()),
+          Action::Send("id author Mock".to_owned()),
+          Action::Send("uciok".to_owned()),
+        ],
+      )
+      .respond("isready", vec![Action::Send("readyok".to_owned())])
+      .respond(
+        "go",
+        vec![
+          Action::Send(format!(
+            "info depth 1 score cp 0 nodes 1 time 0 pv {bestmove}"
+          )),
+          Action::Send(format!("bestmove {bestmove}")),
+        ],
+      )
+  }
+
+  /// Set the response to a command every time it is received
+  #[must_use]
+  pub fn respond(mut self, command: &str, actions: Vec<Action>) -> Self {
+    self.responses.entry(command.to_owned()).or_default().always = actions;
+    self
+  }
+
+  /// Queue a response to be used once for a command, taking priority over the regular response
+  ///
+  /// Queued responses are used in the order they were added
+  #[must_use]
+  pub fn respond_once(mut self, command: &str, actions: Vec<Action>) -> Self {
+    self
+      .responses
+      .entry(command.to_owned())
+      .or_default()
+      .once
+      .push_back(actions);
+    self
+  }
+
+  fn next_response(&mut self, command: &str) -> Vec<Action> {
+    self
+      .responses
+      .get_mut(command)
+      .map_or_else(Vec::new, |response| {
+        response
+          .once
+          .pop_front()
+          .unwrap_or_else(|| response.always.clone())
+      })
+  }
+}
+
+/// The output of a mock engine, equivalent to the stdout of an engine process
+pub struct MockReader {
+  data: Receiver<Vec<u8>>,
+  buffer: Vec<u8>,
+  position: usize,
+}
+
+impl Read for MockReader {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    if self.position >= self.buffer.len() {
+      match self.data.recv() {
+        Ok(data) => {
+          self.buffer = data;
+          self.position = 0;
+        }
+        // the other end has closed
+        Err(_) => return Ok(0),
+      }
+    }
+    let count = buf.len().min(self.buffer.len() - self.position);
+    buf[..count].copy_from_slice(&self.buffer[self.position..self.position + count]);
+    self.position += count;
+    Ok(count)
+  }
+}
+
+/// The input of a mock engine, equivalent to the stdin of an engine process
+pub struct MockWriter(Sender<Vec<u8>>);
+
+impl Write for MockWriter {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self
+      .0
+      .send(buf.to_vec())
+      .map_err(|_| io::Error::from(ErrorKind::BrokenPipe))?;
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+fn pipe() -> (MockWriter, MockReader) {
+  let (tx, rx) = channel();
+  (
+    MockWriter(tx),
+    MockReader {
+      data: rx,
+      buffer: Vec::new(),
+      position: 0,
+    },
+  )
+}
+
+/// Run a script, reading commands from `input` and writing responses to `out`
+///
+/// Returns when the input is closed, `quit` is received or the script crashes
+pub fn run(mut script: Script, input: impl BufRead, mut out: impl Write) {
+  for line in input.lines().map_while(Result::ok) {
+    let command = line.split_whitespace().next().unwrap_or_default();
+    if command == "quit" {
+      return;
+    }
+    for action in script.next_response(command) {
+      match action {
+        Action::Send(output) => {
+          if write(&mut out, output).is_none() {
+            return;
+          }
+        }
+        Action::Delay(duration) => sleep(duration),
+        Action::Crash => return,
+      }
+    }
+  }
+}
+
+/// Start a mock engine on a new thread
+///
+/// Returns the output and input of the engine, for use in place of engine stdout and stdin
+#[must_use]
+pub fn spawn_mock(script: Script) -> (BufReader<MockReader>, MockWriter) {
+  let (engine_out, server_in) = pipe();
+  let (server_out, engine_in) = pipe();
+  spawn(move || run(script, BufReader::new(engine_in), engine_out));
+  (BufReader::new(server_in), server_out)
+}
+
+/// Load a mock engine running the script, equivalent to `load_engine`
+#[must_use]
+pub fn load_mock(script: Script) -> (Sender<Request>, Receiver<UlciResult>) {
+  let (send_results, results) = channel();
+  let (tx, rx) = channel();
+  spawn(move || {
+    let (input, out) = spawn_mock(script);
+    startup_server(rx, &send_results, input, out, false, || ());
+  });
+  while let Ok(result) = results.recv() {
+    if let UlciResult::Startup(_) = result {
+      break;
+    }
+  }
+  (tx, results)
+}
@@ -0,0 +1,192 @@
+use liberty_chess::Board;
+use parking_lot::{const_mutex, Mutex};
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{BufRead, Read, Result, Write};
+use std::panic;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Weak};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// how much history to keep for the report
+const MOVE_COUNT: usize = 20;
+const TRAFFIC_LINES: usize = 100;
+
+struct Position {
+  fen: String,
+  moves: VecDeque<String>,
+}
+
+// only updated once per move
+static POSITION: Mutex<Position> = const_mutex(Position {
+  fen: String::new(),
+  moves: VecDeque::new(),
+});
+
+// the traffic of each open connection, only locked when a connection starts or a report is written
+static CONNECTIONS: Mutex<Vec<Weak<Mutex<VecDeque<String>>>>> = const_mutex(Vec::new());
+
+fn push_limited(queue: &mut VecDeque<String>, item: String, limit: usize) {
+  if queue.len() == limit {
+    queue.pop_front();
+  }
+  queue.push_back(item);
+}
+
+/// Record the current position, and the move that reached it, for bug reports
+pub fn set_position(board: &Board) {
+  let fen = board.to_string();
+  let mut position = POSITION.lock();
+  if let Some(mv) = board.last_move {
+    push_limited(&mut position.moves, mv.to_string(), MOVE_COUNT);
+  }
+  position.fen = fen;
+}
+
+// The recent traffic of one connection, included in reports while the connection is open
+#[derive(Clone)]
+pub(crate) struct Traffic(Arc<Mutex<VecDeque<String>>>);
+
+impl Traffic {
+  pub(crate) fn new() -> Self {
+    let traffic = Arc::new(Mutex::new(VecDeque::with_capacity(TRAFFIC_LINES)));
+    let mut connections = CONNECTIONS.lock();
+    connections.retain(|connection| connection.strong_count() > 0);
+    connections.push(Arc::downgrade(&traffic));
+    Self(traffic)
+  }
+
+  // `incoming` is whether the line was received rather than sent
+  fn record(&self, line: &str, incoming: bool) {
+    let direction = if incoming { '<' } else { '>' };
+    let line = format!("{direction} {}", line.trim_end());
+    push_limited(&mut self.0.lock(), line, TRAFFIC_LINES);
+  }
+}
+
+// Wraps an input to record each line read
+pub(crate) struct RecordedInput<R> {
+  input: R,
+  traffic: Traffic,
+}
+
+impl<R> RecordedInput<R> {
+  pub(crate) const fn new(input: R, traffic: Traffic) -> Self {
+    Self { input, traffic }
+  }
+}
+
+impl<R: Read> Read for RecordedInput<R> {
+  fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+    self.input.read(buf)
+  }
+}
+
+impl<R: BufRead> BufRead for RecordedInput<R> {
+  fn fill_buf(&mut self) -> Result<&[u8]> {
+    self.input.fill_buf()
+  }
+
+  fn consume(&mut self, amount: usize) {
+    self.input.consume(amount);
+  }
+
+  fn read_line(&mut self, buf: &mut String) -> Result<usize> {
+    let start = buf.len();
+    let chars = self.input.read_line(buf)?;
+    if chars > 0 {
+      self.traffic.record(&buf[start..], true);
+    }
+    Ok(chars)
+  }
+}
+
+// Wraps an output to record each line written
+pub(crate) struct RecordedOutput<W> {
+  output: W,
+  traffic: Traffic,
+}
+
+impl<W> RecordedOutput<W> {
+  pub(crate) const fn new(output: W, traffic: Traffic) -> Self {
+    Self { output, traffic }
+  }
+}
+
+impl<W: Write> Write for RecordedOutput<W> {
+  fn write(&mut self, buf: &[u8]) -> Result<usize> {
+    let written = self.output.write(buf)?;
+    for line in String::from_utf8_lossy(&buf[..written]).lines() {
+      self.traffic.record(line, false);
+    }
+    Ok(written)
+  }
+
+  fn flush(&mut self) -> Result<()> {
+    self.output.flush()
+  }
+}
+
+fn write_report(program: &str, version: &str, panic: &str) -> std::io::Result<PathBuf> {
+  let mut report = format!(
+    "{program} v{version} ({} {})\n\nPanic: {panic}\n\n",
+    std::env::consts::OS,
+    std::env::consts::ARCH
+  );
+  // the locks may be held by the thread that panicked
+  match POSITION.try_lock() {
+    Some(position) => {
+      writeln!(report, "Position: {}", position.fen).ok();
+      let moves: Vec<&str> = position.moves.iter().map(String::as_str).collect();
+      writeln!(report, "Last moves: {}", moves.join(" ")).ok();
+    }
+    None => report += "Position unavailable\n",
+  }
+  let connections: Vec<_> = CONNECTIONS
+    .try_lock()
+    .map(|connections| connections.iter().filter_map(Weak::upgrade).collect())
+    .unwrap_or_default();
+  for (i, traffic) in connections.iter().enumerate() {
+    writeln!(report, "\nRecent ULCI traffic on connection {}:", i + 1).ok();
+    match traffic.try_lock() {
+      Some(lines) => {
+        for line in lines.iter() {
+          writeln!(report, "{line}").ok();
+        }
+      }
+      None => report += "Traffic unavailable\n",
+    }
+  }
+  writeln!(report, "\nBacktrace:\n{}", Backtrace::force_capture()).ok();
+  let time = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_secs();
+  let path = std::env::temp_dir().join(format!("{program}-bug-report-{time}.txt"));
+  std::fs::write(&path, report)?;
+  Ok(path)
+}
+
+/// Write a bug report to the temporary directory when the program panics
+///
+/// The report contains the version, the position and moves recorded with [`set_position`],
+/// the recent traffic of each open ULCI connection and a backtrace.
+/// Its path is printed to stderr and sent on the returned channel,
+/// so programs that survive a panic on another thread can show it.
+pub fn install_panic_hook(program: &'static str, version: &'static str) -> Receiver<PathBuf> {
+  let (tx, rx) = channel();
+  let default_hook = panic::take_hook();
+  panic::set_hook(Box::new(move |info| {
+    default_hook(info);
+    match write_report(program, version, &info.to_string()) {
+      Ok(path) => {
+        eprintln!("A bug report has been saved to {}", path.display());
+        tx.send(path).ok();
+      }
+      Err(error) => eprintln!("Could not save a bug report: {error}"),
+    }
+  }));
+  rx
+}
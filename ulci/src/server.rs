@@ -1,3 +1,6 @@
+use crate::compression::compress;
+use crate::games::ChannelInput;
+use crate::report::{RecordedInput, RecordedOutput, Traffic};
 use crate::{
   convert_words, process_info, write, write_mutex, AnalysisResult, ClientInfo, IntOption,
   OptionValue, RangeOption, Score, SearchTime, SupportedFeatures, UlciOption, V1Features,
@@ -10,7 +13,8 @@ use std::collections::{HashMap, HashSet};
 use std::io::{BufRead, Write};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
-use std::thread::spawn;
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant};
 
 /// A request for some ULCI action
 #[derive(Clone)]
@@ -27,6 +31,41 @@ pub enum Request {
   Clock(SearchTime),
   /// The server has results for the client
   AnalysisResult(AnalysisResult),
+  /// The server has results from an analysis engine for a spectator
+  Kibbutz(AnalysisResult),
+  /// Count the positions reachable from a position to a certain depth
+  Perft(String, usize),
+  /// The server has a message to show the user
+  Notice(String),
+}
+
+// Work that has to wait for output from the client
+enum Task {
+  Analysis(AnalysisRequest),
+  Perft(String, usize),
+  // a keepalive ping was sent, which fails once the server has stopped
+  Ping,
+  // the client stopped responding to keepalive pings
+  TimedOut,
+}
+
+/// How to check that a client is still connected, so connections that drop without closing are
+/// detected
+#[derive(Clone, Copy, Debug)]
+pub struct Keepalive {
+  /// How often to ping the client
+  pub interval: Duration,
+  /// How long the client can go without sending anything before the connection is dropped
+  pub timeout: Duration,
+}
+
+impl Default for Keepalive {
+  fn default() -> Self {
+    Self {
+      interval: Duration::from_secs(5),
+      timeout: Duration::from_secs(30),
+    }
+  }
 }
 
 /// A request for analysis
@@ -40,6 +79,8 @@ pub struct AnalysisRequest {
   pub time: SearchTime,
   /// Which moves to analyse (empty Vec = analyse all)
   pub searchmoves: Vec<Move>,
+  /// Moves to search first, only sent to clients supporting the extension
+  pub priority: Vec<Move>,
   /// Should ucinewgame be sent
   pub new_game: bool,
 }
@@ -54,6 +95,8 @@ pub enum UlciResult {
   Startup(ClientInfo),
   /// Information for the server
   Info(InfoType, String),
+  /// Perft is over, return the count for each move and the total
+  Perft(Vec<(Move, usize)>, usize),
 }
 
 impl Default for AnalysisResult {
@@ -86,21 +129,156 @@ pub enum InfoType {
 pub fn startup_server(
   requests: Receiver<Request>,
   results: &Sender<UlciResult>,
-  mut input: impl BufRead,
-  mut out: impl Write + Send + 'static,
+  input: impl BufRead,
+  out: impl Write + Send + 'static,
   debug: bool,
   // To make the GUI work without polling or creating more threads
   completion: impl Fn(),
 ) -> Option<()> {
+  let server = Server::start(requests, results, input, out, debug, &completion)?;
+  // without keepalives only the request thread adds tasks, so stop once it has finished
+  drop(server.tasks);
+  process_analysis(
+    &server.task_queue,
+    results,
+    server.input,
+    &server.out,
+    server.buffer,
+    server.features,
+    completion,
+  )
+}
+
+/// Start up a ULCI server that pings the client and drops the connection if it stops responding
+///
+/// The client is sent `isready` every interval, and the results channel is closed once nothing
+/// has been received from the client for the timeout
+///
+/// Blocks the current thread
+pub fn startup_server_with_keepalive(
+  requests: Receiver<Request>,
+  results: &Sender<UlciResult>,
+  input: impl BufRead + Send + 'static,
+  out: impl Write + Send + 'static,
+  debug: bool,
+  completion: impl Fn(),
+  keepalive: Keepalive,
+) -> Option<()> {
+  let server = Server::start(requests, results, input, out, debug, &completion)?;
+  let (send_line, lines) = channel();
+  let last_heard = Arc::new(Mutex::new(Instant::now()));
+  let heard = last_heard.clone();
+  let timed_out = send_line.clone();
+  let input = server.input;
+  spawn(move || read_lines(input, &send_line, &heard));
+  let ping_out = server.out.clone();
+  let ping_results = results.clone();
+  let tasks = server.tasks;
+  spawn(move || {
+    ping_client(
+      keepalive,
+      &ping_out,
+      &last_heard,
+      &ping_results,
+      &tasks,
+      &timed_out,
+    )
+  });
+  process_analysis(
+    &server.task_queue,
+    results,
+    ChannelInput::new(lines),
+    &server.out,
+    server.buffer,
+    server.features,
+    completion,
+  )
+}
+
+// A connection that has finished the handshake, with the requests being processed
+struct Server<R, W> {
+  input: RecordedInput<R>,
+  out: Arc<Mutex<RecordedOutput<W>>>,
+  // tasks waiting for output from the client
+  tasks: Sender<Task>,
+  task_queue: Receiver<Task>,
+  // input read during the handshake that hasn't been processed yet
+  buffer: String,
+  features: SupportedFeatures,
+}
+
+impl<R: BufRead, W: Write + Send + 'static> Server<R, W> {
+  // Do the handshake and start processing requests
+  fn start(
+    requests: Receiver<Request>,
+    results: &Sender<UlciResult>,
+    input: R,
+    out: W,
+    debug: bool,
+    completion: &impl Fn(),
+  ) -> Option<Self> {
+    let traffic = Traffic::new();
+    let mut input = RecordedInput::new(input, traffic.clone());
+    let mut out = RecordedOutput::new(out, traffic);
+    let mut buffer = String::new();
+    let client_info = setup(results, &mut input, &mut out, debug, &mut buffer)?;
+    let features = client_info.features;
+    results.send(UlciResult::Startup(client_info)).ok();
+    completion();
+    let (tasks, task_queue) = channel();
+    let out = Arc::new(Mutex::new(out));
+    let new_out = out.clone();
+    let new_tasks = tasks.clone();
+    spawn(move || process_server(&requests, &new_tasks, &new_out));
+    Some(Self {
+      input,
+      out,
+      tasks,
+      task_queue,
+      buffer,
+      features,
+    })
+  }
+}
+
+// Forward each line from the client, noting when it was received
+fn read_lines(mut input: impl BufRead, lines: &Sender<String>, last_heard: &Mutex<Instant>) {
   let mut buffer = String::new();
-  let client_info = setup(results, &mut input, &mut out, debug, &mut buffer)?;
-  results.send(UlciResult::Startup(client_info)).ok();
-  completion();
-  let (tx, rx) = channel();
-  let out = Arc::new(Mutex::new(out));
-  let new_out = out.clone();
-  spawn(move || process_server(&requests, &tx, &new_out));
-  process_analysis(&rx, results, input, &out, buffer, completion)
+  while let Ok(chars) = input.read_line(&mut buffer) {
+    if chars == 0 || lines.send(buffer.clone()).is_err() {
+      break;
+    }
+    *last_heard.lock() = Instant::now();
+    buffer.clear();
+  }
+}
+
+fn ping_client(
+  keepalive: Keepalive,
+  out: &Arc<Mutex<impl Write>>,
+  last_heard: &Mutex<Instant>,
+  results: &Sender<UlciResult>,
+  tasks: &Sender<Task>,
+  lines: &Sender<String>,
+) -> Option<()> {
+  loop {
+    sleep(keepalive.interval);
+    if last_heard.lock().elapsed() > keepalive.timeout {
+      results
+        .send(UlciResult::Info(
+          InfoType::Error,
+          "Client stopped responding".to_owned(),
+        ))
+        .ok();
+      // end whatever the server is waiting for
+      tasks.send(Task::TimedOut).ok();
+      lines.send(String::new()).ok();
+      return None;
+    }
+    // stop once the connection has been closed
+    tasks.send(Task::Ping).ok()?;
+    write_mutex(out, "isready")?;
+  }
 }
 
 fn setup(
@@ -133,15 +311,7 @@ fn setup(
         }
         Some("feature") => {
           if let Some(word) = words.next() {
-            match word {
-              "boardsize" => features.v1.board_sizes = true,
-              "pawnconfig" => features.v1.pawn_moves = true,
-              "castling" => features.v1.castling = true,
-              "multiplekings" => features.v1.multiple_kings = true,
-              "promotion" => features.v1.promotion_options = true,
-              "friendlyfire" => features.v1.friendly_fire = true,
-              _ => (),
-            }
+            features.declare(word);
           }
         }
         Some("pieces") => {
@@ -262,18 +432,23 @@ fn setup(
     pieces,
     // not relevant for the server
     depth: 0,
+    // passed on with the other info strings
+    build: None,
   })
 }
 
 fn process_server(
   requests: &Receiver<Request>,
-  tx: &Sender<AnalysisRequest>,
+  tx: &Sender<Task>,
   out: &Arc<Mutex<impl Write>>,
 ) -> Option<()> {
   while let Ok(request) = requests.recv() {
     match request {
       Request::Analysis(request) => {
-        tx.send(request).ok()?;
+        tx.send(Task::Analysis(request)).ok()?;
+      }
+      Request::Perft(fen, depth) => {
+        tx.send(Task::Perft(fen, depth)).ok()?;
       }
       Request::StopAnalysis => {
         write_mutex(out, "stop")?;
@@ -311,51 +486,73 @@ fn process_server(
         write_mutex(out, format!("clock{}", time.to_string()))?;
       }
       Request::AnalysisResult(result) => {
-        // TODO: WDL
-        write_mutex(
-          out,
-          format!(
-            "info depth {} score {} time {} nodes {} pv {}\n",
-            result.depth,
-            // TODO: fix
-            result.score.show_uci(0, true),
-            result.time,
-            result.nodes,
-            result
-              .pv
-              .iter()
-              .map(Move::to_string)
-              .collect::<Vec<String>>()
-              .join(" ")
-          ),
-        )?;
+        write_mutex(out, format!("info {}", show_result(&result)))?;
+      }
+      Request::Kibbutz(result) => {
+        write_mutex(out, format!("kibbutz {}", show_result(&result)))?;
+      }
+      Request::Notice(message) => {
+        write_mutex(out, format!("info string {message}"))?;
       }
     }
   }
   Some(())
 }
 
+fn convert_moves(moves: &[Move]) -> String {
+  moves
+    .iter()
+    .map(Move::to_string)
+    .collect::<Vec<String>>()
+    .join(" ")
+}
+
+fn show_result(result: &AnalysisResult) -> String {
+  let wdl = match result.wdl {
+    Some(wdl) => format!("{} ", wdl.to_string()),
+    None => String::new(),
+  };
+  format!(
+    "depth {} score {} {wdl}time {} nodes {} pv {}",
+    result.depth,
+    // TODO: fix
+    result.score.show_uci(0, true),
+    result.time,
+    result.nodes,
+    result
+      .pv
+      .iter()
+      .map(Move::to_string)
+      .collect::<Vec<String>>()
+      .join(" ")
+  )
+}
+
 fn process_analysis(
-  rx: &Receiver<AnalysisRequest>,
+  rx: &Receiver<Task>,
   tx: &Sender<UlciResult>,
   mut input: impl BufRead,
   out: &Arc<Mutex<impl Write>>,
   mut buffer: String,
+  features: SupportedFeatures,
   completion: impl Fn(),
 ) -> Option<()> {
-  while let Ok(request) = rx.recv() {
+  while let Ok(task) = rx.recv() {
+    let request = match task {
+      Task::Analysis(request) => request,
+      Task::Perft(fen, depth) => {
+        let command = position_command(&format!("fen {fen}"), features.compression);
+        process_perft(tx, &mut input, out, &mut buffer, &command, depth)?;
+        completion();
+        continue;
+      }
+      Task::Ping => continue,
+      Task::TimedOut => return None,
+    };
     let moves = if request.moves.is_empty() {
       String::new()
     } else {
-      format!(
-        " moves {}",
-        request
-          .moves
-          .iter()
-          .map(ToString::to_string)
-          .collect::<Vec<String>>()
-          .join(" ")
-      )
+      format!(" moves {}", convert_moves(&request.moves))
     };
     if request.new_game {
       write_mutex(out, "ucinewgame")?;
@@ -370,22 +567,23 @@ fn process_analysis(
         buffer.clear();
       }
     }
-    write_mutex(out, format!("position fen {}{moves}", request.fen))?;
+    let position = format!("fen {}{moves}", request.fen);
+    write_mutex(out, position_command(&position, features.compression))?;
     buffer.clear();
+    let priority = if request.priority.is_empty() || !features.priority_moves {
+      String::new()
+    } else {
+      format!(" priority {}", convert_moves(&request.priority))
+    };
     let moves = if request.searchmoves.is_empty() {
       String::new()
     } else {
-      format!(
-        " moves {}",
-        request
-          .searchmoves
-          .iter()
-          .map(ToString::to_string)
-          .collect::<Vec<String>>()
-          .join(" ")
-      )
+      format!(" searchmoves {}", convert_moves(&request.searchmoves))
     };
-    write_mutex(out, format!("go{}{moves}", request.time.to_string()))?;
+    write_mutex(
+      out,
+      format!("go{}{priority}{moves}", request.time.to_string()),
+    )?;
     while let Ok(chars) = input.read_line(&mut buffer) {
       if chars == 0 {
         return None;
@@ -415,3 +613,57 @@ fn process_analysis(
   }
   Some(())
 }
+
+// The command to set up a position, compressed if the client supports it and it comes out shorter
+fn position_command(position: &str, compression: bool) -> String {
+  if compression {
+    if let Some(compressed) = compress(position) {
+      if compressed.len() + "compressed ".len() < position.len() {
+        return format!("position compressed {compressed}");
+      }
+    }
+  }
+  format!("position {position}")
+}
+
+// The engine is asked for `readyok` after the perft, so an engine without perft support
+// doesn't leave the server waiting for a count forever
+pub(crate) fn process_perft(
+  tx: &Sender<UlciResult>,
+  input: &mut impl BufRead,
+  out: &Arc<Mutex<impl Write>>,
+  buffer: &mut String,
+  position: &str,
+  depth: usize,
+) -> Option<()> {
+  write_mutex(out, position)?;
+  write_mutex(out, format!("perft {depth}"))?;
+  write_mutex(out, "isready")?;
+  buffer.clear();
+  let mut moves = Vec::new();
+  let mut total = None;
+  while let Ok(chars) = input.read_line(buffer) {
+    if chars == 0 {
+      return None;
+    }
+    if buffer.trim() == "readyok" {
+      buffer.clear();
+      let result = match total {
+        Some(total) => UlciResult::Perft(moves, total),
+        None => UlciResult::Info(InfoType::Error, "perft not supported".to_owned()),
+      };
+      tx.send(result).ok()?;
+      break;
+    }
+    if let Some(count) = buffer.strip_prefix("Nodes searched:") {
+      total = Some(count.trim().parse().unwrap_or_default());
+    } else if let Some((mv, count)) = buffer.split_once(':') {
+      // each move is reported as "<move>: <count>"
+      if let (Ok(mv), Ok(count)) = (mv.trim().parse(), count.trim().parse()) {
+        moves.push((mv, count));
+      }
+    }
+    buffer.clear();
+  }
+  Some(())
+}
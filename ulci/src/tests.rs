@@ -1,4 +1,16 @@
-use crate::Score;
+use crate::client::{go, print_uci, Message};
+use crate::compression::{compress, decompress};
+use crate::games::Games;
+use crate::mock::{load_mock, Action, Script};
+use crate::server::{process_perft, AnalysisRequest, InfoType, Request, UlciResult};
+use crate::{ClientInfo, Score, SearchTime, SupportedFeatures, V1Features};
+use liberty_chess::clock::Millis;
+use liberty_chess::positions::STARTPOS;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::io::{sink, BufRead, Cursor};
+use std::sync::mpsc::channel;
+use std::sync::Arc;
 
 #[test]
 fn win_ordering() {
@@ -24,3 +36,179 @@ fn mixed_ordering() {
   assert!(Score::Win(7) > Score::Centipawn(5));
   assert!(Score::Loss(7) < Score::Centipawn(5));
 }
+
+#[test]
+fn compression_round_trip() {
+  let position = "fen rnbqkbnrrnbqkbnr/pppppppppppppppp/16/16/16/16/16/16/16/16/16/16/16/16/\
+PPPPPPPPPPPPPPPP/RNBQKBNRRNBQKBNR w KQkq - 0 1 moves d1d2 d8d7 d2d1 d7d8 d1d2 d8d7 d2d1 d7d8";
+  let compressed = compress(position).unwrap();
+  assert!(compressed.len() < position.len());
+  assert_eq!(decompress(&compressed).as_deref(), Some(position));
+}
+
+#[test]
+fn compression_invalid() {
+  assert!(compress("fen ~").is_none());
+  assert!(decompress("~AA").is_none());
+  assert!(decompress("a~AFA").is_none());
+}
+
+#[test]
+fn games_routing() {
+  let input = Cursor::new("readyok\ngame 2 bestmove e2e4\nbestmove d2d4\ngame 2 uciok\n");
+  let (tx, rx) = channel();
+  let (_, main, _) = Games::split(input, sink(), Some(tx));
+  let main: Vec<String> = main.lines().map_while(Result::ok).collect();
+  assert_eq!(main, ["readyok", "bestmove d2d4"]);
+  let (game, input, _) = rx.recv().unwrap();
+  assert_eq!(game, 2);
+  let input: Vec<String> = input.lines().map_while(Result::ok).collect();
+  assert_eq!(input, ["bestmove e2e4", "uciok"]);
+}
+
+#[test]
+fn go_moves_to_go() {
+  let (tx, rx) = channel();
+  go(
+    &mut sink(),
+    &tx,
+    "movestogo 12 wtime 6000 btime 5000".split_whitespace(),
+    false,
+  )
+  .unwrap();
+  let Ok(Message::Go(settings)) = rx.recv() else {
+    panic!("go should start a search");
+  };
+  let time = Millis::ZERO;
+  let expected = SearchTime::MovesToGo(Millis::new(6000), time, Millis::new(5000), time, 12);
+  assert!(settings.time == expected);
+  assert!(!settings.clock);
+}
+
+#[test]
+fn go_clock() {
+  let (tx, rx) = channel();
+  go(&mut sink(), &tx, "".split_whitespace(), false).unwrap();
+  let Ok(Message::Go(settings)) = rx.recv() else {
+    panic!("go should start a search");
+  };
+  assert!(settings.clock);
+  go(&mut sink(), &tx, "infinite".split_whitespace(), false).unwrap();
+  let Ok(Message::Go(settings)) = rx.recv() else {
+    panic!("go should start a search");
+  };
+  assert!(settings.time == SearchTime::Infinite);
+  assert!(!settings.clock);
+}
+
+#[test]
+fn mock_bestmove() {
+  let (tx, rx) = load_mock(Script::new("e2e4"));
+  tx.send(Request::Analysis(AnalysisRequest {
+    fen: STARTPOS.to_owned(),
+    moves: Vec::new(),
+    time: SearchTime::Infinite,
+    searchmoves: Vec::new(),
+    priority: Vec::new(),
+    new_game: true,
+  }))
+  .unwrap();
+  let bestmove = rx.iter().find_map(|result| match result {
+    UlciResult::AnalysisStopped(bestmove) => Some(bestmove),
+    _ => None,
+  });
+  assert!(bestmove == "e2e4".parse().ok());
+}
+
+#[test]
+fn mock_crash() {
+  let script = Script::new("e2e4").respond("go", vec![Action::Crash]);
+  let (tx, rx) = load_mock(script);
+  tx.send(Request::Analysis(AnalysisRequest {
+    fen: STARTPOS.to_owned(),
+    moves: Vec::new(),
+    time: SearchTime::Infinite,
+    searchmoves: Vec::new(),
+    priority: Vec::new(),
+    new_game: false,
+  }))
+  .unwrap();
+  assert!(rx
+    .iter()
+    .all(|result| !matches!(result, UlciResult::AnalysisStopped(_))));
+}
+
+#[test]
+fn perft_counts() {
+  let (tx, rx) = channel();
+  let mut input = Cursor::new("e2e4: 20\nd2d4: 20\n\nNodes searched: 40\n\nreadyok\n");
+  let out = Arc::new(Mutex::new(sink()));
+  process_perft(
+    &tx,
+    &mut input,
+    &out,
+    &mut String::new(),
+    "position startpos",
+    2,
+  )
+  .unwrap();
+  let Ok(UlciResult::Perft(moves, total)) = rx.try_recv() else {
+    panic!("perft should report the counts");
+  };
+  assert_eq!(moves.len(), 2);
+  assert_eq!(total, 40);
+}
+
+#[test]
+fn perft_unsupported() {
+  let (tx, rx) = channel();
+  let mut input = Cursor::new("info error unknown command perft\nreadyok\n");
+  let out = Arc::new(Mutex::new(sink()));
+  process_perft(
+    &tx,
+    &mut input,
+    &out,
+    &mut String::new(),
+    "position startpos",
+    2,
+  )
+  .unwrap();
+  assert!(matches!(
+    rx.try_recv(),
+    Ok(UlciResult::Info(InfoType::Error, _))
+  ));
+}
+
+#[test]
+fn features_round_trip() {
+  let mut v1 = V1Features::all();
+  // a partly supported version 1 is declared feature by feature
+  v1.castling = false;
+  let features = SupportedFeatures {
+    v1,
+    priority_moves: true,
+    compression: true,
+    games: true,
+  };
+  let info = ClientInfo {
+    features,
+    name: String::new(),
+    username: None,
+    author: String::new(),
+    options: HashMap::new(),
+    pieces: Vec::new(),
+    depth: 1,
+    build: None,
+  };
+  let mut output = Vec::new();
+  print_uci(&mut output, &info);
+  let mut declared = SupportedFeatures::default();
+  for line in output.lines().map_while(Result::ok) {
+    if let Some(feature) = line.strip_prefix("id feature ") {
+      assert!(declared.declare(feature), "{feature}");
+    }
+  }
+  assert!(declared.v1 == features.v1);
+  assert!(declared.priority_moves && declared.compression && declared.games);
+  assert!(!declared.declare("unknown"));
+}
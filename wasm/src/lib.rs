@@ -0,0 +1,139 @@
+#![warn(missing_docs, unused)]
+//! JavaScript bindings for the Liberty Chess rules engine
+
+use liberty_chess::moves::Move;
+use liberty_chess::parsing::to_char;
+use liberty_chess::{Board, Gamestate};
+use wasm_bindgen::prelude::*;
+
+/// A Liberty Chess position
+#[wasm_bindgen(js_name = Board)]
+pub struct JsBoard {
+  board: Board,
+}
+
+#[wasm_bindgen(js_class = Board)]
+impl JsBoard {
+  /// Load a board from an L-FEN
+  ///
+  /// # Errors
+  ///
+  /// Will throw if the L-FEN is invalid
+  #[wasm_bindgen(constructor)]
+  pub fn new(fen: &str) -> Result<JsBoard, JsError> {
+    Board::new(fen)
+      .map(|board| Self { board })
+      .map_err(|error| JsError::new(&error.to_string()))
+  }
+
+  /// Get the L-FEN of the board
+  #[wasm_bindgen(js_name = toFen)]
+  #[must_use]
+  pub fn to_fen(&self) -> String {
+    self.board.to_string()
+  }
+
+  /// The width of the board
+  #[wasm_bindgen(getter)]
+  #[must_use]
+  pub fn width(&self) -> usize {
+    self.board.width()
+  }
+
+  /// The height of the board
+  #[wasm_bindgen(getter)]
+  #[must_use]
+  pub fn height(&self) -> usize {
+    self.board.height()
+  }
+
+  /// Get the piece on a square as an L-FEN character, or an empty string if it is empty
+  ///
+  /// Row 0 is white's side of the board
+  #[must_use]
+  pub fn piece(&self, row: usize, column: usize) -> String {
+    match self.board.fetch_piece((row, column)) {
+      Some(&piece) if piece != 0 => to_char(piece).to_string(),
+      _ => String::new(),
+    }
+  }
+
+  /// Whether it is white to move
+  #[wasm_bindgen(getter, js_name = whiteToMove)]
+  #[must_use]
+  pub fn white_to_move(&self) -> bool {
+    self.board.to_move()
+  }
+
+  /// Whether the side to move is in check
+  #[wasm_bindgen(js_name = inCheck)]
+  #[must_use]
+  pub fn in_check(&self) -> bool {
+    self.board.in_check()
+  }
+
+  /// The legal moves in long algebraic notation
+  #[wasm_bindgen(js_name = legalMoves)]
+  #[must_use]
+  pub fn legal_moves(&self) -> Box<[JsValue]> {
+    self
+      .board
+      .generate_legal_moves()
+      .iter()
+      .map(|mv| JsValue::from_str(&mv.to_string()))
+      .collect()
+  }
+
+  /// Whether a move in long algebraic notation is legal
+  #[wasm_bindgen(js_name = isLegal)]
+  #[must_use]
+  pub fn is_legal(&self, mv: &str) -> bool {
+    mv.parse::<Move>()
+      .is_ok_and(|mv| self.board.move_if_legal(mv).is_some())
+  }
+
+  /// Play a move in long algebraic notation
+  ///
+  /// # Errors
+  ///
+  /// Will throw if the move can't be parsed or is illegal
+  #[wasm_bindgen(js_name = playMove)]
+  pub fn play_move(&mut self, mv: &str) -> Result<(), JsError> {
+    let parsed = mv
+      .parse::<Move>()
+      .map_err(|error| JsError::new(&error.to_string()))?;
+    self.board = self
+      .board
+      .move_if_legal(parsed)
+      .ok_or_else(|| JsError::new(&format!("Illegal move {mv}")))?;
+    Ok(())
+  }
+
+  /// The state of the game
+  ///
+  /// One of "in progress", "checkmate", "elimination", "stalemate", "fifty move", "repetition" or "material"
+  #[wasm_bindgen(getter)]
+  #[must_use]
+  pub fn state(&self) -> String {
+    match self.board.state() {
+      Gamestate::InProgress => "in progress",
+      Gamestate::Checkmate(_) => "checkmate",
+      Gamestate::Elimination(_) => "elimination",
+      Gamestate::Stalemate => "stalemate",
+      Gamestate::FiftyMove => "fifty move",
+      Gamestate::Repetition => "repetition",
+      Gamestate::Material => "material",
+    }
+    .to_owned()
+  }
+
+  /// The winner of the game, `true` for white, or `undefined` if there is no winner
+  #[wasm_bindgen(getter)]
+  #[must_use]
+  pub fn winner(&self) -> Option<bool> {
+    match self.board.state() {
+      Gamestate::Checkmate(winner) | Gamestate::Elimination(winner) => Some(winner),
+      _ => None,
+    }
+  }
+}